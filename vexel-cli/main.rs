@@ -35,7 +35,7 @@ struct Cli {
     #[arg(required = true)]
     path: String,
 
-    #[arg(short, long, value_parser = ["ppm", "pam", "webp", "jxl"], help = "Output format [default: jxl]")]
+    #[arg(short, long, value_parser = ["ppm", "pam", "png", "tiff", "gif", "webp", "jxl"], help = "Output format [default: jxl]")]
     format: Option<String>,
 
     #[arg(short = 'o', long = "output-dir", help = "Output directory for converted files")]
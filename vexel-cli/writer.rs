@@ -1,6 +1,9 @@
 use std::{fs::File, io::{Error, ErrorKind, Write}, mem::MaybeUninit, path::{Path, PathBuf}};
 use std::ffi::c_void;
 
+use vexel::encode::gif::GifEncoder;
+use vexel::encode::png::PngEncoder;
+use vexel::encode::tiff::TiffEncoder;
 use vexel::{Image, PixelData};
 
 use webp::{AnimEncoder, AnimFrame, WebPConfig};
@@ -86,6 +89,39 @@ impl Writer {
         Ok(())
     }
 
+    pub fn write_png(output_path: &PathBuf, image: &Image) -> Result<(), Error> {
+        let data = PngEncoder::new(Default::default())
+            .encode(image)
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+        let mut file = File::create(output_path)?;
+        file.write_all(&data)?;
+
+        Ok(())
+    }
+
+    pub fn write_gif(output_path: &PathBuf, image: &Image) -> Result<(), Error> {
+        let data = GifEncoder::new(Default::default())
+            .encode(image)
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+        let mut file = File::create(output_path)?;
+        file.write_all(&data)?;
+
+        Ok(())
+    }
+
+    pub fn write_tiff(output_path: &PathBuf, image: &Image) -> Result<(), Error> {
+        let data = TiffEncoder::new(Default::default())
+            .encode(image)
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+
+        let mut file = File::create(output_path)?;
+        file.write_all(&data)?;
+
+        Ok(())
+    }
+
     pub fn write_pam(output_path: &PathBuf, image: &Image) -> Result<(), Error> {
         let mut file = File::create(output_path)?;
         let width = image.width();
@@ -240,6 +276,9 @@ impl Writer {
             "pam" => Writer::write_pam(output_path, image),
             "ppm" => Writer::write_ppm(output_path, image),
             "webp" => Writer::write_webp(output_path, image),
+            "png" => Writer::write_png(output_path, image),
+            "tiff" => Writer::write_tiff(output_path, image),
+            "gif" => Writer::write_gif(output_path, image),
             "jxl" => Writer::write_jxl(output_path, image),
             _ => Err(Error::new(ErrorKind::InvalidInput, format!("Invalid format: {}", format))),
         }
@@ -254,6 +293,9 @@ impl Writer {
                 "ppm" => "ppm",
                 "jxl" => "jxl",
                 "webp" => "webp",
+                "png" => "png",
+                "tiff" => "tiff",
+                "gif" => "gif",
                 _ => return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid format: {}", format))),
             };
 
@@ -268,6 +310,9 @@ impl Writer {
                 "ppm" => Writer::write_ppm(&output_path, &frame_image)?,
                 "jxl" => Writer::write_jxl(&output_path, &frame_image)?,
                 "webp" => Writer::write_webp(&output_path, &frame_image)?,
+                "png" => Writer::write_png(&output_path, &frame_image)?,
+                "tiff" => Writer::write_tiff(&output_path, &frame_image)?,
+                "gif" => Writer::write_gif(&output_path, &frame_image)?,
                 _ => return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid format: {}", format))),
             }
         }
@@ -5,10 +5,14 @@ use crate::decoders::bmp::pixels::PixelDecoder;
 use crate::decoders::bmp::types::{BitmapCompression, BitmapFileHeader, BitmapInfoHeader, BmpExtraMasks, BmpPixelDataInfo, BmpSectionData, BmpSectionInfo, ColorEntry, DibHeader};
 use crate::decoders::jpeg::decoder::JpegDecoder;
 use crate::decoders::png::decoder::PngDecoder;
+use crate::utils::convert;
 use crate::utils::error::{VexelError, VexelResult};
 use crate::utils::icc::ICCProfile;
 use crate::utils::info::BmpInfo;
-use crate::{Image, Limits, log_error, log_warn};
+use crate::utils::metadata::{ImageMetadata, Resolution, ResolutionUnit};
+use crate::utils::progress;
+use crate::utils::report::{DecodeWarning, WarningKind};
+use crate::{Image, ImageFormat, ImageHeader, Limits, PixelFormat};
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
 pub struct BmpDecoder<R: Read + Seek> {
@@ -19,7 +23,7 @@ pub struct BmpDecoder<R: Read + Seek> {
     dib_header: DibHeader,
     extra_masks: Option<(u32, u32, u32, u32)>,
     color_table: Vec<ColorEntry>,
-    icc_profile: Option<ICCProfile>,
+    icc_profile: Option<(Vec<u8>, ICCProfile)>,
     data: Vec<u8>,
     rle_decoded: bool,
     sections: Vec<BmpSectionInfo>,
@@ -71,6 +75,30 @@ impl<R: Read + Seek> BmpDecoder<R> {
         }
     }
 
+    pub fn metadata(&self) -> ImageMetadata {
+        let mut metadata = ImageMetadata::default();
+
+        if let Some((data, profile)) = &self.icc_profile {
+            metadata.icc_data = Some(data.clone());
+            metadata.icc_profile = Some(profile.clone());
+        }
+
+        let (x, y) = self.dib_header.pixels_per_meter();
+        if x > 0 && y > 0 {
+            metadata.resolution = Some(Resolution {
+                x: x as f64,
+                y: y as f64,
+                unit: ResolutionUnit::Meter,
+            });
+        }
+
+        metadata
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn read_file_header(&mut self) -> VexelResult<()> {
         let start_offset = self.reader.stream_position().unwrap_or(0);
         let (file_header, ba_header) = HeaderReader::read_file_header(&mut self.reader)?;
@@ -179,7 +207,7 @@ impl<R: Read + Seek> BmpDecoder<R> {
                 start_offset: file_offset,
                 data: BmpSectionData::IccProfile(profile.clone()),
             });
-            self.icc_profile = Some(profile);
+            self.icc_profile = Some((data, profile));
         }
 
         Ok(())
@@ -200,6 +228,16 @@ impl<R: Read + Seek> BmpDecoder<R> {
         } else {
             let row_size = ((self.dib_header.bits_per_pixel() as u32 * self.width + 31) / 32) * 4;
             let data_size = row_size * self.height;
+
+            if bytes_until_eof < data_size as u64 && row_size > 0 {
+                let missing_rows = (data_size as u64 - bytes_until_eof).div_ceil(row_size as u64);
+                let message = format!("Pixel data ends after {} of {} bytes", bytes_until_eof, data_size);
+                DecodeWarning::new(WarningKind::TruncatedData, message)
+                    .at_offset(pixel_offset + bytes_until_eof)
+                    .with_synthesized_pixels(missing_rows * self.width as u64)
+                    .report();
+            }
+
             std::cmp::min(data_size as u64, bytes_until_eof)
         };
 
@@ -216,33 +254,35 @@ impl<R: Read + Seek> BmpDecoder<R> {
         Ok(())
     }
 
-    fn decode_jpeg(&mut self) -> VexelResult<Image> {
+    fn embedded_bytes(&mut self) -> VexelResult<Vec<u8>> {
         self.reader.seek(SeekFrom::Start(self.file_header.pixel_offset as u64))?;
         let image_size = self.dib_header.image_size();
-        let jpeg_bytes = if image_size > 0 {
-            self.reader.read_bytes(image_size as usize)?
+        if image_size > 0 {
+            Ok(self.reader.read_bytes(image_size as usize)?)
         } else {
-            self.reader.read_to_end()?
-        };
+            Ok(self.reader.read_to_end()?)
+        }
+    }
+
+    fn decode_jpeg(&mut self) -> VexelResult<Image> {
+        let jpeg_bytes = self.embedded_bytes()?;
         let mut jpeg_decoder = JpegDecoder::new(Cursor::new(jpeg_bytes));
         jpeg_decoder.decode()
     }
 
     fn decode_png(&mut self) -> VexelResult<Image> {
-        self.reader.seek(SeekFrom::Start(self.file_header.pixel_offset as u64))?;
-        let image_size = self.dib_header.image_size();
-        let png_bytes = if image_size > 0 {
-            self.reader.read_bytes(image_size as usize)?
-        } else {
-            self.reader.read_to_end()?
-        };
+        let png_bytes = self.embedded_bytes()?;
         let mut png_decoder = PngDecoder::new(Cursor::new(png_bytes));
         png_decoder.decode()
     }
 
     pub fn decode_ico_bmp(data: &[u8], dir_width: u32, dir_height: u32) -> VexelResult<(Image, Vec<u8>)> {
         if data.len() < 4 {
-            return Err(VexelError::Custom("ICO BMP data too short".to_string()));
+            return Err(VexelError::Truncated {
+                format: ImageFormat::Ico,
+                offset: None,
+                expected: Some(4),
+            });
         }
 
         let header_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
@@ -256,7 +296,11 @@ impl<R: Read + Seek> BmpDecoder<R> {
             let colors_used = u32::from_le_bytes([data[32], data[33], data[34], data[35]]);
             (bpp, colors_used, compression, false)
         } else {
-            return Err(VexelError::Custom("ICO BMP header too short".to_string()));
+            return Err(VexelError::Truncated {
+                format: ImageFormat::Ico,
+                offset: None,
+                expected: Some(if header_size == 12 { 12 } else { 36 }),
+            });
         };
 
         let bytes_per_color = if is_core { 3 } else { 4 };
@@ -332,10 +376,165 @@ impl<R: Read + Seek> BmpDecoder<R> {
         Ok((image, and_mask))
     }
 
-    pub fn decode(&mut self) -> VexelResult<Image> {
+    /// Returns the red, green, blue and alpha masks that 16 and 32-bit pixels are decoded with.
+    /// 32-bit pixels without masks use the alpha byte only if any pixel sets it.
+    fn pixel_masks(&self) -> Option<(u32, u32, u32, u32)> {
+        let use_masks = matches!(
+            self.dib_header.compression(),
+            BitmapCompression::BiBitfields | BitmapCompression::BiAlphaBitfields
+        );
+        let masks = use_masks.then(|| self.dib_header.color_masks().or(self.extra_masks)).flatten();
+
+        match self.dib_header.bits_per_pixel() {
+            32 => Some(masks.unwrap_or_else(|| {
+                let has_alpha = self.data.chunks_exact(4).any(|px| px[3] != 0);
+                let a_mask = if has_alpha { 0xFF000000 } else { 0 };
+                (0x00FF0000, 0x0000FF00, 0x000000FF, a_mask)
+            })),
+            _ => masks,
+        }
+    }
+
+    /// Decodes `height` rows of pixel data, stored as in the file, with the masks returned by
+    /// [`pixel_masks`](Self::pixel_masks).
+    fn decode_pixel_rows(
+        &self,
+        data: &[u8],
+        height: u32,
+        bottom_up: bool,
+        masks: Option<(u32, u32, u32, u32)>,
+    ) -> Image {
+        let width = self.width;
+
+        match self.dib_header.bits_per_pixel() {
+            1 => PixelDecoder::decode_1bit_image(data, width, height, bottom_up, &self.color_table),
+            2 => PixelDecoder::decode_2bit_image(data, width, height, bottom_up, &self.color_table),
+            4 => {
+                if self.rle_decoded {
+                    RleDecoder::decode_rle4_image(data, width, height, &self.color_table)
+                } else {
+                    PixelDecoder::decode_4bit_image(data, width, height, bottom_up, &self.color_table)
+                }
+            }
+            8 => {
+                if self.rle_decoded {
+                    RleDecoder::decode_rle8_image(data, width, height, &self.color_table)
+                } else {
+                    PixelDecoder::decode_8bit_image(data, width, height, bottom_up, &self.color_table)
+                }
+            }
+            16 => {
+                if let Some((red_mask, green_mask, blue_mask, alpha_mask)) = masks {
+                    PixelDecoder::decode_16bit_image_masked(
+                        data,
+                        width,
+                        height,
+                        bottom_up,
+                        red_mask,
+                        green_mask,
+                        blue_mask,
+                        alpha_mask,
+                    )
+                } else {
+                    PixelDecoder::decode_16bit_image(data, width, height, bottom_up)
+                }
+            }
+            24 => PixelDecoder::decode_24bit_image(data, width, height, bottom_up),
+            32 => {
+                let (red_mask, green_mask, blue_mask, alpha_mask) =
+                    masks.unwrap_or((0x00FF0000, 0x0000FF00, 0x000000FF, 0));
+                PixelDecoder::decode_32bit_image(
+                    data,
+                    width,
+                    height,
+                    bottom_up,
+                    red_mask,
+                    green_mask,
+                    blue_mask,
+                    alpha_mask,
+                )
+            }
+            64 => PixelDecoder::decode_64bit_image(data, width, height, bottom_up),
+            _ => {
+                let message = format!(
+                    "Invalid bit depth: {}. Attempting to decode as 24bit.",
+                    self.dib_header.bits_per_pixel(),
+                );
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
+                PixelDecoder::decode_24bit_image(data, width, height, bottom_up)
+            }
+        }
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        if let Err(e) = self.read_file_header() {
+            let message = format!("Error reading file header. This might be critical! Error: {}", e);
+            DecodeWarning::recovered_from(&e, message).report();
+        }
+
+        match self.read_info_header() {
+            Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
+            Err(e) => {
+                let message = format!("Error reading info header. This might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
+            }
+            Ok(_) => (),
+        };
+
+        if let Err(e) = self.read_extra_masks() {
+            let message = format!("Error reading extra masks. This might be critical! Error: {}", e);
+            DecodeWarning::recovered_from(&e, message).report();
+        }
+
+        match self.dib_header.compression() {
+            BitmapCompression::BiJpeg => {
+                let bytes = self.embedded_bytes()?;
+                let mut jpeg_decoder = JpegDecoder::new(Cursor::new(bytes));
+                jpeg_decoder.set_limits(self.limits.clone());
+                return jpeg_decoder.probe();
+            }
+            BitmapCompression::BiPng => {
+                let bytes = self.embedded_bytes()?;
+                let mut png_decoder = PngDecoder::new(Cursor::new(bytes));
+                png_decoder.set_limits(self.limits.clone());
+                return png_decoder.probe();
+            }
+            _ => (),
+        }
+
+        let use_masks = matches!(
+            self.dib_header.compression(),
+            BitmapCompression::BiBitfields | BitmapCompression::BiAlphaBitfields
+        );
+        let alpha_mask = use_masks
+            .then(|| self.dib_header.color_masks().or(self.extra_masks))
+            .flatten()
+            .map(|(_, _, _, alpha_mask)| alpha_mask);
+
+        // 32-bit images without masks are only promoted to RGBA when the alpha bytes are used,
+        // which can't be known without reading the pixel data
+        let pixel_format = match self.dib_header.bits_per_pixel() {
+            16 | 32 if alpha_mask.is_some_and(|mask| mask != 0) => PixelFormat::RGBA8,
+            64 => PixelFormat::RGBA8,
+            _ => PixelFormat::RGB8,
+        };
+
+        Ok(ImageHeader {
+            width: self.width,
+            height: self.height,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
+    /// Reads the headers, masks, colour table and ICC profile, recovering from anything but
+    /// an exceeded limit.
+    fn read_headers(&mut self) -> VexelResult<()> {
         match self.read_file_header() {
             Err(e) => {
-                log_error!("Error reading file header. This might be critical! Error: {}", e);
+                let message = format!("Error reading file header. This might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
             }
             Ok(_) => (),
         };
@@ -343,35 +542,45 @@ impl<R: Read + Seek> BmpDecoder<R> {
         match self.read_info_header() {
             Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
             Err(e) => {
-                log_error!("Error reading info header. This might be critical! Error: {}", e);
+                let message = format!("Error reading info header. This might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
             }
             Ok(_) => (),
         };
 
         match self.read_extra_masks() {
             Err(e) => {
-                log_error!("Error reading extra masks. This might be critical! Error: {}", e);
+                let message = format!("Error reading extra masks. This might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
             }
             Ok(_) => (),
         };
 
         match self.read_color_table() {
             Err(e) => {
-                log_error!("Error reading color table. This might be critical! Error: {}", e);
+                let message = format!("Error reading color table. This might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
             }
             Ok(_) => (),
         };
 
         match self.read_icc_profile() {
             Err(e) => {
-                log_warn!("Error reading ICC profile: {}", e);
+                DecodeWarning::recovered_from(&e, format!("Error reading ICC profile: {}", e)).report();
             }
             Ok(_) => (),
         };
 
+        Ok(())
+    }
+
+    pub fn decode(&mut self) -> VexelResult<Image> {
+        self.read_headers()?;
+
         match self.read_pixel_data() {
             Err(e) => {
-                log_error!("Error reading pixel data. This might be critical! Error: {}", e);
+                let message = format!("Error reading pixel data. This might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
             }
             Ok(_) => (),
         };
@@ -380,10 +589,11 @@ impl<R: Read + Seek> BmpDecoder<R> {
             BitmapCompression::BiRgb => (),
             BitmapCompression::BiRle8 => {
                 if self.dib_header.bits_per_pixel() != 8 {
-                    log_warn!(
+                    let message = format!(
                         "Invalid bit depth for RLE8 compression: {}",
-                        self.dib_header.bits_per_pixel()
+                        self.dib_header.bits_per_pixel(),
                     );
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                 }
 
                 self.data = RleDecoder::decode_rle8(&self.data, self.width, self.height)?;
@@ -391,10 +601,11 @@ impl<R: Read + Seek> BmpDecoder<R> {
             }
             BitmapCompression::BiRle4 => {
                 if self.dib_header.bits_per_pixel() != 4 {
-                    log_warn!(
+                    let message = format!(
                         "Invalid bit depth for RLE4 compression: {}",
-                        self.dib_header.bits_per_pixel()
+                        self.dib_header.bits_per_pixel(),
                     );
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                 }
 
                 self.data = RleDecoder::decode_rle4(&self.data, self.width, self.height)?;
@@ -409,86 +620,66 @@ impl<R: Read + Seek> BmpDecoder<R> {
             }
             _ => {
                 // TODO: Implement other compression types
-                log_warn!("Unsupported compression type: {:?}", self.dib_header.compression());
+                let message = format!("Unsupported compression type: {:?}", self.dib_header.compression());
+                DecodeWarning::new(WarningKind::Unsupported, message).report();
             }
         }
 
         let bottom_up = self.dib_header.height() > 0;
-
-        let image = match self.dib_header.bits_per_pixel() {
-            1 => PixelDecoder::decode_1bit_image(&self.data, self.width, self.height, bottom_up, &self.color_table),
-            2 => PixelDecoder::decode_2bit_image(&self.data, self.width, self.height, bottom_up, &self.color_table),
-            4 => {
-                if self.rle_decoded {
-                    RleDecoder::decode_rle4_image(&self.data, self.width, self.height, &self.color_table)
-                } else {
-                    PixelDecoder::decode_4bit_image(&self.data, self.width, self.height, bottom_up, &self.color_table)
-                }
-            }
-            8 => {
-                if self.rle_decoded {
-                    RleDecoder::decode_rle8_image(&self.data, self.width, self.height, &self.color_table)
-                } else {
-                    PixelDecoder::decode_8bit_image(&self.data, self.width, self.height, bottom_up, &self.color_table)
-                }
-            }
-            16 => {
-                let use_masks = matches!(
-                    self.dib_header.compression(),
-                    BitmapCompression::BiBitfields | BitmapCompression::BiAlphaBitfields
-                );
-                if let Some((red_mask, green_mask, blue_mask, alpha_mask)) =
-                    use_masks.then(|| self.dib_header.color_masks().or(self.extra_masks)).flatten()
-                {
-                    PixelDecoder::decode_16bit_image_masked(
-                        &self.data,
-                        self.width,
-                        self.height,
-                        bottom_up,
-                        red_mask,
-                        green_mask,
-                        blue_mask,
-                        alpha_mask,
-                    )
-                } else {
-                    PixelDecoder::decode_16bit_image(&self.data, self.width, self.height, bottom_up)
-                }
-            }
-            24 => PixelDecoder::decode_24bit_image(&self.data, self.width, self.height, bottom_up),
-            32 => {
-                let use_masks = matches!(
-                    self.dib_header.compression(),
-                    BitmapCompression::BiBitfields | BitmapCompression::BiAlphaBitfields
-                );
-                let (red_mask, green_mask, blue_mask, alpha_mask) = use_masks
-                    .then(|| self.dib_header.color_masks().or(self.extra_masks))
-                    .flatten()
-                    .unwrap_or_else(|| {
-                        let has_alpha = self.data.chunks_exact(4).any(|px| px[3] != 0);
-                        let a_mask = if has_alpha { 0xFF000000 } else { 0 };
-                        (0x00FF0000, 0x0000FF00, 0x000000FF, a_mask)
-                    });
-                PixelDecoder::decode_32bit_image(
-                    &self.data,
-                    self.width,
-                    self.height,
-                    bottom_up,
-                    red_mask,
-                    green_mask,
-                    blue_mask,
-                    alpha_mask,
-                )
-            }
-            64 => PixelDecoder::decode_64bit_image(&self.data, self.width, self.height, bottom_up),
-            _ => {
-                log_warn!(
-                    "Invalid bit depth: {}. Attempting to decode as 24bit.",
-                    self.dib_header.bits_per_pixel()
-                );
-                PixelDecoder::decode_24bit_image(&self.data, self.width, self.height, bottom_up)
-            }
-        };
+        let masks = self.pixel_masks();
+        let image = self.decode_pixel_rows(&self.data, self.height, bottom_up, masks);
 
         Ok(image)
     }
+
+    /// Decodes an uncompressed image a row at a time and converts each row straight into
+    /// `buffer`, so the pixels are never held as a whole. Returns `None` without writing anything
+    /// for compressed images, which are only decoded as a whole.
+    pub fn decode_into(&mut self, buffer: &mut [u8], stride: usize, format: &PixelFormat) -> VexelResult<Option<ImageHeader>> {
+        self.read_headers()?;
+
+        let uncompressed = matches!(
+            self.dib_header.compression(),
+            BitmapCompression::BiRgb | BitmapCompression::BiBitfields | BitmapCompression::BiAlphaBitfields
+        );
+        let bits_per_pixel = self.dib_header.bits_per_pixel();
+        if !uncompressed || !matches!(bits_per_pixel, 1 | 2 | 4 | 8 | 16 | 24 | 32 | 64) {
+            return Ok(None);
+        }
+
+        let (width, height) = (self.width, self.height);
+        convert::check_buffer(buffer.len(), stride, width, height, format)?;
+
+        if let Err(e) = self.read_pixel_data() {
+            let message = format!("Error reading pixel data. This might be critical! Error: {}", e);
+            DecodeWarning::recovered_from(&e, message).report();
+        }
+
+        let bottom_up = self.dib_header.height() > 0;
+        let masks = self.pixel_masks();
+        let data = std::mem::take(&mut self.data);
+        let row_size = (bits_per_pixel as usize * width as usize).div_ceil(32) * 4;
+        let mut pixel_format = PixelFormat::RGB8;
+
+        for y in 0..height as usize {
+            let file_row = if bottom_up { height as usize - 1 - y } else { y };
+            let start = (file_row * row_size).min(data.len());
+            let end = (start + row_size).min(data.len());
+
+            let row = self.decode_pixel_rows(&data[start..end], 1, false, masks);
+            let pixels = row.into_frames().remove(0).into_pixels();
+            pixel_format = pixels.pixel_format();
+            convert::write_pixels(&pixels, width, 1, &mut buffer[y * stride..], stride, format);
+
+            progress::rows(0, y as u32 + 1, height)?;
+        }
+
+        Ok(Some(ImageHeader {
+            width,
+            height,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        }))
+    }
 }
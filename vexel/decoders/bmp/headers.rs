@@ -4,7 +4,7 @@ use crate::decoders::bmp::types::{
     DibHeader, OS22XBitmapHeader,
 };
 use crate::utils::error::{VexelError, VexelResult};
-use crate::{log_warn};
+use crate::utils::report::{DecodeWarning, WarningKind};
 use crate::bitreader::BitReader;
 use std::io::{Read, Seek};
 
@@ -32,7 +32,8 @@ impl HeaderReader {
             0x4349 => (), // "IC" - OS/2 icon
             0x5450 => (), // "PT" - OS/2 pointer
             _ => {
-                log_warn!("Invalid BMP signature: 0x{:X}", signature);
+                let message = format!("Invalid BMP signature: 0x{:X}", signature);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
             }
         }
 
@@ -57,10 +58,11 @@ impl HeaderReader {
             108 => DibHeader::V4(Self::read_v4_header(reader)?),
             124 => DibHeader::V5(Self::read_v5_header(reader)?),
             _ => {
-                log_warn!(
+                let message = format!(
                     "Invalid DIB header size: {}, assuming 40 bytes. This may cause issues.",
-                    header_size
+                    header_size,
                 );
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 DibHeader::Info(Self::read_bitmap_info_header(reader)?)
             }
         };
@@ -1,6 +1,8 @@
 use crate::decoders::bmp::simd as simd;
 use crate::decoders::bmp::types::ColorEntry;
 use crate::{log_warn, Image, PixelData};
+#[cfg(feature = "rayon")]
+use crate::utils::{progress, report};
 
 pub struct PixelDecoder;
 
@@ -19,10 +21,13 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 3)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
 
@@ -44,7 +49,7 @@ impl PixelDecoder {
                         dst[x * 3 + 1] = color.green;
                         dst[x * 3 + 2] = color.blue;
                     }
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -92,10 +97,13 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 3)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
 
@@ -118,7 +126,7 @@ impl PixelDecoder {
                         dst[x * 3 + 1] = color.green;
                         dst[x * 3 + 2] = color.blue;
                     }
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -167,10 +175,13 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 3)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
 
@@ -192,7 +203,7 @@ impl PixelDecoder {
                         dst[x * 3 + 1] = color.green;
                         dst[x * 3 + 2] = color.blue;
                     }
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -240,16 +251,19 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 3)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
                     let row_end = (row_offset + width_usize).min(data.len());
                     let indices = if row_offset < data.len() { &data[row_offset..row_end] } else { &[] };
                     simd::apply_palette_row(indices, color_table, dst, width_usize);
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -297,16 +311,19 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * channels)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
                     let row_end = (row_offset + width_usize * 2).min(data.len());
                     let src = if row_offset < data.len() { &data[row_offset..row_end] } else { &[] };
                     simd::expand_rgb16_masked_row(src, dst, width_usize, r_shift, r_bits, g_shift, g_bits, b_shift, b_bits, a_shift, a_bits, has_alpha);
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -343,16 +360,19 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 3)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
                     let row_end = (row_offset + width_usize * 2).min(data.len());
                     let src = if row_offset < data.len() { &data[row_offset..row_end] } else { &[] };
                     simd::expand_rgb555_row(src, dst, width_usize);
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -380,16 +400,19 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 3)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
                     let row_end = (row_offset + width_usize * 3).min(data.len());
                     let src = if row_offset < data.len() { &data[row_offset..row_end] } else { &[] };
                     simd::bgr_to_rgb_row(src, dst, width_usize);
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -437,16 +460,19 @@ impl PixelDecoder {
             #[cfg(feature = "rayon")]
             {
                 use rayon::prelude::*;
+
+                let sink = report::current_sink();
+                let hooks = progress::current_hooks();
                 image_data
                     .par_chunks_mut(width_usize * 4)
                     .enumerate()
-                    .for_each(|(dst_row, dst)| {
+                    .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                         let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                         let row_offset = src_row * src_stride;
                         let row_end = (row_offset + width_usize * 4).min(data.len());
                         let src = if row_offset < data.len() { &data[row_offset..row_end] } else { &[] };
                         simd::extract_channels_rgba_row(src, dst, width_usize, r_shift, r_bits, g_shift, g_bits, b_shift, b_bits, a_shift, a_bits);
-                    });
+                    })));
             }
 
             #[cfg(not(feature = "rayon"))]
@@ -468,16 +494,19 @@ impl PixelDecoder {
             #[cfg(feature = "rayon")]
             {
                 use rayon::prelude::*;
+
+                let sink = report::current_sink();
+                let hooks = progress::current_hooks();
                 image_data
                     .par_chunks_mut(width_usize * 3)
                     .enumerate()
-                    .for_each(|(dst_row, dst)| {
+                    .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                         let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                         let row_offset = src_row * src_stride;
                         let row_end = (row_offset + width_usize * 4).min(data.len());
                         let src = if row_offset < data.len() { &data[row_offset..row_end] } else { &[] };
                         simd::extract_channels_rgb_row(src, dst, width_usize, r_shift, r_bits, g_shift, g_bits, b_shift, b_bits);
-                    });
+                    })));
             }
 
             #[cfg(not(feature = "rayon"))]
@@ -506,10 +535,13 @@ impl PixelDecoder {
         #[cfg(feature = "rayon")]
         {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             image_data
                 .par_chunks_mut(width_usize * 4)
                 .enumerate()
-                .for_each(|(dst_row, dst)| {
+                .for_each(|(dst_row, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let src_row = if bottom_up { height_usize - 1 - dst_row } else { dst_row };
                     let row_offset = src_row * src_stride;
 
@@ -522,7 +554,7 @@ impl PixelDecoder {
                             dst[x * 4 + 3] = data[byte_offset + 7];
                         }
                     }
-                });
+                })));
         }
 
         #[cfg(not(feature = "rayon"))]
@@ -1,4 +1,4 @@
-use crate::log_warn;
+use crate::utils::report::{DecodeWarning, WarningKind};
 use crate::utils::icc::ICCProfile;
 use serde::Serialize;
 use tsify::Tsify;
@@ -31,7 +31,7 @@ impl BitmapCompression {
             12 => BitmapCompression::BiCMYKRle8,
             13 => BitmapCompression::BiCMYKRle4,
             _ => {
-                log_warn!("Invalid compression type: {}", value);
+                DecodeWarning::new(WarningKind::CorruptData, format!("Invalid compression type: {}", value)).report();
                 BitmapCompression::BiRgb
             }
         }
@@ -126,6 +126,18 @@ impl DibHeader {
         }
     }
 
+    pub fn pixels_per_meter(&self) -> (i32, i32) {
+        match self {
+            DibHeader::Core(_) => (0, 0),
+            DibHeader::OS2V2(h) => (h.x_pixels_per_meter, h.y_pixels_per_meter),
+            DibHeader::Info(h) => (h.x_pixels_per_meter, h.y_pixels_per_meter),
+            DibHeader::V2(h) => (h.info.x_pixels_per_meter, h.info.y_pixels_per_meter),
+            DibHeader::V3(h) => (h.v2.info.x_pixels_per_meter, h.v2.info.y_pixels_per_meter),
+            DibHeader::V4(h) => (h.v3.v2.info.x_pixels_per_meter, h.v3.v2.info.y_pixels_per_meter),
+            DibHeader::V5(h) => (h.v4.v3.v2.info.x_pixels_per_meter, h.v4.v3.v2.info.y_pixels_per_meter),
+        }
+    }
+
     pub fn color_masks(&self) -> Option<(u32, u32, u32, u32)> {
         match self {
             DibHeader::V2(h) => Some((h.red_mask, h.green_mask, h.blue_mask, 0)),
@@ -1,8 +1,12 @@
 use crate::bitreader::BitReader;
 use crate::utils::error::{VexelError, VexelResult};
 use crate::utils::info::GifInfo;
-use crate::{Image, ImageFrame, Limits, PixelData, PixelFormat, log_debug, log_warn};
+use crate::utils::metadata::ImageMetadata;
+use crate::utils::progress;
+use crate::utils::report::{DecodeWarning, WarningKind};
+use crate::{Image, ImageFormat, ImageFrame, ImageHeader, Limits, PixelData, PixelFormat, log_debug, log_warn};
 use std::io::{Read, Seek};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::compose_simd::compose_frame;
 use super::lzw::decompress_lzw;
@@ -13,7 +17,7 @@ use super::types::{
     GraphicsControlExtension, PlainTextExtension,
 };
 
-impl<R: Read + Seek + Sync> GifDecoder<R> {
+impl<R: Read + Seek> GifDecoder<R> {
     pub fn new(reader: R) -> Self {
         Self {
             width: 0,
@@ -34,6 +38,9 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
             app_extensions: Vec::new(),
             plain_text_extensions: Vec::new(),
             sections: Vec::new(),
+            pending_gce: None,
+            frames_read: 0,
+            canvas: Vec::new(),
             reader: BitReader::new(reader),
         }
     }
@@ -49,6 +56,32 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
         }
     }
 
+    pub fn metadata(&self) -> ImageMetadata {
+        let mut metadata = ImageMetadata::default();
+
+        for section in &self.sections {
+            if let GifSectionData::CommentExtension(comment) = &section.data {
+                metadata.push_text("Comment", comment.text.clone());
+            }
+        }
+
+        for extension in &self.app_extensions {
+            if extension.identifier != "XMP Data" || extension.auth_code != "XMP" {
+                continue;
+            }
+
+            // Drop the "magic trailer" that follows the packet
+            let end = extension.data.iter().rposition(|&b| b == b'>').map_or(0, |pos| pos + 1);
+            metadata.xmp.push(String::from_utf8_lossy(&extension.data[..end]).to_string());
+        }
+
+        metadata
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn read_header(&mut self) -> VexelResult<()> {
         let start_offset = self.reader.stream_position().unwrap_or(0);
 
@@ -69,7 +102,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
         });
         let offset = sig_offset.unwrap_or(0);
         if offset > 0 || (!buf.starts_with(b"GIF87a") && !buf.starts_with(b"GIF89a")) {
-            log_warn!("GIF signature at offset {}, possibly corrupted header", offset);
+            let message = format!("GIF signature at offset {}, possibly corrupted header", offset);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset + offset as u64).report();
         }
         let sig_len = if buf[offset..].starts_with(b"GIF") { 6 } else { 5 };
         self.version = String::from_utf8_lossy(buf.get(offset + (sig_len - 3)..offset + sig_len).unwrap_or(b"87a")).to_string();
@@ -121,7 +155,9 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
             let bit = match self.reader.read_u8() {
                 Ok(bit) => bit,
                 Err(e) => {
-                    log_warn!("Error reading global color table: {:?}", e);
+                    let offset = self.reader.stream_position().unwrap_or(0);
+                    let message = format!("Error reading global color table: {}", e);
+                    DecodeWarning::recovered_from(&e.into(), message).at_offset(offset).report();
                     continue;
                 }
             };
@@ -146,7 +182,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
     fn read_application_extension(&mut self, start_offset: u64) -> VexelResult<()> {
         let block_size = self.reader.read_u8()?;
         if block_size != 11 {
-            log_warn!("Invalid application extension block size: {}", block_size);
+            let message = format!("Invalid application extension block size: {}", block_size);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         let mut identifier = Vec::with_capacity(8);
@@ -215,6 +252,9 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                 self.app_extensions.push(app_extension);
             }
         } else {
+            // XMP packets are stored as raw bytes, the sub-block sizes are part of the packet
+            let is_xmp = identifier == b"XMP Data" && auth_code == b"XMP";
+
             let mut data = Vec::new();
             loop {
                 let sub_block_size = self.reader.read_u8()? as usize;
@@ -223,12 +263,17 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                     break;
                 }
 
+                if is_xmp {
+                    data.push(sub_block_size as u8);
+                }
+
                 for _ in 0..sub_block_size {
                     data.push(self.reader.read_u8()?);
                 }
             }
 
             let data_length = data.len() as u64;
+            self.limits.reserve_metadata(data_length)?;
             self.sections.push(GifSectionInfo {
                 start_offset,
                 data: GifSectionData::ApplicationExtension(GifApplicationExtensionData {
@@ -255,7 +300,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
     fn read_plain_text_extension(&mut self, start_offset: u64) -> VexelResult<()> {
         let block_size = self.reader.read_u8()?;
         if block_size != 12 {
-            log_warn!("Invalid plain text extension block size: {}", block_size);
+            let message = format!("Invalid plain text extension block size: {}", block_size);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         let left = self.reader.read_u16()?.swap_bytes();
@@ -320,6 +366,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                 break;
             }
 
+            self.limits.reserve_metadata(block_size as u64)?;
+
             let mut block = Vec::with_capacity(block_size as usize);
             for _ in 0..block_size {
                 let byte = self.reader.read_u8()?;
@@ -340,8 +388,13 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
     }
 
     fn read_frames(&mut self) -> VexelResult<()> {
-        let mut current_gce: Option<(u64, GraphicsControlExtension)> = None;
+        while self.read_next_frame()? {}
 
+        Ok(())
+    }
+
+    /// Reads blocks up to and including the next image. Returns `false` once the trailer is reached.
+    fn read_next_frame(&mut self) -> VexelResult<bool> {
         loop {
             let block_start = self.reader.stream_position().unwrap_or(0);
             let block_type = self.reader.read_u8()?;
@@ -349,7 +402,9 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
             match block_type {
                 // Image Separator (0x2C)
                 0x2C => {
-                    self.read_frame(block_start, current_gce.take().map(|(_, gce)| gce))?;
+                    let gce = self.pending_gce.take();
+                    self.read_frame(block_start, gce)?;
+                    return Ok(true);
                 }
                 // Extension Introducer (0x21)
                 0x21 => {
@@ -357,7 +412,7 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                     match label {
                         0xF9 => {
                             let gce = self.read_graphics_control_extension(block_start)?;
-                            current_gce = Some((block_start, gce));
+                            self.pending_gce = Some(gce);
                         }
                         0xFE => {
                             self.read_comment_extension(block_start)?;
@@ -389,16 +444,16 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                         start_offset: block_start,
                         data: GifSectionData::Trailer,
                     });
-                    break;
+                    return Ok(false);
                 }
                 _ => {}
             }
         }
-
-        Ok(())
     }
 
     fn read_frame(&mut self, descriptor_offset: u64, gce: Option<GraphicsControlExtension>) -> VexelResult<()> {
+        self.limits.check_frames(self.frames_read + 1)?;
+
         let left = self.reader.read_u16()?.swap_bytes() as u32;
         let top = self.reader.read_u16()?.swap_bytes() as u32;
         let width = self.reader.read_u16()?.swap_bytes() as u32;
@@ -455,7 +510,11 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                 Ok(0) => break,
                 Ok(size) => size as usize,
                 Err(e) => {
-                    log_warn!("Error reading image sub-block size: {:?}", e);
+                    let offset = self.reader.stream_position().unwrap_or(0);
+                    DecodeWarning::new(WarningKind::TruncatedData, format!("Error reading image sub-block size: {}", e))
+                        .at_offset(offset)
+                        .in_frame(self.frames_read)
+                        .report();
                     break;
                 }
             };
@@ -465,7 +524,11 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
             match self.reader.read_exact(&mut buffer) {
                 Ok(_) => {}
                 Err(e) => {
-                    log_warn!("Error reading image sub-block data: {:?}", e);
+                    let offset = self.reader.stream_position().unwrap_or(0);
+                    DecodeWarning::new(WarningKind::TruncatedData, format!("Error reading image sub-block data: {}", e))
+                        .at_offset(offset)
+                        .in_frame(self.frames_read)
+                        .report();
                     data.extend_from_slice(&buffer);
                     break;
                 }
@@ -512,6 +575,7 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
         }
 
         self.frames.push(frame);
+        self.frames_read += 1;
 
         Ok(())
     }
@@ -519,7 +583,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
     fn read_graphics_control_extension(&mut self, start_offset: u64) -> VexelResult<GraphicsControlExtension> {
         let block_size = self.reader.read_u8()?;
         if block_size != 4 {
-            log_warn!("Invalid graphics control extension block size: {}", block_size);
+            let message = format!("Invalid graphics control extension block size: {}", block_size);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         let packed = self.reader.read_u8()?;
@@ -539,7 +604,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
 
         let terminator = self.reader.read_u8()?;
         if terminator != 0 {
-            log_warn!("Invalid graphics control extension block terminator: {}", terminator);
+            let message = format!("Invalid graphics control extension block terminator: {}", terminator);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         self.sections.push(GifSectionInfo {
@@ -577,7 +643,8 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
                 if src_end <= indices.len() && dst_end <= result.len() {
                     result[dst_start..dst_end].copy_from_slice(&indices[source_pos..src_end]);
                 } else {
-                    log_warn!("Interlace index out of bounds at row {}", y);
+                    let message = format!("Interlace index out of bounds at row {}", y);
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                 }
 
                 source_pos += row_stride;
@@ -589,43 +656,309 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
     }
 
 
-    pub fn decode(&mut self) -> VexelResult<Image> {
+    fn skip_sub_blocks(&mut self) -> VexelResult<()> {
+        loop {
+            let block_size = self.reader.read_u8()?;
+            if block_size == 0 {
+                return Ok(());
+            }
+            self.reader.seek(std::io::SeekFrom::Current(block_size as i64))?;
+        }
+    }
+
+    fn count_frames(&mut self, frame_count: &mut u32) -> VexelResult<()> {
+        while let Ok(block_type) = self.reader.read_u8() {
+            match block_type {
+                0x2C => {
+                    // Left, top, width and height are not needed here
+                    self.reader.seek(std::io::SeekFrom::Current(8))?;
+                    let packed_fields = self.reader.read_u8()?;
+                    if (packed_fields & 0b10000000) != 0 {
+                        let table_size = 3i64 << ((packed_fields & 0b00000111) + 1);
+                        self.reader.seek(std::io::SeekFrom::Current(table_size))?;
+                    }
+
+                    // LZW minimum code size. Truncated image data still produces a frame.
+                    self.reader.read_u8()?;
+                    *frame_count += 1;
+                    self.skip_sub_blocks()?;
+                }
+                0x21 => {
+                    // Fixed-size headers are skipped as a whole, like the decoder reads them,
+                    // even when their block size field is wrong
+                    match self.reader.read_u8()? {
+                        0xF9 => {
+                            self.reader.seek(std::io::SeekFrom::Current(6))?;
+                        }
+                        0xFF => {
+                            self.reader.seek(std::io::SeekFrom::Current(12))?;
+                            self.skip_sub_blocks()?;
+                        }
+                        _ => self.skip_sub_blocks()?,
+                    }
+                }
+                0x3B => break,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
         match self.read_header() {
             Ok(_) => {}
             Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
             Err(e) => {
-                log_warn!("Error reading header, this might be critical! Error: {:?}", e);
+                let e = e.with_format(&ImageFormat::Gif);
+                let message = format!("Error reading header, this might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
+            }
+        };
+
+        if self.global_color_table_flag {
+            let table_size = 3i64 << (self.size_of_global_color_table + 1);
+            self.reader.seek(std::io::SeekFrom::Current(table_size))?;
+        }
+
+        let mut frame_count = 0;
+        if let Err(e) = self.count_frames(&mut frame_count) {
+            let e = e.with_format(&ImageFormat::Gif);
+            let message = format!("Error reading frames, this might be critical! Error: {}", e);
+            DecodeWarning::recovered_from(&e, message).at_offset(self.reader.stream_position().unwrap_or(0)).report();
+        }
+
+        if self.canvas_width == 0 || self.canvas_height == 0 {
+            return Err(VexelError::InvalidDimensions {
+                width: self.canvas_width,
+                height: self.canvas_height,
+            });
+        }
+
+        Ok(ImageHeader {
+            width: self.canvas_width,
+            height: self.canvas_height,
+            pixel_format: PixelFormat::RGBA8,
+            frame_count,
+            is_animated: frame_count > 1,
+        })
+    }
+
+    /// Decompresses the LZW data of a frame into colour table indices in row order. `index` is
+    /// the position of the frame in the file.
+    fn decode_indices(index: usize, frame: &GifFrameInfo) -> VexelResult<Vec<u8>> {
+        let mut indices = match decompress_lzw(frame) {
+            Ok(i) => i,
+            Err(e) => {
+                let e = e.with_format(&ImageFormat::Gif);
+                log_warn!("Error decoding frame: {}", e);
+                return Err(e);
+            }
+        };
+
+        let expected = frame.width as usize * frame.height as usize;
+        if indices.len() < expected {
+            let message = format!("Frame data ends after {} of {} pixels", indices.len(), expected);
+            DecodeWarning::new(WarningKind::TruncatedData, message)
+                .in_frame(index)
+                .with_synthesized_pixels((expected - indices.len()) as u64)
+                .report();
+        }
+
+        if frame.interlace_flag {
+            indices = Self::deinterlace_indices(frame.width, frame.height, &indices);
+        }
+
+        Ok(indices)
+    }
+
+    /// Returns the size of the canvas in bytes. Logical screens of up to 65535x65535 pixels
+    /// overflow `u32`, so the size is computed in `usize`.
+    fn canvas_size(&self) -> VexelResult<usize> {
+        (self.width as usize)
+            .checked_mul(self.height as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or_else(|| {
+                VexelError::LimitExceeded(format!("canvas of {}x{} pixels is too large", self.width, self.height))
+            })
+    }
+
+    /// Allocates the canvas frames are drawn onto, reserved against the allocation budget.
+    fn new_canvas(&mut self) -> VexelResult<Vec<u8>> {
+        let canvas_size = self.canvas_size()?;
+        self.limits.reserve_usize(canvas_size)?;
+
+        Ok(vec![0u8; canvas_size])
+    }
+
+    /// Draws a frame onto `canvas` and returns the composited frame. `canvas` is left in the
+    /// state the next frame is drawn onto, according to the disposal method.
+    fn compose(&self, frame: &GifFrameInfo, indices: &[u8], canvas: &mut Vec<u8>) -> VexelResult<ImageFrame> {
+        let canvas_size = self.canvas_size()?;
+
+        let saved_canvas = match frame.disposal_method {
+            DisposalMethod::Previous | DisposalMethod::Background => Some(canvas.clone()),
+            DisposalMethod::None => None,
+        };
+
+        let color_table = if frame.local_color_table_flag {
+            &frame.local_color_table
+        } else {
+            &self.global_color_table
+        };
+
+        compose_frame(
+            indices,
+            color_table,
+            frame.transparent_index,
+            frame.left as usize,
+            frame.top as usize,
+            frame.width as usize,
+            frame.height as usize,
+            self.width as usize,
+            self.height as usize,
+            canvas,
+        );
+
+        let next_canvas = match frame.disposal_method {
+            DisposalMethod::None => canvas.clone(),
+            DisposalMethod::Background => {
+                let mut next_canvas = saved_canvas.unwrap_or_else(|| vec![0u8; canvas_size]);
+                let clamped_h = frame.height.min(self.height.saturating_sub(frame.top));
+                let clamped_w = frame.width.min(self.width.saturating_sub(frame.left));
+                for y in 0..clamped_h {
+                    let canvas_y = frame.top + y;
+                    let row_base = (canvas_y as usize * self.width as usize + frame.left as usize) * 4;
+                    let row_end = row_base + clamped_w as usize * 4;
+                    if row_end <= next_canvas.len() {
+                        next_canvas[row_base..row_end].fill(0);
+                    }
+                }
+                next_canvas
+            }
+            DisposalMethod::Previous => saved_canvas.unwrap_or_else(|| vec![0u8; canvas_size]),
+        };
+
+        let pixels = std::mem::replace(canvas, next_canvas);
+
+        Ok(ImageFrame::new(self.width, self.height, PixelData::RGBA8(pixels), frame.delay as u32))
+    }
+
+    /// Reads the headers in front of the first frame, so frames can be decoded one at a time
+    /// with [`next_frame`](Self::next_frame).
+    pub fn start_frames(&mut self) -> VexelResult<()> {
+        match self.read_header() {
+            Ok(_) => {}
+            Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
+            Err(e) => {
+                let e = e.with_format(&ImageFormat::Gif);
+                let message = format!("Error reading header, this might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).report();
             }
         };
 
         self.read_global_color_table();
 
+        Ok(())
+    }
+
+    /// Reads and composites the next frame. Only the canvas is kept between frames.
+    pub fn next_frame(&mut self) -> VexelResult<Option<ImageFrame>> {
+        match self.read_next_frame() {
+            Ok(true) => {}
+            Ok(false) => return Ok(None),
+            Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
+            Err(e) => {
+                let e = e.with_format(&ImageFormat::Gif);
+                let offset = self.reader.stream_position().unwrap_or(0);
+                let message = format!("Error reading frames, this might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).at_offset(offset).report();
+                return Ok(None);
+            }
+        }
+
+        // The frame that was just read, frames stay listed for `get_info` once decoded
+        let index = self.frames_read - 1;
+        let Some(frame) = self.frames.get(index) else {
+            return Ok(None);
+        };
+
+        let index_bytes = frame.width as usize * frame.height as usize;
+        self.limits.reserve_usize(index_bytes)?;
+        let indices = Self::decode_indices(index, frame);
+        let indices = match indices {
+            Ok(indices) => indices,
+            Err(e) => {
+                self.limits.free_usize(index_bytes);
+                return Err(e);
+            }
+        };
+
+        if self.canvas.is_empty() {
+            self.canvas = self.new_canvas()?;
+        }
+
+        let mut canvas = std::mem::take(&mut self.canvas);
+        let image_frame = self.compose(&self.frames[index], &indices, &mut canvas);
+        self.canvas = canvas;
+
+        // The index buffer is held until the frame has been composited
+        drop(indices);
+        self.limits.free_usize(index_bytes);
+
+        // Only the canvas is needed for the next frame, not the compressed data of this one
+        self.frames[index].data = Vec::new();
+
+        image_frame.map(Some)
+    }
+
+    pub fn decode(&mut self) -> VexelResult<Image> {
+        self.start_frames()?;
+
         match self.read_frames() {
             Ok(_) => {}
+            Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
             Err(e) => {
-                log_warn!("Error reading frames, this might be critical! Error: {:?}", e);
+                let e = e.with_format(&ImageFormat::Gif);
+                let offset = self.reader.stream_position().unwrap_or(0);
+                let message = format!("Error reading frames, this might be critical! Error: {}", e);
+                DecodeWarning::recovered_from(&e, message).at_offset(offset).report();
             }
         };
 
+        let total = self.frames.len();
+
+        // Every frame is decompressed to indices up front and composited onto its own canvas,
+        // the first canvas was already reserved with the header
+        let index_bytes = self
+            .frames
+            .iter()
+            .fold(0u64, |sum, frame| sum.saturating_add(u64::from(frame.width) * u64::from(frame.height)));
+        self.limits.reserve(index_bytes)?;
+        for _ in 1..total {
+            self.limits.reserve_buffer(self.width, self.height, 4)?;
+        }
+
+        let completed = AtomicUsize::new(0);
+        let decode_frame = |index: usize, frame: &GifFrameInfo| -> VexelResult<Vec<u8>> {
+            let indices = Self::decode_indices(index, frame)?;
+            progress::frames(completed.fetch_add(1, Ordering::Relaxed) + 1, total)?;
+
+            Ok(indices)
+        };
+
         #[cfg(feature = "rayon")]
         let decoded_indices: Vec<Vec<u8>> = {
+            use crate::utils::report;
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             self.frames
                 .par_iter()
-                .map(|frame| {
-                    let mut indices = match decompress_lzw(frame) {
-                        Ok(i) => i,
-                        Err(e) => {
-                            log_warn!("Error decoding frame: {:?}", e);
-                            return Err(e);
-                        }
-                    };
-
-                    if frame.interlace_flag {
-                        indices = Self::deinterlace_indices(frame.width, frame.height, &indices);
-                    }
-
-                    Ok(indices)
+                .enumerate()
+                .map(|(index, frame)| {
+                    report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || decode_frame(index, frame)))
                 })
                 .collect::<VexelResult<Vec<_>>>()?
         };
@@ -634,79 +967,19 @@ impl<R: Read + Seek + Sync> GifDecoder<R> {
         let decoded_indices: Vec<Vec<u8>> = self
             .frames
             .iter()
-            .map(|frame| {
-                let mut indices = match decompress_lzw(frame) {
-                    Ok(i) => i,
-                    Err(e) => {
-                        log_warn!("Error decoding frame: {:?}", e);
-                        return Err(e);
-                    }
-                };
-
-                if frame.interlace_flag {
-                    indices = Self::deinterlace_indices(frame.width, frame.height, &indices);
-                }
-
-                Ok(indices)
-            })
+            .enumerate()
+            .map(|(index, frame)| decode_frame(index, frame))
             .collect::<VexelResult<Vec<_>>>()?;
 
-        let canvas_size = (self.width * self.height * 4) as usize;
-        let mut canvas = vec![0u8; canvas_size];
-        let mut image_frames = Vec::new();
-
-        for (frame, indices) in self.frames.iter().zip(decoded_indices) {
-            let saved_canvas = match frame.disposal_method {
-                DisposalMethod::Previous | DisposalMethod::Background => Some(canvas.clone()),
-                DisposalMethod::None => None,
-            };
-
-            let color_table = if frame.local_color_table_flag {
-                &frame.local_color_table
-            } else {
-                &self.global_color_table
-            };
-
-            compose_frame(
-                &indices,
-                color_table,
-                frame.transparent_index,
-                frame.left as usize,
-                frame.top as usize,
-                frame.width as usize,
-                frame.height as usize,
-                self.width as usize,
-                self.height as usize,
-                &mut canvas,
-            );
-
-            match frame.disposal_method {
-                DisposalMethod::None => {
-                    let next_canvas = canvas.clone();
-                    image_frames.push(ImageFrame::new(self.width, self.height, PixelData::RGBA8(canvas), frame.delay as u32));
-                    canvas = next_canvas;
-                }
-                DisposalMethod::Background => {
-                    let mut next_canvas = saved_canvas.unwrap_or_else(|| vec![0u8; canvas_size]);
-                    let clamped_h = frame.height.min(self.height.saturating_sub(frame.top));
-                    let clamped_w = frame.width.min(self.width.saturating_sub(frame.left));
-                    for y in 0..clamped_h {
-                        let canvas_y = frame.top + y;
-                        let row_base = (canvas_y * self.width + frame.left) as usize * 4;
-                        let row_end = row_base + clamped_w as usize * 4;
-                        if row_end <= next_canvas.len() {
-                            next_canvas[row_base..row_end].fill(0);
-                        }
-                    }
-                    image_frames.push(ImageFrame::new(self.width, self.height, PixelData::RGBA8(canvas), frame.delay as u32));
-                    canvas = next_canvas;
-                }
-                DisposalMethod::Previous => {
-                    image_frames.push(ImageFrame::new(self.width, self.height, PixelData::RGBA8(canvas), frame.delay as u32));
-                    canvas = saved_canvas.unwrap_or_else(|| vec![0u8; canvas_size]);
-                }
-            }
-        }
+        let mut canvas = self.new_canvas()?;
+        let image_frames = self
+            .frames
+            .iter()
+            .zip(decoded_indices)
+            .map(|(frame, indices)| self.compose(frame, &indices, &mut canvas))
+            .collect::<VexelResult<Vec<_>>>()?;
+        self.limits.free(index_bytes);
+        self.limits.free_usize(canvas.len());
 
         if self.width == 0 {
             self.width = self.canvas_width;
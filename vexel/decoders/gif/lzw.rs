@@ -1,5 +1,5 @@
 use crate::utils::error::VexelResult;
-use crate::{log_warn};
+use crate::utils::report::{DecodeWarning, WarningKind};
 
 use super::types::GifFrameInfo;
 
@@ -78,7 +78,7 @@ pub fn decompress_lzw(frame: &GifFrameInfo) -> VexelResult<Vec<u8>> {
             if code < next_code {
                 let (offset, len) = match table.get(code as usize) {
                     Some(&(_, 0)) | None => {
-                        log_warn!("Invalid LZW code: {}", code);
+                        DecodeWarning::new(WarningKind::CorruptData, format!("Invalid LZW code: {}", code)).report();
                         prev_code = Some(code);
                         if next_code >= (1 << code_size) && code_size < 12 {
                             code_size += 1;
@@ -118,7 +118,7 @@ pub fn decompress_lzw(frame: &GifFrameInfo) -> VexelResult<Vec<u8>> {
                     }
                 }
             } else {
-                log_warn!("Invalid LZW code: {}", code);
+                DecodeWarning::new(WarningKind::CorruptData, format!("Invalid LZW code: {}", code)).report();
             }
         } else {
             if let Some(&(offset, len)) = table.get(code as usize) {
@@ -1,6 +1,7 @@
 pub mod compose_simd;
 pub mod decoder;
 pub mod lzw;
+pub mod stream;
 pub mod types;
 
 pub use types::*;
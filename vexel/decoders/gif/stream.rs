@@ -0,0 +1,113 @@
+use crate::utils::stream::ScanProgress;
+
+const HEADER_LENGTH: usize = 13;
+
+/// Finds complete blocks in a GIF that is still being received.
+pub(crate) struct GifScanner {
+    position: usize,
+    progress: ScanProgress,
+}
+
+impl GifScanner {
+    pub(crate) fn new() -> Self {
+        Self {
+            position: 0,
+            progress: ScanProgress::default(),
+        }
+    }
+
+    /// Walks the blocks received since the last call, using the same layout rules as
+    /// [`GifDecoder`](super::decoder::GifDecoder) when counting frames. A partial block is
+    /// picked up again once more data arrives.
+    pub(crate) fn scan(&mut self, data: &[u8]) -> ScanProgress {
+        if self.position == 0 {
+            if data.len() < HEADER_LENGTH {
+                return self.progress;
+            }
+
+            // Signature and logical screen descriptor, followed by the global colour table
+            let packed_fields = data[10];
+            let table_size = if (packed_fields & 0b10000000) != 0 {
+                3 << ((packed_fields & 0b00000111) + 1)
+            } else {
+                0
+            };
+
+            self.position = HEADER_LENGTH + table_size;
+        }
+
+        while !self.progress.done {
+            let Some(&block_type) = data.get(self.position) else {
+                break;
+            };
+
+            let end = match block_type {
+                0x2C => {
+                    self.progress.header_complete = true;
+                    Self::image_end(data, self.position)
+                }
+                0x21 => Self::extension_end(data, self.position),
+                0x3B => {
+                    self.progress.header_complete = true;
+                    self.progress.done = true;
+                    break;
+                }
+                // Unknown bytes are skipped one at a time, like the decoder does
+                _ => Some(self.position + 1),
+            };
+
+            let Some(end) = end else {
+                break;
+            };
+
+            if block_type == 0x2C {
+                self.progress.units += 1;
+            }
+
+            self.position = end;
+        }
+
+        self.progress
+    }
+
+    /// Returns the end of an image descriptor and its data, or `None` if it is not complete yet.
+    fn image_end(data: &[u8], start: usize) -> Option<usize> {
+        // Separator, left, top, width and height
+        let packed_fields = *data.get(start + 9)?;
+        let mut position = start + 10;
+
+        if (packed_fields & 0b10000000) != 0 {
+            position += 3 << ((packed_fields & 0b00000111) + 1);
+        }
+
+        // LZW minimum code size
+        position += 1;
+
+        Self::sub_blocks_end(data, position)
+    }
+
+    /// Returns the end of an extension block, or `None` if it is not complete yet.
+    fn extension_end(data: &[u8], start: usize) -> Option<usize> {
+        // Fixed-size headers are skipped as a whole, even when their block size field is wrong
+        let position = match *data.get(start + 1)? {
+            0xF9 => return Some(start + 8).filter(|&end| end <= data.len()),
+            0xFF => start + 14,
+            _ => start + 2,
+        };
+
+        Self::sub_blocks_end(data, position)
+    }
+
+    fn sub_blocks_end(data: &[u8], mut position: usize) -> Option<usize> {
+        loop {
+            let block_size = *data.get(position)? as usize;
+            position += 1;
+
+            if block_size == 0 {
+                return Some(position);
+            }
+
+            position += block_size;
+        }
+    }
+}
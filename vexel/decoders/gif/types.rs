@@ -134,6 +134,12 @@ pub struct GifDecoder<R: Read + Seek> {
     pub(super) app_extensions: Vec<ApplicationExtension>,
     pub(super) plain_text_extensions: Vec<PlainTextExtension>,
     pub(super) sections: Vec<GifSectionInfo>,
+    /// Graphics control extension waiting for the image descriptor it applies to.
+    pub(super) pending_gce: Option<GraphicsControlExtension>,
+    /// Number of image descriptors read so far.
+    pub(super) frames_read: usize,
+    /// Composited canvas when decoding one frame at a time.
+    pub(super) canvas: Vec<u8>,
     pub(super) reader: BitReader<R>,
 }
 
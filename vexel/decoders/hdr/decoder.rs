@@ -1,7 +1,8 @@
 use crate::bitreader::BitReader;
 use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::report::{DecodeWarning, WarningKind};
 use crate::utils::info::HdrInfo;
-use crate::{Image, Limits, log_warn};
+use crate::{Image, ImageFormat, ImageHeader, Limits, PixelFormat};
 use std::io::{Read, Seek};
 
 use super::pixels::PixelDecoder;
@@ -80,7 +81,7 @@ impl<R: Read + Seek> HdrDecoder<R> {
                 } else if format.contains("32-bit_rle_xyze") {
                     self.format = HdrFormat::XYZE;
                 } else {
-                    log_warn!("Invalid HDR format: {}", format);
+                    DecodeWarning::new(WarningKind::CorruptData, format!("Invalid HDR format: {}", format)).report();
                     self.format = HdrFormat::RGBE;
                 }
 
@@ -124,7 +125,8 @@ impl<R: Read + Seek> HdrDecoder<R> {
                 }
 
                 if p.len() != 8 {
-                    log_warn!("Invalid number of primaries: {}", p.len());
+                    let message = format!("Invalid number of primaries: {}", p.len());
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                     if p.len() > 8 {
                         p.truncate(8);
                     } else {
@@ -153,7 +155,7 @@ impl<R: Read + Seek> HdrDecoder<R> {
             let parts: Vec<&str> = line.split_whitespace().collect();
 
             if parts.len() < 4 {
-                return Err(VexelError::Custom(format!("Invalid header line: {}", line)));
+                return Err(VexelError::corrupt(ImageFormat::Hdr, format!("Invalid header line: {}", line)));
             }
 
             let (dim1, dim2) = match parts[0].chars().nth(1) {
@@ -170,15 +172,15 @@ impl<R: Read + Seek> HdrDecoder<R> {
                     (width_str, height_str)
                 }
                 _ => {
-                    return Err(VexelError::Custom(format!(
+                    return Err(VexelError::corrupt(ImageFormat::Hdr, format!(
                         "Invalid header line: {}, cant parse image dimensions",
                         line
                     )));
                 }
             };
 
-            self.width = dim1.parse::<u32>().map_err(|_| "Failed to parse width")?;
-            self.height = dim2.parse::<u32>().map_err(|_| "Failed to parse height")?;
+            self.width = dim1.parse::<u32>().map_err(|_| VexelError::corrupt(ImageFormat::Hdr, "Failed to parse width"))?;
+            self.height = dim2.parse::<u32>().map_err(|_| VexelError::corrupt(ImageFormat::Hdr, "Failed to parse height"))?;
 
             if self.width == 0 || self.height == 0 {
                 return Err(VexelError::InvalidDimensions {
@@ -220,7 +222,7 @@ impl<R: Read + Seek> HdrDecoder<R> {
         match str.parse::<f32>() {
             Ok(value) => value,
             Err(_) => {
-                log_warn!("Failed to parse float: {}", str);
+                DecodeWarning::new(WarningKind::CorruptData, format!("Failed to parse float: {}", str)).report();
 
                 0.0
             }
@@ -326,22 +328,24 @@ impl<R: Read + Seek> HdrDecoder<R> {
                 let scanline_bytes = width * 4;
 
                 if scanline_start + scanline_bytes > rgbe_data.len() {
-                    log_warn!(
+                    let message = format!(
                         "Scanline index out of bounds: {} >= {}",
                         scanline_start + scanline_bytes,
-                        rgbe_data.len()
+                        rgbe_data.len(),
                     );
+                    DecodeWarning::new(WarningKind::TruncatedData, message).report();
                     break;
                 }
 
                 let src_end = cursor + scanline_bytes;
                 if src_end > compressed.len() {
-                    log_warn!(
+                    let message = format!(
                         "Compressed data too short for scanline {}: need {} bytes, have {}",
                         y,
                         scanline_bytes,
-                        compressed.len() - cursor
+                        compressed.len() - cursor,
                     );
+                    DecodeWarning::new(WarningKind::TruncatedData, message).report();
                     break;
                 }
 
@@ -360,6 +364,22 @@ impl<R: Read + Seek> HdrDecoder<R> {
         }
     }
 
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        self.read_header()?;
+
+        Ok(ImageHeader {
+            width: self.width,
+            height: self.height,
+            pixel_format: PixelFormat::RGB32F,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
     pub fn decode(&mut self) -> VexelResult<Image> {
         self.read_header()?;
 
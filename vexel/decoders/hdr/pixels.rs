@@ -1,4 +1,6 @@
 use crate::utils::error::VexelResult;
+#[cfg(feature = "rayon")]
+use crate::utils::{progress, report};
 use crate::PixelData;
 
 use super::simd;
@@ -49,13 +51,16 @@ fn decode_rgbe(rgbe_data: &[u8], rgb_data: &mut [f32], width: usize) {
     {
         use rayon::prelude::*;
 
+        let sink = report::current_sink();
+        let hooks = progress::current_hooks();
+
         rgb_data
             .par_chunks_mut(width * 3)
             .enumerate()
-            .for_each(|(row, rgb_row)| {
+            .for_each(|(row, rgb_row)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                 let rgbe_row = &rgbe_data[row * width * 4..];
                 decode_rgbe_row(rgbe_row, rgb_row, width, &exp2_table);
-            });
+            })));
     }
 
     #[cfg(not(feature = "rayon"))]
@@ -92,14 +97,17 @@ fn convert_xyze_to_rgb(xyz_data: &[f32], rgb_data: &mut [f32], width: usize) {
     {
         use rayon::prelude::*;
 
+        let sink = report::current_sink();
+        let hooks = progress::current_hooks();
+
         let row_floats = width * 3;
 
         xyz_data
             .par_chunks(row_floats)
             .zip(rgb_data.par_chunks_mut(row_floats))
-            .for_each(|(src_row, dst_row)| {
+            .for_each(|(src_row, dst_row)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                 simd::xyz_to_rgb(src_row, dst_row);
-            });
+            })));
     }
 
     #[cfg(not(feature = "rayon"))]
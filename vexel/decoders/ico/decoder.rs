@@ -6,8 +6,10 @@ use crate::decoders::ico::types::{
 };
 use crate::decoders::png::decoder::PngDecoder;
 use crate::utils::error::{VexelError, VexelResult};
-use crate::utils::image::{Image, ImageFrame, PixelData};
+use crate::utils::report::{DecodeWarning, WarningKind};
+use crate::utils::image::{Image, ImageFormat, ImageFrame, ImageHeader, PixelData, PixelFormat};
 use crate::utils::info::IcoInfo;
+use crate::utils::progress;
 use crate::{Limits, log_error, log_warn};
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
@@ -19,6 +21,8 @@ pub struct IcoDecoder<R: Read + Seek> {
     entries: Vec<IconDirEntry>,
     entry_offsets: Vec<u64>,
     sections: Vec<IcoSectionInfo>,
+    next_entry: usize,
+    frames_read: usize,
     reader: BitReader<R>,
 }
 
@@ -32,6 +36,8 @@ impl<R: Read + Seek> IcoDecoder<R> {
             entries: Vec::new(),
             entry_offsets: Vec::new(),
             sections: Vec::new(),
+            next_entry: 0,
+            frames_read: 0,
             reader: BitReader::with_le(reader),
         }
     }
@@ -47,12 +53,17 @@ impl<R: Read + Seek> IcoDecoder<R> {
         }
     }
 
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn read_header(&mut self) -> VexelResult<u16> {
         let header_offset = self.reader.stream_position().unwrap_or(0);
 
         let reserved = self.reader.read_u16()?;
         if reserved != 0 {
-            log_warn!("ICO reserved field is non-zero: {}", reserved);
+            let message = format!("ICO reserved field is non-zero: {}", reserved);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(header_offset).report();
         }
 
         let type_val = self.reader.read_u16()?;
@@ -60,7 +71,8 @@ impl<R: Read + Seek> IcoDecoder<R> {
             1 => IcoType::Ico,
             2 => IcoType::Cur,
             _ => {
-                log_warn!("Unknown ICO type: {}, treating as ICO", type_val);
+                let message = format!("Unknown ICO type: {}, treating as ICO", type_val);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(header_offset).report();
                 IcoType::Ico
             }
         };
@@ -144,6 +156,13 @@ impl<R: Read + Seek> IcoDecoder<R> {
         Ok(())
     }
 
+    fn image_format(&self) -> ImageFormat {
+        match self.ico_type {
+            IcoType::Ico => ImageFormat::Ico,
+            IcoType::Cur => ImageFormat::Cur,
+        }
+    }
+
     fn push_entry_and_image_data_sections(&mut self) {
         for (entry, &offset) in self.entries.iter().zip(self.entry_offsets.iter()) {
             self.sections.push(IcoSectionInfo {
@@ -180,7 +199,7 @@ impl<R: Read + Seek> IcoDecoder<R> {
 
         let mut png_decoder = PngDecoder::new(Cursor::new(png_bytes));
         png_decoder.set_limits(self.limits.clone());
-        let image = png_decoder.decode()?;
+        let image = progress::without_progress(|| png_decoder.decode())?;
 
         let frame = image.frames().first().cloned().unwrap_or_else(|| {
             ImageFrame::new(
@@ -206,7 +225,81 @@ impl<R: Read + Seek> IcoDecoder<R> {
         Ok(ImageFrame::new(entry.width, entry.height, pixels, 0))
     }
 
-    pub fn decode(&mut self) -> VexelResult<Image> {
+    fn probe_png_entry(&mut self, entry: &IconDirEntry) -> VexelResult<ImageHeader> {
+        self.reader.seek(SeekFrom::Start(entry.image_offset as u64))?;
+        let png_bytes = self.reader.read_bytes(entry.bytes_in_res as usize)?;
+
+        let mut png_decoder = PngDecoder::new(Cursor::new(png_bytes));
+        png_decoder.set_limits(self.limits.clone());
+        png_decoder.probe()
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        let count = match self.read_header() {
+            Ok(c) => c,
+            Err(e) => {
+                log_error!("Error reading ICO header: {}", e);
+                return Err(e);
+            }
+        };
+
+        if count == 0 {
+            return Err(VexelError::missing(self.image_format(), "ICO file contains no images"));
+        }
+
+        if let Err(e) = self.read_entries(count) {
+            DecodeWarning::recovered_from(&e, format!("Error reading ICO entries: {}", e)).report();
+        }
+
+        if let Err(e) = self.detect_image_formats() {
+            DecodeWarning::recovered_from(&e, format!("Error detecting ICO image formats: {}", e)).report();
+        }
+
+        let Some(largest) = self.entries.iter().max_by_key(|e| e.width * e.height).cloned() else {
+            return Err(VexelError::missing(self.image_format(), "ICO file contains no valid entries"));
+        };
+
+        let entries = self.entries.clone();
+        let mut pixel_format = None;
+        let mut frame_count = 0;
+
+        for entry in &entries {
+            if let Err(e) = self.limits.reserve_buffer(entry.width, entry.height, 4) {
+                log_warn!("ICO entry {}x{} exceeds limits, skipping: {}", entry.width, entry.height, e);
+                continue;
+            }
+
+            let entry_format = match entry.image_format {
+                IcoImageFormat::Png => match self.probe_png_entry(entry) {
+                    Ok(header) => header.pixel_format,
+                    Err(e) => {
+                        let message = format!("Error reading PNG frame header in ICO: {}", e);
+                        DecodeWarning::recovered_from(&e, message).report();
+                        continue;
+                    }
+                },
+                IcoImageFormat::Bmp => PixelFormat::RGBA8,
+            };
+
+            pixel_format.get_or_insert(entry_format);
+            frame_count += 1;
+        }
+
+        let Some(pixel_format) = pixel_format else {
+            return Err(VexelError::missing(self.image_format(), "Failed to decode any frames from ICO file"));
+        };
+
+        Ok(ImageHeader {
+            width: largest.width,
+            height: largest.height,
+            pixel_format,
+            frame_count,
+            is_animated: false,
+        })
+    }
+
+    /// Reads the directory, so entries can be decoded one at a time with [`next_frame`](Self::next_frame).
+    pub fn start_frames(&mut self) -> VexelResult<()> {
         let count = match self.read_header() {
             Ok(c) => c,
             Err(e) => {
@@ -216,27 +309,29 @@ impl<R: Read + Seek> IcoDecoder<R> {
         };
 
         if count == 0 {
-            return Err(VexelError::Custom("ICO file contains no images".to_string()));
+            return Err(VexelError::missing(self.image_format(), "ICO file contains no images"));
         }
 
+        self.limits.check_frames(count as usize)?;
+
         match self.read_entries(count) {
             Ok(_) => {}
             Err(e) => {
-                log_error!("Error reading ICO entries: {}", e);
+                DecodeWarning::recovered_from(&e, format!("Error reading ICO entries: {}", e)).report();
             }
         }
 
         match self.detect_image_formats() {
             Ok(_) => {}
             Err(e) => {
-                log_warn!("Error detecting ICO image formats: {}", e);
+                DecodeWarning::recovered_from(&e, format!("Error detecting ICO image formats: {}", e)).report();
             }
         }
 
         self.push_entry_and_image_data_sections();
 
         if self.entries.is_empty() {
-            return Err(VexelError::Custom("ICO file contains no valid entries".to_string()));
+            return Err(VexelError::missing(self.image_format(), "ICO file contains no valid entries"));
         }
 
         let largest = self
@@ -249,36 +344,94 @@ impl<R: Read + Seek> IcoDecoder<R> {
         self.width = largest.width;
         self.height = largest.height;
 
+        Ok(())
+    }
+
+    /// Decodes a single entry. Entries that fail to decode are logged and skipped.
+    fn decode_entry(&mut self, entry: &IconDirEntry) -> Option<ImageFrame> {
+        match entry.image_format {
+            IcoImageFormat::Png => match self.decode_png_frame(entry) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    DecodeWarning::recovered_from(&e, format!("Error decoding PNG frame in ICO: {}", e)).report();
+                    None
+                }
+            },
+            IcoImageFormat::Bmp => match self.decode_bmp_frame(entry) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    DecodeWarning::recovered_from(&e, format!("Error decoding BMP frame in ICO: {}", e)).report();
+                    None
+                }
+            },
+        }
+    }
+
+    /// Decodes the next entry. The memory reserved for it is released once it is handed out.
+    pub fn next_frame(&mut self) -> VexelResult<Option<ImageFrame>> {
+        while let Some(entry) = self.entries.get(self.next_entry).cloned() {
+            self.next_entry += 1;
+
+            if let Err(e) = self.limits.reserve_buffer(entry.width, entry.height, 4) {
+                log_warn!("ICO entry {}x{} exceeds limits, skipping: {}", entry.width, entry.height, e);
+                continue;
+            }
+
+            let frame = self.decode_entry(&entry);
+            self.limits.free_buffer(entry.width, entry.height, 4);
+
+            if frame.is_some() {
+                self.frames_read += 1;
+                return Ok(frame);
+            }
+        }
+
+        if self.frames_read == 0 {
+            return Err(VexelError::missing(self.image_format(), "Failed to decode any frames from ICO file"));
+        }
+
+        Ok(None)
+    }
+
+    /// Skips the next entry without decoding it. Entries that exceed the limits are left out like
+    /// in [`next_frame`](Self::next_frame), but an entry that would fail to decode still counts.
+    pub fn skip_frame(&mut self) -> VexelResult<bool> {
+        while let Some(entry) = self.entries.get(self.next_entry).cloned() {
+            self.next_entry += 1;
+
+            if let Err(e) = self.limits.check_dimensions(entry.width, entry.height) {
+                log_warn!("ICO entry {}x{} exceeds limits, skipping: {}", entry.width, entry.height, e);
+                continue;
+            }
+
+            self.frames_read += 1;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    pub fn decode(&mut self) -> VexelResult<Image> {
+        self.start_frames()?;
+
         let entries = self.entries.clone();
         let mut frames = Vec::with_capacity(entries.len());
 
-        for entry in &entries {
+        for (index, entry) in entries.iter().enumerate() {
             if let Err(e) = self.limits.reserve_buffer(entry.width, entry.height, 4) {
                 log_warn!("ICO entry {}x{} exceeds limits, skipping: {}", entry.width, entry.height, e);
                 continue;
             }
 
-            let frame = match entry.image_format {
-                IcoImageFormat::Png => match self.decode_png_frame(entry) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        log_warn!("Error decoding PNG frame in ICO: {}", e);
-                        continue;
-                    }
-                },
-                IcoImageFormat::Bmp => match self.decode_bmp_frame(entry) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        log_warn!("Error decoding BMP frame in ICO: {}", e);
-                        continue;
-                    }
-                },
-            };
-            frames.push(frame);
+            if let Some(frame) = self.decode_entry(entry) {
+                frames.push(frame);
+            }
+
+            progress::frames(index + 1, entries.len())?;
         }
 
         if frames.is_empty() {
-            return Err(VexelError::Custom("Failed to decode any frames from ICO file".to_string()));
+            return Err(VexelError::missing(self.image_format(), "Failed to decode any frames from ICO file"));
         }
 
         let pixel_format = frames[0].pixel_format();
@@ -2,8 +2,10 @@ use crate::bitreader::BitReader;
 use crate::decoders::jbig1::arithmetic::ArithDecoder;
 use crate::decoders::jbig1::types::{self, *};
 use crate::utils::error::VexelResult;
+use crate::utils::progress;
+use crate::utils::report::{DecodeWarning, WarningKind};
 use crate::utils::info::Jbig1Info;
-use crate::{Image, Limits, PixelData, log_warn};
+use crate::{Image, ImageHeader, Limits, PixelData, PixelFormat, log_warn};
 use std::io::{Read, Seek};
 
 fn ceil_half(x: u32, n: u32) -> u32 {
@@ -105,6 +107,10 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
         }
     }
 
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn read_bih(&mut self) -> VexelResult<()> {
         let bih_offset = self.reader.stream_position().unwrap_or(0);
         let mut bih = [0u8; 20];
@@ -138,32 +144,46 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
         });
 
         if self.dl > self.d {
-            log_warn!("JBIG1: DL ({}) > D ({}), clamping DL to D", self.dl, self.d);
+            let message = format!("JBIG1: DL ({}) > D ({}), clamping DL to D", self.dl, self.d);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(0).report();
             self.dl = self.d;
         }
 
         if self.planes == 0 {
-            log_warn!("JBIG1: planes=0, defaulting to 1");
+            DecodeWarning::new(WarningKind::CorruptData, "JBIG1: planes=0, defaulting to 1").report();
             self.planes = 1;
         }
 
         if self.xd == 0 || self.yd == 0 {
-            log_warn!("JBIG1: zero dimensions ({}x{})", self.xd, self.yd);
+            let message = format!("JBIG1: zero dimensions ({}x{})", self.xd, self.yd);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
         }
 
         self.limits.reserve_buffer(self.xd, self.yd, 1)?;
 
         if self.l0 == 0 {
-            log_warn!("JBIG1: L0=0, defaulting to full image height at layer 0");
+            DecodeWarning::new(WarningKind::CorruptData, "JBIG1: L0=0, defaulting to full image height at layer 0")
+                .report();
             self.l0 = ceil_half(self.yd, self.d as u32);
             if self.l0 == 0 {
                 self.l0 = 1;
             }
         }
 
+        // A stripe of this height already spans the whole image in every layer, anything larger
+        // only overflows the stripe arithmetic
+        let max_l0 = ceil_half(self.yd, self.d as u32).max(1);
+        if self.l0 > max_l0 {
+            let message = format!("JBIG1: L0 ({}) exceeds the image height, clamping to {}", self.l0, max_l0);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(12).report();
+            self.l0 = max_l0;
+        }
+
         let num_layers = (self.d - self.dl + 1) as usize;
         let planes = self.planes as usize;
 
+        self.limits
+            .reserve_usize(planes * num_layers * std::mem::size_of::<ArithDecoder>())?;
         self.ar_decoders = (0..planes).map(|_| (0..num_layers).map(|_| ArithDecoder::new()).collect()).collect();
         self.tx = vec![vec![0i32; num_layers]; planes];
         self.ty = vec![vec![0i32; num_layers]; planes];
@@ -187,17 +207,30 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
         Ok(())
     }
 
-    fn allocate_layer_buffers(&mut self) {
+    /// Allocates the buffers of the two highest resolution layers, which alternate between the
+    /// two slots. Buffers from an earlier allocation are released from the budget first.
+    fn allocate_layer_buffers(&mut self) -> VexelResult<()> {
         let planes = self.planes as usize;
+        let mut slot_sizes = [0usize; 2];
         for layer in self.dl..=self.d {
             let hx = ceil_half(self.xd, (self.d - layer) as u32);
             let hy = ceil_half(self.yd, (self.d - layer) as u32);
             let hbpl = ((hx + 7) / 8) as usize;
-            let total = hbpl * hy as usize;
+            slot_sizes[layer as usize & 1] = hbpl.saturating_mul(hy as usize);
+        }
+
+        let previous = self.lhp.iter().flatten().map(Vec::len).sum();
+        self.limits.free_usize(previous);
+        self.limits
+            .reserve_usize((slot_sizes[0].saturating_add(slot_sizes[1])).saturating_mul(planes))?;
+
+        for (slot, &size) in slot_sizes.iter().enumerate() {
             for plane in 0..planes {
-                self.lhp[layer as usize & 1][plane] = vec![0u8; total];
+                self.lhp[slot][plane] = vec![0u8; size];
             }
         }
+
+        Ok(())
     }
 
     fn read_all_data(&mut self) -> VexelResult<Vec<u8>> {
@@ -217,6 +250,10 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
         let mut at_moves: Vec<AtMove> = Vec::new();
         let mut aborted = false;
 
+        // Every plane and resolution layer has the same number of stripes
+        let stripe_count = self.stripes_for_layer(self.dl) as u64 * (self.d.saturating_sub(self.dl) as u64 + 1) * self.planes as u64;
+        let mut stripes_done = 0u64;
+
         while pos < data.len() {
             if data[pos] != MARKER_ESC {
                 pos += 1;
@@ -279,6 +316,9 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                     }
 
                     self.advance_stripe();
+                    stripes_done += 1;
+                    progress::rows_share(0, stripes_done, stripe_count, self.yd)?;
+
                     self.pseudo = true;
                     self.current_line = 0;
                     self.current_x = 0;
@@ -295,7 +335,9 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                 }
                 MARKER_ATMOVE => {
                     if pos + 7 >= data.len() {
-                        log_warn!("JBIG1: truncated ATMOVE marker");
+                        DecodeWarning::new(WarningKind::TruncatedData, "JBIG1: truncated ATMOVE marker")
+                            .at_offset(self.data_stream_offset + pos as u64)
+                            .report();
                         break;
                     }
                     let line = u32::from_be_bytes([data[pos + 2], data[pos + 3], data[pos + 4], data[pos + 5]]);
@@ -310,7 +352,9 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                 }
                 MARKER_NEWLEN => {
                     if pos + 5 >= data.len() {
-                        log_warn!("JBIG1: truncated NEWLEN marker");
+                        DecodeWarning::new(WarningKind::TruncatedData, "JBIG1: truncated NEWLEN marker")
+                            .at_offset(self.data_stream_offset + pos as u64)
+                            .report();
                         break;
                     }
                     let new_yd = u32::from_be_bytes([data[pos + 2], data[pos + 3], data[pos + 4], data[pos + 5]]);
@@ -321,13 +365,15 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                     if self.options & OPT_VLENGTH != 0 {
                         log_warn!("JBIG1: NEWLEN updating height from {} to {}", self.yd, new_yd);
                         self.yd = new_yd;
-                        self.allocate_layer_buffers();
+                        self.allocate_layer_buffers()?;
                     }
                     pos += 6;
                 }
                 MARKER_COMMENT => {
                     if pos + 5 >= data.len() {
-                        log_warn!("JBIG1: truncated COMMENT marker");
+                        DecodeWarning::new(WarningKind::TruncatedData, "JBIG1: truncated COMMENT marker")
+                            .at_offset(self.data_stream_offset + pos as u64)
+                            .report();
                         break;
                     }
                     let comment_len = u32::from_be_bytes([data[pos + 2], data[pos + 3], data[pos + 4], data[pos + 5]]) as usize;
@@ -346,7 +392,9 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                     sde_start = pos;
                 }
                 MARKER_ABORT => {
-                    log_warn!("JBIG1: ABORT marker encountered, stopping decode");
+                    DecodeWarning::new(WarningKind::TruncatedData, "JBIG1: ABORT marker encountered, stopping decode")
+                        .at_offset(self.data_stream_offset + pos as u64)
+                        .report();
                     self.sections.push(Jbig1SectionInfo {
                         start_offset: self.data_stream_offset + pos as u64,
                         data: Jbig1SectionData::Abort,
@@ -355,7 +403,9 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                     break;
                 }
                 _ => {
-                    log_warn!("JBIG1: unknown marker 0xFF {:02X}, skipping", marker);
+                    let message = format!("JBIG1: unknown marker 0xFF {:02X}, skipping", marker);
+                    let offset = self.data_stream_offset + pos as u64;
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(offset).report();
                     self.sections.push(Jbig1SectionInfo {
                         start_offset: self.data_stream_offset + pos as u64,
                         data: Jbig1SectionData::Unknown(Jbig1UnknownMarkerData { marker }),
@@ -1081,7 +1131,8 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
             let num_planes = self.planes as usize;
 
             if num_planes > 8 {
-                log_warn!("JBIG1: {} planes > 8, decoding plane 0 only", num_planes);
+                let message = format!("JBIG1: {} planes > 8, decoding plane 0 only", num_planes);
+                DecodeWarning::new(WarningKind::Unsupported, message).report();
                 return self.build_single_plane_image(0, layer, hx, hy, hbpl);
             }
 
@@ -1135,6 +1186,25 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
         Ok(Image::from_pixels(hx, hy, pixel_data))
     }
 
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        self.read_bih()?;
+
+        // Multi-plane images are packed into gray levels; more than 8 planes fall back to plane 0
+        let pixel_format = if self.planes == 1 || self.planes > 8 {
+            PixelFormat::L1
+        } else {
+            PixelFormat::L8
+        };
+
+        Ok(ImageHeader {
+            width: self.xd,
+            height: self.yd,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
     pub fn decode(&mut self) -> VexelResult<Image> {
         self.read_bih()?;
 
@@ -1154,12 +1224,13 @@ impl<R: Read + Seek> Jbig1Decoder<R> {
                     self.dppriv = Some(internal);
                 }
                 Err(_) => {
-                    log_warn!("JBIG1: failed to read DPTABLE, using default prediction");
+                    let message = "JBIG1: failed to read DPTABLE, using default prediction";
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                 }
             }
         }
 
-        self.allocate_layer_buffers();
+        self.allocate_layer_buffers()?;
 
         self.data_stream_offset = self.reader.stream_position().unwrap_or(0);
         let data = self.read_all_data()?;
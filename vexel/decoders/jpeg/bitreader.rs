@@ -3,12 +3,41 @@ pub struct JpegBitReader<'a> {
     pos: usize,
     buf: u64,
     bits: u32,
+    read_past_end: bool,
+}
+
+/// Where a [`JpegBitReader`] stopped, to continue once more data has been appended.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JpegBitPosition {
+    pos: usize,
+    buf: u64,
+    bits: u32,
 }
 
 impl<'a> JpegBitReader<'a> {
     #[inline(always)]
     pub fn new(data: &'a [u8]) -> Self {
-        Self { data, pos: 0, buf: 0, bits: 0 }
+        Self::resume(data, JpegBitPosition::default())
+    }
+
+    /// Continues reading `data` at a position returned by [`position`](Self::position) for a
+    /// prefix of it.
+    pub fn resume(data: &'a [u8], position: JpegBitPosition) -> Self {
+        Self {
+            data,
+            pos: position.pos,
+            buf: position.buf,
+            bits: position.bits,
+            read_past_end: false,
+        }
+    }
+
+    pub fn position(&self) -> JpegBitPosition {
+        JpegBitPosition {
+            pos: self.pos,
+            buf: self.buf,
+            bits: self.bits,
+        }
     }
 
     #[inline(always)]
@@ -45,12 +74,25 @@ impl<'a> JpegBitReader<'a> {
             self.refill();
             if self.bits < n {
                 self.bits = n;
+                self.read_past_end = true;
             }
         }
         self.bits -= n;
         ((self.buf >> self.bits) & ((1u64 << n) - 1)) as u32
     }
 
+    /// Returns `true` once all data has been read. Reads past the end return zeros.
+    #[inline(always)]
+    pub fn is_exhausted(&self) -> bool {
+        self.pos >= self.data.len() && self.bits == 0
+    }
+
+    /// Returns `true` if zeros were returned for reads past the end of the data, or a restart
+    /// marker was not found.
+    pub fn read_past_end(&self) -> bool {
+        self.read_past_end
+    }
+
     #[inline(always)]
     pub fn clear_buffer(&mut self) {
         let whole_bytes = (self.bits / 8) as usize;
@@ -65,5 +107,8 @@ impl<'a> JpegBitReader<'a> {
             }
             self.pos += 1;
         }
+
+        // The restart marker hasn't been read, so the data has ended for this interval
+        self.read_past_end = true;
     }
 }
@@ -1,20 +1,29 @@
 use crate::bitreader::BitReader;
 use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::color_transform::{CmykTransform, ColorTarget};
 use crate::utils::exif::ExifReader;
+use crate::utils::image::Region;
 use crate::utils::info::JpegInfo;
+use crate::utils::metadata::{ImageMetadata, Resolution, XMP_SIGNATURE, assemble_icc_chunks, jpeg_resolution};
+use crate::utils::progress;
+use crate::utils::report::{DecodeWarning, WarningKind};
+#[cfg(feature = "rayon")]
+use crate::utils::report;
 use crate::utils::marker::Marker;
-use crate::{Image, ImageFrame, Limits, PixelData, PixelFormat, log_debug, log_warn};
-use crate::decoders::jpeg::idct::dequantize_and_idct;
-use crate::decoders::jpeg::bitreader::JpegBitReader;
+use crate::{Image, ImageFormat, ImageFrame, ImageHeader, Limits, PixelData, PixelFormat, log_debug, log_warn};
+use crate::decoders::jpeg::idct::{dequantize_and_idct, dequantize_and_idct_reduced};
+use crate::decoders::jpeg::bitreader::{JpegBitPosition, JpegBitReader};
 use std::fmt::Debug;
-use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom};
 use crate::decoders::jpeg::markers::{JpegMarker, JPEG_MARKERS};
-use crate::decoders::jpeg::types::{APP14AdobeData, APP2Data, ArithmeticCodingTable, ArithmeticCodingValue, ColorComponentInfo, DACData, DHTData, DQTData, HuffmanTable, IccProfileSequenceInfo, JFIFData, JFIFHeader, JpegCodingMethod, JpegMode, JpegSegmentData, JpegSegmentInfo, Predictor, QuantizationTable, SOFData, SOSData, ScanComponent, ScanData, DEFAULT_QUANTIZATION_TABLE, ZIGZAG_MAP};
+use crate::decoders::jpeg::types::{APP14AdobeData, APP2Data, ArithmeticCodingTable, ArithmeticCodingValue, ColorComponentInfo, DACData, DHTData, DQTData, HuffmanTable, IccProfileSequenceInfo, JFIFData, JFIFHeader, JpegCodingMethod, JpegMode, JpegScale, JpegSegmentData, JpegSegmentInfo, Predictor, QuantizationTable, SOFData, SOSData, ScanComponent, ScanData, DEFAULT_QUANTIZATION_TABLE, ZIGZAG_MAP};
 
 #[derive(Debug, Clone)]
 struct ComponentPlane {
     data: Vec<i32>,
     blocks_per_line: u32,
+    /// Number of samples per block side after the IDCT, smaller than 8 for scaled decoding
+    block_size: u32,
 }
 
 impl ComponentPlane {
@@ -25,6 +34,7 @@ impl ComponentPlane {
         Self {
             blocks_per_line,
             data: vec![0; (blocks_per_line * block_lines * 64) as usize],
+            block_size: 8,
         }
     }
 
@@ -38,10 +48,18 @@ impl ComponentPlane {
         }
     }
 
+    /// Sets the coefficients of `count` rows of blocks, starting at `first`, back to zero.
+    fn clear_block_rows(&mut self, first: u32, count: u32) {
+        let row_length = self.blocks_per_line as usize * 64;
+        let start = (first as usize * row_length).min(self.data.len());
+        let end = (start + count as usize * row_length).min(self.data.len());
+        self.data[start..end].fill(0);
+    }
+
     fn deinterleave(&self, sw: u32, sh: u32) -> Vec<i32> {
         use crate::decoders::jpeg::upsample as up;
         let mut out = vec![0i32; (sw * sh) as usize];
-        up::deinterleave_blocks(&self.data, self.blocks_per_line, sw, sh, &mut out);
+        up::deinterleave_reduced_blocks(&self.data, self.blocks_per_line, self.block_size, sw, sh, &mut out);
         out
     }
 
@@ -223,7 +241,7 @@ impl<'a> ArithmeticDecoder<'a> {
                     }
                     m <<= 1;
                     if m == 0x8000 {
-                        log_warn!("Arithmetic DC magnitude overflow");
+                        DecodeWarning::new(WarningKind::CorruptData, "Arithmetic DC magnitude overflow").report();
                         self.error = true;
                         return last_dc_val[comp_idx];
                     }
@@ -323,7 +341,7 @@ impl<'a> ArithmeticDecoder<'a> {
                 if self.arith_decode(&mut ac_stats[ac_tbl][cur_base + 1]) != 0 { break; }
                 k += 1;
                 if k > se {
-                    log_warn!("Arithmetic AC spectral overflow");
+                    DecodeWarning::new(WarningKind::CorruptData, "Arithmetic AC spectral overflow").report();
                     self.error = true;
                     return;
                 }
@@ -350,7 +368,7 @@ impl<'a> ArithmeticDecoder<'a> {
                         if self.arith_decode(&mut ac_stats[ac_tbl][idx]) == 0 { break; }
                         m <<= 1;
                         if m == 0x8000 {
-                            log_warn!("Arithmetic AC magnitude overflow");
+                            DecodeWarning::new(WarningKind::CorruptData, "Arithmetic AC magnitude overflow").report();
                             self.error = true;
                             return;
                         }
@@ -480,7 +498,7 @@ impl<'a> ArithmeticDecoder<'a> {
                 if self.arith_decode(&mut ac_stats[ac_tbl][cur_base + 1]) != 0 { break; }
                 k += 1;
                 if k > 63 {
-                    log_warn!("Arithmetic AC sequential spectral overflow");
+                    DecodeWarning::new(WarningKind::CorruptData, "Arithmetic AC sequential spectral overflow").report();
                     self.error = true;
                     return;
                 }
@@ -507,7 +525,8 @@ impl<'a> ArithmeticDecoder<'a> {
                         if self.arith_decode(&mut ac_stats[ac_tbl][idx]) == 0 { break; }
                         m <<= 1;
                         if m == 0x8000 {
-                            log_warn!("Arithmetic AC magnitude overflow (sequential)");
+                            let message = "Arithmetic AC magnitude overflow (sequential)";
+                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                             self.error = true;
                             return;
                         }
@@ -580,7 +599,7 @@ impl<'a> ArithmeticDecoder<'a> {
                 m <<= 1;
                 i += 1;
                 if i >= 15 {
-                    log_warn!("Arithmetic lossless magnitude overflow");
+                    DecodeWarning::new(WarningKind::CorruptData, "Arithmetic lossless magnitude overflow").report();
                     self.error = true;
                     return 0;
                 }
@@ -614,6 +633,62 @@ enum JpegColorspace {
     Unknown,
 }
 
+struct BaselineCompInfo {
+    h_samp: u8,
+    v_samp: u8,
+    dc_table: HuffmanTable,
+    ac_table: HuffmanTable,
+}
+
+/// MCU grid of a sequential Huffman scan and the tables of each component.
+struct HuffmanScanLayout {
+    mcu_width: u32,
+    mcu_height: u32,
+    max_v_samp: u32,
+    components: Vec<BaselineCompInfo>,
+    is_differential: bool,
+}
+
+impl HuffmanScanLayout {
+    fn mcu_pixel_height(&self) -> u32 {
+        8 * self.max_v_samp
+    }
+}
+
+/// Entropy decoding state carried from one MCU row of a sequential Huffman scan to the next.
+#[derive(Clone)]
+struct HuffmanScanState {
+    previous_dc: Vec<i32>,
+    restart_counter: u32,
+}
+
+impl HuffmanScanState {
+    fn new(components: usize, restart_interval: u16) -> Self {
+        Self {
+            previous_dc: vec![0; components],
+            restart_counter: restart_interval as u32,
+        }
+    }
+}
+
+/// A baseline scan that is decoded one MCU row at a time while its data is being received,
+/// see [`JpegDecoder::start_incremental_scan`].
+pub(crate) struct IncrementalScan {
+    layout: HuffmanScanLayout,
+    state: HuffmanScanState,
+    planes: Vec<ComponentPlane>,
+    position: JpegBitPosition,
+    decoded_mcu_rows: u32,
+    converted_rows: u32,
+}
+
+impl IncrementalScan {
+    /// Returns the number of image rows converted so far.
+    pub(crate) fn converted_rows(&self) -> u32 {
+        self.converted_rows
+    }
+}
+
 struct HierarchicalFrame {
     width: u32,
     height: u32,
@@ -631,6 +706,8 @@ pub struct JpegDecoder<R: Read + Seek> {
     limits: Limits,
     jfif_header: Option<JFIFHeader>,
     comments: Vec<String>,
+    metadata: ImageMetadata,
+    icc_chunks: Vec<(u8, Vec<u8>)>,
     mode: JpegMode,
     coding_method: JpegCodingMethod,
     quantization_tables: Vec<QuantizationTable>,
@@ -657,6 +734,10 @@ pub struct JpegDecoder<R: Read + Seek> {
     pending_expand_h: bool,
     pending_expand_v: bool,
     hierarchical_frames: Vec<HierarchicalFrame>,
+    /// Set by [`decode_region`](Self::decode_region), only the MCUs around it are transformed
+    region: Option<Region>,
+    scale: JpegScale,
+    color_target: ColorTarget,
 }
 
 impl<R: Read + Seek> JpegDecoder<R> {
@@ -668,6 +749,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
             limits: Limits::default(),
             comments: Vec::new(),
             jfif_header: None,
+            metadata: ImageMetadata::default(),
+            icc_chunks: Vec::new(),
             mode: JpegMode::Baseline,
             coding_method: JpegCodingMethod::Huffman,
             mcu_width: 0,
@@ -694,6 +777,9 @@ impl<R: Read + Seek> JpegDecoder<R> {
             pending_expand_h: false,
             pending_expand_v: false,
             hierarchical_frames: Vec::new(),
+            region: None,
+            scale: JpegScale::Full,
+            color_target: ColorTarget::Original,
         }
     }
 
@@ -701,6 +787,16 @@ impl<R: Read + Seek> JpegDecoder<R> {
         self.limits = limits;
     }
 
+    pub fn set_scale(&mut self, scale: JpegScale) {
+        self.scale = scale;
+    }
+
+    /// CMYK and YCCK images are converted through their ICC profile when a target other than
+    /// [`ColorTarget::Original`] is set, otherwise with the plain CMYK formula.
+    pub fn set_color_target(&mut self, target: ColorTarget) {
+        self.color_target = target;
+    }
+
 
     pub fn get_info(&self) -> JpegInfo {
         JpegInfo {
@@ -708,6 +804,26 @@ impl<R: Read + Seek> JpegDecoder<R> {
         }
     }
 
+    pub fn metadata(&self) -> ImageMetadata {
+        let mut metadata = self.metadata.clone();
+
+        if let Some(data) = assemble_icc_chunks(&self.icc_chunks) {
+            metadata.set_icc(data);
+        }
+
+        let jfif_resolution = self
+            .jfif_header
+            .as_ref()
+            .and_then(|jfif| Resolution::from_jfif(jfif.density_units, jfif.x_density, jfif.y_density));
+        metadata.resolution = jpeg_resolution(jfif_resolution, metadata.exif.as_ref());
+
+        metadata
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn skip_unknown_marker_segment(&mut self, marker: &str, segment_start: u64) -> VexelResult<()> {
         let length = self.reader.read_u16()?;
 
@@ -745,6 +861,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
     fn read_com(&mut self, segment_start: u64) -> VexelResult<()> {
         let length = self.reader.read_u16()?;
+        self.limits.reserve_metadata(length.saturating_sub(2) as u64)?;
 
         let mut comment_bytes = Vec::new();
         for _ in 0..length - 2 {
@@ -753,6 +870,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
         let text = String::from_utf8_lossy(&comment_bytes).to_string();
         self.comments.push(text.clone());
+        self.metadata.push_text("Comment", text.clone());
 
         self.record_segment(segment_start, "COM", JpegSegmentData::COM { text });
 
@@ -834,11 +952,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
         let expected_len = 16 + thumbnail_width as u16 * thumbnail_height as u16 * 3;
         if length != expected_len {
-            log_warn!(
-                "Invalid JFIF segment length, expected {}, got {}",
-                expected_len,
-                length
-            );
+            let message = format!("Invalid JFIF segment length, expected {}, got {}", expected_len, length);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
         }
 
         self.record_segment(segment_start, "APP0", JpegSegmentData::APP0(JFIFData {
@@ -865,6 +980,10 @@ impl<R: Read + Seek> JpegDecoder<R> {
         } else {
             None
         };
+        if exif.is_some() || payload.starts_with(XMP_SIGNATURE) {
+            self.limits.reserve_metadata(payload.len() as u64)?;
+        }
+        self.metadata.read_app1(&payload, exif.as_ref());
 
         self.record_segment(segment_start, "APP1", JpegSegmentData::APP1 { length, exif });
 
@@ -882,6 +1001,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
             let chunk_sequence = payload[null_pos + 1];
             let total_chunks = payload[null_pos + 2];
             let profile_data_length = (payload.len().saturating_sub(null_pos + 3)) as u32;
+            self.limits.reserve_metadata(profile_data_length as u64)?;
+            self.icc_chunks.push((chunk_sequence, payload[null_pos + 3..].to_vec()));
             Some(IccProfileSequenceInfo { chunk_sequence, total_chunks, profile_data_length })
         } else {
             None
@@ -996,11 +1117,13 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
         if self.mode == JpegMode::Lossless {
             if self.precision < 2 || self.precision > 16 {
-                log_warn!("Invalid precision for lossless jpeg mode: {}, clamping", self.precision);
+                let message = format!("Invalid precision for lossless jpeg mode: {}, clamping", self.precision);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
                 self.precision = self.precision.clamp(2, 16);
             }
         } else if self.precision < 8 || self.precision > 16 {
-            log_warn!("Invalid precision: {}, clamping to 8", self.precision);
+            let message = format!("Invalid precision: {}, clamping to 8", self.precision);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
             self.precision = 8;
         }
 
@@ -1008,10 +1131,10 @@ impl<R: Read + Seek> JpegDecoder<R> {
         self.width = self.reader.read_u16()? as u32;
 
         if self.width == 0 {
-            return Err(VexelError::from(Error::new(
-                ErrorKind::InvalidData,
-                "Invalid image dimensions",
-            )));
+            return Err(VexelError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
         }
 
         if self.height == 0 {
@@ -1027,10 +1150,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
         self.component_count = self.reader.read_u8()?;
 
         if self.component_count > 4 || self.component_count == 0 {
-            log_warn!(
-                "Invalid number of components in SOF marker: {}, assuming 3",
-                self.component_count
-            );
+            let message = format!("Invalid number of components in SOF marker: {}, assuming 3", self.component_count);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
             self.component_count = 3;
         }
 
@@ -1059,11 +1180,12 @@ impl<R: Read + Seek> JpegDecoder<R> {
         }
 
         if length != 8 + 3 * self.component_count as u16 {
-            log_warn!(
+            let message = format!(
                 "Invalid SOF marker length, expected {}, got {}",
                 8 + 3 * self.component_count,
-                length
+                length,
             );
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
         }
 
         self.record_segment(segment_start, sof_marker, JpegSegmentData::SOF(SOFData {
@@ -1093,13 +1215,14 @@ impl<R: Read + Seek> JpegDecoder<R> {
         let length = self.reader.read_u16()?;
 
         if length != 4 {
-            log_warn!("Invalid DNL segment length: {}, expected 4", length);
+            let message = format!("Invalid DNL segment length: {}, expected 4", length);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
         }
 
         let number_of_lines = self.reader.read_u16()?;
 
         if number_of_lines == 0 {
-            log_warn!("DNL specifies 0 lines, ignoring");
+            DecodeWarning::new(WarningKind::CorruptData, "DNL specifies 0 lines, ignoring").at_offset(segment_start).report();
         } else {
             self.height = number_of_lines as u32;
             self.mcu_height = (self.height + 7) / 8;
@@ -1190,7 +1313,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
             }
 
             if total_symbols > 256 {
-                log_warn!("Too many symbols in Huffman table: {}", total_symbols);
+                let message = format!("Too many symbols in Huffman table: {}", total_symbols);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
                 total_symbols = 256;
             }
 
@@ -1215,13 +1339,15 @@ impl<R: Read + Seek> JpegDecoder<R> {
             let mut code = 0;
             for i in 0..16 {
                 if huffman_table.offsets.len() <= i + 1 {
-                    log_warn!("Offset index {} is out of bounds in Huffman table", i);
+                    let message = format!("Offset index {} is out of bounds in Huffman table", i);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
                     break;
                 }
 
                 for k in huffman_table.offsets[i]..huffman_table.offsets[i + 1] {
                     if huffman_table.codes.len() <= k as usize {
-                        log_warn!("Code index {} is out of bounds in Huffman table", k);
+                        let message = format!("Code index {} is out of bounds in Huffman table", k);
+                        DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
                         break;
                     }
 
@@ -1261,7 +1387,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     }
                 }
                 _ => {
-                    log_warn!("Invalid Huffman table class: {}, ignoring the table", class);
+                    let message = format!("Invalid Huffman table class: {}, ignoring the table", class);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
                 }
             }
         }
@@ -1306,10 +1433,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                 0 => dc_tables.push(table),
                 1 => ac_tables.push(table),
                 _ => {
-                    log_warn!(
-                        "Invalid arithmetic coding table class: {}, ignoring the table",
-                        table_class
-                    );
+                    let message = format!("Invalid arithmetic coding table class: {}, ignoring the table", table_class);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
                 }
             }
 
@@ -1328,6 +1453,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
         Ok(())
     }
 
+    fn report_scan_eof(&mut self, message: &str) {
+        let offset = self.reader.stream_position().unwrap_or(0);
+        DecodeWarning::new(WarningKind::TruncatedData, message).at_offset(offset).report();
+    }
+
     fn read_start_of_scan(&mut self, segment_start: u64) -> VexelResult<()> {
         if self.coding_method == JpegCodingMethod::Huffman {
             self.ensure_default_huffman_tables();
@@ -1361,15 +1491,16 @@ impl<R: Read + Seek> JpegDecoder<R> {
         let successive_low = successive_approx & 0x0F;
 
         if length != 6 + (2 * scan_component_count as u16) {
-            log_warn!(
+            let message = format!(
                 "Invalid SOS marker length, expected {}, got {}",
                 6 + (2 * scan_component_count as u16),
-                length
+                length,
             );
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(segment_start).report();
         }
 
         let mut current_byte = self.reader.read_u8().unwrap_or_else(|_| {
-            log_warn!("Unexpected EOF while reading first byte of scan data");
+            self.report_scan_eof("Unexpected EOF while reading first byte of scan data");
             0
         });
 
@@ -1382,7 +1513,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                 current_byte = match self.reader.read_u8() {
                     Ok(byte) => byte,
                     Err(_) => {
-                        log_warn!("Unexpected EOF while reading scan data, breaking");
+                        self.report_scan_eof("Unexpected EOF while reading scan data, breaking");
                         break;
                     }
                 };
@@ -1394,7 +1525,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
             let next_byte = match self.reader.read_u8() {
                 Ok(byte) => byte,
                 Err(_) => {
-                    log_warn!("Unexpected EOF while reading scan data, breaking");
+                    self.report_scan_eof("Unexpected EOF while reading scan data, breaking");
                     break;
                 }
             };
@@ -1410,7 +1541,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     current_byte = match self.reader.read_u8() {
                         Ok(byte) => byte,
                         Err(_) => {
-                            log_warn!("Unexpected EOF while reading scan data, breaking");
+                            self.report_scan_eof("Unexpected EOF while reading scan data, breaking");
                             break;
                         }
                     };
@@ -1427,7 +1558,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                         current_byte = match self.reader.read_u8() {
                             Ok(byte) => byte,
                             Err(_) => {
-                                log_warn!("Unexpected EOF while reading scan data, breaking");
+                                self.report_scan_eof("Unexpected EOF while reading scan data, breaking");
                                 break;
                             }
                         };
@@ -1627,7 +1758,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     }
                 }
             }
-            log_warn!("Invalid Huffman code: {}, replacing with 0", code);
+            let message = format!("Invalid Huffman code: {}, replacing with 0", code);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
             return 0;
         }
 
@@ -1644,7 +1776,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
             }
         }
 
-        log_warn!("Invalid Huffman code: {}, replacing with 0", code);
+        let message = format!("Invalid Huffman code: {}, replacing with 0", code);
+        DecodeWarning::new(WarningKind::CorruptData, message).report();
         0
     }
 
@@ -1660,7 +1793,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
         let length = Self::get_next_symbol(reader, dc_table);
 
         if length > 15 {
-            log_warn!("Invalid DC coefficient length (>15): {}", length);
+            let message = format!("Invalid DC coefficient length (>15): {}", length);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
             return Ok(());
         }
 
@@ -1697,7 +1831,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
             }
 
             if i + zero_count as usize >= 64 {
-                log_warn!("Sum of zero count and current index of mcu value exceeds 64");
+                let message = "Sum of zero count and current index of mcu value exceeds 64";
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 return Ok(());
             }
 
@@ -1705,7 +1840,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
             let max_coefficient_length = if self.precision > 8 { 16 } else { 10 };
             if coefficient_length > max_coefficient_length {
-                log_warn!("Invalid coefficient length: {}, replacing with 0", coefficient_length);
+                let message = format!("Invalid coefficient length: {}, replacing with 0", coefficient_length);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 coefficient_length = 0;
             }
 
@@ -1756,18 +1892,14 @@ impl<R: Read + Seek> JpegDecoder<R> {
             JpegCodingMethod::Huffman => self.decode_progressive_scans(&mut component_planes)?,
             JpegCodingMethod::Arithmetic => self.decode_progressive_scans_arithmetic(&mut component_planes)?,
         }
-        self.dequantize_and_idct_planes(&mut component_planes)?;
 
-        let mut pixel_data = self.upsample_and_convert(&component_planes)?;
-        pixel_data.correct_pixels(self.width, self.height);
-
-        Ok(Image::from_pixels(self.width, self.height, pixel_data))
+        self.planes_to_image(component_planes)
     }
 
     fn decode_progressive_scans(&mut self, planes: &mut [ComponentPlane]) -> VexelResult<()> {
         let mut previous_dc = vec![0i32; planes.len()];
 
-        for scan in &self.scans {
+        for (scan_index, scan) in self.scans.iter().enumerate() {
             let mut reader = JpegBitReader::new(scan.data.as_slice());
             let mut skips = 0;
             let restart_interval = self.restart_interval;
@@ -1812,13 +1944,15 @@ impl<R: Read + Seek> JpegDecoder<R> {
                 {
                     Some((i, c)) => (i, c),
                     None => {
-                        log_warn!("Component not found: {}", scan_comp.component_id);
+                        let message = format!("Component not found: {}", scan_comp.component_id);
+                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                         return None;
                     }
                 };
 
                 if plane_index >= planes.len() {
-                    log_warn!("Invalid plane index: {}", plane_index);
+                    let message = format!("Invalid plane index: {}", plane_index);
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                     return None;
                 }
 
@@ -1829,10 +1963,12 @@ impl<R: Read + Seek> JpegDecoder<R> {
                 let ac_table_index = scan.ac_tables.iter().position(|t| t.id == scan_comp.ac_table_selector);
 
                 if scan.start_spectral == 0 && scan.successive_high == 0 && dc_table_index.is_none() {
-                    log_warn!("DC table not found: {}", scan_comp.dc_table_selector);
+                    let message = format!("DC table not found: {}", scan_comp.dc_table_selector);
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                 }
                 if scan.end_spectral > 0 && ac_table_index.is_none() {
-                    log_warn!("AC table not found: {}", scan_comp.ac_table_selector);
+                    let message = format!("AC table not found: {}", scan_comp.ac_table_selector);
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                 }
 
                 Some(ScanCompInfo { plane_index, h_blocks, v_blocks, dc_table_index, ac_table_index })
@@ -1874,7 +2010,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                                             let dc_table = match info.dc_table_index.and_then(|i| scan.dc_tables.get(i)) {
                                                 Some(table) => table,
                                                 None => {
-                                                    log_warn!("DC table missing for block, skipping");
+                                                    let message = "DC table missing for block, skipping";
+                                                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                     continue;
                                                 }
                                             };
@@ -1882,7 +2019,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
                                             let length = Self::get_next_symbol(&mut reader, dc_table);
 
                                             if length > 15 {
-                                                log_warn!("Invalid DC coefficient length (>15): {}", length);
+                                                let message = format!(
+                                                    "Invalid DC coefficient length (>15): {}",
+                                                    length,
+                                                );
+                                                DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                 continue;
                                             }
 
@@ -1921,7 +2062,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                                             let ac_table = match info.ac_table_index.and_then(|i| scan.ac_tables.get(i)) {
                                                 Some(table) => table,
                                                 None => {
-                                                    log_warn!("AC table missing for block, skipping");
+                                                    let message = "AC table missing for block, skipping";
+                                                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                     continue;
                                                 }
                                             };
@@ -1935,10 +2077,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
                                                 if length != 0 {
                                                     if k + num_zeros as usize > 63 {
-                                                        log_warn!(
+                                                        let message = format!(
                                                             "Zero run-length exceeded spectral selection: {}",
-                                                            k + num_zeros as usize
+                                                            k + num_zeros as usize,
                                                         );
+                                                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                         break;
                                                     }
 
@@ -1949,7 +2092,12 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
                                                     let max_ac_len = if self.precision > 8 { 15 } else { 10 };
                                                     if length > max_ac_len {
-                                                        log_warn!("Invalid AC coefficient length (>{}): {}", max_ac_len, length);
+                                                        let message = format!(
+                                                            "Invalid AC coefficient length (>{}): {}",
+                                                            max_ac_len,
+                                                            length,
+                                                        );
+                                                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                         break;
                                                     }
 
@@ -1966,10 +2114,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
                                                 } else {
                                                     if num_zeros == 15 {
                                                         if k + num_zeros as usize > scan.end_spectral as usize {
-                                                            log_warn!(
+                                                            let message = format!(
                                                                 "Zero run-length exceeded spectral selection: {}",
-                                                                k + num_zeros as usize
+                                                                k + num_zeros as usize,
                                                             );
+                                                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                             break;
                                                         }
 
@@ -1996,7 +2145,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                                                 let ac_table = match info.ac_table_index.and_then(|i| scan.ac_tables.get(i)) {
                                                     Some(table) => table,
                                                     None => {
-                                                        log_warn!("AC table missing for block (refining), skipping");
+                                                        let message = "AC table missing for block (refining), skipping";
+                                                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                         continue;
                                                     }
                                                 };
@@ -2010,10 +2160,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
                                                     if length != 0 {
                                                         if length != 1 {
-                                                            log_warn!(
+                                                            let message = format!(
                                                                 "Invalid AC coefficient length (refining): {}",
-                                                                length
+                                                                length,
                                                             );
+                                                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                                                             break;
                                                         }
 
@@ -2087,7 +2238,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
                         }
                     }
                 }
+
+                progress::check_cancelled()?;
             }
+
+            self.report_scans(scan_index + 1)?;
         }
         Ok(())
     }
@@ -2105,13 +2260,17 @@ impl<R: Read + Seek> JpegDecoder<R> {
         }
 
         // TODO handle restarts
-        for _ in 0..height {
+        for row in 0..height {
             for _ in 0..width {
                 for (i, scan_component) in scan.components.iter().enumerate() {
                     let dc_table = match scan.dc_tables.iter().find(|t| t.id == scan_component.dc_table_selector) {
                         Some(table) => table,
                         None => {
-                            log_warn!("No DC table found for component {} during lossless decoding. Using default table which will most likely produce incorrect results.", i);
+                            let message = format!(
+                                "No DC table found for component {} during lossless decoding. Using default table which will most likely produce incorrect results.",
+                                i,
+                            );
+                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                             &Self::default_lossless_dc_table()
                         }
                     };
@@ -2131,7 +2290,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                         }
                         16 => 32768,
                         _ => {
-                            log_warn!("Invalid difference: {}", bits_to_read);
+                            let message = format!("Invalid difference: {}", bits_to_read);
+                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                             0
                         }
                     };
@@ -2139,6 +2299,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     differences[i].push(diff);
                 }
             }
+
+            progress::rows(0, row as u32 + 1, height as u32)?;
         }
 
         Ok(differences)
@@ -2203,6 +2365,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     db[c][x] = v;
                 }
             }
+
+            progress::rows(0, y as u32 + 1, height as u32)?;
         }
 
         Ok(differences)
@@ -2417,12 +2581,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
         // TODO there can be multiple scans in lossless mode somehow
         let scan = match self.scans.first() {
             Some(s) => s.clone(),
-            None => {
-                return Err(VexelError::from(Error::new(
-                    ErrorKind::InvalidData,
-                    "No scan data found",
-                )))
-            }
+            None => return Err(VexelError::missing(ImageFormat::Jpeg, "No scan data found")),
         };
 
         let differences = if self.coding_method == JpegCodingMethod::Arithmetic {
@@ -2442,7 +2601,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
             6 => Predictor::RaRbRc3,
             7 => Predictor::RaRb,
             _ => {
-                log_warn!("Invalid predictor selection: {}", scan.start_spectral);
+                let message = format!("Invalid predictor selection: {}", scan.start_spectral);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 Predictor::NoPrediction
             }
         };
@@ -2454,7 +2614,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
     fn decode_arithmetic_to_planes(&mut self, planes: &mut [ComponentPlane]) -> VexelResult<()> {
         if self.scans.is_empty() {
-            log_warn!("No scans found in JPEG data");
+            DecodeWarning::new(WarningKind::CorruptData, "No scans found in JPEG data").report();
             return Ok(());
         }
 
@@ -2490,6 +2650,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
         let mcu_width = (self.width + 8 * max_h_samp as u32 - 1) / (8 * max_h_samp as u32);
         let mcu_height = (self.height + 8 * max_v_samp as u32 - 1) / (8 * max_v_samp as u32);
+        let mcu_height = mcu_height.min(self.decoded_rows().div_ceil(8 * max_v_samp as u32));
 
         let mut restart_counter = self.restart_interval as u32;
 
@@ -2546,6 +2707,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     }
                 }
             }
+
+            self.report_mcu_rows(mcu_y + 1, mcu_height)?;
         }
 
         Ok(())
@@ -2557,7 +2720,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
         let mut per_scan_dc_context = vec![0usize; planes.len()];
         let mut per_scan_last_dc_val = vec![0i32; planes.len()];
 
-        for scan in &self.scans {
+        for (scan_index, scan) in self.scans.iter().enumerate() {
             let is_dc_scan = scan.start_spectral == 0;
             let is_first_scan = scan.successive_high == 0;
             let is_first_dc_scan = is_dc_scan && is_first_scan;
@@ -2717,7 +2880,11 @@ impl<R: Read + Seek> JpegDecoder<R> {
                         }
                     }
                 }
+
+                progress::check_cancelled()?;
             }
+
+            self.report_scans(scan_index + 1)?;
         }
 
         Ok(())
@@ -2726,14 +2893,46 @@ impl<R: Read + Seek> JpegDecoder<R> {
     fn decode_huffman_to_planes(&mut self, planes: &mut [ComponentPlane]) -> VexelResult<()> {
         if self.scans.len() < 1 {
             // Well, nothing to do here, how did this even happen?
-            log_warn!("No scans found in JPEG data");
+            DecodeWarning::new(WarningKind::CorruptData, "No scans found in JPEG data").report();
             return Ok(());
         }
 
-        let scan = &self.scans[0];
-        let is_non_interleaved = scan.components.len() == 1;
-        let mut reader = JpegBitReader::new(scan.data.as_slice());
-        let mut previous_dc = vec![0i32; planes.len()];
+        let layout = self.huffman_scan_layout();
+        let mut state = HuffmanScanState::new(planes.len(), self.restart_interval);
+        let mut reader = JpegBitReader::new(self.scans[0].data.as_slice());
+
+        let total_mcus = layout.mcu_width as u64 * layout.mcu_height as u64;
+        // Rows below the requested region are never used
+        let mcu_height = layout.mcu_height.min(self.decoded_rows().div_ceil(layout.mcu_pixel_height()));
+        let mut truncated = false;
+
+        for mcu_y in 0..mcu_height {
+            let exhausted_at = self.decode_huffman_mcu_row(&mut reader, &layout, &mut state, planes, mcu_y);
+
+            if let Some(mcu_x) = exhausted_at.filter(|_| !truncated) {
+                truncated = true;
+
+                let mcu_index = mcu_y as u64 * layout.mcu_width as u64 + mcu_x as u64;
+                let (width, height) = self.output_dimensions();
+                let missing = (width as u64 * height as u64) * (total_mcus - mcu_index) / total_mcus;
+
+                let message = format!("Scan data ends at MCU {} of {}", mcu_index, total_mcus);
+                DecodeWarning::new(WarningKind::TruncatedData, message)
+                    .in_frame(0)
+                    .with_synthesized_pixels(missing)
+                    .report();
+            }
+
+            self.report_mcu_rows(mcu_y + 1, mcu_height)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the MCU grid of the first scan and the Huffman tables of each component,
+    /// substituting defaults for missing tables.
+    fn huffman_scan_layout(&self) -> HuffmanScanLayout {
+        let is_non_interleaved = self.scans[0].components.len() == 1;
 
         let mut max_h_samp = if is_non_interleaved {
             1
@@ -2755,7 +2954,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
         };
 
         if max_h_samp == 0 || max_v_samp == 0 {
-            log_warn!("Invalid sampling factors: ({}, {})", max_h_samp, max_v_samp);
+            let message = format!("Invalid sampling factors: ({}, {})", max_h_samp, max_v_samp);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
             max_h_samp = 1;
             max_v_samp = 1;
         }
@@ -2766,16 +2966,10 @@ impl<R: Read + Seek> JpegDecoder<R> {
         let default_dc_table = Self::default_lossless_dc_table();
         let default_ac_table = Self::default_lossless_dc_table();
 
-        struct BaselineCompInfo {
-            h_samp: u8,
-            v_samp: u8,
-            dc_table: HuffmanTable,
-            ac_table: HuffmanTable,
-        }
-
-        let comp_infos: Vec<BaselineCompInfo> = self.components.iter().enumerate().filter_map(|(comp_idx, comp)| {
+        let components = self.components.iter().enumerate().filter_map(|(comp_idx, comp)| {
             if self.scans[0].components.len() <= comp_idx {
-                log_warn!("Component index out of bounds: {} {}", self.scans[0].components.len(), comp_idx);
+                let message = format!("Component index out of bounds: {} {}", self.scans[0].components.len(), comp_idx);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 return None;
             }
 
@@ -2783,12 +2977,20 @@ impl<R: Read + Seek> JpegDecoder<R> {
             let ac_selector = self.scans[0].components[comp_idx].ac_table_selector;
 
             let dc_table = self.scans[0].dc_tables.iter().find(|t| t.id == dc_selector).cloned().unwrap_or_else(|| {
-                log_warn!("DC table {} not found in baseline mode, substituting default, image will be corrupted.", dc_selector);
+                let message = format!(
+                    "DC table {} not found in baseline mode, substituting default, image will be corrupted.",
+                    dc_selector,
+                );
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 default_dc_table.clone()
             });
 
             let ac_table = self.scans[0].ac_tables.iter().find(|t| t.id == ac_selector).cloned().unwrap_or_else(|| {
-                log_warn!("AC table {} not found in baseline mode, substituting default, image will be corrupted.", ac_selector);
+                let message = format!(
+                    "AC table {} not found in baseline mode, substituting default, image will be corrupted.",
+                    ac_selector,
+                );
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 default_ac_table.clone()
             });
 
@@ -2798,61 +3000,98 @@ impl<R: Read + Seek> JpegDecoder<R> {
             Some(BaselineCompInfo { h_samp, v_samp, dc_table, ac_table })
         }).collect();
 
-        let mut restart_counter = self.restart_interval as u32;
+        HuffmanScanLayout {
+            mcu_width,
+            mcu_height,
+            max_v_samp: max_v_samp as u32,
+            components,
+            is_differential: matches!(self.mode, JpegMode::DifferentialSequential | JpegMode::DifferentialProgressive),
+        }
+    }
 
-        for mcu_y in 0..mcu_height {
-            for mcu_x in 0..mcu_width {
-                if self.restart_interval > 0 {
-                    if restart_counter == 0 {
-                        previous_dc.fill(0);
-                        reader.clear_buffer();
-                        restart_counter = self.restart_interval as u32;
-                    }
+    /// Decodes row `mcu_y` of MCUs into `planes`. Returns the first MCU of the row that was
+    /// reached after the scan data ran out, if any.
+    fn decode_huffman_mcu_row(
+        &self,
+        reader: &mut JpegBitReader<'_>,
+        layout: &HuffmanScanLayout,
+        state: &mut HuffmanScanState,
+        planes: &mut [ComponentPlane],
+        mcu_y: u32,
+    ) -> Option<u32> {
+        let mut exhausted_at = None;
+
+        for mcu_x in 0..layout.mcu_width {
+            if exhausted_at.is_none() && reader.is_exhausted() {
+                exhausted_at = Some(mcu_x);
+            }
 
-                    restart_counter = restart_counter.saturating_sub(1);
+            if self.restart_interval > 0 {
+                if state.restart_counter == 0 {
+                    state.previous_dc.fill(0);
+                    reader.clear_buffer();
+                    state.restart_counter = self.restart_interval as u32;
                 }
 
-                for (comp_idx, info) in comp_infos.iter().enumerate() {
-                    for v in 0..info.v_samp {
-                        for h in 0..info.h_samp {
-                            let block_x = mcu_x * info.h_samp as u32 + h as u32;
-                            let block_y = mcu_y * info.v_samp as u32 + v as u32;
+                state.restart_counter = state.restart_counter.saturating_sub(1);
+            }
 
-                            if comp_idx >= previous_dc.len() {
-                                log_warn!(
-                                    "Component is larger than previous DC buffer: {} {}",
-                                    comp_idx,
-                                    previous_dc.len()
-                                );
-                                continue;
-                            }
+            for (comp_idx, info) in layout.components.iter().enumerate() {
+                for v in 0..info.v_samp {
+                    for h in 0..info.h_samp {
+                        let block_x = mcu_x * info.h_samp as u32 + h as u32;
+                        let block_y = mcu_y * info.v_samp as u32 + v as u32;
+
+                        if comp_idx >= state.previous_dc.len() {
+                            let message = format!(
+                                "Component is larger than previous DC buffer: {} {}",
+                                comp_idx,
+                                state.previous_dc.len(),
+                            );
+                            DecodeWarning::new(WarningKind::CorruptData, message).report();
+                            continue;
+                        }
 
-                            if let Some(block) = planes[comp_idx].get_block_mut(block_x, block_y) {
-                                let is_differential = matches!(self.mode, JpegMode::DifferentialSequential | JpegMode::DifferentialProgressive);
-                                match self.decode_mcu(
-                                    &mut reader,
-                                    block,
-                                    &info.dc_table,
-                                    &info.ac_table,
-                                    &mut previous_dc[comp_idx],
-                                    is_differential,
-                                ) {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        log_warn!("Failed to decode MCU: {}", e);
-                                    }
-                                };
-                            }
+                        if let Some(block) = planes[comp_idx].get_block_mut(block_x, block_y) {
+                            match self.decode_mcu(
+                                reader,
+                                block,
+                                &info.dc_table,
+                                &info.ac_table,
+                                &mut state.previous_dc[comp_idx],
+                                layout.is_differential,
+                            ) {
+                                Ok(_) => {}
+                                Err(e) => {
+                                    let message = format!("Failed to decode MCU: {}", e);
+                                    DecodeWarning::recovered_from(&e, message).report();
+                                }
+                            };
                         }
                     }
                 }
             }
         }
 
-        Ok(())
+        exhausted_at
+    }
+
+    /// Reports `completed` of `total` MCU rows of a sequential scan as rows of the output image.
+    fn report_mcu_rows(&self, completed: u32, total: u32) -> VexelResult<()> {
+        progress::rows_share(0, completed as u64, total as u64, self.output_dimensions().1)
     }
 
-    fn dequantize_and_idct_planes(&self, planes: &mut [ComponentPlane]) -> VexelResult<()> {
+    /// Reports that the first `completed` scans of a progressive image were decoded, as the same
+    /// share of its rows.
+    fn report_scans(&self, completed: usize) -> VexelResult<()> {
+        progress::rows_share(0, completed as u64, self.scans.len() as u64, self.output_dimensions().1)
+    }
+
+    /// Dequantizes and transforms the blocks of all planes. With a `window`, only the blocks
+    /// covering it are transformed. Blocks are always transformed in the same groups of eight,
+    /// so the result doesn't depend on which groups are skipped. With a reduced scale, blocks are
+    /// transformed to the sizes returned by [`component_block_size`](Self::component_block_size).
+    fn dequantize_and_idct_planes(&self, planes: &mut [ComponentPlane], window: Option<Region>) -> VexelResult<()> {
         let level_shift = if self.precision <= 8 { 128i32 } else { 2048i32 };
 
         let default_table = QuantizationTable {
@@ -2873,21 +3112,77 @@ impl<R: Read + Seek> JpegDecoder<R> {
                 })
                 .map(|t| t.table_natural.as_slice())
                 .unwrap_or_else(|| {
-                    log_warn!("Quantization table not found for component, substituting default one.");
+                    let message = "Quantization table not found for component, substituting default one.";
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                     default_table.table_natural.as_slice()
                 });
 
+            let block_size = self.component_block_size(comp_idx);
+            if block_size != 8 {
+                plane.block_size = block_size;
+
+                #[cfg(feature = "rayon")]
+                {
+                    use rayon::prelude::*;
+
+                    let sink = report::current_sink();
+                    let hooks = progress::current_hooks();
+                    plane.data.par_chunks_mut(64 * 8).for_each(|chunk| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
+                        dequantize_and_idct_reduced(chunk, quant_data, level_shift, block_size as usize);
+                    })));
+                }
+
+                #[cfg(not(feature = "rayon"))]
+                dequantize_and_idct_reduced(&mut plane.data, quant_data, level_shift, block_size as usize);
+
+                continue;
+            }
+
+            let blocks = window.map(|window| self.component_blocks(comp_idx, window));
+            let blocks_per_line = plane.blocks_per_line.max(1) as usize;
+
+            let is_needed = |group: usize| {
+                let Some(blocks) = blocks else {
+                    return true;
+                };
+
+                let first = group * 8;
+                let last = first + 7;
+
+                (first / blocks_per_line..=last / blocks_per_line).any(|block_y| {
+                    let start = if block_y == first / blocks_per_line { first % blocks_per_line } else { 0 };
+                    let end = if block_y == last / blocks_per_line { last % blocks_per_line } else { blocks_per_line - 1 };
+
+                    (blocks.y as usize..blocks.bottom() as usize).contains(&block_y)
+                        && start < blocks.right() as usize
+                        && end >= blocks.x as usize
+                })
+            };
+
             #[cfg(feature = "rayon")]
             {
                 use rayon::prelude::*;
-                plane.data.par_chunks_mut(64 * 8).for_each(|chunk| {
-                    dequantize_and_idct(chunk, quant_data, level_shift);
-                });
+
+                let sink = report::current_sink();
+                let hooks = progress::current_hooks();
+                plane.data.par_chunks_mut(64 * 8).enumerate().for_each(|(group, chunk)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
+                    if is_needed(group) {
+                        dequantize_and_idct(chunk, quant_data, level_shift);
+                    }
+                })));
             }
 
             #[cfg(not(feature = "rayon"))]
             {
-                dequantize_and_idct(&mut plane.data, quant_data, level_shift);
+                if window.is_none() {
+                    dequantize_and_idct(&mut plane.data, quant_data, level_shift);
+                } else {
+                    for (group, chunk) in plane.data.chunks_mut(64 * 8).enumerate() {
+                        if is_needed(group) {
+                            dequantize_and_idct(chunk, quant_data, level_shift);
+                        }
+                    }
+                }
             }
         }
 
@@ -2986,6 +3281,10 @@ impl<R: Read + Seek> JpegDecoder<R> {
             }
         };
 
+        // The embedded profile is only applied when a colour target is requested
+        let transform = (self.color_target != ColorTarget::Original)
+            .then(|| CmykTransform::cached(&assemble_icc_chunks(&self.icc_chunks).unwrap_or_default()));
+
         let mut pixels = vec![0u8; npixels * 3];
 
         for dy in 0..th {
@@ -3023,10 +3322,12 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     )
                 };
 
+                // Adobe stores inverted values, 255 means no ink
                 let idx = dy * tw + dx;
-                let r_out = ((c_inv * k_inv + 127) / 255).clamp(0, 255) as u8;
-                let g_out = ((m_inv * k_inv + 127) / 255).clamp(0, 255) as u8;
-                let b_out = ((y_inv * k_inv + 127) / 255).clamp(0, 255) as u8;
+                let [r_out, g_out, b_out] = match &transform {
+                    Some(transform) => transform.to_rgb8([c_inv, m_inv, y_inv, k_inv].map(|v| 255 - v as u8)),
+                    None => [c_inv, m_inv, y_inv].map(|v| ((v * k_inv + 127) / 255).clamp(0, 255) as u8),
+                };
 
                 pixels[idx * 3]     = r_out;
                 pixels[idx * 3 + 1] = g_out;
@@ -3037,24 +3338,35 @@ impl<R: Read + Seek> JpegDecoder<R> {
         Ok(PixelData::RGB8(pixels))
     }
 
-    fn upsample_and_convert(&self, planes: &[ComponentPlane]) -> VexelResult<PixelData> {
+    /// Upsamples and converts `width` x `height` pixels from the planes, which either cover the
+    /// whole image or an MCU-aligned window of it.
+    fn upsample_and_convert(&self, planes: &[ComponentPlane], width: u32, height: u32) -> VexelResult<PixelData> {
         use crate::decoders::jpeg::upsample as up;
 
         let max_h_samp = self.components.iter().map(|c| c.horizontal_sampling_factor).max().unwrap_or(1);
         let max_v_samp = self.components.iter().map(|c| c.vertical_sampling_factor).max().unwrap_or(1);
 
-        let tw = self.width as usize;
-        let th = self.height as usize;
+        let tw = width as usize;
+        let th = height as usize;
         let npixels = tw * th;
 
+        // Scaled images are always converted in full, their components are sized relative to the
+        // full-size image and the number of samples each block was transformed to
+        let (full_width, full_height) = match self.scale_denominator() {
+            1 => (width, height),
+            _ => (self.width, self.height),
+        };
+
         let source_dims: Vec<(usize, usize)> = self
             .components
             .iter()
-            .map(|comp| {
-                let sw = ((self.width * comp.horizontal_sampling_factor as u32 + max_h_samp as u32 - 1)
-                    / max_h_samp as u32) as usize;
-                let sh = ((self.height * comp.vertical_sampling_factor as u32 + max_v_samp as u32 - 1)
-                    / max_v_samp as u32) as usize;
+            .enumerate()
+            .map(|(comp_idx, comp)| {
+                let block_size = planes.get(comp_idx).map_or(8, |plane| plane.block_size);
+                let h_units = max_h_samp as u32 * 8;
+                let v_units = max_v_samp as u32 * 8;
+                let sw = (full_width * comp.horizontal_sampling_factor as u32 * block_size).div_ceil(h_units) as usize;
+                let sh = (full_height * comp.vertical_sampling_factor as u32 * block_size).div_ceil(v_units) as usize;
                 (sw, sh)
             })
             .collect();
@@ -3062,10 +3374,17 @@ impl<R: Read + Seek> JpegDecoder<R> {
         #[cfg(feature = "rayon")]
         let deinterleaved: Vec<Vec<i32>> = {
             use rayon::prelude::*;
+
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             planes
                 .par_iter()
                 .zip(source_dims.par_iter())
-                .map(|(plane, &(sw, sh))| plane.deinterleave(sw as u32, sh as u32))
+                .map(|(plane, &(sw, sh))| {
+                    report::with_sink(sink.clone(), || {
+                        progress::with_hooks(hooks.clone(), || plane.deinterleave(sw as u32, sh as u32))
+                    })
+                })
                 .collect()
         };
 
@@ -3113,7 +3432,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
         }
 
         if deinterleaved.len() < 3 {
-            log_warn!("Invalid number of planes for RGB conversion: {}.", deinterleaved.len());
+            let message = format!("Invalid number of planes for RGB conversion: {}.", deinterleaved.len());
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
         }
 
         let (y_sw, y_sh) = source_dims[0];
@@ -3277,7 +3597,10 @@ impl<R: Read + Seek> JpegDecoder<R> {
             #[cfg(feature = "rayon")]
             {
                 use rayon::prelude::*;
-                pixels.par_chunks_mut(tw * 3).enumerate().for_each(|(dy, dst)| {
+
+                let sink = report::current_sink();
+                let hooks = progress::current_hooks();
+                pixels.par_chunks_mut(tw * 3).enumerate().for_each(|(dy, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let mut y_row = vec![0i32; tw];
                     let mut cb_row = vec![0i32; tw];
                     let mut cr_row = vec![0i32; tw];
@@ -3305,7 +3628,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     }
 
                     fill_row_8!(dy, y_row, cb_row, cr_row, dst);
-                });
+                })));
             }
 
             #[cfg(not(feature = "rayon"))]
@@ -3327,7 +3650,10 @@ impl<R: Read + Seek> JpegDecoder<R> {
             #[cfg(feature = "rayon")]
             {
                 use rayon::prelude::*;
-                pixels16.par_chunks_mut(tw * 3).enumerate().for_each(|(dy, dst)| {
+
+                let sink = report::current_sink();
+                let hooks = progress::current_hooks();
+                pixels16.par_chunks_mut(tw * 3).enumerate().for_each(|(dy, dst)| report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || {
                     let mut y_row = vec![0i32; tw];
                     let mut cb_row = vec![0i32; tw];
                     let mut cr_row = vec![0i32; tw];
@@ -3355,7 +3681,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     }
 
                     fill_row_16!(dy, y_row, cb_row, cr_row, dst);
-                });
+                })));
             }
 
             #[cfg(not(feature = "rayon"))]
@@ -3374,7 +3700,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
         }
     }
 
-    fn decode_baseline(&mut self) -> VexelResult<Image> {
+    /// Allocates the coefficient planes of a sequential image, covering whole MCUs.
+    fn sequential_planes(&self) -> Vec<ComponentPlane> {
         let max_h_samp = self
             .components
             .iter()
@@ -3391,7 +3718,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
         let mcu_width = (self.width + 8 * max_h_samp as u32 - 1) / (8 * max_h_samp as u32);
         let mcu_height = (self.height + 8 * max_v_samp as u32 - 1) / (8 * max_v_samp as u32);
 
-        let mut component_planes: Vec<ComponentPlane> = self
+        self
             .components
             .iter()
             .map(|comp| {
@@ -3400,19 +3727,294 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
                 ComponentPlane::new(comp_width, comp_height)
             })
-            .collect();
+            .collect()
+    }
+
+    fn decode_baseline(&mut self) -> VexelResult<Image> {
+        let mut component_planes = self.sequential_planes();
 
         match self.coding_method {
             JpegCodingMethod::Huffman => self.decode_huffman_to_planes(&mut component_planes)?,
             JpegCodingMethod::Arithmetic => self.decode_arithmetic_to_planes(&mut component_planes)?,
         }
 
-        self.dequantize_and_idct_planes(&mut component_planes)?;
+        self.planes_to_image(component_planes)
+    }
 
-        let mut pixel_data = self.upsample_and_convert(&component_planes)?;
-        pixel_data.correct_pixels(self.width, self.height);
+    /// Transforms the decoded blocks and converts them to pixels. With a region set, only the
+    /// blocks around it are processed, see [`decode_window`](Self::decode_window).
+    fn planes_to_image(&self, mut planes: Vec<ComponentPlane>) -> VexelResult<Image> {
+        let (width, height) = self.output_dimensions();
+        let full = Region::new(0, 0, width, height);
+        let region = self.region.unwrap_or(full);
+        let window = self.decode_window(region);
 
-        Ok(Image::from_pixels(self.width, self.height, pixel_data))
+        if window == full {
+            self.dequantize_and_idct_planes(&mut planes, None)?;
+        } else {
+            self.dequantize_and_idct_planes(&mut planes, Some(window))?;
+            planes = self.crop_planes(&planes, window);
+        }
+
+        let mut pixel_data = self.upsample_and_convert(&planes, window.width, window.height)?;
+        pixel_data.correct_pixels(window.width, window.height);
+        let pixel_data = pixel_data.cropped(window.width, window.height, region.relative_to(window));
+
+        Ok(Image::from_pixels(region.width, region.height, pixel_data))
+    }
+
+    /// Reads the segments up to the first scan and prepares decoding it one MCU row at a time
+    /// with [`decode_incremental_rows`](Self::decode_incremental_rows). Returns `None` for images
+    /// that can't be decoded that way, anything but a sequential Huffman image with a single scan
+    /// of all components, each subsampled by 1 or 2.
+    pub(crate) fn start_incremental_scan(&mut self) -> VexelResult<Option<IncrementalScan>> {
+        while self.scans.is_empty() {
+            let Some(marker) = self.reader.next_marker(&JPEG_MARKERS)? else {
+                return Ok(None);
+            };
+
+            let segment_start = self.reader.stream_position()?.saturating_sub(2);
+            match marker {
+                JpegMarker::SOI => {}
+                JpegMarker::EOI => return Ok(None),
+                _ => self.read_segment(&marker, segment_start)?,
+            }
+        }
+
+        let is_sequential = matches!(self.mode, JpegMode::Baseline | JpegMode::ExtendedSequential);
+        let (max_h_samp, max_v_samp) = self.max_sampling_factors();
+        // Single component scans use one block per MCU, whatever the sampling factors say
+        let single_block_mcus = self.components.len() > 1 || (max_h_samp == 1 && max_v_samp == 1);
+
+        if !is_sequential
+            || self.is_hierarchical
+            || self.coding_method != JpegCodingMethod::Huffman
+            || self.width == 0
+            || self.height == 0
+            || self.scans[0].components.len() != self.components.len()
+            || !self.has_simple_sampling()
+            || !single_block_mcus
+        {
+            return Ok(None);
+        }
+
+        let planes = self.sequential_planes();
+
+        Ok(Some(IncrementalScan {
+            layout: self.huffman_scan_layout(),
+            state: HuffmanScanState::new(planes.len(), self.restart_interval),
+            planes,
+            position: JpegBitPosition::default(),
+            decoded_mcu_rows: 0,
+            converted_rows: 0,
+        }))
+    }
+
+    /// Decodes the MCU rows of `scan` that are complete in `data`, the entropy-coded data received
+    /// so far, and returns the image rows below the ones returned before that can be converted.
+    ///
+    /// A row of MCUs that runs past the end of `data` is decoded again from the same state on the
+    /// next call. The last decoded row of MCUs is only converted once the one below it has been
+    /// decoded, as upsampling refers to it, so the rows are the same as in a full decode.
+    pub(crate) fn decode_incremental_rows(
+        &self,
+        scan: &mut IncrementalScan,
+        data: &[u8],
+    ) -> VexelResult<Option<PixelData>> {
+        while scan.decoded_mcu_rows < scan.layout.mcu_height {
+            let mcu_y = scan.decoded_mcu_rows;
+            let state = scan.state.clone();
+            let mut reader = JpegBitReader::resume(data, scan.position);
+
+            self.decode_huffman_mcu_row(&mut reader, &scan.layout, &mut scan.state, &mut scan.planes, mcu_y);
+
+            if reader.read_past_end() {
+                scan.state = state;
+                for (plane, info) in scan.planes.iter_mut().zip(&scan.layout.components) {
+                    plane.clear_block_rows(mcu_y * info.v_samp as u32, info.v_samp as u32);
+                }
+                break;
+            }
+
+            scan.position = reader.position();
+            scan.decoded_mcu_rows += 1;
+        }
+
+        let rows = match scan.decoded_mcu_rows {
+            decoded if decoded == scan.layout.mcu_height => self.height,
+            decoded => (decoded.saturating_sub(1) * scan.layout.mcu_pixel_height()).min(self.height),
+        };
+
+        if rows <= scan.converted_rows {
+            return Ok(None);
+        }
+
+        let region = Region::new(0, scan.converted_rows, self.width, rows - scan.converted_rows);
+        let window = self.mcu_window(region);
+        let mut planes = self.crop_planes(&scan.planes, window);
+        self.dequantize_and_idct_planes(&mut planes, None)?;
+
+        let mut pixel_data = self.upsample_and_convert(&planes, window.width, window.height)?;
+        pixel_data.correct_pixels(window.width, window.height);
+        scan.converted_rows = rows;
+
+        Ok(Some(pixel_data.cropped(window.width, window.height, region.relative_to(window))))
+    }
+
+    fn max_sampling_factors(&self) -> (u32, u32) {
+        let max_h_samp = self.components.iter().map(|c| c.horizontal_sampling_factor).max().unwrap_or(1);
+        let max_v_samp = self.components.iter().map(|c| c.vertical_sampling_factor).max().unwrap_or(1);
+
+        (max_h_samp.max(1) as u32, max_v_samp.max(1) as u32)
+    }
+
+    /// Returns the MCU-aligned part of the image that is transformed and converted to decode
+    /// `region`. It includes one more MCU on each side, so upsampling sees the same neighbouring
+    /// samples as in a full decode.
+    ///
+    /// Components that aren't subsampled by exactly 1 or 2 are upsampled with ratios that depend
+    /// on the size of the image, so those images are converted in full. Lossless and
+    /// hierarchical images don't go through this path at all.
+    fn decode_window(&self, region: Region) -> Region {
+        let (width, height) = self.output_dimensions();
+        let full = Region::new(0, 0, width, height);
+
+        if self.region.is_none()
+            || self.is_hierarchical
+            || self.mode == JpegMode::Lossless
+            || self.scale_denominator() != 1
+            || !self.has_simple_sampling()
+        {
+            return full;
+        }
+
+        self.mcu_window(region)
+    }
+
+    /// Whether every component is subsampled by exactly 1 or 2 relative to the largest one.
+    fn has_simple_sampling(&self) -> bool {
+        let (max_h_samp, max_v_samp) = self.max_sampling_factors();
+
+        self.components.iter().all(|c| {
+            let (h, v) = (c.horizontal_sampling_factor as u32, c.vertical_sampling_factor as u32);
+            (h == max_h_samp || h * 2 == max_h_samp) && (v == max_v_samp || v * 2 == max_v_samp)
+        })
+    }
+
+    /// Returns the MCU-aligned part of the image around `region`, with one more MCU on each side.
+    fn mcu_window(&self, region: Region) -> Region {
+        let (max_h_samp, max_v_samp) = self.max_sampling_factors();
+        let mcu_width = 8 * max_h_samp;
+        let mcu_height = 8 * max_v_samp;
+
+        let x = (region.x / mcu_width).saturating_sub(1) * mcu_width;
+        let y = (region.y / mcu_height).saturating_sub(1) * mcu_height;
+        let right = (region.right().div_ceil(mcu_width) + 1).saturating_mul(mcu_width).min(self.width);
+        let bottom = (region.bottom().div_ceil(mcu_height) + 1).saturating_mul(mcu_height).min(self.height);
+
+        Region::new(x, y, right - x, bottom - y)
+    }
+
+    /// Number of image rows that have to be entropy decoded.
+    fn decoded_rows(&self) -> u32 {
+        match self.region {
+            Some(region) if self.scale_denominator() == 1 => self.decode_window(region).bottom(),
+            _ => self.height,
+        }
+    }
+
+    /// Returns the scale denominator that applies to this image. Lossless and hierarchical
+    /// images are always decoded at full size.
+    fn scale_denominator(&self) -> u32 {
+        if self.is_hierarchical || self.mode == JpegMode::Lossless {
+            return 1;
+        }
+
+        self.scale.denominator()
+    }
+
+    /// Returns the dimensions of the decoded image, after scaling.
+    fn output_dimensions(&self) -> (u32, u32) {
+        if self.is_hierarchical {
+            return (self.dhp_width, self.dhp_height);
+        }
+
+        let denominator = self.scale_denominator();
+        (self.width.div_ceil(denominator), self.height.div_ceil(denominator))
+    }
+
+    /// Returns the number of samples per block side that a component's blocks are transformed to.
+    ///
+    /// At reduced scales, subsampled components use a correspondingly larger transform, so that
+    /// chroma is reconstructed from its own coefficients instead of being upsampled, the same way
+    /// libjpeg does it. A 2x2 subsampled image decoded at 1/2 scale uses 4x4 transforms for luma
+    /// and full 8x8 transforms for chroma. Any remaining ratio is handled by the upsampler.
+    fn component_block_size(&self, comp_idx: usize) -> u32 {
+        let denominator = self.scale_denominator();
+        if denominator == 1 {
+            return 8;
+        }
+
+        let (max_h_samp, max_v_samp) = self.max_sampling_factors();
+        let ratio = self
+            .components
+            .get(comp_idx)
+            .map(|c| {
+                let h = (max_h_samp / (c.horizontal_sampling_factor as u32).max(1)).max(1);
+                let v = (max_v_samp / (c.vertical_sampling_factor as u32).max(1)).max(1);
+                h.min(v)
+            })
+            .unwrap_or(1);
+
+        // Only power-of-two transform sizes are implemented
+        (8 / denominator * (1 << ratio.ilog2())).min(8)
+    }
+
+    /// Returns the blocks of a component that cover an MCU-aligned `window`, in block units.
+    fn component_blocks(&self, comp_idx: usize, window: Region) -> Region {
+        let (max_h_samp, max_v_samp) = self.max_sampling_factors();
+        let (h, v) = self
+            .components
+            .get(comp_idx)
+            .map(|c| (c.horizontal_sampling_factor as u32, c.vertical_sampling_factor as u32))
+            .unwrap_or((1, 1));
+
+        let mcu_width = 8 * max_h_samp;
+        let mcu_height = 8 * max_v_samp;
+
+        Region::new(
+            window.x / mcu_width * h,
+            window.y / mcu_height * v,
+            window.width.div_ceil(mcu_width) * h,
+            window.height.div_ceil(mcu_height) * v,
+        )
+    }
+
+    /// Copies the blocks of each component that cover `window` into smaller planes.
+    fn crop_planes(&self, planes: &[ComponentPlane], window: Region) -> Vec<ComponentPlane> {
+        planes
+            .iter()
+            .enumerate()
+            .map(|(comp_idx, plane)| {
+                let blocks = self.component_blocks(comp_idx, window);
+                let row_length = blocks.width as usize * 64;
+                let mut data = Vec::with_capacity(row_length * blocks.height as usize);
+
+                for block_y in blocks.y..blocks.bottom() {
+                    let start = (block_y * plane.blocks_per_line + blocks.x) as usize * 64;
+                    match plane.data.get(start..start + row_length) {
+                        Some(row) => data.extend_from_slice(row),
+                        None => data.resize(data.len() + row_length, 0),
+                    }
+                }
+
+                ComponentPlane {
+                    data,
+                    blocks_per_line: blocks.width,
+                    block_size: plane.block_size,
+                }
+            })
+            .collect()
     }
 
     fn decode_frame_to_pixels(
@@ -3523,7 +4125,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     JpegCodingMethod::Arithmetic => self.decode_progressive_scans_arithmetic(&mut planes)?,
                 }
 
-                self.dequantize_and_idct_planes(&mut planes)?;
+                self.dequantize_and_idct_planes(&mut planes, None)?;
                 
                 Ok(self.planes_to_component_pixels(&planes))
             }
@@ -3546,7 +4148,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
                     JpegCodingMethod::Arithmetic => self.decode_arithmetic_to_planes(&mut planes)?,
                 }
 
-                self.dequantize_and_idct_planes(&mut planes)?;
+                self.dequantize_and_idct_planes(&mut planes, None)?;
                 let result = self.planes_to_component_pixels(&planes);
 
                 Ok(result)
@@ -3628,7 +4230,7 @@ impl<R: Read + Seek> JpegDecoder<R> {
 
     fn decode_hierarchical(&mut self) -> VexelResult<Image> {
         if self.hierarchical_frames.is_empty() {
-            return Err(VexelError::from(Error::new(ErrorKind::InvalidData, "No hierarchical frames found")));
+            return Err(VexelError::missing(ImageFormat::Jpeg, "No hierarchical frames found"));
         }
 
         let final_width = self.dhp_width;
@@ -3852,6 +4454,203 @@ impl<R: Read + Seek> JpegDecoder<R> {
         });
     }
 
+    fn read_segment(&mut self, marker: &JpegMarker, segment_start: u64) -> VexelResult<()> {
+        match marker {
+            JpegMarker::COM => self.read_com(segment_start),
+            JpegMarker::APP0 => self.read_app0_jfif(segment_start),
+            JpegMarker::APP1 => self.read_app1_exif(segment_start),
+            JpegMarker::APP2 => self.read_app2_icc(segment_start),
+            JpegMarker::APP3 => self.read_app_generic("APP3", segment_start),
+            JpegMarker::APP4 => self.read_app_generic("APP4", segment_start),
+            JpegMarker::APP5 => self.read_app_generic("APP5", segment_start),
+            JpegMarker::APP6 => self.read_app_generic("APP6", segment_start),
+            JpegMarker::APP7 => self.read_app_generic("APP7", segment_start),
+            JpegMarker::APP8 => self.read_app_generic("APP8", segment_start),
+            JpegMarker::APP9 => self.read_app_generic("APP9", segment_start),
+            JpegMarker::APP10 => self.read_app_generic("APP10", segment_start),
+            JpegMarker::APP11 => self.read_app_generic("APP11", segment_start),
+            JpegMarker::APP12 => self.read_app_generic("APP12", segment_start),
+            JpegMarker::APP13 => self.read_app_generic("APP13", segment_start),
+            JpegMarker::APP14 => self.read_app14_adobe(segment_start),
+            JpegMarker::APP15 => self.read_app_generic("APP15", segment_start),
+            JpegMarker::SOF0 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.read_start_of_frame("SOF0", segment_start)
+            }
+            JpegMarker::SOF1 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::ExtendedSequential;
+                self.read_start_of_frame("SOF1", segment_start)
+            }
+            JpegMarker::SOF2 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::Progressive;
+                self.read_start_of_frame("SOF2", segment_start)
+            }
+            JpegMarker::SOF3 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::Lossless;
+                self.read_start_of_frame("SOF3", segment_start)
+            }
+            JpegMarker::SOF5 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::DifferentialSequential;
+                self.read_start_of_frame("SOF5", segment_start)
+            }
+            JpegMarker::SOF6 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::DifferentialProgressive;
+                self.read_start_of_frame("SOF6", segment_start)
+            }
+            JpegMarker::SOF7 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::DifferentialLossless;
+                self.read_start_of_frame("SOF7", segment_start)
+            }
+            JpegMarker::SOF9 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::ExtendedSequential;
+                self.coding_method = JpegCodingMethod::Arithmetic;
+                self.read_start_of_frame("SOF9", segment_start)
+            }
+            JpegMarker::SOF10 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::Progressive;
+                self.coding_method = JpegCodingMethod::Arithmetic;
+                self.read_start_of_frame("SOF10", segment_start)
+            }
+            JpegMarker::SOF11 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::Lossless;
+                self.coding_method = JpegCodingMethod::Arithmetic;
+                self.read_start_of_frame("SOF11", segment_start)
+            }
+            JpegMarker::SOF13 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::DifferentialSequential;
+                self.coding_method = JpegCodingMethod::Arithmetic;
+                self.read_start_of_frame("SOF13", segment_start)
+            }
+            JpegMarker::SOF14 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::DifferentialProgressive;
+                self.coding_method = JpegCodingMethod::Arithmetic;
+                self.read_start_of_frame("SOF14", segment_start)
+            }
+            JpegMarker::SOF15 => {
+                if self.is_hierarchical { self.finalize_current_frame(); }
+                self.mode = JpegMode::DifferentialLossless;
+                self.coding_method = JpegCodingMethod::Arithmetic;
+                self.read_start_of_frame("SOF15", segment_start)
+            }
+            JpegMarker::DHP => self.read_dhp(segment_start),
+            JpegMarker::EXP => self.read_exp(segment_start),
+            JpegMarker::DRI => self.read_restart_interval(segment_start),
+            JpegMarker::DNL => self.read_dnl(segment_start),
+            JpegMarker::DQT => self.read_quantization_table(segment_start),
+            JpegMarker::DHT => self.read_huffman_table(segment_start),
+            JpegMarker::DAC => self.read_dac(segment_start),
+            JpegMarker::SOS => self.read_start_of_scan(segment_start),
+            _ => {
+                log_warn!("Unhandled marker found: {:?}", marker);
+                self.skip_unknown_marker_segment(&format!("{:?}", marker), segment_start)
+            }
+        }
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        while let Ok(Some(marker)) = self.reader.next_marker(&JPEG_MARKERS) {
+            let segment_start = self.reader.stream_position().unwrap_or(0).saturating_sub(2);
+
+            let result = match marker {
+                JpegMarker::SOI => {
+                    self.record_segment(segment_start, "SOI", JpegSegmentData::SOI);
+                    Ok(())
+                }
+                JpegMarker::EOI => {
+                    self.record_segment(segment_start, "EOI", JpegSegmentData::EOI);
+                    break;
+                }
+                // Frame headers are complete once the first scan starts, unless the height
+                // is deferred to a DNL marker that follows the scan
+                JpegMarker::SOS if !self.components.is_empty() && (self.height > 0 || self.is_hierarchical) => break,
+                _ => self.read_segment(&marker, segment_start),
+            };
+
+            match result {
+                Ok(_) => {}
+                Err(VexelError::LimitExceeded(_)) => return Err(result.unwrap_err()),
+                Err(e) => {
+                    let message = format!("Failed to process {:?} marker segment: {}", marker, e);
+                    DecodeWarning::recovered_from(&e, message).at_offset(segment_start).report();
+                }
+            }
+        }
+
+        if self.components.is_empty() {
+            return Err(VexelError::missing(ImageFormat::Jpeg, "No frame header found"));
+        }
+
+        if self.width == 0 || self.height == 0 {
+            return Err(VexelError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        let is_16bit = self.precision > 8;
+        let pick = |low: PixelFormat, high: PixelFormat| if is_16bit { high } else { low };
+        let nc = self.components.len();
+
+        let pixel_format = if nc == 1 {
+            pick(PixelFormat::L8, PixelFormat::L16)
+        } else if self.is_hierarchical {
+            if nc == 4 {
+                PixelFormat::RGB8
+            } else {
+                pick(PixelFormat::RGB8, PixelFormat::RGB16)
+            }
+        } else if self.mode == JpegMode::Lossless {
+            if nc == 4 {
+                pick(PixelFormat::RGBA8, PixelFormat::RGBA16)
+            } else {
+                pick(PixelFormat::RGB8, PixelFormat::RGB16)
+            }
+        } else {
+            match self.detect_colorspace() {
+                JpegColorspace::CMYK | JpegColorspace::YCCK => PixelFormat::RGB8,
+                JpegColorspace::RGBA => pick(PixelFormat::RGBA8, PixelFormat::RGBA16),
+                _ => pick(PixelFormat::RGB8, PixelFormat::RGB16),
+            }
+        };
+
+        let (width, height) = self.output_dimensions();
+
+        Ok(ImageHeader {
+            width,
+            height,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
+    /// Decodes `region` of the image. Entropy decoding stops after the last MCU row it needs, and
+    /// only the MCUs around it are transformed and converted. Lossless and hierarchical images
+    /// are decoded in full and cropped.
+    pub fn decode_region(&mut self, region: Region) -> VexelResult<Image> {
+        self.region = Some(region);
+        let result = self.decode();
+        self.region = None;
+
+        let image = result?;
+        if self.is_hierarchical || self.mode == JpegMode::Lossless {
+            return Ok(image.cropped(region));
+        }
+
+        Ok(image)
+    }
+
     pub fn decode(&mut self) -> VexelResult<Image> {
         while let Ok(marker) = self.reader.next_marker(&JPEG_MARKERS) {
             match marker {
@@ -3869,112 +4668,15 @@ impl<R: Read + Seek> JpegDecoder<R> {
                             self.record_segment(segment_start, "EOI", JpegSegmentData::EOI);
                             break;
                         }
-                        JpegMarker::COM => self.read_com(segment_start),
-                        JpegMarker::APP0 => self.read_app0_jfif(segment_start),
-                        JpegMarker::APP1 => self.read_app1_exif(segment_start),
-                        JpegMarker::APP2 => self.read_app2_icc(segment_start),
-                        JpegMarker::APP3 => self.read_app_generic("APP3", segment_start),
-                        JpegMarker::APP4 => self.read_app_generic("APP4", segment_start),
-                        JpegMarker::APP5 => self.read_app_generic("APP5", segment_start),
-                        JpegMarker::APP6 => self.read_app_generic("APP6", segment_start),
-                        JpegMarker::APP7 => self.read_app_generic("APP7", segment_start),
-                        JpegMarker::APP8 => self.read_app_generic("APP8", segment_start),
-                        JpegMarker::APP9 => self.read_app_generic("APP9", segment_start),
-                        JpegMarker::APP10 => self.read_app_generic("APP10", segment_start),
-                        JpegMarker::APP11 => self.read_app_generic("APP11", segment_start),
-                        JpegMarker::APP12 => self.read_app_generic("APP12", segment_start),
-                        JpegMarker::APP13 => self.read_app_generic("APP13", segment_start),
-                        JpegMarker::APP14 => self.read_app14_adobe(segment_start),
-                        JpegMarker::APP15 => self.read_app_generic("APP15", segment_start),
-                        JpegMarker::SOF0 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.read_start_of_frame("SOF0", segment_start)
-                        }
-                        JpegMarker::SOF1 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::ExtendedSequential;
-                            self.read_start_of_frame("SOF1", segment_start)
-                        }
-                        JpegMarker::SOF2 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::Progressive;
-                            self.read_start_of_frame("SOF2", segment_start)
-                        }
-                        JpegMarker::SOF3 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::Lossless;
-                            self.read_start_of_frame("SOF3", segment_start)
-                        }
-                        JpegMarker::SOF5 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::DifferentialSequential;
-                            self.read_start_of_frame("SOF5", segment_start)
-                        }
-                        JpegMarker::SOF6 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::DifferentialProgressive;
-                            self.read_start_of_frame("SOF6", segment_start)
-                        }
-                        JpegMarker::SOF7 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::DifferentialLossless;
-                            self.read_start_of_frame("SOF7", segment_start)
-                        }
-                        JpegMarker::SOF9 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::ExtendedSequential;
-                            self.coding_method = JpegCodingMethod::Arithmetic;
-                            self.read_start_of_frame("SOF9", segment_start)
-                        }
-                        JpegMarker::SOF10 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::Progressive;
-                            self.coding_method = JpegCodingMethod::Arithmetic;
-                            self.read_start_of_frame("SOF10", segment_start)
-                        }
-                        JpegMarker::SOF11 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::Lossless;
-                            self.coding_method = JpegCodingMethod::Arithmetic;
-                            self.read_start_of_frame("SOF11", segment_start)
-                        }
-                        JpegMarker::SOF13 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::DifferentialSequential;
-                            self.coding_method = JpegCodingMethod::Arithmetic;
-                            self.read_start_of_frame("SOF13", segment_start)
-                        }
-                        JpegMarker::SOF14 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::DifferentialProgressive;
-                            self.coding_method = JpegCodingMethod::Arithmetic;
-                            self.read_start_of_frame("SOF14", segment_start)
-                        }
-                        JpegMarker::SOF15 => {
-                            if self.is_hierarchical { self.finalize_current_frame(); }
-                            self.mode = JpegMode::DifferentialLossless;
-                            self.coding_method = JpegCodingMethod::Arithmetic;
-                            self.read_start_of_frame("SOF15", segment_start)
-                        }
-                        JpegMarker::DHP => self.read_dhp(segment_start),
-                        JpegMarker::EXP => self.read_exp(segment_start),
-                        JpegMarker::DRI => self.read_restart_interval(segment_start),
-                        JpegMarker::DNL => self.read_dnl(segment_start),
-                        JpegMarker::DQT => self.read_quantization_table(segment_start),
-                        JpegMarker::DHT => self.read_huffman_table(segment_start),
-                        JpegMarker::DAC => self.read_dac(segment_start),
-                        JpegMarker::SOS => self.read_start_of_scan(segment_start),
-                        _ => {
-                            log_warn!("Unhandled marker found: {:?}", marker);
-                            self.skip_unknown_marker_segment(&format!("{:?}", marker), segment_start)
-                        }
+                        _ => self.read_segment(&marker, segment_start),
                     };
 
                     match result {
                         Ok(_) => {}
                         Err(VexelError::LimitExceeded(_)) => return Err(result.unwrap_err()),
                         Err(e) => {
-                            log_warn!("Failed to process {:?} marker segment: {}", marker, e);
+                            let message = format!("Failed to process {:?} marker segment: {}", marker, e);
+                            DecodeWarning::recovered_from(&e, message).at_offset(segment_start).report();
                         }
                     }
                 }
@@ -4010,16 +4712,20 @@ impl<R: Read + Seek> JpegDecoder<R> {
             self.finalize_current_frame();
         }
 
-        if self.width == 0 || self.height == 0 || self.components.is_empty() {
-            return Err(VexelError::from(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                format!(
-                    "Invalid JPEG: dimensions={}x{}, components={}",
-                    self.width,
-                    self.height,
-                    self.components.len()
-                ),
-            )));
+        if self.components.is_empty() {
+            return Err(VexelError::missing(ImageFormat::Jpeg, "No frame header found"));
+        }
+
+        if self.width == 0 || self.height == 0 {
+            return Err(VexelError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        if let Some(region) = self.region {
+            let (width, height) = self.output_dimensions();
+            region.check_bounds(width, height)?;
         }
 
         if self.is_hierarchical {
@@ -4046,7 +4752,8 @@ impl<R: Read + Seek> JpegDecoder<R> {
                 Ok(image)
             }
             JpegMode::DifferentialSequential | JpegMode::DifferentialProgressive | JpegMode::DifferentialLossless => {
-                log_warn!("Differential JPEG mode outside hierarchical context, treating as baseline");
+                let message = "Differential JPEG mode outside hierarchical context, treating as baseline";
+                DecodeWarning::new(WarningKind::Unsupported, message).report();
                 let image = self.decode_baseline()?;
                 Ok(image)
             }
@@ -39,6 +39,18 @@ fn precompute_scaled_quant(quant: &[u16]) -> [f32; 64] {
     out
 }
 
+/// Dequantizes and transforms each block to `size` x `size` samples using only its lowest
+/// `size` x `size` coefficients, for decoding at 1/2, 1/4 or 1/8 scale. The samples are stored
+/// row by row at the start of each block. A `size` of 8 is a regular full transform.
+pub fn dequantize_and_idct_reduced(blocks: &mut [i32], quant: &[u16], level_shift: i32, size: usize) {
+    match size {
+        1 => dequantize_and_idct_reduced_scalar::<1>(blocks, quant, level_shift),
+        2 => dequantize_and_idct_reduced_scalar::<2>(blocks, quant, level_shift),
+        4 => dequantize_and_idct_reduced_scalar::<4>(blocks, quant, level_shift),
+        _ => dequantize_and_idct(blocks, quant, level_shift),
+    }
+}
+
 // ─── Scalar ───────────────────────────────────────────────────
 
 #[inline(always)]
@@ -127,6 +139,48 @@ fn idct_block_precomputed(block: &mut [i32], scaled_quant: &[f32; 64], level_shi
     }
 }
 
+/// Basis of the `N`-point IDCT, `table[x][u] = C(u) / 2 * cos((2x + 1) * u * pi / 2N)`. With the
+/// 8-point normalisation this keeps the sample values of the full-size transform.
+fn reduced_idct_table<const N: usize>() -> [[f32; N]; N] {
+    let mut table = [[0.0f32; N]; N];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            let c = if u == 0 { std::f32::consts::FRAC_1_SQRT_2 } else { 1.0 };
+            let angle = (2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / (2 * N) as f32;
+            *value = c / 2.0 * angle.cos();
+        }
+    }
+    table
+}
+
+fn dequantize_and_idct_reduced_scalar<const N: usize>(blocks: &mut [i32], quant: &[u16], level_shift: i32) {
+    let table = reduced_idct_table::<N>();
+
+    for block in blocks.chunks_exact_mut(64) {
+        let mut temp = [[0.0f32; N]; N];
+
+        for v in 0..N {
+            for x in 0..N {
+                let mut sum = 0.0f32;
+                for u in 0..N {
+                    sum += block[v * 8 + u] as f32 * quant[v * 8 + u] as f32 * table[x][u];
+                }
+                temp[v][x] = sum;
+            }
+        }
+
+        for y in 0..N {
+            for x in 0..N {
+                let mut sum = 0.0f32;
+                for v in 0..N {
+                    sum += temp[v][x] * table[y][v];
+                }
+                block[y * N + x] = (sum.round() as i32).clamp(-level_shift, level_shift * 2 - 1);
+            }
+        }
+    }
+}
+
 // ─── AVX2 ─────────────────────────────────────────────────────
 
 #[cfg(target_arch = "x86_64")]
@@ -1,6 +1,7 @@
 pub mod markers;
 pub mod decoder;
 pub mod types;
+pub mod stream;
 pub mod upsample;
 pub mod idct;
 pub mod bitreader;
@@ -0,0 +1,222 @@
+use crate::utils::error::VexelResult;
+use crate::utils::marker::Marker;
+use crate::utils::stream::ScanProgress;
+use crate::PixelData;
+use std::io::Cursor;
+
+use super::decoder::{IncrementalScan, JpegDecoder};
+use super::markers::JpegMarker;
+
+/// Finds complete segments and scans in a JPEG that is still being received, and decodes the
+/// rows of baseline images as their scan data arrives.
+pub(crate) struct JpegScanner {
+    position: usize,
+    /// Set while inside entropy-coded data, where to continue looking for the end of the scan.
+    scan_data: Option<usize>,
+    /// Set once the first scan header has been read, for images whose rows can be decoded as
+    /// they arrive.
+    rows: Option<Box<JpegRows>>,
+    progress: ScanProgress,
+}
+
+/// Rows of a baseline image decoded from the scan data received so far.
+struct JpegRows {
+    decoder: JpegDecoder<Cursor<Vec<u8>>>,
+    scan: IncrementalScan,
+    /// Entropy-coded data received so far, without stuffed bytes, as the decoder reads it.
+    scan_data: Vec<u8>,
+    /// Where to continue reading the entropy-coded data in the file.
+    position: usize,
+    scan_complete: bool,
+}
+
+impl JpegScanner {
+    pub(crate) fn new() -> Self {
+        Self {
+            // Start of image
+            position: 2,
+            scan_data: None,
+            rows: None,
+            progress: ScanProgress::default(),
+        }
+    }
+
+    /// Walks the markers received since the last call. A scan is complete once the marker
+    /// following its entropy-coded data has arrived.
+    pub(crate) fn scan(&mut self, data: &[u8]) -> ScanProgress {
+        while !self.progress.done {
+            if let Some(search_from) = self.scan_data {
+                match Self::find_scan_end(data, search_from) {
+                    Ok(end) => {
+                        self.scan_data = None;
+                        self.position = end;
+                        self.progress.units += 1;
+                    }
+                    Err(resume) => {
+                        self.scan_data = Some(resume);
+                        break;
+                    }
+                }
+            }
+
+            // Fill bytes may precede a marker
+            let mut position = self.position;
+            while data.get(position) == Some(&0xFF) && data.get(position + 1) == Some(&0xFF) {
+                position += 1;
+            }
+
+            let (Some(&prefix), Some(&code)) = (data.get(position), data.get(position + 1)) else {
+                break;
+            };
+
+            if prefix != 0xFF {
+                // Garbage between segments, resynchronize on the next byte
+                self.position = position + 1;
+                continue;
+            }
+
+            let marker = JpegMarker::from_u16(u16::from_be_bytes([prefix, code]));
+
+            match marker {
+                Some(JpegMarker::EOI) => {
+                    self.progress.done = true;
+                    break;
+                }
+                Some(
+                    JpegMarker::SOI
+                    | JpegMarker::TEM
+                    | JpegMarker::RST0
+                    | JpegMarker::RST1
+                    | JpegMarker::RST2
+                    | JpegMarker::RST3
+                    | JpegMarker::RST4
+                    | JpegMarker::RST5
+                    | JpegMarker::RST6
+                    | JpegMarker::RST7,
+                ) => {
+                    self.position = position + 2;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let Some(length) = data.get(position + 2..position + 4) else {
+                break;
+            };
+
+            let end = position + 2 + u16::from_be_bytes([length[0], length[1]]) as usize;
+            if end > data.len() {
+                break;
+            }
+
+            if marker == Some(JpegMarker::SOS) {
+                // Frame header and tables always come before the first scan
+                if !self.progress.header_complete {
+                    self.rows = JpegRows::new(&data[..end]).map(Box::new);
+                }
+
+                self.progress.header_complete = true;
+                self.scan_data = Some(end);
+            }
+
+            self.position = end;
+        }
+
+        self.progress
+    }
+
+    /// Returns `true` if rows are decoded as they arrive, which is known once the first scan header
+    /// has been received. Only baseline images with a single scan of all components are.
+    pub(crate) fn decodes_rows(&self) -> bool {
+        self.rows.is_some()
+    }
+
+    /// Decodes the rows that the scan data received since the last call completes. Returns the
+    /// number of new rows and their pixels, if there are any.
+    pub(crate) fn decode_rows(&mut self, data: &[u8]) -> VexelResult<Option<(u32, PixelData)>> {
+        match &mut self.rows {
+            Some(rows) => rows.decode(data),
+            None => Ok(None),
+        }
+    }
+
+    /// Looks for the first marker after entropy-coded data, skipping stuffed zero bytes and
+    /// restart markers. Returns its position, or where to resume searching once more data arrives.
+    fn find_scan_end(data: &[u8], from: usize) -> Result<usize, usize> {
+        let mut position = from;
+
+        while position + 1 < data.len() {
+            if data[position] == 0xFF {
+                match data[position + 1] {
+                    0x00 | 0xFF | 0xD0..=0xD7 => {}
+                    _ => return Ok(position),
+                }
+            }
+
+            position += 1;
+        }
+
+        Err(position)
+    }
+}
+
+impl JpegRows {
+    /// Reads the headers in `data`, which ends with the header of the first scan.
+    fn new(data: &[u8]) -> Option<JpegRows> {
+        // The end of image marker ends the scan data before the decoder reaches the end of the
+        // buffer, which it would report as truncated
+        let mut headers = data.to_vec();
+        headers.extend_from_slice(&JpegMarker::EOI.to_u16().to_be_bytes());
+
+        let mut decoder = JpegDecoder::new(Cursor::new(headers));
+        let scan = decoder.start_incremental_scan().ok()??;
+
+        Some(JpegRows {
+            decoder,
+            scan,
+            scan_data: Vec::new(),
+            position: data.len(),
+            scan_complete: false,
+        })
+    }
+
+    fn decode(&mut self, data: &[u8]) -> VexelResult<Option<(u32, PixelData)>> {
+        self.read_scan_data(data);
+
+        let converted_rows = self.scan.converted_rows();
+        let pixels = self.decoder.decode_incremental_rows(&mut self.scan, &self.scan_data)?;
+
+        Ok(pixels.map(|pixels| (self.scan.converted_rows() - converted_rows, pixels)))
+    }
+
+    /// Appends the entropy-coded data received since the last call to `scan_data`, the same way
+    /// the decoder reads it: stuffed zero bytes and fill bytes are removed, restart markers are
+    /// kept. A 0xFF byte is only read once the byte after it has arrived.
+    fn read_scan_data(&mut self, data: &[u8]) {
+        while !self.scan_complete && self.position < data.len() {
+            let byte = data[self.position];
+            if byte != 0xFF {
+                self.scan_data.push(byte);
+                self.position += 1;
+                continue;
+            }
+
+            let Some(&next) = data.get(self.position + 1) else {
+                break;
+            };
+
+            match next {
+                0x00 => {
+                    self.scan_data.push(0xFF);
+                    self.position += 2;
+                }
+                0xFF => self.position += 1,
+                0xD0..=0xD7 => {
+                    self.scan_data.extend_from_slice(&[0xFF, next]);
+                    self.position += 2;
+                }
+                _ => self.scan_complete = true,
+            }
+        }
+    }
+}
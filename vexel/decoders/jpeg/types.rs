@@ -150,6 +150,31 @@ pub enum JpegCodingMethod {
     Arithmetic,
 }
 
+/// Size at which DCT-based JPEGs are decoded, set with [`Vexel::set_jpeg_scale`](crate::Vexel::set_jpeg_scale).
+///
+/// Reduced sizes transform each 8x8 block to 4x4, 2x2 or a single sample, which is much faster
+/// than decoding at full size and downscaling afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum JpegScale {
+    #[default]
+    Full,
+    Half,
+    Quarter,
+    Eighth,
+}
+
+impl JpegScale {
+    /// Returns the denominator of the scale factor, 1, 2, 4 or 8.
+    pub fn denominator(&self) -> u32 {
+        match self {
+            JpegScale::Full => 1,
+            JpegScale::Half => 2,
+            JpegScale::Quarter => 4,
+            JpegScale::Eighth => 8,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Tsify)]
 pub struct QuantizationTable {
     pub id: u8,
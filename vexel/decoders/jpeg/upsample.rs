@@ -10,6 +10,16 @@ pub fn deinterleave_blocks(data: &[i32], blocks_per_line: u32, sw: u32, sh: u32,
     deinterleave_blocks_scalar(data, blocks_per_line, sw, sh, out);
 }
 
+/// Like [`deinterleave_blocks`], for blocks that were transformed to `size` x `size` samples by a
+/// reduced IDCT. Each block still occupies 64 values, with the samples stored at its start.
+pub fn deinterleave_reduced_blocks(data: &[i32], blocks_per_line: u32, size: u32, sw: u32, sh: u32, out: &mut [i32]) {
+    if size == 8 {
+        return deinterleave_blocks(data, blocks_per_line, sw, sh, out);
+    }
+
+    deinterleave_reduced_blocks_scalar(data, blocks_per_line, size, sw, sh, out);
+}
+
 pub fn upsample_h2v1_row(src: &[i32], dst: &mut [i32], sw: usize, tw: usize) {
     #[cfg(target_arch = "x86_64")]
     if is_x86_feature_detected!("avx2") {
@@ -102,6 +112,24 @@ fn deinterleave_blocks_scalar(data: &[i32], blocks_per_line: u32, sw: u32, sh: u
     }
 }
 
+fn deinterleave_reduced_blocks_scalar(data: &[i32], blocks_per_line: u32, size: u32, sw: u32, sh: u32, out: &mut [i32]) {
+    let size = size.max(1) as usize;
+    let sw = sw as usize;
+    let sh = sh as usize;
+    let bpl = blocks_per_line as usize;
+
+    for y in 0..sh {
+        let (by, py) = (y / size, y % size);
+        for x in 0..sw {
+            let (bx, px) = (x / size, x % size);
+            let src_idx = (by * bpl + bx) * 64 + py * size + px;
+            if let Some(&value) = data.get(src_idx) {
+                out[y * sw + x] = value;
+            }
+        }
+    }
+}
+
 fn upsample_h2v1_row_scalar(src: &[i32], dst: &mut [i32], sw: usize, tw: usize) {
     if sw == 0 {
         return;
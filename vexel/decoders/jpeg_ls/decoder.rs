@@ -1,10 +1,13 @@
 use std::io::{Read, Seek};
 
 use crate::decoders::jpeg::types::{APP14AdobeData, IccProfileSequenceInfo, JFIFData};
-use crate::utils::error::VexelResult;
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::report::{DecodeWarning, WarningKind};
+use crate::utils::progress;
 use crate::utils::exif::ExifReader;
 use crate::utils::info::JpegLsInfo;
-use crate::{Image, Limits, PixelData, log_warn};
+use crate::utils::metadata::{ImageMetadata, Resolution, XMP_SIGNATURE, assemble_icc_chunks, jpeg_resolution};
+use crate::{Image, ImageFormat, ImageHeader, Limits, PixelData, PixelFormat, log_warn};
 use crate::bitreader::BitReader;
 
 use super::bitreader::JlsBitReader;
@@ -21,6 +24,8 @@ pub struct JpegLsDecoder<R: Read + Seek> {
     color_transform: u8,
     restart_interval: usize,
     sections: Vec<JpegLsSectionInfo>,
+    metadata: ImageMetadata,
+    icc_chunks: Vec<(u8, Vec<u8>)>,
 }
 
 impl<R: Read + Seek> JpegLsDecoder<R> {
@@ -34,6 +39,8 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
             color_transform: 0,
             restart_interval: 0,
             sections: Vec::new(),
+            metadata: ImageMetadata::default(),
+            icc_chunks: Vec::new(),
         }
     }
 
@@ -47,6 +54,28 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
         }
     }
 
+    pub fn metadata(&self) -> ImageMetadata {
+        let mut metadata = self.metadata.clone();
+
+        if let Some(data) = assemble_icc_chunks(&self.icc_chunks) {
+            metadata.set_icc(data);
+        }
+
+        let jfif_resolution = self.sections.iter().find_map(|section| match &section.data {
+            JpegLsSectionData::App(JpegLsAppData { jfif: Some(jfif), .. }) => {
+                Resolution::from_jfif(jfif.density_units, jfif.x_density, jfif.y_density)
+            }
+            _ => None,
+        });
+        metadata.resolution = jpeg_resolution(jfif_resolution, metadata.exif.as_ref());
+
+        metadata
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn read_u8(&mut self) -> std::io::Result<u8> {
         Ok(self.reader.read_bits(8)? as u8)
     }
@@ -158,10 +187,10 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
             if number_of_lines > 0 {
                 frame.height = number_of_lines as u32;
             } else {
-                log_warn!("DNL specifies 0 lines, ignoring");
+                DecodeWarning::new(WarningKind::CorruptData, "DNL specifies 0 lines, ignoring").report();
             }
         } else {
-            log_warn!("DNL marker encountered before SOF, ignoring");
+            DecodeWarning::new(WarningKind::CorruptData, "DNL marker encountered before SOF, ignoring").report();
         }
 
         Ok(JpegLsDnlData { length, number_of_lines })
@@ -273,6 +302,67 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
         Ok(data)
     }
 
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        loop {
+            let marker = match self.reader.next_marker(&JPEG_LS_MARKERS) {
+                Ok(Some(m)) => m,
+                Ok(None) => break,
+                Err(_) => break,
+            };
+
+            match marker {
+                JpegLsMarker::SOI => {}
+                JpegLsMarker::EOI => break,
+                JpegLsMarker::SOF55 => {
+                    self.read_sof()?;
+                }
+                JpegLsMarker::DNL => {
+                    if let Err(e) = self.read_dnl() {
+                        let message = format!("Failed to read DNL segment: {}", e);
+                        DecodeWarning::recovered_from(&e.into(), message).report();
+                    }
+                }
+                JpegLsMarker::SOS => {
+                    if self.frame.as_ref().is_some_and(|frame| frame.height > 0) {
+                        break;
+                    }
+
+                    // Height is defined by a DNL marker after the scan data
+                    self.read_sos()?;
+                    self.read_compressed_data()?;
+                }
+                _ => {
+                    // Skip the whole segment so its payload is not mistaken for a marker
+                    let len = self.read_u16()?;
+                    self.reader.read_bytes(len.saturating_sub(2) as usize)?;
+                }
+            }
+        }
+
+        let frame = match self.frame.as_ref() {
+            Some(f) => f,
+            None => return Err(VexelError::missing(ImageFormat::JpegLs, "No SOF marker found")),
+        };
+
+        let components = frame.component_count();
+        let pixel_format = match (frame.precision > 8, components) {
+            (true, 1) => PixelFormat::L16,
+            (true, 4) => PixelFormat::RGBA16,
+            (true, _) => PixelFormat::RGB16,
+            (false, 1) => PixelFormat::L8,
+            (false, 4) => PixelFormat::RGBA8,
+            (false, _) => PixelFormat::RGB8,
+        };
+
+        Ok(ImageHeader {
+            width: frame.width,
+            height: frame.height,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
     pub fn decode(&mut self) -> VexelResult<Image> {
         loop {
             let marker = match self.reader.next_marker(&JPEG_LS_MARKERS) {
@@ -343,7 +433,8 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                             });
                         }
                         Err(e) => {
-                            log_warn!("Failed to read DNL segment: {}", e);
+                            let message = format!("Failed to read DNL segment: {}", e);
+                            DecodeWarning::recovered_from(&e.into(), message).at_offset(marker_start).report();
                         }
                     }
                 }
@@ -387,6 +478,10 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                     } else {
                         None
                     };
+                    if exif.is_some() || payload.starts_with(XMP_SIGNATURE) {
+                        self.limits.reserve_metadata(payload.len() as u64)?;
+                    }
+                    self.metadata.read_app1(&payload, exif.as_ref());
                     let identifier = if payload.len() >= 4 {
                         let null_pos = payload.iter().position(|&b| b == 0).unwrap_or(payload.len().min(32));
                         String::from_utf8_lossy(&payload[..null_pos]).into_owned().into()
@@ -401,6 +496,8 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                     let null_pos = payload.iter().position(|&b| b == 0).unwrap_or(payload.len());
                     let identifier = String::from_utf8_lossy(&payload[..null_pos]).to_string();
                     let icc = if identifier == "ICC_PROFILE" && payload.len() >= null_pos + 3 {
+                        self.limits.reserve_metadata(payload.len().saturating_sub(null_pos + 3) as u64)?;
+                        self.icc_chunks.push((payload[null_pos + 1], payload[null_pos + 3..].to_vec()));
                         Some(IccProfileSequenceInfo {
                             chunk_sequence: payload[null_pos + 1],
                             total_chunks: payload[null_pos + 2],
@@ -458,11 +555,13 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                 JpegLsMarker::COM => {
                     let len = self.read_u16()?;
                     let payload_len = len.saturating_sub(2) as usize;
+                    self.limits.reserve_metadata(payload_len as u64)?;
                     let mut text_bytes = Vec::with_capacity(payload_len);
                     for _ in 0..payload_len {
                         text_bytes.push(self.read_u8()?);
                     }
                     let text = String::from_utf8_lossy(&text_bytes).into_owned();
+                    self.metadata.push_text("Comment", text.clone());
                     self.sections.push(JpegLsSectionInfo {
                         start_offset: marker_start,
                         data: JpegLsSectionData::Com(JpegLsComData { length: len, text }),
@@ -477,11 +576,11 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
 
         let frame = match self.frame.as_ref() {
             Some(f) => f.clone(),
-            None => return Err(crate::utils::error::VexelError::Custom("No SOF marker found".into())),
+            None => return Err(VexelError::missing(ImageFormat::JpegLs, "No SOF marker found")),
         };
 
         if self.scans.is_empty() {
-            return Err(crate::utils::error::VexelError::Custom("No SOS marker found".into()));
+            return Err(VexelError::missing(ImageFormat::JpegLs, "No SOS marker found"));
         }
 
         let width = frame.width as usize;
@@ -497,7 +596,8 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
         let mut scans_alpha: Option<i32> = None;
 
         let pixel_data: Vec<u16> = if scans.len() > 1 {
-            let mut planes: Vec<(Vec<u16>, usize, usize)> = Vec::with_capacity(scans.len());
+            let scan_count = scans.len();
+            let mut planes: Vec<(Vec<u16>, usize, usize)> = Vec::with_capacity(scan_count);
 
             for (scan, data) in scans {
                 let alpha = if scan.alpha > 1 { scan.alpha } else { frame.alpha };
@@ -571,8 +671,9 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                     reset,
                     scan.restart_interval,
                     stats_alpha,
-                );
+                )?;
                 planes.push((plane, plane_w, plane_h));
+                progress::rows_share(0, planes.len() as u64, scan_count as u64, height as u32)?;
             }
 
             let num_planes = planes.len();
@@ -662,7 +763,7 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                         &mut br, &mut state, width, height, components,
                         alpha, near, lossy, limit, qbpp, reset,
                         restart_interval, stats_alpha,
-                    )
+                    )?
                 }
                 InterleaveMode::Line => {
                     let sampling: Vec<(u8, u8)> = frame.components.iter()
@@ -672,14 +773,14 @@ impl<R: Read + Seek> JpegLsDecoder<R> {
                         &mut br, &mut state, width, height, components,
                         alpha, near, lossy, limit, qbpp, reset, &sampling,
                         restart_interval, stats_alpha,
-                    )
+                    )?
                 }
                 InterleaveMode::Sample => {
                     decode_sample_interleaved(
                         &mut br, &mut state, width, height, components,
                         alpha, near, lossy, limit, qbpp, reset,
                         restart_interval, stats_alpha,
-                    )
+                    )?
                 }
             }
         };
@@ -768,7 +869,7 @@ fn decode_single_plane(
     reset: i32,
     restart_interval: usize,
     stats_alpha: i32,
-) -> Vec<u16> {
+) -> VexelResult<Vec<u16>> {
     let buf_size = width + 3;
     let mut prev_line = vec![0i32; buf_size];
     let mut curr_line = vec![0i32; buf_size];
@@ -802,9 +903,10 @@ fn decode_single_plane(
 
         std::mem::swap(&mut prev_line, &mut curr_line);
         rows_in_interval += 1;
+        progress::check_cancelled()?;
     }
 
-    result
+    Ok(result)
 }
 
 fn decode_plane_interleaved(
@@ -821,7 +923,7 @@ fn decode_plane_interleaved(
     reset: i32,
     restart_interval: usize,
     stats_alpha: i32,
-) -> Vec<u16> {
+) -> VexelResult<Vec<u16>> {
     let mut planes: Vec<Vec<u16>> = (0..components)
         .map(|_| Vec::with_capacity(width * height))
         .collect();
@@ -838,7 +940,7 @@ fn decode_plane_interleaved(
         let mut curr_line = vec![0i32; buf_size];
         let mut rows_in_interval = 0usize;
 
-        for _row in 0..height {
+        for row in 0..height {
             if restart_interval > 0 && rows_in_interval == restart_interval {
                 br.consume_restart_marker();
                 prev_line.iter_mut().for_each(|v| *v = 0);
@@ -862,6 +964,9 @@ fn decode_plane_interleaved(
 
             std::mem::swap(&mut prev_line, &mut curr_line);
             rows_in_interval += 1;
+
+            let completed = comp * height + row + 1;
+            progress::rows_share(0, completed as u64, (components * height) as u64, height as u32)?;
         }
     }
 
@@ -872,7 +977,7 @@ fn decode_plane_interleaved(
         }
     }
 
-    result
+    Ok(result)
 }
 
 fn decode_line_interleaved(
@@ -890,7 +995,7 @@ fn decode_line_interleaved(
     sampling: &[(u8, u8)],
     restart_interval: usize,
     stats_alpha: i32,
-) -> Vec<u16> {
+) -> VexelResult<Vec<u16>> {
     let max_val = alpha - 1;
     let scale = 65535 / max_val;
 
@@ -903,7 +1008,7 @@ fn decode_line_interleaved(
         let mut result = Vec::with_capacity(width * height * components);
         let mut rows_in_interval = 0usize;
 
-        for _row in 0..height {
+        for row in 0..height {
             if restart_interval > 0 && rows_in_interval == restart_interval {
                 br.consume_restart_marker();
                 for comp in 0..components {
@@ -937,9 +1042,10 @@ fn decode_line_interleaved(
             }
 
             rows_in_interval += 1;
+            progress::rows(0, row as u32 + 1, height as u32)?;
         }
 
-        return result;
+        return Ok(result);
     }
 
     let max_h = sampling.iter().map(|&(h, _)| h as usize).max().unwrap_or(1);
@@ -964,7 +1070,7 @@ fn decode_line_interleaved(
         0
     };
 
-    for _ in 0..num_groups {
+    for group in 0..num_groups {
         if interval_in_groups > 0 && groups_in_interval == interval_in_groups {
             br.consume_restart_marker();
             for comp in 0..components {
@@ -999,6 +1105,7 @@ fn decode_line_interleaved(
         }
 
         groups_in_interval += 1;
+        progress::rows_share(0, group as u64 + 1, num_groups as u64, height as u32)?;
     }
 
     let mut result = Vec::with_capacity(width * height * components);
@@ -1019,7 +1126,7 @@ fn decode_line_interleaved(
         }
     }
 
-    result
+    Ok(result)
 }
 
 fn decode_sample_interleaved(
@@ -1036,7 +1143,7 @@ fn decode_sample_interleaved(
     reset: i32,
     restart_interval: usize,
     stats_alpha: i32,
-) -> Vec<u16> {
+) -> VexelResult<Vec<u16>> {
     let total_samples = width * components;
     let buf_size = total_samples + components * 3;
     let mut result = Vec::with_capacity(width * height * components);
@@ -1048,7 +1155,7 @@ fn decode_sample_interleaved(
     let mut curr_line = vec![0i32; buf_size];
     let mut rows_in_interval = 0usize;
 
-    for _row in 0..height {
+    for row in 0..height {
         if restart_interval > 0 && rows_in_interval == restart_interval {
             br.consume_restart_marker();
             prev_line.iter_mut().for_each(|v| *v = 0);
@@ -1077,9 +1184,10 @@ fn decode_sample_interleaved(
 
         std::mem::swap(&mut prev_line, &mut curr_line);
         rows_in_interval += 1;
+        progress::rows(0, row as u32 + 1, height as u32)?;
     }
 
-    result
+    Ok(result)
 }
 
 fn predict(ra: i32, rb: i32, rc: i32) -> i32 {
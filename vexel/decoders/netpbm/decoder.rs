@@ -1,9 +1,13 @@
 use crate::bitreader::BitReader;
+use crate::utils::color_transform::{CmykTransform, ColorTarget};
+use crate::utils::convert;
 use crate::utils::error::{VexelError, VexelResult};
-use crate::utils::image::{ImageFrame, PixelFormat};
+use crate::utils::image::{ImageFormat, ImageFrame, PixelFormat};
 use crate::utils::info::NetpbmInfo;
-use crate::{Image, Limits, PixelData, log_warn};
-use std::io::{Cursor, Read, Seek};
+use crate::utils::report::{DecodeWarning, WarningKind};
+use crate::{Image, ImageHeader, Limits, PixelData, log_warn};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::sync::Arc;
 
 use super::simd;
 use super::types::{NetpbmFormat, NetpbmHeaderData, NetpbmPixelDataInfo, NetpbmSectionData, NetpbmSectionInfo, TupleType};
@@ -20,6 +24,7 @@ pub struct NetPbmDecoder<R: Read + Seek> {
     reader: BitReader<R>,
     lookahead: Option<u8>,
     sections: Vec<NetpbmSectionInfo>,
+    color_target: ColorTarget,
 }
 
 impl<R: Read + Seek> NetPbmDecoder<R> {
@@ -36,6 +41,7 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
             reader: BitReader::new(reader),
             lookahead: None,
             sections: Vec::new(),
+            color_target: ColorTarget::Original,
         }
     }
 
@@ -43,6 +49,12 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
         self.limits = limits;
     }
 
+    /// CMYK PAM images are converted with the built-in press model when a target other than
+    /// [`ColorTarget::Original`] is set, otherwise with the plain CMYK formula.
+    pub fn set_color_target(&mut self, target: ColorTarget) {
+        self.color_target = target;
+    }
+
 
     pub fn get_info(&self) -> NetpbmInfo {
         NetpbmInfo {
@@ -50,6 +62,10 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
         }
     }
 
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn scale_to_8bit(value: u32, max_value: u32) -> u8 {
         ((value as f32 * 255.0 / max_value as f32).round() as u32).min(255) as u8
     }
@@ -105,7 +121,8 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                     number = match number.checked_mul(10).and_then(|n| n.checked_add((byte - b'0') as u32)) {
                         Some(n) => n,
                         None => {
-                            log_warn!("Number is too large: {} + {}", number, (byte - b'0') as u32);
+                            let message = format!("Number is too large: {} + {}", number, (byte - b'0') as u32);
+                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                             number
                         }
                     };
@@ -175,7 +192,7 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
             0x5036 => NetpbmFormat::P6,
             0x5037 => NetpbmFormat::P7,
             _ => {
-                log_warn!("Invalid magic number: {}", magick);
+                DecodeWarning::new(WarningKind::CorruptData, format!("Invalid magic number: {}", magick)).report();
                 NetpbmFormat::P6
             }
         };
@@ -222,12 +239,14 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                 self.max_value = self.read_decimal(first)?;
 
                 if self.max_value == 0 {
-                    log_warn!("Invalid MAXVAL value: {}", self.max_value);
+                    let message = format!("Invalid MAXVAL value: {}", self.max_value);
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                     self.max_value = 255;
                 }
 
                 if self.max_value > 65535 {
-                    log_warn!("Invalid MAXVAL value: {}", self.max_value);
+                    let message = format!("Invalid MAXVAL value: {}", self.max_value);
+                    DecodeWarning::new(WarningKind::CorruptData, message).report();
                     self.max_value = 65535;
                 }
             }
@@ -248,25 +267,29 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                 "ENDHDR" => break,
                 "WIDTH" => {
                     self.width = value.parse::<u32>().or_else(|_| {
-                        log_warn!("Invalid WIDTH value: {}", value);
+                        let message = format!("Invalid WIDTH value: {}", value);
+                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                         Ok::<u32, VexelError>(0)
                     })?
                 }
                 "HEIGHT" => {
                     self.height = value.parse::<u32>().or_else(|_| {
-                        log_warn!("Invalid HEIGHT value: {}", value);
+                        let message = format!("Invalid HEIGHT value: {}", value);
+                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                         Ok::<u32, VexelError>(0)
                     })?
                 }
                 "DEPTH" => {
                     self.depth = value.parse::<u8>().or_else(|_| {
-                        log_warn!("Invalid DEPTH value: {}", value);
+                        let message = format!("Invalid DEPTH value: {}", value);
+                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                         Ok::<u8, VexelError>(3)
                     })?
                 }
                 "MAXVAL" => {
                     self.max_value = value.parse().or_else(|_| {
-                        log_warn!("Invalid MAXVAL value: {}", value);
+                        let message = format!("Invalid MAXVAL value: {}", value);
+                        DecodeWarning::new(WarningKind::CorruptData, message).report();
                         Ok::<u32, VexelError>(255)
                     })?
                 }
@@ -327,7 +350,7 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
             let value = match self.read_ascii_number() {
                 Ok(v) => v.clamp(0, self.max_value),
                 Err(e) => {
-                    log_warn!("Error reading ASCII number: {:?}", e);
+                    DecodeWarning::recovered_from(&e, format!("Error reading ASCII number: {}", e)).report();
                     0
                 }
             };
@@ -342,7 +365,7 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
         let mut samples = Vec::with_capacity(count);
         for _ in 0..count {
             let v = self.read_ascii_number().map(|v| v.clamp(0, max_value)).unwrap_or_else(|e| {
-                log_warn!("Error reading ASCII number: {:?}", e);
+                DecodeWarning::recovered_from(&e, format!("Error reading ASCII number: {}", e)).report();
                 0
             });
             samples.push(v);
@@ -414,6 +437,20 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
         Ok(PixelData::RGB16(image_data))
     }
 
+    /// The built-in press model is used when a colour target is set, PAM has no ICC profiles.
+    fn cmyk_transform(&self) -> Option<Arc<CmykTransform>> {
+        (self.color_target != ColorTarget::Original).then(|| CmykTransform::cached(&[]))
+    }
+
+    /// Converts ink amounts in `[0, 1]` to RGB in `[0, 1]`.
+    fn cmyk_to_rgb(cmyk: [f32; 4], transform: Option<&CmykTransform>) -> [f32; 3] {
+        let [c, m, y, k] = cmyk;
+        match transform {
+            Some(transform) => transform.to_rgb(cmyk.map(f64::from)).map(|v| v as f32),
+            None => [(1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)],
+        }
+    }
+
     fn decode_pam(&self, data: &[u8]) -> VexelResult<PixelData> {
         let depth = self.depth;
         let max_value = self.max_value;
@@ -494,6 +531,7 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
 
             (Some(TupleType::CMYK), 4) => {
                 let mv = max_value as f32;
+                let transform = self.cmyk_transform();
                 if !is_16bit {
                     let mut image_data = vec![0u8; pixel_count * 3];
                     for (i, chunk) in data.chunks_exact(4).enumerate() {
@@ -501,9 +539,10 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                         let m = chunk[1] as f32 / mv;
                         let y = chunk[2] as f32 / mv;
                         let k = chunk[3] as f32 / mv;
-                        image_data[i * 3] = ((1.0 - c) * (1.0 - k) * 255.0).round() as u8;
-                        image_data[i * 3 + 1] = ((1.0 - m) * (1.0 - k) * 255.0).round() as u8;
-                        image_data[i * 3 + 2] = ((1.0 - y) * (1.0 - k) * 255.0).round() as u8;
+                        let [r, g, b] = Self::cmyk_to_rgb([c, m, y, k], transform.as_deref());
+                        image_data[i * 3] = (r * 255.0).round() as u8;
+                        image_data[i * 3 + 1] = (g * 255.0).round() as u8;
+                        image_data[i * 3 + 2] = (b * 255.0).round() as u8;
                     }
                     Ok(PixelData::RGB8(image_data))
                 } else {
@@ -514,9 +553,10 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                         let m = reader.read_u16().unwrap_or(0) as f32 / mv;
                         let y = reader.read_u16().unwrap_or(0) as f32 / mv;
                         let k = reader.read_u16().unwrap_or(0) as f32 / mv;
-                        image_data[i * 3] = ((1.0 - c) * (1.0 - k) * 65535.0).round() as u16;
-                        image_data[i * 3 + 1] = ((1.0 - m) * (1.0 - k) * 65535.0).round() as u16;
-                        image_data[i * 3 + 2] = ((1.0 - y) * (1.0 - k) * 65535.0).round() as u16;
+                        let [r, g, b] = Self::cmyk_to_rgb([c, m, y, k], transform.as_deref());
+                        image_data[i * 3] = (r * 65535.0).round() as u16;
+                        image_data[i * 3 + 1] = (g * 65535.0).round() as u16;
+                        image_data[i * 3 + 2] = (b * 65535.0).round() as u16;
                     }
                     Ok(PixelData::RGB16(image_data))
                 }
@@ -524,6 +564,7 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
 
             (Some(TupleType::CMYKAlpha), 5) => {
                 let mv = max_value as f32;
+                let transform = self.cmyk_transform();
                 if !is_16bit {
                     let mut image_data = vec![0u8; pixel_count * 4];
                     for (i, chunk) in data.chunks_exact(5).enumerate() {
@@ -532,9 +573,10 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                         let y = chunk[2] as f32 / mv;
                         let k = chunk[3] as f32 / mv;
                         let a = chunk[4];
-                        image_data[i * 4] = ((1.0 - c) * (1.0 - k) * 255.0).round() as u8;
-                        image_data[i * 4 + 1] = ((1.0 - m) * (1.0 - k) * 255.0).round() as u8;
-                        image_data[i * 4 + 2] = ((1.0 - y) * (1.0 - k) * 255.0).round() as u8;
+                        let [r, g, b] = Self::cmyk_to_rgb([c, m, y, k], transform.as_deref());
+                        image_data[i * 4] = (r * 255.0).round() as u8;
+                        image_data[i * 4 + 1] = (g * 255.0).round() as u8;
+                        image_data[i * 4 + 2] = (b * 255.0).round() as u8;
                         image_data[i * 4 + 3] = Self::scale_to_8bit(a as u32, max_value);
                     }
                     Ok(PixelData::RGBA8(image_data))
@@ -547,9 +589,10 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                         let y = reader.read_u16().unwrap_or(0) as f32 / mv;
                         let k = reader.read_u16().unwrap_or(0) as f32 / mv;
                         let a = reader.read_u16().unwrap_or(0);
-                        image_data[i * 4] = ((1.0 - c) * (1.0 - k) * 65535.0).round() as u16;
-                        image_data[i * 4 + 1] = ((1.0 - m) * (1.0 - k) * 65535.0).round() as u16;
-                        image_data[i * 4 + 2] = ((1.0 - y) * (1.0 - k) * 65535.0).round() as u16;
+                        let [r, g, b] = Self::cmyk_to_rgb([c, m, y, k], transform.as_deref());
+                        image_data[i * 4] = (r * 65535.0).round() as u16;
+                        image_data[i * 4 + 1] = (g * 65535.0).round() as u16;
+                        image_data[i * 4 + 2] = (b * 65535.0).round() as u16;
                         image_data[i * 4 + 3] = Self::scale_to_16bit(a as u32, max_value);
                     }
                     Ok(PixelData::RGBA16(image_data))
@@ -557,11 +600,12 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
             }
 
             _ => {
-                log_warn!(
+                let message = format!(
                     "Incorrect tuple type / depth combination: {:?}, {}. Inferring layout from depth.",
                     self.tuple_type,
-                    self.depth
+                    self.depth,
                 );
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
 
                 let depth = depth as usize;
 
@@ -678,7 +722,8 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
                 self.decode_pam(&data)
             }
             None => {
-                log_warn!("Format not set before decoding, assuming binary pixmap (P6)");
+                let message = "Format not set before decoding, assuming binary pixmap (P6)";
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 let byte_count = self.width as usize * self.height as usize * 3 * bytes_per_sample;
                 let data = self.read_binary_frame_data(byte_count)?;
                 self.decode_binary_pixmap(&data)
@@ -706,6 +751,212 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
         }
     }
 
+    fn frame_pixel_format(&self) -> PixelFormat {
+        let is_16bit = self.max_value > 255;
+
+        match &self.format {
+            Some(NetpbmFormat::P1) | Some(NetpbmFormat::P4) => PixelFormat::L1,
+            Some(NetpbmFormat::P2) | Some(NetpbmFormat::P5) => {
+                if is_16bit {
+                    PixelFormat::L16
+                } else {
+                    PixelFormat::L8
+                }
+            }
+            Some(NetpbmFormat::P7) => {
+                let depth = if self.depth == 0 { 3 } else { self.depth };
+                let is_16bit = self.max_value.min(65535) > 255;
+                let pick = |low: PixelFormat, high: PixelFormat| if is_16bit { high } else { low };
+
+                match (&self.tuple_type, depth) {
+                    (Some(TupleType::BlackAndWhite), 1) => PixelFormat::L1,
+                    (Some(TupleType::Grayscale), 1) => pick(PixelFormat::L8, PixelFormat::L16),
+                    (Some(TupleType::RGB), 3) => pick(PixelFormat::RGB8, PixelFormat::RGB16),
+                    (Some(TupleType::BlackAndWhiteAlpha), 2) => PixelFormat::LA8,
+                    (Some(TupleType::GrayscaleAlpha), 2) => pick(PixelFormat::LA8, PixelFormat::LA16),
+                    (Some(TupleType::RGBAlpha), 4) => pick(PixelFormat::RGBA8, PixelFormat::RGBA16),
+                    (Some(TupleType::CMYK), 4) => pick(PixelFormat::RGB8, PixelFormat::RGB16),
+                    (Some(TupleType::CMYKAlpha), 5) => pick(PixelFormat::RGBA8, PixelFormat::RGBA16),
+                    (_, 1) => pick(PixelFormat::L8, PixelFormat::L16),
+                    (_, 2) => pick(PixelFormat::LA8, PixelFormat::LA16),
+                    (_, 4) => pick(PixelFormat::RGBA8, PixelFormat::RGBA16),
+                    _ => pick(PixelFormat::RGB8, PixelFormat::RGB16),
+                }
+            }
+            _ => {
+                if is_16bit {
+                    PixelFormat::RGB16
+                } else {
+                    PixelFormat::RGB8
+                }
+            }
+        }
+    }
+
+    fn skip_frame_data(&mut self) -> VexelResult<()> {
+        let bytes_per_sample = if self.max_value > 255 { 2u64 } else { 1u64 };
+        let pixel_count = self.width as u64 * self.height as u64;
+
+        let byte_count = match &self.format {
+            Some(NetpbmFormat::P1) | Some(NetpbmFormat::P2) | Some(NetpbmFormat::P3) => return self.skip_to_next_header(),
+            Some(NetpbmFormat::P4) => (self.width as u64).div_ceil(8) * self.height as u64,
+            Some(NetpbmFormat::P5) => pixel_count * bytes_per_sample,
+            Some(NetpbmFormat::P7) => {
+                let depth = if self.depth == 0 { 3 } else { self.depth } as u64;
+                let max_value = if self.max_value == 0 { 255 } else { self.max_value };
+                let bps = if max_value > 255 { 2u64 } else { 1u64 };
+                pixel_count * depth * bps
+            }
+            _ => pixel_count * 3 * bytes_per_sample,
+        };
+
+        self.reader.seek(SeekFrom::Current(byte_count as i64))?;
+        Ok(())
+    }
+
+    /// Moves to the next `P` outside of a comment, where the header of the next frame starts.
+    /// Plain frames have no fixed size, so their samples are skimmed in blocks instead of parsed.
+    fn skip_to_next_header(&mut self) -> VexelResult<()> {
+        let mut in_comment = false;
+
+        if let Some(byte) = self.lookahead.take() {
+            if byte == b'P' {
+                self.lookahead = Some(byte);
+                return Ok(());
+            }
+            in_comment = byte == b'#';
+        }
+
+        loop {
+            let position = self.reader.stream_position()?;
+            let length = self.reader.bytes_left()?.min(64 * 1024) as usize;
+            if length == 0 {
+                return Ok(());
+            }
+
+            let block = self.reader.read_bytes(length)?;
+            for (i, &byte) in block.iter().enumerate() {
+                match byte {
+                    b'#' => in_comment = true,
+                    b'\n' | b'\r' => in_comment = false,
+                    b'P' if !in_comment => {
+                        self.reader.seek(SeekFrom::Start(position + i as u64))?;
+                        return Ok(());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        let mut header: Option<ImageHeader> = None;
+
+        loop {
+            self.reset_frame_state();
+
+            match self.read_header() {
+                Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
+                Err(e) => {
+                    if header.is_none() {
+                        return Err(e);
+                    }
+                    break;
+                }
+                Ok(_) => {}
+            }
+
+            match header.as_mut() {
+                Some(header) => header.frame_count += 1,
+                None => {
+                    header = Some(ImageHeader {
+                        width: self.width,
+                        height: self.height,
+                        pixel_format: self.frame_pixel_format(),
+                        frame_count: 1,
+                        is_animated: false,
+                    });
+                }
+            }
+
+            if self.skip_frame_data().is_err() || !self.has_more_data() {
+                break;
+            }
+        }
+
+        header.ok_or_else(|| VexelError::missing(ImageFormat::Unknown, "No header found"))
+    }
+
+    /// Reads the first frame of a binary graymap or pixmap (P5, P6) a row at a time and converts
+    /// each row straight into `buffer`, so the frame is never held as a whole. Returns `None`
+    /// without writing anything for the other variants, which are only decoded as a whole.
+    pub fn decode_into(&mut self, buffer: &mut [u8], stride: usize, format: &PixelFormat) -> VexelResult<Option<ImageHeader>> {
+        self.reset_frame_state();
+        self.read_header()?;
+
+        let channels = match self.format {
+            Some(NetpbmFormat::P5) => 1,
+            Some(NetpbmFormat::P6) => 3,
+            _ => return Ok(None),
+        };
+
+        let (width, height) = (self.width, self.height);
+        convert::check_buffer(buffer.len(), stride, width, height, format)?;
+
+        let samples = width as usize * channels;
+        let is_16bit = self.max_value > 255;
+        let mut raw = vec![0u8; if is_16bit { samples * 2 } else { samples }];
+        let mut row = match (channels, is_16bit) {
+            (1, false) => PixelData::L8(vec![0; samples]),
+            (1, true) => PixelData::L16(vec![0; samples]),
+            (_, false) => PixelData::RGB8(vec![0; samples]),
+            (_, true) => PixelData::RGB16(vec![0; samples]),
+        };
+
+        let pixel_data_start = self.reader.stream_position().unwrap_or(0);
+        let mut truncated = false;
+
+        for y in 0..height as usize {
+            if !truncated && self.reader.read_exact(&mut raw).is_err() {
+                let message = format!("Pixel data ends at row {} of {}, filling the rest with zeros", y, height);
+                DecodeWarning::new(WarningKind::TruncatedData, message)
+                    .in_frame(0)
+                    .with_synthesized_pixels((height as u64 - y as u64) * width as u64)
+                    .report();
+                truncated = true;
+            }
+            if truncated {
+                raw.fill(0);
+            }
+
+            match &mut row {
+                PixelData::L8(pixels) | PixelData::RGB8(pixels) => simd::scale_u8(&raw, pixels, self.max_value as u8),
+                PixelData::L16(pixels) | PixelData::RGB16(pixels) => {
+                    simd::scale_u16_be(&raw, pixels, self.max_value as u16)
+                }
+                _ => {}
+            }
+
+            convert::write_pixels(&row, width, 1, &mut buffer[y * stride..], stride, format);
+        }
+
+        let pixel_data_end = self.reader.stream_position().unwrap_or(pixel_data_start);
+        self.sections.push(NetpbmSectionInfo {
+            start_offset: pixel_data_start,
+            data: NetpbmSectionData::PixelData(NetpbmPixelDataInfo {
+                length: pixel_data_end.saturating_sub(pixel_data_start),
+            }),
+        });
+
+        Ok(Some(ImageHeader {
+            width,
+            height,
+            pixel_format: row.pixel_format(),
+            frame_count: 1,
+            is_animated: false,
+        }))
+    }
+
     pub fn decode(&mut self) -> VexelResult<Image> {
         let mut frames: Vec<ImageFrame> = Vec::new();
         let mut first_width = 0u32;
@@ -717,9 +968,9 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
 
             match self.read_header() {
                 Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
-                Err(_) => {
+                Err(e) => {
                     if frames.is_empty() {
-                        return Err(VexelError::Custom("Error reading header".to_string()));
+                        return Err(e);
                     }
                     break;
                 }
@@ -731,9 +982,9 @@ impl<R: Read + Seek> NetPbmDecoder<R> {
 
             let mut pixel_data = match self.read_and_decode_frame() {
                 Ok(data) => data,
-                Err(_) => {
+                Err(e) => {
                     if frames.is_empty() {
-                        return Err(VexelError::Custom("Error decoding image".to_string()));
+                        return Err(e);
                     }
                     break;
                 }
@@ -1,17 +1,37 @@
 use crate::utils::deflate::ZlibDecoder;
 use crate::utils::error::{VexelError, VexelResult};
-use crate::{ImageFrame, PixelData};
+use crate::utils::progress;
+use crate::{ImageFormat, ImageFrame, Limits, PixelData};
 use super::types::{ColorType, FctlChunk, PngFrame, TransparencyData};
 use super::pixels::PixelDecoder;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct AnimationDecoder {
     width: u32,
     height: u32,
+    canvas: PixelData,
+    restore_canvas: Option<PixelData>,
+    prev_dispose_op: u8,
+    prev_fctl: Option<FctlChunk>,
+    /// Bounds the inflation of each frame's fdAT data.
+    limits: Limits,
 }
 
 impl AnimationDecoder {
-    pub fn new(width: u32, height: u32) -> Self {
-        Self { width, height }
+    pub fn new(width: u32, height: u32, limits: Limits) -> Self {
+        Self {
+            width,
+            height,
+            canvas: PixelData::RGBA8(vec![0; (width * height * 4) as usize]),
+            restore_canvas: None,
+            prev_dispose_op: 0,
+            prev_fctl: None,
+            limits,
+        }
+    }
+
+    fn blank(&self) -> PixelData {
+        PixelData::RGBA8(vec![0; (self.width * self.height * 4) as usize])
     }
 
     fn compose_frame(
@@ -75,34 +95,18 @@ impl AnimationDecoder {
         Ok(output)
     }
 
-    pub fn decode_apng_frames(
-        &mut self,
-        frames: &[PngFrame],
-        bit_depth: u8,
-        color_type: ColorType,
-        interlace: bool,
-        palette: Option<Vec<[u8; 3]>>,
-        transparency: Option<TransparencyData>,
-    ) -> VexelResult<Vec<ImageFrame>> {
+    /// Checks that there is at least one frame with data and that all frames fit on the canvas.
+    pub fn validate_frames(&self, frames: &[PngFrame]) -> VexelResult<()> {
         if frames
             .iter()
             .filter(|f| f.fctl_info.width > 0 && f.fctl_info.height > 0 && !f.fdat.is_empty())
             .count()
             == 0
         {
-            return Err(VexelError::Custom("No valid frames found".into()));
+            return Err(VexelError::missing(ImageFormat::Png, "No valid frames found"));
         }
 
-        let decoded_pixels = self.pre_decode_frames(frames, bit_depth, color_type, interlace, &palette, &transparency);
-
-        let blank = PixelData::RGBA8(vec![0; (self.width * self.height * 4) as usize]);
-        let mut canvas = blank.clone();
-        let mut output_frames: Vec<ImageFrame> = Vec::with_capacity(frames.len());
-        let mut restore_canvas: Option<PixelData> = None;
-        let mut prev_dispose_op: u8 = 0;
-        let mut prev_fctl: Option<&FctlChunk> = None;
-
-        for (frame, pixels) in frames.iter().zip(decoded_pixels.into_iter()) {
+        for frame in frames {
             let fctl = &frame.fctl_info;
 
             if fctl.width == 0
@@ -110,56 +114,85 @@ impl AnimationDecoder {
                 || fctl.x_offset + fctl.width > self.width
                 || fctl.y_offset + fctl.height > self.height
             {
-                return Err(VexelError::Custom("Invalid frame dimensions".into()));
+                return Err(VexelError::corrupt(ImageFormat::Png, "Invalid frame dimensions"));
             }
+        }
 
-            if prev_dispose_op == 2 {
-                canvas = match restore_canvas.take() {
-                    Some(r) => r,
-                    None => blank.clone(),
-                };
-            } else if prev_dispose_op == 1 {
-                if let Some(pf) = prev_fctl {
-                    let row_bytes = pf.width as usize * 4;
-                    let data = canvas.as_bytes_mut();
-                    for y in 0..pf.height {
-                        let row_start = ((y + pf.y_offset) * self.width + pf.x_offset) as usize * 4;
-                        if row_start + row_bytes <= data.len() {
-                            data[row_start..row_start + row_bytes].fill(0);
-                        }
-                    }
-                }
-            }
+        Ok(())
+    }
 
-            if fctl.dispose_op == 2 {
-                restore_canvas = Some(canvas.clone());
-            } else {
-                restore_canvas = None;
+    pub fn decode_apng_frames(
+        &mut self,
+        frames: &[PngFrame],
+        bit_depth: u8,
+        color_type: ColorType,
+        interlace: bool,
+        palette: Option<Vec<[u8; 3]>>,
+        transparency: Option<TransparencyData>,
+    ) -> VexelResult<Vec<ImageFrame>> {
+        self.validate_frames(frames)?;
+
+        let decoded_pixels = self.pre_decode_frames(frames, bit_depth, color_type, interlace, &palette, &transparency)?;
+
+        let mut output_frames: Vec<ImageFrame> = Vec::with_capacity(frames.len());
+
+        for (frame, pixels) in frames.iter().zip(decoded_pixels.into_iter()) {
+            if let Some(image_frame) = self.compose_next(&frame.fctl_info, pixels)? {
+                output_frames.push(image_frame);
             }
+        }
 
-            let pixels = match pixels {
-                Some(p) => p,
-                None => continue,
+        Ok(output_frames)
+    }
+
+    /// Applies the disposal of the previous frame and draws the next one onto the canvas.
+    /// Frames whose pixels could not be decoded are skipped and return `None`.
+    pub fn compose_next(&mut self, fctl: &FctlChunk, pixels: Option<PixelData>) -> VexelResult<Option<ImageFrame>> {
+        if self.prev_dispose_op == 2 {
+            self.canvas = match self.restore_canvas.take() {
+                Some(r) => r,
+                None => self.blank(),
             };
+        } else if self.prev_dispose_op == 1 {
+            if let Some(pf) = &self.prev_fctl {
+                let row_bytes = pf.width as usize * 4;
+                let data = self.canvas.as_bytes_mut();
+                for y in 0..pf.height {
+                    let row_start = ((y + pf.y_offset) * self.width + pf.x_offset) as usize * 4;
+                    if row_start + row_bytes <= data.len() {
+                        data[row_start..row_start + row_bytes].fill(0);
+                    }
+                }
+            }
+        }
 
-            canvas = self.compose_frame(pixels, fctl, canvas)?;
-
-            prev_dispose_op = fctl.dispose_op;
-            prev_fctl = Some(fctl);
-
-            output_frames.push(ImageFrame::new(
-                self.width,
-                self.height,
-                canvas.clone(),
-                if fctl.delay_den == 0 {
-                    fctl.delay_num as u32 * 10
-                } else {
-                    (fctl.delay_num as f32 / fctl.delay_den as f32 * 1000.0).round() as u32
-                },
-            ));
+        if fctl.dispose_op == 2 {
+            self.restore_canvas = Some(self.canvas.clone());
+        } else {
+            self.restore_canvas = None;
         }
 
-        Ok(output_frames)
+        let pixels = match pixels {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        let canvas = std::mem::replace(&mut self.canvas, PixelData::RGBA8(Vec::new()));
+        self.canvas = self.compose_frame(pixels, fctl, canvas)?;
+
+        self.prev_dispose_op = fctl.dispose_op;
+        self.prev_fctl = Some(fctl.clone());
+
+        Ok(Some(ImageFrame::new(
+            self.width,
+            self.height,
+            self.canvas.clone(),
+            if fctl.delay_den == 0 {
+                fctl.delay_num as u32 * 10
+            } else {
+                (fctl.delay_num as f32 / fctl.delay_den as f32 * 1000.0).round() as u32
+            },
+        )))
     }
 
     fn pre_decode_frames(
@@ -170,52 +203,49 @@ impl AnimationDecoder {
         interlace: bool,
         palette: &Option<Vec<[u8; 3]>>,
         transparency: &Option<TransparencyData>,
-    ) -> Vec<Option<PixelData>> {
+    ) -> VexelResult<Vec<Option<PixelData>>> {
+        let completed = AtomicUsize::new(0);
+        let decode_frame = |frame: &PngFrame| -> VexelResult<Option<PixelData>> {
+            let fctl = &frame.fctl_info;
+            let pixels = decode_frame_pixels(
+                &frame.fdat,
+                bit_depth,
+                color_type,
+                fctl.width,
+                fctl.height,
+                interlace,
+                palette,
+                transparency,
+                &mut self.limits.clone(),
+            )?;
+            progress::frames(completed.fetch_add(1, Ordering::Relaxed) + 1, frames.len())?;
+
+            Ok(pixels)
+        };
+
         #[cfg(feature = "rayon")]
         {
+            use crate::utils::report;
             use rayon::prelude::*;
 
+            let sink = report::current_sink();
+            let hooks = progress::current_hooks();
             frames
                 .par_iter()
                 .map(|frame| {
-                    let fctl = &frame.fctl_info;
-                    decode_frame_pixels(
-                        &frame.fdat,
-                        bit_depth,
-                        color_type,
-                        fctl.width,
-                        fctl.height,
-                        interlace,
-                        palette,
-                        transparency,
-                    )
+                    report::with_sink(sink.clone(), || progress::with_hooks(hooks.clone(), || decode_frame(frame)))
                 })
                 .collect()
         }
 
         #[cfg(not(feature = "rayon"))]
         {
-            frames
-                .iter()
-                .map(|frame| {
-                    let fctl = &frame.fctl_info;
-                    decode_frame_pixels(
-                        &frame.fdat,
-                        bit_depth,
-                        color_type,
-                        fctl.width,
-                        fctl.height,
-                        interlace,
-                        palette,
-                        transparency,
-                    )
-                })
-                .collect()
+            frames.iter().map(decode_frame).collect()
         }
     }
 }
 
-fn decode_frame_pixels(
+pub fn decode_frame_pixels(
     fdat: &[u8],
     bit_depth: u8,
     color_type: ColorType,
@@ -224,7 +254,8 @@ fn decode_frame_pixels(
     interlace: bool,
     palette: &Option<Vec<[u8; 3]>>,
     transparency: &Option<TransparencyData>,
-) -> Option<PixelData> {
+    limits: &mut Limits,
+) -> VexelResult<Option<PixelData>> {
     let decoder = PixelDecoder::new(
         bit_depth,
         color_type,
@@ -234,11 +265,15 @@ fn decode_frame_pixels(
         transparency.clone(),
     );
 
-    let decompressed = ZlibDecoder::from_bytes(fdat.to_vec()).decode();
-    let frame_pixels = decoder.deinterlace_scan_lines(&decompressed, width, height).ok()?;
-    let mut pixels = decoder.decode_pixels_by_type(frame_pixels).ok()?;
+    let decompressed = ZlibDecoder::from_bytes(fdat.to_vec()).decode_reserved(limits)?;
+    let frame_pixels = decoder.deinterlace_scan_lines(&decompressed, width, height, None).ok();
+    limits.free_usize(decompressed.len());
+
+    let Some(mut pixels) = frame_pixels.and_then(|p| decoder.decode_pixels_by_type(p).ok()) else {
+        return Ok(None);
+    };
     pixels.correct_pixels(width, height);
-    Some(pixels)
+    Ok(Some(pixels))
 }
 
 impl PixelDecoder {
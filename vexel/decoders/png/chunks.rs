@@ -1,9 +1,11 @@
 use crate::bitreader::BitReader;
-use crate::log_warn;
 use crate::utils::deflate::ZlibDecoder;
-use crate::utils::error::VexelResult;
+use crate::utils::error::{VexelError, VexelResult};
 use crate::utils::icc::ICCProfile;
+use crate::utils::report::{DecodeWarning, WarningKind};
+use crate::Limits;
 use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 use super::types::*;
 
 pub struct ChunkReader;
@@ -26,7 +28,8 @@ impl ChunkReader {
         let bit_depth = match bit_depth {
             1 | 2 | 4 | 8 | 16 => bit_depth,
             _ => {
-                log_warn!("Invalid bit depth: {}", bit_depth);
+                let message = format!("Invalid bit depth: {}", bit_depth);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 8
             }
         };
@@ -38,7 +41,8 @@ impl ChunkReader {
             4 => ColorType::GrayscaleAlpha,
             6 => ColorType::RGBA,
             _ => {
-                log_warn!("Invalid color type: {}", color_type_raw);
+                let message = format!("Invalid color type: {}", color_type_raw);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 ColorType::RGB
             }
         };
@@ -47,7 +51,8 @@ impl ChunkReader {
             0 => CompressionMethod::Deflate,
             1 => CompressionMethod::None,
             _ => {
-                log_warn!("Invalid compression method: {}", compression_method_raw);
+                let message = format!("Invalid compression method: {}", compression_method_raw);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 CompressionMethod::None
             }
         };
@@ -56,7 +61,8 @@ impl ChunkReader {
             0 => true,
             1 => false,
             _ => {
-                log_warn!("Invalid filter method: {}", filter_method);
+                let message = format!("Invalid filter method: {}", filter_method);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 true
             }
         };
@@ -65,7 +71,8 @@ impl ChunkReader {
             0 => false,
             1 => true,
             _ => {
-                log_warn!("Invalid interlace method: {}", interlace_method);
+                let message = format!("Invalid interlace method: {}", interlace_method);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 false
             }
         };
@@ -99,7 +106,9 @@ impl ChunkReader {
         let length = length_u32;
 
         if length % 3 != 0 {
-            log_warn!("PLTE chunk length is not a multiple of 3");
+            DecodeWarning::new(WarningKind::CorruptData, "PLTE chunk length is not a multiple of 3")
+                .at_offset(start_offset)
+                .report();
         }
 
         let entries = length / 3;
@@ -130,20 +139,31 @@ impl ChunkReader {
         reader: &mut BitReader<R>,
         chunks: &mut Vec<PngChunkInfo>,
         idat_data: &mut Vec<u8>,
+        idat_ranges: &mut Vec<Range<usize>>,
         frames: &mut Vec<PngFrame>,
     ) -> VexelResult<()> {
         let (start_offset, length_u32, chunk_type_str, crc) = capture_chunk_info(reader)?;
 
-        let mut chunk_data = vec![0u8; length_u32 as usize];
-        reader.read_exact(&mut chunk_data)?;
-        reader.read_u32()?;
+        let data_start = start_offset + 8;
+        let data_end = data_start + length_u32 as u64;
 
-        if !frames.is_empty() {
-            let fctl_info = frames.last_mut().unwrap();
-            fctl_info.fdat.extend_from_slice(&chunk_data);
-        }
+        // The image data of a still image is inflated straight from an input that is in memory
+        if frames.is_empty() && borrow_chunk_data(reader, data_start, length_u32).is_some() {
+            idat_ranges.push(data_start as usize..data_end as usize);
+            reader.seek(SeekFrom::Start(data_end))?;
+            reader.read_u32()?;
+        } else {
+            let mut chunk_data = vec![0u8; length_u32 as usize];
+            reader.read_exact(&mut chunk_data)?;
+            reader.read_u32()?;
+
+            if !frames.is_empty() {
+                let fctl_info = frames.last_mut().unwrap();
+                fctl_info.fdat.extend_from_slice(&chunk_data);
+            }
 
-        idat_data.extend_from_slice(&chunk_data);
+            idat_data.extend_from_slice(&chunk_data);
+        }
 
         chunks.push(PngChunkInfo {
             start_offset,
@@ -161,7 +181,8 @@ impl ChunkReader {
     pub fn read_iccp<R: Read + Seek>(
         reader: &mut BitReader<R>,
         chunks: &mut Vec<PngChunkInfo>,
-    ) -> VexelResult<(String, ICCProfile)> {
+        limits: &mut Limits,
+    ) -> VexelResult<(String, Vec<u8>, ICCProfile)> {
         let (start_offset, length_u32, chunk_type_str, crc) = capture_chunk_info(reader)?;
 
         let length = length_u32;
@@ -177,14 +198,17 @@ impl ChunkReader {
             }
 
             if !((byte >= 32 && byte <= 126) || byte >= 161) {
-                log_warn!("Invalid character in iCCP profile name: {}, replacing with space", byte);
+                let message = format!("Invalid character in iCCP profile name: {}, replacing with space", byte);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 profile_name_bytes.push(32);
             } else {
                 profile_name_bytes.push(byte);
             }
 
             if profile_name_bytes.len() >= 79 {
-                log_warn!("iCCP profile name too long");
+                DecodeWarning::new(WarningKind::CorruptData, "iCCP profile name too long")
+                    .at_offset(start_offset)
+                    .report();
                 break;
             }
         }
@@ -193,7 +217,8 @@ impl ChunkReader {
         num_read += 1;
 
         if compression_method != 0 {
-            log_warn!("Invalid compression method in iCCP chunk: {}", compression_method);
+            let message = format!("Invalid compression method in iCCP chunk: {}", compression_method);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         let mut compressed_profile = Vec::new();
@@ -202,7 +227,7 @@ impl ChunkReader {
             num_read += 1;
         }
 
-        let profile_data = ZlibDecoder::from_bytes(compressed_profile).decode();
+        let profile_data = inflate_metadata(compressed_profile, limits)?;
 
         let icc = ICCProfile::new(&*profile_data)?;
         let profile_name = String::from_utf8_lossy(&profile_name_bytes).to_string();
@@ -219,7 +244,7 @@ impl ChunkReader {
             }),
         });
 
-        Ok((profile_name, icc))
+        Ok((profile_name, profile_data, icc))
     }
 
     pub fn read_iend<R: Read + Seek>(
@@ -255,14 +280,15 @@ impl ChunkReader {
             }
 
             if !((byte >= 32 && byte <= 126) || byte >= 161) {
-                log_warn!("Invalid character in sPLT name: {}, replacing with space", byte);
+                let message = format!("Invalid character in sPLT name: {}, replacing with space", byte);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 name.push(32);
             }
 
             name.push(byte);
 
             if name.len() >= 79 {
-                log_warn!("sPLT name too long");
+                DecodeWarning::new(WarningKind::CorruptData, "sPLT name too long").at_offset(start_offset).report();
                 break;
             }
         }
@@ -270,7 +296,8 @@ impl ChunkReader {
         let mut sample_depth = reader.read_u8()?;
 
         if sample_depth != 8 && sample_depth != 16 {
-            log_warn!("Invalid sPLT sample depth: {}, assuming 8", sample_depth);
+            let message = format!("Invalid sPLT sample depth: {}, assuming 8", sample_depth);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             sample_depth = 8;
         }
 
@@ -278,7 +305,7 @@ impl ChunkReader {
         let remaining_bytes = length - (name.len() as u32 + 2);
 
         if remaining_bytes % entry_size as u32 != 0 {
-            log_warn!("Invalid sPLT chunk length");
+            DecodeWarning::new(WarningKind::CorruptData, "Invalid sPLT chunk length").at_offset(start_offset).report();
         }
 
         let num_entries = remaining_bytes / entry_size as u32;
@@ -344,7 +371,8 @@ impl ChunkReader {
             2 => RenderingIntent::Saturation,
             3 => RenderingIntent::AbsoluteColorimetric,
             n => {
-                log_warn!("Invalid sRGB rendering intent: {}", n);
+                let message = format!("Invalid sRGB rendering intent: {}", n);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 RenderingIntent::Perceptual
             }
         };
@@ -442,7 +470,9 @@ impl ChunkReader {
         let trns_data = match color_type {
             ColorType::Grayscale => {
                 if length != 2 {
-                    log_warn!("Invalid tRNS length for grayscale");
+                    DecodeWarning::new(WarningKind::CorruptData, "Invalid tRNS length for grayscale")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 let value = reader.read_u16()?;
@@ -450,7 +480,9 @@ impl ChunkReader {
             }
             ColorType::RGB => {
                 if length != 6 {
-                    log_warn!("Invalid tRNS length for RGB");
+                    DecodeWarning::new(WarningKind::CorruptData, "Invalid tRNS length for RGB")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 let r = reader.read_u16()?;
@@ -461,7 +493,9 @@ impl ChunkReader {
             }
             ColorType::Indexed => {
                 if palette.is_none() {
-                    log_warn!("tRNS chunk before PLTE chunk");
+                    DecodeWarning::new(WarningKind::CorruptData, "tRNS chunk before PLTE chunk")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 let mut value = vec![0; length as usize];
@@ -470,7 +504,8 @@ impl ChunkReader {
                 TransparencyData::Palette(value)
             }
             _ => {
-                log_warn!("tRNS chunk not allowed for color type {:?}", color_type);
+                let message = format!("tRNS chunk not allowed for color type {:?}", color_type);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 TransparencyData::Grayscale(0)
             }
         };
@@ -502,7 +537,9 @@ impl ChunkReader {
         let background = match color_type {
             ColorType::Grayscale | ColorType::GrayscaleAlpha => {
                 if length != 2 {
-                    log_warn!("Invalid bKGD length for grayscale");
+                    DecodeWarning::new(WarningKind::CorruptData, "Invalid bKGD length for grayscale")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 let value = reader.read_u16()?;
@@ -510,7 +547,9 @@ impl ChunkReader {
             }
             ColorType::RGB | ColorType::RGBA => {
                 if length != 6 {
-                    log_warn!("Invalid bKGD length for RGB");
+                    DecodeWarning::new(WarningKind::CorruptData, "Invalid bKGD length for RGB")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 let r = reader.read_u16()?;
@@ -521,11 +560,15 @@ impl ChunkReader {
             }
             ColorType::Indexed => {
                 if length != 1 {
-                    log_warn!("Invalid bKGD length for indexed color");
+                    DecodeWarning::new(WarningKind::CorruptData, "Invalid bKGD length for indexed color")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 if palette.is_none() {
-                    log_warn!("bKGD chunk before PLTE chunk");
+                    DecodeWarning::new(WarningKind::CorruptData, "bKGD chunk before PLTE chunk")
+                        .at_offset(start_offset)
+                        .report();
                 }
 
                 let value = reader.read_u8()?;
@@ -598,7 +641,8 @@ impl ChunkReader {
         let sbit_data = match color_type {
             ColorType::Grayscale => {
                 if length != 1 {
-                    log_warn!("Invalid sBIT length for grayscale: {}", length);
+                    let message = format!("Invalid sBIT length for grayscale: {}", length);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 }
 
                 SignificantBits::Grayscale {
@@ -607,7 +651,8 @@ impl ChunkReader {
             }
             ColorType::RGB => {
                 if length != 3 {
-                    log_warn!("Invalid sBIT length for RGB: {}", length);
+                    let message = format!("Invalid sBIT length for RGB: {}", length);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 }
 
                 SignificantBits::RGB {
@@ -618,7 +663,8 @@ impl ChunkReader {
             }
             ColorType::Indexed => {
                 if length != 3 {
-                    log_warn!("Invalid sBIT length for indexed color: {}", length);
+                    let message = format!("Invalid sBIT length for indexed color: {}", length);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 }
 
                 SignificantBits::Indexed {
@@ -629,7 +675,8 @@ impl ChunkReader {
             }
             ColorType::GrayscaleAlpha => {
                 if length != 2 {
-                    log_warn!("Invalid sBIT length for grayscale alpha: {}", length);
+                    let message = format!("Invalid sBIT length for grayscale alpha: {}", length);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 }
 
                 SignificantBits::GrayscaleAlpha {
@@ -639,7 +686,8 @@ impl ChunkReader {
             }
             ColorType::RGBA => {
                 if length != 4 {
-                    log_warn!("Invalid sBIT length for RGBA: {}", length);
+                    let message = format!("Invalid sBIT length for RGBA: {}", length);
+                    DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
                 }
 
                 SignificantBits::RGBA {
@@ -673,7 +721,9 @@ impl ChunkReader {
         let (start_offset, length_u32, chunk_type_str, crc) = capture_chunk_info(reader)?;
 
         if palette.is_none() {
-            log_warn!("Encountered hIST chunk before PLTE chunk");
+            DecodeWarning::new(WarningKind::CorruptData, "Encountered hIST chunk before PLTE chunk")
+                .at_offset(start_offset)
+                .report();
             return Ok(Vec::new());
         }
 
@@ -681,7 +731,8 @@ impl ChunkReader {
         let length = length_u32;
 
         if length as usize != palette_len * 2 {
-            log_warn!("Invalid hIST length: {}, expected {}", length, palette_len * 2);
+            let message = format!("Invalid hIST length: {}, expected {}", length, palette_len * 2);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         let entry_count = (length / 2) as usize;
@@ -719,23 +770,28 @@ impl ChunkReader {
         let second = reader.read_u8()?;
 
         if month < 1 || month > 12 {
-            log_warn!("Invalid month in tIME chunk: {}", month);
+            let message = format!("Invalid month in tIME chunk: {}", month);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         if day < 1 || day > 31 {
-            log_warn!("Invalid day in tIME chunk: {}", day);
+            let message = format!("Invalid day in tIME chunk: {}", day);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         if hour > 23 {
-            log_warn!("Invalid hour in tIME chunk: {}", hour);
+            let message = format!("Invalid hour in tIME chunk: {}", hour);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         if minute > 59 {
-            log_warn!("Invalid minute in tIME chunk: {}", minute);
+            let message = format!("Invalid minute in tIME chunk: {}", minute);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         if second > 60 {
-            log_warn!("Invalid second in tIME chunk: {}", second);
+            let message = format!("Invalid second in tIME chunk: {}", second);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         let time = ImageTime {
@@ -764,8 +820,10 @@ impl ChunkReader {
     pub fn read_text<R: Read + Seek>(
         reader: &mut BitReader<R>,
         chunks: &mut Vec<PngChunkInfo>,
+        limits: &mut Limits,
     ) -> VexelResult<PngText> {
         let (start_offset, length_u32, chunk_type_str, crc) = capture_chunk_info(reader)?;
+        limits.reserve_metadata(length_u32 as u64)?;
 
         let length = length_u32;
         let mut chunk_data = vec![0u8; length as usize];
@@ -798,6 +856,7 @@ impl ChunkReader {
     pub fn read_ztxt<R: Read + Seek>(
         reader: &mut BitReader<R>,
         chunks: &mut Vec<PngChunkInfo>,
+        limits: &mut Limits,
     ) -> VexelResult<PngText> {
         let (start_offset, length_u32, chunk_type_str, crc) = capture_chunk_info(reader)?;
 
@@ -811,7 +870,8 @@ impl ChunkReader {
         let compression_method = chunk_data.get(after_null).copied().unwrap_or(0);
 
         if compression_method != 0 {
-            log_warn!("Unknown compression method in zTXt chunk: {}", compression_method);
+            let message = format!("Unknown compression method in zTXt chunk: {}", compression_method);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             let png_text = PngText::Compressed { keyword, text: String::new() };
             chunks.push(PngChunkInfo {
                 start_offset,
@@ -832,7 +892,7 @@ impl ChunkReader {
             Vec::new()
         };
 
-        let text_bytes = ZlibDecoder::from_bytes(compressed_text).decode();
+        let text_bytes = inflate_metadata(compressed_text, limits)?;
 
         let text = String::from_utf8_lossy(&text_bytes).to_string();
 
@@ -858,6 +918,7 @@ impl ChunkReader {
     pub fn read_itxt<R: Read + Seek>(
         reader: &mut BitReader<R>,
         chunks: &mut Vec<PngChunkInfo>,
+        limits: &mut Limits,
     ) -> VexelResult<PngText> {
         let (start_offset, length_u32, chunk_type_str, crc) = capture_chunk_info(reader)?;
 
@@ -887,12 +948,14 @@ impl ChunkReader {
 
         let text = if compression_flag == 1 {
             if compression_method != 0 {
-                log_warn!("Invalid compression method in iTXt chunk: {}", compression_method);
+                let message = format!("Invalid compression method in iTXt chunk: {}", compression_method);
+                DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             }
 
-            let decompressed = ZlibDecoder::from_bytes(text_bytes).decode();
+            let decompressed = inflate_metadata(text_bytes, limits)?;
             String::from_utf8_lossy(&decompressed).to_string()
         } else {
+            limits.reserve_metadata(text_bytes.len() as u64)?;
             String::from_utf8_lossy(&text_bytes).to_string()
         };
 
@@ -927,7 +990,9 @@ impl ChunkReader {
         let num_plays = reader.read_u32()?;
 
         if num_frames == 0 {
-            log_warn!("acTL chunk with zero frames");
+            DecodeWarning::new(WarningKind::CorruptData, "acTL chunk with zero frames")
+                .at_offset(start_offset)
+                .report();
         }
 
         let actl = ActlChunk { num_frames, num_plays };
@@ -965,32 +1030,41 @@ impl ChunkReader {
         let mut blend_op = reader.read_u8()?;
 
         if x_offset + width_frame > width {
-            log_warn!(format!(
+            let message = format!(
                 "fcTL width would overflow actual image width, clamping: x_offset={}, width={}, image_width={}",
-                x_offset, width_frame, width
-            ));
+                x_offset,
+                width_frame,
+                width,
+            );
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             width_frame = width.saturating_sub(x_offset);
         }
 
         if y_offset + height_frame > height {
-            log_warn!(format!(
+            let message = format!(
                 "fcTL height would overflow actual image height, clamping: y_offset={}, height={}, image_height={}",
-                y_offset, height_frame, height
-            ));
+                y_offset,
+                height_frame,
+                height,
+            );
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             height_frame = height.saturating_sub(y_offset);
         }
 
         if width_frame == 0 || height_frame == 0 {
-            log_warn!(format!("Invalid fcTL parameters: width={}, height={}", width_frame, height_frame));
+            let message = format!("Invalid fcTL parameters: width={}, height={}", width_frame, height_frame);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
         }
 
         if dispose_op > 2 {
-            log_warn!(format!("Invalid fcTL dispose_op: {}", dispose_op));
+            let message = format!("Invalid fcTL dispose_op: {}", dispose_op);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             dispose_op = 0;
         }
 
         if blend_op > 1 {
-            log_warn!(format!("Invalid fcTL blend_op: {}", blend_op));
+            let message = format!("Invalid fcTL blend_op: {}", blend_op);
+            DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
             blend_op = 0;
         }
 
@@ -1032,7 +1106,9 @@ impl ChunkReader {
         let sequence_number = reader.read_u32()?;
 
         if frames.is_empty() {
-            log_warn!("fdAT chunk without preceding fcTL chunk");
+            DecodeWarning::new(WarningKind::CorruptData, "fdAT chunk without preceding fcTL chunk")
+                .at_offset(start_offset)
+                .report();
             return Ok(());
         }
 
@@ -1059,6 +1135,42 @@ impl ChunkReader {
     }
 }
 
+/// Inflates compressed text or an ICC profile, reserving the result against the metadata and
+/// the allocation budget. Both budgets bound the inflation, so a decompression bomb stops early
+/// even without a metadata limit.
+fn inflate_metadata(data: Vec<u8>, limits: &mut Limits) -> VexelResult<Vec<u8>> {
+    let decoder = ZlibDecoder::from_bytes(data);
+
+    // Whichever budget is tighter bounds the inflation
+    let inflated = match limits.metadata_remaining() {
+        Some(remaining) if limits.alloc_remaining().is_none_or(|alloc| remaining < alloc) => {
+            let max_output = usize::try_from(remaining).unwrap_or(usize::MAX);
+            let inflated = decoder.decode_with_limit(max_output).ok_or_else(|| {
+                VexelError::LimitExceeded(format!(
+                    "compressed metadata inflates to more than the remaining budget of {} bytes",
+                    remaining
+                ))
+            })?;
+            limits.reserve_usize(inflated.len())?;
+            inflated
+        }
+        _ => decoder.decode_reserved(limits)?,
+    };
+
+    limits.reserve_metadata(inflated.len() as u64)?;
+
+    Ok(inflated)
+}
+
+/// Returns the `length` bytes of chunk data at `data_start` borrowed from the input, or `None` if
+/// the input isn't in memory or ends before the data does.
+pub(crate) fn borrow_chunk_data<R: Read + Seek>(reader: &BitReader<R>, data_start: u64, length: u32) -> Option<&[u8]> {
+    let start = usize::try_from(data_start).ok()?;
+    let end = start.checked_add(length as usize)?;
+
+    reader.source_bytes()?.get(start..end)
+}
+
 pub fn capture_chunk_info<R: Read + Seek>(
     reader: &mut BitReader<R>,
 ) -> VexelResult<(u64, u32, String, u32)> {
@@ -1077,21 +1189,32 @@ pub fn capture_chunk_info<R: Read + Seek>(
     reader.read_exact(&mut chunk_type)?;
     let chunk_type_str = String::from_utf8_lossy(&chunk_type).to_string();
 
-    let mut chunk_data = vec![0; length_u32 as usize];
-    reader.read_exact(&mut chunk_data)?;
+    let calculator = CrcCalculator::new();
+    let data_start = start_offset + 8;
+
+    // Check the data in place when the input is in memory
+    let borrowed_crc = borrow_chunk_data(reader, data_start, length_u32)
+        .map(|chunk_data| calculator.calculate_crc_two_parts(&chunk_type, chunk_data));
+    let calculated_crc = match borrowed_crc {
+        Some(calculated_crc) => {
+            reader.seek(SeekFrom::Start(data_start + length_u32 as u64))?;
+            calculated_crc
+        }
+        None => {
+            let mut chunk_data = vec![0; length_u32 as usize];
+            reader.read_exact(&mut chunk_data)?;
+            calculator.calculate_crc_two_parts(&chunk_type, &chunk_data)
+        }
+    };
 
     let crc = reader.read_u32()?;
 
-    let calculator = CrcCalculator::new();
-    let calculated_crc = calculator.calculate_crc_two_parts(&chunk_type, &chunk_data);
-
     if calculated_crc != crc {
-        log_warn!(
+        let message = format!(
             "CRC mismatch for chunk {}: expected 0x{:08x}, calculated 0x{:08x}",
-            chunk_type_str,
-            crc,
-            calculated_crc
+            chunk_type_str, crc, calculated_crc
         );
+        DecodeWarning::new(WarningKind::CorruptData, message).at_offset(start_offset).report();
     }
 
     reader.seek(SeekFrom::Start(start_offset + 8))?;
@@ -1,12 +1,17 @@
 use crate::bitreader::BitReader;
+use crate::utils::convert;
 use crate::utils::deflate::ZlibDecoder;
 use crate::utils::error::{VexelError, VexelResult};
 use crate::utils::icc::ICCProfile;
 use crate::utils::info::PngInfo;
-use crate::{Image, Limits, PixelData, PixelFormat, log_debug, log_warn};
+use crate::utils::metadata::{ImageMetadata, Resolution, ResolutionUnit};
+use crate::utils::progress;
+use crate::{Image, ImageFormat, ImageFrame, ImageHeader, Limits, PixelData, PixelFormat, log_debug};
+use crate::utils::report::{DecodeWarning, WarningKind};
 use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 
-use super::animation::AnimationDecoder;
+use super::animation::{decode_frame_pixels, AnimationDecoder};
 use super::chunks::{self, ChunkReader};
 use super::pixels::PixelDecoder;
 use super::types::*;
@@ -22,8 +27,10 @@ pub struct PngDecoder<R: Read + Seek> {
     interlace: bool,
     palette: Option<Vec<[u8; 3]>>,
     idat_data: Vec<u8>,
+    /// Positions of the IDAT chunk data in the input, when it is borrowed instead of copied.
+    idat_ranges: Vec<Range<usize>>,
     gamma: Option<f32>,
-    icc_profile: Option<(String, ICCProfile)>,
+    icc_profile: Option<(String, Vec<u8>, ICCProfile)>,
     transparency: Option<TransparencyData>,
     background: Option<BackgroundData>,
     rendering_intent: Option<RenderingIntent>,
@@ -37,6 +44,9 @@ pub struct PngDecoder<R: Read + Seek> {
     frames: Vec<PngFrame>,
     actl_info: Option<ActlChunk>,
     chunks: Vec<PngChunkInfo>,
+    /// Canvas state when decoding APNG frames one at a time.
+    animation: Option<AnimationDecoder>,
+    next_frame_index: usize,
     reader: BitReader<R>,
 }
 
@@ -53,6 +63,7 @@ impl<R: Read + Seek> PngDecoder<R> {
             interlace: false,
             palette: None,
             idat_data: Vec::new(),
+            idat_ranges: Vec::new(),
             gamma: None,
             icc_profile: None,
             transparency: None,
@@ -68,6 +79,8 @@ impl<R: Read + Seek> PngDecoder<R> {
             frames: Vec::new(),
             actl_info: None,
             chunks: Vec::new(),
+            animation: None,
+            next_frame_index: 0,
             reader: BitReader::new(reader),
         }
     }
@@ -76,6 +89,12 @@ impl<R: Read + Seek> PngDecoder<R> {
         self.limits = limits;
     }
 
+    /// Reads the image data of still images straight from the input instead of copying it, see
+    /// [`BitReader::set_source_bytes`].
+    pub fn set_source_bytes(&mut self, source_bytes: fn(&R) -> &[u8]) {
+        self.reader.set_source_bytes(source_bytes);
+    }
+
 
     pub fn get_info(&self) -> PngInfo {
         PngInfo {
@@ -83,7 +102,52 @@ impl<R: Read + Seek> PngDecoder<R> {
         }
     }
 
+    pub fn metadata(&self) -> ImageMetadata {
+        let mut metadata = ImageMetadata::default();
+
+        if let Some((_, data, profile)) = &self.icc_profile {
+            metadata.icc_data = Some(data.clone());
+            metadata.icc_profile = Some(profile.clone());
+        }
+
+        for text in &self.text_chunks {
+            match text {
+                PngText::International { keyword, text, .. } if keyword == "XML:com.adobe.xmp" => {
+                    metadata.xmp.push(text.clone());
+                }
+                PngText::Basic { keyword, text }
+                | PngText::Compressed { keyword, text }
+                | PngText::International { keyword, text, .. } => metadata.push_text(keyword, text.clone()),
+            }
+        }
+
+        metadata.resolution = self.physical_dimensions.as_ref().and_then(|phys| {
+            if phys.pixels_per_unit_x == 0 || phys.pixels_per_unit_y == 0 {
+                return None;
+            }
+
+            let unit = match phys.unit {
+                PhysicalUnit::Meter => ResolutionUnit::Meter,
+                PhysicalUnit::Unknown => ResolutionUnit::None,
+            };
+
+            Some(Resolution {
+                x: phys.pixels_per_unit_x as f64,
+                y: phys.pixels_per_unit_y as f64,
+                unit,
+            })
+        });
+
+        metadata
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn decode_pixels(&mut self) -> VexelResult<PixelData> {
+        let mut inflated_size = 0;
+
         if self.compression_method == CompressionMethod::Deflate {
             let bits_per_pixel = match self.color_type {
                 ColorType::Grayscale => self.bit_depth as u32,
@@ -94,8 +158,13 @@ impl<R: Read + Seek> PngDecoder<R> {
             };
             let bytes_per_row = ((bits_per_pixel * self.width + 7) / 8 + 1) as usize;
             let capacity = bytes_per_row * self.height as usize;
-            self.idat_data = ZlibDecoder::from_bytes(std::mem::take(&mut self.idat_data))
-                .decode_with_capacity(capacity);
+            // The inflated data is held until the pixels are unfiltered
+            self.limits.reserve_usize(capacity)?;
+            inflated_size = capacity;
+            self.idat_data = self.inflate_idat(capacity);
+        } else if !self.idat_ranges.is_empty() {
+            let stored = self.idat_segments().concat();
+            self.idat_data = stored;
         }
 
         let bits_per_pixel = match self.color_type {
@@ -110,21 +179,27 @@ impl<R: Read + Seek> PngDecoder<R> {
         if !self.interlace && bytes_per_row > 0 && self.idat_data.len() > 0 {
             let actual_rows = (self.idat_data.len() as u32) / bytes_per_row;
             if actual_rows < self.height {
-                log_warn!(
+                let message = format!(
                     "Decompressed data covers only {} rows, but IHDR declares {}. Adjusting height.",
                     actual_rows,
-                    self.height
+                    self.height,
                 );
+                let warning = DecodeWarning::new(WarningKind::TruncatedData, message).in_frame(0);
+                // The image data runs out in the last IDAT chunk
+                match self.chunks.iter().rfind(|chunk| chunk.chunk_type == "IDAT") {
+                    Some(chunk) => warning.at_offset(chunk.start_offset).report(),
+                    None => warning.report(),
+                }
                 self.height = actual_rows;
             }
         }
 
         if self.idat_data.is_empty() {
-            log_warn!(
-                "No decompressed pixel data for {}x{} image, filling with zeros",
-                self.width,
-                self.height
-            );
+            let message = format!("No decompressed pixel data for {}x{} image, filling with zeros", self.width, self.height);
+            DecodeWarning::new(WarningKind::TruncatedData, message)
+                .in_frame(0)
+                .with_synthesized_pixels(self.width as u64 * self.height as u64)
+                .report();
             return Ok(PixelData::RGBA8(vec![0u8; (self.width * self.height * 4) as usize]));
         }
 
@@ -137,7 +212,8 @@ impl<R: Read + Seek> PngDecoder<R> {
             self.transparency.clone(),
         );
 
-        let data = pixel_decoder.deinterlace_scan_lines(&self.idat_data, self.width, self.height)?;
+        let data = pixel_decoder.deinterlace_scan_lines(&self.idat_data, self.width, self.height, Some(0))?;
+        self.limits.free_usize(inflated_size);
 
         let mut pixels = match self.color_type {
             ColorType::Indexed => pixel_decoder.decode_indexed(data)?,
@@ -152,7 +228,117 @@ impl<R: Read + Seek> PngDecoder<R> {
         Ok(pixels)
     }
 
-    pub fn decode(&mut self) -> VexelResult<Image> {
+    /// Returns the IDAT chunk data, borrowed from the input where possible, followed by the data
+    /// that was copied.
+    fn idat_segments(&self) -> Vec<&[u8]> {
+        let source = self.reader.source_bytes().unwrap_or_default();
+        let borrowed = self.idat_ranges.iter().filter_map(|range| source.get(range.clone()));
+
+        borrowed.chain(std::iter::once(&self.idat_data[..])).collect()
+    }
+
+    /// Inflates the image data without joining the IDAT chunks first.
+    fn inflate_idat(&mut self, capacity: usize) -> Vec<u8> {
+        let inflated = ZlibDecoder::decode_segments(&self.idat_segments(), capacity);
+        self.idat_data = Vec::new();
+        self.idat_ranges.clear();
+
+        inflated
+    }
+
+    /// Returns the decoder for the pixels of a single-frame, non-interlaced image once the headers
+    /// have been read with [`probe`](Self::probe), to convert rows while the image data arrives.
+    pub(crate) fn row_decoder(&self) -> Option<PixelDecoder> {
+        if self.interlace || self.actl_info.is_some() || self.compression_method != CompressionMethod::Deflate {
+            return None;
+        }
+
+        Some(PixelDecoder::new(
+            self.bit_depth,
+            self.color_type,
+            self.width,
+            self.interlace,
+            self.palette.clone(),
+            self.transparency.clone(),
+        ))
+    }
+
+    /// Decodes a single-frame, non-interlaced image and converts each row straight into `buffer`
+    /// as soon as it is unfiltered, so the pixels are never held as a whole. Returns `None`
+    /// without writing anything for interlaced and animated images, which are only decoded as a
+    /// whole. Rows missing from truncated image data are filled with zeros.
+    pub fn decode_into(&mut self, buffer: &mut [u8], stride: usize, format: &PixelFormat) -> VexelResult<Option<ImageHeader>> {
+        self.read_chunks(false)?;
+
+        let Some(pixel_decoder) = self.row_decoder() else {
+            return Ok(None);
+        };
+
+        let (width, height) = (self.width, self.height);
+        if width == 0 || height == 0 {
+            return Err(VexelError::InvalidDimensions { width, height });
+        }
+        convert::check_buffer(buffer.len(), stride, width, height, format)?;
+
+        let filter_decoder = pixel_decoder.filter_decoder();
+        let row_length = (filter_decoder.get_bits_per_pixel() as usize * width as usize).div_ceil(8);
+        let scanline_length = row_length + 1;
+        let capacity = scanline_length * height as usize;
+        // The inflated data is held until the last row is written
+        self.limits.reserve_usize(capacity)?;
+        let scanlines = self.inflate_idat(capacity);
+
+        let complete_rows = (scanlines.len() / scanline_length).min(height as usize);
+        if complete_rows < height as usize {
+            let message = format!(
+                "Decompressed data covers only {} of {} rows, filling the rest with zeros",
+                complete_rows, height
+            );
+            let warning = DecodeWarning::new(WarningKind::TruncatedData, message)
+                .in_frame(0)
+                .with_synthesized_pixels((height as u64 - complete_rows as u64) * width as u64);
+            match self.chunks.iter().rfind(|chunk| chunk.chunk_type == "IDAT") {
+                Some(chunk) => warning.at_offset(chunk.start_offset).report(),
+                None => warning.report(),
+            }
+        }
+
+        let row_bytes = width as usize * format.bytes_per_pixel();
+        let mut prior_row = vec![0u8; row_length];
+        let mut pixel_format = PixelFormat::RGBA8;
+
+        for y in 0..height as usize {
+            let dst = &mut buffer[y * stride..];
+
+            if y < complete_rows {
+                let scanline = &scanlines[y * scanline_length..(y + 1) * scanline_length];
+                let mut row = vec![0u8; row_length];
+                filter_decoder.unfilter_row(scanline, &mut row, &prior_row);
+                prior_row.copy_from_slice(&row);
+
+                let mut pixels = pixel_decoder.decode_pixels_by_type(row)?;
+                pixels.correct_pixels(width, 1);
+                pixel_format = pixels.pixel_format();
+                convert::write_pixels(&pixels, width, 1, dst, stride, format);
+            } else {
+                dst[..row_bytes].fill(0);
+            }
+
+            progress::rows(0, y as u32 + 1, height)?;
+        }
+
+        self.limits.free_usize(capacity);
+
+        Ok(Some(ImageHeader {
+            width,
+            height,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        }))
+    }
+
+    fn read_chunks(&mut self, headers_only: bool) -> VexelResult<()> {
         let mut signature = vec![0u8; 8];
         self.reader.seek(SeekFrom::Start(0))?;
         self.reader.read_exact(&mut signature)?;
@@ -177,6 +363,10 @@ impl<R: Read + Seek> PngDecoder<R> {
                 Some(chunk) => {
                     log_debug!("Found chunk: {:?}", chunk);
 
+                    if headers_only && matches!(chunk, PngChunk::IDAT | PngChunk::IEND) {
+                        break;
+                    }
+
                     let chunk_data_start = self.reader.stream_position()?;
                     let chunk_length = {
                         self.reader.seek(SeekFrom::Start(chunk_data_start - 8))?;
@@ -223,6 +413,7 @@ impl<R: Read + Seek> PngDecoder<R> {
                                 &mut self.reader,
                                 &mut self.chunks,
                                 &mut self.idat_data,
+                                &mut self.idat_ranges,
                                 &mut self.frames,
                             )
                         }
@@ -239,19 +430,19 @@ impl<R: Read + Seek> PngDecoder<R> {
                             }
                         }
                         PngChunk::TEXT => {
-                            match ChunkReader::read_text(&mut self.reader, &mut self.chunks) {
+                            match ChunkReader::read_text(&mut self.reader, &mut self.chunks, &mut self.limits) {
                                 Ok(text) => { self.text_chunks.push(text); Ok(()) }
                                 Err(e) => Err(e),
                             }
                         }
                         PngChunk::ZTXT => {
-                            match ChunkReader::read_ztxt(&mut self.reader, &mut self.chunks) {
+                            match ChunkReader::read_ztxt(&mut self.reader, &mut self.chunks, &mut self.limits) {
                                 Ok(text) => { self.text_chunks.push(text); Ok(()) }
                                 Err(e) => Err(e),
                             }
                         }
                         PngChunk::ITXT => {
-                            match ChunkReader::read_itxt(&mut self.reader, &mut self.chunks) {
+                            match ChunkReader::read_itxt(&mut self.reader, &mut self.chunks, &mut self.limits) {
                                 Ok(text) => { self.text_chunks.push(text); Ok(()) }
                                 Err(e) => Err(e),
                             }
@@ -311,6 +502,8 @@ impl<R: Read + Seek> PngDecoder<R> {
                             }
                         }
                         PngChunk::FCTL => {
+                            self.limits.check_frames(self.frames.len() + 1)?;
+
                             match ChunkReader::read_fctl(&mut self.reader, &mut self.chunks, self.width, self.height) {
                                 Ok(fctl) => {
                                     self.frames.push(PngFrame {
@@ -324,7 +517,7 @@ impl<R: Read + Seek> PngDecoder<R> {
                         }
                         PngChunk::FDAT => ChunkReader::read_fdat(&mut self.reader, &mut self.chunks, &mut self.frames),
                         PngChunk::ICCP => {
-                            match ChunkReader::read_iccp(&mut self.reader, &mut self.chunks) {
+                            match ChunkReader::read_iccp(&mut self.reader, &mut self.chunks, &mut self.limits) {
                                 Ok(icc) => { self.icc_profile = Some(icc); Ok(()) }
                                 Err(e) => Err(e),
                             }
@@ -334,7 +527,13 @@ impl<R: Read + Seek> PngDecoder<R> {
 
                     match result {
                         Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
-                        Err(e) => log_warn!("Error reading chunk {:?}: {:?}", chunk, e),
+                        // Nothing can be decoded without the image header
+                        Err(e) if chunk == PngChunk::IHDR => return Err(e),
+                        Err(e) => {
+                            let e = e.with_format(&ImageFormat::Png);
+                            let message = format!("Error reading chunk {:?}: {}", chunk, e);
+                            DecodeWarning::recovered_from(&e, message).at_offset(chunk_data_start - 8).report();
+                        }
                         Ok(_) => {}
                     }
 
@@ -400,8 +599,122 @@ impl<R: Read + Seek> PngDecoder<R> {
             }
         }
 
+        Ok(())
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        self.read_chunks(true)?;
+
+        if self.width == 0 || self.height == 0 {
+            return Err(VexelError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        if let Some(actl) = &self.actl_info {
+            return Ok(ImageHeader {
+                width: self.width,
+                height: self.height,
+                pixel_format: PixelFormat::RGBA8,
+                frame_count: actl.num_frames,
+                is_animated: actl.num_frames > 1,
+            });
+        }
+
+        let is_16bit = self.bit_depth == 16;
+        let pixel_format = match (self.color_type, &self.transparency) {
+            (ColorType::Indexed, Some(TransparencyData::Palette(_))) => PixelFormat::RGBA8,
+            (ColorType::Indexed, _) => PixelFormat::RGB8,
+            (ColorType::Grayscale, Some(TransparencyData::Grayscale(_))) if is_16bit => PixelFormat::LA16,
+            (ColorType::Grayscale, Some(TransparencyData::Grayscale(_))) => PixelFormat::LA8,
+            (ColorType::Grayscale, _) if is_16bit => PixelFormat::L16,
+            (ColorType::Grayscale, _) => PixelFormat::L8,
+            (ColorType::RGB, Some(TransparencyData::RGB(..))) if is_16bit => PixelFormat::RGBA16,
+            (ColorType::RGB, Some(TransparencyData::RGB(..))) => PixelFormat::RGBA8,
+            (ColorType::RGB, _) if is_16bit => PixelFormat::RGB16,
+            (ColorType::RGB, _) => PixelFormat::RGB8,
+            (ColorType::GrayscaleAlpha, _) if is_16bit => PixelFormat::LA16,
+            (ColorType::GrayscaleAlpha, _) => PixelFormat::LA8,
+            (ColorType::RGBA, _) if is_16bit => PixelFormat::RGBA16,
+            (ColorType::RGBA, _) => PixelFormat::RGBA8,
+        };
+
+        Ok(ImageHeader {
+            width: self.width,
+            height: self.height,
+            pixel_format,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
+    /// Reads all chunks, so frames can be decoded one at a time with [`next_frame`](Self::next_frame).
+    /// Frame data is kept compressed until the frame is decoded.
+    pub fn start_frames(&mut self) -> VexelResult<()> {
+        self.read_chunks(false)?;
+
+        if self.actl_info.is_some() {
+            let animation = AnimationDecoder::new(self.width, self.height, self.limits.clone());
+
+            match animation.validate_frames(&self.frames) {
+                Ok(_) => self.animation = Some(animation),
+                Err(e) => {
+                    let e = e.with_format(&ImageFormat::Png);
+                    let message = format!("Error decoding APNG frames: {}", e);
+                    DecodeWarning::recovered_from(&e, message).in_frame(0).report();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes and composites the next APNG frame, or the image itself for static PNGs.
+    pub fn next_frame(&mut self) -> VexelResult<Option<ImageFrame>> {
+        if let Some(animation) = &mut self.animation {
+            while let Some(frame) = self.frames.get_mut(self.next_frame_index) {
+                self.next_frame_index += 1;
+
+                let fdat = std::mem::take(&mut frame.fdat);
+                let fctl = &frame.fctl_info;
+                let pixels = decode_frame_pixels(
+                    &fdat,
+                    self.bit_depth,
+                    self.color_type,
+                    fctl.width,
+                    fctl.height,
+                    self.interlace,
+                    &self.palette,
+                    &self.transparency,
+                    &mut self.limits,
+                )?;
+
+                progress::check_cancelled()?;
+
+                if let Some(image_frame) = animation.compose_next(fctl, pixels)? {
+                    return Ok(Some(image_frame));
+                }
+            }
+
+            return Ok(None);
+        }
+
+        if self.next_frame_index > 0 {
+            return Ok(None);
+        }
+
+        self.next_frame_index = 1;
+        let pixel_data = self.decode_pixels()?;
+
+        Ok(Some(ImageFrame::new(self.width, self.height, pixel_data, 0)))
+    }
+
+    pub fn decode(&mut self) -> VexelResult<Image> {
+        self.read_chunks(false)?;
+
         if self.actl_info.is_some() {
-            let mut anim_decoder = AnimationDecoder::new(self.width, self.height);
+            let mut anim_decoder = AnimationDecoder::new(self.width, self.height, self.limits.clone());
             let result = anim_decoder.decode_apng_frames(
                 &self.frames,
                 self.bit_depth,
@@ -411,10 +724,14 @@ impl<R: Read + Seek> PngDecoder<R> {
                 self.transparency.clone(),
             );
 
-            if let Ok(image_frames) = result {
-                return Ok(Image::new(self.width, self.height, PixelFormat::RGBA8, image_frames));
-            } else {
-                log_warn!("Error decoding APNG frames: {:?}", result);
+            match result {
+                Ok(image_frames) => return Ok(Image::new(self.width, self.height, PixelFormat::RGBA8, image_frames)),
+                Err(e @ (VexelError::Cancelled | VexelError::LimitExceeded(_))) => return Err(e),
+                Err(e) => {
+                    let e = e.with_format(&ImageFormat::Png);
+                    let message = format!("Error decoding APNG frames: {}", e);
+                    DecodeWarning::recovered_from(&e, message).in_frame(0).report();
+                }
             }
         }
 
@@ -1,5 +1,6 @@
-use crate::log_warn;
 use crate::utils::error::VexelResult;
+use crate::utils::progress;
+use crate::utils::report::{DecodeWarning, WarningKind};
 use super::filter_simd;
 use super::types::{FilterType, ColorType};
 
@@ -26,7 +27,8 @@ impl FilterDecoder {
     fn decode_sub_filter(&self, src: &[u8], dst: &mut [u8], bytes_per_pixel: usize) {
         let len = src.len();
         if dst.len() < len || bytes_per_pixel > len {
-            log_warn!("Invalid range for sub filter: {}", bytes_per_pixel);
+            let message = format!("Invalid range for sub filter: {}", bytes_per_pixel);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
             return;
         }
         dst[..bytes_per_pixel].copy_from_slice(&src[..bytes_per_pixel]);
@@ -38,7 +40,7 @@ impl FilterDecoder {
     fn decode_up_filter(&self, src: &[u8], dst: &mut [u8], prior: &[u8]) {
         let len = src.len();
         if dst.len() < len || prior.len() < len {
-            log_warn!("Invalid range for up filter");
+            DecodeWarning::new(WarningKind::CorruptData, "Invalid range for up filter").report();
             return;
         }
         for i in 0..len {
@@ -49,7 +51,8 @@ impl FilterDecoder {
     fn decode_average_filter(&self, src: &[u8], dst: &mut [u8], prior: &[u8], bytes_per_pixel: usize) {
         let len = src.len();
         if dst.len() < len || prior.len() < len || bytes_per_pixel > len {
-            log_warn!("Invalid range for average filter: {}", bytes_per_pixel);
+            let message = format!("Invalid range for average filter: {}", bytes_per_pixel);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
             return;
         }
         filter_simd::decode_average(src, dst, prior, bytes_per_pixel);
@@ -58,7 +61,8 @@ impl FilterDecoder {
     fn decode_paeth_filter(&self, src: &[u8], dst: &mut [u8], prior: &[u8], bytes_per_pixel: usize) {
         let len = src.len();
         if dst.len() < len || prior.len() < len || bytes_per_pixel > len {
-            log_warn!("Invalid range for paeth filter: {}", bytes_per_pixel);
+            let message = format!("Invalid range for paeth filter: {}", bytes_per_pixel);
+            DecodeWarning::new(WarningKind::CorruptData, message).report();
             return;
         }
         for i in 0..bytes_per_pixel {
@@ -72,10 +76,49 @@ impl FilterDecoder {
         }
     }
 
-    pub fn unfilter_scanlines(&self, data: &[u8], pass_width: u32) -> VexelResult<Vec<u8>> {
+    /// Reverses the filter of one scanline, which starts with its filter type, into `dst`.
+    /// `prior_row` is the unfiltered row above it, zeros for the first row.
+    pub(crate) fn unfilter_row(&self, scanline: &[u8], dst: &mut [u8], prior_row: &[u8]) {
+        let bytes_per_pixel = (self.get_bits_per_pixel() as usize).div_ceil(8);
+
+        let filter_type = match scanline[0] {
+            0 => FilterType::None,
+            1 => FilterType::Sub,
+            2 => FilterType::Up,
+            3 => FilterType::Average,
+            4 => FilterType::Paeth,
+            _ => {
+                DecodeWarning::new(WarningKind::CorruptData, format!("Invalid filter type: {}", scanline[0])).report();
+                FilterType::None
+            }
+        };
+
+        let filtered = &scanline[1..];
+
+        match filter_type {
+            FilterType::None => {
+                dst.copy_from_slice(filtered);
+            }
+            FilterType::Sub => {
+                self.decode_sub_filter(filtered, dst, bytes_per_pixel);
+            }
+            FilterType::Up => {
+                self.decode_up_filter(filtered, dst, prior_row);
+            }
+            FilterType::Average => {
+                self.decode_average_filter(filtered, dst, prior_row, bytes_per_pixel);
+            }
+            FilterType::Paeth => {
+                self.decode_paeth_filter(filtered, dst, prior_row, bytes_per_pixel);
+            }
+        }
+    }
+
+    /// Reverses the filters of each scanline. If `frame` is set, the rows are reported as the
+    /// progress of that frame.
+    pub fn unfilter_scanlines(&self, data: &[u8], pass_width: u32, frame: Option<usize>) -> VexelResult<Vec<u8>> {
         let bits_per_pixel = self.get_bits_per_pixel();
 
-        let bytes_per_pixel = (bits_per_pixel as usize + 7) / 8;
         let bytes_per_row = (pass_width as usize * bits_per_pixel as usize + 7) / 8;
         let scanline_bytes = 1 + bytes_per_row;
 
@@ -85,57 +128,29 @@ impl FilterDecoder {
 
         for (row_idx, scanline) in data.chunks(scanline_bytes).enumerate() {
             if scanline.len() < scanline_bytes {
-                log_warn!(
-                    "Invalid scanline length: {}, expected: {}",
-                    scanline.len(),
-                    scanline_bytes
-                );
+                let message = format!("Invalid scanline length: {}, expected: {}", scanline.len(), scanline_bytes);
+                DecodeWarning::new(WarningKind::TruncatedData, message).report();
                 unfiltered.truncate(row_idx * bytes_per_row);
                 break;
             }
 
-            let filter_type = match scanline[0] {
-                0 => FilterType::None,
-                1 => FilterType::Sub,
-                2 => FilterType::Up,
-                3 => FilterType::Average,
-                4 => FilterType::Paeth,
-                _ => {
-                    log_warn!("Invalid filter type: {}", scanline[0]);
-                    FilterType::None
-                }
-            };
-
-            let filtered = &scanline[1..];
             let dst_start = row_idx * bytes_per_row;
             let dst = &mut unfiltered[dst_start..dst_start + bytes_per_row];
-
-            match filter_type {
-                FilterType::None => {
-                    dst.copy_from_slice(filtered);
-                }
-                FilterType::Sub => {
-                    self.decode_sub_filter(filtered, dst, bytes_per_pixel);
-                }
-                FilterType::Up => {
-                    self.decode_up_filter(filtered, dst, &prior_row);
-                }
-                FilterType::Average => {
-                    self.decode_average_filter(filtered, dst, &prior_row, bytes_per_pixel);
-                }
-                FilterType::Paeth => {
-                    self.decode_paeth_filter(filtered, dst, &prior_row, bytes_per_pixel);
-                }
-            }
+            self.unfilter_row(scanline, dst, &prior_row);
 
             prior_row.copy_from_slice(dst);
+
+            match frame {
+                Some(frame) => progress::rows(frame, row_idx as u32 + 1, num_rows as u32)?,
+                None => progress::check_cancelled()?,
+            }
         }
 
         Ok(unfiltered)
     }
 }
 
-fn paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
+pub(crate) fn paeth_predictor(a: u8, b: u8, c: u8) -> u8 {
     let a = a as i16;
     let b = b as i16;
     let c = c as i16;
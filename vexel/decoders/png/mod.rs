@@ -5,6 +5,7 @@ pub mod decoder;
 pub mod filter_simd;
 pub mod filters;
 pub mod pixels;
+pub mod stream;
 pub mod types;
 
 pub use decoder::PngDecoder;
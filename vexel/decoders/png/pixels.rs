@@ -1,7 +1,8 @@
-use crate::log_warn;
 use crate::utils::error::VexelResult;
+use crate::utils::progress;
 use crate::utils::traits::SafeAccess;
 use crate::PixelData;
+use crate::utils::report::{DecodeWarning, WarningKind};
 use super::types::{ColorType, TransparencyData};
 use super::filters::FilterDecoder;
 
@@ -43,11 +44,16 @@ impl PixelDecoder {
         }
     }
 
+    /// Returns the decoder that reverses the filters of the scanlines.
+    pub(crate) fn filter_decoder(&self) -> FilterDecoder {
+        FilterDecoder::new(self.bit_depth, self.color_type)
+    }
+
     pub fn decode_indexed(&self, input: Vec<u8>) -> VexelResult<PixelData> {
         let palette = match &self.palette {
             Some(palette) => palette,
             None => {
-                log_warn!("No palette found for indexed color");
+                DecodeWarning::new(WarningKind::CorruptData, "No palette found for indexed color").report();
                 return Ok(PixelData::RGB8(Vec::new()));
             }
         };
@@ -224,10 +230,8 @@ impl PixelDecoder {
                 Ok(PixelData::LA16(output))
             }
             _ => {
-                log_warn!(
-                    "Invalid bit depth for grayscale alpha: {}, assuming 8 bits",
-                    self.bit_depth
-                );
+                let message = format!("Invalid bit depth for grayscale alpha: {}, assuming 8 bits", self.bit_depth);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
 
                 Ok(PixelData::LA8(input))
             }
@@ -279,7 +283,8 @@ impl PixelDecoder {
                 }
             }
             _ => {
-                log_warn!("Invalid bit depth for RGB color: {}, assuming 8 bits", self.bit_depth);
+                let message = format!("Invalid bit depth for RGB color: {}, assuming 8 bits", self.bit_depth);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 Ok(PixelData::RGB8(input))
             }
         }
@@ -300,17 +305,20 @@ impl PixelDecoder {
                 Ok(PixelData::RGBA16(output))
             }
             _ => {
-                log_warn!("Invalid bit depth for RGBA color: {}, assuming 8 bits", self.bit_depth);
+                let message = format!("Invalid bit depth for RGBA color: {}, assuming 8 bits", self.bit_depth);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
                 Ok(PixelData::RGBA8(input))
             }
         }
     }
 
-    pub fn deinterlace_scan_lines(&self, data: &[u8], width: u32, height: u32) -> VexelResult<Vec<u8>> {
-        let filter_decoder = FilterDecoder::new(self.bit_depth, self.color_type);
+    /// Unfilters and deinterlaces the scanlines. If `frame` is set, the progress of that frame is
+    /// reported, per row for non-interlaced images and once at the end for interlaced ones.
+    pub fn deinterlace_scan_lines(&self, data: &[u8], width: u32, height: u32, frame: Option<usize>) -> VexelResult<Vec<u8>> {
+        let filter_decoder = self.filter_decoder();
 
         if !self.interlace {
-            return filter_decoder.unfilter_scanlines(data, width);
+            return filter_decoder.unfilter_scanlines(data, width, frame);
         }
 
         const ADAM7_COL_START: [usize; 7] = [0, 4, 0, 2, 0, 1, 0];
@@ -339,12 +347,12 @@ impl PixelDecoder {
             let pass_size = (pass_bytes_per_row + 1) * pass_height;
 
             if data_offset + pass_size > data.len() {
-                log_warn!("Insufficient data for interlaced image");
+                DecodeWarning::new(WarningKind::TruncatedData, "Insufficient data for interlaced image").report();
                 break;
             }
 
             let pass_data = &data[data_offset..data_offset + pass_size];
-            let unfiltered = filter_decoder.unfilter_scanlines(pass_data, pass_width as u32)?;
+            let unfiltered = filter_decoder.unfilter_scanlines(pass_data, pass_width as u32, None)?;
             let mut unfiltered_idx = 0;
 
             for row in 0..pass_height {
@@ -367,7 +375,8 @@ impl PixelDecoder {
                         let in_bit_shift = (pixels_per_byte - 1 - (col % pixels_per_byte)) * bits_per_pixel as usize;
 
                         if in_byte_idx > unfiltered.len() {
-                            log_warn!("Invalid byte index: {} > {}", in_byte_idx, unfiltered.len());
+                            let message = format!("Invalid byte index: {} > {}", in_byte_idx, unfiltered.len());
+                            DecodeWarning::new(WarningKind::CorruptData, message).report();
                             continue;
                         }
 
@@ -406,6 +415,10 @@ impl PixelDecoder {
             data_offset += pass_size;
         }
 
+        if let Some(frame) = frame {
+            progress::rows(frame, height, height)?;
+        }
+
         Ok(output)
     }
 }
@@ -0,0 +1,156 @@
+use crate::utils::deflate::ZlibStream;
+use crate::utils::error::VexelResult;
+use crate::utils::stream::ScanProgress;
+use crate::PixelData;
+use std::io::Cursor;
+
+use super::decoder::PngDecoder;
+use super::filters::FilterDecoder;
+use super::pixels::PixelDecoder;
+use super::types::{get_chunk, PngChunk};
+
+const SIGNATURE_LENGTH: usize = 8;
+
+/// Finds complete chunks in a PNG that is still being received, and decodes the rows of
+/// non-interlaced images as their image data arrives.
+pub(crate) struct PngScanner {
+    position: usize,
+    idat: ZlibStream,
+    /// Set once the headers have been read, for images whose rows can be decoded as they arrive.
+    rows: Option<PngRows>,
+    headers_read: bool,
+    progress: ScanProgress,
+}
+
+/// Rows of a non-interlaced image decoded from the image data received so far.
+struct PngRows {
+    pixel_decoder: PixelDecoder,
+    filter_decoder: FilterDecoder,
+    width: u32,
+    height: u32,
+    /// Inflated scanlines that haven't been decoded yet.
+    scanlines: Vec<u8>,
+    /// The last unfiltered row, which the filter of the next one refers to.
+    prior_row: Vec<u8>,
+    decoded_rows: u32,
+}
+
+impl PngScanner {
+    pub(crate) fn new() -> Self {
+        Self {
+            position: SIGNATURE_LENGTH,
+            idat: ZlibStream::new(),
+            rows: None,
+            headers_read: false,
+            progress: ScanProgress::default(),
+        }
+    }
+
+    /// Walks the chunks received since the last call. Only complete chunks are consumed,
+    /// a partial chunk is picked up again once more data arrives.
+    pub(crate) fn scan(&mut self, data: &[u8]) -> ScanProgress {
+        while !self.progress.done && self.position + 8 <= data.len() {
+            let start = self.position;
+            let length = u32::from_be_bytes([data[start], data[start + 1], data[start + 2], data[start + 3]]) as usize;
+            let chunk_type = [data[start + 4], data[start + 5], data[start + 6], data[start + 7]];
+            let chunk = get_chunk(&chunk_type);
+
+            // Everything the pixel data depends on comes before the first IDAT
+            if matches!(chunk, Some(PngChunk::IDAT | PngChunk::IEND)) && !self.headers_read {
+                self.headers_read = true;
+                self.progress.header_complete = true;
+                self.rows = PngRows::new(&data[..start + 8]);
+            }
+
+            let end = match start.checked_add(12).and_then(|end| end.checked_add(length)) {
+                Some(end) if end <= data.len() => end,
+                Some(_) => break,
+                None => {
+                    self.progress.done = true;
+                    break;
+                }
+            };
+
+            match chunk {
+                Some(PngChunk::IDAT) => {
+                    self.idat.push(&data[start + 8..end - 4]);
+                    self.progress.units += 1;
+                }
+                Some(PngChunk::IEND) => self.progress.done = true,
+                _ => {}
+            }
+
+            self.position = end;
+        }
+
+        self.progress
+    }
+
+    /// Returns `true` if rows are decoded as they arrive, which is known once the headers have
+    /// been received. Interlaced and animated images aren't.
+    pub(crate) fn decodes_rows(&self) -> bool {
+        self.rows.is_some()
+    }
+
+    /// Inflates the image data received since the last call and decodes the rows it completes.
+    /// Returns the number of new rows and their pixels, if there are any.
+    pub(crate) fn decode_rows(&mut self) -> VexelResult<Option<(u32, PixelData)>> {
+        let Some(rows) = &mut self.rows else {
+            return Ok(None);
+        };
+
+        self.idat.inflate(&mut rows.scanlines);
+        rows.decode()
+    }
+}
+
+impl PngRows {
+    /// Reads the headers in `data`, which ends with the header of the first IDAT chunk.
+    fn new(data: &[u8]) -> Option<PngRows> {
+        let mut decoder = PngDecoder::new(Cursor::new(data));
+        let header = decoder.probe().ok()?;
+        let pixel_decoder = decoder.row_decoder()?;
+        let filter_decoder = pixel_decoder.filter_decoder();
+        let row_length = (filter_decoder.get_bits_per_pixel() as usize * header.width as usize).div_ceil(8);
+
+        Some(PngRows {
+            pixel_decoder,
+            filter_decoder,
+            width: header.width,
+            height: header.height,
+            scanlines: Vec::new(),
+            prior_row: vec![0; row_length],
+            decoded_rows: 0,
+        })
+    }
+
+    fn decode(&mut self) -> VexelResult<Option<(u32, PixelData)>> {
+        let row_length = self.prior_row.len();
+        let scanline_length = row_length + 1;
+        let count = (self.scanlines.len() / scanline_length).min((self.height - self.decoded_rows) as usize);
+
+        if self.decoded_rows == self.height {
+            // Anything after the last row is ignored, as in a full decode
+            self.scanlines.clear();
+        }
+
+        if count == 0 {
+            return Ok(None);
+        }
+
+        let mut unfiltered = vec![0u8; count * row_length];
+        let scanlines = self.scanlines.chunks_exact(scanline_length);
+        for (scanline, row) in scanlines.zip(unfiltered.chunks_exact_mut(row_length)) {
+            self.filter_decoder.unfilter_row(scanline, row, &self.prior_row);
+            self.prior_row.copy_from_slice(row);
+        }
+
+        self.scanlines.drain(..count * scanline_length);
+        self.decoded_rows += count as u32;
+
+        let mut pixels = self.pixel_decoder.decode_pixels_by_type(unfiltered)?;
+        pixels.correct_pixels(self.width, count as u32);
+
+        Ok(Some((count as u32, pixels)))
+    }
+}
@@ -1,7 +1,9 @@
 use crate::utils::bitreader::BitReader;
+use crate::utils::convert;
 use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::report::{DecodeWarning, WarningKind};
 use crate::utils::info::TgaInfo;
-use crate::{Image, Limits, PixelData, log_warn};
+use crate::{Image, ImageHeader, Limits, PixelData, PixelFormat};
 use std::io::{Read, Seek, SeekFrom};
 
 use super::types::{
@@ -39,6 +41,10 @@ impl<R: Read + Seek> TgaDecoder<R> {
         }
     }
 
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
     fn read_header(&mut self) -> VexelResult<TgaHeader> {
         Ok(TgaHeader {
             id_length: self.reader.read_u8()?,
@@ -155,7 +161,7 @@ impl<R: Read + Seek> TgaDecoder<R> {
                 [r, g, b, if has_alpha { a } else { 255 }]
             }
             bpp => {
-                log_warn!("Unsupported TGA palette BPP: {}", bpp);
+                DecodeWarning::new(WarningKind::Unsupported, format!("Unsupported TGA palette BPP: {}", bpp)).report();
                 [0, 0, 0, 255]
             }
         }
@@ -225,44 +231,46 @@ impl<R: Read + Seek> TgaDecoder<R> {
                 [r, g, b, if has_alpha { a } else { 255 }]
             }
             bpp => {
-                log_warn!("Unsupported TGA BPP: {}", bpp);
+                DecodeWarning::new(WarningKind::Unsupported, format!("Unsupported TGA BPP: {}", bpp)).report();
                 [0, 0, 0, 255]
             }
         }
     }
 
-    fn decode_image_data(
-        &mut self,
-        header: &TgaHeader,
-        palette: &[[u8; 4]],
-        has_alpha: bool,
-        is_rle: bool,
-    ) -> Vec<u8> {
+    /// Reads the pixels in file order and passes each row of RGBA8 pixels to `emit_row` with its
+    /// index in the file. Rows the data ends before are passed as zeros.
+    fn decode_image_data(&mut self, layout: &TgaLayout, mut emit_row: impl FnMut(usize, &mut [u8])) {
+        let header = &layout.header;
         let width = header.width as usize;
         let height = header.height as usize;
         let total_pixels = width * height;
         let image_type = header.image_type_raw & IMAGE_TYPE_MASK;
         let is_paletted = image_type == IMAGE_TYPE_PALETTED;
         let bpp = header.bpp;
+        let palette = &layout.palette;
+        let has_alpha = layout.has_alpha;
 
-        let mut pixels = vec![0u8; total_pixels * 4];
+        let mut row = vec![0u8; width * 4];
         let mut written = 0usize;
+        let mut column = 0usize;
 
         macro_rules! write_pixel {
             ($px:expr) => {
                 if written < total_pixels {
-                    let off = written * 4;
+                    let off = column * 4;
                     let px = $px;
-                    pixels[off] = px[0];
-                    pixels[off + 1] = px[1];
-                    pixels[off + 2] = px[2];
-                    pixels[off + 3] = px[3];
+                    row[off..off + 4].copy_from_slice(&px);
                     written += 1;
+                    column += 1;
+                    if column == width {
+                        emit_row(written / width - 1, &mut row);
+                        column = 0;
+                    }
                 }
             };
         }
 
-        if is_rle {
+        if layout.is_rle {
             'rle: while written < total_pixels {
                 let packet = match self.reader.read_u8() {
                     Ok(b) => b,
@@ -308,46 +316,57 @@ impl<R: Read + Seek> TgaDecoder<R> {
             }
         }
 
-        pixels
+        if written < total_pixels {
+            row[column * 4..].fill(0);
+            for y in written / width..height {
+                emit_row(y, &mut row);
+                row.fill(0);
+            }
+        }
     }
 
-    fn apply_orientation(pixels: &mut [u8], width: usize, height: usize, flags: u8) {
-        let flip_h = (flags & FLAG_ORIGIN_RIGHT) != 0;
-        let flip_v = (flags & FLAG_ORIGIN_TOP) == 0;
-
-        if flip_h {
-            let row_bytes = width * 4;
-            for y in 0..height {
-                let start = y * row_bytes;
-                let row = &mut pixels[start..start + row_bytes];
-                let mut l = 0;
-                let mut r = width.saturating_sub(1);
-                while l < r {
-                    row.swap(l * 4, r * 4);
-                    row.swap(l * 4 + 1, r * 4 + 1);
-                    row.swap(l * 4 + 2, r * 4 + 2);
-                    row.swap(l * 4 + 3, r * 4 + 3);
-                    l += 1;
-                    r -= 1;
+    /// Turns a row as stored in the file into the row that is displayed: mirrors it if the image
+    /// is stored right to left, and returns its index from the top.
+    fn orient_row(flags: u8, height: usize, y: usize, row: &mut [u8]) -> usize {
+        if (flags & FLAG_ORIGIN_RIGHT) != 0 {
+            let width = row.len() / 4;
+            for x in 0..width / 2 {
+                for c in 0..4 {
+                    row.swap(x * 4 + c, (width - 1 - x) * 4 + c);
                 }
             }
         }
 
-        if flip_v {
-            let stride = width * 4;
-            let mut top = 0;
-            let mut bot = height.saturating_sub(1);
-            while top < bot {
-                for i in 0..stride {
-                    pixels.swap(top * stride + i, bot * stride + i);
-                }
-                top += 1;
-                bot -= 1;
-            }
+        if (flags & FLAG_ORIGIN_TOP) == 0 {
+            height - 1 - y
+        } else {
+            y
         }
     }
 
-    pub fn decode(&mut self) -> VexelResult<Image> {
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        let header = self.read_header()?;
+
+        if header.width == 0 || header.height == 0 {
+            return Err(VexelError::InvalidDimensions {
+                width: header.width as u32,
+                height: header.height as u32,
+            });
+        }
+
+        self.limits.reserve_buffer(header.width as u32, header.height as u32, 4)?;
+
+        Ok(ImageHeader {
+            width: header.width as u32,
+            height: header.height as u32,
+            pixel_format: PixelFormat::RGBA8,
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
+    /// Reads everything before the pixel data and leaves the reader at its start.
+    fn read_layout(&mut self) -> VexelResult<TgaLayout> {
         self.sections.clear();
 
         let header = self.read_header()?;
@@ -409,8 +428,6 @@ impl<R: Read + Seek> TgaDecoder<R> {
             });
         }
 
-        self.limits.reserve_buffer(header.width as u32, header.height as u32, 4)?;
-
         let image_type = header.image_type_raw & IMAGE_TYPE_MASK;
         let is_rle = (header.image_type_raw & IMAGE_TYPE_FLAG_RLE) != 0;
         let alpha_bits = header.flags & FLAG_ALPHA_SIZE_MASK;
@@ -448,8 +465,26 @@ impl<R: Read + Seek> TgaDecoder<R> {
             Vec::new()
         };
 
+        Ok(TgaLayout {
+            header,
+            palette,
+            has_alpha,
+            is_rle,
+            footer: footer_info,
+        })
+    }
+
+    /// Reads the pixel data, passing each row to `emit_row` as it is displayed along with its
+    /// index from the top, then the extension area and footer.
+    fn read_image(&mut self, layout: TgaLayout, mut emit_row: impl FnMut(usize, &mut [u8])) {
+        let flags = layout.header.flags;
+        let height = layout.header.height as usize;
+
         let pixel_data_offset = self.reader.stream_position().unwrap_or(0);
-        let mut pixels = self.decode_image_data(&header, &palette, has_alpha, is_rle);
+        self.decode_image_data(&layout, |y, row| {
+            let y = Self::orient_row(flags, height, y, row);
+            emit_row(y, row);
+        });
         let pixel_data_end = self.reader.stream_position().unwrap_or(pixel_data_offset);
 
         self.sections.push(TgaSectionInfo {
@@ -459,7 +494,7 @@ impl<R: Read + Seek> TgaDecoder<R> {
             }),
         });
 
-        if let Some((footer_data, footer_offset)) = footer_info {
+        if let Some((footer_data, footer_offset)) = layout.footer {
             if footer_data.extension_area_offset != 0 {
                 let ext_offset = footer_data.extension_area_offset as u64;
                 if let Some(ext_area) = self.read_extension_area(ext_offset) {
@@ -474,12 +509,57 @@ impl<R: Read + Seek> TgaDecoder<R> {
                 data: TgaSectionData::Footer(footer_data),
             });
         }
+    }
+
+    pub fn decode(&mut self) -> VexelResult<Image> {
+        let layout = self.read_layout()?;
+        let (width, height) = (layout.header.width as u32, layout.header.height as u32);
 
-        Self::apply_orientation(&mut pixels, header.width as usize, header.height as usize, header.flags);
+        self.limits.reserve_buffer(width, height, 4)?;
+
+        let row_bytes = width as usize * 4;
+        let mut pixels = vec![0u8; row_bytes * height as usize];
+        self.read_image(layout, |y, row| {
+            pixels[y * row_bytes..(y + 1) * row_bytes].copy_from_slice(row);
+        });
 
         let mut pixel_data = PixelData::RGBA8(pixels);
-        pixel_data.correct_pixels(header.width as u32, header.height as u32);
+        pixel_data.correct_pixels(width, height);
 
-        Ok(Image::from_pixels(header.width as u32, header.height as u32, pixel_data))
+        Ok(Image::from_pixels(width, height, pixel_data))
     }
+
+    /// Decodes the image and converts each row straight into `buffer` as soon as it is read, so
+    /// the pixels are never held as a whole.
+    pub fn decode_into(&mut self, buffer: &mut [u8], stride: usize, format: &PixelFormat) -> VexelResult<Option<ImageHeader>> {
+        let layout = self.read_layout()?;
+        let (width, height) = (layout.header.width as u32, layout.header.height as u32);
+
+        convert::check_buffer(buffer.len(), stride, width, height, format)?;
+
+        let mut pixels = PixelData::RGBA8(vec![0u8; width as usize * 4]);
+        self.read_image(layout, |y, row| {
+            if let PixelData::RGBA8(pixels) = &mut pixels {
+                pixels.copy_from_slice(row);
+            }
+            convert::write_pixels(&pixels, width, 1, &mut buffer[y * stride..], stride, format);
+        });
+
+        Ok(Some(ImageHeader {
+            width,
+            height,
+            pixel_format: PixelFormat::RGBA8,
+            frame_count: 1,
+            is_animated: false,
+        }))
+    }
+}
+
+/// Everything about the image that is read before the pixel data.
+struct TgaLayout {
+    header: TgaHeader,
+    palette: Vec<[u8; 4]>,
+    has_alpha: bool,
+    is_rle: bool,
+    footer: Option<(TgaFooterData, u64)>,
 }
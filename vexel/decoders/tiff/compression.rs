@@ -1,4 +1,6 @@
 use crate::utils::deflate::ZlibDecoder;
+use crate::utils::error::VexelResult;
+use crate::Limits;
 
 const LZW_CLEAR_CODE: u16 = 256;
 const LZW_EOI_CODE: u16 = 257;
@@ -302,8 +304,13 @@ pub fn decompress_packbits(data: &[u8]) -> Vec<u8> {
     output
 }
 
-pub fn decompress_deflate(data: &[u8]) -> Vec<u8> {
-    ZlibDecoder::from_bytes(data.to_vec()).decode()
+/// Inflates a Deflate strip or tile, bounded by the remaining allocation budget. Like the
+/// compressed bytes in `read_chunk`, the output is checked against the budget but not held.
+pub fn decompress_deflate(data: &[u8], limits: &mut Limits) -> VexelResult<Vec<u8>> {
+    let output = ZlibDecoder::from_bytes(data.to_vec()).decode_reserved(limits)?;
+    limits.free_usize(output.len());
+
+    Ok(output)
 }
 
 fn sgilog_decode_row(bp: &[u8], offset: &mut usize, tp: &mut [u32], npixels: usize, shifts: &[u32]) {
@@ -1,27 +1,76 @@
 use crate::bitreader::BitReader;
 use crate::decoders::jpeg::decoder::JpegDecoder;
 use crate::decoders::png::decoder::PngDecoder;
+use crate::utils::color_transform::ColorTarget;
 use crate::utils::error::{VexelError, VexelResult};
-use crate::utils::image::ImageFrame;
+use crate::utils::exif::{self, ExifData, ExifEntry, ExifIfd, ExifReader, ExifValue, IfdType};
+use crate::utils::icc::ICCProfile;
+use crate::utils::image::{ImageFormat, ImageFrame, PixelData, Region};
+use crate::utils::info::TiffInfo;
+use crate::utils::metadata::{ImageMetadata, Orientation, Resolution};
+use crate::utils::progress;
+use crate::utils::report::{DecodeWarning, WarningKind};
 use crate::utils::types::ByteOrder;
-use crate::{Image, Limits, log_warn};
+use crate::{Image, ImageHeader, Limits, PixelFormat, log_debug};
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
 use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
 
 use super::compression::{
     apply_predictor_float, apply_predictor_horizontal, apply_predictor_horizontal_be, decompress_deflate,
     decompress_lzw, decompress_packbits, decompress_sgilog, decompress_sgilog24,
 };
 use super::pixels::PixelReader;
-use super::reader::{read_multiple_rationals, read_multiple_values, read_rational, read_single_value};
-use super::types::{Compression, Predictor, SampleFormat, TiffHeader};
+use super::reader::{read_multiple_rationals, read_multiple_values, read_rational, read_single_value, ValueField};
+use super::types::{
+    Compression, Predictor, SampleFormat, TiffChunkLayout, TiffFileHeaderData, TiffHeader, TiffIfdData, TiffIfdEntry,
+    PhotometricInterpretation, PlanarConfiguration, TiffSectionData, TiffSectionInfo, TiffTags,
+};
+
+/// The compressed bytes of a strip or tile, borrowed from the input when it is in memory.
+enum Chunk {
+    Borrowed(Range<usize>),
+    Owned(Vec<u8>),
+}
+
+impl Chunk {
+    fn bytes<'a, R: Read + Seek>(&'a self, reader: &'a BitReader<R>) -> &'a [u8] {
+        match self {
+            Chunk::Borrowed(range) => {
+                reader.source_bytes().and_then(|source| source.get(range.clone())).unwrap_or_default()
+            }
+            Chunk::Owned(data) => data,
+        }
+    }
+
+    fn into_vec<R: Read + Seek>(self, reader: &BitReader<R>) -> Vec<u8> {
+        match self {
+            Chunk::Borrowed(_) => self.bytes(reader).to_vec(),
+            Chunk::Owned(data) => data,
+        }
+    }
+}
 
 pub struct TiffDecoder<R: Read + Seek> {
     width: u32,
     height: u32,
     limits: Limits,
     byte_order: ByteOrder,
+    /// BigTIFF files use 8-byte offsets and counts
+    big_tiff: bool,
     header: TiffHeader,
     reader: BitReader<R>,
+    file_len: u64,
+    sections: Vec<TiffSectionInfo>,
+    /// State for decoding frames one at a time
+    next_ifd_offset: u64,
+    visited_ifds: HashSet<u64>,
+    pending_frames: VecDeque<ImageFrame>,
+    frames_read: usize,
+    /// Set by [`decode_region`](Self::decode_region), only this part of each page is decoded
+    region: Option<Region>,
+    color_target: ColorTarget,
 }
 
 impl<R: Read + Seek> TiffDecoder<R> {
@@ -31,8 +80,17 @@ impl<R: Read + Seek> TiffDecoder<R> {
             height: 0,
             limits: Limits::default(),
             byte_order: ByteOrder::LittleEndian,
+            big_tiff: false,
             header: TiffHeader::default(),
             reader: BitReader::new(reader),
+            file_len: 0,
+            sections: Vec::new(),
+            next_ifd_offset: 0,
+            visited_ifds: HashSet::new(),
+            pending_frames: VecDeque::new(),
+            frames_read: 0,
+            region: None,
+            color_target: ColorTarget::Original,
         }
     }
 
@@ -40,79 +98,240 @@ impl<R: Read + Seek> TiffDecoder<R> {
         self.limits = limits;
     }
 
+    /// Reads strips and tiles straight from the input instead of copying them, see
+    /// [`BitReader::set_source_bytes`].
+    pub fn set_source_bytes(&mut self, source_bytes: fn(&R) -> &[u8]) {
+        self.reader.set_source_bytes(source_bytes);
+    }
+
+    /// CMYK pages are converted through their ICC profile when a target other than
+    /// [`ColorTarget::Original`] is set, otherwise with the plain CMYK formula.
+    pub fn set_color_target(&mut self, target: ColorTarget) {
+        self.color_target = target;
+    }
+
+    pub fn get_info(&self) -> TiffInfo {
+        TiffInfo {
+            sections: self.sections.clone(),
+        }
+    }
+
+    /// Collects the metadata of the first IFD and the sections it links to.
+    pub fn metadata(&self) -> ImageMetadata {
+        let mut metadata = ImageMetadata::default();
+
+        let mut sections = self
+            .sections
+            .iter()
+            .skip_while(|section| !matches!(section.data, TiffSectionData::Ifd(_)));
+
+        let entries = match sections.next().map(|section| &section.data) {
+            Some(TiffSectionData::Ifd(ifd)) => &ifd.entries,
+            _ => return metadata,
+        };
+
+        let mut exif_ifd = None;
+        let mut gps_ifd = None;
+        let mut interop_ifd = None;
+
+        for section in sections {
+            match &section.data {
+                TiffSectionData::Ifd(_) => break,
+                TiffSectionData::ExifIfd(ifd) => exif_ifd = Some(ifd.clone()),
+                TiffSectionData::GpsIfd(ifd) => gps_ifd = Some(ifd.clone()),
+                TiffSectionData::InteropIfd(ifd) => interop_ifd = Some(ifd.clone()),
+                TiffSectionData::IccProfile(profile) => metadata.icc_profile = Some(profile.as_ref().clone()),
+                _ => {}
+            }
+        }
+
+        if let Some(entry) = find_entry(entries, TiffTags::InterColorProfile) {
+            if let ExifValue::Undefined(data) | ExifValue::Byte(data) = &entry.value {
+                metadata.icc_data = Some(data.clone());
+            }
+        }
+
+        if exif_ifd.is_some() || gps_ifd.is_some() {
+            let ifd0 = entries
+                .iter()
+                .map(|entry| ExifEntry {
+                    tag: entry.tag,
+                    tag_name: exif::tag_name(entry.tag, IfdType::Ifd0).map(|s| s.to_string()),
+                    value: entry.value.clone(),
+                })
+                .collect();
+
+            metadata.exif = Some(ExifData {
+                byte_order: self.byte_order,
+                ifd0: ExifIfd { entries: ifd0 },
+                exif_ifd,
+                gps_ifd,
+                interop_ifd,
+                ifd1: None,
+            });
+        }
+
+        if let Some(entry) = find_entry(entries, TiffTags::XMP) {
+            if let ExifValue::Undefined(data) | ExifValue::Byte(data) = &entry.value {
+                metadata.xmp.push(String::from_utf8_lossy(data).to_string());
+            }
+        }
+
+        for tag in [
+            TiffTags::ImageDescription,
+            TiffTags::Software,
+            TiffTags::Artist,
+            TiffTags::Copyright,
+            TiffTags::DateTime,
+        ] {
+            if let Some(ExifValue::Ascii(strings)) = find_entry(entries, tag).map(|entry| &entry.value) {
+                let value = strings.join("\n");
+                if !value.is_empty() {
+                    metadata.push_text(&format!("{:?}", tag), value);
+                }
+            }
+        }
+
+        let rational = |tag: TiffTags| match find_entry(entries, tag).map(|entry| &entry.value) {
+            Some(ExifValue::Rational(v)) => v.first().filter(|(_, d)| *d != 0).map(|&(n, d)| n as f64 / d as f64),
+            _ => None,
+        };
+
+        if let (Some(x), Some(y)) = (rational(TiffTags::XResolution), rational(TiffTags::YResolution)) {
+            let unit = entry_value(entries, TiffTags::ResolutionUnit).unwrap_or(2);
+            metadata.resolution = Resolution::from_tiff(x, y, unit);
+        }
+
+        if let Some(value) = entry_value(entries, TiffTags::Orientation) {
+            match Orientation::try_from(value) {
+                Ok(orientation) => metadata.orientation = orientation,
+                Err(e) => DecodeWarning::recovered_from(&e, format!("{}", e)).report(),
+            }
+        }
+
+        metadata
+    }
+
+    pub fn into_reader(self) -> R {
+        self.reader.into_inner()
+    }
+
+    fn read_file_header(&mut self) -> VexelResult<u64> {
+        self.sections.clear();
 
-    fn read_file_header(&mut self) -> VexelResult<u32> {
         let mut byte_order_marker = [0u8; 2];
         self.reader.read_exact(&mut byte_order_marker)?;
 
         let byte_order = match &byte_order_marker {
             b"II" => ByteOrder::LittleEndian,
             b"MM" => ByteOrder::BigEndian,
-            _ => return Err(VexelError::Custom("Invalid byte order marker".to_string())),
+            _ => return Err(VexelError::corrupt(ImageFormat::Tiff, "Invalid byte order marker")),
         };
 
         self.byte_order = byte_order;
         self.reader.set_endianness(byte_order);
 
         let magic = self.reader.read_u16()?;
-        if magic != 42 {
-            return Err(VexelError::Custom("Not a TIFF file".to_string()));
+        self.big_tiff = match magic {
+            42 => false,
+            43 => true,
+            _ => return Err(VexelError::corrupt(ImageFormat::Tiff, "Not a TIFF file")),
+        };
+
+        self.file_len = self.reader.stream_position()? + self.reader.bytes_left()?;
+
+        if self.big_tiff {
+            let offset_size = self.reader.read_u16()?;
+            let reserved = self.reader.read_u16()?;
+            if offset_size != 8 || reserved != 0 {
+                return Err(VexelError::unsupported(
+                    ImageFormat::Tiff,
+                    format!("BigTIFF offset size {} is not supported", offset_size),
+                ));
+            }
         }
 
-        let ifd_offset = self.reader.read_u32()?;
+        let ifd_offset = self.read_offset()?;
+
+        self.sections.push(TiffSectionInfo {
+            start_offset: 0,
+            data: TiffSectionData::FileHeader(TiffFileHeaderData {
+                byte_order,
+                magic,
+                first_ifd_offset: ifd_offset,
+            }),
+        });
+
         Ok(ifd_offset)
     }
 
-    fn read_ifd(&mut self, ifd_offset: u32) -> VexelResult<u32> {
+    /// Reads an offset, 4 bytes in classic TIFF and 8 in BigTIFF.
+    fn read_offset(&mut self) -> VexelResult<u64> {
+        Ok(if self.big_tiff { self.reader.read_u64()? } else { self.reader.read_u32()? as u64 })
+    }
+
+    fn read_entry_count(&mut self) -> VexelResult<u64> {
+        Ok(if self.big_tiff { self.reader.read_u64()? } else { self.reader.read_u16()? as u64 })
+    }
+
+    fn read_entry_header(&mut self) -> VexelResult<(u16, u16, u64, ValueField)> {
+        let tag = self.reader.read_u16()?;
+        let type_ = self.reader.read_u16()?;
+        let count = self.read_offset()?;
+        let field = ValueField::read(&mut self.reader, self.byte_order, self.big_tiff)?;
+        Ok((tag, type_, count, field))
+    }
+
+    fn read_ifd(&mut self, ifd_offset: u64) -> VexelResult<u64> {
         self.header = TiffHeader::default();
 
-        self.reader.seek(SeekFrom::Start(ifd_offset as u64))?;
+        self.reader.seek(SeekFrom::Start(ifd_offset))?;
 
-        let num_entries = self.reader.read_u16()?;
+        let num_entries = self.read_entry_count()?;
+        let mut entries = Vec::new();
 
         for _ in 0..num_entries {
-            let tag = self.reader.read_u16()?;
-            let type_ = self.reader.read_u16()?;
-            let count = self.reader.read_u32()?;
-            let value_offset = self.reader.read_u32()?;
+            let (tag, type_, count, field) = self.read_entry_header()?;
 
             let current_pos = self.reader.stream_position()?;
 
+            entries.push(self.read_entry(tag, type_, count, field)?);
+
             match tag {
-                256 => self.header.image_width = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?,
-                257 => self.header.image_length = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?,
+                256 => self.header.image_width = read_single_value(type_, field, &mut self.reader)?,
+                257 => self.header.image_length = read_single_value(type_, field, &mut self.reader)?,
                 258 => {
                     self.header.bits_per_sample =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?
+                        read_multiple_values(type_, count, field, &mut self.reader)?
                 }
                 259 => {
-                    let compression_value: u32 = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?;
+                    let compression_value: u32 = read_single_value(type_, field, &mut self.reader)?;
                     self.header.compression =
                         Compression::try_from(compression_value as u16).unwrap_or(Compression::None);
                 }
                 262 => {
-                    self.header.photometric_interpretation = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?
+                    self.header.photometric_interpretation = read_single_value(type_, field, &mut self.reader)?
                 }
                 273 => {
                     self.header.strip_offsets =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?
+                        read_multiple_values(type_, count, field, &mut self.reader)?
                 }
-                277 => self.header.samples_per_pixel = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?,
-                278 => self.header.rows_per_strip = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?,
+                277 => self.header.samples_per_pixel = read_single_value(type_, field, &mut self.reader)?,
+                278 => self.header.rows_per_strip = read_single_value(type_, field, &mut self.reader)?,
                 279 => {
                     self.header.strip_byte_counts =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?
+                        read_multiple_values(type_, count, field, &mut self.reader)?
                 }
-                282 => self.header.x_resolution = read_rational(value_offset, &mut self.reader)?,
-                283 => self.header.y_resolution = read_rational(value_offset, &mut self.reader)?,
-                284 => self.header.planar_configuration = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?,
-                296 => self.header.resolution_unit = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?,
+                282 => self.header.x_resolution = read_rational(field, &mut self.reader)?,
+                283 => self.header.y_resolution = read_rational(field, &mut self.reader)?,
+                284 => self.header.planar_configuration = read_single_value(type_, field, &mut self.reader)?,
+                296 => self.header.resolution_unit = read_single_value(type_, field, &mut self.reader)?,
                 317 => {
-                    let predictor_value: u32 = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?;
+                    let predictor_value: u32 = read_single_value(type_, field, &mut self.reader)?;
                     self.header.predictor = Predictor::try_from(predictor_value).unwrap_or(Predictor::None);
                 }
                 320 => {
-                    self.reader.seek(SeekFrom::Start(value_offset as u64))?;
+                    self.reader.seek(SeekFrom::Start(field.offset()))?;
                     let mut color_map = Vec::with_capacity(count as usize);
                     for _ in 0..count {
                         color_map.push(self.reader.read_u16()?);
@@ -120,58 +339,58 @@ impl<R: Read + Seek> TiffDecoder<R> {
                     self.header.color_map = color_map;
                 }
                 322 => {
-                    self.header.tile_width = Some(read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?);
+                    self.header.tile_width = Some(read_single_value(type_, field, &mut self.reader)?);
                 }
                 323 => {
-                    self.header.tile_length = Some(read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?);
+                    self.header.tile_length = Some(read_single_value(type_, field, &mut self.reader)?);
                 }
                 324 => {
                     self.header.tile_offsets =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?;
+                        read_multiple_values(type_, count, field, &mut self.reader)?;
                 }
                 325 => {
                     self.header.tile_byte_counts =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?;
+                        read_multiple_values(type_, count, field, &mut self.reader)?;
                 }
                 32997 => {
-                    self.header.image_depth = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?;
+                    self.header.image_depth = read_single_value(type_, field, &mut self.reader)?;
                 }
                 32998 => {
-                    self.header.tile_depth = read_single_value(type_, value_offset, self.byte_order, &mut self.reader)?;
+                    self.header.tile_depth = read_single_value(type_, field, &mut self.reader)?;
                 }
                 338 => {
                     self.header.extra_samples =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?;
+                        read_multiple_values(type_, count, field, &mut self.reader)?;
                 }
                 339 => {
                     let raw_formats: Vec<u32> =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?;
+                        read_multiple_values(type_, count, field, &mut self.reader)?;
                     self.header.sample_format = raw_formats
                         .into_iter()
                         .map(|v| SampleFormat::try_from(v).unwrap_or(SampleFormat::UnsignedInt))
                         .collect();
                 }
                 347 => {
-                    self.reader.seek(SeekFrom::Start(value_offset as u64))?;
+                    self.reader.seek(SeekFrom::Start(field.offset()))?;
                     let mut jpeg_tables = vec![0u8; count as usize];
                     self.reader.read_exact(&mut jpeg_tables)?;
                     self.header.jpeg_tables = jpeg_tables;
                 }
                 529 => {
-                    let rationals = read_multiple_rationals(count, value_offset, &mut self.reader)?;
+                    let rationals = read_multiple_rationals(count, field, &mut self.reader)?;
                     if rationals.len() >= 3 {
                         self.header.ycbcr_coefficients = [rationals[0], rationals[1], rationals[2]];
                     }
                 }
                 530 => {
                     let values: Vec<u32> =
-                        read_multiple_values(type_, count, value_offset, self.byte_order, &mut self.reader)?;
+                        read_multiple_values(type_, count, field, &mut self.reader)?;
                     if values.len() >= 2 {
                         self.header.ycbcr_sub_sampling = [values[0] as u16, values[1] as u16];
                     }
                 }
                 532 => {
-                    let rationals = read_multiple_rationals(count, value_offset, &mut self.reader)?;
+                    let rationals = read_multiple_rationals(count, field, &mut self.reader)?;
                     if rationals.len() >= 6 {
                         self.header.reference_black_white = [
                             rationals[0],
@@ -189,59 +408,311 @@ impl<R: Read + Seek> TiffDecoder<R> {
             self.reader.seek(SeekFrom::Start(current_pos))?;
         }
 
+        if let Some(entry) = find_entry(&entries, TiffTags::InterColorProfile) {
+            if let ExifValue::Undefined(data) | ExifValue::Byte(data) = &entry.value {
+                self.header.icc_profile = data.clone();
+            }
+        }
+
         self.width = self.header.image_width;
         self.height = self.header.image_length;
 
-        self.limits.reserve_buffer(self.width, self.height, 4)?;
+        self.limits.check_dimensions(self.width, self.height)?;
+        let (buffer_width, buffer_height) = self.buffer_size();
+        self.limits.reserve_buffer(buffer_width, buffer_height, 4)?;
+
+        let next_ifd_offset = self.read_offset().unwrap_or(0);
+
+        let index = self
+            .sections
+            .iter()
+            .filter(|section| matches!(section.data, TiffSectionData::Ifd(_)))
+            .count();
+
+        // Linked sections are read first, the IFD itself goes in front of them
+        let position = self.sections.len();
+        match self.read_linked_sections(index, ifd_offset, &entries) {
+            Ok(()) => {}
+            Err(e @ VexelError::LimitExceeded(_)) => return Err(e),
+            Err(e) => DecodeWarning::recovered_from(&e, format!("Failed to read linked TIFF metadata: {}", e)).report(),
+        }
+
+        let ifd_section = TiffSectionInfo {
+            start_offset: ifd_offset,
+            data: TiffSectionData::Ifd(TiffIfdData {
+                index,
+                layout: chunk_layout(&entries),
+                entries,
+                next_ifd_offset,
+            }),
+        };
+
+        self.sections.insert(position, ifd_section);
 
-        let next_ifd_offset = self.reader.read_u32().unwrap_or(0);
         Ok(next_ifd_offset)
     }
 
+    /// Reads the value of a single IFD entry, following the offset when it doesn't fit inline.
+    fn read_entry(&mut self, tag: u16, field_type: u16, count: u64, field: ValueField) -> VexelResult<TiffIfdEntry> {
+        // IFD pointers are stored as plain LONG or LONG8 offsets
+        let value_type = match field_type {
+            13 => 4,
+            18 => 16,
+            _ => field_type,
+        };
+        let value_offset = field.offset();
+
+        let value = match ExifReader::type_size(value_type) {
+            Some(type_size) => {
+                let total_size = count.saturating_mul(type_size as u64);
+
+                let bytes = if let Some(inline) = field.inline(total_size) {
+                    inline.to_vec()
+                } else if value_offset.saturating_add(total_size) > self.file_len {
+                    let message = format!("Value of TIFF tag {} extends past the end of the file", tag);
+                    DecodeWarning::new(WarningKind::TruncatedData, message).at_offset(value_offset).report();
+                    Vec::new()
+                } else if total_size > MAX_TAG_VALUE_BYTES && !value_is_required(tag) {
+                    log_debug!("Skipping {} byte value of TIFF tag {}", total_size, tag);
+                    Vec::new()
+                } else {
+                    self.limits.reserve(total_size)?;
+                    if is_metadata_blob(tag) {
+                        self.limits.reserve_metadata(total_size)?;
+                    }
+
+                    let bytes = match self.reader.seek(SeekFrom::Start(value_offset)) {
+                        Ok(_) => self.reader.read_bytes(total_size as usize),
+                        Err(e) => Err(e),
+                    };
+                    self.limits.free(total_size);
+
+                    match bytes {
+                        Ok(bytes) => bytes,
+                        Err(e) => {
+                            let message = format!("Error reading value of TIFF tag {}: {}", tag, e);
+                            DecodeWarning::recovered_from(&e.into(), message).at_offset(value_offset).report();
+                            Vec::new()
+                        }
+                    }
+                };
+
+                ExifReader::decode_value(self.byte_order, value_type, count as usize, &bytes)
+            }
+            None => {
+                let message = format!("Unknown TIFF field type {} for tag {}", field_type, tag);
+                DecodeWarning::new(WarningKind::CorruptData, message).report();
+                ExifValue::Undefined(Vec::new())
+            }
+        };
+
+        Ok(TiffIfdEntry {
+            tag,
+            tag_name: TiffTags::try_from(tag).ok().map(|t| format!("{:?}", t)),
+            field_type,
+            count,
+            value_offset,
+            value,
+        })
+    }
+
+    /// Reads all entries of the IFD at `offset` without touching the image header.
+    /// Used for SubIFDs and the EXIF, GPS and Interoperability IFDs.
+    fn read_ifd_entries(&mut self, offset: u64) -> VexelResult<(Vec<TiffIfdEntry>, u64)> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let num_entries = self.read_entry_count()?;
+        let mut entries = Vec::new();
+
+        for _ in 0..num_entries {
+            let (tag, type_, count, field) = self.read_entry_header()?;
+
+            let current_pos = self.reader.stream_position()?;
+            entries.push(self.read_entry(tag, type_, count, field)?);
+            self.reader.seek(SeekFrom::Start(current_pos))?;
+        }
+
+        let next_ifd_offset = self.read_offset().unwrap_or(0);
+        Ok((entries, next_ifd_offset))
+    }
+
+    fn read_exif_ifd(&mut self, offset: u64, ifd_type: IfdType) -> VexelResult<ExifIfd> {
+        let (entries, _) = self.read_ifd_entries(offset)?;
+
+        // ICC profiles and XMP packets were already counted by `read_entry`
+        let value_bytes = entries
+            .iter()
+            .filter(|entry| !is_metadata_blob(entry.tag))
+            .map(|entry| {
+                let type_size = ExifReader::type_size(entry.field_type).unwrap_or(0) as u64;
+                entry.count.saturating_mul(type_size)
+            })
+            .fold(0u64, u64::saturating_add);
+        self.limits.reserve_metadata(value_bytes)?;
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| ExifEntry {
+                tag: entry.tag,
+                tag_name: exif::tag_name(entry.tag, ifd_type).map(|s| s.to_string()),
+                value: entry.value,
+            })
+            .collect();
+
+        Ok(ExifIfd { entries })
+    }
+
+    /// Collects the sections an IFD points to: SubIFDs, EXIF/GPS IFDs and the embedded ICC profile.
+    fn read_linked_sections(&mut self, index: usize, ifd_offset: u64, entries: &[TiffIfdEntry]) -> VexelResult<()> {
+        if let Some(entry) = find_entry(entries, TiffTags::InterColorProfile) {
+            let data = match &entry.value {
+                ExifValue::Undefined(data) | ExifValue::Byte(data) => data.as_slice(),
+                _ => &[],
+            };
+
+            match ICCProfile::new(data) {
+                Ok(profile) => self.sections.push(TiffSectionInfo {
+                    start_offset: entry.value_offset,
+                    data: TiffSectionData::IccProfile(Box::new(profile)),
+                }),
+                Err(e) => {
+                    let message = format!("Error reading ICC profile: {}", e);
+                    DecodeWarning::recovered_from(&e.into(), message).at_offset(entry.value_offset).report();
+                }
+            }
+        }
+
+        if let Some(offset) = entry_values(entries, TiffTags::ExifIFD).first().copied() {
+            let exif_ifd = self.read_exif_ifd(offset, IfdType::ExifIfd)?;
+
+            let interop_offset = exif_ifd
+                .entries
+                .iter()
+                .find(|entry| entry.tag == TiffTags::InteroperabilityIFD as u16)
+                .and_then(|entry| match &entry.value {
+                    ExifValue::Long(v) => v.first().map(|&x| x as u64),
+                    ExifValue::Short(v) => v.first().map(|&x| x as u64),
+                    ExifValue::Long8(v) => v.first().copied(),
+                    _ => None,
+                });
+
+            self.sections.push(TiffSectionInfo {
+                start_offset: offset,
+                data: TiffSectionData::ExifIfd(exif_ifd),
+            });
+
+            if let Some(interop_offset) = interop_offset.filter(|&o| o != 0) {
+                let interop_ifd = self.read_exif_ifd(interop_offset, IfdType::InteropIfd)?;
+                self.sections.push(TiffSectionInfo {
+                    start_offset: interop_offset,
+                    data: TiffSectionData::InteropIfd(interop_ifd),
+                });
+            }
+        }
+
+        if let Some(offset) = entry_values(entries, TiffTags::GPSInfo).first().copied() {
+            let gps_ifd = self.read_exif_ifd(offset, IfdType::GpsIfd)?;
+            self.sections.push(TiffSectionInfo {
+                start_offset: offset,
+                data: TiffSectionData::GpsIfd(gps_ifd),
+            });
+        }
+
+        for offset in entry_values(entries, TiffTags::SubIFDs) {
+            if offset == 0 || offset == ifd_offset {
+                continue;
+            }
+
+            let (sub_entries, next_ifd_offset) = self.read_ifd_entries(offset)?;
+            self.sections.push(TiffSectionInfo {
+                start_offset: offset,
+                data: TiffSectionData::SubIfd(TiffIfdData {
+                    index,
+                    layout: chunk_layout(&sub_entries),
+                    entries: sub_entries,
+                    next_ifd_offset,
+                }),
+            });
+        }
+
+        Ok(())
+    }
+
     fn bits_for(&self, channel: usize) -> u16 {
         self.header.bits_per_sample.get(channel).copied().unwrap_or(8)
     }
 
-    fn decompress_chunk(&self, data: Vec<u8>) -> Vec<u8> {
-        match self.header.compression {
-            Compression::None => data,
-            Compression::LZW => decompress_lzw(&data),
-            Compression::PackBits => decompress_packbits(&data),
-            Compression::AdobeDeflate | Compression::Deflate => decompress_deflate(&data),
+    /// Number of 8-bit channels a JPEG compressed strip or tile is decoded to: gray for
+    /// single-sample images, RGB for everything else.
+    fn jpeg_channels(&self) -> usize {
+        if self.header.samples_per_pixel == 1 { 1 } else { 3 }
+    }
+
+    /// Returns the pixels of a JPEG decoded from a strip or tile with [`jpeg_channels`](Self::jpeg_channels) channels.
+    fn jpeg_samples(&self, image: &Image) -> Vec<u8> {
+        let rgb = image.as_rgb8();
+
+        match self.jpeg_channels() {
+            1 => rgb.chunks_exact(3).map(|pixel| pixel[0]).collect(),
+            _ => rgb,
+        }
+    }
+
+    fn decompress_chunk(&mut self, chunk: Chunk) -> VexelResult<Vec<u8>> {
+        let data = chunk.bytes(&self.reader);
+
+        Ok(match self.header.compression {
+            Compression::LZW => decompress_lzw(data),
+            Compression::PackBits => decompress_packbits(data),
+            Compression::AdobeDeflate | Compression::Deflate => decompress_deflate(data, &mut self.limits)?,
             Compression::JPEG => self.decompress_jpeg_strip(data),
             Compression::OldJPEG => self.decompress_jpeg_strip(data),
             Compression::PNG => self.decompress_png_strip(data),
-            _ => {
-                log_warn!("Unsupported compression method {:?}, using raw data, image will be incorrect", self.header.compression);
-                data
-            },
-        }
+            // Unsupported compression is reported once per page by `decode_current_ifd`
+            _ => chunk.into_vec(&self.reader),
+        })
+    }
+
+    fn is_compression_supported(&self) -> bool {
+        matches!(
+            self.header.compression,
+            Compression::None
+                | Compression::LZW
+                | Compression::PackBits
+                | Compression::AdobeDeflate
+                | Compression::Deflate
+                | Compression::JPEG
+                | Compression::OldJPEG
+                | Compression::PNG
+        )
     }
 
-    fn decompress_png_strip(&self, strip_data: Vec<u8>) -> Vec<u8> {
-        let cursor = Cursor::new(strip_data);
-        let mut png_decoder = PngDecoder::new(cursor);
+    fn decompress_png_strip(&self, strip_data: &[u8]) -> Vec<u8> {
+        let mut png_decoder = PngDecoder::new(Cursor::new(strip_data));
+        png_decoder.set_source_bytes(|cursor: &Cursor<&[u8]>| *cursor.get_ref());
 
-        match png_decoder.decode() {
+        match progress::without_progress(|| png_decoder.decode()) {
             Ok(image) => image.as_rgb8(),
             Err(_) => Vec::new(),
         }
     }
 
-    fn decompress_jpeg_strip(&self, strip_data: Vec<u8>) -> Vec<u8> {
+    fn decompress_jpeg_strip(&self, strip_data: &[u8]) -> Vec<u8> {
+        self.decode_jpeg_chunk(strip_data).unwrap_or_default()
+    }
+
+    /// Decodes a JPEG strip or tile with the tables shared by the page spliced in.
+    fn decode_jpeg_chunk(&self, chunk_data: &[u8]) -> VexelResult<Vec<u8>> {
         let jpeg_data = if !self.header.jpeg_tables.is_empty() {
-            self.splice_jpeg_tables(&self.header.jpeg_tables, &strip_data)
+            Cow::Owned(self.splice_jpeg_tables(&self.header.jpeg_tables, chunk_data))
         } else {
-            strip_data
+            Cow::Borrowed(chunk_data)
         };
 
-        let cursor = Cursor::new(jpeg_data);
-        let mut jpeg_decoder = JpegDecoder::new(cursor);
+        let mut jpeg_decoder = JpegDecoder::new(Cursor::new(&jpeg_data[..]));
+        let image = progress::without_progress(|| jpeg_decoder.decode())?;
 
-        match jpeg_decoder.decode() {
-            Ok(image) => image.as_rgb8(),
-            Err(_) => Vec::new(),
-        }
+        Ok(self.jpeg_samples(&image))
     }
 
     fn splice_jpeg_tables(&self, tables: &[u8], strip: &[u8]) -> Vec<u8> {
@@ -305,19 +776,42 @@ impl<R: Read + Seek> TiffDecoder<R> {
         }
     }
 
-    fn read_strip_data(&mut self) -> VexelResult<Vec<u8>> {
+    /// Reads the compressed bytes of a strip or tile, or borrows them when the input is in memory.
+    /// The byte count comes straight from the file, so it is checked against the allocation
+    /// budget before a buffer is allocated.
+    fn read_chunk(&mut self, offset: u64, byte_count: u64) -> VexelResult<Chunk> {
+        let in_source = |end: &u64| self.reader.source_bytes().is_some_and(|source| *end <= source.len() as u64);
+        if let Some(end) = offset.checked_add(byte_count).filter(in_source) {
+            self.reader.seek(SeekFrom::Start(end))?;
+            return Ok(Chunk::Borrowed(offset as usize..end as usize));
+        }
+
+        self.limits.reserve(byte_count)?;
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let mut data = vec![0u8; byte_count as usize];
+        let result = self.reader.read_exact(&mut data);
+        self.limits.free(byte_count);
+        result?;
+
+        Ok(Chunk::Owned(data))
+    }
+
+    /// Reads and decompresses the strips overlapping `window`, which spans whole strips.
+    fn read_strip_data(&mut self, window: Region, frame: usize) -> VexelResult<Vec<u8>> {
         let is_jpeg = matches!(self.header.compression, Compression::JPEG | Compression::OldJPEG);
 
         if is_jpeg {
-            return self.read_strip_data_jpeg();
+            return self.read_strip_data_jpeg(window, frame);
         }
 
         let is_sgilog = matches!(self.header.compression, Compression::SGILog | Compression::SGILog24);
 
         let rows_per_strip = self.header.rows_per_strip;
         let image_width = self.width;
-        let is_planar = self.header.planar_configuration == super::types::PlanarConfiguration::Planar
+        let is_planar = self.header.planar_configuration == PlanarConfiguration::Planar
             && self.header.samples_per_pixel > 1;
+        let is_partial = window != Region::new(0, 0, self.width, self.height);
         let mut bytes = Vec::new();
 
         let offsets = self.header.strip_offsets.clone();
@@ -330,11 +824,6 @@ impl<R: Read + Seek> TiffDecoder<R> {
         };
 
         for (strip_idx, (offset, byte_count)) in offsets.iter().zip(byte_counts.iter()).enumerate() {
-            self.reader.seek(SeekFrom::Start(*offset as u64))?;
-
-            let mut strip_data = vec![0u8; *byte_count as usize];
-            self.reader.read_exact(&mut strip_data)?;
-
             let strip_within_plane = if is_planar && strips_per_plane > 0 {
                 strip_idx % strips_per_plane
             } else {
@@ -344,14 +833,21 @@ impl<R: Read + Seek> TiffDecoder<R> {
             let strip_row_end = strip_row_start.saturating_add(rows_per_strip).min(self.height);
             let strip_rows = strip_row_end.saturating_sub(strip_row_start) as usize;
 
+            if is_partial && (strip_row_end <= window.y || strip_row_start >= window.bottom()) {
+                continue;
+            }
+
+            let strip_data = self.read_chunk(*offset, *byte_count)?;
+
             let mut decompressed = if is_sgilog {
+                let strip_data = strip_data.bytes(&self.reader);
                 if matches!(self.header.compression, Compression::SGILog24) {
-                    decompress_sgilog24(&strip_data, image_width as usize, strip_rows)
+                    decompress_sgilog24(strip_data, image_width as usize, strip_rows)
                 } else {
-                    decompress_sgilog(&strip_data, image_width as usize, strip_rows)
+                    decompress_sgilog(strip_data, image_width as usize, strip_rows)
                 }
             } else {
-                self.decompress_chunk(strip_data)
+                self.decompress_chunk(strip_data)?
             };
 
             if self.header.predictor != Predictor::None && !is_sgilog {
@@ -370,64 +866,64 @@ impl<R: Read + Seek> TiffDecoder<R> {
             }
 
             bytes.extend_from_slice(&decompressed);
+            self.report_chunks(frame, strip_idx + 1, total_strips)?;
         }
 
         Ok(bytes)
     }
 
-    fn read_strip_data_jpeg(&mut self) -> VexelResult<Vec<u8>> {
+    fn read_strip_data_jpeg(&mut self, window: Region, frame: usize) -> VexelResult<Vec<u8>> {
         let image_width = self.width as usize;
         let image_height = self.height as usize;
         let rows_per_strip = self.header.rows_per_strip as usize;
-        let spp = self.header.samples_per_pixel as usize;
-        let bytes_per_pixel = spp.max(3);
+        let bytes_per_pixel = self.jpeg_channels();
+        let is_partial = window != Region::new(0, 0, self.width, self.height);
+        let window_y = window.y as usize;
 
-        let mut image_data = vec![0u8; image_width * image_height * bytes_per_pixel];
+        let mut image_data = vec![0u8; image_width * window.height as usize * bytes_per_pixel];
 
         let offsets = self.header.strip_offsets.clone();
         let byte_counts = self.header.strip_byte_counts.clone();
-        let jpeg_tables = self.header.jpeg_tables.clone();
 
         for (strip_idx, (offset, byte_count)) in offsets.iter().zip(byte_counts.iter()).enumerate() {
-            self.reader.seek(SeekFrom::Start(*offset as u64))?;
-
-            let mut strip_data = vec![0u8; *byte_count as usize];
-            self.reader.read_exact(&mut strip_data)?;
+            let strip_row_start = strip_idx * rows_per_strip;
+            let strip_row_end = (strip_row_start + rows_per_strip).min(image_height);
 
-            let jpeg_data = if !jpeg_tables.is_empty() {
-                self.splice_jpeg_tables(&jpeg_tables, &strip_data)
-            } else {
-                strip_data
-            };
+            if is_partial && (strip_row_end <= window_y || strip_row_start >= window.bottom() as usize) {
+                continue;
+            }
 
-            let cursor = Cursor::new(jpeg_data);
-            let mut jpeg_decoder = JpegDecoder::new(cursor);
+            let strip_data = self.read_chunk(*offset, *byte_count)?;
 
-            let strip_pixels = match jpeg_decoder.decode() {
-                Ok(image) => image.as_rgb8(),
-                Err(_) => continue,
+            let strip_pixels = match self.decode_jpeg_chunk(strip_data.bytes(&self.reader)) {
+                Ok(strip_pixels) => strip_pixels,
+                Err(_) => {
+                    self.report_chunks(frame, strip_idx + 1, offsets.len())?;
+                    continue;
+                }
             };
 
-            let strip_row_start = strip_idx * rows_per_strip;
-            let strip_row_end = (strip_row_start + rows_per_strip).min(image_height);
             let decoded_rows = strip_row_end - strip_row_start;
             let row_bytes = image_width * bytes_per_pixel;
 
             for row in 0..decoded_rows {
                 let src_start = row * row_bytes;
-                let dst_start = (strip_row_start + row) * row_bytes;
+                let dst_start = (strip_row_start + row - window_y) * row_bytes;
 
                 if src_start + row_bytes <= strip_pixels.len() && dst_start + row_bytes <= image_data.len() {
                     image_data[dst_start..dst_start + row_bytes]
                         .copy_from_slice(&strip_pixels[src_start..src_start + row_bytes]);
                 }
             }
+
+            self.report_chunks(frame, strip_idx + 1, offsets.len())?;
         }
 
         Ok(image_data)
     }
 
-    fn read_tile_data_volumetric(&mut self) -> VexelResult<Vec<Vec<u8>>> {
+    /// Reads the slices of a volumetric image. Progress is reported for `frame`, the first slice.
+    fn read_tile_data_volumetric(&mut self, frame: usize) -> VexelResult<Vec<Vec<u8>>> {
         let tile_width = self.header.tile_width.unwrap_or(self.width) as usize;
         let tile_height = self.header.tile_length.unwrap_or(self.height) as usize;
         let tile_depth = self.header.tile_depth.max(1) as usize;
@@ -450,6 +946,11 @@ impl<R: Read + Seek> TiffDecoder<R> {
             image_width * image_height * bytes_per_pixel
         };
 
+        // The slices are held alongside one decompressed tile at a time
+        let tile_size = tile_width * tile_height * tile_depth * bytes_per_pixel.max(1);
+        let working_size = slice_size.saturating_mul(image_depth).saturating_add(tile_size);
+        self.limits.reserve_usize(working_size)?;
+
         let mut slices = vec![vec![0u8; slice_size]; image_depth];
 
         let tile_offsets = self.header.tile_offsets.clone();
@@ -465,15 +966,15 @@ impl<R: Read + Seek> TiffDecoder<R> {
                     };
                     let byte_count = tile_byte_counts.get(tile_idx).copied().unwrap_or(0);
 
-                    self.reader.seek(SeekFrom::Start(offset as u64))?;
-                    let mut raw_tile = vec![0u8; byte_count as usize];
-                    self.reader.read_exact(&mut raw_tile)?;
+                    let raw_tile = self.read_chunk(offset, byte_count)?;
 
-                    let mut tile_data = self.decompress_chunk(raw_tile);
+                    let mut tile_data = self.decompress_chunk(raw_tile)?;
                     if self.header.predictor != Predictor::None && bps >= 8 {
                         self.apply_predictor(&mut tile_data, tile_width as u32);
                     }
 
+                    self.report_chunks(frame, tile_idx + 1, tile_offsets.len())?;
+
                     let img_x = tx * tile_width;
                     let img_y = ty * tile_height;
                     let img_z_start = tz * tile_depth;
@@ -533,22 +1034,31 @@ impl<R: Read + Seek> TiffDecoder<R> {
             }
         }
 
+        self.limits.free_usize(working_size);
+
         Ok(slices)
     }
 
-    fn read_tile_data(&mut self) -> VexelResult<Vec<u8>> {
+    /// Reads and decompresses the tiles overlapping `window`, which is aligned to tile boundaries
+    /// or the edges of the image, and assembles them into a buffer of the window's size.
+    fn read_tile_data(&mut self, window: Region, frame: usize) -> VexelResult<Vec<u8>> {
         let is_jpeg = matches!(self.header.compression, Compression::JPEG | Compression::OldJPEG);
-        let is_planar = self.header.planar_configuration == super::types::PlanarConfiguration::Planar
+        let is_planar = self.header.planar_configuration == PlanarConfiguration::Planar
             && self.header.samples_per_pixel > 1;
+        let is_partial = window != Region::new(0, 0, self.width, self.height);
 
         let tile_width = self.header.tile_width.unwrap_or(self.width) as usize;
         let tile_height = self.header.tile_length.unwrap_or(self.height) as usize;
+        let (window_x, window_y) = (window.x as usize, window.y as usize);
+        let (window_right, window_bottom) = (window.right() as usize, window.bottom() as usize);
         let image_width = self.width as usize;
         let image_height = self.height as usize;
+        let window_width = window.width as usize;
+        let window_height = window.height as usize;
         let spp = self.header.samples_per_pixel as usize;
         let bps = self.bits_for(0);
         let bytes_per_sample = (bps as usize).div_ceil(8);
-        let bytes_per_pixel = if is_jpeg { spp.max(3) } else { bytes_per_sample * spp };
+        let bytes_per_pixel = if is_jpeg { self.jpeg_channels() } else { bytes_per_sample * spp };
         let bytes_per_plane_sample = bytes_per_sample;
         let is_sub_byte = bps < 8 && !is_jpeg;
 
@@ -557,32 +1067,28 @@ impl<R: Read + Seek> TiffDecoder<R> {
         let tiles_per_plane = tiles_x * tiles_y;
 
         let img_data_size = if is_sub_byte {
-            image_width.div_ceil(8) * image_height
+            window_width.div_ceil(8) * window_height
         } else {
-            image_width * image_height * bytes_per_pixel
+            window_width * window_height * bytes_per_pixel
+        };
+
+        let tile_overlaps = |img_x: usize, img_y: usize| {
+            !is_partial
+                || (img_x < window_right && img_x + tile_width > window_x && img_y < window_bottom && img_y + tile_height > window_y)
         };
 
         let tile_offsets = self.header.tile_offsets.clone();
         let tile_byte_counts = self.header.tile_byte_counts.clone();
-        let jpeg_tables = self.header.jpeg_tables.clone();
+
+        // One decompressed tile is held at a time
+        let tile_size = tile_width.saturating_mul(tile_height).saturating_mul(bytes_per_pixel.max(1));
+        self.limits.reserve_usize(tile_size)?;
 
         if is_planar && !is_jpeg && !is_sub_byte {
-            let plane_size = image_width * image_height * bytes_per_plane_sample;
+            let plane_size = window_width * window_height * bytes_per_plane_sample;
             let mut planar_data = vec![0u8; plane_size * spp];
 
             for tile_idx in 0..tile_offsets.len() {
-                let offset = tile_offsets[tile_idx];
-                let byte_count = tile_byte_counts.get(tile_idx).copied().unwrap_or(0);
-
-                self.reader.seek(SeekFrom::Start(offset as u64))?;
-                let mut raw_tile = vec![0u8; byte_count as usize];
-                self.reader.read_exact(&mut raw_tile)?;
-
-                let mut tile_data = self.decompress_chunk(raw_tile);
-                if self.header.predictor != Predictor::None && bps >= 8 {
-                    self.apply_predictor_planar(&mut tile_data, tile_width as u32);
-                }
-
                 let sample = tile_idx / tiles_per_plane;
                 let within_plane = tile_idx % tiles_per_plane;
                 let tile_col = within_plane % tiles_x;
@@ -592,6 +1098,20 @@ impl<R: Read + Seek> TiffDecoder<R> {
                 let img_y = tile_row * tile_height;
                 let plane_offset = sample * plane_size;
 
+                if !tile_overlaps(img_x, img_y) {
+                    continue;
+                }
+
+                let offset = tile_offsets[tile_idx];
+                let byte_count = tile_byte_counts.get(tile_idx).copied().unwrap_or(0);
+
+                let raw_tile = self.read_chunk(offset, byte_count)?;
+
+                let mut tile_data = self.decompress_chunk(raw_tile)?;
+                if self.header.predictor != Predictor::None && bps >= 8 {
+                    self.apply_predictor_planar(&mut tile_data, tile_width as u32);
+                }
+
                 let expected_tile_bytes = tile_width * tile_height * bytes_per_plane_sample;
                 if tile_data.len() < expected_tile_bytes {
                     tile_data.resize(expected_tile_bytes, 0);
@@ -599,16 +1119,16 @@ impl<R: Read + Seek> TiffDecoder<R> {
 
                 for row in 0..tile_height {
                     let py = img_y + row;
-                    if py >= image_height {
+                    if py >= window_bottom {
                         break;
                     }
                     for col in 0..tile_width {
                         let px = img_x + col;
-                        if px >= image_width {
+                        if px >= window_right {
                             continue;
                         }
                         let src = (row * tile_width + col) * bytes_per_plane_sample;
-                        let dst = plane_offset + (py * image_width + px) * bytes_per_plane_sample;
+                        let dst = plane_offset + ((py - window_y) * window_width + px - window_x) * bytes_per_plane_sample;
                         if src + bytes_per_plane_sample <= tile_data.len()
                             && dst + bytes_per_plane_sample <= planar_data.len()
                         {
@@ -617,68 +1137,63 @@ impl<R: Read + Seek> TiffDecoder<R> {
                         }
                     }
                 }
+
+                self.report_chunks(frame, tile_idx + 1, tile_offsets.len())?;
             }
 
+            self.limits.free_usize(tile_size);
             return Ok(planar_data);
         }
 
         let mut image_data = vec![0u8; img_data_size];
 
         for tile_idx in 0..tile_offsets.len() {
+            let tile_col = tile_idx % tiles_x;
+            let tile_row = tile_idx / tiles_x;
+
+            let img_x = tile_col * tile_width;
+            let img_y = tile_row * tile_height;
+
+            if !tile_overlaps(img_x, img_y) {
+                continue;
+            }
+
             let offset = tile_offsets[tile_idx];
             let byte_count = tile_byte_counts.get(tile_idx).copied().unwrap_or(0);
 
-            self.reader.seek(SeekFrom::Start(offset as u64))?;
-            let mut raw_tile = vec![0u8; byte_count as usize];
-            self.reader.read_exact(&mut raw_tile)?;
+            let raw_tile = self.read_chunk(offset, byte_count)?;
 
             let tile_data = if is_jpeg {
-                let jpeg_data = if !jpeg_tables.is_empty() {
-                    self.splice_jpeg_tables(&jpeg_tables, &raw_tile)
-                } else {
-                    raw_tile
-                };
-
-                let cursor = Cursor::new(jpeg_data);
-                let mut jpeg_decoder = JpegDecoder::new(cursor);
-                match jpeg_decoder.decode() {
-                    Ok(image) => image.as_rgb8(),
-                    Err(_) => vec![0u8; tile_width * tile_height * bytes_per_pixel],
-                }
+                self.decode_jpeg_chunk(raw_tile.bytes(&self.reader))
+                    .unwrap_or_else(|_| vec![0u8; tile_width * tile_height * bytes_per_pixel])
             } else {
-                let mut d = self.decompress_chunk(raw_tile);
+                let mut d = self.decompress_chunk(raw_tile)?;
                 if self.header.predictor != Predictor::None && self.bits_for(0) >= 8 {
                     self.apply_predictor(&mut d, tile_width as u32);
                 }
                 d
             };
 
-            let tile_col = tile_idx % tiles_x;
-            let tile_row = tile_idx / tiles_x;
-
-            let img_x = tile_col * tile_width;
-            let img_y = tile_row * tile_height;
-
             if is_sub_byte {
                 let tile_row_bytes = tile_width.div_ceil(8);
-                let img_row_bytes = image_width.div_ceil(8);
+                let img_row_bytes = window_width.div_ceil(8);
                 for row in 0..tile_height {
                     let py = img_y + row;
-                    if py >= image_height {
+                    if py >= window_bottom {
                         break;
                     }
                     let tile_row_start = row * tile_row_bytes;
-                    let img_row_start = py * img_row_bytes;
+                    let img_row_start = (py - window_y) * img_row_bytes;
                     for col in 0..tile_width {
                         let px = img_x + col;
-                        if px >= image_width {
+                        if px >= window_right {
                             continue;
                         }
                         let src_byte_idx = tile_row_start + col / 8;
                         let src_byte = tile_data.get(src_byte_idx).copied().unwrap_or(0);
                         let bit = (src_byte >> (7 - (col % 8))) & 1;
-                        let dst_byte_idx = img_row_start + px / 8;
-                        let dst_bit_pos = 7 - (px % 8);
+                        let dst_byte_idx = img_row_start + (px - window_x) / 8;
+                        let dst_bit_pos = 7 - ((px - window_x) % 8);
                         if dst_byte_idx < image_data.len() {
                             image_data[dst_byte_idx] = (image_data[dst_byte_idx] & !(1 << dst_bit_pos)) | (bit << dst_bit_pos);
                         }
@@ -693,18 +1208,18 @@ impl<R: Read + Seek> TiffDecoder<R> {
 
                 for row in 0..tile_height {
                     let py = img_y + row;
-                    if py >= image_height {
+                    if py >= window_bottom {
                         break;
                     }
 
                     for col in 0..tile_width {
                         let px = img_x + col;
-                        if px >= image_width {
+                        if px >= window_right {
                             continue;
                         }
 
                         let tile_pixel_offset = (row * tile_width + col) * bytes_per_pixel;
-                        let img_pixel_offset = (py * image_width + px) * bytes_per_pixel;
+                        let img_pixel_offset = ((py - window_y) * window_width + px - window_x) * bytes_per_pixel;
 
                         if tile_pixel_offset + bytes_per_pixel <= tile_data.len()
                             && img_pixel_offset + bytes_per_pixel <= image_data.len()
@@ -715,18 +1230,19 @@ impl<R: Read + Seek> TiffDecoder<R> {
                     }
                 }
             }
+
+            self.report_chunks(frame, tile_idx + 1, tile_offsets.len())?;
         }
 
+        self.limits.free_usize(tile_size);
+
         Ok(image_data)
     }
 
-    fn decode_current_ifd(&mut self) -> VexelResult<Vec<ImageFrame>> {
-        let is_tiled = self.header.tile_width.is_some() && !self.header.tile_offsets.is_empty();
-        let is_jpeg = matches!(self.header.compression, Compression::JPEG | Compression::OldJPEG);
-        let image_depth = self.header.image_depth.max(1);
-        let is_volumetric = is_tiled && image_depth > 1;
+    fn layout_header(&self) -> TiffHeader {
+        let h = &self.header;
 
-        let make_header = |h: &TiffHeader| TiffHeader {
+        TiffHeader {
             image_width: h.image_width,
             image_length: h.image_length,
             bits_per_sample: h.bits_per_sample.clone(),
@@ -754,53 +1270,311 @@ impl<R: Read + Seek> TiffDecoder<R> {
             jpeg_tables: h.jpeg_tables.clone(),
             image_depth: h.image_depth,
             tile_depth: h.tile_depth,
+            icc_profile: h.icc_profile.clone(),
+        }
+    }
+
+    /// Returns the part of the current IFD to decode, or `None` if it lies outside of the
+    /// requested region.
+    fn page_region(&self) -> Option<Region> {
+        match self.region {
+            Some(region) => region.clamped(self.width, self.height),
+            None => Some(Region::new(0, 0, self.width, self.height)),
+        }
+    }
+
+    /// Returns the strips or tiles that have to be read to decode `region` of the current IFD.
+    ///
+    /// Strips and tiles are only converted to pixels on their own when their rows line up with
+    /// the rows of the image. Sub-byte samples are packed across rows and subsampled YCbCr is
+    /// converted in blocks, so those, and files with an unexpected number of strips or tiles,
+    /// are read in full.
+    fn decode_window(&self, region: Region) -> Region {
+        let full = Region::new(0, 0, self.width, self.height);
+        let header = &self.header;
+
+        let is_tiled = header.tile_width.is_some() && !header.tile_offsets.is_empty();
+        let is_jpeg = matches!(header.compression, Compression::JPEG | Compression::OldJPEG);
+        let is_planar = header.planar_configuration == PlanarConfiguration::Planar && header.samples_per_pixel > 1;
+
+        if !is_jpeg {
+            let byte_aligned = header.bits_per_sample.iter().all(|bits| bits % 8 == 0)
+                || (header.samples_per_pixel == 1 && self.bits_for(0) == 1);
+            let subsampled = header.photometric_interpretation == PhotometricInterpretation::YCbCr
+                && header.ycbcr_sub_sampling != [1, 1];
+
+            if !byte_aligned || subsampled {
+                return full;
+            }
+        }
+
+        let planes = if is_planar && !is_jpeg {
+            header.samples_per_pixel as usize
+        } else {
+            1
         };
 
+        if is_tiled {
+            let tile_width = header.tile_width.unwrap_or(self.width);
+            let tile_height = header.tile_length.unwrap_or(self.height);
+            if tile_width == 0 || tile_height == 0 || header.image_depth > 1 {
+                return full;
+            }
+
+            let tiles = self.width.div_ceil(tile_width) as usize * self.height.div_ceil(tile_height) as usize;
+            if header.tile_offsets.len() != tiles * planes {
+                return full;
+            }
+
+            let x = region.x / tile_width * tile_width;
+            let y = region.y / tile_height * tile_height;
+            let right = region.right().div_ceil(tile_width).saturating_mul(tile_width).min(self.width);
+            let bottom = region.bottom().div_ceil(tile_height).saturating_mul(tile_height).min(self.height);
+
+            Region::new(x, y, right - x, bottom - y)
+        } else {
+            let rows_per_strip = header.rows_per_strip;
+            if rows_per_strip == 0 {
+                return full;
+            }
+
+            let strips = self.height.div_ceil(rows_per_strip) as usize * planes;
+            if header.strip_offsets.len() != strips || header.strip_byte_counts.len() != strips {
+                return full;
+            }
+
+            let y = region.y / rows_per_strip * rows_per_strip;
+            let bottom = region.bottom().div_ceil(rows_per_strip).saturating_mul(rows_per_strip).min(self.height);
+
+            Region::new(0, y, self.width, bottom - y)
+        }
+    }
+
+    /// Reports the progress of `frame` after `completed` of `total` strips or tiles were read.
+    fn report_chunks(&self, frame: usize, completed: usize, total: usize) -> VexelResult<()> {
+        progress::rows_share(frame, completed as u64, total as u64, self.height)
+    }
+
+    /// Returns the size of the pixel buffer needed for the current IFD.
+    fn buffer_size(&self) -> (u32, u32) {
+        match self.page_region() {
+            Some(region) => {
+                let window = self.decode_window(region);
+                (window.width, window.height)
+            }
+            None => (0, 0),
+        }
+    }
+
+    /// Decodes the page described by the last IFD read. `frame` is the index of its first frame,
+    /// used in warnings.
+    fn decode_current_ifd(&mut self, frame: usize) -> VexelResult<Vec<ImageFrame>> {
+        let is_tiled = self.header.tile_width.is_some() && !self.header.tile_offsets.is_empty();
+        let is_jpeg = matches!(self.header.compression, Compression::JPEG | Compression::OldJPEG);
+        let image_depth = self.header.image_depth.max(1);
+        let is_volumetric = is_tiled && image_depth > 1;
+
+        let Some(region) = self.page_region() else {
+            return Ok(Vec::new());
+        };
+
+        if !self.is_compression_supported() {
+            let message = format!("Unsupported compression method {:?}, using raw data, image will be incorrect", self.header.compression);
+            let pixels = region.width as u64 * region.height as u64 * if is_volumetric { image_depth as u64 } else { 1 };
+            DecodeWarning::new(WarningKind::Unsupported, message)
+                .in_frame(frame)
+                .with_synthesized_pixels(pixels)
+                .report();
+        }
+
         if is_volumetric {
-            let slices = self.read_tile_data_volumetric()?;
-            let header = make_header(&self.header);
+            let slices = self.read_tile_data_volumetric(frame)?;
+            let header = self.layout_header();
             let pixel_reader = PixelReader {
                 byte_order: self.byte_order,
                 width: self.width,
                 height: self.height,
+                color_target: self.color_target,
             };
 
             let mut frames = Vec::with_capacity(slices.len());
             for slice_data in slices {
                 let mut pd = pixel_reader.convert_to_pixel_data(slice_data, &header)?;
                 pd.correct_pixels(self.width, self.height);
-                frames.push(ImageFrame::new(self.width, self.height, pd, 0));
+                let mut frame = ImageFrame::new(self.width, self.height, pd, 0);
+                frame.crop(region);
+                frames.push(frame);
             }
             return Ok(frames);
         }
 
+        let window = self.decode_window(region);
+
         let bytes = if is_tiled {
-            self.read_tile_data()?
+            self.read_tile_data(window, frame)?
         } else {
-            self.read_strip_data()?
+            self.read_strip_data(window, frame)?
         };
 
         let pixel_data = if is_jpeg {
-            let spp = self.header.samples_per_pixel.max(3);
-            if spp >= 4 {
-                crate::utils::image::PixelData::RGBA8(bytes)
-            } else {
-                crate::utils::image::PixelData::RGB8(bytes)
+            match self.jpeg_channels() {
+                1 => PixelData::L8(bytes),
+                _ => PixelData::RGB8(bytes),
             }
         } else {
-            let header = make_header(&self.header);
+            let header = self.layout_header();
             let pixel_reader = PixelReader {
                 byte_order: self.byte_order,
-                width: self.width,
-                height: self.height,
+                width: window.width,
+                height: window.height,
+                color_target: self.color_target,
             };
 
             let mut pd = pixel_reader.convert_to_pixel_data(bytes, &header)?;
-            pd.correct_pixels(self.width, self.height);
+            pd.correct_pixels(window.width, window.height);
             pd
         };
 
-        Ok(vec![ImageFrame::new(self.width, self.height, pixel_data, 0)])
+        let mut frame = ImageFrame::new(window.width, window.height, pixel_data, 0);
+        frame.crop(region.relative_to(window));
+
+        Ok(vec![frame])
+    }
+
+    fn predicted_pixel_format(&self) -> VexelResult<PixelFormat> {
+        if matches!(self.header.compression, Compression::JPEG | Compression::OldJPEG) {
+            return Ok(match self.jpeg_channels() {
+                1 => PixelFormat::L8,
+                _ => PixelFormat::RGB8,
+            });
+        }
+
+        PixelReader::pixel_format(&self.layout_header())
+    }
+
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        let first_ifd_offset = self.read_file_header()?;
+
+        let mut first: Option<(u32, u32, PixelFormat)> = None;
+        let mut frame_count = 0u32;
+        let mut visited = HashSet::new();
+        let mut next_ifd_offset = first_ifd_offset;
+
+        while next_ifd_offset != 0 && visited.insert(next_ifd_offset) {
+            next_ifd_offset = self.read_ifd(next_ifd_offset)?;
+
+            let is_tiled = self.header.tile_width.is_some() && !self.header.tile_offsets.is_empty();
+            let image_depth = self.header.image_depth.max(1);
+            frame_count += if is_tiled && image_depth > 1 { image_depth } else { 1 };
+
+            if first.is_none() {
+                first = Some((self.width, self.height, self.predicted_pixel_format()?));
+            }
+        }
+
+        let Some((width, height, pixel_format)) = first else {
+            return Err(VexelError::missing(ImageFormat::Tiff, "No frames decoded from TIFF"));
+        };
+
+        Ok(ImageHeader {
+            width,
+            height,
+            pixel_format,
+            frame_count,
+            is_animated: false,
+        })
+    }
+
+    /// Reads the file header, so frames can be decoded one at a time with [`next_frame`](Self::next_frame).
+    pub fn start_frames(&mut self) -> VexelResult<()> {
+        self.next_ifd_offset = self.read_file_header()?;
+
+        Ok(())
+    }
+
+    /// Reads the next IFD in the chain. Returns `false` at the end of the chain or when it loops
+    /// back on itself. Frames are handed out one at a time, so the pixel buffer reserved for the
+    /// IFD is released again right away.
+    fn read_next_ifd(&mut self) -> VexelResult<bool> {
+        let offset = self.next_ifd_offset;
+        if offset == 0 || !self.visited_ifds.insert(offset) {
+            return Ok(false);
+        }
+
+        self.next_ifd_offset = self.read_ifd(offset)?;
+        let (buffer_width, buffer_height) = self.buffer_size();
+        self.limits.free_buffer(buffer_width, buffer_height, 4);
+
+        Ok(true)
+    }
+
+    /// Decodes the frames of the current IFD. A failure after the first frame ends the chain,
+    /// like it does in [`decode`](Self::decode).
+    fn decode_pending_frames(&mut self) -> VexelResult<()> {
+        self.limits.check_frames(self.frames_read + 1)?;
+
+        match self.decode_current_ifd(self.frames_read) {
+            Ok(frames) => self.pending_frames.extend(frames),
+            Err(e @ (VexelError::Cancelled | VexelError::LimitExceeded(_))) => return Err(e),
+            Err(e) => {
+                if self.frames_read == 0 {
+                    return Err(e);
+                }
+                let message = format!("Failed to decode TIFF frame: {}", e);
+                DecodeWarning::recovered_from(&e, message).in_frame(self.frames_read).report();
+                self.next_ifd_offset = 0;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decodes the next frame. Volumetric IFDs hold several frames, which are decoded together.
+    pub fn next_frame(&mut self) -> VexelResult<Option<ImageFrame>> {
+        while self.pending_frames.is_empty() {
+            if !self.read_next_ifd()? {
+                if self.frames_read == 0 {
+                    return Err(VexelError::missing(ImageFormat::Tiff, "No frames decoded from TIFF"));
+                }
+                return Ok(None);
+            }
+
+            self.decode_pending_frames()?;
+        }
+
+        self.frames_read += 1;
+
+        Ok(self.pending_frames.pop_front())
+    }
+
+    /// Skips the next frame. Only the IFD is read, its pixel data is not decoded unless the
+    /// frame is part of a volumetric image. Returns `false` if there are no more frames.
+    pub fn skip_frame(&mut self) -> VexelResult<bool> {
+        if self.pending_frames.is_empty() {
+            if !self.read_next_ifd()? {
+                return Ok(false);
+            }
+
+            let is_tiled = self.header.tile_width.is_some() && !self.header.tile_offsets.is_empty();
+            if !is_tiled || self.header.image_depth <= 1 {
+                self.frames_read += 1;
+                return Ok(true);
+            }
+
+            self.decode_pending_frames()?;
+        }
+
+        Ok(self.next_frame()?.is_some())
+    }
+
+    /// Decodes the part of every page that lies within `region`. Only the strips or tiles
+    /// overlapping it are read, see [`decode_window`](Self::decode_window).
+    pub fn decode_region(&mut self, region: Region) -> VexelResult<Image> {
+        self.region = Some(region);
+        let result = self.decode();
+        self.region = None;
+
+        result
     }
 
     pub fn decode(&mut self) -> VexelResult<Image> {
@@ -812,20 +1586,29 @@ impl<R: Read + Seek> TiffDecoder<R> {
         while next_ifd_offset != 0 {
             next_ifd_offset = self.read_ifd(next_ifd_offset)?;
 
-            match self.decode_current_ifd() {
+            // The region has to lie within the first page, later pages may be smaller
+            if let Some(region) = self.region.filter(|_| frames.is_empty()) {
+                region.check_bounds(self.width, self.height)?;
+            }
+
+            self.limits.check_frames(frames.len() + 1)?;
+
+            match self.decode_current_ifd(frames.len()) {
                 Ok(ifd_frames) => frames.extend(ifd_frames),
+                Err(e @ (VexelError::Cancelled | VexelError::LimitExceeded(_))) => return Err(e),
                 Err(e) => {
-                    log_warn!("Failed to decode TIFF frame: {}", e);
                     if frames.is_empty() {
                         return Err(e);
                     }
+                    let message = format!("Failed to decode TIFF frame: {}", e);
+                    DecodeWarning::recovered_from(&e, message).in_frame(frames.len()).report();
                     break;
                 }
             }
         }
 
         if frames.is_empty() {
-            return Err(VexelError::Custom("No frames decoded from TIFF".to_string()));
+            return Err(VexelError::missing(ImageFormat::Tiff, "No frames decoded from TIFF"));
         }
 
         let width = frames[0].width();
@@ -835,3 +1618,80 @@ impl<R: Read + Seek> TiffDecoder<R> {
         Ok(Image::new(width, height, pixel_format, frames))
     }
 }
+
+/// Values larger than this are only read for the tags the decoder itself needs,
+/// large private blobs (e.g. Photoshop layer data) are left empty.
+const MAX_TAG_VALUE_BYTES: u64 = 1 << 20;
+
+fn is_metadata_blob(tag: u16) -> bool {
+    matches!(TiffTags::try_from(tag), Ok(TiffTags::InterColorProfile | TiffTags::XMP))
+}
+
+fn value_is_required(tag: u16) -> bool {
+    matches!(
+        TiffTags::try_from(tag),
+        Ok(TiffTags::StripOffsets
+            | TiffTags::StripByteCounts
+            | TiffTags::TileOffsets
+            | TiffTags::TileByteCounts
+            | TiffTags::SubIFDs
+            | TiffTags::InterColorProfile
+            | TiffTags::XMP)
+    )
+}
+
+fn find_entry(entries: &[TiffIfdEntry], tag: TiffTags) -> Option<&TiffIfdEntry> {
+    entries.iter().find(|entry| entry.tag == tag as u16)
+}
+
+fn entry_values(entries: &[TiffIfdEntry], tag: TiffTags) -> Vec<u64> {
+    match find_entry(entries, tag).map(|entry| &entry.value) {
+        Some(ExifValue::Byte(v)) => v.iter().map(|&x| x as u64).collect(),
+        Some(ExifValue::Short(v)) => v.iter().map(|&x| x as u64).collect(),
+        Some(ExifValue::Long(v)) => v.iter().map(|&x| x as u64).collect(),
+        Some(ExifValue::Long8(v)) => v.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn entry_value(entries: &[TiffIfdEntry], tag: TiffTags) -> Option<u32> {
+    entry_values(entries, tag).first().map(|&v| v as u32)
+}
+
+/// Describes how the image data of an IFD is split into strips or tiles.
+fn chunk_layout(entries: &[TiffIfdEntry]) -> Option<TiffChunkLayout> {
+    let width = entry_value(entries, TiffTags::ImageWidth)?;
+    let length = entry_value(entries, TiffTags::ImageLength)?;
+
+    let samples_per_pixel = entry_value(entries, TiffTags::SamplesPerPixel).unwrap_or(1).max(1);
+    let planar = entry_value(entries, TiffTags::PlanarConfiguration) == Some(2);
+    let planes = if planar { samples_per_pixel } else { 1 };
+
+    let (tiled, chunk_width, chunk_length, offsets, byte_counts) = match entry_value(entries, TiffTags::TileWidth) {
+        Some(tile_width) => (
+            true,
+            tile_width,
+            entry_value(entries, TiffTags::TileLength).unwrap_or(tile_width),
+            entry_values(entries, TiffTags::TileOffsets),
+            entry_values(entries, TiffTags::TileByteCounts),
+        ),
+        None => (
+            false,
+            width,
+            entry_value(entries, TiffTags::RowsPerStrip).unwrap_or(u32::MAX).min(length),
+            entry_values(entries, TiffTags::StripOffsets),
+            entry_values(entries, TiffTags::StripByteCounts),
+        ),
+    };
+
+    Some(TiffChunkLayout {
+        tiled,
+        chunk_width,
+        chunk_length,
+        chunks_across: width.div_ceil(chunk_width.max(1)),
+        chunks_down: length.div_ceil(chunk_length.max(1)),
+        planes,
+        chunk_count: offsets.len(),
+        total_bytes: byte_counts.iter().sum(),
+    })
+}
@@ -1,6 +1,8 @@
+use crate::utils::color_transform::{CmykTransform, ColorTarget};
 use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::ImageFormat;
 use crate::utils::types::ByteOrder;
-use crate::PixelData;
+use crate::{PixelData, PixelFormat};
 
 use super::color::{
     cielab_to_rgb, cmyk_to_rgb, cmyk_to_rgb_f32, f32_from_bytes, f64_from_bytes, float24_to_f32, half_to_f32,
@@ -13,6 +15,7 @@ pub struct PixelReader {
     pub byte_order: ByteOrder,
     pub width: u32,
     pub height: u32,
+    pub color_target: ColorTarget,
 }
 
 impl PixelReader {
@@ -758,7 +761,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
                 }
             }
 
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                 "Unsupported RGB format: {} bps, {:?} format",
                 bps, fmt
             ))),
@@ -770,7 +773,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
         let color_map = &header.color_map;
 
         if color_map.is_empty() {
-            return Err(VexelError::Custom("Missing ColorMap for Palette image".to_string()));
+            return Err(VexelError::missing(ImageFormat::Tiff, "Missing ColorMap for Palette image"));
         }
 
         let n_colors = 1usize << bps;
@@ -837,7 +840,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
                     })
                     .collect(),
                 _ => {
-                    return Err(VexelError::Custom(format!(
+                    return Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                         "Unsupported bit depth for Palette: {}",
                         bps
                     )))
@@ -885,7 +888,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
                 })
                 .collect(),
             _ => {
-                return Err(VexelError::Custom(format!(
+                return Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                     "Unsupported bit depth for Palette: {}",
                     bps
                 )))
@@ -902,64 +905,70 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
         let bytes_per_sample = (bps as usize).div_ceil(8);
         let bytes_per_pixel = bytes_per_sample * spp;
 
+        // The embedded profile is only applied when a colour target is requested
+        let transform =
+            (self.color_target != ColorTarget::Original).then(|| CmykTransform::cached(&header.icc_profile));
+
         match bps {
             8 => {
+                let rgb = |chunk: &[u8]| match &transform {
+                    Some(transform) => transform.to_rgb8([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                    None => {
+                        let (r, g, b) = cmyk_to_rgb(chunk[0], chunk[1], chunk[2], chunk[3]);
+                        [r, g, b]
+                    }
+                };
+
                 if has_alpha {
                     let pixels: Vec<u8> = data
                         .chunks_exact(bytes_per_pixel)
                         .flat_map(|chunk| {
-                            let (r, g, b) = cmyk_to_rgb(chunk[0], chunk[1], chunk[2], chunk[3]);
+                            let [r, g, b] = rgb(chunk);
                             let a = chunk[4];
                             [r, g, b, a]
                         })
                         .collect();
                     Ok(PixelData::RGBA8(pixels))
                 } else {
-                    let pixels: Vec<u8> = data
-                        .chunks_exact(bytes_per_pixel)
-                        .flat_map(|chunk| {
-                            let (r, g, b) = cmyk_to_rgb(chunk[0], chunk[1], chunk[2], chunk[3]);
-                            [r, g, b]
-                        })
-                        .collect();
+                    let pixels: Vec<u8> = data.chunks_exact(bytes_per_pixel).flat_map(rgb).collect();
                     Ok(PixelData::RGB8(pixels))
                 }
             }
             16 => {
+                let rgb = |chunk: &[u8]| match &transform {
+                    Some(transform) => {
+                        let cmyk =
+                            [0, 2, 4, 6].map(|i| u16_from_bytes(&chunk[i..i + 2], self.byte_order) as f64 / 65535.0);
+                        transform.to_rgb(cmyk).map(|v| (v * 65535.0).round() as u16)
+                    }
+                    None => {
+                        let [c, m, y, k] =
+                            [0, 2, 4, 6].map(|i| u16_from_bytes(&chunk[i..i + 2], self.byte_order) as f32 / 65535.0);
+                        let (r, g, b) = cmyk_to_rgb_f32(c, m, y, k);
+                        [r, g, b].map(|v| (v * 65535.0).round() as u16)
+                    }
+                };
+
                 if has_alpha {
                     let pixels: Vec<u16> = data
                         .chunks_exact(bytes_per_pixel)
                         .flat_map(|chunk| {
-                            let c = u16_from_bytes(&chunk[0..2], self.byte_order) as f32 / 65535.0;
-                            let m = u16_from_bytes(&chunk[2..4], self.byte_order) as f32 / 65535.0;
-                            let y = u16_from_bytes(&chunk[4..6], self.byte_order) as f32 / 65535.0;
-                            let k = u16_from_bytes(&chunk[6..8], self.byte_order) as f32 / 65535.0;
-                            let (r, g, b) = cmyk_to_rgb_f32(c, m, y, k);
+                            let [r, g, b] = rgb(chunk);
                             let a = if chunk.len() >= 10 {
-                                u16_from_bytes(&chunk[8..10], self.byte_order) as f32 / 65535.0
+                                u16_from_bytes(&chunk[8..10], self.byte_order)
                             } else {
-                                1.0
+                                u16::MAX
                             };
-                            [(r * 65535.0).round() as u16, (g * 65535.0).round() as u16, (b * 65535.0).round() as u16, (a * 65535.0).round() as u16]
+                            [r, g, b, a]
                         })
                         .collect();
                     Ok(PixelData::RGBA16(pixels))
                 } else {
-                    let pixels: Vec<u16> = data
-                        .chunks_exact(bytes_per_pixel)
-                        .flat_map(|chunk| {
-                            let c = u16_from_bytes(&chunk[0..2], self.byte_order) as f32 / 65535.0;
-                            let m = u16_from_bytes(&chunk[2..4], self.byte_order) as f32 / 65535.0;
-                            let y = u16_from_bytes(&chunk[4..6], self.byte_order) as f32 / 65535.0;
-                            let k = u16_from_bytes(&chunk[6..8], self.byte_order) as f32 / 65535.0;
-                            let (r, g, b) = cmyk_to_rgb_f32(c, m, y, k);
-                            [(r * 65535.0).round() as u16, (g * 65535.0).round() as u16, (b * 65535.0).round() as u16]
-                        })
-                        .collect();
+                    let pixels: Vec<u16> = data.chunks_exact(bytes_per_pixel).flat_map(rgb).collect();
                     Ok(PixelData::RGB16(pixels))
                 }
             }
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                 "Unsupported CMYK bit depth: {}",
                 bps
             ))),
@@ -969,7 +978,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
     pub fn read_ycbcr(&self, data: &[u8], header: &TiffHeader) -> VexelResult<PixelData> {
         let bps = Self::bits_for(header, 0);
         if bps != 8 {
-            return Err(VexelError::Custom(format!(
+            return Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                 "Unsupported YCbCr bit depth: {} (only 8-bit supported)",
                 bps
             )));
@@ -1077,7 +1086,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
                     .collect();
                 Ok(PixelData::RGB16(pixels))
             }
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                 "Unsupported CIELab bit depth: {}",
                 bps
             ))),
@@ -1120,7 +1129,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
                     .collect();
                 Ok(PixelData::RGB16(pixels))
             }
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                 "Unsupported ICCLab bit depth: {}",
                 bps
             ))),
@@ -1163,7 +1172,7 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
                     .collect();
                 Ok(PixelData::RGB16(pixels))
             }
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::unsupported(ImageFormat::Tiff, format!(
                 "Unsupported ITULab bit depth: {}",
                 bps
             ))),
@@ -1194,6 +1203,87 @@ pub fn sample_format_for(header: &TiffHeader, channel: usize) -> SampleFormat {
         chunky
     }
 
+    /// Returns the format [`convert_to_pixel_data`](Self::convert_to_pixel_data) produces for
+    /// `header`, without converting any data. Fails where the conversion would.
+    pub fn pixel_format(header: &TiffHeader) -> VexelResult<PixelFormat> {
+        use SampleFormat::{Float, SignedInt, UnsignedInt};
+
+        let bps = Self::bits_for(header, 0);
+        let fmt = Self::sample_format_for(header, 0);
+        let spp = header.samples_per_pixel as usize;
+        let has_alpha = Self::has_alpha(header);
+
+        let unsupported = |kind: &str| Err(VexelError::unsupported(ImageFormat::Tiff, format!("Unsupported {} bit depth: {}", kind, bps)));
+        let rgb = |wide: bool, alpha: bool| match (wide, alpha) {
+            (false, false) => PixelFormat::RGB8,
+            (false, true) => PixelFormat::RGBA8,
+            (true, false) => PixelFormat::RGB16,
+            (true, true) => PixelFormat::RGBA16,
+        };
+
+        let format = match header.photometric_interpretation {
+            PhotometricInterpretation::WhiteIsZero | PhotometricInterpretation::BlackIsZero => match (bps, fmt, has_alpha) {
+                (1, UnsignedInt, false) => PixelFormat::L1,
+                (2 | 4 | 8, UnsignedInt, false) | (8, SignedInt, false) => PixelFormat::L8,
+                (16, UnsignedInt | SignedInt, false) => PixelFormat::L16,
+                (32, UnsignedInt | SignedInt | Float, false) | (16 | 24, Float, false) => PixelFormat::L32F,
+                (64, UnsignedInt | SignedInt | Float, false) => PixelFormat::L64F,
+                (8, _, true) if spp >= 2 => PixelFormat::LA8,
+                (16, UnsignedInt | SignedInt, true) if spp >= 2 => PixelFormat::LA16,
+                (16, Float, true) | (32, UnsignedInt | SignedInt | Float, true) if spp >= 2 => PixelFormat::LA32F,
+                (64, UnsignedInt | SignedInt | Float, true) if spp >= 2 => PixelFormat::LA64F,
+                _ => PixelFormat::L8,
+            },
+            PhotometricInterpretation::RGB => {
+                let alpha = spp >= 4 && has_alpha;
+
+                match (bps, fmt) {
+                    (8, UnsignedInt | SignedInt) => rgb(false, alpha),
+                    (16, UnsignedInt | SignedInt) => rgb(true, alpha),
+                    (32, UnsignedInt | SignedInt | Float) | (16, Float) if alpha => PixelFormat::RGBA32F,
+                    (32, UnsignedInt | SignedInt | Float) | (16, Float) => PixelFormat::RGB32F,
+                    (64, UnsignedInt | SignedInt | Float) if alpha => PixelFormat::RGBA64F,
+                    (64, UnsignedInt | SignedInt | Float) => PixelFormat::RGB64F,
+                    _ => return Err(VexelError::unsupported(ImageFormat::Tiff, format!(
+                        "Unsupported RGB format: {} bps, {:?} format",
+                        bps, fmt
+                    ))),
+                }
+            }
+            PhotometricInterpretation::Palette => {
+                if header.color_map.is_empty() {
+                    return Err(VexelError::missing(ImageFormat::Tiff, "Missing ColorMap for Palette image"));
+                }
+                if !matches!(bps, 1 | 2 | 4 | 8) {
+                    return Err(VexelError::unsupported(ImageFormat::Tiff, format!("Unsupported bit depth for Palette: {}", bps)));
+                }
+
+                let n_colors = 1usize << bps;
+                rgb(header.color_map.iter().take(n_colors * 3).any(|&v| v >= 256), false)
+            }
+            PhotometricInterpretation::TransparencyMask => PixelFormat::L1,
+            PhotometricInterpretation::CMYK => match bps {
+                8 | 16 => rgb(bps == 16, spp >= 5 && has_alpha),
+                _ => return unsupported("CMYK"),
+            },
+            PhotometricInterpretation::YCbCr => match bps {
+                8 => PixelFormat::RGB8,
+                _ => return Err(VexelError::unsupported(ImageFormat::Tiff, format!(
+                    "Unsupported YCbCr bit depth: {} (only 8-bit supported)",
+                    bps
+                ))),
+            },
+            photometric @ (PhotometricInterpretation::CIELab | PhotometricInterpretation::ICCLab | PhotometricInterpretation::ITULab) => match bps {
+                8 | 16 => rgb(bps == 16, false),
+                _ => return unsupported(&format!("{:?}", photometric)),
+            },
+            PhotometricInterpretation::LogLuv => PixelFormat::RGB8,
+            PhotometricInterpretation::LogL => PixelFormat::L8,
+        };
+
+        Ok(format)
+    }
+
     pub fn convert_to_pixel_data(&self, data: Vec<u8>, header: &TiffHeader) -> VexelResult<PixelData> {
         let data = if header.planar_configuration == PlanarConfiguration::Planar && header.samples_per_pixel > 1 {
             self.convert_planar_to_chunky(&data, header)
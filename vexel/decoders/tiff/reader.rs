@@ -1,120 +1,165 @@
 use crate::bitreader::BitReader;
 use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::ImageFormat;
 use crate::utils::types::ByteOrder;
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Cursor, Read, Seek, SeekFrom};
 
-pub fn read_single_value<T, R: Read + Seek>(
-    type_: u16,
-    value_offset: u32,
+/// Value field of an IFD entry. Holds the value itself when it fits (4 bytes, 8 in BigTIFF),
+/// otherwise the offset of the value.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueField {
+    bytes: [u8; 8],
+    len: usize,
     byte_order: ByteOrder,
-    reader: &mut BitReader<R>,
-) -> VexelResult<T>
-where
-    T: TryFrom<u32>,
-{
-    let value = match type_ {
-        1 => match byte_order {
-            ByteOrder::BigEndian => (value_offset >> 24) & 0xFF,
-            ByteOrder::LittleEndian => value_offset & 0xFF,
-        },
-        3 => match byte_order {
-            ByteOrder::BigEndian => (value_offset >> 16) & 0xFFFF,
-            ByteOrder::LittleEndian => value_offset & 0xFFFF,
+}
+
+impl ValueField {
+    pub fn read<R: Read + Seek>(reader: &mut BitReader<R>, byte_order: ByteOrder, big_tiff: bool) -> VexelResult<Self> {
+        let len = if big_tiff { 8 } else { 4 };
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes[..len])?;
+        Ok(Self { bytes, len, byte_order })
+    }
+
+    /// The field interpreted as an offset into the file.
+    pub fn offset(&self) -> u64 {
+        let b = self.bytes;
+        match (self.len, self.byte_order) {
+            (8, ByteOrder::LittleEndian) => u64::from_le_bytes(b),
+            (8, ByteOrder::BigEndian) => u64::from_be_bytes(b),
+            (_, ByteOrder::LittleEndian) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as u64,
+            (_, ByteOrder::BigEndian) => u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as u64,
+        }
+    }
+
+    /// Returns the inline value when `size` bytes fit in the field.
+    pub fn inline(&self, size: u64) -> Option<&[u8]> {
+        (size <= self.len as u64).then(|| &self.bytes[..size as usize])
+    }
+
+    fn inline_reader(&self, size: u64) -> Option<BitReader<Cursor<&[u8]>>> {
+        let mut reader = BitReader::new(Cursor::new(self.inline(size)?));
+        reader.set_endianness(self.byte_order);
+        Some(reader)
+    }
+}
+
+fn bytes_per_value(type_: u16) -> u64 {
+    match type_ {
+        1 | 2 => 1,
+        3 => 2,
+        4 => 4,
+        5 | 10 | 16 | 17 | 18 => 8,
+        _ => 4,
+    }
+}
+
+fn read_integer<R: Read + Seek>(type_: u16, reader: &mut BitReader<R>) -> VexelResult<u64> {
+    Ok(match type_ {
+        1 => reader.read_u8()? as u64,
+        3 => reader.read_u16()? as u64,
+        4 => reader.read_u32()? as u64,
+        16 | 18 => reader.read_u64()?,
+        _ => return Err(VexelError::unsupported(ImageFormat::Tiff, "Unsupported type")),
+    })
+}
+
+fn read_value<R: Read + Seek>(type_: u16, field: ValueField, reader: &mut BitReader<R>) -> VexelResult<u64> {
+    match type_ {
+        1 | 3 | 4 | 16 | 18 => match field.inline_reader(bytes_per_value(type_)) {
+            Some(mut inline_reader) => read_integer(type_, &mut inline_reader),
+            None => {
+                reader.seek(SeekFrom::Start(field.offset()))?;
+                read_integer(type_, reader)
+            }
         },
-        4 => value_offset,
         _ => {
-            reader.seek(SeekFrom::Start(value_offset as u64))?;
-            reader.read_u32()?
+            reader.seek(SeekFrom::Start(field.offset()))?;
+            Ok(reader.read_u32()? as u64)
         }
-    };
+    }
+}
 
-    T::try_from(value).map_err(|_| VexelError::Custom("Value conversion error".to_string()))
+pub fn read_single_value<T, R: Read + Seek>(type_: u16, field: ValueField, reader: &mut BitReader<R>) -> VexelResult<T>
+where
+    T: TryFrom<u32>,
+{
+    let value = read_value(type_, field, reader)?;
+
+    u32::try_from(value)
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| VexelError::corrupt(ImageFormat::Tiff, "Value conversion error"))
 }
 
 pub fn read_multiple_values<T, R: Read + Seek>(
     type_: u16,
-    count: u32,
-    value_offset: u32,
-    byte_order: ByteOrder,
+    count: u64,
+    field: ValueField,
     reader: &mut BitReader<R>,
 ) -> VexelResult<Vec<T>>
 where
-    T: TryFrom<u32>,
+    T: TryFrom<u64>,
 {
-    let mut values = Vec::with_capacity(count as usize);
+    let convert = |value: u64| T::try_from(value).map_err(|_| VexelError::corrupt(ImageFormat::Tiff, "Value conversion error"));
 
     if count == 1 {
-        values.push(read_single_value(type_, value_offset, byte_order, reader)?);
-        return Ok(values);
+        return Ok(vec![convert(read_value(type_, field, reader)?)?]);
     }
 
-    let bytes_per_value: u32 = match type_ {
-        1 | 2 => 1,
-        3 => 2,
-        4 => 4,
-        5 | 10 => 8,
-        _ => 4,
-    };
-    let total_bytes = bytes_per_value * count;
+    let total_bytes = bytes_per_value(type_).saturating_mul(count);
 
-    if total_bytes <= 4 {
-        let val_bytes = match byte_order {
-            ByteOrder::LittleEndian => value_offset.to_le_bytes(),
-            ByteOrder::BigEndian => value_offset.to_be_bytes(),
-        };
-        let mut cursor = std::io::Cursor::new(val_bytes);
-        let mut inline_reader = BitReader::new(&mut cursor);
-        inline_reader.set_endianness(byte_order);
-        for _ in 0..count {
-            let value = match type_ {
-                1 => inline_reader.read_u8()? as u32,
-                3 => inline_reader.read_u16()? as u32,
-                4 => inline_reader.read_u32()?,
-                _ => inline_reader.read_u8()? as u32,
-            };
-            values.push(T::try_from(value).map_err(|_| VexelError::Custom("Value conversion error".to_string()))?);
-        }
-        return Ok(values);
+    if let Some(mut inline_reader) = field.inline_reader(total_bytes) {
+        let inline_type = if matches!(type_, 3 | 4 | 16 | 18) { type_ } else { 1 };
+        return (0..count).map(|_| convert(read_integer(inline_type, &mut inline_reader)?)).collect();
     }
 
-    reader.seek(SeekFrom::Start(value_offset as u64))?;
+    reader.seek(SeekFrom::Start(field.offset()))?;
 
+    let mut values = Vec::new();
     for _ in 0..count {
-        let value = match type_ {
-            1 => reader.read_u8()? as u32,
-            3 => reader.read_u16()? as u32,
-            4 => reader.read_u32()?,
-            _ => return Err(VexelError::Custom("Unsupported type".to_string())),
-        };
-
-        values.push(T::try_from(value).map_err(|_| VexelError::Custom("Value conversion error".to_string()))?);
+        values.push(convert(read_integer(type_, reader)?)?);
     }
 
     Ok(values)
 }
 
-pub fn read_rational<R: Read + Seek>(value_offset: u32, reader: &mut BitReader<R>) -> VexelResult<f32> {
-    reader.seek(SeekFrom::Start(value_offset as u64))?;
-    let numerator = reader.read_u32()?;
-    let denominator = reader.read_u32()?;
+fn read_rational_pair<R: Read + Seek>(reader: &mut BitReader<R>) -> VexelResult<(u32, u32)> {
+    Ok((reader.read_u32()?, reader.read_u32()?))
+}
+
+pub fn read_rational<R: Read + Seek>(field: ValueField, reader: &mut BitReader<R>) -> VexelResult<f32> {
+    let (numerator, denominator) = match field.inline_reader(8) {
+        Some(mut inline_reader) => read_rational_pair(&mut inline_reader)?,
+        None => {
+            reader.seek(SeekFrom::Start(field.offset()))?;
+            read_rational_pair(reader)?
+        }
+    };
 
     if denominator == 0 {
-        return Err(VexelError::Custom("Division by zero in rational".to_string()));
+        return Err(VexelError::corrupt(ImageFormat::Tiff, "Division by zero in rational"));
     }
 
     Ok(numerator as f32 / denominator as f32)
 }
 
 pub fn read_multiple_rationals<R: Read + Seek>(
-    count: u32,
-    value_offset: u32,
+    count: u64,
+    field: ValueField,
     reader: &mut BitReader<R>,
 ) -> VexelResult<Vec<f32>> {
-    reader.seek(SeekFrom::Start(value_offset as u64))?;
-    let mut values = Vec::with_capacity(count as usize);
+    let mut inline_reader = field.inline_reader(count.saturating_mul(8));
+    if inline_reader.is_none() {
+        reader.seek(SeekFrom::Start(field.offset()))?;
+    }
+
+    let mut values = Vec::new();
     for _ in 0..count {
-        let numerator = reader.read_u32()?;
-        let denominator = reader.read_u32()?;
+        let (numerator, denominator) = match inline_reader.as_mut() {
+            Some(inline_reader) => read_rational_pair(inline_reader)?,
+            None => read_rational_pair(reader)?,
+        };
         if denominator == 0 {
             values.push(0.0);
         } else {
@@ -1,4 +1,10 @@
 use crate::utils::error::VexelError;
+use crate::utils::image::ImageFormat;
+use crate::utils::exif::{ExifIfd, ExifValue};
+use crate::utils::icc::ICCProfile;
+use crate::utils::types::ByteOrder;
+use serde::Serialize;
+use tsify::Tsify;
 
 // TODO some tags are commented out since they are duplicates, but with different values
 // This probably requires a different approach to handle them
@@ -103,6 +109,8 @@ pub enum TiffTags {
     ImageRatingPercent = 18249,
     ImageID = 32781,
     WangAnnotation = 32932,
+    ImageDepth = 32997,
+    TileDepth = 32998,
     CFARepeatPatternDim = 33421,
     CFAPattern = 33422,
     BatteryLevel = 33423,
@@ -356,6 +364,354 @@ pub enum TiffTags {
     DefaultUserCrop = 51125,
 }
 
+impl TryFrom<u16> for TiffTags {
+    type Error = VexelError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            254 => Ok(Self::NewSubfileType),
+            255 => Ok(Self::SubfileType),
+            256 => Ok(Self::ImageWidth),
+            257 => Ok(Self::ImageLength),
+            258 => Ok(Self::BitsPerSample),
+            259 => Ok(Self::Compression),
+            262 => Ok(Self::PhotometricInterpretation),
+            263 => Ok(Self::Threshholding),
+            264 => Ok(Self::CellWidth),
+            265 => Ok(Self::CellLength),
+            266 => Ok(Self::FillOrder),
+            269 => Ok(Self::DocumentName),
+            270 => Ok(Self::ImageDescription),
+            271 => Ok(Self::Make),
+            272 => Ok(Self::Model),
+            273 => Ok(Self::StripOffsets),
+            274 => Ok(Self::Orientation),
+            277 => Ok(Self::SamplesPerPixel),
+            278 => Ok(Self::RowsPerStrip),
+            279 => Ok(Self::StripByteCounts),
+            280 => Ok(Self::MinSampleValue),
+            281 => Ok(Self::MaxSampleValue),
+            282 => Ok(Self::XResolution),
+            283 => Ok(Self::YResolution),
+            284 => Ok(Self::PlanarConfiguration),
+            285 => Ok(Self::PageName),
+            286 => Ok(Self::XPosition),
+            287 => Ok(Self::YPosition),
+            288 => Ok(Self::FreeOffsets),
+            289 => Ok(Self::FreeByteCounts),
+            290 => Ok(Self::GrayResponseUnit),
+            291 => Ok(Self::GrayResponseCurve),
+            292 => Ok(Self::T4Options),
+            293 => Ok(Self::T6Options),
+            296 => Ok(Self::ResolutionUnit),
+            297 => Ok(Self::PageNumber),
+            301 => Ok(Self::TransferFunction),
+            305 => Ok(Self::Software),
+            306 => Ok(Self::DateTime),
+            315 => Ok(Self::Artist),
+            316 => Ok(Self::HostComputer),
+            317 => Ok(Self::Predictor),
+            318 => Ok(Self::WhitePoint),
+            319 => Ok(Self::PrimaryChromaticities),
+            320 => Ok(Self::ColorMap),
+            321 => Ok(Self::HalftoneHints),
+            322 => Ok(Self::TileWidth),
+            323 => Ok(Self::TileLength),
+            324 => Ok(Self::TileOffsets),
+            325 => Ok(Self::TileByteCounts),
+            326 => Ok(Self::BadFaxLines),
+            327 => Ok(Self::CleanFaxData),
+            328 => Ok(Self::ConsecutiveBadFaxLines),
+            330 => Ok(Self::SubIFDs),
+            332 => Ok(Self::InkSet),
+            333 => Ok(Self::InkNames),
+            334 => Ok(Self::NumberOfInks),
+            336 => Ok(Self::DotRange),
+            337 => Ok(Self::TargetPrinter),
+            338 => Ok(Self::ExtraSamples),
+            339 => Ok(Self::SampleFormat),
+            340 => Ok(Self::SMinSampleValue),
+            341 => Ok(Self::SMaxSampleValue),
+            342 => Ok(Self::TransferRange),
+            343 => Ok(Self::ClipPath),
+            344 => Ok(Self::XClipPathUnits),
+            345 => Ok(Self::YClipPathUnits),
+            346 => Ok(Self::Indexed),
+            347 => Ok(Self::JPEGTables),
+            351 => Ok(Self::OPIProxy),
+            400 => Ok(Self::GlobalParametersIFD),
+            401 => Ok(Self::ProfileType),
+            402 => Ok(Self::FaxProfile),
+            403 => Ok(Self::CodingMethods),
+            404 => Ok(Self::VersionYear),
+            405 => Ok(Self::ModeNumber),
+            433 => Ok(Self::Decode),
+            434 => Ok(Self::DefaultImageColor),
+            512 => Ok(Self::JPEGProc),
+            513 => Ok(Self::JPEGInterchangeFormat),
+            514 => Ok(Self::JPEGInterchangeFormatLength),
+            515 => Ok(Self::JPEGRestartInterval),
+            517 => Ok(Self::JPEGLosslessPredictors),
+            518 => Ok(Self::JPEGPointTransforms),
+            519 => Ok(Self::JPEGQTables),
+            520 => Ok(Self::JPEGDCTables),
+            521 => Ok(Self::JPEGACTables),
+            529 => Ok(Self::YCbCrCoefficients),
+            530 => Ok(Self::YCbCrSubSampling),
+            531 => Ok(Self::YCbCrPositioning),
+            532 => Ok(Self::ReferenceBlackWhite),
+            559 => Ok(Self::StripRowCounts),
+            700 => Ok(Self::XMP),
+            18246 => Ok(Self::ImageRating),
+            18249 => Ok(Self::ImageRatingPercent),
+            32781 => Ok(Self::ImageID),
+            32932 => Ok(Self::WangAnnotation),
+            32997 => Ok(Self::ImageDepth),
+            32998 => Ok(Self::TileDepth),
+            33421 => Ok(Self::CFARepeatPatternDim),
+            33422 => Ok(Self::CFAPattern),
+            33423 => Ok(Self::BatteryLevel),
+            33432 => Ok(Self::Copyright),
+            33434 => Ok(Self::ExposureTime),
+            33437 => Ok(Self::FNumber),
+            33445 => Ok(Self::MDFileTag),
+            33446 => Ok(Self::MDScalePixel),
+            33447 => Ok(Self::MDColorTable),
+            33448 => Ok(Self::MDLabName),
+            33449 => Ok(Self::MDSampleInfo),
+            33450 => Ok(Self::MDPrepDate),
+            33451 => Ok(Self::MDPrepTime),
+            33452 => Ok(Self::MDFileUnits),
+            33550 => Ok(Self::ModelPixelScaleTag),
+            33723 => Ok(Self::IPTCNAA),
+            33918 => Ok(Self::INGRPacketDataTag),
+            33919 => Ok(Self::INGRFlagRegisters),
+            33920 => Ok(Self::IrasBTransformationMatrix),
+            33922 => Ok(Self::ModelTiepointTag),
+            34016 => Ok(Self::Site),
+            34017 => Ok(Self::ColorSequence),
+            34018 => Ok(Self::IT8Header),
+            34019 => Ok(Self::RasterPadding),
+            34020 => Ok(Self::BitsPerRunLength),
+            34021 => Ok(Self::BitsPerExtendedRunLength),
+            34022 => Ok(Self::ColorTable),
+            34023 => Ok(Self::ImageColorIndicator),
+            34024 => Ok(Self::BackgroundColorIndicator),
+            34025 => Ok(Self::ImageColorValue),
+            34026 => Ok(Self::BackgroundColorValue),
+            34027 => Ok(Self::PixelIntensityRange),
+            34028 => Ok(Self::TransparencyIndicator),
+            34029 => Ok(Self::ColorCharacterization),
+            34030 => Ok(Self::HCUsage),
+            34031 => Ok(Self::TrapIndicator),
+            34032 => Ok(Self::CMYKEquivalent),
+            34033 => Ok(Self::Reserved),
+            34264 => Ok(Self::ModelTransformationTag),
+            34377 => Ok(Self::Photoshop),
+            34665 => Ok(Self::ExifIFD),
+            34675 => Ok(Self::InterColorProfile),
+            34732 => Ok(Self::ImageLayer),
+            34735 => Ok(Self::GeoKeyDirectoryTag),
+            34736 => Ok(Self::GeoDoubleParamsTag),
+            34737 => Ok(Self::GeoAsciiParamsTag),
+            34850 => Ok(Self::ExposureProgram),
+            34852 => Ok(Self::SpectralSensitivity),
+            34853 => Ok(Self::GPSInfo),
+            34855 => Ok(Self::ISOSpeedRatings),
+            34856 => Ok(Self::OECF),
+            34857 => Ok(Self::Interlace),
+            34858 => Ok(Self::TimeZoneOffset),
+            34859 => Ok(Self::SelfTimeMode),
+            34864 => Ok(Self::SensitivityType),
+            34865 => Ok(Self::StandardOutputSensitivity),
+            34866 => Ok(Self::RecommendedExposureIndex),
+            34867 => Ok(Self::ISOSpeed),
+            34868 => Ok(Self::ISOSpeedLatitudeyyy),
+            34869 => Ok(Self::ISOSpeedLatitudezzz),
+            34908 => Ok(Self::HylaFAXFaxRecvParams),
+            34909 => Ok(Self::HylaFAXFaxSubAddress),
+            34910 => Ok(Self::HylaFAXFaxRecvTime),
+            36864 => Ok(Self::ExifVersion),
+            36867 => Ok(Self::DateTimeOriginal),
+            36868 => Ok(Self::DateTimeDigitized),
+            37121 => Ok(Self::ComponentsConfiguration),
+            37122 => Ok(Self::CompressedBitsPerPixel),
+            37377 => Ok(Self::ShutterSpeedValue),
+            37378 => Ok(Self::ApertureValue),
+            37379 => Ok(Self::BrightnessValue),
+            37380 => Ok(Self::ExposureBiasValue),
+            37381 => Ok(Self::MaxApertureValue),
+            37382 => Ok(Self::SubjectDistance),
+            37383 => Ok(Self::MeteringMode),
+            37384 => Ok(Self::LightSource),
+            37385 => Ok(Self::Flash),
+            37386 => Ok(Self::FocalLength),
+            37387 => Ok(Self::FlashEnergy),
+            37388 => Ok(Self::SpatialFrequencyResponse),
+            37389 => Ok(Self::Noise),
+            37390 => Ok(Self::FocalPlaneXResolution),
+            37391 => Ok(Self::FocalPlaneYResolution),
+            37392 => Ok(Self::FocalPlaneResolutionUnit),
+            37393 => Ok(Self::ImageNumber),
+            37394 => Ok(Self::SecurityClassification),
+            37395 => Ok(Self::ImageHistory),
+            37396 => Ok(Self::SubjectLocation),
+            37397 => Ok(Self::ExposureIndex),
+            37398 => Ok(Self::TIFFEPStandardID),
+            37399 => Ok(Self::SensingMethod),
+            37500 => Ok(Self::MakerNote),
+            37510 => Ok(Self::UserComment),
+            37520 => Ok(Self::SubsecTime),
+            37521 => Ok(Self::SubsecTimeOriginal),
+            37522 => Ok(Self::SubsecTimeDigitized),
+            37724 => Ok(Self::ImageSourceData),
+            40091 => Ok(Self::XPTitle),
+            40092 => Ok(Self::XPComment),
+            40093 => Ok(Self::XPAuthor),
+            40094 => Ok(Self::XPKeywords),
+            40095 => Ok(Self::XPSubject),
+            40960 => Ok(Self::FlashpixVersion),
+            40961 => Ok(Self::ColorSpace),
+            40962 => Ok(Self::PixelXDimension),
+            40963 => Ok(Self::PixelYDimension),
+            40964 => Ok(Self::RelatedSoundFile),
+            40965 => Ok(Self::InteroperabilityIFD),
+            41728 => Ok(Self::FileSource),
+            41729 => Ok(Self::SceneType),
+            41985 => Ok(Self::CustomRendered),
+            41986 => Ok(Self::ExposureMode),
+            41987 => Ok(Self::WhiteBalance),
+            41988 => Ok(Self::DigitalZoomRatio),
+            41989 => Ok(Self::FocalLengthIn35mmFilm),
+            41990 => Ok(Self::SceneCaptureType),
+            41991 => Ok(Self::GainControl),
+            41992 => Ok(Self::Contrast),
+            41993 => Ok(Self::Saturation),
+            41994 => Ok(Self::Sharpness),
+            41995 => Ok(Self::DeviceSettingDescription),
+            41996 => Ok(Self::SubjectDistanceRange),
+            42016 => Ok(Self::ImageUniqueID),
+            42032 => Ok(Self::CameraOwnerName),
+            42033 => Ok(Self::BodySerialNumber),
+            42034 => Ok(Self::LensSpecification),
+            42035 => Ok(Self::LensMake),
+            42036 => Ok(Self::LensModel),
+            42037 => Ok(Self::LensSerialNumber),
+            42112 => Ok(Self::GdalMetadata),
+            42113 => Ok(Self::GdalNodata),
+            48129 => Ok(Self::PixelFormat),
+            48130 => Ok(Self::Transformation),
+            48131 => Ok(Self::Uncompressed),
+            48132 => Ok(Self::ImageType),
+            48257 => Ok(Self::ImageHeight),
+            48258 => Ok(Self::WidthResolution),
+            48259 => Ok(Self::HeightResolution),
+            48320 => Ok(Self::ImageOffset),
+            48321 => Ok(Self::ImageByteCount),
+            48322 => Ok(Self::AlphaOffset),
+            48323 => Ok(Self::AlphaByteCount),
+            48324 => Ok(Self::ImageDataDiscard),
+            48325 => Ok(Self::AlphaDataDiscard),
+            50215 => Ok(Self::OceScanJobDescription),
+            50216 => Ok(Self::OceApplicationSelector),
+            50217 => Ok(Self::OceIdentificationNumber),
+            50218 => Ok(Self::OceImageLogicCharacteristics),
+            50341 => Ok(Self::PrintImageMatching),
+            50706 => Ok(Self::DNGVersion),
+            50707 => Ok(Self::DNGBackwardVersion),
+            50708 => Ok(Self::UniqueCameraModel),
+            50709 => Ok(Self::LocalizedCameraModel),
+            50710 => Ok(Self::CFAPlaneColor),
+            50711 => Ok(Self::CFALayout),
+            50712 => Ok(Self::LinearizationTable),
+            50713 => Ok(Self::BlackLevelRepeatDim),
+            50714 => Ok(Self::BlackLevel),
+            50715 => Ok(Self::BlackLevelDeltaH),
+            50716 => Ok(Self::BlackLevelDeltaV),
+            50717 => Ok(Self::WhiteLevel),
+            50718 => Ok(Self::DefaultScale),
+            50719 => Ok(Self::DefaultCropOrigin),
+            50720 => Ok(Self::DefaultCropSize),
+            50721 => Ok(Self::ColorMatrix1),
+            50722 => Ok(Self::ColorMatrix2),
+            50723 => Ok(Self::CameraCalibration1),
+            50724 => Ok(Self::CameraCalibration2),
+            50725 => Ok(Self::ReductionMatrix1),
+            50726 => Ok(Self::ReductionMatrix2),
+            50727 => Ok(Self::AnalogBalance),
+            50728 => Ok(Self::AsShotNeutral),
+            50729 => Ok(Self::AsShotWhiteXY),
+            50730 => Ok(Self::BaselineExposure),
+            50731 => Ok(Self::BaselineNoise),
+            50732 => Ok(Self::BaselineSharpness),
+            50733 => Ok(Self::BayerGreenSplit),
+            50734 => Ok(Self::LinearResponseLimit),
+            50735 => Ok(Self::CameraSerialNumber),
+            50736 => Ok(Self::LensInfo),
+            50737 => Ok(Self::ChromaBlurRadius),
+            50738 => Ok(Self::AntiAliasStrength),
+            50739 => Ok(Self::ShadowScale),
+            50740 => Ok(Self::DNGPrivateData),
+            50741 => Ok(Self::MakerNoteSafety),
+            50778 => Ok(Self::CalibrationIlluminant1),
+            50779 => Ok(Self::CalibrationIlluminant2),
+            50780 => Ok(Self::BestQualityScale),
+            50781 => Ok(Self::RawDataUniqueID),
+            50784 => Ok(Self::AliasLayerMetadata),
+            50827 => Ok(Self::OriginalRawFileName),
+            50828 => Ok(Self::OriginalRawFileData),
+            50829 => Ok(Self::ActiveArea),
+            50830 => Ok(Self::MaskedAreas),
+            50831 => Ok(Self::AsShotICCProfile),
+            50832 => Ok(Self::AsShotPreProfileMatrix),
+            50833 => Ok(Self::CurrentICCProfile),
+            50834 => Ok(Self::CurrentPreProfileMatrix),
+            50879 => Ok(Self::ColorimetricReference),
+            50931 => Ok(Self::CameraCalibrationSignature),
+            50932 => Ok(Self::ProfileCalibrationSignature),
+            50933 => Ok(Self::ExtraCameraProfiles),
+            50934 => Ok(Self::AsShotProfileName),
+            50935 => Ok(Self::NoiseReductionApplied),
+            50936 => Ok(Self::ProfileName),
+            50937 => Ok(Self::ProfileHueSatMapDims),
+            50938 => Ok(Self::ProfileHueSatMapData1),
+            50939 => Ok(Self::ProfileHueSatMapData2),
+            50940 => Ok(Self::ProfileToneCurve),
+            50941 => Ok(Self::ProfileEmbedPolicy),
+            50942 => Ok(Self::ProfileCopyright),
+            50964 => Ok(Self::ForwardMatrix1),
+            50965 => Ok(Self::ForwardMatrix2),
+            50966 => Ok(Self::PreviewApplicationName),
+            50967 => Ok(Self::PreviewApplicationVersion),
+            50968 => Ok(Self::PreviewSettingsName),
+            50969 => Ok(Self::PreviewSettingsDigest),
+            50970 => Ok(Self::PreviewColorSpace),
+            50971 => Ok(Self::PreviewDateTime),
+            50972 => Ok(Self::RawImageDigest),
+            50973 => Ok(Self::OriginalRawFileDigest),
+            50974 => Ok(Self::SubTileBlockSize),
+            50975 => Ok(Self::RowInterleaveFactor),
+            50981 => Ok(Self::ProfileLookTableDims),
+            50982 => Ok(Self::ProfileLookTableData),
+            51008 => Ok(Self::OpcodeList1),
+            51009 => Ok(Self::OpcodeList2),
+            51022 => Ok(Self::OpcodeList3),
+            51041 => Ok(Self::NoiseProfile),
+            51089 => Ok(Self::OriginalDefaultFinalSize),
+            51090 => Ok(Self::OriginalBestQualityFinalSize),
+            51091 => Ok(Self::OriginalDefaultCropSize),
+            51107 => Ok(Self::ProfileHueSatMapEncoding),
+            51108 => Ok(Self::ProfileLookTableEncoding),
+            51109 => Ok(Self::BaselineExposureOffset),
+            51110 => Ok(Self::DefaultBlackRender),
+            51111 => Ok(Self::NewRawImageDigest),
+            51112 => Ok(Self::RawToPreviewGain),
+            51125 => Ok(Self::DefaultUserCrop),
+            _ => Err(VexelError::corrupt(ImageFormat::Tiff, format!("Unknown TIFF tag: {}", value))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Compression {
     None = 1,
@@ -429,7 +785,7 @@ impl TryFrom<u16> for Compression {
             50001 => Ok(Self::WebP),
             50002 => Ok(Self::JXL),
             34933 => Ok(Self::PNG),
-            _ => Err(VexelError::Custom(format!("Invalid compression value: {}", value))),
+            _ => Err(VexelError::unsupported(ImageFormat::Tiff, format!("Invalid compression value: {}", value))),
         }
     }
 }
@@ -457,7 +813,7 @@ impl TryFrom<u32> for SampleFormat {
             2 => Ok(Self::SignedInt),
             3 => Ok(Self::Float),
             4 => Ok(Self::Void),
-            _ => Err(VexelError::Custom(format!("Invalid sample format value: {}", value))),
+            _ => Err(VexelError::corrupt(ImageFormat::Tiff, format!("Invalid sample format value: {}", value))),
         }
     }
 }
@@ -503,7 +859,7 @@ impl TryFrom<u32> for PhotometricInterpretation {
             10 => Ok(Self::ITULab),
             32844 => Ok(Self::LogL),
             32845 => Ok(Self::LogLuv),
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::corrupt(ImageFormat::Tiff, format!(
                 "Invalid photometric interpretation value: {}",
                 value
             ))),
@@ -526,24 +882,11 @@ impl TryFrom<u32> for ResolutionUnit {
             1 => Ok(Self::NoUnit),
             2 => Ok(Self::Inch),
             3 => Ok(Self::Centimeter),
-            _ => Err(VexelError::Custom(format!("Invalid resolution unit value: {}", value))),
+            _ => Err(VexelError::corrupt(ImageFormat::Tiff, format!("Invalid resolution unit value: {}", value))),
         }
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Orientation {
-    TopLeft = 1,
-    TopRight = 2,
-    BottomRight = 3,
-    BottomLeft = 4,
-    LeftTop = 5,
-    RightTop = 6,
-    RightBottom = 7,
-    LeftBottom = 8,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PlanarConfiguration {
     Chunky = 1, // RGB RGB RGB ...
@@ -557,7 +900,7 @@ impl TryFrom<u32> for PlanarConfiguration {
         match value {
             1 => Ok(Self::Chunky),
             2 => Ok(Self::Planar),
-            _ => Err(VexelError::Custom(format!(
+            _ => Err(VexelError::corrupt(ImageFormat::Tiff, format!(
                 "Invalid planar configuration value: {}",
                 value
             ))),
@@ -586,7 +929,7 @@ impl TryFrom<u32> for Predictor {
             1 => Ok(Self::None),
             2 => Ok(Self::HorizontalDifferencing),
             3 => Ok(Self::FloatingPoint),
-            _ => Err(VexelError::Custom(format!("Invalid predictor value: {}", value))),
+            _ => Err(VexelError::corrupt(ImageFormat::Tiff, format!("Invalid predictor value: {}", value))),
         }
     }
 }
@@ -598,10 +941,10 @@ pub struct TiffHeader {
     pub bits_per_sample: Vec<u16>,
     pub compression: Compression,
     pub photometric_interpretation: PhotometricInterpretation,
-    pub strip_offsets: Vec<u32>,
+    pub strip_offsets: Vec<u64>,
     pub samples_per_pixel: u16,
     pub rows_per_strip: u32,
-    pub strip_byte_counts: Vec<u32>,
+    pub strip_byte_counts: Vec<u64>,
     pub x_resolution: f32,
     pub y_resolution: f32,
     pub planar_configuration: PlanarConfiguration,
@@ -614,12 +957,13 @@ pub struct TiffHeader {
     pub reference_black_white: [f32; 6],
     pub tile_width: Option<u32>,
     pub tile_length: Option<u32>,
-    pub tile_offsets: Vec<u32>,
-    pub tile_byte_counts: Vec<u32>,
+    pub tile_offsets: Vec<u64>,
+    pub tile_byte_counts: Vec<u64>,
     pub predictor: Predictor,
     pub jpeg_tables: Vec<u8>,
     pub image_depth: u32,
     pub tile_depth: u32,
+    pub icc_profile: Vec<u8>,
 }
 
 impl Default for TiffHeader {
@@ -652,6 +996,62 @@ impl Default for TiffHeader {
             jpeg_tables: Vec::new(),
             image_depth: 1,
             tile_depth: 1,
+            icc_profile: Vec::new(),
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct TiffFileHeaderData {
+    pub byte_order: ByteOrder,
+    pub magic: u16,
+    pub first_ifd_offset: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct TiffIfdEntry {
+    pub tag: u16,
+    pub tag_name: Option<String>,
+    pub field_type: u16,
+    pub count: u64,
+    pub value_offset: u64,
+    pub value: ExifValue,
+}
+
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct TiffChunkLayout {
+    pub tiled: bool,
+    pub chunk_width: u32,
+    pub chunk_length: u32,
+    pub chunks_across: u32,
+    pub chunks_down: u32,
+    pub planes: u32,
+    pub chunk_count: usize,
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct TiffIfdData {
+    pub index: usize,
+    pub entries: Vec<TiffIfdEntry>,
+    pub layout: Option<TiffChunkLayout>,
+    pub next_ifd_offset: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[serde(tag = "type")]
+pub enum TiffSectionData {
+    FileHeader(TiffFileHeaderData),
+    Ifd(TiffIfdData),
+    SubIfd(TiffIfdData),
+    ExifIfd(ExifIfd),
+    GpsIfd(ExifIfd),
+    InteropIfd(ExifIfd),
+    IccProfile(Box<ICCProfile>),
+}
+
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct TiffSectionInfo {
+    pub start_offset: u64,
+    pub data: TiffSectionData,
+}
@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::lzw::compress_lzw;
+use super::quantize::{build_palette, map_pixels, Quantizer};
+use crate::decoders::gif::types::DisposalMethod;
+use crate::utils::convert;
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::{Image, PixelFormat};
+
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const GRAPHIC_CONTROL_LABEL: u8 = 0xF9;
+const COMMENT_LABEL: u8 = 0xFE;
+const APPLICATION_LABEL: u8 = 0xFF;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+/// Key of the text entries written as comment extensions, the same the decoder reads them as.
+const COMMENT_KEY: &str = "Comment";
+
+/// Options for [`GifEncoder`].
+#[derive(Debug, Clone)]
+pub struct GifOptions {
+    /// Method used to reduce images with more colours than fit in a palette.
+    pub quantizer: Quantizer,
+    /// Largest number of entries in a palette, from 2 to 256. One entry is taken by the
+    /// transparent colour when it is needed.
+    pub max_colors: u16,
+    /// Spreads the error of the quantized colours to the neighbouring pixels with
+    /// Floyd–Steinberg dithering.
+    pub dither: bool,
+    /// Gives every frame its own palette, quantized from that frame alone, instead of one global
+    /// palette shared by all frames.
+    pub local_palettes: bool,
+    /// Writes only the rectangle that changed since the previous frame, with the pixels that
+    /// stayed the same left transparent, and picks the disposal method that keeps it smallest.
+    pub optimize: bool,
+    /// Number of times an animation repeats after playing once, 0 to loop forever. `None`
+    /// leaves out the NETSCAPE2.0 extension, so the animation plays once.
+    pub loop_count: Option<u16>,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            quantizer: Quantizer::MedianCut,
+            max_colors: 256,
+            dither: false,
+            local_palettes: false,
+            optimize: true,
+            loop_count: Some(0),
+        }
+    }
+}
+
+struct Palette {
+    colors: Vec<[u8; 3]>,
+    transparent_index: Option<u8>,
+}
+
+impl Palette {
+    /// Number of bits of the colour table size, which holds at least two entries.
+    fn size_bits(&self) -> u8 {
+        let entries = self.colors.len() + self.transparent_index.is_some() as usize;
+        (1..=8).find(|&bits| entries <= 1 << bits).unwrap_or(8)
+    }
+
+    /// Returns the displayed RGBA colour of every index.
+    fn to_rgba(&self, indices: &[u8]) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(indices.len() * 4);
+        for &index in indices {
+            match self.colors.get(index as usize) {
+                Some(color) if Some(index) != self.transparent_index => {
+                    rgba.extend_from_slice(color);
+                    rgba.push(255);
+                }
+                _ => rgba.extend_from_slice(&[0, 0, 0, 0]),
+            }
+        }
+        rgba
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Rect {
+    fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        let left = self.left.min(other.left);
+        let top = self.top.min(other.top);
+        let right = (self.left + self.width).max(other.left + other.width);
+        let bottom = (self.top + self.height).max(other.top + other.height);
+
+        Rect {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+}
+
+/// A quantized frame whose disposal method is only known once the next frame is planned.
+struct PendingFrame {
+    indices: Vec<u8>,
+    /// Canvas as shown once the frame is drawn.
+    displayed: Vec<u8>,
+    /// Canvas the frame is drawn onto.
+    drawn_on: Vec<u8>,
+    rect: Rect,
+    palette_index: usize,
+    delay: u32,
+}
+
+/// Writes images as GIF89a, animated when they have more than one frame.
+///
+/// Images with more colours than fit in a palette are quantized, and pixels with an alpha below
+/// 128 are written as transparent. Text entries with the key `Comment` are written as comment
+/// extensions.
+pub struct GifEncoder {
+    options: GifOptions,
+}
+
+impl GifEncoder {
+    pub fn new(options: GifOptions) -> Self {
+        Self { options }
+    }
+
+    /// Encodes `image` into a GIF file.
+    ///
+    /// Frames are placed at the top left corner and replace the area they cover, each shown for
+    /// its delay rounded to hundredths of a second.
+    pub fn encode(&self, image: &Image) -> VexelResult<Vec<u8>> {
+        let width = image.width();
+        let height = image.height();
+
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(VexelError::InvalidDimensions { width, height });
+        }
+
+        if !(2..=256).contains(&self.options.max_colors) {
+            return Err(VexelError::Custom(format!(
+                "A GIF palette holds 2 to 256 colours, not {}",
+                self.options.max_colors
+            )));
+        }
+
+        let frames = image.frames();
+        if frames.is_empty() {
+            return Err(VexelError::Custom("Image has no frames to encode".to_string()));
+        }
+
+        if let Some(frame) = frames.iter().find(|frame| frame.width() > width || frame.height() > height) {
+            return Err(VexelError::Custom(format!(
+                "A {}x{} frame does not fit in the {}x{} image",
+                frame.width(),
+                frame.height(),
+                width,
+                height
+            )));
+        }
+
+        let canvases = compose_canvases(image);
+        let animated = canvases.len() > 1;
+        let has_transparency = |canvas: &[u8]| canvas.chunks_exact(4).any(|pixel| pixel[3] == 0);
+
+        let palettes: Vec<Palette> = if self.options.local_palettes {
+            canvases
+                .iter()
+                .enumerate()
+                .map(|(index, canvas)| {
+                    let transparent = has_transparency(canvas) || (self.options.optimize && index > 0);
+                    self.build_palette(&[canvas], transparent)
+                })
+                .collect()
+        } else {
+            let all: Vec<&Vec<u8>> = canvases.iter().collect();
+            let transparent = all.iter().any(|canvas| has_transparency(canvas)) || (self.options.optimize && animated);
+            vec![self.build_palette(&all, transparent)]
+        };
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"GIF89a");
+        out.extend_from_slice(&(width as u16).to_le_bytes());
+        out.extend_from_slice(&(height as u16).to_le_bytes());
+
+        let global = (!self.options.local_palettes).then(|| &palettes[0]);
+        match global {
+            Some(palette) => {
+                let bits = palette.size_bits();
+                out.push(0x80 | (bits - 1) << 4 | (bits - 1));
+            }
+            None => out.push(0x70),
+        }
+        // Background colour index and pixel aspect ratio
+        out.extend_from_slice(&[0, 0]);
+
+        if let Some(palette) = global {
+            write_color_table(&mut out, palette);
+        }
+
+        if animated {
+            if let Some(loop_count) = self.options.loop_count {
+                out.extend_from_slice(&[EXTENSION_INTRODUCER, APPLICATION_LABEL, 11]);
+                out.extend_from_slice(b"NETSCAPE2.0");
+                out.extend_from_slice(&[3, 1]);
+                out.extend_from_slice(&loop_count.to_le_bytes());
+                out.push(0);
+            }
+        }
+
+        for entry in image.metadata().text.iter().filter(|entry| entry.key == COMMENT_KEY) {
+            write_comment(&mut out, &entry.value);
+        }
+
+        let canvas_width = width as usize;
+        let canvas_height = height as usize;
+        let full = Rect {
+            left: 0,
+            top: 0,
+            width: canvas_width,
+            height: canvas_height,
+        };
+
+        let mut pending: Option<PendingFrame> = None;
+        for (index, (canvas, frame)) in canvases.iter().zip(frames).enumerate() {
+            let palette_index = if self.options.local_palettes { index } else { 0 };
+            let palette = &palettes[palette_index];
+            let indices = map_pixels(
+                canvas,
+                canvas_width,
+                &palette.colors,
+                palette.transparent_index.unwrap_or(0),
+                self.options.dither,
+            );
+            let displayed = palette.to_rgba(&indices);
+
+            let drawn_on = match pending.take() {
+                None => vec![0u8; canvas.len()],
+                Some(mut previous) => {
+                    let (disposal, rect, drawn_on) = self.plan_disposal(&previous, &displayed, canvas_width);
+                    previous.rect = rect;
+                    self.write_frame(&mut out, &previous, &palettes, disposal, canvas_width, animated);
+                    drawn_on
+                }
+            };
+
+            let rect = if self.options.optimize {
+                changed_rect(&drawn_on, &displayed, canvas_width, canvas_height).unwrap_or(Rect {
+                    left: 0,
+                    top: 0,
+                    width: 1,
+                    height: 1,
+                })
+            } else {
+                full
+            };
+
+            pending = Some(PendingFrame {
+                indices,
+                displayed,
+                drawn_on,
+                rect,
+                palette_index,
+                delay: frame.delay(),
+            });
+        }
+
+        if let Some(last) = pending {
+            self.write_frame(&mut out, &last, &palettes, DisposalMethod::None, canvas_width, animated);
+        }
+
+        out.push(TRAILER);
+
+        Ok(out)
+    }
+
+    /// Encodes `image` into a GIF file and writes it to `writer`.
+    pub fn write<W: Write>(&self, image: &Image, writer: &mut W) -> VexelResult<()> {
+        writer.write_all(&self.encode(image)?)?;
+        Ok(())
+    }
+
+    /// Builds a palette for the opaque pixels of `canvases`, with an entry for transparency at
+    /// the end if `transparent` is set.
+    fn build_palette(&self, canvases: &[&Vec<u8>], transparent: bool) -> Palette {
+        let mut histogram: HashMap<[u8; 3], u32> = HashMap::new();
+        for pixel in canvases.iter().flat_map(|canvas| canvas.chunks_exact(4)) {
+            if pixel[3] != 0 {
+                *histogram.entry([pixel[0], pixel[1], pixel[2]]).or_insert(0) += 1;
+            }
+        }
+
+        let max_colors = self.options.max_colors as usize - transparent as usize;
+        let colors = build_palette(&histogram, max_colors, self.options.quantizer);
+        let transparent_index = transparent.then_some(colors.len() as u8);
+
+        Palette {
+            colors,
+            transparent_index,
+        }
+    }
+
+    /// Picks the disposal method of `previous` that leaves the smallest area to redraw for a
+    /// frame showing `next`. Returns the method, the rectangle `previous` has to cover and the
+    /// canvas the next frame is drawn onto.
+    fn plan_disposal(&self, previous: &PendingFrame, next: &[u8], width: usize) -> (DisposalMethod, Rect, Vec<u8>) {
+        let height = next.len() / 4 / width;
+        // Transparent pixels don't replace what is below them, so pixels that turn transparent
+        // have to be cleared by the disposal of the previous frame
+        let blocks = |base: &[u8]| {
+            base.chunks_exact(4)
+                .zip(next.chunks_exact(4))
+                .any(|(before, after)| before[3] != 0 && after[3] == 0)
+        };
+
+        let mut candidates = Vec::new();
+
+        if !blocks(&previous.displayed) {
+            candidates.push((DisposalMethod::None, previous.rect, previous.displayed.clone()));
+        }
+
+        // Clearing to the background also has to cover the pixels that turn transparent
+        let cleared = match turned_transparent(&previous.displayed, next, width, height) {
+            Some(rect) => rect.union(&previous.rect),
+            None => previous.rect,
+        };
+        let mut background = previous.displayed.clone();
+        for y in cleared.top..cleared.top + cleared.height {
+            let start = (y * width + cleared.left) * 4;
+            background[start..start + cleared.width * 4].fill(0);
+        }
+        candidates.push((DisposalMethod::Background, cleared, background));
+
+        if self.options.optimize && !blocks(&previous.drawn_on) {
+            candidates.push((DisposalMethod::Previous, previous.rect, previous.drawn_on.clone()));
+        }
+
+        if !self.options.optimize {
+            return candidates.swap_remove(0);
+        }
+
+        let area = |base: &[u8]| changed_rect(base, next, width, height).map_or(0, |rect| rect.area());
+        // Stable, so ties keep the order above
+        let best = (0..candidates.len()).min_by_key(|&index| area(&candidates[index].2)).unwrap_or(0);
+        candidates.swap_remove(best)
+    }
+
+    fn write_frame(
+        &self,
+        out: &mut Vec<u8>,
+        frame: &PendingFrame,
+        palettes: &[Palette],
+        disposal: DisposalMethod,
+        canvas_width: usize,
+        animated: bool,
+    ) {
+        let palette = &palettes[frame.palette_index];
+        let rect = frame.rect;
+
+        if animated || palette.transparent_index.is_some() {
+            let disposal_bits = match disposal {
+                DisposalMethod::None => 1,
+                DisposalMethod::Background => 2,
+                DisposalMethod::Previous => 3,
+            };
+            let delay = (frame.delay.saturating_add(5) / 10).min(u16::MAX as u32) as u16;
+
+            out.extend_from_slice(&[EXTENSION_INTRODUCER, GRAPHIC_CONTROL_LABEL, 4]);
+            out.push(disposal_bits << 2 | palette.transparent_index.is_some() as u8);
+            out.extend_from_slice(&delay.to_le_bytes());
+            out.push(palette.transparent_index.unwrap_or(0));
+            out.push(0);
+        }
+
+        out.push(IMAGE_SEPARATOR);
+        for value in [rect.left, rect.top, rect.width, rect.height] {
+            out.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+
+        let bits = palette.size_bits();
+        if self.options.local_palettes {
+            out.push(0x80 | (bits - 1));
+            write_color_table(out, palette);
+        } else {
+            out.push(0);
+        }
+
+        let mut indices = Vec::with_capacity(rect.area());
+        for y in rect.top..rect.top + rect.height {
+            for x in rect.left..rect.left + rect.width {
+                let pixel = y * canvas_width + x;
+                let unchanged = frame.displayed[pixel * 4..pixel * 4 + 4] == frame.drawn_on[pixel * 4..pixel * 4 + 4];
+                match palette.transparent_index {
+                    Some(transparent) if self.options.optimize && unchanged => indices.push(transparent),
+                    _ => indices.push(frame.indices[pixel]),
+                }
+            }
+        }
+
+        let min_code_size = bits.max(2);
+        out.push(min_code_size);
+        write_sub_blocks(out, &compress_lzw(&indices, min_code_size));
+    }
+}
+
+/// Draws every frame onto the canvas in turn and returns the canvas after each one, with the
+/// alpha reduced to fully opaque or fully transparent.
+fn compose_canvases(image: &Image) -> Vec<Vec<u8>> {
+    let width = image.width() as usize;
+    let mut canvas = vec![0u8; width * image.height() as usize * 4];
+    let mut canvases = Vec::with_capacity(image.frames().len());
+
+    for frame in image.frames() {
+        let frame_width = frame.width() as usize;
+        let stride = frame_width * 4;
+        let mut rgba = vec![0u8; stride * frame.height() as usize];
+        convert::write_pixels(frame.pixels(), frame.width(), frame.height(), &mut rgba, stride, &PixelFormat::RGBA8);
+
+        for (y, row) in rgba.chunks_exact(stride.max(1)).enumerate() {
+            let start = y * width * 4;
+            canvas[start..start + stride].copy_from_slice(row);
+        }
+
+        for pixel in canvas.chunks_exact_mut(4) {
+            if pixel[3] < 128 {
+                pixel.fill(0);
+            } else {
+                pixel[3] = 255;
+            }
+        }
+
+        canvases.push(canvas.clone());
+    }
+
+    canvases
+}
+
+/// Returns the bounding box of the pixels that differ between `before` and `after`.
+fn changed_rect(before: &[u8], after: &[u8], width: usize, height: usize) -> Option<Rect> {
+    bounding_box(width, height, |pixel| before[pixel * 4..pixel * 4 + 4] != after[pixel * 4..pixel * 4 + 4])
+}
+
+/// Returns the bounding box of the pixels that are opaque in `before` and transparent in `after`.
+fn turned_transparent(before: &[u8], after: &[u8], width: usize, height: usize) -> Option<Rect> {
+    bounding_box(width, height, |pixel| before[pixel * 4 + 3] != 0 && after[pixel * 4 + 3] == 0)
+}
+
+fn bounding_box(width: usize, height: usize, selected: impl Fn(usize) -> bool) -> Option<Rect> {
+    let (mut left, mut top, mut right, mut bottom) = (usize::MAX, usize::MAX, 0, 0);
+
+    for y in 0..height {
+        for x in 0..width {
+            if selected(y * width + x) {
+                left = left.min(x);
+                top = top.min(y);
+                right = right.max(x + 1);
+                bottom = bottom.max(y + 1);
+            }
+        }
+    }
+
+    (left != usize::MAX).then(|| Rect {
+        left,
+        top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+/// Writes the colours of `palette`, padded with black to the size of the table.
+fn write_color_table(out: &mut Vec<u8>, palette: &Palette) {
+    let entries = 1usize << palette.size_bits();
+    for index in 0..entries {
+        out.extend_from_slice(palette.colors.get(index).unwrap_or(&[0, 0, 0]));
+    }
+}
+
+fn write_comment(out: &mut Vec<u8>, text: &str) {
+    out.extend_from_slice(&[EXTENSION_INTRODUCER, COMMENT_LABEL]);
+
+    // Blocks end on character boundaries, so each of them is valid UTF-8 on its own
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = rest.len().min(255);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        out.push(end as u8);
+        out.extend_from_slice(&rest.as_bytes()[..end]);
+        rest = &rest[end..];
+    }
+
+    out.push(0);
+}
+
+/// Splits `data` into sub-blocks of up to 255 bytes, followed by the block terminator.
+fn write_sub_blocks(out: &mut Vec<u8>, data: &[u8]) {
+    for block in data.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+const MAX_BITS: u32 = 12;
+const MAX_CODES: u16 = 1 << MAX_BITS;
+
+struct BitWriterLsb {
+    output: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriterLsb {
+    fn new() -> Self {
+        BitWriterLsb {
+            output: Vec::new(),
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, code: u16, nbits: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += nbits;
+        while self.bits >= 8 {
+            self.output.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.output.push(self.buffer as u8);
+        }
+        self.output
+    }
+}
+
+/// Compresses palette indices with the LSB-first LZW variant of GIF, the inverse of
+/// `decompress_lzw`. Every index has to fit in `min_code_size` bits.
+pub fn compress_lzw(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let min_code_size = min_code_size.clamp(2, 8) as u32;
+    let clear_code = 1u16 << min_code_size;
+    let end_code = clear_code + 1;
+
+    let mut writer = BitWriterLsb::new();
+    let mut table: HashMap<u32, u16> = HashMap::new();
+    let mut free = end_code + 1;
+    let mut nbits = min_code_size + 1;
+
+    writer.write(clear_code, nbits);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        writer.write(end_code, nbits);
+        return writer.finish();
+    };
+
+    let mut prefix = first as u16;
+    for &index in rest {
+        let key = ((prefix as u32) << 8) | index as u32;
+        if let Some(&code) = table.get(&key) {
+            prefix = code;
+            continue;
+        }
+
+        writer.write(prefix, nbits);
+
+        if free < MAX_CODES {
+            table.insert(key, free);
+            free += 1;
+            if free as u32 > 1 << nbits && nbits < MAX_BITS {
+                nbits += 1;
+            }
+        } else {
+            // The table is full, start over instead of writing codes that can't be added
+            writer.write(clear_code, nbits);
+            table.clear();
+            free = end_code + 1;
+            nbits = min_code_size + 1;
+        }
+
+        prefix = index as u16;
+    }
+
+    // The decoder adds an entry for the last code too, which can widen the end code
+    writer.write(prefix, nbits);
+    if free as u32 >= 1 << nbits && nbits < MAX_BITS {
+        nbits += 1;
+    }
+
+    writer.write(end_code, nbits);
+    writer.finish()
+}
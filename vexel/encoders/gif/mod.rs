@@ -0,0 +1,6 @@
+mod encoder;
+mod lzw;
+mod quantize;
+
+pub use encoder::{GifEncoder, GifOptions};
+pub use quantize::Quantizer;
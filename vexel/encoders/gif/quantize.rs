@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+/// Method used to reduce images with more colours than fit in a palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantizer {
+    /// Repeatedly splits the box of colours with the widest range at its weighted median.
+    MedianCut,
+    /// Builds an octree of the colours and merges its least used branches.
+    Octree,
+}
+
+/// Builds a palette of at most `max_colors` colours for a histogram of colours and the number
+/// of pixels using them. The colours are kept as they are if there are few enough of them.
+pub fn build_palette(histogram: &HashMap<[u8; 3], u32>, max_colors: usize, quantizer: Quantizer) -> Vec<[u8; 3]> {
+    let mut colors: Vec<([u8; 3], u32)> = histogram.iter().map(|(&color, &count)| (color, count)).collect();
+    // Sorted so the palette does not depend on the iteration order of the histogram
+    colors.sort_unstable();
+
+    if colors.len() <= max_colors {
+        return colors.into_iter().map(|(color, _)| color).collect();
+    }
+
+    match quantizer {
+        Quantizer::MedianCut => median_cut(colors, max_colors),
+        Quantizer::Octree => octree(&colors, max_colors),
+    }
+}
+
+fn median_cut(colors: Vec<([u8; 3], u32)>, max_colors: usize) -> Vec<[u8; 3]> {
+    let mut boxes = vec![colors];
+
+    while boxes.len() < max_colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (index, channel, range)
+            })
+            .max_by_key(|&(_, _, range)| range);
+
+        let Some((index, channel, _)) = widest else {
+            break;
+        };
+
+        let mut lower = boxes.swap_remove(index);
+        lower.sort_unstable_by_key(|&(color, _)| color[channel]);
+
+        let total: u64 = lower.iter().map(|&(_, count)| count as u64).sum();
+        let mut seen = 0u64;
+        let mut split = lower.len() / 2;
+        for (i, &(_, count)) in lower.iter().enumerate() {
+            seen += count as u64;
+            if seen * 2 >= total {
+                split = i + 1;
+                break;
+            }
+        }
+
+        // Both halves need at least one colour
+        let upper = lower.split_off(split.clamp(1, lower.len() - 1));
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|colors| average(colors.iter().copied())).collect()
+}
+
+/// Returns the channel with the largest range of values in `colors`, and that range.
+fn widest_channel(colors: &[([u8; 3], u32)]) -> (usize, u8) {
+    let mut min = [u8::MAX; 3];
+    let mut max = [0u8; 3];
+    for (color, _) in colors {
+        for channel in 0..3 {
+            min[channel] = min[channel].min(color[channel]);
+            max[channel] = max[channel].max(color[channel]);
+        }
+    }
+
+    (0..3).map(|channel| (channel, max[channel] - min[channel])).max_by_key(|&(_, range)| range).unwrap_or((0, 0))
+}
+
+fn average(colors: impl Iterator<Item = ([u8; 3], u32)>) -> [u8; 3] {
+    let mut sums = [0u64; 3];
+    let mut total = 0u64;
+    for (color, count) in colors {
+        for channel in 0..3 {
+            sums[channel] += color[channel] as u64 * count as u64;
+        }
+        total += count as u64;
+    }
+
+    let total = total.max(1);
+    sums.map(|sum| ((sum + total / 2) / total) as u8)
+}
+
+#[derive(Default)]
+struct OctreeNode {
+    /// Indices of the children in the node list, 0 for none since the root is never a child.
+    children: [usize; 8],
+    sums: [u64; 3],
+    count: u64,
+    leaf: bool,
+}
+
+fn octree(colors: &[([u8; 3], u32)], max_colors: usize) -> Vec<[u8; 3]> {
+    let mut nodes = vec![OctreeNode::default()];
+    // Inner nodes at each depth, the root is at depth 0 and the leaves at depth 8
+    let mut levels: Vec<Vec<usize>> = vec![Vec::new(); 8];
+    levels[0].push(0);
+
+    for &(color, count) in colors {
+        let mut node = 0;
+        for depth in 0..8 {
+            let shift = 7 - depth;
+            let child = (((color[0] >> shift) & 1) << 2 | ((color[1] >> shift) & 1) << 1 | ((color[2] >> shift) & 1)) as usize;
+
+            if nodes[node].children[child] == 0 {
+                nodes.push(OctreeNode::default());
+                let index = nodes.len() - 1;
+                nodes[node].children[child] = index;
+                if depth < 7 {
+                    levels[depth + 1].push(index);
+                }
+            }
+
+            node = nodes[node].children[child];
+        }
+
+        let leaf = &mut nodes[node];
+        for (sum, &value) in leaf.sums.iter_mut().zip(&color) {
+            *sum += value as u64 * count as u64;
+        }
+        leaf.count += count as u64;
+        leaf.leaf = true;
+    }
+
+    // Every colour is distinct, so it has a leaf of its own
+    let mut leaves = colors.len();
+
+    // Merge the nodes whose children are all leaves, the least used first, until the palette
+    // is small enough. Every deeper level is merged completely before moving up.
+    for depth in (0..8).rev() {
+        if leaves <= max_colors {
+            break;
+        }
+
+        let mut level = std::mem::take(&mut levels[depth]);
+        let subtree_count = |nodes: &[OctreeNode], node: usize| -> u64 {
+            nodes[node].children.iter().filter(|&&child| child != 0).map(|&child| nodes[child].count).sum()
+        };
+        level.sort_by_cached_key(|&node| subtree_count(&nodes, node));
+
+        for node in level {
+            if leaves <= max_colors {
+                break;
+            }
+
+            let children = std::mem::take(&mut nodes[node].children);
+            let mut merged = 0;
+            for child in children.into_iter().filter(|&child| child != 0) {
+                let (sums, count) = (nodes[child].sums, nodes[child].count);
+                for (sum, value) in nodes[node].sums.iter_mut().zip(sums) {
+                    *sum += value;
+                }
+                nodes[node].count += count;
+                merged += 1;
+            }
+
+            nodes[node].leaf = true;
+            leaves -= merged - 1;
+        }
+    }
+
+    let mut palette = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(node) = stack.pop() {
+        let node = &nodes[node];
+        if node.leaf {
+            let count = node.count.max(1);
+            palette.push(node.sums.map(|sum| ((sum + count / 2) / count) as u8));
+        } else {
+            stack.extend(node.children.iter().rev().filter(|&&child| child != 0));
+        }
+    }
+
+    palette
+}
+
+/// Maps the pixels of an RGBA canvas to the nearest colours of `palette`, optionally with
+/// Floyd–Steinberg dithering. Pixels with an alpha below 128 get `transparent_index`.
+pub fn map_pixels(rgba: &[u8], width: usize, palette: &[[u8; 3]], transparent_index: u8, dither: bool) -> Vec<u8> {
+    let mut cache: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut nearest = |color: [u8; 3]| {
+        *cache.entry(color).or_insert_with(|| {
+            let distance = |entry: &[u8; 3]| -> u32 {
+                (0..3).map(|channel| (entry[channel] as i32 - color[channel] as i32).pow(2) as u32).sum()
+            };
+            (0..palette.len()).min_by_key(|&index| distance(&palette[index])).unwrap_or(0) as u8
+        })
+    };
+
+    let mut indices = Vec::with_capacity(rgba.len() / 4);
+
+    if !dither || width == 0 {
+        for pixel in rgba.chunks_exact(4) {
+            indices.push(if pixel[3] < 128 { transparent_index } else { nearest([pixel[0], pixel[1], pixel[2]]) });
+        }
+        return indices;
+    }
+
+    // Errors carried to the current and the next row, in sixteenths
+    let mut current = vec![0i32; (width + 2) * 3];
+    let mut next = vec![0i32; (width + 2) * 3];
+
+    for row in rgba.chunks_exact(width * 4) {
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            if pixel[3] < 128 {
+                indices.push(transparent_index);
+                continue;
+            }
+
+            // Offset by one so the pixels on both edges can spread their error
+            let at = (x + 1) * 3;
+            let mut wanted = [0i32; 3];
+            for channel in 0..3 {
+                wanted[channel] = (pixel[channel] as i32 + current[at + channel] / 16).clamp(0, 255);
+            }
+
+            let index = nearest(wanted.map(|value| value as u8));
+            indices.push(index);
+
+            for channel in 0..3 {
+                let error = wanted[channel] - palette[index as usize][channel] as i32;
+                current[at + 3 + channel] += error * 7;
+                next[at - 3 + channel] += error * 3;
+                next[at + channel] += error * 5;
+                next[at + 3 + channel] += error;
+            }
+        }
+
+        std::mem::swap(&mut current, &mut next);
+        next.fill(0);
+    }
+
+    indices
+}
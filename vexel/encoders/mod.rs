@@ -0,0 +1,3 @@
+pub mod gif;
+pub mod png;
+pub mod tiff;
@@ -0,0 +1,209 @@
+use crate::decoders::png::crc_simd::update_crc;
+use crate::decoders::png::types::{Chromaticities, ColorType, RenderingIntent};
+use crate::utils::deflate::ZlibEncoder;
+use crate::utils::metadata::{Resolution, ResolutionUnit};
+
+const SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+/// Largest amount of compressed data written in a single IDAT or fdAT chunk.
+const IMAGE_DATA_CHUNK_SIZE: usize = 1 << 18;
+
+/// Text longer than this is compressed.
+const TEXT_COMPRESSION_THRESHOLD: usize = 1024;
+
+pub const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+
+/// Builds a PNG file chunk by chunk, starting with the signature.
+pub struct ChunkWriter {
+    data: Vec<u8>,
+    sequence: u32,
+}
+
+impl ChunkWriter {
+    pub fn new() -> Self {
+        Self {
+            data: SIGNATURE.to_vec(),
+            sequence: 0,
+        }
+    }
+
+    pub fn write(&mut self, chunk_type: &[u8; 4], data: &[u8]) {
+        let crc = update_crc(update_crc(0xffffffff, chunk_type), data) ^ 0xffffffff;
+
+        self.data.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        self.data.extend_from_slice(chunk_type);
+        self.data.extend_from_slice(data);
+        self.data.extend_from_slice(&crc.to_be_bytes());
+    }
+
+    pub fn write_ihdr(&mut self, width: u32, height: u32, bit_depth: u8, color_type: ColorType, interlaced: bool) {
+        let mut data = Vec::with_capacity(13);
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[bit_depth, color_type as u8, 0, 0, interlaced as u8]);
+        self.write(b"IHDR", &data);
+    }
+
+    pub fn write_actl(&mut self, num_frames: u32, num_plays: u32) {
+        let mut data = Vec::with_capacity(8);
+        data.extend_from_slice(&num_frames.to_be_bytes());
+        data.extend_from_slice(&num_plays.to_be_bytes());
+        self.write(b"acTL", &data);
+    }
+
+    /// Writes the control chunk of a frame placed at the top left of the canvas, which replaces
+    /// the canvas area it covers and is left in place when the next frame is drawn.
+    pub fn write_fctl(&mut self, width: u32, height: u32, delay: u32) {
+        // Delays are stored as a fraction of 16-bit parts, fall back to centiseconds for long ones
+        let (delay_num, delay_den) = match u16::try_from(delay) {
+            Ok(delay) => (delay, 1000u16),
+            Err(_) => ((delay / 10).min(u16::MAX as u32) as u16, 100u16),
+        };
+
+        let mut data = Vec::with_capacity(26);
+        data.extend_from_slice(&self.next_sequence().to_be_bytes());
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(&delay_num.to_be_bytes());
+        data.extend_from_slice(&delay_den.to_be_bytes());
+        // APNG_DISPOSE_OP_NONE and APNG_BLEND_OP_SOURCE
+        data.extend_from_slice(&[0, 0]);
+        self.write(b"fcTL", &data);
+    }
+
+    pub fn write_gama(&mut self, gamma: f32) {
+        let gamma = (gamma as f64 * 100000.0).round() as u32;
+        self.write(b"gAMA", &gamma.to_be_bytes());
+    }
+
+    pub fn write_chrm(&mut self, chromaticities: &Chromaticities) {
+        let values = [
+            chromaticities.white_point_x,
+            chromaticities.white_point_y,
+            chromaticities.red_x,
+            chromaticities.red_y,
+            chromaticities.green_x,
+            chromaticities.green_y,
+            chromaticities.blue_x,
+            chromaticities.blue_y,
+        ];
+
+        let mut data = Vec::with_capacity(32);
+        for value in values {
+            data.extend_from_slice(&((value as f64 * 100000.0).round() as u32).to_be_bytes());
+        }
+        self.write(b"cHRM", &data);
+    }
+
+    pub fn write_srgb(&mut self, intent: RenderingIntent) {
+        self.write(b"sRGB", &[intent as u8]);
+    }
+
+    pub fn write_iccp(&mut self, profile: &[u8], level: u8) {
+        let mut data = b"ICC Profile\0\0".to_vec();
+        data.extend_from_slice(&ZlibEncoder::new(level).encode(profile));
+        self.write(b"iCCP", &data);
+    }
+
+    pub fn write_phys(&mut self, resolution: &Resolution) {
+        let (scale, unit) = match resolution.unit {
+            ResolutionUnit::None => (1.0, 0u8),
+            ResolutionUnit::Inch => (1.0 / 0.0254, 1),
+            ResolutionUnit::Centimeter => (100.0, 1),
+            ResolutionUnit::Meter => (1.0, 1),
+        };
+        let to_u32 = |value: f64| (value * scale).round().clamp(1.0, u32::MAX as f64) as u32;
+
+        let mut data = Vec::with_capacity(9);
+        data.extend_from_slice(&to_u32(resolution.x).to_be_bytes());
+        data.extend_from_slice(&to_u32(resolution.y).to_be_bytes());
+        data.push(unit);
+        self.write(b"pHYs", &data);
+    }
+
+    pub fn write_plte(&mut self, palette: &[[u8; 4]]) {
+        let data: Vec<u8> = palette.iter().flat_map(|entry| [entry[0], entry[1], entry[2]]).collect();
+        self.write(b"PLTE", &data);
+    }
+
+    /// Writes a text entry as tEXt or zTXt if it is Latin-1, and as iTXt otherwise. `keyword`
+    /// must already be validated with [`is_valid_keyword`].
+    pub fn write_text(&mut self, keyword: &str, text: &str, level: u8) {
+        let compress = text.len() > TEXT_COMPRESSION_THRESHOLD;
+
+        if let Some(latin1) = to_latin1(text) {
+            let mut data = to_latin1(keyword).unwrap_or_default();
+            data.push(0);
+
+            if compress {
+                data.push(0);
+                data.extend_from_slice(&ZlibEncoder::new(level).encode(&latin1));
+                self.write(b"zTXt", &data);
+            } else {
+                data.extend_from_slice(&latin1);
+                self.write(b"tEXt", &data);
+            }
+        } else {
+            self.write_itxt(keyword, text, compress, level);
+        }
+    }
+
+    /// Writes an iTXt chunk without a language tag or translated keyword.
+    pub fn write_itxt(&mut self, keyword: &str, text: &str, compress: bool, level: u8) {
+        let mut data = to_latin1(keyword).unwrap_or_default();
+        data.extend_from_slice(&[0, compress as u8, 0, 0, 0]);
+
+        if compress {
+            data.extend_from_slice(&ZlibEncoder::new(level).encode(text.as_bytes()));
+        } else {
+            data.extend_from_slice(text.as_bytes());
+        }
+        self.write(b"iTXt", &data);
+    }
+
+    /// Writes compressed image data as IDAT chunks for the default image, or as fdAT chunks for
+    /// the other frames of an animation.
+    pub fn write_image_data(&mut self, compressed: &[u8], default_image: bool) {
+        for part in compressed.chunks(IMAGE_DATA_CHUNK_SIZE) {
+            if default_image {
+                self.write(b"IDAT", part);
+            } else {
+                let mut data = Vec::with_capacity(part.len() + 4);
+                data.extend_from_slice(&self.next_sequence().to_be_bytes());
+                data.extend_from_slice(part);
+                self.write(b"fdAT", &data);
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.write(b"IEND", &[]);
+        self.data
+    }
+
+    fn next_sequence(&mut self) -> u32 {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        sequence
+    }
+}
+
+/// Checks that `keyword` is 1-79 printable Latin-1 characters without leading, trailing or
+/// consecutive spaces.
+pub fn is_valid_keyword(keyword: &str) -> bool {
+    let Some(bytes) = to_latin1(keyword) else {
+        return false;
+    };
+
+    (1..=79).contains(&bytes.len())
+        && bytes.iter().all(|&b| (32..=126).contains(&b) || b >= 161)
+        && !keyword.starts_with(' ')
+        && !keyword.ends_with(' ')
+        && !keyword.contains("  ")
+}
+
+fn to_latin1(text: &str) -> Option<Vec<u8>> {
+    text.chars().map(|c| u8::try_from(c as u32).ok()).collect()
+}
@@ -0,0 +1,519 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use super::chunks::{is_valid_keyword, ChunkWriter, XMP_KEYWORD};
+use super::filters::FilterEncoder;
+use crate::decoders::png::types::{Chromaticities, ColorType, FilterType, RenderingIntent};
+use crate::log_warn;
+use crate::utils::convert;
+use crate::utils::deflate::{ZlibEncoder, DEFAULT_LEVEL};
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::{Image, ImageFrame, PixelFormat};
+
+/// Adam7 passes as (x offset, y offset, x step, y step).
+const ADAM7_PASSES: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Options for [`PngEncoder`].
+#[derive(Debug, Clone)]
+pub struct PngOptions {
+    /// Colour type to write. `None` picks the one matching the pixel format of the first frame.
+    ///
+    /// Images with alpha written as [`ColorType::Grayscale`] or [`ColorType::RGB`] keep their
+    /// transparency as a tRNS colour key when every pixel is fully opaque or fully transparent,
+    /// otherwise the alpha channel is dropped. [`ColorType::Indexed`] needs the frames to have
+    /// at most 256 distinct colours between them.
+    pub color_type: Option<ColorType>,
+    /// Bits per sample. `None` picks 1 for [`PixelFormat::L1`], 16 for formats with more than
+    /// 8 bits per sample, the smallest depth that holds the palette for indexed images and 8
+    /// otherwise.
+    pub bit_depth: Option<u8>,
+    /// Writes the image with Adam7 interlacing.
+    pub interlaced: bool,
+    /// Deflate compression level from 0 to 9.
+    pub compression_level: u8,
+    /// Filter applied to every row. `None` picks the filter for each row, or no filter for
+    /// indexed images and bit depths below 8.
+    pub filter: Option<FilterType>,
+    /// Gamma to store in a gAMA chunk, such as 0.45455 for the sRGB curve.
+    pub gamma: Option<f32>,
+    /// Primaries and white point to store in a cHRM chunk.
+    pub chromaticities: Option<Chromaticities>,
+    /// Marks the image as sRGB with this rendering intent. Ignored when the image has an ICC
+    /// profile, which is written instead.
+    pub rendering_intent: Option<RenderingIntent>,
+    /// Number of times an animation plays, 0 to loop forever.
+    pub num_plays: u32,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            color_type: None,
+            bit_depth: None,
+            interlaced: false,
+            compression_level: DEFAULT_LEVEL,
+            filter: None,
+            gamma: None,
+            chromaticities: None,
+            rendering_intent: None,
+            num_plays: 0,
+        }
+    }
+}
+
+/// Samples of every frame, reduced to the colour type and bit depth of the file.
+struct Converted {
+    /// Samples of each frame scaled to `bit_depth`, or palette indices for indexed images.
+    frames: Vec<Vec<u16>>,
+    bit_depth: u8,
+    palette: Option<Vec<[u8; 4]>>,
+    /// Contents of the tRNS chunk.
+    transparency: Option<Vec<u8>>,
+}
+
+/// Writes images as PNG, or as APNG when they have more than one frame and at least one of
+/// them has a delay. Frames without delays, such as TIFF pages or ICO variants, are not an
+/// animation and only the first one is written.
+///
+/// The ICC profile, XMP packets, text entries and resolution of the image metadata are written
+/// along with the pixels. gAMA, cHRM and sRGB chunks only come from [`PngOptions`], they are
+/// not taken from the image.
+pub struct PngEncoder {
+    options: PngOptions,
+}
+
+impl PngEncoder {
+    pub fn new(options: PngOptions) -> Self {
+        Self { options }
+    }
+
+    /// Encodes `image` into a PNG file.
+    ///
+    /// The first frame has to cover the whole image. Later frames are placed at the top left
+    /// corner and replace the area they cover, each shown for its delay.
+    pub fn encode(&self, image: &Image) -> VexelResult<Vec<u8>> {
+        let width = image.width();
+        let height = image.height();
+
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(VexelError::InvalidDimensions { width, height });
+        }
+
+        let mut frames: &[ImageFrame] = image.frames();
+        let first = frames.first().ok_or_else(|| VexelError::Custom("Image has no frames to encode".to_string()))?;
+
+        if frames.len() > 1 && frames.iter().all(|frame| frame.delay() == 0) {
+            log_warn!("None of the {} frames has a delay, only the first one is written", frames.len());
+            frames = &frames[..1];
+        }
+
+        if first.width() != width || first.height() != height {
+            return Err(VexelError::Custom(format!(
+                "The first frame is {}x{} but a PNG's first frame has to cover the whole {}x{} image",
+                first.width(),
+                first.height(),
+                width,
+                height
+            )));
+        }
+
+        if let Some(frame) = frames.iter().find(|frame| frame.width() > width || frame.height() > height) {
+            return Err(VexelError::Custom(format!(
+                "A {}x{} frame does not fit in the {}x{} image",
+                frame.width(),
+                frame.height(),
+                width,
+                height
+            )));
+        }
+
+        let color_type = self.options.color_type.unwrap_or_else(|| default_color_type(&first.pixel_format()));
+        let converted = match color_type {
+            ColorType::Indexed => self.to_indexed(frames)?,
+            _ => self.to_samples(frames, color_type)?,
+        };
+
+        let level = self.options.compression_level.min(9);
+        let animated = frames.len() > 1;
+        let mut writer = ChunkWriter::new();
+
+        writer.write_ihdr(width, height, converted.bit_depth, color_type, self.options.interlaced);
+        if animated {
+            writer.write_actl(frames.len() as u32, self.options.num_plays);
+        }
+
+        self.write_metadata(&mut writer, image, level);
+
+        if let Some(palette) = &converted.palette {
+            writer.write_plte(palette);
+        }
+        if let Some(transparency) = &converted.transparency {
+            writer.write(b"tRNS", transparency);
+        }
+
+        let channels = channel_count(color_type);
+        let bits_per_pixel = converted.bit_depth as u32 * channels as u32;
+        let filter = match self.options.filter {
+            None if color_type == ColorType::Indexed || converted.bit_depth < 8 => Some(FilterType::None),
+            filter => filter,
+        };
+        let filter_encoder = FilterEncoder::new(bits_per_pixel, filter);
+
+        for (index, (frame, samples)) in frames.iter().zip(&converted.frames).enumerate() {
+            if animated {
+                writer.write_fctl(frame.width(), frame.height(), frame.delay());
+            }
+
+            let raw = self.filter_frame(samples, frame, channels, converted.bit_depth, &filter_encoder);
+            writer.write_image_data(&ZlibEncoder::new(level).encode(&raw), index == 0);
+        }
+
+        Ok(writer.finish())
+    }
+
+    /// Encodes `image` into a PNG file and writes it to `writer`.
+    pub fn write<W: Write>(&self, image: &Image, writer: &mut W) -> VexelResult<()> {
+        writer.write_all(&self.encode(image)?)?;
+        Ok(())
+    }
+
+    fn write_metadata(&self, writer: &mut ChunkWriter, image: &Image, level: u8) {
+        let metadata = image.metadata();
+
+        if let Some(chromaticities) = &self.options.chromaticities {
+            writer.write_chrm(chromaticities);
+        }
+        if let Some(gamma) = self.options.gamma {
+            writer.write_gama(gamma);
+        }
+
+        // iCCP and sRGB are mutually exclusive
+        match (&metadata.icc_data, self.options.rendering_intent) {
+            (Some(profile), _) => writer.write_iccp(profile, level),
+            (None, Some(intent)) => writer.write_srgb(intent),
+            (None, None) => {}
+        }
+
+        if let Some(resolution) = &metadata.resolution {
+            writer.write_phys(resolution);
+        }
+
+        for entry in &metadata.text {
+            if is_valid_keyword(&entry.key) {
+                writer.write_text(&entry.key, &entry.value, level);
+            } else {
+                log_warn!("Skipping text entry with invalid PNG keyword: {:?}", entry.key);
+            }
+        }
+
+        for packet in &metadata.xmp {
+            writer.write_itxt(XMP_KEYWORD, packet, false, level);
+        }
+    }
+
+    /// Converts the frames to samples of a non-indexed colour type.
+    fn to_samples(&self, frames: &[ImageFrame], color_type: ColorType) -> VexelResult<Converted> {
+        let bit_depth = match self.options.bit_depth {
+            Some(bit_depth) => bit_depth,
+            None => match frames[0].pixel_format() {
+                PixelFormat::L1 if color_type == ColorType::Grayscale => 1,
+                PixelFormat::L1 | PixelFormat::L8 | PixelFormat::LA8 | PixelFormat::RGB8 | PixelFormat::RGBA8 => 8,
+                _ => 16,
+            },
+        };
+        check_bit_depth(color_type, bit_depth)?;
+
+        let channels = channel_count(color_type);
+        // Transparency of colour types without alpha can only be kept as a colour key
+        let keyed = matches!(color_type, ColorType::Grayscale | ColorType::RGB) && frames.iter().any(|frame| frame.has_alpha());
+        let stride = channels + keyed as usize;
+        let wide = bit_depth == 16;
+        let max = (1u32 << bit_depth) - 1;
+
+        let mut converted = Vec::with_capacity(frames.len());
+        for frame in frames {
+            let buffer = convert_frame(frame, &sample_format(stride, wide));
+            let mut samples: Vec<u16> = if wide {
+                buffer.chunks_exact(2).map(|bytes| u16::from_ne_bytes([bytes[0], bytes[1]])).collect()
+            } else {
+                buffer.iter().map(|&value| value as u16).collect()
+            };
+
+            if bit_depth < 8 {
+                // Only the gray sample, the alpha of a keyed image stays at 8 bits
+                for sample in samples.iter_mut().step_by(stride) {
+                    *sample = ((*sample as u32 * max + 127) / 255) as u16;
+                }
+            }
+
+            converted.push(samples);
+        }
+
+        let transparency = if keyed {
+            apply_color_key(&mut converted, channels, bit_depth, if wide { u16::MAX } else { 255 })
+        } else {
+            None
+        };
+
+        Ok(Converted {
+            frames: converted,
+            bit_depth,
+            palette: None,
+            transparency,
+        })
+    }
+
+    /// Converts the frames to palette indices, with the palette entries that are not fully
+    /// opaque placed first to keep the tRNS chunk short.
+    fn to_indexed(&self, frames: &[ImageFrame]) -> VexelResult<Converted> {
+        let rgba_frames: Vec<Vec<u8>> = frames.iter().map(|frame| convert_frame(frame, &PixelFormat::RGBA8)).collect();
+
+        let mut colors = Vec::new();
+        let mut seen = HashSet::new();
+        for pixel in rgba_frames.iter().flat_map(|rgba| rgba.chunks_exact(4)) {
+            let color = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            if seen.insert(color) {
+                if colors.len() == 256 {
+                    return Err(VexelError::Custom(
+                        "Image has more than 256 colours, which do not fit in a PNG palette".to_string(),
+                    ));
+                }
+                colors.push(color);
+            }
+        }
+
+        // Stable, so the colours keep the order they first appear in otherwise
+        colors.sort_by_key(|color| color[3] == 255);
+
+        let smallest_depth = [1u8, 2, 4, 8].into_iter().find(|&depth| colors.len() <= 1 << depth).unwrap_or(8);
+        let bit_depth = self.options.bit_depth.unwrap_or(smallest_depth);
+        check_bit_depth(ColorType::Indexed, bit_depth)?;
+
+        if bit_depth < smallest_depth {
+            return Err(VexelError::Custom(format!(
+                "A palette of {} colours does not fit in {}-bit indices",
+                colors.len(),
+                bit_depth
+            )));
+        }
+
+        let indices: HashMap<[u8; 4], u16> = colors.iter().enumerate().map(|(index, &color)| (color, index as u16)).collect();
+        let frames = rgba_frames
+            .iter()
+            .map(|rgba| rgba.chunks_exact(4).map(|pixel| indices[&[pixel[0], pixel[1], pixel[2], pixel[3]]]).collect())
+            .collect();
+
+        let alphas: Vec<u8> = colors.iter().map(|color| color[3]).take_while(|&alpha| alpha != 255).collect();
+
+        Ok(Converted {
+            frames,
+            bit_depth,
+            palette: Some(colors),
+            transparency: if alphas.is_empty() { None } else { Some(alphas) },
+        })
+    }
+
+    /// Packs the rows of a frame, or of each of its Adam7 passes, and filters them.
+    fn filter_frame(&self, samples: &[u16], frame: &ImageFrame, channels: usize, bit_depth: u8, filter: &FilterEncoder) -> Vec<u8> {
+        let width = frame.width() as usize;
+        let height = frame.height() as usize;
+        let mut raw = Vec::new();
+
+        if !self.options.interlaced {
+            filter_rows(samples, width, channels, bit_depth, filter, &mut raw);
+            return raw;
+        }
+
+        for (x_offset, y_offset, x_step, y_step) in ADAM7_PASSES {
+            let pass_width = width.saturating_sub(x_offset).div_ceil(x_step);
+            let pass_height = height.saturating_sub(y_offset).div_ceil(y_step);
+
+            // Empty passes have no rows and no filter bytes
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+
+            let mut pass = Vec::with_capacity(pass_width * pass_height * channels);
+            for y in (y_offset..height).step_by(y_step) {
+                for x in (x_offset..width).step_by(x_step) {
+                    let start = (y * width + x) * channels;
+                    pass.extend_from_slice(&samples[start..start + channels]);
+                }
+            }
+
+            filter_rows(&pass, pass_width, channels, bit_depth, filter, &mut raw);
+        }
+
+        raw
+    }
+}
+
+fn default_color_type(format: &PixelFormat) -> ColorType {
+    match format {
+        PixelFormat::L1 | PixelFormat::L8 | PixelFormat::L16 | PixelFormat::L32F | PixelFormat::L64F => ColorType::Grayscale,
+        PixelFormat::LA8 | PixelFormat::LA16 | PixelFormat::LA32F | PixelFormat::LA64F => ColorType::GrayscaleAlpha,
+        PixelFormat::RGB8 | PixelFormat::RGB16 | PixelFormat::RGB32F | PixelFormat::RGB64F => ColorType::RGB,
+        PixelFormat::RGBA8 | PixelFormat::RGBA16 | PixelFormat::RGBA32F | PixelFormat::RGBA64F => ColorType::RGBA,
+    }
+}
+
+fn channel_count(color_type: ColorType) -> usize {
+    match color_type {
+        ColorType::Grayscale | ColorType::Indexed => 1,
+        ColorType::GrayscaleAlpha => 2,
+        ColorType::RGB => 3,
+        ColorType::RGBA => 4,
+    }
+}
+
+fn check_bit_depth(color_type: ColorType, bit_depth: u8) -> VexelResult<()> {
+    let allowed: &[u8] = match color_type {
+        ColorType::Grayscale => &[1, 2, 4, 8, 16],
+        ColorType::Indexed => &[1, 2, 4, 8],
+        ColorType::RGB | ColorType::GrayscaleAlpha | ColorType::RGBA => &[8, 16],
+    };
+
+    if allowed.contains(&bit_depth) {
+        Ok(())
+    } else {
+        Err(VexelError::Custom(format!(
+            "PNG colour type {:?} does not allow a bit depth of {}",
+            color_type, bit_depth
+        )))
+    }
+}
+
+/// Returns the pixel format with `channels` samples of 8 bits, or of 16 bits if `wide`.
+fn sample_format(channels: usize, wide: bool) -> PixelFormat {
+    match (channels, wide) {
+        (1, false) => PixelFormat::L8,
+        (1, true) => PixelFormat::L16,
+        (2, false) => PixelFormat::LA8,
+        (2, true) => PixelFormat::LA16,
+        (3, false) => PixelFormat::RGB8,
+        (3, true) => PixelFormat::RGB16,
+        (_, false) => PixelFormat::RGBA8,
+        (_, true) => PixelFormat::RGBA16,
+    }
+}
+
+fn convert_frame(frame: &ImageFrame, format: &PixelFormat) -> Vec<u8> {
+    let stride = frame.width() as usize * format.bytes_per_pixel();
+    let mut buffer = vec![0u8; stride * frame.height() as usize];
+    convert::write_pixels(frame.pixels(), frame.width(), frame.height(), &mut buffer, stride, format);
+    buffer
+}
+
+/// Removes the alpha sample that follows the `channels` colour samples of every pixel, giving
+/// the transparent pixels a colour no opaque pixel uses. Returns the tRNS chunk holding that
+/// colour, or `None` if the image is opaque or the transparency can't be kept.
+fn apply_color_key(frames: &mut [Vec<u16>], channels: usize, bit_depth: u8, alpha_max: u16) -> Option<Vec<u8>> {
+    let stride = channels + 1;
+    let color_of = |pixel: &[u16]| {
+        let mut color = [0u16; 3];
+        color[..channels].copy_from_slice(&pixel[..channels]);
+        color
+    };
+
+    let mut opaque = HashSet::new();
+    let mut first_transparent = None;
+    let mut binary = true;
+
+    for pixel in frames.iter().flat_map(|samples| samples.chunks_exact(stride)) {
+        match pixel[channels] {
+            alpha if alpha == alpha_max => {
+                opaque.insert(color_of(pixel));
+            }
+            0 => {
+                first_transparent.get_or_insert(color_of(pixel));
+            }
+            _ => binary = false,
+        }
+    }
+
+    let key = match first_transparent {
+        Some(_) if !binary => {
+            log_warn!("Dropping the alpha channel, PNG colour type without alpha can't keep partial transparency");
+            None
+        }
+        Some(color) if !opaque.contains(&color) => Some(color),
+        Some(_) => {
+            // Every colour of a gray image may be taken, but the search ends after at most one
+            // candidate per opaque colour
+            let max = (1u64 << bit_depth) - 1;
+            let color_count = 1u64 << (bit_depth as u32 * channels as u32).min(63);
+            let key = (0..color_count)
+                .map(|index| {
+                    let mut color = [0u16; 3];
+                    for (channel, sample) in color.iter_mut().take(channels).enumerate() {
+                        *sample = ((index >> (channel as u32 * bit_depth as u32)) & max) as u16;
+                    }
+                    color
+                })
+                .find(|color| !opaque.contains(color));
+
+            if key.is_none() {
+                log_warn!("Dropping the alpha channel, every colour is used by an opaque pixel");
+            }
+            key
+        }
+        None => None,
+    };
+
+    for samples in frames.iter_mut() {
+        *samples = samples
+            .chunks_exact(stride)
+            .flat_map(|pixel| {
+                let color = match key {
+                    Some(key) if pixel[channels] == 0 => key,
+                    _ => color_of(pixel),
+                };
+                color.into_iter().take(channels)
+            })
+            .collect();
+    }
+
+    key.map(|key| key[..channels].iter().flat_map(|sample| sample.to_be_bytes()).collect())
+}
+
+/// Packs rows of `width` pixels into bytes and appends them filtered to `out`.
+fn filter_rows(samples: &[u16], width: usize, channels: usize, bit_depth: u8, filter: &FilterEncoder, out: &mut Vec<u8>) {
+    let row_samples = width * channels;
+    let row_bytes = (row_samples * bit_depth as usize).div_ceil(8);
+    let mut packed = Vec::with_capacity(row_bytes * (samples.len() / row_samples));
+
+    for row in samples.chunks_exact(row_samples) {
+        match bit_depth {
+            16 => packed.extend(row.iter().flat_map(|sample| sample.to_be_bytes())),
+            8 => packed.extend(row.iter().map(|&sample| sample as u8)),
+            _ => {
+                // Pixels below 8 bits are packed from the most significant bit, with the last
+                // byte of a row padded with zeros
+                let mut byte = 0u8;
+                let mut bits = 0;
+                for &sample in row {
+                    byte = (byte << bit_depth) | sample as u8;
+                    bits += bit_depth;
+                    if bits == 8 {
+                        packed.push(byte);
+                        byte = 0;
+                        bits = 0;
+                    }
+                }
+                if bits > 0 {
+                    packed.push(byte << (8 - bits));
+                }
+            }
+        }
+    }
+
+    filter.encode(&packed, row_bytes, out);
+}
@@ -0,0 +1,101 @@
+use crate::decoders::png::filters::paeth_predictor;
+use crate::decoders::png::types::FilterType;
+
+const FILTER_TYPES: [FilterType; 5] = [
+    FilterType::None,
+    FilterType::Sub,
+    FilterType::Up,
+    FilterType::Average,
+    FilterType::Paeth,
+];
+
+pub struct FilterEncoder {
+    bytes_per_pixel: usize,
+    filter: Option<FilterType>,
+}
+
+impl FilterEncoder {
+    /// Creates an encoder that applies `filter` to every row, or picks the best filter for each
+    /// row if it is `None`.
+    pub fn new(bits_per_pixel: u32, filter: Option<FilterType>) -> Self {
+        Self {
+            bytes_per_pixel: (bits_per_pixel as usize).div_ceil(8),
+            filter,
+        }
+    }
+
+    /// Filters `data`, made of rows of `row_bytes` each, and appends each row with its filter
+    /// type byte in front to `out`.
+    pub fn encode(&self, data: &[u8], row_bytes: usize, out: &mut Vec<u8>) {
+        let zero_row = vec![0u8; row_bytes];
+        let mut best = vec![0u8; row_bytes];
+        let mut candidate = vec![0u8; row_bytes];
+        let mut prior: &[u8] = &zero_row;
+
+        out.reserve(data.len() + data.len() / row_bytes.max(1));
+
+        for row in data.chunks_exact(row_bytes) {
+            let filter = match self.filter {
+                Some(filter) => {
+                    self.apply_filter(filter, row, prior, &mut best);
+                    filter
+                }
+                None => {
+                    // Same heuristic as libpng, the filter with the smallest sum of the
+                    // filtered bytes taken as signed values usually compresses best
+                    let mut best_filter = FilterType::None;
+                    let mut best_score = u64::MAX;
+
+                    for filter in FILTER_TYPES {
+                        self.apply_filter(filter, row, prior, &mut candidate);
+                        let score = candidate.iter().map(|&b| (b as i8).unsigned_abs() as u64).sum();
+
+                        if score < best_score {
+                            best_score = score;
+                            best_filter = filter;
+                            std::mem::swap(&mut best, &mut candidate);
+                        }
+                    }
+
+                    best_filter
+                }
+            };
+
+            out.push(filter as u8);
+            out.extend_from_slice(&best);
+            prior = row;
+        }
+    }
+
+    fn apply_filter(&self, filter: FilterType, row: &[u8], prior: &[u8], out: &mut [u8]) {
+        let bpp = self.bytes_per_pixel;
+
+        match filter {
+            FilterType::None => out.copy_from_slice(row),
+            FilterType::Sub => {
+                for i in 0..row.len() {
+                    let left = if i >= bpp { row[i - bpp] } else { 0 };
+                    out[i] = row[i].wrapping_sub(left);
+                }
+            }
+            FilterType::Up => {
+                for i in 0..row.len() {
+                    out[i] = row[i].wrapping_sub(prior[i]);
+                }
+            }
+            FilterType::Average => {
+                for i in 0..row.len() {
+                    let left = if i >= bpp { row[i - bpp] } else { 0 };
+                    let average = ((left as u16 + prior[i] as u16) / 2) as u8;
+                    out[i] = row[i].wrapping_sub(average);
+                }
+            }
+            FilterType::Paeth => {
+                for i in 0..row.len() {
+                    let (left, upper_left) = if i >= bpp { (row[i - bpp], prior[i - bpp]) } else { (0, 0) };
+                    out[i] = row[i].wrapping_sub(paeth_predictor(left, prior[i], upper_left));
+                }
+            }
+        }
+    }
+}
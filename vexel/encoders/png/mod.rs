@@ -0,0 +1,6 @@
+mod chunks;
+mod encoder;
+mod filters;
+
+pub use crate::decoders::png::types::{Chromaticities, ColorType, FilterType, RenderingIntent};
+pub use encoder::{PngEncoder, PngOptions};
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::utils::types::ByteOrder;
+
+const LZW_CLEAR_CODE: u16 = 256;
+const LZW_EOI_CODE: u16 = 257;
+const LZW_FIRST_CODE: u16 = 258;
+const LZW_MIN_BITS: u32 = 9;
+const LZW_MAX_BITS: u32 = 12;
+/// The table is reset once it reaches this code, one short of the last 12-bit code.
+const LZW_LAST_CODE: u16 = (1 << LZW_MAX_BITS) - 2;
+
+struct BitWriterMsb {
+    output: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriterMsb {
+    fn new() -> Self {
+        BitWriterMsb {
+            output: Vec::new(),
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, code: u16, nbits: u32) {
+        self.buffer = (self.buffer << nbits) | code as u32;
+        self.bits += nbits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.output.push((self.buffer >> self.bits) as u8);
+        }
+        self.buffer &= (1 << self.bits) - 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.output.push((self.buffer << (8 - self.bits)) as u8);
+        }
+        self.output
+    }
+}
+
+/// Compresses `data` with the MSB-first LZW variant of TIFF 6.0. Code widths grow one code
+/// early, like libtiff and the decoder in `decompress_lzw` expect.
+pub fn compress_lzw(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriterMsb::new();
+    let mut table: HashMap<u32, u16> = HashMap::new();
+    let mut free = LZW_FIRST_CODE;
+    let mut nbits = LZW_MIN_BITS;
+
+    writer.write(LZW_CLEAR_CODE, nbits);
+
+    let Some((&first, rest)) = data.split_first() else {
+        writer.write(LZW_EOI_CODE, nbits);
+        return writer.finish();
+    };
+
+    let mut prefix = first as u16;
+    for &byte in rest {
+        let key = ((prefix as u32) << 8) | byte as u32;
+        if let Some(&code) = table.get(&key) {
+            prefix = code;
+            continue;
+        }
+
+        writer.write(prefix, nbits);
+        table.insert(key, free);
+        free += 1;
+
+        if free == LZW_LAST_CODE {
+            writer.write(LZW_CLEAR_CODE, nbits);
+            table.clear();
+            free = LZW_FIRST_CODE;
+            nbits = LZW_MIN_BITS;
+        } else if free as u32 > (1 << nbits) - 1 {
+            nbits += 1;
+        }
+
+        prefix = byte as u16;
+    }
+
+    // The decoder still adds an entry for the last code, which can widen the EOI code
+    writer.write(prefix, nbits);
+    free += 1;
+    if free == LZW_LAST_CODE {
+        writer.write(LZW_CLEAR_CODE, nbits);
+        nbits = LZW_MIN_BITS;
+    } else if free as u32 > (1 << nbits) - 1 {
+        nbits += 1;
+    }
+
+    writer.write(LZW_EOI_CODE, nbits);
+    writer.finish()
+}
+
+/// Compresses `data` with PackBits, packing each row of `row_bytes` separately as TIFF
+/// requires.
+pub fn compress_packbits(data: &[u8], row_bytes: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() + data.len() / 128 + 1);
+
+    for row in data.chunks(row_bytes.max(1)) {
+        let mut i = 0;
+        while i < row.len() {
+            let mut run = 1;
+            while i + run < row.len() && run < 128 && row[i + run] == row[i] {
+                run += 1;
+            }
+
+            if run >= 2 {
+                output.push((1 - run as i16) as u8);
+                output.push(row[i]);
+                i += run;
+                continue;
+            }
+
+            // Literal bytes up to the next run
+            let start = i;
+            while i < row.len() && i - start < 128 {
+                if i + 1 < row.len() && row[i] == row[i + 1] {
+                    break;
+                }
+                i += 1;
+            }
+
+            output.push((i - start - 1) as u8);
+            output.extend_from_slice(&row[start..i]);
+        }
+    }
+
+    output
+}
+
+/// Replaces each sample with its difference to the same sample of the pixel to its left, the
+/// inverse of `apply_predictor_horizontal`. Only 8 and 16-bit samples are supported.
+pub fn encode_predictor_horizontal(data: &mut [u8], width: u32, samples_per_pixel: u16, bits_per_sample: u16, byte_order: ByteOrder) {
+    let spp = samples_per_pixel as usize;
+    let row_samples = width as usize * spp;
+    if row_samples == 0 {
+        return;
+    }
+
+    match bits_per_sample {
+        8 => {
+            for row in data.chunks_exact_mut(row_samples) {
+                for i in (spp..row_samples).rev() {
+                    row[i] = row[i].wrapping_sub(row[i - spp]);
+                }
+            }
+        }
+        16 => {
+            let read = |bytes: &[u8]| match byte_order {
+                ByteOrder::LittleEndian => u16::from_le_bytes([bytes[0], bytes[1]]),
+                ByteOrder::BigEndian => u16::from_be_bytes([bytes[0], bytes[1]]),
+            };
+            let write = |value: u16| match byte_order {
+                ByteOrder::LittleEndian => value.to_le_bytes(),
+                ByteOrder::BigEndian => value.to_be_bytes(),
+            };
+
+            for row in data.chunks_exact_mut(row_samples * 2) {
+                for i in (spp..row_samples).rev() {
+                    let value = read(&row[i * 2..]).wrapping_sub(read(&row[(i - spp) * 2..]));
+                    row[i * 2..i * 2 + 2].copy_from_slice(&write(value));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Splits the floating-point samples of each row into byte planes, most significant first, and
+/// differences the bytes, the inverse of `apply_predictor_float`.
+pub fn encode_predictor_float(data: &mut [u8], width: u32, samples_per_pixel: u16, bits_per_sample: u16, byte_order: ByteOrder) {
+    let bps = bits_per_sample as usize / 8;
+    let spp = samples_per_pixel as usize;
+    let row_samples = width as usize * spp;
+    let row_stride = row_samples * bps;
+
+    if row_stride == 0 {
+        return;
+    }
+
+    let mut planes = vec![0u8; row_stride];
+    for row in data.chunks_exact_mut(row_stride) {
+        for sample in 0..row_samples {
+            for byte in 0..bps {
+                let plane = match byte_order {
+                    ByteOrder::BigEndian => byte,
+                    ByteOrder::LittleEndian => bps - byte - 1,
+                };
+                planes[plane * row_samples + sample] = row[sample * bps + byte];
+            }
+        }
+
+        for i in (spp..row_stride).rev() {
+            planes[i] = planes[i].wrapping_sub(planes[i - spp]);
+        }
+
+        row.copy_from_slice(&planes);
+    }
+}
@@ -0,0 +1,424 @@
+use std::io::Write;
+
+use super::compression::{compress_lzw, compress_packbits, encode_predictor_float, encode_predictor_horizontal};
+use super::ifd::{IfdEntry, TagValue, TiffWriter};
+use crate::decoders::tiff::types::{
+    Compression, ExtraSampleType, PhotometricInterpretation, PlanarConfiguration, Predictor, SampleFormat, TiffTags,
+};
+use crate::utils::deflate::{ZlibEncoder, DEFAULT_LEVEL};
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::{Image, ImageFrame, PixelData, PixelFormat};
+use crate::utils::metadata::{ImageMetadata, ResolutionUnit};
+use crate::utils::types::ByteOrder;
+
+/// Size strips are cut to when no `rows_per_strip` is given, the same as libtiff.
+const STRIP_SIZE: usize = 8192;
+
+/// Options for [`TiffEncoder`].
+#[derive(Debug, Clone)]
+pub struct TiffOptions {
+    /// Compression of the strips or tiles. [`Compression::None`], [`Compression::LZW`],
+    /// [`Compression::PackBits`], [`Compression::AdobeDeflate`] and [`Compression::Deflate`] are
+    /// supported.
+    pub compression: Compression,
+    /// Deflate compression level from 0 to 9.
+    pub compression_level: u8,
+    /// Predictor applied before compression. [`Predictor::HorizontalDifferencing`] works on 8
+    /// and 16-bit integer samples, [`Predictor::FloatingPoint`] on float samples.
+    pub predictor: Predictor,
+    /// Whether the samples of a pixel are stored together or in a separate plane per channel.
+    pub planar_configuration: PlanarConfiguration,
+    /// Width and height of tiles, both multiples of 16. `None` writes strips.
+    pub tile_size: Option<(u32, u32)>,
+    /// Rows in each strip. `None` cuts strips of about 8 KiB.
+    pub rows_per_strip: Option<u32>,
+    /// Writes BigTIFF, with 64-bit offsets for files larger than 4 GiB.
+    pub big_tiff: bool,
+    pub byte_order: ByteOrder,
+}
+
+impl Default for TiffOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::None,
+            compression_level: DEFAULT_LEVEL,
+            predictor: Predictor::None,
+            planar_configuration: PlanarConfiguration::Chunky,
+            tile_size: None,
+            rows_per_strip: None,
+            big_tiff: false,
+            byte_order: ByteOrder::LittleEndian,
+        }
+    }
+}
+
+/// How the samples of a frame are stored.
+struct SampleLayout {
+    channels: usize,
+    bits_per_sample: u16,
+    is_float: bool,
+    has_alpha: bool,
+}
+
+impl SampleLayout {
+    fn of(format: &PixelFormat) -> Self {
+        let bits_per_sample = match format {
+            PixelFormat::L1 => 1,
+            PixelFormat::L8 | PixelFormat::LA8 | PixelFormat::RGB8 | PixelFormat::RGBA8 => 8,
+            PixelFormat::L16 | PixelFormat::LA16 | PixelFormat::RGB16 | PixelFormat::RGBA16 => 16,
+            PixelFormat::L32F | PixelFormat::LA32F | PixelFormat::RGB32F | PixelFormat::RGBA32F => 32,
+            PixelFormat::L64F | PixelFormat::LA64F | PixelFormat::RGB64F | PixelFormat::RGBA64F => 64,
+        };
+        let channels = format.channels();
+
+        SampleLayout {
+            channels,
+            bits_per_sample,
+            is_float: bits_per_sample >= 32,
+            has_alpha: channels == 2 || channels == 4,
+        }
+    }
+}
+
+/// Writes images as TIFF, with one IFD per frame.
+///
+/// The resolution, ICC profile and orientation of the image metadata are written to every IFD.
+pub struct TiffEncoder {
+    options: TiffOptions,
+}
+
+impl TiffEncoder {
+    pub fn new(options: TiffOptions) -> Self {
+        Self { options }
+    }
+
+    /// Encodes `image` into a TIFF file.
+    pub fn encode(&self, image: &Image) -> VexelResult<Vec<u8>> {
+        let frames = image.frames();
+        if frames.is_empty() {
+            return Err(VexelError::Custom("Image has no frames to encode".to_string()));
+        }
+
+        self.check_options()?;
+
+        let mut writer = TiffWriter::new(self.options.byte_order, self.options.big_tiff);
+        for (page, frame) in frames.iter().enumerate() {
+            let mut entries = self.write_frame(&mut writer, frame)?;
+            entries.extend(metadata_entries(image.metadata()));
+
+            if frames.len() > 1 {
+                entries.push(IfdEntry::new(
+                    TiffTags::PageNumber,
+                    TagValue::Short(vec![page.min(u16::MAX as usize) as u16, frames.len().min(u16::MAX as usize) as u16]),
+                ));
+            }
+
+            writer.write_ifd(entries)?;
+        }
+
+        Ok(writer.finish())
+    }
+
+    /// Encodes `image` into a TIFF file and writes it to `writer`.
+    pub fn write<W: Write>(&self, image: &Image, writer: &mut W) -> VexelResult<()> {
+        writer.write_all(&self.encode(image)?)?;
+        Ok(())
+    }
+
+    fn check_options(&self) -> VexelResult<()> {
+        let compression = self.options.compression;
+        if !matches!(
+            compression,
+            Compression::None | Compression::LZW | Compression::PackBits | Compression::AdobeDeflate | Compression::Deflate
+        ) {
+            return Err(VexelError::Custom(format!("TIFF encoder does not support {:?} compression", compression)));
+        }
+
+        if let Some((tile_width, tile_height)) = self.options.tile_size {
+            if tile_width == 0 || tile_height == 0 || tile_width % 16 != 0 || tile_height % 16 != 0 {
+                return Err(VexelError::Custom(format!(
+                    "TIFF tile size must be a non-zero multiple of 16, got {}x{}",
+                    tile_width, tile_height
+                )));
+            }
+        }
+
+        if self.options.rows_per_strip == Some(0) {
+            return Err(VexelError::Custom("TIFF strips must have at least one row".to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the strips or tiles of `frame` and returns the IFD entries describing them.
+    fn write_frame(&self, writer: &mut TiffWriter, frame: &ImageFrame) -> VexelResult<Vec<IfdEntry>> {
+        let width = frame.width();
+        let height = frame.height();
+        if width == 0 || height == 0 {
+            return Err(VexelError::InvalidDimensions { width, height });
+        }
+
+        let layout = SampleLayout::of(&frame.pixel_format());
+        let predictor = self.options.predictor;
+
+        match predictor {
+            Predictor::HorizontalDifferencing if layout.is_float || layout.bits_per_sample == 1 => {
+                return Err(VexelError::Custom(format!(
+                    "The horizontal predictor needs 8 or 16-bit integer samples, not {:?}",
+                    frame.pixel_format()
+                )));
+            }
+            Predictor::FloatingPoint if !layout.is_float => {
+                return Err(VexelError::Custom(format!(
+                    "The floating point predictor needs float samples, not {:?}",
+                    frame.pixel_format()
+                )));
+            }
+            _ => {}
+        }
+
+        let planar = self.options.planar_configuration == PlanarConfiguration::Planar && layout.channels > 1;
+        let planes: Vec<Vec<u8>> = if planar {
+            (0..layout.channels).map(|channel| self.sample_bytes(frame, Some(channel))).collect()
+        } else {
+            vec![self.sample_bytes(frame, None)]
+        };
+
+        let plane_channels = if planar { 1 } else { layout.channels };
+        let bits_per_pixel = plane_channels * layout.bits_per_sample as usize;
+        let row_bytes = (width as usize * bits_per_pixel).div_ceil(8);
+
+        let plane_bytes = row_bytes * height as usize;
+        if let Some(plane) = planes.iter().find(|plane| plane.len() < plane_bytes) {
+            return Err(VexelError::Custom(format!(
+                "A {}x{} {:?} frame needs {} bytes of samples per plane, but only has {}",
+                width,
+                height,
+                frame.pixel_format(),
+                plane_bytes,
+                plane.len()
+            )));
+        }
+
+        let mut offsets = Vec::new();
+        let mut byte_counts = Vec::new();
+        let mut entries = Vec::new();
+
+        let mut write_chunk = |mut chunk: Vec<u8>, chunk_width: u32, chunk_row_bytes: usize| -> VexelResult<()> {
+            match predictor {
+                Predictor::HorizontalDifferencing => encode_predictor_horizontal(
+                    &mut chunk,
+                    chunk_width,
+                    plane_channels as u16,
+                    layout.bits_per_sample,
+                    self.options.byte_order,
+                ),
+                Predictor::FloatingPoint => encode_predictor_float(
+                    &mut chunk,
+                    chunk_width,
+                    plane_channels as u16,
+                    layout.bits_per_sample,
+                    self.options.byte_order,
+                ),
+                Predictor::None => {}
+            }
+
+            let compressed = match self.options.compression {
+                Compression::LZW => compress_lzw(&chunk),
+                Compression::PackBits => compress_packbits(&chunk, chunk_row_bytes),
+                Compression::AdobeDeflate | Compression::Deflate => {
+                    ZlibEncoder::new(self.options.compression_level).encode(&chunk)
+                }
+                _ => chunk,
+            };
+
+            offsets.push(writer.write_data(&compressed)?);
+            byte_counts.push(compressed.len() as u64);
+            Ok(())
+        };
+
+        match self.options.tile_size {
+            Some((tile_width, tile_height)) => {
+                let tile_row_bytes = (tile_width as usize * bits_per_pixel).div_ceil(8);
+                let tiles_across = width.div_ceil(tile_width);
+                let tiles_down = height.div_ceil(tile_height);
+
+                for plane in &planes {
+                    for tile_y in 0..tiles_down {
+                        for tile_x in 0..tiles_across {
+                            // Tiles are always full size, the parts outside the image are zero.
+                            // Tile widths are multiples of 16, so tiles start on whole bytes.
+                            let mut tile = vec![0u8; tile_row_bytes * tile_height as usize];
+                            let start = (tile_x * tile_width) as usize * bits_per_pixel / 8;
+                            let length = tile_row_bytes.min(row_bytes - start);
+                            let first_row = (tile_y * tile_height) as usize;
+                            let rows = (tile_height as usize).min(height as usize - first_row);
+
+                            for (y, tile_row) in tile.chunks_exact_mut(tile_row_bytes).take(rows).enumerate() {
+                                let row_start = (first_row + y) * row_bytes + start;
+                                tile_row[..length].copy_from_slice(&plane[row_start..row_start + length]);
+                            }
+
+                            write_chunk(tile, tile_width, tile_row_bytes)?;
+                        }
+                    }
+                }
+
+                entries.push(IfdEntry::new(TiffTags::TileWidth, TagValue::Long(vec![tile_width])));
+                entries.push(IfdEntry::new(TiffTags::TileLength, TagValue::Long(vec![tile_height])));
+                entries.push(IfdEntry::new(TiffTags::TileOffsets, TagValue::Offsets(offsets)));
+                entries.push(IfdEntry::new(TiffTags::TileByteCounts, TagValue::Offsets(byte_counts)));
+            }
+            None => {
+                let rows_per_strip = self
+                    .options
+                    .rows_per_strip
+                    .unwrap_or((STRIP_SIZE / row_bytes).max(1) as u32)
+                    .min(height);
+                let strip_bytes = rows_per_strip as usize * row_bytes;
+
+                for plane in &planes {
+                    for strip in plane.chunks(strip_bytes) {
+                        write_chunk(strip.to_vec(), width, row_bytes)?;
+                    }
+                }
+
+                entries.push(IfdEntry::new(TiffTags::RowsPerStrip, TagValue::Long(vec![rows_per_strip])));
+                entries.push(IfdEntry::new(TiffTags::StripOffsets, TagValue::Offsets(offsets)));
+                entries.push(IfdEntry::new(TiffTags::StripByteCounts, TagValue::Offsets(byte_counts)));
+            }
+        }
+
+        let photometric = if layout.channels >= 3 {
+            PhotometricInterpretation::RGB
+        } else {
+            PhotometricInterpretation::BlackIsZero
+        };
+
+        entries.push(IfdEntry::new(TiffTags::ImageWidth, TagValue::Long(vec![width])));
+        entries.push(IfdEntry::new(TiffTags::ImageLength, TagValue::Long(vec![height])));
+        entries.push(IfdEntry::new(
+            TiffTags::BitsPerSample,
+            TagValue::Short(vec![layout.bits_per_sample; layout.channels]),
+        ));
+        entries.push(IfdEntry::new(
+            TiffTags::Compression,
+            TagValue::Short(vec![self.options.compression as u16]),
+        ));
+        entries.push(IfdEntry::new(TiffTags::PhotometricInterpretation, TagValue::Short(vec![photometric as u16])));
+        entries.push(IfdEntry::new(TiffTags::SamplesPerPixel, TagValue::Short(vec![layout.channels as u16])));
+        entries.push(IfdEntry::new(
+            TiffTags::PlanarConfiguration,
+            TagValue::Short(vec![if planar { PlanarConfiguration::Planar } else { PlanarConfiguration::Chunky } as u16]),
+        ));
+
+        if predictor != Predictor::None {
+            entries.push(IfdEntry::new(TiffTags::Predictor, TagValue::Short(vec![predictor as u16])));
+        }
+        if layout.has_alpha {
+            entries.push(IfdEntry::new(
+                TiffTags::ExtraSamples,
+                TagValue::Short(vec![ExtraSampleType::UnassociatedAlpha as u16]),
+            ));
+        }
+        if layout.is_float {
+            entries.push(IfdEntry::new(
+                TiffTags::SampleFormat,
+                TagValue::Short(vec![SampleFormat::Float as u16; layout.channels]),
+            ));
+        }
+
+        Ok(entries)
+    }
+
+    /// Returns the samples of `frame` in file byte order, one row after another. Only the
+    /// samples of `channel` are returned if it is given. 1-bit rows are packed from the most
+    /// significant bit and padded to whole bytes.
+    fn sample_bytes(&self, frame: &ImageFrame, channel: Option<usize>) -> Vec<u8> {
+        let channels = frame.pixel_format().channels();
+        let big_endian = self.options.byte_order == ByteOrder::BigEndian;
+
+        match frame.pixels() {
+            PixelData::L1(pixels) => {
+                let width = frame.width() as usize;
+                let mut packed = Vec::with_capacity(width.div_ceil(8) * frame.height() as usize);
+
+                for row in pixels.chunks(width) {
+                    for bits in row.chunks(8) {
+                        let byte = bits.iter().enumerate().fold(0u8, |byte, (i, &bit)| byte | (((bit != 0) as u8) << (7 - i)));
+                        packed.push(byte);
+                    }
+                }
+
+                packed
+            }
+            PixelData::L8(p) | PixelData::LA8(p) | PixelData::RGB8(p) | PixelData::RGBA8(p) => {
+                select_samples(p, channels, channel, |v| [v])
+            }
+            PixelData::L16(p) | PixelData::LA16(p) | PixelData::RGB16(p) | PixelData::RGBA16(p) => {
+                select_samples(p, channels, channel, |v| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() })
+            }
+            PixelData::L32F(p) | PixelData::LA32F(p) | PixelData::RGB32F(p) | PixelData::RGBA32F(p) => {
+                select_samples(p, channels, channel, |v| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() })
+            }
+            PixelData::L64F(p) | PixelData::LA64F(p) | PixelData::RGB64F(p) | PixelData::RGBA64F(p) => {
+                select_samples(p, channels, channel, |v| if big_endian { v.to_be_bytes() } else { v.to_le_bytes() })
+            }
+        }
+    }
+}
+
+fn select_samples<T: Copy, const SIZE: usize>(
+    samples: &[T],
+    channels: usize,
+    channel: Option<usize>,
+    to_bytes: impl Fn(T) -> [u8; SIZE],
+) -> Vec<u8> {
+    match channel {
+        Some(channel) => samples.iter().skip(channel).step_by(channels).flat_map(|&v| to_bytes(v)).collect(),
+        None => samples.iter().flat_map(|&v| to_bytes(v)).collect(),
+    }
+}
+
+fn metadata_entries(metadata: &ImageMetadata) -> Vec<IfdEntry> {
+    let mut entries = vec![IfdEntry::new(
+        TiffTags::Orientation,
+        TagValue::Short(vec![metadata.orientation as u16]),
+    )];
+
+    if let Some(resolution) = &metadata.resolution {
+        // TIFF has no unit for meters, those are stored per centimeter
+        let (scale, unit) = match resolution.unit {
+            ResolutionUnit::None => (1.0, 1u16),
+            ResolutionUnit::Inch => (1.0, 2),
+            ResolutionUnit::Centimeter => (1.0, 3),
+            ResolutionUnit::Meter => (0.01, 3),
+        };
+
+        entries.push(IfdEntry::new(TiffTags::XResolution, TagValue::Rational(vec![to_rational(resolution.x * scale)])));
+        entries.push(IfdEntry::new(TiffTags::YResolution, TagValue::Rational(vec![to_rational(resolution.y * scale)])));
+        entries.push(IfdEntry::new(TiffTags::ResolutionUnit, TagValue::Short(vec![unit])));
+    }
+
+    if let Some(icc) = &metadata.icc_data {
+        entries.push(IfdEntry::new(TiffTags::InterColorProfile, TagValue::Undefined(icc.clone())));
+    }
+
+    entries
+}
+
+/// Converts a positive value to a fraction, exact for whole numbers and to four decimal places
+/// otherwise.
+fn to_rational(value: f64) -> (u32, u32) {
+    let value = value.max(0.0);
+    if value.fract() == 0.0 && value <= u32::MAX as f64 {
+        return (value as u32, 1);
+    }
+
+    let mut denominator = 10000u32;
+    while denominator > 1 && value * denominator as f64 > u32::MAX as f64 {
+        denominator /= 10;
+    }
+
+    ((value * denominator as f64).round().min(u32::MAX as f64) as u32, denominator)
+}
@@ -0,0 +1,231 @@
+use crate::decoders::tiff::types::TiffTags;
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::types::ByteOrder;
+
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+const TYPE_UNDEFINED: u16 = 7;
+const TYPE_LONG8: u16 = 16;
+
+pub enum TagValue {
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Rational(Vec<(u32, u32)>),
+    Undefined(Vec<u8>),
+    /// File offsets and byte counts, written as LONG in classic TIFF and LONG8 in BigTIFF.
+    Offsets(Vec<u64>),
+}
+
+pub struct IfdEntry {
+    pub tag: TiffTags,
+    pub value: TagValue,
+}
+
+impl IfdEntry {
+    pub fn new(tag: TiffTags, value: TagValue) -> Self {
+        IfdEntry { tag, value }
+    }
+}
+
+/// Builds a TIFF or BigTIFF file, with the IFDs linked in the order they are written.
+pub struct TiffWriter {
+    data: Vec<u8>,
+    byte_order: ByteOrder,
+    big_tiff: bool,
+    /// Position of the offset that has to point to the next IFD.
+    next_ifd_pointer: usize,
+}
+
+impl TiffWriter {
+    pub fn new(byte_order: ByteOrder, big_tiff: bool) -> Self {
+        let mut writer = TiffWriter {
+            data: Vec::new(),
+            byte_order,
+            big_tiff,
+            next_ifd_pointer: 0,
+        };
+
+        let marker = match byte_order {
+            ByteOrder::LittleEndian => b"II",
+            ByteOrder::BigEndian => b"MM",
+        };
+        writer.data.extend_from_slice(marker);
+
+        if big_tiff {
+            writer.put_u16(43);
+            writer.put_u16(8);
+            writer.put_u16(0);
+        } else {
+            writer.put_u16(42);
+        }
+
+        writer.next_ifd_pointer = writer.data.len();
+        writer.put_offset(0);
+
+        writer
+    }
+
+    /// Writes a strip, tile or other block of data and returns its offset.
+    pub fn write_data(&mut self, data: &[u8]) -> VexelResult<u64> {
+        self.align();
+        let offset = self.data.len() as u64;
+        self.data.extend_from_slice(data);
+        self.check_size()?;
+
+        Ok(offset)
+    }
+
+    /// Writes an IFD with `entries` and links it after the previous one.
+    pub fn write_ifd(&mut self, mut entries: Vec<IfdEntry>) -> VexelResult<()> {
+        entries.sort_by_key(|entry| entry.tag as u16);
+
+        self.align();
+        let ifd_offset = self.data.len();
+        let (count_size, entry_size) = if self.big_tiff { (8, 20) } else { (2, 12) };
+        let inline_size = if self.big_tiff { 8 } else { 4 };
+        let external_base = ifd_offset + count_size + entries.len() * entry_size + inline_size;
+        let mut external = Vec::new();
+
+        if self.big_tiff {
+            self.put_u64(entries.len() as u64);
+        } else {
+            self.put_u16(entries.len() as u16);
+        }
+
+        for entry in &entries {
+            let (field_type, count, bytes) = self.encode_value(&entry.value);
+
+            self.put_u16(entry.tag as u16);
+            self.put_u16(field_type);
+            self.put_offset(count as u64);
+
+            if bytes.len() <= inline_size {
+                // Values that fit are stored in the entry itself, left-justified
+                self.data.extend_from_slice(&bytes);
+                self.data.resize(self.data.len() + inline_size - bytes.len(), 0);
+            } else {
+                self.put_offset((external_base + external.len()) as u64);
+                external.extend_from_slice(&bytes);
+                if external.len() % 2 == 1 {
+                    external.push(0);
+                }
+            }
+        }
+
+        let next_ifd_pointer = self.data.len();
+        self.put_offset(0);
+        self.data.extend_from_slice(&external);
+        self.check_size()?;
+
+        let pointer = self.next_ifd_pointer;
+        self.patch_offset(pointer, ifd_offset as u64);
+        self.next_ifd_pointer = next_ifd_pointer;
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn encode_value(&self, value: &TagValue) -> (u16, usize, Vec<u8>) {
+        let mut bytes = Vec::new();
+
+        match value {
+            TagValue::Short(values) => {
+                values.iter().for_each(|&v| bytes.extend_from_slice(&self.u16_bytes(v)));
+                (TYPE_SHORT, values.len(), bytes)
+            }
+            TagValue::Long(values) => {
+                values.iter().for_each(|&v| bytes.extend_from_slice(&self.u32_bytes(v)));
+                (TYPE_LONG, values.len(), bytes)
+            }
+            TagValue::Rational(values) => {
+                for &(numerator, denominator) in values {
+                    bytes.extend_from_slice(&self.u32_bytes(numerator));
+                    bytes.extend_from_slice(&self.u32_bytes(denominator));
+                }
+                (TYPE_RATIONAL, values.len(), bytes)
+            }
+            TagValue::Undefined(values) => (TYPE_UNDEFINED, values.len(), values.clone()),
+            TagValue::Offsets(values) if self.big_tiff => {
+                values.iter().for_each(|&v| bytes.extend_from_slice(&self.u64_bytes(v)));
+                (TYPE_LONG8, values.len(), bytes)
+            }
+            TagValue::Offsets(values) => {
+                // Classic files are checked to stay below 4 GiB, so every offset fits
+                values.iter().for_each(|&v| bytes.extend_from_slice(&self.u32_bytes(v as u32)));
+                (TYPE_LONG, values.len(), bytes)
+            }
+        }
+    }
+
+    fn check_size(&self) -> VexelResult<()> {
+        if !self.big_tiff && self.data.len() > u32::MAX as usize {
+            return Err(VexelError::LimitExceeded(
+                "TIFF file exceeds 4 GiB, write it as BigTIFF instead".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Pads the file to an even offset, where IFDs and their values have to start.
+    fn align(&mut self) {
+        if self.data.len() % 2 == 1 {
+            self.data.push(0);
+        }
+    }
+
+    fn put_u16(&mut self, value: u16) {
+        let bytes = self.u16_bytes(value);
+        self.data.extend_from_slice(&bytes);
+    }
+
+    fn put_u64(&mut self, value: u64) {
+        let bytes = self.u64_bytes(value);
+        self.data.extend_from_slice(&bytes);
+    }
+
+    /// Writes an offset or count, 4 bytes wide in classic TIFF and 8 in BigTIFF.
+    fn put_offset(&mut self, value: u64) {
+        if self.big_tiff {
+            self.put_u64(value);
+        } else {
+            let bytes = self.u32_bytes(value as u32);
+            self.data.extend_from_slice(&bytes);
+        }
+    }
+
+    fn patch_offset(&mut self, position: usize, value: u64) {
+        if self.big_tiff {
+            let bytes = self.u64_bytes(value);
+            self.data[position..position + 8].copy_from_slice(&bytes);
+        } else {
+            let bytes = self.u32_bytes(value as u32);
+            self.data[position..position + 4].copy_from_slice(&bytes);
+        }
+    }
+
+    fn u16_bytes(&self, value: u16) -> [u8; 2] {
+        match self.byte_order {
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+            ByteOrder::BigEndian => value.to_be_bytes(),
+        }
+    }
+
+    fn u32_bytes(&self, value: u32) -> [u8; 4] {
+        match self.byte_order {
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+            ByteOrder::BigEndian => value.to_be_bytes(),
+        }
+    }
+
+    fn u64_bytes(&self, value: u64) -> [u8; 8] {
+        match self.byte_order {
+            ByteOrder::LittleEndian => value.to_le_bytes(),
+            ByteOrder::BigEndian => value.to_be_bytes(),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod compression;
+mod encoder;
+mod ifd;
+
+pub use crate::decoders::tiff::types::{Compression, PlanarConfiguration, Predictor};
+pub use crate::utils::types::ByteOrder;
+pub use encoder::{TiffEncoder, TiffOptions};
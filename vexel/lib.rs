@@ -1,4 +1,5 @@
 mod decoders;
+mod encoders;
 mod utils;
 
 use crate::decoders::bmp::BmpDecoder;
@@ -12,18 +13,55 @@ use crate::decoders::netpbm::NetPbmDecoder;
 use crate::decoders::png::PngDecoder;
 use crate::decoders::tga::TgaDecoder;
 use crate::decoders::tiff::TiffDecoder;
-
+use crate::utils::convert;
+use crate::utils::image::Region;
+use crate::utils::progress::{self, Hooks};
+use crate::utils::registry::SNIFF_LENGTH;
+use crate::utils::scan;
+use crate::utils::sniff::{self, HEADER_LENGTH, TRAILER_LENGTH};
+use crate::utils::report::{self, Collector};
+
+pub use decoders::jpeg::types::JpegScale;
 pub(crate) use utils::bitreader;
 pub(crate) use utils::logger::{log_debug, log_warn, log_error};
+pub use utils::color_transform::ColorTarget;
 pub use utils::error::{VexelError, VexelResult};
 pub use utils::limits::Limits;
 pub use utils::image::Image;
 pub use utils::image::ImageFormat;
 pub use utils::image::ImageFrame;
+pub use utils::image::ImageHeader;
 pub use utils::image::PixelData;
 pub use utils::image::PixelFormat;
-pub use utils::info::ImageInfo;
-pub use utils::logger::{LogLevel, set_log_level};
+pub use utils::info::{CustomInfo, ImageInfo};
+pub use utils::metadata::{ImageMetadata, Orientation, Resolution, ResolutionUnit, TextEntry};
+pub use utils::options::{CancellationToken, DecodeOptions, DecodeProgress, ProgressCallback, Strictness};
+pub use utils::stream::{Progress, StreamDecoder};
+pub use utils::frames::Frames;
+pub use utils::exif::{ExifData, ExifEntry, ExifIfd, ExifValue};
+pub use utils::icc::ICCProfile;
+pub use utils::logger::{LogLevel, LogRecord, LogSink, set_log_level};
+pub use utils::registry::{register_decoder, DecoderRegistration, ImageDecoder, ReadSeek};
+pub use utils::report::{DecodeReport, DecodeWarning, WarningKind};
+pub use utils::scan::SubReader;
+pub use utils::sniff::{guess_format, Confidence};
+#[cfg(feature = "mmap")]
+pub use memmap2::Mmap;
+
+/// Deflate (RFC 1951) and zlib (RFC 1950) compression, as used by PNG and TIFF.
+pub mod deflate {
+    pub use crate::utils::deflate::{adler32, DeflateDecoder, DeflateEncoder, ZlibDecoder, ZlibEncoder, DEFAULT_LEVEL};
+}
+
+/// Encoders that write an [`Image`] to a file.
+pub mod encode {
+    /// GIF encoding with colour quantization.
+    pub use crate::encoders::gif;
+    /// PNG and APNG encoding.
+    pub use crate::encoders::png;
+    /// TIFF and BigTIFF encoding.
+    pub use crate::encoders::tiff;
+}
 
 use serde::Serialize;
 use std::fs::File;
@@ -53,9 +91,327 @@ pub(crate) enum Decoders<R: Read + Seek> {
     Tga(TgaDecoder<R>),
     Jbig1(Jbig1Decoder<R>),
     Ico(IcoDecoder<R>),
+    /// A format without a built-in decoder, only read through its registered [`ImageDecoder`].
+    Other(R),
+    /// Placeholder while the reader moves to a new decoder.
     Unknown,
 }
 
+/// The [`ImageDecoder`] registered for a built-in format. Every call reads the file with a new
+/// format decoder, and keeps the info and metadata it collected.
+pub(crate) struct BuiltinDecoder {
+    format: ImageFormat,
+    limits: Limits,
+    jpeg_scale: JpegScale,
+    color_target: ColorTarget,
+    info: ImageInfo,
+    metadata: ImageMetadata,
+}
+
+impl BuiltinDecoder {
+    pub(crate) fn new(format: ImageFormat) -> Self {
+        BuiltinDecoder {
+            format,
+            limits: Limits::default(),
+            jpeg_scale: JpegScale::Full,
+            color_target: ColorTarget::Original,
+            info: ImageInfo::Unknown,
+            metadata: ImageMetadata::default(),
+        }
+    }
+
+    fn call<S: Read + Seek, T>(
+        &mut self,
+        mut decoder: Decoders<S>,
+        f: impl FnOnce(&mut Decoders<S>) -> VexelResult<T>,
+    ) -> VexelResult<T> {
+        decoder.set_limits(self.limits.clone());
+        decoder.set_jpeg_scale(self.jpeg_scale);
+        decoder.set_color_target(self.color_target);
+
+        let result = f(&mut decoder);
+        self.info = decoder.info();
+        self.metadata = decoder.metadata();
+
+        result
+    }
+
+    fn reader_decoder<'r>(&self, reader: &'r mut dyn ReadSeek) -> Decoders<&'r mut dyn ReadSeek> {
+        Decoders::new(&self.format, reader)
+    }
+
+    /// Returns a format decoder that borrows from `data` where it can instead of copying it.
+    fn bytes_decoder<'d>(&self, data: &'d [u8]) -> Decoders<Cursor<&'d [u8]>> {
+        let mut decoder = Decoders::new(&self.format, Cursor::new(data));
+        decoder.set_source_bytes(|cursor: &Cursor<&[u8]>| *cursor.get_ref());
+
+        decoder
+    }
+}
+
+impl ImageDecoder for BuiltinDecoder {
+    fn probe(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<ImageHeader> {
+        self.call(self.reader_decoder(reader), |decoder| decoder.probe())
+    }
+
+    fn probe_bytes(&mut self, data: &[u8]) -> VexelResult<ImageHeader> {
+        self.call(self.bytes_decoder(data), |decoder| decoder.probe())
+    }
+
+    fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    fn decode(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<Image> {
+        self.call(self.reader_decoder(reader), |decoder| decoder.decode())
+    }
+
+    fn decode_bytes(&mut self, data: &[u8]) -> VexelResult<Image> {
+        self.call(self.bytes_decoder(data), |decoder| decoder.decode())
+    }
+
+    fn info(&self) -> ImageInfo {
+        self.info.clone()
+    }
+
+    fn metadata(&self) -> ImageMetadata {
+        self.metadata.clone()
+    }
+
+    fn set_color_target(&mut self, target: ColorTarget) {
+        self.color_target = target;
+    }
+
+    fn set_jpeg_scale(&mut self, scale: JpegScale) {
+        self.jpeg_scale = scale;
+    }
+}
+
+/// The input a registered [`ImageDecoder`] reads from.
+enum Input<'a> {
+    Reader(&'a mut dyn ReadSeek),
+    /// The whole input, when it is in memory.
+    Bytes(&'a [u8]),
+}
+
+/// Runs `f`, converting a panic inside a decoder into [`VexelError::Panic`].
+fn catch_panic<T>(f: impl FnOnce() -> VexelResult<T>) -> VexelResult<T> {
+    #[cfg(fuzzing)]
+    {
+        return f();
+    }
+
+    #[cfg(not(fuzzing))]
+    {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+
+        result.unwrap_or_else(|payload| {
+            let msg = payload
+                .downcast_ref::<String>()
+                .map(|s| s.as_str())
+                .or_else(|| payload.downcast_ref::<&str>().copied())
+                .unwrap_or("unknown panic");
+
+            Err(VexelError::Panic(msg.to_string()))
+        })
+    }
+}
+
+impl<R: Read + Seek> Decoders<R> {
+    fn new(format: &ImageFormat, reader: R) -> Self {
+        match format {
+            ImageFormat::Jpeg => Decoders::Jpeg(JpegDecoder::new(reader)),
+            ImageFormat::JpegLs => Decoders::JpegLs(JpegLsDecoder::new(reader)),
+            ImageFormat::Gif => Decoders::Gif(GifDecoder::new(reader)),
+            ImageFormat::NetPbmP1
+            | ImageFormat::NetPbmP2
+            | ImageFormat::NetPbmP3
+            | ImageFormat::NetPbmP4
+            | ImageFormat::NetPbmP5
+            | ImageFormat::NetPbmP6
+            | ImageFormat::NetPbmP7 => Decoders::Netpbm(NetPbmDecoder::new(reader)),
+            ImageFormat::Bmp => Decoders::Bmp(BmpDecoder::new(reader)),
+            ImageFormat::Png => Decoders::Png(PngDecoder::new(reader)),
+            ImageFormat::Hdr => Decoders::Hdr(HdrDecoder::new(reader)),
+            ImageFormat::Tiff => Decoders::Tiff(TiffDecoder::new(reader)),
+            ImageFormat::Tga => Decoders::Tga(TgaDecoder::new(reader)),
+            ImageFormat::Jbig1 => Decoders::Jbig1(Jbig1Decoder::new(reader)),
+            ImageFormat::Ico | ImageFormat::Cur => Decoders::Ico(IcoDecoder::new(reader)),
+            ImageFormat::Custom(_) | ImageFormat::Unknown => Decoders::Other(reader),
+        }
+    }
+
+    fn set_limits(&mut self, limits: Limits) {
+        match self {
+            Decoders::Jpeg(d) => d.set_limits(limits),
+            Decoders::JpegLs(d) => d.set_limits(limits),
+            Decoders::Png(d) => d.set_limits(limits),
+            Decoders::Gif(d) => d.set_limits(limits),
+            Decoders::Netpbm(d) => d.set_limits(limits),
+            Decoders::Bmp(d) => d.set_limits(limits),
+            Decoders::Hdr(d) => d.set_limits(limits),
+            Decoders::Tiff(d) => d.set_limits(limits),
+            Decoders::Tga(d) => d.set_limits(limits),
+            Decoders::Jbig1(d) => d.set_limits(limits),
+            Decoders::Ico(d) => d.set_limits(limits),
+            Decoders::Other(_) | Decoders::Unknown => {}
+        }
+    }
+
+    /// Lets the decoders that can read straight from an input in memory borrow from it.
+    fn set_source_bytes(&mut self, source_bytes: fn(&R) -> &[u8]) {
+        match self {
+            Decoders::Png(d) => d.set_source_bytes(source_bytes),
+            Decoders::Tiff(d) => d.set_source_bytes(source_bytes),
+            _ => {}
+        }
+    }
+
+    fn set_jpeg_scale(&mut self, scale: JpegScale) {
+        if let Decoders::Jpeg(d) = self {
+            d.set_scale(scale);
+        }
+    }
+
+    fn set_color_target(&mut self, target: ColorTarget) {
+        match self {
+            Decoders::Jpeg(d) => d.set_color_target(target),
+            Decoders::Tiff(d) => d.set_color_target(target),
+            Decoders::Netpbm(d) => d.set_color_target(target),
+            _ => {}
+        }
+    }
+
+    fn probe(&mut self) -> VexelResult<ImageHeader> {
+        match self {
+            Decoders::Jpeg(decoder) => decoder.probe(),
+            Decoders::JpegLs(decoder) => decoder.probe(),
+            Decoders::Png(decoder) => decoder.probe(),
+            Decoders::Gif(decoder) => decoder.probe(),
+            Decoders::Netpbm(decoder) => decoder.probe(),
+            Decoders::Bmp(decoder) => decoder.probe(),
+            Decoders::Hdr(decoder) => decoder.probe(),
+            Decoders::Tiff(decoder) => decoder.probe(),
+            Decoders::Tga(decoder) => decoder.probe(),
+            Decoders::Jbig1(decoder) => decoder.probe(),
+            Decoders::Ico(decoder) => decoder.probe(),
+            Decoders::Other(_) | Decoders::Unknown => Err(VexelError::UnsupportedFormat("Unknown format".to_string())),
+        }
+    }
+
+    fn decode(&mut self) -> VexelResult<Image> {
+        match self {
+            Decoders::Jpeg(decoder) => impl_decode!(decoder),
+            Decoders::JpegLs(decoder) => impl_decode!(decoder),
+            Decoders::Png(decoder) => impl_decode!(decoder),
+            Decoders::Gif(decoder) => impl_decode!(decoder),
+            Decoders::Netpbm(decoder) => impl_decode!(decoder),
+            Decoders::Bmp(decoder) => impl_decode!(decoder),
+            Decoders::Hdr(decoder) => impl_decode!(decoder),
+            Decoders::Tiff(decoder) => impl_decode!(decoder),
+            Decoders::Tga(decoder) => impl_decode!(decoder),
+            Decoders::Jbig1(decoder) => impl_decode!(decoder),
+            Decoders::Ico(decoder) => impl_decode!(decoder),
+            Decoders::Other(_) | Decoders::Unknown => Err(VexelError::UnsupportedFormat("Unknown format".to_string())),
+        }
+    }
+
+    fn info(&self) -> ImageInfo {
+        match self {
+            Decoders::Jpeg(d) => ImageInfo::Jpeg(d.get_info()),
+            Decoders::JpegLs(d) => ImageInfo::JpegLs(d.get_info()),
+            Decoders::Png(d) => ImageInfo::Png(d.get_info()),
+            Decoders::Gif(d) => ImageInfo::Gif(d.get_info()),
+            Decoders::Netpbm(d) => ImageInfo::Netpbm(d.get_info()),
+            Decoders::Bmp(d) => ImageInfo::Bmp(d.get_info()),
+            Decoders::Hdr(d) => ImageInfo::Hdr(d.get_info()),
+            Decoders::Tiff(d) => ImageInfo::Tiff(d.get_info()),
+            Decoders::Tga(d) => ImageInfo::Tga(d.get_info()),
+            Decoders::Jbig1(d) => ImageInfo::Jbig1(d.get_info()),
+            Decoders::Ico(d) => ImageInfo::Ico(d.get_info()),
+            Decoders::Other(_) | Decoders::Unknown => ImageInfo::Unknown,
+        }
+    }
+
+    fn metadata(&self) -> ImageMetadata {
+        match self {
+            Decoders::Jpeg(d) => d.metadata(),
+            Decoders::JpegLs(d) => d.metadata(),
+            Decoders::Png(d) => d.metadata(),
+            Decoders::Gif(d) => d.metadata(),
+            Decoders::Bmp(d) => d.metadata(),
+            Decoders::Tiff(d) => d.metadata(),
+            Decoders::Netpbm(_)
+            | Decoders::Hdr(_)
+            | Decoders::Tga(_)
+            | Decoders::Jbig1(_)
+            | Decoders::Ico(_)
+            | Decoders::Other(_)
+            | Decoders::Unknown => ImageMetadata::default(),
+        }
+    }
+
+    /// Prepares decoding frames one at a time. Returns `false` for formats that decode all frames at once.
+    fn start_frames(&mut self) -> VexelResult<bool> {
+        match self {
+            Decoders::Png(d) => d.start_frames().map(|_| true),
+            Decoders::Gif(d) => d.start_frames().map(|_| true),
+            Decoders::Tiff(d) => d.start_frames().map(|_| true),
+            Decoders::Ico(d) => d.start_frames().map(|_| true),
+            _ => Ok(false),
+        }
+    }
+
+    fn next_frame(&mut self) -> VexelResult<Option<ImageFrame>> {
+        match self {
+            Decoders::Png(d) => d.next_frame(),
+            Decoders::Gif(d) => d.next_frame(),
+            Decoders::Tiff(d) => d.next_frame(),
+            Decoders::Ico(d) => d.next_frame(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Writes the first frame into `buffer` a row at a time, for formats that can be read that
+    /// way. Returns `None` when the format has to be decoded as a whole.
+    fn decode_into(&mut self, buffer: &mut [u8], stride: usize, format: &PixelFormat) -> VexelResult<Option<ImageHeader>> {
+        match self {
+            Decoders::Png(d) => d.decode_into(buffer, stride, format),
+            Decoders::Netpbm(d) => d.decode_into(buffer, stride, format),
+            Decoders::Bmp(d) => d.decode_into(buffer, stride, format),
+            Decoders::Tga(d) => d.decode_into(buffer, stride, format),
+            _ => Ok(None),
+        }
+    }
+
+    /// Skips the next frame, without decoding its pixel data where the format allows it.
+    fn skip_frame(&mut self) -> VexelResult<bool> {
+        match self {
+            Decoders::Tiff(d) => d.skip_frame(),
+            Decoders::Ico(d) => d.skip_frame(),
+            _ => self.next_frame().map(|frame| frame.is_some()),
+        }
+    }
+
+    fn into_reader(self) -> Option<R> {
+        match self {
+            Decoders::Jpeg(d) => Some(d.into_reader()),
+            Decoders::JpegLs(d) => Some(d.into_reader()),
+            Decoders::Png(d) => Some(d.into_reader()),
+            Decoders::Gif(d) => Some(d.into_reader()),
+            Decoders::Netpbm(d) => Some(d.into_reader()),
+            Decoders::Bmp(d) => Some(d.into_reader()),
+            Decoders::Hdr(d) => Some(d.into_reader()),
+            Decoders::Tiff(d) => Some(d.into_reader()),
+            Decoders::Tga(d) => Some(d.into_reader()),
+            Decoders::Jbig1(d) => Some(d.into_reader()),
+            Decoders::Ico(d) => Some(d.into_reader()),
+            Decoders::Other(reader) => Some(reader),
+            Decoders::Unknown => None,
+        }
+    }
+}
+
 /// The main image decoder.
 ///
 /// Wraps any [`Read`] + [`Seek`] source, detects the image format from its
@@ -72,20 +428,84 @@ pub(crate) enum Decoders<R: Read + Seek> {
 /// # Ok::<(), vexel::VexelError>(())
 /// ```
 pub struct Vexel<R: Read + Seek> {
-    decoder: Decoders<R>,
+    /// The registered decoder of the format, `None` if the format is unknown.
+    decoder: Option<Box<dyn ImageDecoder>>,
+    /// The format decoder used for frames, regions and [`decode_into`](Self::decode_into),
+    /// which also holds the reader.
+    source: Decoders<R>,
     format: ImageFormat,
     limits: Limits,
+    apply_orientation: bool,
+    color_target: ColorTarget,
+    jpeg_scale: JpegScale,
+    options: DecodeOptions,
+    input_len: Option<u64>,
+    start_offset: u64,
+    /// Returns the input held by the reader, set for inputs that are in memory as a whole.
+    source_bytes: Option<fn(&R) -> &[u8]>,
 }
 
 impl Vexel<File> {
     /// Opens an image file at `path` and returns a decoder ready for use.
     ///
     /// The file is wrapped in a [`BufReader`]. The image
-    /// format is detected automatically from the file's magic bytes. Returns an
+    /// format is detected automatically from the file's magic bytes, and the
+    /// extension of `path` decides between TGA and JBIG1, which have none. Returns an
     /// error if the file cannot be opened or the format cannot be identified.
     pub fn open<P: AsRef<Path>>(path: P) -> VexelResult<Vexel<BufReader<File>>> {
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str()).map(str::to_owned);
         let file = File::open(path)?;
-        Vexel::new(BufReader::with_capacity(256 * 1024, file))
+        Vexel::with_extension(BufReader::with_capacity(256 * 1024, file), extension.as_deref())
+    }
+
+    /// Opens an image file at `path` by mapping it into memory, and returns a decoder that reads
+    /// from the mapping through a [`Cursor`].
+    ///
+    /// Unlike [`open`](Self::open), there is no intermediate read buffer, and pages the decoder
+    /// never reads, such as the strips skipped by [`decode_region`](Self::decode_region) or the
+    /// pages skipped by [`frames`](Self::frames), are not loaded from disk. PNG and TIFF decoders
+    /// borrow the compressed image data from the mapping like in [`from_slice`](Vexel::from_slice).
+    /// The format is detected the same way as in `open`. Requires the `mmap` feature.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the decoder exists. Changes made by
+    /// other processes show up in the mapping, and reading past the end of a truncated file
+    /// terminates the process on most platforms. See [`Mmap::map`].
+    #[cfg(feature = "mmap")]
+    pub unsafe fn open_mmap<P: AsRef<Path>>(path: P) -> VexelResult<Vexel<Cursor<Mmap>>> {
+        let extension = path.as_ref().extension().and_then(|extension| extension.to_str()).map(str::to_owned);
+        let file = File::open(path)?;
+        let map = Mmap::map(&file)?;
+        let mut vexel = Vexel::with_extension(Cursor::new(map), extension.as_deref())?;
+        vexel.set_source_bytes(|cursor: &Cursor<Mmap>| &cursor.get_ref()[..]);
+
+        Ok(vexel)
+    }
+}
+
+impl<'a> Vexel<Cursor<&'a [u8]>> {
+    /// Creates a decoder that reads from a borrowed `data` through a [`Cursor`].
+    ///
+    /// Use this instead of [`new`](Self::new) with an owned [`Cursor`] when the image is already
+    /// in memory, so the input doesn't have to be cloned into a `Vec`. The PNG and TIFF decoders
+    /// borrow their compressed image data, the IDAT chunks and the strips and tiles, straight
+    /// from `data` instead of copying it, other decoders read from it like from any other reader.
+    /// Registered decoders get the slice through [`ImageDecoder::decode_bytes`]. The format is
+    /// detected like in `new`.
+    ///
+    /// ```no_run
+    /// use vexel::Vexel;
+    ///
+    /// let data = std::fs::read("image.png")?;
+    /// let image = Vexel::from_slice(&data)?.decode()?;
+    /// # Ok::<(), vexel::VexelError>(())
+    /// ```
+    pub fn from_slice(data: &'a [u8]) -> VexelResult<Vexel<Cursor<&'a [u8]>>> {
+        let mut vexel = Vexel::new(Cursor::new(data))?;
+        vexel.set_source_bytes(|cursor: &Cursor<&[u8]>| *cursor.get_ref());
+
+        Ok(vexel)
     }
 }
 
@@ -93,349 +513,605 @@ impl<R: Read + Seek + Sync> Vexel<R> {
     /// Creates a new decoder from any [`Read`] + [`Seek`] source.
     ///
     /// The image format is detected automatically from the first bytes of the
-    /// reader. Use [`open`](Vexel::open) when decoding a file on disk; use this
-    /// constructor when working with an in-memory buffer or a custom reader.
+    /// reader. Use [`open`](Vexel::open) when decoding a file on disk and
+    /// [`from_slice`](Vexel::from_slice) for an in-memory buffer; use this
+    /// constructor when working with a custom reader.
     /// Returns an error if the format cannot be identified.
-    pub fn new(mut reader: R) -> VexelResult<Vexel<R>> {
-        let format = Vexel::try_guess_format(&mut reader)?;
+    pub fn new(reader: R) -> VexelResult<Vexel<R>> {
+        Vexel::with_extension(reader, None)
+    }
 
-        let decoder = match format {
-            ImageFormat::Jpeg => Decoders::Jpeg(JpegDecoder::new(reader)),
-            ImageFormat::JpegLs => Decoders::JpegLs(JpegLsDecoder::new(reader)),
-            ImageFormat::Gif => Decoders::Gif(GifDecoder::new(reader)),
-            ImageFormat::NetPbmP1
-            | ImageFormat::NetPbmP2
-            | ImageFormat::NetPbmP3
-            | ImageFormat::NetPbmP4
-            | ImageFormat::NetPbmP5
-            | ImageFormat::NetPbmP6
-            | ImageFormat::NetPbmP7 => Decoders::Netpbm(NetPbmDecoder::new(reader)),
-            ImageFormat::Bmp => Decoders::Bmp(BmpDecoder::new(reader)),
-            ImageFormat::Png => Decoders::Png(PngDecoder::new(reader)),
-            ImageFormat::Hdr => Decoders::Hdr(HdrDecoder::new(reader)),
-            ImageFormat::Tiff => Decoders::Tiff(TiffDecoder::new(reader)),
-            ImageFormat::Tga => Decoders::Tga(TgaDecoder::new(reader)),
-            ImageFormat::Jbig1 => Decoders::Jbig1(Jbig1Decoder::new(reader)),
-            ImageFormat::Ico | ImageFormat::Cur => Decoders::Ico(IcoDecoder::new(reader)),
-            ImageFormat::Unknown => Decoders::Unknown,
+    /// Creates a decoder like [`new`](Self::new), with the file extension as a hint for formats
+    /// without magic bytes.
+    fn with_extension(mut reader: R, extension: Option<&str>) -> VexelResult<Vexel<R>> {
+        let format = Vexel::try_guess_format(&mut reader, extension)?;
+        let input_len = reader.seek(SeekFrom::End(0)).ok();
+        reader.seek(SeekFrom::Start(0))?;
+
+        let decoder = utils::registry::create_decoder(&format);
+        let source = Decoders::new(&format, reader);
+
+        Ok(Vexel {
+            decoder,
+            source,
+            format,
+            limits: Limits::default(),
+            apply_orientation: false,
+            color_target: ColorTarget::Original,
+            jpeg_scale: JpegScale::Full,
+            options: DecodeOptions::default(),
+            input_len,
+            start_offset: 0,
+            source_bytes: None,
+        })
+    }
+
+    /// Creates a decoder like [`new`](Self::new) for images that may be preceded by other data,
+    /// such as files recovered from email attachments, disk images or broken uploads.
+    ///
+    /// If the reader does not start with a recognizable image, it is scanned for the first PNG,
+    /// JPEG, GIF or TIFF signature and the image is decoded from there through a [`SubReader`].
+    /// The skipped bytes are reported as a [`WarningKind::LeadingData`] warning in
+    /// [`Image::report`], whose offset is where the image starts. Offsets of other warnings are
+    /// relative to that start. In [`Strictness::Strict`] mode, skipped data fails the decode
+    /// like any other warning.
+    ///
+    /// The whole reader may be read while scanning, so set [`Limits::max_input_bytes`] for
+    /// untrusted input.
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use vexel::Vexel;
+    ///
+    /// let mut decoder = Vexel::new_scanning(File::open("attachment.bin")?)?;
+    /// let image = decoder.decode()?;
+    /// # Ok::<(), vexel::VexelError>(())
+    /// ```
+    pub fn new_scanning(mut reader: R) -> VexelResult<Vexel<SubReader<R>>> {
+        let mut header = [0u8; HEADER_LENGTH];
+        let mut read_pos = 0;
+
+        while read_pos < header.len() {
+            match reader.read(&mut header[read_pos..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => read_pos += n,
+            }
+        }
+
+        // Only magic bytes count, headers of TGA and JBIG1 could just as well be junk
+        let recognized = sniff::score(&header[..read_pos], &[], None)
+            .first()
+            .is_some_and(|(_, confidence)| *confidence >= Confidence::High);
+
+        let start_offset = if recognized {
+            0
+        } else {
+            scan::find_signature(&mut reader)?.unwrap_or(0)
         };
 
-        Ok(Vexel { decoder, format, limits: Limits::default() })
+        let mut vexel = Vexel::new(SubReader::new(reader, start_offset)?)?;
+        vexel.start_offset = start_offset;
+
+        Ok(vexel)
+    }
+
+    /// Lets the decoders borrow from an input that is in memory as a whole, through the slice
+    /// `source_bytes` returns for the reader.
+    fn set_source_bytes(&mut self, source_bytes: fn(&R) -> &[u8]) {
+        self.source_bytes = Some(source_bytes);
+        self.source.set_source_bytes(source_bytes);
     }
 
     /// Sets resource limits that the decoder will enforce during decoding.
     ///
-    /// Limits can cap the maximum image dimensions and the total number of bytes
-    /// allocated for pixel data. Call this before [`decode`](Self::decode); limits
-    /// applied after decoding has started have no effect.
+    /// Limits can cap the maximum image dimensions, the total number of bytes
+    /// allocated for pixel data, the number of frames, the size of the metadata and
+    /// input, and the time a decode may take. Call this before [`decode`](Self::decode);
+    /// limits applied after decoding has started have no effect.
     pub fn set_limits(&mut self, limits: Limits) {
         self.limits = limits.clone();
-        match &mut self.decoder {
-            Decoders::Jpeg(d) => d.set_limits(limits),
-            Decoders::JpegLs(d) => d.set_limits(limits),
-            Decoders::Png(d) => d.set_limits(limits),
-            Decoders::Gif(d) => d.set_limits(limits),
-            Decoders::Netpbm(d) => d.set_limits(limits),
-            Decoders::Bmp(d) => d.set_limits(limits),
-            Decoders::Hdr(d) => d.set_limits(limits),
-            Decoders::Tiff(d) => d.set_limits(limits),
-            Decoders::Tga(d) => d.set_limits(limits),
-            Decoders::Jbig1(d) => d.set_limits(limits),
-            Decoders::Ico(d) => d.set_limits(limits),
-            Decoders::Unknown => {}
+        if let Some(decoder) = &mut self.decoder {
+            decoder.set_limits(limits.clone());
+        }
+        self.source.set_limits(limits);
+    }
+
+    /// Sets whether [`decode`](Self::decode) rotates and mirrors the image according to the
+    /// EXIF/TIFF `Orientation` tag. Disabled by default.
+    ///
+    /// When disabled, the orientation is only reported through [`ImageMetadata::orientation`]
+    /// and can be applied later with [`Image::apply_orientation`].
+    pub fn set_apply_orientation(&mut self, apply: bool) {
+        self.apply_orientation = apply;
+    }
+
+    /// Sets the colour space that [`decode`](Self::decode) converts images with an embedded
+    /// ICC profile to. Defaults to [`ColorTarget::Original`], which leaves the pixels unchanged.
+    ///
+    /// CMYK JPEG, TIFF and PAM images are always decoded to RGB. With `Original` this uses the
+    /// plain CMYK formula, any other target converts them through the embedded CMYK profile, or
+    /// a built-in SWOP-like press model when there is none. See [`Image::apply_color_profile`]
+    /// for the other supported profiles.
+    pub fn set_color_target(&mut self, target: ColorTarget) {
+        self.color_target = target;
+        if let Some(decoder) = &mut self.decoder {
+            decoder.set_color_target(target);
         }
+        self.source.set_color_target(target);
+    }
+
+    /// Sets the options used by [`decode`](Self::decode), [`decode_region`](Self::decode_region)
+    /// and [`probe`](Self::probe).
+    ///
+    /// With [`Strictness::Strict`], any problem the decoder would otherwise recover from, such as
+    /// a CRC mismatch, truncated data or an unsupported compression method, fails the call with
+    /// [`VexelError::Malformed`] describing the first problem found. The decoder stops at its next
+    /// row, strip or frame instead of finishing the image. Problems are the warnings listed in
+    /// [`Image::report`] in the default lenient mode, except [`WarningKind::Other`] messages,
+    /// which are informational.
+    ///
+    /// A [`progress`](DecodeOptions::progress) callback is told how many rows or frames have
+    /// been decoded, and a cancelled [`cancellation`](DecodeOptions::cancellation) token makes the
+    /// call return [`VexelError::Cancelled`]. The options also apply to [`frames`](Self::frames).
+    pub fn set_decode_options(&mut self, options: DecodeOptions) {
+        self.options = options;
+    }
+
+    /// Sets the size at which JPEG images are decoded. Defaults to [`JpegScale::Full`].
+    ///
+    /// Reduced scales use 4x4, 2x2 or DC-only transforms instead of decoding the full image,
+    /// which makes thumbnails several times faster to produce. The result is `ceil(width / n)` x
+    /// `ceil(height / n)` for a scale of 1/n, and [`probe`](Self::probe) and
+    /// [`decode_region`](Self::decode_region) use the scaled dimensions too. Lossless and
+    /// hierarchical JPEGs and other formats are always decoded at full size.
+    pub fn set_jpeg_scale(&mut self, scale: JpegScale) {
+        self.jpeg_scale = scale;
+        if let Some(decoder) = &mut self.decoder {
+            decoder.set_jpeg_scale(scale);
+        }
+        self.source.set_jpeg_scale(scale);
     }
 
     /// Decodes the image and returns an [`Image`] containing all frames.
     ///
+    /// Metadata found in the file is attached to the result and available through [`Image::metadata`].
+    /// Problems the decoder recovered from, such as truncated or corrupt data, are listed in
+    /// [`Image::report`].
+    ///
     /// # Errors
     ///
     /// - [`VexelError::IoError`] — underlying read or seek failed
     /// - [`VexelError::UnsupportedFormat`] — format was not recognized at construction time
     /// - [`VexelError::InvalidDimensions`] — decoded width or height is zero or otherwise invalid
     /// - [`VexelError::LimitExceeded`] — a [`Limits`] constraint was breached during decoding
+    /// - [`VexelError::Truncated`] — the file ends before the headers or required data
+    /// - [`VexelError::Corrupt`] — the data is invalid and could not be decoded
+    /// - [`VexelError::Unsupported`] — the file uses a feature the decoder does not implement
+    /// - [`VexelError::MissingData`] — a required part of the file, such as the image data, is absent
+    /// - [`VexelError::Malformed`] — a problem was found in [`Strictness::Strict`] mode
     /// - [`VexelError::Custom`] — a format-specific error that does not fit another variant
     /// - [`VexelError::Panic`] — the decoder panicked internally; the panic message is captured
     pub fn decode(&mut self) -> VexelResult<Image> {
-        macro_rules! dispatch {
-            () => {
-                match &mut self.decoder {
-                    Decoders::Jpeg(decoder) => impl_decode!(decoder),
-                    Decoders::JpegLs(decoder) => impl_decode!(decoder),
-                    Decoders::Png(decoder) => impl_decode!(decoder),
-                    Decoders::Gif(decoder) => impl_decode!(decoder),
-                    Decoders::Netpbm(decoder) => impl_decode!(decoder),
-                    Decoders::Bmp(decoder) => impl_decode!(decoder),
-                    Decoders::Hdr(decoder) => impl_decode!(decoder),
-                    Decoders::Tiff(decoder) => impl_decode!(decoder),
-                    Decoders::Tga(decoder) => impl_decode!(decoder),
-                    Decoders::Jbig1(decoder) => impl_decode!(decoder),
-                    Decoders::Ico(decoder) => impl_decode!(decoder),
-                    Decoders::Unknown => Err(VexelError::UnsupportedFormat("Unknown format".to_string())),
-                }
-            };
-        }
-
-        #[cfg(fuzzing)]
-        {
-            return dispatch!();
-        }
-
-        #[cfg(not(fuzzing))]
-        {
-            let result = panic::catch_unwind(AssertUnwindSafe(|| dispatch!()));
-
-            result.unwrap_or_else(|payload| {
-                let msg = payload
-                    .downcast_ref::<String>()
-                    .map(|s| s.as_str())
-                    .or_else(|| payload.downcast_ref::<&str>().copied())
-                    .unwrap_or("unknown panic");
+        let (image, warnings) = self.run_decoder(|vexel| vexel.decode_registered())?;
+        let metadata = self.registered_metadata();
 
-                Err(VexelError::Panic(msg.to_string()))
-            })
-        }
+        Ok(self.finish_image(image, metadata, warnings))
     }
 
-    /// Returns the detected image format.
+    /// Decodes only the `width` x `height` rectangle at (`x`, `y`) and returns it as an [`Image`].
     ///
-    /// The format is determined during construction and does not change. If the
-    /// format could not be identified, [`ImageFormat::Unknown`] is returned.
-    pub fn get_format(&self) -> ImageFormat {
-        self.format.clone()
+    /// The result is the same as cropping the output of [`decode`](Self::decode), but tiled and
+    /// stripped TIFFs only read and decompress the tiles or strips that overlap the rectangle,
+    /// and JPEGs skip the IDCT and colour conversion outside of it. Sequential JPEGs also stop
+    /// entropy decoding after the last MCU row the rectangle covers. Other formats are decoded
+    /// in full and then cropped.
+    ///
+    /// The rectangle is given in the stored orientation of the image, before
+    /// [`set_apply_orientation`](Self::set_apply_orientation) rotates it. Orientation and colour
+    /// conversion are applied to the cropped result. Frames that are smaller than the image, such
+    /// as ICO entries, are cropped to the part of the rectangle they cover.
+    ///
+    /// ```no_run
+    /// use vexel::Vexel;
+    ///
+    /// let mut decoder = Vexel::open("scan.tif")?;
+    /// let viewport = decoder.decode_region(4096, 2048, 1920, 1080)?;
+    /// # Ok::<(), vexel::VexelError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - [`VexelError::InvalidDimensions`] — the rectangle is empty
+    /// - [`VexelError::Custom`] — the rectangle does not lie within the image
+    /// - Any error returned by [`decode`](Self::decode)
+    pub fn decode_region(&mut self, x: u32, y: u32, width: u32, height: u32) -> VexelResult<Image> {
+        let region = Region::new(x, y, width, height);
+        self.reset_decoder()?;
+
+        let ((image, metadata), warnings) = self.run_decoder(|vexel| match &mut vexel.source {
+            Decoders::Jpeg(decoder) => Ok((decoder.decode_region(region)?, decoder.metadata())),
+            Decoders::Tiff(decoder) => Ok((decoder.decode_region(region)?, decoder.metadata())),
+            _ => {
+                let image = vexel.decode_registered()?;
+                region.check_bounds(image.width(), image.height())?;
+
+                Ok((image.cropped(region), vexel.registered_metadata()))
+            }
+        })?;
+
+        Ok(self.finish_image(image, metadata, warnings))
     }
 
-    /// Returns format-specific metadata collected during the last [`decode`](Self::decode) call.
+    /// Decodes the first frame straight into `buffer` as `format`, without returning an [`Image`].
     ///
-    /// Metadata is populated as a side effect of decoding, so [`decode`](Self::decode) must be
-    /// called first. Calling `get_info` before `decode` returns an empty or zeroed struct.
-    /// The returned [`ImageInfo`] variant matches the detected format.
-    pub fn get_info(&mut self) -> ImageInfo {
-        match &mut self.decoder {
-            Decoders::Jpeg(jpeg_decoder) => {
-                let image_data = jpeg_decoder.get_info();
+    /// Meant for writing into memory the caller already owns, such as shared memory or texture
+    /// staging buffers. Rows start `stride` bytes apart, and the bytes between the end of a row
+    /// and the start of the next one are left untouched. Samples wider than a byte are written in
+    /// native byte order, like [`PixelData::as_bytes`], colour is converted to gray with the
+    /// BT.601 luma weights and alpha is dropped or added as fully opaque.
+    ///
+    /// Non-interlaced PNG, uncompressed BMP, TGA and binary PGM and PPM files are decoded a row at
+    /// a time and each row is converted straight into the buffer, so the frame is never held as a
+    /// whole. Rows missing from truncated files are filled with zeros. Other formats, and frames
+    /// that [`set_apply_orientation`](Self::set_apply_orientation) rotates or
+    /// [`set_color_target`](Self::set_color_target) converts, are decoded first and then
+    /// converted a row at a time, so there is no intermediate copy like the one
+    /// [`Image::as_rgba8`] makes. Multi-page and animated GIF, APNG, TIFF and ICO files only
+    /// decode their first frame.
+    ///
+    /// The buffer is checked against the size given by [`probe`](Self::probe), after rotation,
+    /// before anything is decoded. ICO files probe as their largest entry, so they are checked
+    /// against the decoded first entry instead. Use [`PixelFormat::bytes_per_pixel`] to size the
+    /// buffer from the probed size.
+    ///
+    /// Returns the header of the written frame, with its final width and height and the pixel
+    /// format the decoder produced before conversion.
+    ///
+    /// ```no_run
+    /// use vexel::{PixelFormat, Vexel};
+    ///
+    /// let mut decoder = Vexel::open("photo.jpg")?;
+    /// let header = decoder.probe()?;
+    /// let stride = (header.width as usize * 4).next_multiple_of(256);
+    /// let mut staging = vec![0u8; stride * header.height as usize];
+    /// decoder.decode_into(&mut staging, stride, PixelFormat::RGBA8)?;
+    /// # Ok::<(), vexel::VexelError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - [`VexelError::Custom`] — the stride is shorter than a row or the buffer is too small
+    /// - [`VexelError::LimitExceeded`] — the buffer size overflows `usize`
+    /// - Any error returned by [`decode`](Self::decode)
+    pub fn decode_into(&mut self, buffer: &mut [u8], stride: usize, format: PixelFormat) -> VexelResult<ImageHeader> {
+        let (probed, _) = self.run_decoder(|vexel| vexel.probe_registered())?;
+        let metadata = self.registered_metadata();
+        let oriented = self.apply_orientation && metadata.orientation != Orientation::TopLeft;
+        let converted = self.color_target != ColorTarget::Original && metadata.icc_profile.is_some();
+
+        // ICO files probe as their largest entry, which isn't necessarily the first one
+        if !matches!(self.format, ImageFormat::Ico | ImageFormat::Cur) {
+            let (width, height) = match oriented && metadata.orientation.swaps_dimensions() {
+                true => (probed.height, probed.width),
+                false => (probed.width, probed.height),
+            };
+            convert::check_buffer(buffer.len(), stride, width, height, &format)?;
+        }
 
-                ImageInfo::Jpeg(image_data)
-            }
-            Decoders::Png(png_decoder) => {
-                let image_data = png_decoder.get_info();
+        // Rows can only be written as they are decoded when the frame isn't rotated or
+        // converted afterwards
+        if !oriented && !converted {
+            self.reset_decoder()?;
 
-                ImageInfo::Png(image_data)
+            let (header, _) = self.run_decoder(|vexel| vexel.source.decode_into(buffer, stride, &format))?;
+            if let Some(header) = header {
+                return Ok(header);
             }
-            Decoders::Bmp(bmp_decoder) => {
-                let image_data = bmp_decoder.get_info();
+        }
 
-                ImageInfo::Bmp(image_data)
-            }
-            Decoders::Gif(gif_decoder) => {
-                let image_data = gif_decoder.get_info();
+        // The decoder may have read the header before declining
+        self.reset_decoder()?;
 
-                ImageInfo::Gif(image_data)
+        // Only the first frame is decoded where the format decodes frames one at a time
+        let ((frame, metadata), warnings) = self.run_decoder(|vexel| match vexel.source.start_frames()? {
+            true => Ok((vexel.source.next_frame()?, vexel.source.metadata())),
+            false => {
+                let image = vexel.decode_registered()?;
+                Ok((image.into_frames().into_iter().next(), vexel.registered_metadata()))
             }
-            Decoders::Netpbm(netpbm_decoder) => {
-                let image_data = netpbm_decoder.get_info();
+        })?;
+        let Some(frame) = frame else {
+            return Err(VexelError::missing(self.format.clone(), "The image has no frames"));
+        };
 
-                ImageInfo::Netpbm(image_data)
-            }
-            Decoders::Hdr(hdr_decoder) => {
-                let image_data = hdr_decoder.get_info();
+        let image = self.finish_image(Image::from_frame(frame), metadata, warnings);
+        let frame = image.into_frames().remove(0);
 
-                ImageInfo::Hdr(image_data)
-            }
-            Decoders::JpegLs(jpeg_ls_decoder) => {
-                let image_data = jpeg_ls_decoder.get_info();
-                ImageInfo::JpegLs(image_data)
-            }
-            Decoders::Jbig1(jbig1_decoder) => {
-                let image_data = jbig1_decoder.get_info();
-                ImageInfo::Jbig1(image_data)
-            }
-            Decoders::Ico(ico_decoder) => {
-                let image_data = ico_decoder.get_info();
-                ImageInfo::Ico(image_data)
-            }
-            Decoders::Tga(tga_decoder) => {
-                let image_data = tga_decoder.get_info();
-                ImageInfo::Tga(image_data)
+        let (width, height) = (frame.width(), frame.height());
+        convert::check_buffer(buffer.len(), stride, width, height, &format)?;
+
+        let mut pixels = frame.into_pixels();
+        pixels.correct_pixels(width, height);
+        convert::write_pixels(&pixels, width, height, buffer, stride, &format);
+
+        Ok(ImageHeader {
+            width,
+            height,
+            pixel_format: pixels.pixel_format(),
+            frame_count: 1,
+            is_animated: false,
+        })
+    }
+
+    /// Runs `f` on the decoder, catching panics and collecting the warnings it raises. In
+    /// strict mode the decoder stops at its next progress check after the first recovery
+    /// warning, which is returned as [`VexelError::Malformed`].
+    pub(crate) fn run_decoder<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> VexelResult<T>,
+    ) -> VexelResult<(T, Collector)> {
+        let start_offset = self.start_offset;
+        self.collect_warnings(|vexel| {
+            if start_offset > 0 {
+                let message = format!("Skipped {} bytes in front of the image", start_offset);
+                DecodeWarning::new(WarningKind::LeadingData, message).at_offset(start_offset).report();
             }
-            _ => unimplemented!(),
-        }
+
+            vexel.call_decoder(f)
+        })
     }
 
-    fn try_guess_format(reader: &mut R) -> VexelResult<ImageFormat> {
-        let mut header = [0u8; 32];
-        let mut read_pos = 0;
+    /// Runs `f` and collects the warnings it raises, failing with [`VexelError::Malformed`] in
+    /// strict mode if one of them is a recovery warning. Unlike [`run_decoder`](Self::run_decoder),
+    /// skipped leading data is not reported again, so [`Frames`] uses it for every frame after
+    /// the first.
+    pub(crate) fn collect_warnings<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> VexelResult<T>,
+    ) -> VexelResult<(T, Collector)> {
+        let strict = self.options.strictness == Strictness::Strict;
+        let (result, warnings) = report::collect(strict, || f(self));
+        // The error the decoder stopped with is a consequence of the rejected warning
+        if let Some(warning) = warnings.rejected() {
+            return Err(VexelError::Malformed {
+                format: self.format.clone(),
+                warning: warning.clone(),
+            });
+        }
 
-        while read_pos < header.len() {
-            match reader.read(&mut header[read_pos..]) {
-                Ok(0) | Err(_) => break,
-                Ok(n) => read_pos += n,
-            }
+        Ok((result?, warnings))
+    }
+
+    /// Runs `f` on the decoder with the progress, cancellation and logging hooks of the options,
+    /// catching panics and setting the format of errors raised by code shared between decoders.
+    pub(crate) fn call_decoder<T>(&mut self, f: impl FnOnce(&mut Self) -> VexelResult<T>) -> VexelResult<T> {
+        if let Some(input_len) = self.input_len {
+            self.limits.check_input_size(input_len).map_err(|e| e.with_format(&self.format))?;
         }
 
-        reader.seek(SeekFrom::Start(0))?;
+        let hooks = Hooks::from_options(&self.options, &self.limits, &self.format);
 
-        // JPEG-LS
-        if header.starts_with(&[0xFF, 0xD8]) {
-            if header.windows(2).any(|window| window == [0xFF, 0xF7]) {
-                return Ok(ImageFormat::JpegLs);
-            }
+        progress::with_hooks(hooks, || {
+            progress::check_cancelled()?;
+            catch_panic(|| f(self))
+        })
+        .map_err(|e| e.with_format(&self.format))
+    }
 
-            if header.len() >= 12 && header[2] == 0xFF && header[3] == 0xE8 && &header[6..12] == b"SPIFF\0" {
-                return Ok(ImageFormat::JpegLs);
+    /// Runs `f` with the registered decoder on the source rewound to its start, or on the whole
+    /// input if it is in memory. The format decoder is replaced by a new one afterwards, as `f`
+    /// may leave the reader anywhere.
+    fn call_registered<T>(&mut self, f: impl FnOnce(&mut dyn ImageDecoder, Input<'_>) -> VexelResult<T>) -> VexelResult<T> {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Err(VexelError::UnsupportedFormat("Unknown format".to_string()));
+        };
+
+        let source = std::mem::replace(&mut self.source, Decoders::Unknown);
+        let Some(mut reader) = source.into_reader() else {
+            return Err(VexelError::UnsupportedFormat("Unknown format".to_string()));
+        };
+
+        // Panics are caught here already, the reader would be lost otherwise
+        let source_bytes = self.source_bytes;
+        let result = catch_panic(|| match source_bytes {
+            Some(source_bytes) => f(decoder.as_mut(), Input::Bytes(source_bytes(&reader))),
+            None => {
+                reader.seek(SeekFrom::Start(0))?;
+                f(decoder.as_mut(), Input::Reader(&mut reader))
             }
-        }
+        });
 
-        // JPEG
-        if header.starts_with(&[0xFF, 0xD8]) {
-            return Ok(ImageFormat::Jpeg);
-        }
+        let rewound = reader.seek(SeekFrom::Start(0));
+        self.source = self.new_source(reader);
+        rewound?;
 
-        // PNG
-        if header.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
-            return Ok(ImageFormat::Png);
-        }
+        result
+    }
 
-        // GIF
-        if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
-            return Ok(ImageFormat::Gif);
-        }
+    /// Probes through the registered decoder and checks the dimensions against the limits.
+    fn probe_registered(&mut self) -> VexelResult<ImageHeader> {
+        let header = self.call_registered(|decoder, input| match input {
+            Input::Reader(reader) => decoder.probe(reader),
+            Input::Bytes(data) => decoder.probe_bytes(data),
+        })?;
+        self.limits.check_dimensions(header.width, header.height)?;
 
-        // Netpbm
-        if header.starts_with(b"P") {
-            match header[1] {
-                b'1' => return Ok(ImageFormat::NetPbmP1),
-                b'2' => return Ok(ImageFormat::NetPbmP2),
-                b'3' => return Ok(ImageFormat::NetPbmP3),
-                b'4' => return Ok(ImageFormat::NetPbmP4),
-                b'5' => return Ok(ImageFormat::NetPbmP5),
-                b'6' => return Ok(ImageFormat::NetPbmP6),
-                b'7' => return Ok(ImageFormat::NetPbmP7),
-                _ => {}
-            }
-        }
+        Ok(header)
+    }
 
-        // BMP
-        match &header[0..2] {
-            b"BM" | b"BA" | b"CI" | b"CP" | b"IC" | b"PT" => return Ok(ImageFormat::Bmp),
-            _ => {}
+    /// Decodes through the registered decoder and checks the dimensions and the number of frames
+    /// against the limits.
+    fn decode_registered(&mut self) -> VexelResult<Image> {
+        let mut image = self.call_registered(|decoder, input| match input {
+            Input::Reader(reader) => decoder.decode(reader),
+            Input::Bytes(data) => decoder.decode_bytes(data),
+        })?;
+
+        self.limits.check_dimensions(image.width(), image.height())?;
+        for frame in image.frames() {
+            self.limits.check_dimensions(frame.width(), frame.height())?;
         }
+        self.limits.check_frames(image.frames().len())?;
 
-        // ICO / CUR
-        let ico_count = u16::from_le_bytes([header[4], header[5]]);
-        if header.starts_with(&[0x00, 0x00, 0x01, 0x00]) && ico_count > 0 {
-            return Ok(ImageFormat::Ico);
-        }
+        // Conversions expect every frame to hold exactly its own pixels
+        image.correct_pixels();
 
-        if header.starts_with(&[0x00, 0x00, 0x02, 0x00]) && ico_count > 0 {
-            return Ok(ImageFormat::Cur);
-        }
+        Ok(image)
+    }
 
-        // HDR
-        if header.starts_with(b"#?RADIANCE") {
-            return Ok(ImageFormat::Hdr);
-        }
+    fn registered_metadata(&self) -> ImageMetadata {
+        self.decoder.as_ref().map(|decoder| decoder.metadata()).unwrap_or_default()
+    }
+
+    /// Attaches the metadata and the decode report, and applies orientation and colour
+    /// conversion as configured.
+    fn finish_image(&self, mut image: Image, metadata: ImageMetadata, warnings: Collector) -> Image {
+        image.set_metadata(metadata);
+        image.set_report(DecodeReport::new(warnings, image.total_pixels()));
 
-        // TIFF
-        if (header.starts_with(b"II") || header.starts_with(b"MM"))
-            && ((header[2] == 42 && header[3] == 0) || (header[2] == 0 && header[3] == 42))
-        {
-            return Ok(ImageFormat::Tiff);
+        if self.apply_orientation {
+            image.apply_orientation();
         }
 
-        // TGA
-        // Targa does not have a magic number, so we have to check the header manually.
-        let image_type = header[2];
-        let color_map_type = header[1];
-        let palette_bpp = header[7];
+        image.apply_color_profile(self.color_target);
 
-        let valid_image_type = matches!(image_type, 0 | 1 | 2 | 3 | 9 | 10 | 11 | 32 | 33);
-        let valid_color_map = matches!(color_map_type, 0 | 1);
+        image
+    }
 
-        let pixel_depth = header[16];
-        let valid_depth = matches!(pixel_depth, 1 | 4 | 8 | 15 | 16 | 24 | 32);
+    /// Reads the image headers and returns basic properties without decoding any pixel data.
+    ///
+    /// This is much cheaper than [`decode`](Self::decode) and is meant for cases where only the
+    /// dimensions, pixel format or frame count are needed up front. [`Limits`] are enforced the
+    /// same way as during decoding, so an image that would be rejected by `decode` is rejected
+    /// here too.
+    ///
+    /// The source is rewound before and after probing, so `probe` can be called at any time,
+    /// including before or after [`decode`](Self::decode). Note that this resets any state
+    /// collected by a previous decode, such as the data returned by [`get_info`](Self::get_info).
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`decode`](Self::decode) for malformed headers.
+    pub fn probe(&mut self) -> VexelResult<ImageHeader> {
+        let (header, _) = self.run_decoder(|vexel| vexel.probe_registered())?;
 
-        let descriptor = header[17];
-        let valid_descriptor = (descriptor & 0xC0) == 0;
+        Ok(header)
+    }
 
-        let is_paletted_type = matches!(image_type, 1 | 9);
-        let palette_consistent = !is_paletted_type || color_map_type == 1;
-        let valid_palette_bpp = color_map_type != 1 || matches!(palette_bpp, 15 | 16 | 24 | 32);
+    /// Returns an iterator that decodes the frames of the image one at a time.
+    ///
+    /// GIF, APNG, TIFF and ICO frames are decoded and composited as the iterator advances, and
+    /// only the state needed for the next frame is kept, so long animations and multi-page
+    /// documents don't have to fit in memory at once. [`Limits`] apply to each frame separately.
+    /// Other formats are decoded in full on the first call to `next`.
+    ///
+    /// [`Iterator::nth`] and [`Iterator::skip`] seek past TIFF pages and ICO entries without
+    /// decoding their pixel data. Animation frames depend on the frames before them, so those
+    /// are still decoded.
+    ///
+    /// Orientation and colour conversion are applied to every frame as configured. The source is
+    /// rewound first, like in [`probe`](Self::probe).
+    ///
+    /// ```no_run
+    /// use vexel::Vexel;
+    ///
+    /// let mut decoder = Vexel::open("scan.tif")?;
+    /// for frame in decoder.frames()?.skip(10).take(5) {
+    ///     let frame = frame?;
+    ///     println!("{}x{}", frame.width(), frame.height());
+    /// }
+    /// # Ok::<(), vexel::VexelError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`decode`](Self::decode) for malformed headers. Errors while
+    /// decoding a frame are returned by the iterator, which ends after the first error.
+    pub fn frames(&mut self) -> VexelResult<Frames<'_, R>> {
+        self.reset_decoder()?;
+
+        Frames::new(self)
+    }
+
+    /// Replaces the format decoder with a fresh one reading from the start of the source.
+    fn reset_decoder(&mut self) -> VexelResult<()> {
+        let source = std::mem::replace(&mut self.source, Decoders::Unknown);
 
-        if valid_image_type && valid_color_map && valid_depth && valid_descriptor && palette_consistent && valid_palette_bpp {
-            return Ok(ImageFormat::Tga);
+        if let Some(mut reader) = source.into_reader() {
+            reader.seek(SeekFrom::Start(0))?;
+            self.source = self.new_source(reader);
         }
 
-        // JBIG1 - no magic bytes; validate the 20-byte BIH header
-        // Byte 0 (DL) <= byte 1 (D), byte 2 (planes) in 1..=8, byte 3 = 0 (reserved)
-        // XD, YD, L0 (big-endian u32 at offsets 4, 8, 12) must be non-zero and <= 65535
-        let xd = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
-        let yd = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
-        let l0 = u32::from_be_bytes([header[12], header[13], header[14], header[15]]);
-        if header[3] == 0
-            && (1..=8).contains(&header[2])
-            && header[0] <= header[1]
-            && xd > 0 && xd <= 65535
-            && yd > 0 && yd <= 65535
-            && l0 > 0 && l0 <= 65535
-        {
-            return Ok(ImageFormat::Jbig1);
+        Ok(())
+    }
+
+    /// Creates a format decoder reading from `reader`, with the settings of this decoder.
+    fn new_source(&self, reader: R) -> Decoders<R> {
+        let mut source = Decoders::new(&self.format, reader);
+        source.set_limits(self.limits.clone());
+        source.set_jpeg_scale(self.jpeg_scale);
+        source.set_color_target(self.color_target);
+        if let Some(source_bytes) = self.source_bytes {
+            source.set_source_bytes(source_bytes);
         }
 
-        // If all else fails, let's try harder and pray that we get the right format
-        Vexel::try_guess_format_harder(reader)
+        source
+    }
+
+    /// Returns the detected image format.
+    ///
+    /// The format is determined during construction and does not change. If the
+    /// format could not be identified, [`ImageFormat::Unknown`] is returned.
+    pub fn get_format(&self) -> ImageFormat {
+        self.format.clone()
     }
 
-    fn try_guess_format_harder(reader: &mut R) -> VexelResult<ImageFormat> {
-        const HEADER_SIZE: usize = 48;
-        const FOOTER_SIZE: usize = 12;
-        let mut header = [0u8; HEADER_SIZE];
+    /// Returns format-specific metadata collected during the last [`decode`](Self::decode) call.
+    ///
+    /// Metadata is populated as a side effect of decoding, so [`decode`](Self::decode) must be
+    /// called first. Calling `get_info` before `decode` returns an empty or zeroed struct.
+    /// The returned [`ImageInfo`] variant matches the detected format.
+    pub fn get_info(&mut self) -> ImageInfo {
+        self.decoder.as_ref().map(|decoder| decoder.info()).unwrap_or(ImageInfo::Unknown)
+    }
+
+    /// Detects the format from the first bytes of `reader`. TGA and JBIG1 headers can be valid for
+    /// both formats, `extension` decides between them. Files that no sniffer recognizes are
+    /// scored by [`sniff::score`], which also accepts damaged signatures.
+    fn try_guess_format(reader: &mut R, extension: Option<&str>) -> VexelResult<ImageFormat> {
+        let mut header = [0u8; HEADER_LENGTH];
         let mut read_pos = 0;
 
-        while read_pos < HEADER_SIZE {
+        while read_pos < header.len() {
             match reader.read(&mut header[read_pos..]) {
-                Ok(0) | Err(_) => {
-                    for i in read_pos..HEADER_SIZE {
-                        header[i] = 0;
-                    }
-                }
+                Ok(0) | Err(_) => break,
                 Ok(n) => read_pos += n,
             }
         }
 
-        let mut footer = [0u8; FOOTER_SIZE];
-        reader.seek(SeekFrom::End(-(FOOTER_SIZE as i64)))?;
-        reader.read_exact(&mut footer)?;
+        let mut trailer = Vec::with_capacity(TRAILER_LENGTH);
+        if let Ok(len) = reader.seek(SeekFrom::End(0)) {
+            reader.seek(SeekFrom::Start(len.saturating_sub(TRAILER_LENGTH as u64)))?;
+            reader.read_to_end(&mut trailer)?;
+        }
 
         reader.seek(SeekFrom::Start(0))?;
 
-        // PNG
-        let header_str = String::from_utf8_lossy(&header).to_lowercase();
-        let chunks = ["png", "ihdr", "idat", "iend"];
-
-        if chunks.iter().any(|chunk| header_str.contains(chunk)) {
-            return Ok(ImageFormat::Png);
-        }
-
-        // JPEG
-        if footer.ends_with(&[0xFF, 0xD9]) {
-            return Ok(ImageFormat::Jpeg);
-        }
+        let guesses = sniff::score(&header[..read_pos], &trailer, extension);
 
-        // GIF
-        for i in 0..4 {
-            if header[i..].starts_with(b"GIF87a") || header[i..].starts_with(b"GIF89a") {
-                return Ok(ImageFormat::Gif);
-            }
-            if header[i..].starts_with(b"IF87a") || header[i..].starts_with(b"IF89a") {
-                return Ok(ImageFormat::Gif);
+        // Registered formats first, then the built-in ones
+        if let Some(format) = utils::registry::sniff(&header[..SNIFF_LENGTH]) {
+            if matches!(format, ImageFormat::Tga | ImageFormat::Jbig1) {
+                if let Some((best, _)) = guesses.first().filter(|(best, _)| matches!(best, ImageFormat::Tga | ImageFormat::Jbig1)) {
+                    return Ok(best.clone());
+                }
             }
-        }
 
-        // TODO other formats
+            return Ok(format);
+        }
 
-        // We tried
-        Ok(ImageFormat::Unknown)
+        // If all else fails, take the most likely of the damaged signatures and pray that it's right
+        Ok(guesses.into_iter().next().map(|(format, _)| format).unwrap_or(ImageFormat::Unknown))
     }
 }
 
@@ -470,8 +1146,7 @@ pub struct JsImageFrame {
 /// Errors are returned as strings.
 #[wasm_bindgen(js_name = getInfo)]
 pub fn get_info(data: &[u8]) -> Result<ImageInfo, String> {
-    let cursor = Cursor::new(data);
-    let mut decoder = Vexel::new(cursor).map_err(|e| e.to_string())?;
+    let mut decoder = Vexel::from_slice(data).map_err(|e| e.to_string())?;
 
     decoder.decode().map_err(|e| e.to_string())?;
     let info = decoder.get_info();
@@ -484,8 +1159,7 @@ pub fn get_info(data: &[u8]) -> Result<ImageInfo, String> {
 /// Errors are returned as strings.
 #[wasm_bindgen(js_name = decodeImage)]
 pub fn decode_image(data: &[u8]) -> Result<JsImage, String> {
-    let cursor = Cursor::new(data);
-    let mut decoder = Vexel::new(cursor).map_err(|e| e.to_string())?;
+    let mut decoder = Vexel::from_slice(data).map_err(|e| e.to_string())?;
 
     let image = decoder.decode().map_err(|e| e.to_string())?;
 
@@ -529,7 +1203,7 @@ pub fn wasm_set_log_level(level: &str) -> Result<(), String> {
 #[wasm_bindgen(js_name = tryGuessFormat)]
 pub fn try_guess_format(data: &[u8]) -> Result<String, String> {
     let mut cursor = Cursor::new(data);
-    let format = Vexel::try_guess_format(&mut cursor).map_err(|e| e.to_string())?;
+    let format = Vexel::try_guess_format(&mut cursor, None).map_err(|e| e.to_string())?;
 
     Ok(format!("{:?}", format))
 }
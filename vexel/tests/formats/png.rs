@@ -50,5 +50,11 @@ pub fn test_cases() -> Vec<TestCase> {
                 reference_path: "png/rgb_alpha_8bit.avif"
             },
         },
+        TestCase {
+            name: "PNG truncated IDAT",
+            path: "png/corrupted/p90_narrow_aisle_mainpic_1_large.png",
+            validation: None,
+            comparison: Comparison::None,
+        },
     ]
 }
@@ -10,7 +10,7 @@ use jpegxl_sys::decode::{
     JxlDecoderStatus,
 };
 use jpegxl_sys::metadata::codestream_header::JxlBasicInfo;
-use vexel::{Image, PixelData, Vexel};
+use vexel::{Image, ImageFormat, PixelData, PixelFormat, Vexel};
 
 pub const BASE_PATH: &str = "./tests/images/";
 pub const REFERENCES_PATH: &str = "./tests/references/";
@@ -662,3 +662,43 @@ pub fn test_decode(test_case: TestCase) -> Result<TestResult, Box<dyn std::error
         Err(e) => Ok(TestResult::Fail(format!("decode error: {:?}", e))),
     }
 }
+
+pub fn test_probe(test_case: &TestCase) -> Result<TestResult, Box<dyn std::error::Error>> {
+    let mut decoder = Vexel::open(get_in_path(test_case.path))?;
+
+    let header = match decoder.probe() {
+        Ok(header) => header,
+        Err(e) => return Ok(TestResult::Fail(format!("probe error: {:?}", e))),
+    };
+
+    let image = match decoder.decode() {
+        Ok(image) => image,
+        Err(e) => return Ok(TestResult::Fail(format!("decode error: {:?}", e))),
+    };
+
+    // 32-bit BMPs without an alpha mask only become RGBA when the pixel data uses the alpha bytes
+    let format_matches = header.pixel_format == image.pixel_format()
+        || (decoder.get_format() == ImageFormat::Bmp
+            && header.pixel_format == PixelFormat::RGB8
+            && image.pixel_format() == PixelFormat::RGBA8);
+
+    if header.width != image.width()
+        || header.height != image.height()
+        || header.frame_count as usize != image.frames().len()
+        || !format_matches
+    {
+        return Ok(TestResult::Fail(format!(
+            "probe returned {}x{} {:?} with {} frames, decode returned {}x{} {:?} with {} frames",
+            header.width,
+            header.height,
+            header.pixel_format,
+            header.frame_count,
+            image.width(),
+            image.height(),
+            image.pixel_format(),
+            image.frames().len()
+        )));
+    }
+
+    Ok(TestResult::Ok { mse: None, ssim: None, psnr: None })
+}
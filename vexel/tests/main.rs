@@ -3,8 +3,9 @@ mod corpus;
 mod formats;
 
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use harness::*;
-use vexel::{Vexel, VexelError};
+use vexel::{guess_format, CancellationToken, ColorTarget, Confidence, CustomInfo, DecodeOptions, DecodeProgress, DecoderRegistration, Image, ImageDecoder, ImageFormat, ImageFrame, ImageHeader, ImageInfo, JpegScale, Limits, LogLevel, LogRecord, Orientation, PixelData, PixelFormat, Progress, ReadSeek, StreamDecoder, Strictness, Vexel, VexelError, VexelResult, WarningKind};
 
 fn load_env_file() {
     let env_path = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().join(".env");
@@ -146,6 +147,1569 @@ fn test_all_formats() -> Result<(), Box<dyn std::error::Error>> {
     run_test_cases(test_cases)
 }
 
+#[test]
+fn test_probe_all_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mut test_cases = Vec::new();
+    test_cases.extend(formats::jpeg::test_cases());
+    test_cases.extend(formats::jpeg_ls::test_cases());
+    test_cases.extend(formats::bmp::test_cases());
+    test_cases.extend(formats::png::test_cases());
+    test_cases.extend(formats::gif::test_cases());
+    test_cases.extend(formats::ico::test_cases());
+    test_cases.extend(formats::tga::test_cases());
+    test_cases.extend(formats::jbig1::test_cases());
+    test_cases.extend(formats::netpbm::test_cases());
+    test_cases.extend(formats::hdr::test_cases());
+    test_cases.extend(formats::tiff::test_cases());
+
+    let name_width = test_cases.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    let mut failures: Vec<String> = Vec::new();
+
+    for test_case in &test_cases {
+        match test_probe(test_case) {
+            Ok(harness::TestResult::Ok { .. }) => {
+                println!("  {:<width$}  OK", test_case.name, width = name_width);
+            }
+            Ok(harness::TestResult::Fail(msg)) => {
+                println!("  {:<width$}  FAIL  {}", test_case.name, msg, width = name_width);
+                failures.push(test_case.name.to_string());
+            }
+            Err(e) => {
+                println!("  {:<width$}  FAIL  {}", test_case.name, e, width = name_width);
+                failures.push(test_case.name.to_string());
+            }
+        }
+    }
+
+    println!();
+    println!("  {}/{} passed", test_cases.len() - failures.len(), test_cases.len());
+
+    if !failures.is_empty() {
+        return Err(format!("{} tests failed: {}", failures.len(), failures.join(", ")).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_probe_respects_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let mut decoder = Vexel::open(get_in_path("png/rgb_8bit.png"))?;
+    decoder.set_limits(Limits { max_image_width: Some(8), ..Limits::default() });
+
+    match decoder.probe() {
+        Err(VexelError::LimitExceeded(_)) => Ok(()),
+        other => Err(format!("expected LimitExceeded, got {:?}", other).into()),
+    }
+}
+
+#[test]
+fn test_extended_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let decode_with = |path: &str, limits: Limits| -> VexelResult<Image> {
+        let mut decoder = Vexel::open(get_in_path(path))?;
+        decoder.set_limits(limits);
+        decoder.decode()
+    };
+
+    let limits = Limits { max_frames: Some(1), ..Limits::default() };
+    if !matches!(decode_with("gif/totoro.gif", limits.clone()), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the frame limit to be enforced for GIF".into());
+    }
+    if !matches!(decode_with("tiff/gray_frames_u1.tif", limits.clone()), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the frame limit to be enforced for TIFF".into());
+    }
+    decode_with("gif/totoro_still.gif", limits)?;
+
+    let limits = Limits { max_metadata_bytes: Some(64), ..Limits::default() };
+    if !matches!(decode_with("jpeg/arithmetic.jpg", limits), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the metadata limit to be enforced".into());
+    }
+
+    let limits = Limits { max_input_bytes: Some(16), ..Limits::default() };
+    if !matches!(decode_with("png/rgb_8bit.png", limits), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the input size limit to be enforced".into());
+    }
+
+    let limits = Limits { max_decode_duration: Some(std::time::Duration::ZERO), ..Limits::default() };
+    if !matches!(decode_with("jpeg/cat.jpg", limits), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the decode duration limit to be enforced".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_compressed_metadata_bomb() -> Result<(), Box<dyn std::error::Error>> {
+    fn put_bits(out: &mut Vec<u8>, acc: &mut u64, count: &mut u32, value: u64, len: u32) {
+        *acc |= value << *count;
+        *count += len;
+        while *count >= 8 {
+            out.push(*acc as u8);
+            *acc >>= 8;
+            *count -= 8;
+        }
+    }
+
+    // One fixed Huffman block: a zero literal followed by length 258 matches at distance 1,
+    // which inflates to about 600 MiB. Huffman codes are stored bit-reversed.
+    let mut bomb = vec![0x78, 0x01];
+    let (mut acc, mut count) = (0u64, 0u32);
+    put_bits(&mut bomb, &mut acc, &mut count, 0b011, 3);
+    put_bits(&mut bomb, &mut acc, &mut count, 0x0C, 8);
+    for _ in 0..(600 << 20) / 258 {
+        put_bits(&mut bomb, &mut acc, &mut count, 0xA3, 13);
+    }
+    put_bits(&mut bomb, &mut acc, &mut count, 0, 7);
+    if count > 0 {
+        bomb.push(acc as u8);
+    }
+    bomb.extend_from_slice(&[0; 4]);
+
+    let png = std::fs::read(get_in_path("png/rgb_8bit.png"))?;
+    for (chunk_type, prefix) in [(b"zTXt", &b"Comment\0\0"[..]), (b"iCCP", &b"icc\0\0"[..])] {
+        // Inserted right after IHDR, with a zero CRC which is only reported as a warning
+        let mut data = png[..33].to_vec();
+        data.extend_from_slice(&((prefix.len() + bomb.len()) as u32).to_be_bytes());
+        data.extend_from_slice(chunk_type);
+        data.extend_from_slice(prefix);
+        data.extend_from_slice(&bomb);
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&png[33..]);
+
+        let mut decoder = Vexel::from_slice(&data)?;
+        decoder.set_limits(Limits::default());
+        match decoder.decode() {
+            Err(VexelError::LimitExceeded(msg)) if msg.contains("inflates") => {}
+            other => {
+                let chunk_type = String::from_utf8_lossy(chunk_type);
+                return Err(format!("expected LimitExceeded for a {} bomb, got {:?}", chunk_type, other.map(|_| ())).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_guess_format() -> Result<(), Box<dyn std::error::Error>> {
+    let data = std::fs::read(get_in_path("png/rgb_8bit.png"))?;
+    if guess_format(&data, None).first() != Some(&(ImageFormat::Png, Confidence::Certain)) {
+        return Err(format!("unexpected guesses for a PNG: {:?}", guess_format(&data, None)).into());
+    }
+
+    // A mangled signature is still recognized, with a lower confidence
+    let mut damaged = data.clone();
+    damaged[0] = 0;
+    if guess_format(&damaged, None).first() != Some(&(ImageFormat::Png, Confidence::High)) {
+        return Err(format!("unexpected guesses for a damaged PNG: {:?}", guess_format(&damaged, None)).into());
+    }
+
+    // BigTIFF, in both byte orders
+    for header in [b"II+\0\x08\0\0\0\x10\0\0\0\0\0\0\0", b"MM\0+\0\x08\0\0\0\0\0\0\0\0\0\x10"] {
+        if guess_format(header, None).first() != Some(&(ImageFormat::Tiff, Confidence::Certain)) {
+            return Err(format!("unexpected guesses for a BigTIFF: {:?}", guess_format(header, None)).into());
+        }
+    }
+
+    let data = std::fs::read(get_in_path("jbig1/2x2.jbg"))?;
+    let guesses = guess_format(&data, Some("jbg"));
+    if guesses.first().map(|guess| &guess.0) != Some(&ImageFormat::Jbig1) {
+        return Err(format!("expected the extension to favour JBIG1: {:?}", guesses).into());
+    }
+
+    if !guess_format(b"not an image", None).is_empty() {
+        return Err("expected no guesses for text".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_tiff_get_info() -> Result<(), Box<dyn std::error::Error>> {
+    let mut decoder = Vexel::open(get_in_path("tiff/gray_frames_u1.tif"))?;
+    let image = decoder.decode()?;
+
+    let ImageInfo::Tiff(info) = decoder.get_info() else {
+        return Err("expected TIFF info".into());
+    };
+
+    // File header plus at least one IFD per frame
+    if info.sections.len() <= image.frames().len() {
+        return Err(format!("expected more than {} sections, got {}", image.frames().len(), info.sections.len()).into());
+    }
+
+    let text = info.to_string();
+    if !text.contains("ImageWidth (256)") || !text.contains("Next IFD offset") {
+        return Err(format!("unexpected TIFF info output:\n{}", text).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_image_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let mut decoder = Vexel::open(get_in_path("jpeg/arithmetic.jpg"))?;
+    let image = decoder.decode()?;
+    let metadata = image.metadata();
+
+    if metadata.icc_data.is_none() || metadata.icc_profile.is_none() {
+        return Err("expected an ICC profile".into());
+    }
+
+    if metadata.text("Comment") != Some("Created with GIMP") {
+        return Err(format!("unexpected comment: {:?}", metadata.text("Comment")).into());
+    }
+
+    let dpi = metadata.resolution.and_then(|resolution| resolution.dpi());
+    if dpi != Some((300.0, 300.0)) {
+        return Err(format!("unexpected resolution: {:?}", metadata.resolution).into());
+    }
+
+    // Conversions keep the metadata
+    if image.into_rgba8().metadata().icc_data.is_none() {
+        return Err("metadata was lost after conversion".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_orientation() -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_in_path("jpeg/canon_hdr_YES.jpg");
+
+    // Reported, but not applied by default
+    let image = Vexel::open(&path)?.decode()?;
+    if image.metadata().orientation != Orientation::RightTop || (image.width(), image.height()) != (2048, 1536) {
+        return Err(format!(
+            "unexpected orientation {:?} for {}x{}",
+            image.metadata().orientation,
+            image.width(),
+            image.height()
+        )
+        .into());
+    }
+
+    let mut decoder = Vexel::open(&path)?;
+    decoder.set_apply_orientation(true);
+    let rotated = decoder.decode()?;
+
+    if (rotated.width(), rotated.height()) != (1536, 2048) {
+        return Err(format!("expected 1536x2048, got {}x{}", rotated.width(), rotated.height()).into());
+    }
+
+    let frame = &rotated.frames()[0];
+    if (frame.width(), frame.height()) != (1536, 2048) || rotated.metadata().orientation != Orientation::TopLeft {
+        return Err("frame was not rotated".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_color_profile_conversion() -> Result<(), Box<dyn std::error::Error>> {
+    // The embedded profile swaps the red and green primaries, so the converted
+    // image should match the same image stored without a profile
+    let mut decoder = Vexel::open(get_in_path("bmp/rgb24prof2.bmp"))?;
+    decoder.set_color_target(ColorTarget::Srgb);
+    let converted = decoder.decode()?;
+
+    if converted.metadata().icc_profile.is_some() {
+        return Err("profile should be removed after conversion".into());
+    }
+
+    let reference = Vexel::open(get_in_path("bmp/rgb24.bmp"))?.decode()?;
+    let max_diff = converted
+        .as_rgb8()
+        .iter()
+        .zip(reference.as_rgb8().iter())
+        .map(|(&a, &b)| (a as i32 - b as i32).abs())
+        .max()
+        .unwrap_or(0);
+
+    if max_diff > 16 {
+        return Err(format!("converted image differs from reference by {}", max_diff).into());
+    }
+
+    let mut decoder = Vexel::open(get_in_path("bmp/rgb24prof2.bmp"))?;
+    decoder.set_color_target(ColorTarget::LinearSrgb);
+    let linear = decoder.decode()?;
+
+    if linear.pixel_format() != PixelFormat::RGB32F {
+        return Err(format!("expected RGB32F, got {:?}", linear.pixel_format()).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cmyk_profile_conversion() -> Result<(), Box<dyn std::error::Error>> {
+    // YCCK image with an embedded lut16 press profile, the top left corner is a pale blue sky
+    let original = Vexel::open(get_in_path("jpeg/ycck.jpg"))?.decode()?;
+
+    let profile = original.metadata().icc_profile.as_ref().ok_or("missing ICC profile")?;
+    if profile.header.color_space.trim() != "CMYK" {
+        return Err(format!("expected CMYK profile, got {}", profile.header.color_space).into());
+    }
+
+    let mut decoder = Vexel::open(get_in_path("jpeg/ycck.jpg"))?;
+    decoder.set_color_target(ColorTarget::Srgb);
+    let converted = decoder.decode()?;
+
+    if converted.metadata().icc_profile.is_some() {
+        return Err("sRGB target should remove the CMYK profile".into());
+    }
+
+    let pixels = converted.as_rgb8();
+    let expected = [197, 213, 237];
+    let max_diff = pixels[..3]
+        .iter()
+        .zip(expected.iter())
+        .map(|(&a, &b)| (a as i32 - b as i32).abs())
+        .max()
+        .unwrap_or(0);
+
+    if max_diff > 8 {
+        return Err(format!("expected about {:?}, got {:?}", expected, &pixels[..3]).into());
+    }
+
+    // Without a target the plain CMYK formula is used
+    if original.as_rgb8() == pixels {
+        return Err("the profile should only be applied with a colour target".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stream_decoder() -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_in_path("gif/totoro.gif");
+    let data = std::fs::read(&path)?;
+
+    let mut decoder = StreamDecoder::new();
+    let mut saw_header = false;
+    let mut frames = 0;
+
+    for chunk in data.chunks(4096) {
+        match decoder.feed(chunk)? {
+            Progress::Header(header) => {
+                saw_header = true;
+                if header.width != 300 || header.height != 300 {
+                    return Err(format!("unexpected header {}x{}", header.width, header.height).into());
+                }
+            }
+            Progress::Frames(count) => {
+                if count <= frames {
+                    return Err(format!("frame count went from {} to {}", frames, count).into());
+                }
+                frames = count;
+            }
+            Progress::Done => break,
+            _ => {}
+        }
+    }
+
+    if !saw_header || frames == 0 || !decoder.is_done() {
+        return Err(format!("incomplete progress: header {}, frames {}", saw_header, frames).into());
+    }
+
+    let streamed = decoder.finish()?;
+    let reference = Vexel::open(&path)?.decode()?;
+
+    if streamed.frames().len() != reference.frames().len() || streamed.as_rgba8() != reference.as_rgba8() {
+        return Err("streamed image differs from a regular decode".into());
+    }
+
+    // Truncated data is not an error, it just needs more input
+    let mut decoder = StreamDecoder::new();
+    let progress = decoder.feed(&data[..data.len() / 2])?;
+    if progress == Progress::Done {
+        return Err("half of the file should not be reported as done".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stream_decoder_png() -> Result<(), Box<dyn std::error::Error>> {
+    use vexel::encode::png::{PngEncoder, PngOptions};
+
+    // Stored without compression, so the image data is split over several IDAT chunks
+    let source = Vexel::open(get_in_path("jpeg/cat.jpg"))?.decode()?;
+    let options = PngOptions {
+        compression_level: 0,
+        ..PngOptions::default()
+    };
+    let data = PngEncoder::new(options).encode(&source)?;
+    let reference = Vexel::from_slice(&data)?.decode()?;
+
+    let mut decoder = StreamDecoder::new();
+    let mut saw_header = false;
+    let mut rows = 0;
+
+    for chunk in data.chunks(4096) {
+        match decoder.feed(chunk)? {
+            Progress::Header(header) => {
+                saw_header = true;
+                if (header.width, header.height) != (reference.width(), reference.height()) {
+                    return Err(format!("unexpected header {}x{}", header.width, header.height).into());
+                }
+            }
+            Progress::Rows(count) => {
+                if count <= rows || count > reference.height() {
+                    return Err(format!("row count went from {} to {}", rows, count).into());
+                }
+                rows = count;
+
+                // The rows decoded so far are the same as in a regular decode
+                let image = decoder.image().ok_or("no partial image after new rows")?;
+                let row_length = reference.width() as usize * 4;
+                if image.height() != count || image.as_rgba8() != reference.as_rgba8()[..count as usize * row_length] {
+                    return Err(format!("the first {} rows differ from a regular decode", count).into());
+                }
+            }
+            Progress::Done => break,
+            _ => {}
+        }
+    }
+
+    if !saw_header || rows < reference.height() / 2 || !decoder.is_done() {
+        return Err(format!("incomplete progress: header {}, rows {}", saw_header, rows).into());
+    }
+
+    if decoder.finish()?.as_rgba8() != reference.as_rgba8() {
+        return Err("streamed image differs from a regular decode".into());
+    }
+
+    // Limits are still errors
+    let mut decoder = StreamDecoder::new();
+    decoder.set_limits(Limits {
+        max_image_width: Some(reference.width() - 1),
+        ..Limits::default()
+    });
+    let result = data.chunks(4096).try_for_each(|chunk| decoder.feed(chunk).map(|_| ()));
+    if !matches!(result, Err(VexelError::LimitExceeded(_))) {
+        return Err(format!("expected the width limit to be enforced, got {:?}", result).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stream_decoder_jpeg() -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_in_path("jpeg/rose_progressive_12bit.jpg");
+    let data = std::fs::read(&path)?;
+    let reference = Vexel::open(&path)?.decode()?;
+
+    let mut decoder = StreamDecoder::new();
+    let mut scans = 0;
+
+    for chunk in data.chunks(1024) {
+        match decoder.feed(chunk)? {
+            Progress::Scans(count) => {
+                if count <= scans {
+                    return Err(format!("scan count went from {} to {}", scans, count).into());
+                }
+                scans = count;
+
+                // Each checkpoint holds a partial image of the full size
+                let image = decoder.image().ok_or("no partial image at a checkpoint")?;
+                if (image.width(), image.height()) != (reference.width(), reference.height()) {
+                    return Err(format!("partial image is {}x{}", image.width(), image.height()).into());
+                }
+            }
+            Progress::Done => break,
+            _ => {}
+        }
+    }
+
+    // A progressive JPEG has several scans, so there is more than one checkpoint before the end
+    if scans < 2 || !decoder.is_done() {
+        return Err(format!("incomplete progress: {} scans", scans).into());
+    }
+
+    if decoder.finish()?.as_rgba8() != reference.as_rgba8() {
+        return Err("streamed image differs from a regular decode".into());
+    }
+
+    // Running out of data before the first scan ends is not an error
+    let mut decoder = StreamDecoder::new();
+    let progress = decoder.feed(&data[..data.len() / 8])?;
+    if progress == Progress::Done {
+        return Err("an eighth of the file should not be reported as done".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_stream_decoder_baseline_jpeg() -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_in_path("jpeg/cat.jpg");
+    let data = std::fs::read(&path)?;
+    let reference = Vexel::open(&path)?.decode()?;
+    let row_length = reference.width() as usize * 4;
+
+    let mut decoder = StreamDecoder::new();
+    let mut rows = 0;
+    let mut updates = 0;
+
+    for chunk in data.chunks(1024) {
+        match decoder.feed(chunk)? {
+            Progress::Rows(count) => {
+                if count <= rows || count > reference.height() {
+                    return Err(format!("row count went from {} to {}", rows, count).into());
+                }
+                rows = count;
+                updates += 1;
+
+                // MCU rows are decoded as they arrive and match a regular decode
+                let image = decoder.image().ok_or("no partial image after new rows")?;
+                if image.height() != count || image.as_rgba8() != reference.as_rgba8()[..count as usize * row_length] {
+                    return Err(format!("the first {} rows differ from a regular decode", count).into());
+                }
+            }
+            Progress::Scans(_) => return Err("a baseline JPEG should be decoded by rows".into()),
+            Progress::Done => break,
+            _ => {}
+        }
+    }
+
+    if updates < 2 || !decoder.is_done() {
+        return Err(format!("incomplete progress: {} rows in {} updates", rows, updates).into());
+    }
+
+    if decoder.finish()?.as_rgba8() != reference.as_rgba8() {
+        return Err("streamed image differs from a regular decode".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_frames_iterator() -> Result<(), Box<dyn std::error::Error>> {
+    let path = get_in_path("gif/totoro.gif");
+    let image = Vexel::open(&path)?.decode()?;
+
+    let mut decoder = Vexel::open(&path)?;
+    let frames = decoder.frames()?.collect::<Result<Vec<_>, _>>()?;
+
+    if frames.len() != image.frames().len() {
+        return Err(format!("expected {} frames, got {}", image.frames().len(), frames.len()).into());
+    }
+
+    for (i, (frame, expected)) in frames.iter().zip(image.frames()).enumerate() {
+        if frame.as_rgba8() != expected.as_rgba8() || frame.delay() != expected.delay() {
+            return Err(format!("frame {} differs from a regular decode", i).into());
+        }
+    }
+
+    // Pages are handed out one at a time, so a budget that fits two pages is enough
+    let path = get_in_path("tiff/gray_frames_u1.tif");
+    let header = Vexel::open(&path)?.probe()?;
+    let limits = Limits {
+        max_alloc: Some(header.width as u64 * header.height as u64 * 4 * 2),
+        ..Limits::default()
+    };
+
+    let mut decoder = Vexel::open(&path)?;
+    decoder.set_limits(limits.clone());
+    if !matches!(decoder.decode(), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected decode to exceed the limits".into());
+    }
+
+    let mut decoder = Vexel::open(&path)?;
+    decoder.set_limits(limits);
+    let pages = decoder.frames()?.collect::<Result<Vec<_>, _>>()?;
+    if pages.len() != header.frame_count as usize {
+        return Err(format!("expected {} pages, got {}", header.frame_count, pages.len()).into());
+    }
+
+    let mut decoder = Vexel::open(&path)?;
+    let Some(page) = decoder.frames()?.nth(5) else {
+        return Err("expected a sixth page".into());
+    };
+    if page?.as_rgba8() != pages[5].as_rgba8() {
+        return Err("seeking to a page differs from iterating to it".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_region() -> Result<(), Box<dyn std::error::Error>> {
+    fn crop(frame: &ImageFrame, x: u32, y: u32, width: u32, height: u32) -> Vec<u8> {
+        let pixels = frame.as_rgba8();
+        let mut out = Vec::new();
+
+        for row in y..y + height {
+            let start = (row * frame.width() + x) as usize * 4;
+            out.extend_from_slice(&pixels[start..start + width as usize * 4]);
+        }
+
+        out
+    }
+
+    for path in ["jpeg/cat.jpg", "jpeg/rose_progressive_12bit.jpg", "tiff/rgb_tiled_u2.tif", "tiff/gray_tiled_i8.tif"] {
+        let image = Vexel::open(get_in_path(path))?.decode()?;
+        let (width, height) = (image.width(), image.height());
+
+        for (x, y, w, h) in [(0, 0, width, height), (width / 4, height / 5, width / 2, height / 3), (width - 5, height - 3, 5, 3)] {
+            let region = Vexel::open(get_in_path(path))?.decode_region(x, y, w, h)?;
+
+            if region.width() != w || region.height() != h {
+                return Err(format!("{}: expected a {}x{} region, got {}x{}", path, w, h, region.width(), region.height()).into());
+            }
+
+            if region.frames()[0].as_rgba8() != crop(&image.frames()[0], x, y, w, h) {
+                return Err(format!("{}: region at ({}, {}) differs from a cropped decode", path, x, y).into());
+            }
+        }
+
+        if Vexel::open(get_in_path(path))?.decode_region(width - 1, 0, 2, 1).is_ok() {
+            return Err(format!("{}: expected an error for a region outside of the image", path).into());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_into() -> Result<(), Box<dyn std::error::Error>> {
+    let paths = [
+        "png/gray_alpha_8bit.png",
+        "png/rgb_8bit.png",
+        "jpeg/cat.jpg",
+        "bmp/rgb16-565.bmp",
+        "bmp/pal2.bmp",
+        "bmp/rgba32.bmp",
+        "bmp/RLE4_2.bmp",
+        "tga/CBW8.tga",
+        "tga/TGA_24_rle.tga",
+        "tga/TGA_flipped_uncompressed.tga",
+        "netpbm/3c-8b.ppm",
+        "netpbm/P5_multiframe.pgm",
+        "gif/totoro.gif",
+    ];
+    for path in paths {
+        let image = Vexel::open(get_in_path(path))?.decode()?;
+        let (width, height) = (image.width() as usize, image.height() as usize);
+
+        for (format, expected) in [(PixelFormat::RGBA8, image.as_rgba8()), (PixelFormat::RGB8, image.as_rgb8())] {
+            let row_bytes = width * format.bytes_per_pixel();
+            let stride = row_bytes + 7;
+            let mut buffer = vec![0xAA; stride * height];
+
+            let header = Vexel::open(get_in_path(path))?.decode_into(&mut buffer, stride, format.clone())?;
+            if (header.width as usize, header.height as usize) != (width, height) {
+                return Err(format!("{}: unexpected {}x{} image", path, header.width, header.height).into());
+            }
+
+            for (y, row) in buffer.chunks(stride).enumerate() {
+                if row[..row_bytes] != expected[y * row_bytes..(y + 1) * row_bytes] {
+                    return Err(format!("{}: row {} differs from as_{:?}", path, y, format).into());
+                }
+
+                if row[row_bytes..].iter().any(|&b| b != 0xAA) {
+                    return Err(format!("{}: padding after row {} was overwritten", path, y).into());
+                }
+            }
+        }
+
+        // The buffer is rejected if it is too small or the stride is too short, before anything
+        // is written
+        let mut small = vec![0xAA; width * 4 * height - 1];
+        if Vexel::open(get_in_path(path))?.decode_into(&mut small, width * 4, PixelFormat::RGBA8).is_ok() {
+            return Err(format!("{}: expected an error for a buffer that is too small", path).into());
+        }
+        if small.iter().any(|&b| b != 0xAA) {
+            return Err(format!("{}: a buffer that is too small was written to", path).into());
+        }
+
+        let mut buffer = vec![0; width * 4 * height];
+        if Vexel::open(get_in_path(path))?.decode_into(&mut buffer, width * 4 - 1, PixelFormat::RGBA8).is_ok() {
+            return Err(format!("{}: expected an error for a stride that is too short", path).into());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_from_slice() -> Result<(), Box<dyn std::error::Error>> {
+    let paths = [
+        "png/rgb_8bit.png",
+        "png/corrupted/jxls-amazing-2.png",
+        "jpeg/cat.jpg",
+        "tiff/rgb_tiled_u2.tif",
+        "tiff/rgb_i1_deflate.tif",
+        "gif/totoro.gif",
+    ];
+
+    for path in paths {
+        let expected = Vexel::open(get_in_path(path))?.decode()?;
+        let data = std::fs::read(get_in_path(path))?;
+
+        let mut decoder = Vexel::from_slice(&data)?;
+        let image = decoder.decode()?;
+        if decoder.get_format() != Vexel::open(get_in_path(path))?.get_format() || image.frames().len() != expected.frames().len() {
+            return Err(format!("{}: decoding from a slice found a different image", path).into());
+        }
+
+        if image.as_rgba8() != expected.as_rgba8() {
+            return Err(format!("{}: pixels decoded from a slice differ", path).into());
+        }
+
+        if image.report().warnings.len() != expected.report().warnings.len() {
+            return Err(format!("{}: decoding from a slice reported different warnings", path).into());
+        }
+
+        // Data borrowed from a truncated slice must end where a reader runs out of data
+        let truncated = &data[..data.len() / 2];
+        let from_reader = Vexel::new(std::io::Cursor::new(truncated.to_vec())).and_then(|mut decoder| decoder.decode());
+        let from_slice = Vexel::from_slice(truncated).and_then(|mut decoder| decoder.decode());
+        let pixels = |result: VexelResult<Image>| result.map(|image| image.as_rgba8()).map_err(|e| e.to_string());
+        if pixels(from_reader) != pixels(from_slice) {
+            return Err(format!("{}: decoding a truncated slice differs from a reader", path).into());
+        }
+
+        #[cfg(feature = "mmap")]
+        {
+            let mapped = unsafe { Vexel::open_mmap(get_in_path(path))? }.decode()?;
+            if mapped.as_rgba8() != expected.as_rgba8() {
+                return Err(format!("{}: pixels decoded from a mapped file differ", path).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_deflate_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    use vexel::deflate::{adler32, DeflateDecoder, DeflateEncoder, ZlibDecoder, ZlibEncoder};
+
+    let mut state = 0x2545_F491u32;
+    let noise: Vec<u8> = (0..100_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+
+    let inputs = [
+        Vec::new(),
+        vec![42],
+        vec![0; 200_000],
+        b"abcabcabd".repeat(5000),
+        noise,
+        std::fs::read(get_in_path("bmp/Parrots.bmp"))?,
+        std::fs::read(get_in_path("jpeg/cat.jpg"))?,
+    ];
+
+    for (i, data) in inputs.iter().enumerate() {
+        for level in 0..=9 {
+            let zlib = ZlibEncoder::new(level).encode(data);
+            if ZlibDecoder::from_bytes(zlib.clone()).decode() != *data {
+                return Err(format!("input {}: zlib stream at level {} does not round-trip", i, level).into());
+            }
+
+            if zlib[zlib.len() - 4..] != adler32(data).to_be_bytes() || u16::from_be_bytes([zlib[0], zlib[1]]) % 31 != 0 {
+                return Err(format!("input {}: invalid zlib header or checksum at level {}", i, level).into());
+            }
+
+            let deflate = DeflateEncoder::new(level).encode(data);
+            if DeflateDecoder::from_bytes(deflate.clone()).decode_with_capacity(data.len()) != *data {
+                return Err(format!("input {}: deflate stream at level {} does not round-trip", i, level).into());
+            }
+
+            // Incompressible data falls back to stored blocks, which add 5 bytes per block
+            if deflate.len() > data.len() + 5 * (data.len() / 16384 + 1) {
+                return Err(format!("input {}: {} bytes grew to {} at level {}", i, data.len(), deflate.len(), level).into());
+            }
+        }
+    }
+
+    if adler32(b"Wikipedia") != 0x11E6_0398 {
+        return Err("unexpected Adler-32 checksum".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_png_encoder() -> Result<(), Box<dyn std::error::Error>> {
+    use vexel::encode::png::{ColorType, FilterType, PngEncoder, PngOptions};
+    use vexel::{Resolution, ResolutionUnit, TextEntry};
+
+    let (width, height) = (37u32, 23u32);
+    let count = (width * height) as usize;
+    let rgba16: Vec<u16> = (0..count * 4).map(|i| (i.wrapping_mul(2654435761) >> 7) as u16).collect();
+    let rgba16_image = Image::from_pixels(width, height, PixelData::RGBA16(rgba16.clone()));
+
+    for interlaced in [false, true] {
+        for filter in [None, Some(FilterType::None), Some(FilterType::Paeth)] {
+            let options = PngOptions { interlaced, filter, ..Default::default() };
+
+            let png = PngEncoder::new(options.clone()).encode(&rgba16_image)?;
+            if !matches!(Vexel::from_slice(&png)?.decode()?.pixels(), PixelData::RGBA16(ref p) if *p == rgba16) {
+                return Err(format!("16-bit RGBA does not round-trip (interlaced: {})", interlaced).into());
+            }
+
+            for bit_depth in [1u8, 2, 4, 8, 16] {
+                // Levels that are exact at every bit depth, with the brightest one kept for the
+                // transparent pixels so it can serve as the colour key
+                let max = (1u32 << bit_depth.min(8)) - 1;
+                let gray: Vec<u8> = (0..count as u32).map(|i| ((i * 31 % max) * 255 / max) as u8).collect();
+                let gray_alpha: Vec<u8> = gray
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, &g)| if i % 5 == 0 { [255, 0] } else { [g, 255] })
+                    .collect();
+
+                let image = Image::from_pixels(width, height, PixelData::LA8(gray_alpha.clone()));
+                let options = PngOptions { color_type: Some(ColorType::Grayscale), bit_depth: Some(bit_depth), ..options.clone() };
+                let decoded = Vexel::from_slice(&PngEncoder::new(options).encode(&image)?)?.decode()?;
+
+                let expected: Vec<u8> = gray_alpha.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect();
+                if decoded.as_rgba8() != expected {
+                    return Err(format!("{}-bit keyed grayscale does not round-trip", bit_depth).into());
+                }
+            }
+
+            for colors in [2usize, 3, 16, 200] {
+                let rgba: Vec<u8> = (0..count)
+                    .flat_map(|i| {
+                        let c = i * 7 % colors;
+                        [c as u8, (c * 3) as u8, 9, if c % 3 == 0 { (c * 5) as u8 } else { 255 }]
+                    })
+                    .collect();
+
+                let image = Image::from_pixels(width, height, PixelData::RGBA8(rgba.clone()));
+                let options = PngOptions { color_type: Some(ColorType::Indexed), ..options.clone() };
+                if Vexel::from_slice(&PngEncoder::new(options).encode(&image)?)?.decode()?.as_rgba8() != rgba {
+                    return Err(format!("indexed image with {} colours does not round-trip", colors).into());
+                }
+            }
+        }
+    }
+
+    let mut image = Vexel::open(get_in_path("bmp/rgb24prof.bmp"))?.decode()?;
+    let metadata = image.metadata_mut();
+    metadata.text.push(TextEntry { key: "Title".to_string(), value: "Parrots".to_string() });
+    metadata.text.push(TextEntry { key: "Comment".to_string(), value: "long ".repeat(500) });
+    metadata.text.push(TextEntry { key: "Author".to_string(), value: "Zoë 日本".to_string() });
+    metadata.xmp.push("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>".to_string());
+    metadata.resolution = Some(Resolution { x: 2835.0, y: 2835.0, unit: ResolutionUnit::Meter });
+
+    let decoded = Vexel::from_slice(&PngEncoder::new(PngOptions::default()).encode(&image)?)?.decode()?;
+    if decoded.as_rgb8() != image.as_rgb8() {
+        return Err("pixels of the image with metadata do not round-trip".into());
+    }
+    for (field, same) in [
+        ("icc_data", decoded.metadata().icc_data == image.metadata().icc_data),
+        ("text", decoded.metadata().text == image.metadata().text),
+        ("xmp", decoded.metadata().xmp == image.metadata().xmp),
+        ("resolution", decoded.metadata().resolution == image.metadata().resolution),
+    ] {
+        if !same {
+            return Err(format!("metadata field {} does not round-trip", field).into());
+        }
+    }
+
+    let animation = Vexel::open(get_in_path("gif/gray_frames_u1.gif"))?.decode()?;
+    for interlaced in [false, true] {
+        let options = PngOptions { interlaced, ..Default::default() };
+        let decoded = Vexel::from_slice(&PngEncoder::new(options).encode(&animation)?)?.decode()?;
+
+        if decoded.frames().len() != animation.frames().len() {
+            return Err(format!("expected {} APNG frames, got {}", animation.frames().len(), decoded.frames().len()).into());
+        }
+        for (i, (frame, original)) in decoded.frames().iter().zip(animation.frames()).enumerate() {
+            if frame.as_rgba8() != original.as_rgba8() || frame.delay() != original.delay() {
+                return Err(format!("APNG frame {} does not round-trip", i).into());
+            }
+        }
+    }
+
+    // Icon variants have no delays and are not an animation
+    let icon = Vexel::open(get_in_path("ico/kuromi-2bd2e238.cur"))?.decode()?;
+    let decoded = Vexel::from_slice(&PngEncoder::new(PngOptions::default()).encode(&icon)?)?.decode()?;
+    if decoded.frames().len() != 1 || decoded.as_rgba8() != icon.frames()[0].as_rgba8() {
+        return Err("only the first icon variant should be written".into());
+    }
+
+    let options = PngOptions { color_type: Some(ColorType::RGB), bit_depth: Some(4), ..Default::default() };
+    if PngEncoder::new(options).encode(&rgba16_image).is_ok() {
+        return Err("4-bit RGB should be rejected".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_tiff_encoder() -> Result<(), Box<dyn std::error::Error>> {
+    use vexel::encode::tiff::{ByteOrder, Compression, PlanarConfiguration, Predictor, TiffEncoder, TiffOptions};
+    use vexel::{Resolution, ResolutionUnit};
+
+    let (width, height) = (37u32, 23u32);
+    let count = (width * height) as usize;
+    // Smooth samples mixed with noise, so every compression has something to do
+    let value = |i: usize| if i % 3 == 0 { (i / 7) as u32 } else { (i.wrapping_mul(2654435761) >> 5) as u32 };
+    let samples = |channels: usize| (0..count * channels).map(value);
+
+    let pixel_data = [
+        PixelData::L1(samples(1).map(|v| (v & 1) as u8).collect()),
+        PixelData::L8(samples(1).map(|v| v as u8).collect()),
+        PixelData::LA8(samples(2).map(|v| v as u8).collect()),
+        PixelData::RGB8(samples(3).map(|v| v as u8).collect()),
+        PixelData::RGBA8(samples(4).map(|v| v as u8).collect()),
+        PixelData::L16(samples(1).map(|v| v as u16).collect()),
+        PixelData::LA16(samples(2).map(|v| v as u16).collect()),
+        PixelData::RGB16(samples(3).map(|v| v as u16).collect()),
+        PixelData::RGBA16(samples(4).map(|v| v as u16).collect()),
+        PixelData::L32F(samples(1).map(|v| v as f32 / 977.0).collect()),
+        PixelData::LA32F(samples(2).map(|v| v as f32 / 977.0).collect()),
+        PixelData::RGB32F(samples(3).map(|v| v as f32 / 977.0).collect()),
+        PixelData::RGBA32F(samples(4).map(|v| v as f32 / 977.0).collect()),
+        PixelData::L64F(samples(1).map(|v| v as f64 / 977.0).collect()),
+        PixelData::LA64F(samples(2).map(|v| v as f64 / 977.0).collect()),
+        PixelData::RGB64F(samples(3).map(|v| v as f64 / 977.0).collect()),
+        PixelData::RGBA64F(samples(4).map(|v| v as f64 / 977.0).collect()),
+    ];
+
+    for pixels in pixel_data {
+        let format = pixels.pixel_format();
+        let image = Image::from_pixels(width, height, pixels.clone());
+        let predictor = match format {
+            PixelFormat::L1 => Predictor::None,
+            PixelFormat::L32F | PixelFormat::LA32F | PixelFormat::RGB32F | PixelFormat::RGBA32F => Predictor::FloatingPoint,
+            PixelFormat::L64F | PixelFormat::LA64F | PixelFormat::RGB64F | PixelFormat::RGBA64F => Predictor::FloatingPoint,
+            _ => Predictor::HorizontalDifferencing,
+        };
+
+        for compression in [Compression::None, Compression::LZW, Compression::PackBits, Compression::AdobeDeflate] {
+            for (planar_configuration, tile_size, byte_order) in [
+                (PlanarConfiguration::Chunky, None, ByteOrder::LittleEndian),
+                (PlanarConfiguration::Planar, None, ByteOrder::BigEndian),
+                (PlanarConfiguration::Chunky, Some((16, 32)), ByteOrder::BigEndian),
+                (PlanarConfiguration::Planar, Some((32, 16)), ByteOrder::LittleEndian),
+            ] {
+                let options = TiffOptions {
+                    compression,
+                    predictor: if compression == Compression::None { Predictor::None } else { predictor },
+                    planar_configuration,
+                    tile_size,
+                    rows_per_strip: Some(5),
+                    byte_order,
+                    ..Default::default()
+                };
+
+                let decoded = Vexel::from_slice(&TiffEncoder::new(options.clone()).encode(&image)?)?.decode()?;
+                if decoded.pixel_format() != format || decoded.pixels().as_bytes() != pixels.as_bytes() {
+                    return Err(format!("{:?} does not round-trip with {:?}", format, options).into());
+                }
+            }
+        }
+    }
+
+    let mut image = Image::new(
+        10,
+        8,
+        PixelFormat::RGB8,
+        vec![
+            ImageFrame::new(10, 8, PixelData::RGB8((0..240).map(|v| v as u8).collect()), 0),
+            ImageFrame::new(5, 3, PixelData::L16(vec![300; 15]), 0),
+            ImageFrame::new(9, 9, PixelData::L1((0..81).map(|v| v as u8 % 2).collect()), 0),
+        ],
+    );
+    let metadata = image.metadata_mut();
+    metadata.icc_data = Vexel::open(get_in_path("bmp/rgb24prof.bmp"))?.decode()?.metadata().icc_data.clone();
+    metadata.resolution = Some(Resolution { x: 300.0, y: 150.5, unit: ResolutionUnit::Inch });
+    metadata.orientation = Orientation::RightTop;
+
+    for big_tiff in [false, true] {
+        let options = TiffOptions { compression: Compression::LZW, big_tiff, ..Default::default() };
+        let encoded = TiffEncoder::new(options).encode(&image)?;
+        if big_tiff && encoded[..8] != [b'I', b'I', 43, 0, 8, 0, 0, 0] {
+            return Err("invalid BigTIFF header".into());
+        }
+
+        let decoded = Vexel::from_slice(&encoded)?.decode()?;
+        if decoded.frames().len() != 3 {
+            return Err(format!("expected 3 pages, got {}", decoded.frames().len()).into());
+        }
+        for (i, (frame, original)) in decoded.frames().iter().zip(image.frames()).enumerate() {
+            if frame.pixels().as_bytes() != original.pixels().as_bytes() {
+                return Err(format!("page {} does not round-trip (BigTIFF: {})", i, big_tiff).into());
+            }
+        }
+        if decoded.metadata().icc_data != image.metadata().icc_data
+            || decoded.metadata().resolution != image.metadata().resolution
+            || decoded.metadata().orientation != Orientation::RightTop
+        {
+            return Err(format!("TIFF metadata does not round-trip (BigTIFF: {})", big_tiff).into());
+        }
+    }
+
+    let options = TiffOptions { compression: Compression::JPEG, ..Default::default() };
+    if TiffEncoder::new(options).encode(&image).is_ok() {
+        return Err("JPEG compression should be rejected".into());
+    }
+
+    let short = Image::from_pixels(10, 8, PixelData::RGB8(vec![0; 200]));
+    for tile_size in [None, Some((16, 16))] {
+        let options = TiffOptions { tile_size, ..Default::default() };
+        if TiffEncoder::new(options).encode(&short).is_ok() {
+            return Err(format!("a short frame buffer should be rejected (tiles: {:?})", tile_size).into());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_gif_encoder() -> Result<(), Box<dyn std::error::Error>> {
+    use vexel::encode::gif::{GifEncoder, GifOptions, Quantizer};
+
+    // A square moving over a gradient, with a transparent strip in every other frame and a
+    // frame smaller than the canvas
+    let (width, height) = (40u32, 30u32);
+    let mut frames = Vec::new();
+    for k in 0..6u32 {
+        let (frame_width, frame_height) = if k == 3 { (20, 10) } else { (width, height) };
+        let mut pixels = Vec::new();
+        for y in 0..frame_height {
+            for x in 0..frame_width {
+                if (k * 5..k * 5 + 8).contains(&x) && (k * 3..k * 3 + 8).contains(&y) {
+                    pixels.extend_from_slice(&[255, (k * 40) as u8, 0, 255]);
+                } else {
+                    let alpha = if k % 2 == 1 && x < 3 { 0 } else { 255 };
+                    pixels.extend_from_slice(&[(x / 4 * 24) as u8, (y / 3 * 24) as u8, 90, alpha]);
+                }
+            }
+        }
+        frames.push(ImageFrame::new(frame_width, frame_height, PixelData::RGBA8(pixels), 100 + k * 10));
+    }
+    let image = Image::new(width, height, PixelFormat::RGBA8, frames);
+
+    // What every frame should look like, each drawn over the previous ones
+    let mut canvas = vec![0u8; (width * height * 4) as usize];
+    let mut expected = Vec::new();
+    for frame in image.frames() {
+        let rgba = frame.clone().into_rgba8();
+        let row_bytes = frame.width() as usize * 4;
+        for (y, row) in rgba.pixels().as_bytes().chunks_exact(row_bytes).enumerate() {
+            let start = y * width as usize * 4;
+            canvas[start..start + row_bytes].copy_from_slice(row);
+        }
+        expected.push(canvas.clone());
+    }
+
+    for optimize in [true, false] {
+        for local_palettes in [false, true] {
+            for dither in [false, true] {
+                let options = GifOptions {
+                    optimize,
+                    local_palettes,
+                    dither,
+                    loop_count: Some(3),
+                    ..Default::default()
+                };
+
+                let data = GifEncoder::new(options.clone()).encode(&image)?;
+                if !data.windows(15).any(|window| window == b"NETSCAPE2.0\x03\x01\x03\x00") {
+                    return Err("GIF has no NETSCAPE2.0 loop count".into());
+                }
+
+                let decoded = Vexel::from_slice(&data)?.decode()?;
+                if decoded.frames().len() != expected.len() {
+                    return Err(format!("expected {} frames, got {}", expected.len(), decoded.frames().len()).into());
+                }
+
+                for (i, (frame, expected)) in decoded.frames().iter().zip(&expected).enumerate() {
+                    // Fully transparent pixels can have any colour
+                    let matches = frame
+                        .pixels()
+                        .as_bytes()
+                        .chunks_exact(4)
+                        .zip(expected.chunks_exact(4))
+                        .all(|(got, want)| (got[3] == 0 && want[3] == 0) || got == want);
+                    if !matches || frame.delay() != image.frames()[i].delay() {
+                        return Err(format!("frame {} does not round-trip with {:?}", i, options).into());
+                    }
+                }
+            }
+        }
+    }
+
+    let image = Vexel::open(get_in_path("gif/gray_frames_u1.gif"))?.decode()?;
+    let decoded = Vexel::from_slice(&GifEncoder::new(GifOptions::default()).encode(&image)?)?.decode()?;
+    if decoded.frames().len() != image.frames().len() {
+        return Err("gray_frames_u1.gif lost frames".into());
+    }
+    for (frame, original) in decoded.frames().iter().zip(image.frames()) {
+        if frame.pixels().as_bytes() != original.pixels().as_bytes() {
+            return Err("gray_frames_u1.gif does not round-trip".into());
+        }
+    }
+
+    let image = Vexel::open(get_in_path("bmp/Parrots.bmp"))?.decode()?;
+    let original = image.frames()[0].clone().into_rgba8();
+    for quantizer in [Quantizer::MedianCut, Quantizer::Octree] {
+        for (max_colors, max_error) in [(256, 6.0), (16, 20.0)] {
+            let options = GifOptions {
+                quantizer,
+                max_colors,
+                dither: max_colors == 16,
+                ..Default::default()
+            };
+
+            let decoded = Vexel::from_slice(&GifEncoder::new(options).encode(&image)?)?.decode()?;
+            let mut colors = std::collections::HashSet::new();
+            let mut error = 0u64;
+            for (got, want) in decoded.frames()[0].pixels().as_bytes().chunks_exact(4).zip(original.pixels().as_bytes().chunks_exact(4)) {
+                colors.insert([got[0], got[1], got[2]]);
+                error += (0..3).map(|c| got[c].abs_diff(want[c]) as u64).sum::<u64>();
+            }
+
+            let mean_error = error as f64 / (image.width() * image.height() * 3) as f64;
+            if colors.len() > max_colors as usize || mean_error > max_error {
+                return Err(format!("{:?} gives {} colours with a mean error of {:.2}", quantizer, colors.len(), mean_error).into());
+            }
+        }
+    }
+
+    let options = GifOptions { max_colors: 300, ..Default::default() };
+    if GifEncoder::new(options).encode(&image).is_ok() {
+        return Err("a palette of 300 colours should be rejected".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_jpeg_scale() -> Result<(), Box<dyn std::error::Error>> {
+    for path in ["jpeg/cat.jpg", "jpeg/rose_progressive_12bit.jpg", "jpeg/demo2_arithmetic.jpg"] {
+        let image = Vexel::open(get_in_path(path))?.decode()?;
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let full = image.as_rgb8();
+
+        for (scale, n) in [(JpegScale::Half, 2), (JpegScale::Quarter, 4), (JpegScale::Eighth, 8)] {
+            let mut decoder = Vexel::open(get_in_path(path))?;
+            decoder.set_jpeg_scale(scale);
+            let header = decoder.probe()?;
+            let scaled = decoder.decode()?;
+            let (w, h) = (scaled.width() as usize, scaled.height() as usize);
+
+            if (w, h) != (width.div_ceil(n), height.div_ceil(n)) || (header.width as usize, header.height as usize) != (w, h) {
+                return Err(format!("{}: unexpected {}x{} image at {:?}", path, w, h, scale).into());
+            }
+
+            // Each sample should be close to the average of the pixels it covers
+            let pixels = scaled.as_rgb8();
+            let mut total_error = 0.0;
+            for y in 0..h {
+                for x in 0..w {
+                    for c in 0..3 {
+                        let (mut sum, mut count) = (0.0, 0.0);
+                        for yy in y * n..((y + 1) * n).min(height) {
+                            for xx in x * n..((x + 1) * n).min(width) {
+                                sum += full[(yy * width + xx) * 3 + c] as f64;
+                                count += 1.0;
+                            }
+                        }
+                        total_error += (sum / count - pixels[(y * w + x) * 3 + c] as f64).abs();
+                    }
+                }
+            }
+
+            let mean_error = total_error / (w * h * 3) as f64;
+            if mean_error > 4.0 {
+                return Err(format!("{}: mean error {:.2} at {:?} is too large", path, mean_error, scale).into());
+            }
+
+            let mut decoder = Vexel::open(get_in_path(path))?;
+            decoder.set_jpeg_scale(scale);
+            let region = decoder.decode_region(w as u32 / 4, h as u32 / 4, w as u32 / 2, h as u32 / 2)?;
+            if (region.width(), region.height()) != (w as u32 / 2, h as u32 / 2) {
+                return Err(format!("{}: region of a scaled image has the wrong size", path).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_decoder() -> Result<(), Box<dyn std::error::Error>> {
+    // "VXGR", little-endian width and height, then one byte per pixel. Limits are left to Vexel.
+    struct GrayDecoder;
+
+    impl GrayDecoder {
+        fn read_header(&self, reader: &mut dyn ReadSeek) -> VexelResult<(u32, u32)> {
+            let mut header = [0u8; 12];
+            reader.read_exact(&mut header)?;
+
+            let width = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            let height = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+
+            Ok((width, height))
+        }
+    }
+
+    impl ImageDecoder for GrayDecoder {
+        fn probe(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<ImageHeader> {
+            let (width, height) = self.read_header(reader)?;
+
+            Ok(ImageHeader {
+                width,
+                height,
+                pixel_format: PixelFormat::L8,
+                frame_count: 1,
+                is_animated: false,
+            })
+        }
+
+        fn set_limits(&mut self, _limits: Limits) {}
+
+        fn decode(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<Image> {
+            let (width, height) = self.read_header(reader)?;
+            assert!(width > 0, "empty image");
+
+            let mut pixels = vec![0u8; (width * height) as usize];
+            reader.read_exact(&mut pixels)?;
+
+            Ok(Image::from_pixels(width, height, PixelData::L8(pixels)))
+        }
+
+        fn info(&self) -> ImageInfo {
+            ImageInfo::Custom(CustomInfo {
+                format: "VXGR".to_string(),
+                entries: Vec::new(),
+            })
+        }
+    }
+
+    vexel::register_decoder(DecoderRegistration {
+        name: "vxgray",
+        sniff: |header| header.starts_with(b"VXGR"),
+        create: || Box::new(GrayDecoder),
+    });
+
+    let file = |width: u32, height: u32| {
+        let mut data = b"VXGR".to_vec();
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend((0..width * height).map(|i| (i * 50) as u8));
+        std::io::Cursor::new(data)
+    };
+
+    let mut decoder = Vexel::new(file(3, 2))?;
+    if decoder.get_format() != ImageFormat::Custom("vxgray") {
+        return Err(format!("expected the custom format, got {:?}", decoder.get_format()).into());
+    }
+
+    let header = decoder.probe()?;
+    if (header.width, header.height) != (3, 2) {
+        return Err(format!("unexpected probe result {}x{}", header.width, header.height).into());
+    }
+
+    let image = decoder.decode()?;
+    if image.pixels().as_bytes() != [0, 50, 100, 150, 200, 250] {
+        return Err("unexpected pixels".into());
+    }
+    if !matches!(decoder.get_info(), ImageInfo::Custom(_)) {
+        return Err("expected custom info".into());
+    }
+
+    let region = Vexel::new(file(3, 2))?.decode_region(1, 0, 2, 2)?;
+    if region.pixels().as_bytes() != [50, 100, 200, 250] {
+        return Err("unexpected region pixels".into());
+    }
+
+    let mut decoder = Vexel::new(file(3, 2))?;
+    decoder.set_limits(Limits {
+        max_image_width: Some(2),
+        ..Limits::default()
+    });
+    if !matches!(decoder.probe(), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the width limit to be enforced by probe".into());
+    }
+    if !matches!(decoder.decode(), Err(VexelError::LimitExceeded(_))) {
+        return Err("expected the width limit to be enforced".into());
+    }
+
+    if !matches!(Vexel::new(file(0, 2))?.decode(), Err(VexelError::Panic(_))) {
+        return Err("expected a panic in the decoder to be caught".into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_report() -> Result<(), Box<dyn std::error::Error>> {
+    let image = Vexel::open(get_in_path("jpeg/cat.jpg"))?.decode()?;
+    let report = image.report();
+    if !report.is_clean() || report.decoded_pixels() != report.total_pixels {
+        return Err(format!("expected a clean report, got {:?}", report.warnings).into());
+    }
+
+    // Cut the file in half, so the lower part of the image has to be filled in
+    let data = std::fs::read(get_in_path("jpeg/cat.jpg"))?;
+    let image = Vexel::new(std::io::Cursor::new(data[..data.len() / 2].to_vec()))?.decode()?;
+    let report = image.report();
+    if !report.has(WarningKind::TruncatedData) || report.synthesized_pixels == 0 || report.decoded_pixels() == 0 {
+        return Err(format!("expected a truncated JPEG, got {:?}", report).into());
+    }
+
+    let data = std::fs::read(get_in_path("bmp/Parrots.bmp"))?;
+    let cut = data.len() - 1000;
+    let image = Vexel::new(std::io::Cursor::new(data[..cut].to_vec()))?.decode()?;
+    let warning = image
+        .report()
+        .warnings
+        .iter()
+        .find(|w| w.kind == WarningKind::TruncatedData)
+        .ok_or("expected a truncated BMP")?;
+    if warning.offset != Some(cut as u64) || warning.synthesized_pixels == 0 {
+        return Err(format!("unexpected warning {:?}", warning).into());
+    }
+
+    let image = Vexel::open(get_in_path("tiff/memorial_luv32.tif"))?.decode()?;
+    let warning = image.report().warnings.first().ok_or("expected a warning")?;
+    if warning.kind != WarningKind::Unsupported || warning.frame != Some(0) || image.report().decoded_pixels() != 0 {
+        return Err(format!("unexpected warning {:?}", warning).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_log_sink() -> Result<(), Box<dyn std::error::Error>> {
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let sink_records = records.clone();
+
+    let data = std::fs::read(get_in_path("bmp/Parrots.bmp"))?;
+    let cut = data.len() - 1000;
+    let mut decoder = Vexel::new(std::io::Cursor::new(data[..cut].to_vec()))?;
+    decoder.set_decode_options(DecodeOptions {
+        log_sink: Some(Arc::new(move |record: &LogRecord| sink_records.lock().unwrap().push(record.clone()))),
+        ..Default::default()
+    });
+    decoder.decode()?;
+
+    let records = records.lock().unwrap();
+    let record = records
+        .iter()
+        .find(|record| record.offset == Some(cut as u64))
+        .ok_or_else(|| format!("expected a record at the truncation offset, got {:?}", records))?;
+    if record.level != LogLevel::Warning || record.format != Some(ImageFormat::Bmp) {
+        return Err(format!("unexpected record {:?}", record).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_leading_data() -> Result<(), Box<dyn std::error::Error>> {
+    let data = std::fs::read(get_in_path("png/rgb_8bit.png"))?;
+    let reference = Vexel::new(std::io::Cursor::new(data.clone()))?.decode()?;
+
+    let mut wrapped = b"Content-Type: image/png\r\n\r\n".to_vec();
+    let start = wrapped.len() as u64;
+    wrapped.extend_from_slice(&data);
+
+    let mut decoder = Vexel::new_scanning(std::io::Cursor::new(wrapped))?;
+    let image = decoder.decode()?;
+    if decoder.get_format() != ImageFormat::Png || image.as_rgba8() != reference.as_rgba8() {
+        return Err("expected the wrapped PNG to decode like the original".into());
+    }
+
+    let warning = image
+        .report()
+        .warnings
+        .iter()
+        .find(|w| w.kind == WarningKind::LeadingData)
+        .ok_or("expected the skipped data to be reported")?;
+    if warning.offset != Some(start) {
+        return Err(format!("unexpected warning {:?}", warning).into());
+    }
+
+    // Files without leading data decode as usual
+    let image = Vexel::new_scanning(std::io::Cursor::new(data))?.decode()?;
+    if !image.report().is_clean() {
+        return Err(format!("expected a clean report, got {:?}", image.report().warnings).into());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_strict_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let strict = DecodeOptions {
+        strictness: Strictness::Strict,
+        ..Default::default()
+    };
+
+    let mut decoder = Vexel::open(get_in_path("png/rgb_8bit.png"))?;
+    decoder.set_decode_options(strict.clone());
+    decoder.decode()?;
+
+    // Informational messages, such as an unknown APP0 segment or a short colour table, are
+    // reported but don't fail a strict decode
+    for path in ["jpeg/mjpeg.jpg", "bmp/terrain2.bmp"] {
+        let mut decoder = Vexel::open(get_in_path(path))?;
+        decoder.set_decode_options(strict.clone());
+        let image = decoder.decode()?;
+        if image.report().warnings.iter().any(|warning| warning.kind.is_recovery()) {
+            return Err(format!("unexpected recovery warning in {}", path).into());
+        }
+    }
+
+    // Break the CRC of the IHDR chunk, which starts at offset 8
+    let mut data = std::fs::read(get_in_path("png/rgb_8bit.png"))?;
+    data[29] ^= 0xFF;
+
+    let image = Vexel::new(std::io::Cursor::new(data.clone()))?.decode()?;
+    if !image.report().has(WarningKind::CorruptData) {
+        return Err("expected the lenient decode to report the CRC mismatch".into());
+    }
+
+    for probe in [false, true] {
+        let mut decoder = Vexel::new(std::io::Cursor::new(data.clone()))?;
+        decoder.set_decode_options(strict.clone());
+        let result = if probe { decoder.probe().map(|_| ()) } else { decoder.decode().map(|_| ()) };
+
+        match result {
+            Err(VexelError::Malformed { format: ImageFormat::Png, warning })
+                if warning.kind == WarningKind::CorruptData && warning.offset == Some(8) => {}
+            other => return Err(format!("expected a malformed PNG, got {:?}", other).into()),
+        }
+    }
+
+    // Frames go through the same checks, and collect their warnings
+    let mut decoder = Vexel::new(std::io::Cursor::new(data.clone()))?;
+    let mut frames = decoder.frames()?;
+    frames.by_ref().try_for_each(|frame| frame.map(drop))?;
+    if !frames.report().has(WarningKind::CorruptData) || frames.report().total_pixels == 0 {
+        return Err(format!("expected the frames to report the CRC mismatch, got {:?}", frames.report()).into());
+    }
+
+    let mut decoder = Vexel::new(std::io::Cursor::new(data))?;
+    decoder.set_decode_options(strict.clone());
+    match decoder.frames().and_then(|mut frames| frames.try_for_each(|frame| frame.map(drop))) {
+        Err(VexelError::Malformed { format: ImageFormat::Png, .. }) => {}
+        other => return Err(format!("expected malformed PNG frames, got {:?}", other).into()),
+    }
+
+    // A GIF cut off in the middle of its frames keeps the frames read so far, unless strict
+    let data = std::fs::read(get_in_path("gif/totoro.gif"))?;
+    let data = data[..data.len() / 2].to_vec();
+
+    let mut decoder = Vexel::new(std::io::Cursor::new(data.clone()))?;
+    let mut frames = decoder.frames()?;
+    frames.by_ref().try_for_each(|frame| frame.map(drop))?;
+    if !frames.report().has(WarningKind::TruncatedData) {
+        return Err(format!("expected the frames to report the truncation, got {:?}", frames.report()).into());
+    }
+
+    let mut decoder = Vexel::new(std::io::Cursor::new(data))?;
+    decoder.set_decode_options(strict.clone());
+    match decoder.frames().and_then(|mut frames| frames.try_for_each(|frame| frame.map(drop))) {
+        Err(VexelError::Malformed { format: ImageFormat::Gif, warning }) if warning.kind == WarningKind::TruncatedData => {}
+        other => return Err(format!("expected truncated GIF frames, got {:?}", other).into()),
+    }
+
+    let data = std::fs::read(get_in_path("jpeg/cat.jpg"))?;
+    let mut decoder = Vexel::new(std::io::Cursor::new(data[..data.len() / 2].to_vec()))?;
+    decoder.set_decode_options(strict);
+    match decoder.decode() {
+        Err(VexelError::Malformed { format: ImageFormat::Jpeg, warning }) if warning.kind == WarningKind::TruncatedData => {}
+        other => return Err(format!("expected a truncated JPEG, got {:?}", other.map(|_| ())).into()),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_error_variants() -> Result<(), Box<dyn std::error::Error>> {
+    // Signature and the start of the IHDR chunk
+    let data = std::fs::read(get_in_path("png/rgb_8bit.png"))?;
+    match Vexel::new(std::io::Cursor::new(data[..20].to_vec()))?.decode() {
+        Err(VexelError::Truncated { format: ImageFormat::Png, offset: Some(offset), .. }) if offset <= 20 => {}
+        other => return Err(format!("expected a truncated PNG, got {:?}", other.map(|_| ())).into()),
+    }
+
+    let data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y ten +X 10\n".to_vec();
+    match Vexel::new(std::io::Cursor::new(data))?.decode() {
+        Err(VexelError::Corrupt { format: ImageFormat::Hdr, .. }) => {}
+        other => return Err(format!("expected a corrupt HDR, got {:?}", other.map(|_| ())).into()),
+    }
+
+    let data = vec![0xFF, 0xD8, 0xFF, 0xD9];
+    match Vexel::new(std::io::Cursor::new(data))?.decode() {
+        Err(VexelError::MissingData { format: ImageFormat::Jpeg, .. }) => {}
+        other => return Err(format!("expected a JPEG without frames, got {:?}", other.map(|_| ())).into()),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_progress() -> Result<(), Box<dyn std::error::Error>> {
+    for path in ["jpeg/cat.jpg", "png/rgb_8bit.png", "gif/totoro.gif"] {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+
+        let mut decoder = Vexel::open(get_in_path(path))?;
+        decoder.set_decode_options(DecodeOptions {
+            progress: Some(Arc::new(move |progress| sink.lock().unwrap().push(progress))),
+            ..Default::default()
+        });
+        decoder.decode()?;
+
+        let events = events.lock().unwrap();
+        let finished = match events.last() {
+            Some(DecodeProgress::Rows { completed, total, .. }) => completed == total,
+            Some(DecodeProgress::Frames { completed, total }) => completed == total,
+            None => false,
+        };
+
+        if !finished {
+            return Err(format!("{} did not report completion: {:?}", path, events.last()).into());
+        }
+    }
+
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let mut decoder = Vexel::open(get_in_path("jpeg/cat.jpg"))?;
+    decoder.set_decode_options(DecodeOptions {
+        progress: Some(Arc::new(move |_| cancel.cancel())),
+        cancellation: Some(token),
+        ..Default::default()
+    });
+
+    match decoder.decode() {
+        Err(VexelError::Cancelled) => {}
+        other => return Err(format!("expected a cancelled decode, got {:?}", other.map(|_| ())).into()),
+    }
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let mut decoder = Vexel::open(get_in_path("gif/totoro.gif"))?;
+    decoder.set_decode_options(DecodeOptions {
+        cancellation: Some(token),
+        ..Default::default()
+    });
+
+    match decoder.decode() {
+        Err(VexelError::Cancelled) => {}
+        other => return Err(format!("expected a cancelled decode, got {:?}", other.map(|_| ())).into()),
+    }
+
+    Ok(())
+}
+
 #[test]
 #[ignore = "dev only"]
 // This test is used during development for convenience for any new image formats
@@ -1,3 +1,4 @@
+use crate::utils::error::TruncatedRead;
 use crate::utils::marker::Marker;
 use crate::utils::types::ByteOrder;
 use std::collections::HashSet;
@@ -9,6 +10,8 @@ pub struct BitReader<R: Read + Seek> {
     bits_in_buffer: u8,
     little_endian: bool,
     pub total_bits_consumed: u64,
+    /// Returns the input held by `reader`, for inputs that are in memory as a whole.
+    source_bytes: Option<fn(&R) -> &[u8]>,
 }
 
 impl<R: Read + Seek> BitReader<R> {
@@ -19,6 +22,7 @@ impl<R: Read + Seek> BitReader<R> {
             bits_in_buffer: 0,
             little_endian: false,
             total_bits_consumed: 0,
+            source_bytes: None,
         }
     }
 
@@ -30,9 +34,28 @@ impl<R: Read + Seek> BitReader<R> {
             bits_in_buffer: 0,
             little_endian: true,
             total_bits_consumed: 0,
+            source_bytes: None,
         }
     }
 
+    /// Sets how to get at the input held by the reader, for readers over data that is in memory
+    /// as a whole, such as a [`Cursor`](std::io::Cursor) over a slice.
+    pub fn set_source_bytes(&mut self, source_bytes: fn(&R) -> &[u8]) {
+        self.source_bytes = Some(source_bytes);
+    }
+
+    /// Returns the whole input if it is in memory, so decoders can borrow the data they need
+    /// instead of reading it into their own buffers. Positions in the slice are stream positions.
+    pub fn source_bytes(&self) -> Option<&[u8]> {
+        self.source_bytes.map(|source_bytes| source_bytes(&self.reader))
+    }
+
+    /// Consumes the BitReader and returns the underlying reader.
+    /// Any bits left in the buffer are discarded.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
     /// Sets the endianness of the BitReader.
     ///
     /// # Parameters
@@ -60,7 +83,7 @@ impl<R: Read + Seek> BitReader<R> {
     pub fn read_bit(&mut self) -> Result<bool, std::io::Error> {
         if self.bits_in_buffer == 0 {
             let mut byte = [0u8; 1];
-            self.reader.read_exact(&mut byte)?;
+            self.fill(&mut byte)?;
             self.buffer = u32::from(byte[0]);
             self.bits_in_buffer = 8;
         }
@@ -104,7 +127,7 @@ impl<R: Read + Seek> BitReader<R> {
     /// - `std::io::Error` if an I/O error occurs
     pub fn read_u8(&mut self) -> Result<u8, std::io::Error> {
         let mut byte = [0u8; 1];
-        self.reader.read_exact(&mut byte)?;
+        self.fill(&mut byte)?;
         Ok(byte[0])
     }
 
@@ -146,13 +169,47 @@ impl<R: Read + Seek> BitReader<R> {
         }
     }
 
+    /// Reads a single 64-bit value from the bitstream.
+    ///
+    /// # Returns
+    /// - The 64-bit value read
+    /// - `std::io::Error` if an I/O error occurs
+    pub fn read_u64(&mut self) -> Result<u64, std::io::Error> {
+        let first = self.read_u32()? as u64;
+        let second = self.read_u32()? as u64;
+        if self.little_endian {
+            Ok(first | (second << 32))
+        } else {
+            Ok((first << 32) | second)
+        }
+    }
+
     /// Reads the exact number of bytes required to fill the buffer.
     ///
     /// # Returns
     /// - `Ok(())` if the operation is successful
     /// - `std::io::Error` if an I/O error occurs
     pub fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
-        self.reader.read_exact(buf)
+        self.fill(buf)
+    }
+
+    /// Fills `buf` from the reader. Running out of data returns an
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) error that records where the data
+    /// ends, which is reported as [`VexelError::Truncated`](crate::VexelError::Truncated).
+    #[inline(always)]
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
+        self.reader.read_exact(buf).map_err(|e| {
+            if e.kind() != std::io::ErrorKind::UnexpectedEof {
+                return e;
+            }
+
+            let truncated = TruncatedRead {
+                offset: self.reader.stream_position().unwrap_or(0),
+                expected: buf.len() as u64,
+            };
+
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, truncated)
+        })
     }
 
     /// Clears the current bit buffer.
@@ -236,7 +293,7 @@ impl<R: Read + Seek> BitReader<R> {
     #[allow(dead_code)]
     pub fn peek_bytes(&mut self, n: usize) -> Result<Vec<u8>, std::io::Error> {
         let mut bytes = vec![0; n];
-        self.reader.read_exact(&mut bytes)?;
+        self.fill(&mut bytes)?;
         self.reader.seek(SeekFrom::Current(-(n as i64)))?;
 
         Ok(bytes)
@@ -283,7 +340,7 @@ impl<R: Read + Seek> BitReader<R> {
     /// - `std::io::Error` if an I/O error occurs
     pub fn read_bytes(&mut self, n: usize) -> Result<Vec<u8>, std::io::Error> {
         let mut bytes = vec![0; n];
-        self.reader.read_exact(&mut bytes)?;
+        self.fill(&mut bytes)?;
         self.clear_buffer();
 
         Ok(bytes)
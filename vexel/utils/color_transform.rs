@@ -0,0 +1,715 @@
+use crate::log_warn;
+use crate::utils::icc::{CurveData, ICCProfile, ICCTagData, LutData};
+use crate::utils::image::PixelData;
+use std::sync::{Arc, Mutex};
+
+/// Colour space that decoded pixels are converted to when an ICC profile is embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorTarget {
+    /// Pixels are left in the colour space of the embedded profile.
+    #[default]
+    Original,
+    /// sRGB, keeping the pixel format of the decoded image.
+    Srgb,
+    /// Linear-light values with sRGB primaries. Integer pixel data is converted to 32-bit float,
+    /// 64-bit float data stays 64-bit.
+    LinearSrgb,
+}
+
+// XYZ relative to the D50 PCS white point to linear sRGB, with Bradford adaptation to D65
+const XYZ_D50_TO_LINEAR_SRGB: [[f64; 3]; 3] = [
+    [3.1338561, -1.6168667, -0.4906146],
+    [-0.9787684, 1.9161415, 0.0334540],
+    [0.0719453, -0.2289914, 1.4052427],
+];
+
+/// Number of entries in the lookup table used to encode linear values to 8-bit sRGB.
+const ENCODE_LUT_SIZE: usize = 4096;
+
+/// Grid points per channel of the precomputed CMYK to sRGB table.
+const CMYK_GRID_SIZE: usize = 17;
+
+/// Number of CMYK tables kept by [`CmykTransform::cached`].
+const CMYK_CACHE_SIZE: usize = 4;
+
+/// Recently built CMYK tables, keyed by the raw ICC profile. Empty for the built-in model.
+static CMYK_TRANSFORMS: Mutex<Vec<(Vec<u8>, Arc<CmykTransform>)>> = Mutex::new(Vec::new());
+
+/// Maximum number of input channels of an ICC lookup table.
+const MAX_LUT_CHANNELS: usize = 15;
+
+const D50_WHITE: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// A tone reproduction curve from a `curv` or `para` tag.
+#[derive(Debug, Clone)]
+pub(crate) enum ToneCurve {
+    Gamma(f64),
+    Table(Vec<f64>),
+    Parametric { function_type: u16, params: [f64; 7] },
+}
+
+impl ToneCurve {
+    pub(crate) fn from_tag(tag: &ICCTagData) -> Option<ToneCurve> {
+        match tag {
+            ICCTagData::Curve(CurveData::Identity) => Some(ToneCurve::Gamma(1.0)),
+            ICCTagData::Curve(CurveData::Gamma(gamma)) => Some(ToneCurve::Gamma(*gamma)),
+            ICCTagData::Curve(CurveData::Table(table)) if !table.is_empty() => {
+                Some(ToneCurve::Table(table.iter().map(|&v| v as f64 / 65535.0).collect()))
+            }
+            ICCTagData::ParametricCurve(curve) => {
+                let expected = match curve.function_type {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return None,
+                };
+
+                if curve.params.len() < expected {
+                    return None;
+                }
+
+                let mut params = [0.0; 7];
+                params[..expected].copy_from_slice(&curve.params[..expected]);
+
+                Some(ToneCurve::Parametric {
+                    function_type: curve.function_type,
+                    params,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluates the curve for a device value. Negative values are clamped to 0,
+    /// gamma and parametric curves extend past 1.0 so float images keep their
+    /// highlights, sampled tables are clamped to their last entry.
+    pub(crate) fn eval(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+
+        match self {
+            ToneCurve::Gamma(gamma) => x.powf(*gamma),
+            ToneCurve::Table(table) => {
+                if table.len() == 1 {
+                    return table[0];
+                }
+
+                let pos = x.min(1.0) * (table.len() - 1) as f64;
+                let index = (pos as usize).min(table.len() - 2);
+                let frac = pos - index as f64;
+
+                table[index] + (table[index + 1] - table[index]) * frac
+            }
+            ToneCurve::Parametric { function_type, params } => {
+                let [g, a, b, c, d, e, f] = *params;
+
+                // Formulas from the ICC specification, section 10.18
+                match function_type {
+                    0 => x.powf(g),
+                    1 => {
+                        if x >= -b / a {
+                            (a * x + b).max(0.0).powf(g)
+                        } else {
+                            0.0
+                        }
+                    }
+                    2 => {
+                        if x >= -b / a {
+                            (a * x + b).max(0.0).powf(g) + c
+                        } else {
+                            c
+                        }
+                    }
+                    3 => {
+                        if x >= d {
+                            (a * x + b).max(0.0).powf(g)
+                        } else {
+                            c * x
+                        }
+                    }
+                    _ => {
+                        if x >= d {
+                            (a * x + b).max(0.0).powf(g) + e
+                        } else {
+                            c * x + f
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Transform built from a matrix/TRC profile: per-channel curves to linear light,
+/// followed by a matrix to linear sRGB. Gray profiles only have a single curve.
+///
+/// The conversion uses the relative colorimetric intent. Colorants in v2 and v4 profiles
+/// are already adapted to the D50 PCS, so `chad` and `wtpt` are not needed here.
+#[derive(Debug, Clone)]
+pub(crate) struct MatrixTrcTransform {
+    curves: Vec<ToneCurve>,
+    matrix: [[f64; 3]; 3],
+}
+
+impl MatrixTrcTransform {
+    /// Builds a transform from an RGB or gray display profile.
+    /// Returns `None` if the profile does not have the matrix/TRC tags.
+    pub(crate) fn new(profile: &ICCProfile) -> Option<MatrixTrcTransform> {
+        let curve = |sig: &str| profile.tags.get(sig).and_then(ToneCurve::from_tag);
+
+        match profile.header.color_space.trim() {
+            "RGB" => {
+                let colorant = |sig: &str| match profile.tags.get(sig) {
+                    Some(ICCTagData::XYZ(values)) => values.first().map(|v| [v.x, v.y, v.z]),
+                    _ => None,
+                };
+
+                let curves = vec![curve("rTRC")?, curve("gTRC")?, curve("bTRC")?];
+                let columns = [colorant("rXYZ")?, colorant("gXYZ")?, colorant("bXYZ")?];
+
+                let mut matrix = [[0.0; 3]; 3];
+                for (row, out) in matrix.iter_mut().enumerate() {
+                    for (col, value) in out.iter_mut().enumerate() {
+                        *value = (0..3).map(|k| XYZ_D50_TO_LINEAR_SRGB[row][k] * columns[col][k]).sum();
+                    }
+                }
+
+                Some(MatrixTrcTransform { curves, matrix })
+            }
+            "Grayscale" | "GRAY" => Some(MatrixTrcTransform {
+                curves: vec![curve("kTRC")?],
+                matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            }),
+            _ => None,
+        }
+    }
+
+    /// Transform for pixels that are already in sRGB, used to linearize them.
+    pub(crate) fn srgb() -> MatrixTrcTransform {
+        let curve = ToneCurve::Parametric {
+            function_type: 3,
+            params: [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045, 0.0, 0.0],
+        };
+
+        MatrixTrcTransform {
+            curves: vec![curve; 3],
+            matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    pub(crate) fn is_gray(&self) -> bool {
+        self.curves.len() == 1
+    }
+
+    /// Converts the pixels to `target`. Alpha is copied unchanged.
+    ///
+    /// Pixel data that does not match the profile (gray pixels with an RGB profile and the
+    /// other way around) and 1-bit data are returned as-is.
+    pub(crate) fn apply(&self, pixels: PixelData, target: ColorTarget) -> PixelData {
+        if target == ColorTarget::Original {
+            return pixels;
+        }
+
+        let linear = target == ColorTarget::LinearSrgb;
+
+        match (pixels, self.is_gray()) {
+            (PixelData::RGB8(p), false) if linear => PixelData::RGB32F(self.convert(&p, 3)),
+            (PixelData::RGB8(p), false) => PixelData::RGB8(self.convert(&p, 3)),
+            (PixelData::RGBA8(p), false) if linear => PixelData::RGBA32F(self.convert(&p, 4)),
+            (PixelData::RGBA8(p), false) => PixelData::RGBA8(self.convert(&p, 4)),
+            (PixelData::RGB16(p), false) if linear => PixelData::RGB32F(self.convert(&p, 3)),
+            (PixelData::RGB16(p), false) => PixelData::RGB16(self.convert(&p, 3)),
+            (PixelData::RGBA16(p), false) if linear => PixelData::RGBA32F(self.convert(&p, 4)),
+            (PixelData::RGBA16(p), false) => PixelData::RGBA16(self.convert(&p, 4)),
+            (PixelData::RGB32F(p), false) => PixelData::RGB32F(self.convert_float(&p, 3, linear)),
+            (PixelData::RGBA32F(p), false) => PixelData::RGBA32F(self.convert_float(&p, 4, linear)),
+            (PixelData::RGB64F(p), false) => PixelData::RGB64F(self.convert_float(&p, 3, linear)),
+            (PixelData::RGBA64F(p), false) => PixelData::RGBA64F(self.convert_float(&p, 4, linear)),
+            (PixelData::L8(p), true) if linear => PixelData::L32F(self.convert(&p, 1)),
+            (PixelData::L8(p), true) => PixelData::L8(self.convert(&p, 1)),
+            (PixelData::LA8(p), true) if linear => PixelData::LA32F(self.convert(&p, 2)),
+            (PixelData::LA8(p), true) => PixelData::LA8(self.convert(&p, 2)),
+            (PixelData::L16(p), true) if linear => PixelData::L32F(self.convert(&p, 1)),
+            (PixelData::L16(p), true) => PixelData::L16(self.convert(&p, 1)),
+            (PixelData::LA16(p), true) if linear => PixelData::LA32F(self.convert(&p, 2)),
+            (PixelData::LA16(p), true) => PixelData::LA16(self.convert(&p, 2)),
+            (PixelData::L32F(p), true) => PixelData::L32F(self.convert_float(&p, 1, linear)),
+            (PixelData::LA32F(p), true) => PixelData::LA32F(self.convert_float(&p, 2, linear)),
+            (PixelData::L64F(p), true) => PixelData::L64F(self.convert_float(&p, 1, linear)),
+            (PixelData::LA64F(p), true) => PixelData::LA64F(self.convert_float(&p, 2, linear)),
+            (pixels, _) => {
+                log_warn!(
+                    "Cannot apply {} ICC profile to {:?} pixels",
+                    if self.is_gray() { "gray" } else { "RGB" },
+                    pixels.pixel_format()
+                );
+                pixels
+            }
+        }
+    }
+
+    /// Linearizes device values of one pixel and maps them to linear sRGB.
+    /// Only the first value of `color` is used for gray profiles.
+    fn to_linear(&self, color: [f64; 3], tables: Option<&[Vec<f64>]>, raw: [usize; 3]) -> [f64; 3] {
+        let mut lin = [0.0; 3];
+        for (i, curve) in self.curves.iter().enumerate() {
+            lin[i] = match tables {
+                Some(tables) => tables[i][raw[i]],
+                None => curve.eval(color[i]),
+            };
+        }
+
+        if self.is_gray() {
+            return [lin[0]; 3];
+        }
+
+        let m = &self.matrix;
+        [
+            m[0][0] * lin[0] + m[0][1] * lin[1] + m[0][2] * lin[2],
+            m[1][0] * lin[0] + m[1][1] * lin[1] + m[1][2] * lin[2],
+            m[2][0] * lin[0] + m[2][1] * lin[1] + m[2][2] * lin[2],
+        ]
+    }
+
+    /// Converts integer samples, either encoding the result back to sRGB in the same sample
+    /// type or writing linear values, depending on the output type `U`.
+    fn convert<T: Sample, U: Sample>(&self, src: &[T], channels: usize) -> Vec<U> {
+        let color_channels = self.curves.len();
+
+        // 8-bit input only has 256 possible values per channel
+        let tables: Option<Vec<Vec<f64>>> = T::IS_U8.then(|| {
+            self.curves
+                .iter()
+                .map(|curve| (0..256).map(|v| curve.eval(v as f64 / 255.0)).collect())
+                .collect()
+        });
+
+        let encode_lut: Option<Vec<f64>> = (U::IS_U8).then(|| {
+            (0..ENCODE_LUT_SIZE)
+                .map(|i| srgb_encode(i as f64 / (ENCODE_LUT_SIZE - 1) as f64))
+                .collect()
+        });
+
+        let encode = |value: f64| -> U {
+            if U::IS_FLOAT {
+                return U::from_unit(value);
+            }
+
+            match &encode_lut {
+                Some(lut) => {
+                    let index = (value.clamp(0.0, 1.0) * (ENCODE_LUT_SIZE - 1) as f64).round() as usize;
+                    U::from_unit(lut[index])
+                }
+                None => U::from_unit(srgb_encode(value)),
+            }
+        };
+
+        let mut out = Vec::with_capacity(src.len());
+
+        for pixel in src.chunks_exact(channels) {
+            let mut color = [0.0; 3];
+            let mut raw = [0usize; 3];
+            for i in 0..color_channels {
+                color[i] = pixel[i].to_unit();
+                raw[i] = pixel[i].index();
+            }
+
+            let rgb = self.to_linear(color, tables.as_deref(), raw);
+
+            for &value in &rgb[..color_channels] {
+                out.push(encode(value));
+            }
+
+            for &alpha in &pixel[color_channels..] {
+                out.push(U::from_unit(alpha.to_unit()));
+            }
+        }
+
+        out
+    }
+
+    /// Converts float samples in place of the same type, either to linear or to encoded sRGB.
+    fn convert_float<T: Sample>(&self, src: &[T], channels: usize, linear: bool) -> Vec<T> {
+        let color_channels = self.curves.len();
+        let mut out = Vec::with_capacity(src.len());
+
+        for pixel in src.chunks_exact(channels) {
+            let mut color = [0.0; 3];
+            for i in 0..color_channels {
+                color[i] = pixel[i].to_unit();
+            }
+
+            let rgb = self.to_linear(color, None, [0; 3]);
+
+            for &value in &rgb[..color_channels] {
+                out.push(T::from_unit(if linear { value } else { srgb_encode(value) }));
+            }
+
+            out.extend_from_slice(&pixel[color_channels..]);
+        }
+
+        out
+    }
+}
+
+/// Colour lookup table with values normalized to `[0, 1]`.
+#[derive(Debug, Clone)]
+struct Clut {
+    grid_points: Vec<usize>,
+    values: Vec<f64>,
+}
+
+/// Evaluator for a device to PCS lookup table from an `mft1`, `mft2` or `mAB ` tag.
+///
+/// The stages are applied in the order the ICC specification defines for `mAB `:
+/// A curves, CLUT, M curves, matrix and B curves. `mft1`/`mft2` tables only use the
+/// A curves, CLUT and B curves, their matrix is only defined for XYZ input.
+#[derive(Debug, Clone)]
+struct LutPipeline {
+    a_curves: Vec<ToneCurve>,
+    clut: Option<Clut>,
+    m_curves: Vec<ToneCurve>,
+    matrix: Option<[f64; 12]>,
+    b_curves: Vec<ToneCurve>,
+    /// `mft2` tables use the legacy 16-bit Lab encoding.
+    legacy_lab: bool,
+}
+
+impl LutPipeline {
+    fn new(lut: &LutData) -> Option<LutPipeline> {
+        let curves = |tags: &[ICCTagData]| tags.iter().map(ToneCurve::from_tag).collect::<Option<Vec<_>>>();
+
+        let inputs = lut.input_channels as usize;
+        if inputs == 0 || inputs > MAX_LUT_CHANNELS || lut.output_channels != 3 || lut.lut_type == "mBA " {
+            return None;
+        }
+
+        let clut = lut.clut.as_ref().map(|clut| Clut {
+            grid_points: clut.grid_points.iter().map(|&g| g as usize).collect(),
+            values: clut.values.iter().map(|&v| v as f64 / 65535.0).collect(),
+        });
+
+        // Without a CLUT the channel count cannot change
+        if clut.is_none() && inputs != 3 {
+            return None;
+        }
+
+        let matrix = match &lut.matrix {
+            Some(matrix) if lut.lut_type == "mAB " => Some(matrix.as_slice().try_into().ok()?),
+            _ => None,
+        };
+
+        Some(LutPipeline {
+            a_curves: curves(&lut.a_curves)?,
+            clut,
+            m_curves: curves(&lut.m_curves)?,
+            matrix,
+            b_curves: curves(&lut.b_curves)?,
+            legacy_lab: lut.lut_type == "mft2",
+        })
+    }
+
+    /// Evaluates the table for device values in `[0, 1]`, returning encoded PCS values.
+    fn eval(&self, input: &[f64]) -> [f64; 3] {
+        let apply = |curves: &[ToneCurve], values: &mut [f64]| {
+            for (value, curve) in values.iter_mut().zip(curves) {
+                *value = curve.eval(*value);
+            }
+        };
+
+        let mut device = [0.0; MAX_LUT_CHANNELS];
+        let device = &mut device[..input.len()];
+        device.copy_from_slice(input);
+        apply(&self.a_curves, device);
+
+        let mut pcs = [0.0; 3];
+        match &self.clut {
+            Some(clut) => interpolate(&clut.values, &clut.grid_points, device, &mut pcs),
+            None => pcs.copy_from_slice(&device[..3]),
+        }
+
+        apply(&self.m_curves, &mut pcs);
+
+        if let Some(m) = &self.matrix {
+            let [x, y, z] = pcs;
+            pcs = [
+                m[0] * x + m[1] * y + m[2] * z + m[9],
+                m[3] * x + m[4] * y + m[5] * z + m[10],
+                m[6] * x + m[7] * y + m[8] * z + m[11],
+            ];
+        }
+
+        apply(&self.b_curves, &mut pcs);
+
+        pcs
+    }
+}
+
+/// Simplex interpolation in a lookup table where the first input varies slowest.
+/// Inputs are clamped to `[0, 1]`, one value per output channel is written to `out`.
+fn interpolate<T: Copy + Into<f64>>(values: &[T], grid_points: &[usize], input: &[f64], out: &mut [f64]) {
+    let outputs = out.len();
+    let mut base = 0;
+    let mut stride = outputs;
+    let mut steps = [(0.0, 0); MAX_LUT_CHANNELS];
+
+    for (i, (&x, &points)) in input.iter().zip(grid_points).enumerate().rev() {
+        if points > 1 {
+            let pos = x.clamp(0.0, 1.0) * (points - 1) as f64;
+            let index = (pos as usize).min(points - 2);
+            base += index * stride;
+            steps[i] = (pos - index as f64, stride);
+        }
+        stride *= points;
+    }
+
+    // Walk from the base vertex towards the opposite corner, largest fraction first
+    let steps = &mut steps[..input.len()];
+    steps.sort_unstable_by(|a, b| b.0.total_cmp(&a.0));
+
+    out.fill(0.0);
+    let mut vertex = base;
+    let mut previous = 1.0;
+
+    for &(frac, step) in steps.iter() {
+        let weight = previous - frac;
+        for (o, &v) in out.iter_mut().zip(&values[vertex..vertex + outputs]) {
+            *o += weight * v.into();
+        }
+        vertex += step;
+        previous = frac;
+    }
+
+    for (o, &v) in out.iter_mut().zip(&values[vertex..vertex + outputs]) {
+        *o += previous * v.into();
+    }
+}
+
+/// Decodes PCS values from a lookup table and converts them to linear sRGB.
+fn pcs_to_linear_srgb(pcs: [f64; 3], lab: bool, legacy_lab: bool) -> [f64; 3] {
+    let xyz = if lab {
+        let (l, a, b) = if legacy_lab {
+            (
+                pcs[0] * 65535.0 / 65280.0 * 100.0,
+                pcs[1] * 65535.0 / 256.0 - 128.0,
+                pcs[2] * 65535.0 / 256.0 - 128.0,
+            )
+        } else {
+            (pcs[0] * 100.0, pcs[1] * 255.0 - 128.0, pcs[2] * 255.0 - 128.0)
+        };
+
+        let f_inv = |t: f64| {
+            if t > 6.0 / 29.0 {
+                t * t * t
+            } else {
+                3.0 * (6.0 / 29.0) * (6.0 / 29.0) * (t - 4.0 / 29.0)
+            }
+        };
+
+        let fy = (l + 16.0) / 116.0;
+        [
+            D50_WHITE[0] * f_inv(fy + a / 500.0),
+            D50_WHITE[1] * f_inv(fy),
+            D50_WHITE[2] * f_inv(fy - b / 200.0),
+        ]
+    } else {
+        pcs.map(|v| v * 65535.0 / 32768.0)
+    };
+
+    XYZ_D50_TO_LINEAR_SRGB.map(|row| row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2])
+}
+
+/// Built-in press model used when there is no CMYK profile. Each ink filters the light
+/// reflected by the paper, after a SWOP-like dot gain of 20% in the midtones.
+fn default_cmyk_to_linear(cmyk: [f64; 4]) -> [f64; 3] {
+    // Linear sRGB reflectance of solid process inks: cyan (0, 174, 239), magenta (236, 0, 140),
+    // yellow (255, 242, 0) and black (35, 31, 32)
+    const INKS: [[f64; 3]; 4] = [
+        [0.0, 0.423, 0.864],
+        [0.839, 0.0, 0.262],
+        [1.0, 0.888, 0.0],
+        [0.017, 0.014, 0.014],
+    ];
+    const DOT_GAIN: f64 = 0.8;
+
+    let mut rgb = [1.0; 3];
+    for (amount, ink) in cmyk.iter().zip(INKS) {
+        let coverage = amount + DOT_GAIN * amount * (1.0 - amount);
+        for (value, reflectance) in rgb.iter_mut().zip(ink) {
+            *value *= 1.0 - coverage * (1.0 - reflectance);
+        }
+    }
+
+    rgb
+}
+
+/// CMYK to sRGB conversion through a precomputed table, built from the `A2B` tag of a
+/// CMYK output profile or from a built-in SWOP-like model when there is no usable profile.
+///
+/// Relative colorimetric `A2B1` is preferred, so paper white maps to sRGB white.
+#[derive(Debug, Clone)]
+pub(crate) struct CmykTransform {
+    grid: Vec<f32>,
+}
+
+impl CmykTransform {
+    /// Returns the transform for the raw ICC profile `data`, or for the built-in model when
+    /// `data` is empty. Building the table evaluates the profile at every grid point, so it is
+    /// reused for every strip, page and image with the same profile.
+    pub(crate) fn cached(data: &[u8]) -> Arc<CmykTransform> {
+        if let Ok(cache) = CMYK_TRANSFORMS.lock() {
+            if let Some((_, transform)) = cache.iter().find(|(profile, _)| profile == data) {
+                return transform.clone();
+            }
+        }
+
+        let profile = if data.is_empty() { None } else { ICCProfile::new(data).ok() };
+        let transform = Arc::new(CmykTransform::new(profile.as_ref()));
+
+        if let Ok(mut cache) = CMYK_TRANSFORMS.lock() {
+            if cache.len() >= CMYK_CACHE_SIZE {
+                cache.remove(0);
+            }
+            cache.push((data.to_vec(), transform.clone()));
+        }
+
+        transform
+    }
+
+    fn new(profile: Option<&ICCProfile>) -> CmykTransform {
+        let pipeline = profile.and_then(|profile| {
+            let pipeline = Self::profile_pipeline(profile);
+            if pipeline.is_none() {
+                log_warn!("Unsupported CMYK ICC profile, using the default CMYK conversion");
+            }
+            pipeline
+        });
+
+        let n = CMYK_GRID_SIZE;
+        let mut grid = Vec::with_capacity(n.pow(4) * 3);
+
+        for index in 0..n.pow(4) {
+            let cmyk = [index / (n * n * n), index / (n * n) % n, index / n % n, index % n]
+                .map(|i| i as f64 / (n - 1) as f64);
+
+            let linear = match &pipeline {
+                Some((pipeline, lab)) => pcs_to_linear_srgb(pipeline.eval(&cmyk), *lab, pipeline.legacy_lab),
+                None => default_cmyk_to_linear(cmyk),
+            };
+
+            grid.extend(linear.map(|v| srgb_encode(v.clamp(0.0, 1.0)) as f32));
+        }
+
+        CmykTransform { grid }
+    }
+
+    /// Returns the device to PCS table of a CMYK profile and whether its PCS is Lab.
+    fn profile_pipeline(profile: &ICCProfile) -> Option<(LutPipeline, bool)> {
+        if profile.header.color_space.trim() != "CMYK" {
+            return None;
+        }
+
+        let lab = match profile.header.pcs.trim() {
+            "Lab" | "CIE L*a*b*" => true,
+            "XYZ" => false,
+            _ => return None,
+        };
+
+        let pipeline = ["A2B1", "A2B0", "A2B2"].iter().find_map(|sig| match profile.tags.get(*sig) {
+            Some(ICCTagData::Lut(lut)) if lut.input_channels == 4 => LutPipeline::new(lut),
+            _ => None,
+        })?;
+
+        Some((pipeline, lab))
+    }
+
+    /// Converts ink amounts in `[0, 1]`, where 0 is no ink, to encoded sRGB in `[0, 1]`.
+    pub(crate) fn to_rgb(&self, cmyk: [f64; 4]) -> [f64; 3] {
+        let mut rgb = [0.0; 3];
+        interpolate(&self.grid, &[CMYK_GRID_SIZE; 4], &cmyk, &mut rgb);
+        rgb
+    }
+
+    /// Converts 8-bit ink amounts, where 0 is no ink, to 8-bit sRGB.
+    pub(crate) fn to_rgb8(&self, cmyk: [u8; 4]) -> [u8; 3] {
+        self.to_rgb(cmyk.map(|v| v as f64 / 255.0)).map(|v| (v * 255.0).round() as u8)
+    }
+}
+
+/// Applies the sRGB transfer function to a linear value. Negative values are mirrored.
+fn srgb_encode(value: f64) -> f64 {
+    let magnitude = value.abs();
+    let encoded = if magnitude <= 0.0031308 {
+        magnitude * 12.92
+    } else {
+        1.055 * magnitude.powf(1.0 / 2.4) - 0.055
+    };
+
+    encoded.copysign(value)
+}
+
+trait Sample: Copy {
+    /// Whether every value fits a 256-entry lookup table.
+    const IS_U8: bool = false;
+    /// Whether values are stored as-is, without scaling or clamping.
+    const IS_FLOAT: bool = false;
+
+    fn to_unit(self) -> f64;
+    fn from_unit(value: f64) -> Self;
+
+    /// Index into a 256-entry lookup table, only meaningful when `IS_U8` is set.
+    fn index(self) -> usize {
+        0
+    }
+}
+
+impl Sample for u8 {
+    const IS_U8: bool = true;
+
+    fn to_unit(self) -> f64 {
+        self as f64 / 255.0
+    }
+
+    fn from_unit(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Sample for u16 {
+    fn to_unit(self) -> f64 {
+        self as f64 / 65535.0
+    }
+
+    fn from_unit(value: f64) -> Self {
+        (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+    }
+}
+
+impl Sample for f32 {
+    const IS_FLOAT: bool = true;
+
+    fn to_unit(self) -> f64 {
+        self as f64
+    }
+
+    fn from_unit(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl Sample for f64 {
+    const IS_FLOAT: bool = true;
+
+    fn to_unit(self) -> f64 {
+        self
+    }
+
+    fn from_unit(value: f64) -> Self {
+        value
+    }
+}
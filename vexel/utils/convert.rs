@@ -0,0 +1,183 @@
+use crate::utils::channel_simd;
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::{PixelData, PixelFormat};
+
+/// Checks that a buffer of `len` bytes holds `width` x `height` pixels of `format` with rows
+/// `stride` bytes apart. The last row doesn't need the padding after it.
+pub(crate) fn check_buffer(len: usize, stride: usize, width: u32, height: u32, format: &PixelFormat) -> VexelResult<()> {
+    let row_bytes = (width as usize)
+        .checked_mul(format.bytes_per_pixel())
+        .ok_or_else(|| VexelError::LimitExceeded(format!("A row of {} {:?} pixels does not fit in memory", width, format)))?;
+
+    if stride < row_bytes {
+        return Err(VexelError::Custom(format!(
+            "Stride of {} bytes is smaller than a row of {} {:?} pixels ({} bytes)",
+            stride, width, format, row_bytes
+        )));
+    }
+
+    let required = stride
+        .checked_mul((height as usize).saturating_sub(1))
+        .and_then(|padded| padded.checked_add(row_bytes))
+        .ok_or_else(|| VexelError::LimitExceeded(format!("A {}x{} buffer does not fit in memory", width, height)))?;
+
+    if len < required {
+        return Err(VexelError::Custom(format!(
+            "Buffer of {} bytes is too small for {}x{} {:?} pixels with a stride of {}, {} bytes are needed",
+            len, width, height, format, stride, required
+        )));
+    }
+
+    Ok(())
+}
+
+/// Writes `width` x `height` pixels into `dst` as `format`, converting each row straight into
+/// place. Rows start `stride` bytes apart and the padding between them is left untouched.
+///
+/// The buffer must have been validated with [`check_buffer`]. Multi-byte samples are written in
+/// native byte order, like [`PixelData::as_bytes`]. Colour is converted to gray with the BT.601
+/// luma weights, and alpha is dropped or added as fully opaque.
+pub(crate) fn write_pixels(pixels: &PixelData, width: u32, height: u32, dst: &mut [u8], stride: usize, format: &PixelFormat) {
+    let width = width as usize;
+    let height = height as usize;
+    let source_channels = pixels.pixel_format().channels();
+    let source_row = width * source_channels;
+    let row_bytes = width * format.bytes_per_pixel();
+    let rows = dst.chunks_mut(stride).take(height).map(|row| &mut row[..row_bytes]);
+
+    let is_8bit = matches!(format, PixelFormat::L8 | PixelFormat::LA8 | PixelFormat::RGB8 | PixelFormat::RGBA8);
+    let same_channels = format.channels() == source_channels;
+
+    match (pixels, format) {
+        _ if pixels.pixel_format() == *format => {
+            let source = pixels.as_bytes();
+            for (src, dst) in source.chunks_exact(row_bytes).zip(rows) {
+                dst.copy_from_slice(src);
+            }
+        }
+        (PixelData::RGB8(p), PixelFormat::RGBA8) => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                channel_simd::rgb_to_rgba(src, dst);
+            }
+        }
+        (PixelData::RGBA8(p), PixelFormat::RGB8) => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                channel_simd::rgba_to_rgb(src, dst);
+            }
+        }
+        (PixelData::RGB16(p) | PixelData::RGBA16(p) | PixelData::L16(p) | PixelData::LA16(p), _) if is_8bit && same_channels => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                channel_simd::scale_u16_to_u8(src, dst);
+            }
+        }
+        (PixelData::RGB32F(p) | PixelData::RGBA32F(p) | PixelData::L32F(p) | PixelData::LA32F(p), _) if is_8bit && same_channels => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                channel_simd::scale_f32_to_u8(src, dst);
+            }
+        }
+        (PixelData::L8(p), PixelFormat::RGB8) => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                for (&g, out) in src.iter().zip(dst.chunks_exact_mut(3)) {
+                    out.copy_from_slice(&[g, g, g]);
+                }
+            }
+        }
+        (PixelData::L8(p), PixelFormat::RGBA8) => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                for (&g, out) in src.iter().zip(dst.chunks_exact_mut(4)) {
+                    out.copy_from_slice(&[g, g, g, 255]);
+                }
+            }
+        }
+        (PixelData::LA8(p), PixelFormat::RGBA8) => {
+            for (src, dst) in p.chunks_exact(source_row).zip(rows) {
+                for (c, out) in src.chunks_exact(2).zip(dst.chunks_exact_mut(4)) {
+                    out.copy_from_slice(&[c[0], c[0], c[0], c[1]]);
+                }
+            }
+        }
+        _ => {
+            // Anything else goes through one row of normalized RGBA
+            let mut scratch = vec![[0.0f32; 4]; width];
+            for (y, dst) in rows.enumerate() {
+                let range = y * source_row..(y + 1) * source_row;
+                load_row(pixels, range, &mut scratch);
+                store_row(&scratch, format, dst);
+            }
+        }
+    }
+}
+
+/// Loads the samples in `range` of `pixels` as RGBA in `[0.0, 1.0]`. Float samples are not
+/// clamped.
+fn load_row(pixels: &PixelData, range: std::ops::Range<usize>, out: &mut [[f32; 4]]) {
+    match pixels {
+        PixelData::RGB8(p) => load(&p[range], 3, |v| v as f32 / 255.0, out),
+        PixelData::RGBA8(p) => load(&p[range], 4, |v| v as f32 / 255.0, out),
+        PixelData::RGB16(p) => load(&p[range], 3, |v| v as f32 / 65535.0, out),
+        PixelData::RGBA16(p) => load(&p[range], 4, |v| v as f32 / 65535.0, out),
+        PixelData::RGB32F(p) => load(&p[range], 3, |v| v, out),
+        PixelData::RGBA32F(p) => load(&p[range], 4, |v| v, out),
+        PixelData::RGB64F(p) => load(&p[range], 3, |v| v as f32, out),
+        PixelData::RGBA64F(p) => load(&p[range], 4, |v| v as f32, out),
+        PixelData::L1(p) => load(&p[range], 1, |v| v as f32, out),
+        PixelData::L8(p) => load(&p[range], 1, |v| v as f32 / 255.0, out),
+        PixelData::L16(p) => load(&p[range], 1, |v| v as f32 / 65535.0, out),
+        PixelData::LA8(p) => load(&p[range], 2, |v| v as f32 / 255.0, out),
+        PixelData::LA16(p) => load(&p[range], 2, |v| v as f32 / 65535.0, out),
+        PixelData::L32F(p) => load(&p[range], 1, |v| v, out),
+        PixelData::LA32F(p) => load(&p[range], 2, |v| v, out),
+        PixelData::L64F(p) => load(&p[range], 1, |v| v as f32, out),
+        PixelData::LA64F(p) => load(&p[range], 2, |v| v as f32, out),
+    }
+}
+
+fn load<T: Copy>(src: &[T], channels: usize, unit: impl Fn(T) -> f32, out: &mut [[f32; 4]]) {
+    for (pixel, out) in src.chunks_exact(channels).zip(out.iter_mut()) {
+        *out = match channels {
+            1 => {
+                let g = unit(pixel[0]);
+                [g, g, g, 1.0]
+            }
+            2 => {
+                let g = unit(pixel[0]);
+                [g, g, g, unit(pixel[1])]
+            }
+            3 => [unit(pixel[0]), unit(pixel[1]), unit(pixel[2]), 1.0],
+            _ => [unit(pixel[0]), unit(pixel[1]), unit(pixel[2]), unit(pixel[3])],
+        };
+    }
+}
+
+/// Stores a row of normalized RGBA into `dst` as `format`.
+fn store_row(src: &[[f32; 4]], format: &PixelFormat, dst: &mut [u8]) {
+    let channels = format.channels();
+
+    match format {
+        PixelFormat::L1 => store(src, channels, dst, |v| [(v >= 0.5) as u8]),
+        PixelFormat::L8 | PixelFormat::LA8 | PixelFormat::RGB8 | PixelFormat::RGBA8 => {
+            store(src, channels, dst, |v| [(v.clamp(0.0, 1.0) * 255.0).round() as u8])
+        }
+        PixelFormat::L16 | PixelFormat::LA16 | PixelFormat::RGB16 | PixelFormat::RGBA16 => {
+            store(src, channels, dst, |v| ((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_ne_bytes())
+        }
+        PixelFormat::L32F | PixelFormat::LA32F | PixelFormat::RGB32F | PixelFormat::RGBA32F => {
+            store(src, channels, dst, |v| v.to_ne_bytes())
+        }
+        PixelFormat::L64F | PixelFormat::LA64F | PixelFormat::RGB64F | PixelFormat::RGBA64F => {
+            store(src, channels, dst, |v| (v as f64).to_ne_bytes())
+        }
+    }
+}
+
+fn store<const SIZE: usize>(src: &[[f32; 4]], channels: usize, dst: &mut [u8], encode: impl Fn(f32) -> [u8; SIZE]) {
+    for (&[r, g, b, a], out) in src.iter().zip(dst.chunks_exact_mut(channels * SIZE)) {
+        // Gray sources are kept exact instead of going through the weights
+        let luma = if r == g && g == b { g } else { 0.299 * r + 0.587 * g + 0.114 * b };
+        let samples = [[luma, 0.0, 0.0, 0.0], [luma, a, 0.0, 0.0], [r, g, b, 0.0], [r, g, b, a]][channels - 1];
+
+        for (sample, bytes) in samples.iter().zip(out.chunks_exact_mut(SIZE)) {
+            bytes.copy_from_slice(&encode(*sample));
+        }
+    }
+}
@@ -1,7 +1,12 @@
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::limits::Limits;
 use std::io;
 
+mod encoder;
 mod simd;
 
+pub use encoder::{adler32, DeflateEncoder, ZlibEncoder, DEFAULT_LEVEL};
+
 const MAX_BITS: usize = 15;
 const CODELEN_SYMBOLS: usize = 19;
 
@@ -328,6 +333,9 @@ fn build_dynamic_tables(bits: &mut BitBuffer) -> Result<(Box<HuffTable>, Box<Huf
         if consumed == 0 || entry.is_invalid() {
             return Err(io::Error::new(io::ErrorKind::InvalidData, "Bad cl code"));
         }
+        if bits.stop_at_end && consumed > bits.count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough bits"));
+        }
         bits.consume(consumed);
 
         let sym = entry.cl_sym();
@@ -380,21 +388,60 @@ fn build_fixed_tables() -> (Box<HuffTable>, Box<HuffTable>) {
     (litlen, dist)
 }
 
-struct BitBuffer {
+/// Reads bits from input that is stored in one or more segments, which are read as if they
+/// were joined.
+struct BitBuffer<'a> {
     buf: u64,
     count: u32,
-    src: Vec<u8>,
+    src: &'a [u8],
+    /// Segments to read once `src` is exhausted.
+    rest: &'a [&'a [u8]],
     pos: usize,
+    /// Length of the segments before `src`.
+    consumed: u64,
+    /// Fail at the end of the input instead of reading zero bits past it.
+    stop_at_end: bool,
 }
 
-impl BitBuffer {
-    fn new(src: Vec<u8>) -> Self {
-        BitBuffer { buf: 0, count: 0, src, pos: 0 }
+impl<'a> BitBuffer<'a> {
+    fn new(segments: &'a [&'a [u8]]) -> Self {
+        let (src, rest) = segments.split_first().map_or((&[][..], &[][..]), |(first, rest)| (*first, rest));
+        BitBuffer { buf: 0, count: 0, src, rest, pos: 0, consumed: 0, stop_at_end: false }
+    }
+
+    fn stopping_at_end(segments: &'a [&'a [u8]]) -> Self {
+        BitBuffer { stop_at_end: true, ..BitBuffer::new(segments) }
+    }
+
+    /// Number of bits consumed from the input. Only meaningful while no bits past the end were consumed.
+    fn bit_position(&self) -> u64 {
+        (self.consumed + self.pos as u64) * 8 - self.count as u64
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.pos >= self.src.len() && self.rest.iter().all(|segment| segment.is_empty())
+    }
+
+    /// Moves on to the next non-empty segment, returns `false` at the end of the input.
+    fn next_segment(&mut self) -> bool {
+        while let Some((first, rest)) = self.rest.split_first() {
+            self.consumed += self.src.len() as u64;
+            self.src = first;
+            self.rest = rest;
+            self.pos = 0;
+            if !first.is_empty() {
+                return true;
+            }
+        }
+        false
     }
 
     #[inline(always)]
     fn fill(&mut self) {
-        while self.count <= 56 && self.pos < self.src.len() {
+        while self.count <= 56 {
+            if self.pos >= self.src.len() && !self.next_segment() {
+                break;
+            }
             self.buf |= (self.src[self.pos] as u64) << self.count;
             self.count += 8;
             self.pos += 1;
@@ -427,7 +474,7 @@ impl BitBuffer {
             self.count -= 8;
             return Ok(b);
         }
-        if self.pos >= self.src.len() {
+        if self.pos >= self.src.len() && !self.next_segment() {
             return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "EOF"));
         }
         let b = self.src[self.pos];
@@ -459,6 +506,9 @@ impl BitBuffer {
         if entry.is_invalid() || consumed == 0 {
             return Err(io::Error::new(io::ErrorKind::InvalidData, "Bad litlen code"));
         }
+        if self.stop_at_end && consumed > self.count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough bits"));
+        }
         self.consume(consumed);
         Ok(entry)
     }
@@ -470,6 +520,9 @@ impl BitBuffer {
         if entry.is_invalid() || consumed == 0 {
             return Err(io::Error::new(io::ErrorKind::InvalidData, "Bad dist code"));
         }
+        if self.stop_at_end && consumed > self.count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough bits"));
+        }
         self.consume(consumed);
         Ok(entry)
     }
@@ -522,6 +575,7 @@ fn decode_block(bits: &mut BitBuffer, litlen: &HuffTable, dist: &HuffTable, outp
             let extra_bits = entry.full_len() - base_len_bits;
             let extra = if extra_bits > 0 {
                 if bits.count < extra_bits { bits.fill(); }
+                if bits.stop_at_end && bits.count < extra_bits { return false; }
                 let v = bits.peek(extra_bits);
                 bits.consume(extra_bits);
                 v as usize
@@ -541,6 +595,7 @@ fn decode_block(bits: &mut BitBuffer, litlen: &HuffTable, dist: &HuffTable, outp
             let dist_extra_bits = dist_entry.full_len() - dist_base_bits;
             let dist_extra = if dist_extra_bits > 0 {
                 if bits.count < dist_extra_bits { bits.fill(); }
+                if bits.stop_at_end && bits.count < dist_extra_bits { return false; }
                 let v = bits.peek(dist_extra_bits);
                 bits.consume(dist_extra_bits);
                 v as usize
@@ -563,6 +618,82 @@ fn decode_block(bits: &mut BitBuffer, litlen: &HuffTable, dist: &HuffTable, outp
     }
 }
 
+/// Inflates the next block. Returns whether it was the final block, or `None` if the block is
+/// invalid or incomplete.
+fn inflate_block(bits: &mut BitBuffer, fixed_litlen: &HuffTable, fixed_dist: &HuffTable, output: &mut Vec<u8>, max_output: usize) -> Option<bool> {
+    bits.fill();
+    let bfinal = bits.read_bits(1).ok()?;
+    let btype = bits.read_bits(2).ok()?;
+
+    let ok = match btype {
+        0 => {
+            bits.align_to_byte();
+            let len = bits.read_u16_le_aligned().ok()?;
+            let nlen = bits.read_u16_le_aligned().ok()?;
+            if len != !nlen { return None; }
+            output.reserve(len as usize);
+            let mut ok = true;
+            for _ in 0..len {
+                if output.len() >= max_output { break; }
+                match bits.read_byte_aligned() {
+                    Ok(b) => output.push(b),
+                    Err(_) => { ok = false; break; }
+                }
+            }
+            ok
+        }
+        1 => decode_block(bits, fixed_litlen, fixed_dist, output, max_output),
+        2 => {
+            match build_dynamic_tables(bits) {
+                Ok((litlen, dist)) => decode_block(bits, &litlen, &dist, output, max_output),
+                Err(_) => false,
+            }
+        }
+        _ => return None,
+    };
+
+    ok.then_some(bfinal == 1)
+}
+
+/// Output limit of a stream decoded with the given capacity hint.
+fn max_output_for(capacity_hint: usize) -> usize {
+    if capacity_hint > 0 { capacity_hint * 4 } else { 256 * 1024 * 1024 }
+}
+
+/// Inflates blocks from `bits` until the final block, or until the stream turns out to be invalid.
+fn inflate_all(bits: &mut BitBuffer, capacity_hint: usize, max_output: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(capacity_hint.min(max_output));
+
+    let (fixed_litlen, fixed_dist) = build_fixed_tables();
+
+    while let Some(false) = inflate_block(bits, &fixed_litlen, &fixed_dist, &mut output, max_output) {}
+
+    output
+}
+
+/// Inflates the zlib stream stored in `segments`. Returns an empty output if the header is invalid.
+fn inflate_zlib(segments: &[&[u8]], capacity_hint: usize, max_output: usize) -> Vec<u8> {
+    let mut bits = BitBuffer::new(segments);
+
+    let (Ok(cmf), Ok(flg)) = (bits.read_byte_aligned(), bits.read_byte_aligned()) else {
+        return Vec::new();
+    };
+    if cmf & 0x0F != 8 {
+        return Vec::new();
+    }
+
+    // Skip the identifier of the preset dictionary
+    if (flg >> 5) & 1 != 0 && (0..4).any(|_| bits.read_byte_aligned().is_err()) {
+        return Vec::new();
+    }
+
+    if bits.is_exhausted() {
+        return Vec::new();
+    }
+
+    inflate_all(&mut bits, capacity_hint, max_output)
+}
+
 pub struct DeflateDecoder {
     data: Vec<u8>,
 }
@@ -573,62 +704,12 @@ impl DeflateDecoder {
     }
 
     pub fn decode_with_capacity(&self, capacity_hint: usize) -> Vec<u8> {
-        let mut bits = BitBuffer::new(self.data.clone());
-        let mut output = Vec::with_capacity(capacity_hint);
-        let max_output = if capacity_hint > 0 { capacity_hint * 4 } else { 256 * 1024 * 1024 };
-
-        let (fixed_litlen, fixed_dist) = build_fixed_tables();
-
-        loop {
-            bits.fill();
-            let bfinal = match bits.read_bits(1) {
-                Ok(v) => v,
-                Err(_) => break,
-            };
-            let btype = match bits.read_bits(2) {
-                Ok(v) => v,
-                Err(_) => break,
-            };
-
-            let ok = match btype {
-                0 => {
-                    bits.align_to_byte();
-                    let len = match bits.read_u16_le_aligned() {
-                        Ok(v) => v,
-                        Err(_) => break,
-                    };
-                    let nlen = match bits.read_u16_le_aligned() {
-                        Ok(v) => v,
-                        Err(_) => break,
-                    };
-                    if len != !nlen { break; }
-                    output.reserve(len as usize);
-                    let mut ok = true;
-                    for _ in 0..len {
-                        if output.len() >= max_output { break; }
-                        match bits.read_byte_aligned() {
-                            Ok(b) => output.push(b),
-                            Err(_) => { ok = false; break; }
-                        }
-                    }
-                    ok
-                }
-                1 => decode_block(&mut bits, &fixed_litlen, &fixed_dist, &mut output, max_output),
-                2 => {
-                    match build_dynamic_tables(&mut bits) {
-                        Ok((litlen, dist)) => decode_block(&mut bits, &litlen, &dist, &mut output, max_output),
-                        Err(_) => false,
-                    }
-                }
-                _ => break,
-            };
-
-            if bfinal == 1 || !ok {
-                break;
-            }
-        }
+        self.decode_bounded(capacity_hint, max_output_for(capacity_hint))
+    }
 
-        output
+    /// Decodes at most `max_output` bytes, the rest of the stream is ignored.
+    pub fn decode_bounded(&self, capacity_hint: usize, max_output: usize) -> Vec<u8> {
+        inflate_all(&mut BitBuffer::new(&[&self.data]), capacity_hint, max_output)
     }
 }
 
@@ -646,13 +727,185 @@ impl ZlibDecoder {
     }
 
     pub fn decode_with_capacity(&self, capacity_hint: usize) -> Vec<u8> {
-        if self.data.len() < 2 { return Vec::new(); }
-        let cmf = self.data[0];
-        let flg = self.data[1];
-        if cmf & 0x0F != 8 { return Vec::new(); }
-        let fdict = (flg >> 5) & 1;
-        let offset = if fdict != 0 && self.data.len() >= 6 { 6 } else { 2 };
-        if offset >= self.data.len() { return Vec::new(); }
-        DeflateDecoder::from_bytes(self.data[offset..].to_vec()).decode_with_capacity(capacity_hint)
+        Self::decode_segments(&[&self.data], capacity_hint)
+    }
+
+    /// Decodes the stream, or returns `None` if it inflates to more than `max_output` bytes.
+    pub fn decode_with_limit(&self, max_output: usize) -> Option<Vec<u8>> {
+        Self::decode_segments_with_limit(&[&self.data], max_output)
+    }
+
+    /// Decodes the stream, bounded by the remaining allocation budget of `limits`, and reserves
+    /// the output against it. Release the reservation with [`Limits::free_usize`] once the output
+    /// is dropped.
+    pub(crate) fn decode_reserved(&self, limits: &mut Limits) -> VexelResult<Vec<u8>> {
+        Self::decode_segments_reserved(&[&self.data], limits)
+    }
+
+    /// Decodes a stream that is stored in `segments` without joining them first, such as the
+    /// IDAT chunks of a PNG file borrowed from the input.
+    pub(crate) fn decode_segments(segments: &[&[u8]], capacity_hint: usize) -> Vec<u8> {
+        inflate_zlib(segments, capacity_hint, max_output_for(capacity_hint))
+    }
+
+    /// Like [`decode_with_limit`](Self::decode_with_limit), for a stream stored in `segments`.
+    pub(crate) fn decode_segments_with_limit(segments: &[&[u8]], max_output: usize) -> Option<Vec<u8>> {
+        // One byte more than allowed tells a stream that is too large from one that fits exactly
+        let output = inflate_zlib(segments, 0, max_output.saturating_add(1));
+        (output.len() <= max_output).then_some(output)
+    }
+
+    /// Like [`decode_reserved`](Self::decode_reserved), for a stream stored in `segments`.
+    pub(crate) fn decode_segments_reserved(segments: &[&[u8]], limits: &mut Limits) -> VexelResult<Vec<u8>> {
+        let output = match limits.alloc_remaining() {
+            Some(remaining) => {
+                let max_output = usize::try_from(remaining).unwrap_or(usize::MAX);
+                Self::decode_segments_with_limit(segments, max_output).ok_or_else(|| {
+                    VexelError::LimitExceeded(format!(
+                        "compressed data inflates to more than the remaining budget of {} bytes",
+                        remaining
+                    ))
+                })?
+            }
+            None => Self::decode_segments(segments, 0),
+        };
+
+        limits.reserve_usize(output.len())?;
+
+        Ok(output)
+    }
+}
+
+/// Distance that back-references in a deflate stream can reach.
+const WINDOW_SIZE: usize = 32 * 1024;
+
+/// Inflates a zlib stream that is received in pieces.
+///
+/// Complete blocks are inflated once. The block that is still being received is inflated from its
+/// start each time [`inflate`](Self::inflate) is called, and is only kept once it is complete.
+/// Each output byte is handed out once, as soon as the data it depends on has been received.
+pub(crate) struct ZlibStream {
+    /// Received data from the start of the first incomplete block.
+    input: Vec<u8>,
+    /// Bits of the first byte of `input` that belong to the previous block.
+    skip_bits: u32,
+    /// The last output of the complete blocks, which later blocks can refer back to.
+    window: Vec<u8>,
+    /// Number of bytes the complete blocks inflated to.
+    inflated: usize,
+    /// Number of bytes handed out, which can include part of the incomplete block.
+    emitted: usize,
+    header_read: bool,
+    finished: bool,
+}
+
+impl ZlibStream {
+    pub(crate) fn new() -> Self {
+        ZlibStream {
+            input: Vec::new(),
+            skip_bits: 0,
+            window: Vec::new(),
+            inflated: 0,
+            emitted: 0,
+            header_read: false,
+            finished: false,
+        }
+    }
+
+    pub(crate) fn push(&mut self, data: &[u8]) {
+        if !self.finished {
+            self.input.extend_from_slice(data);
+        }
+    }
+
+    /// Inflates the data pushed so far and appends the bytes not handed out before to `output`,
+    /// including those of the incomplete block that have been received.
+    pub(crate) fn inflate(&mut self, output: &mut Vec<u8>) {
+        if self.finished || !self.read_header() {
+            return;
+        }
+
+        let mut input = std::mem::take(&mut self.input);
+        let segments = [&input[..]];
+        let mut bits = BitBuffer::stopping_at_end(&segments);
+        if bits.read_bits(self.skip_bits).is_err() {
+            self.input = input;
+            return;
+        }
+
+        let (fixed_litlen, fixed_dist) = build_fixed_tables();
+        let mut block_start = bits.bit_position();
+
+        loop {
+            let window_len = self.window.len();
+            let result = inflate_block(&mut bits, &fixed_litlen, &fixed_dist, &mut self.window, usize::MAX);
+            self.emit(window_len, output);
+
+            match result {
+                Some(last) => {
+                    self.inflated += self.window.len() - window_len;
+                    block_start = bits.bit_position();
+
+                    if self.window.len() > 2 * WINDOW_SIZE {
+                        self.window.drain(..self.window.len() - WINDOW_SIZE);
+                    }
+
+                    if last {
+                        self.finished = true;
+                        break;
+                    }
+                }
+                None if bits.is_exhausted() => {
+                    self.window.truncate(window_len);
+                    break;
+                }
+                // Invalid data, nothing after it can be inflated
+                None => {
+                    self.inflated += self.window.len() - window_len;
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+
+        input.drain(..(block_start / 8) as usize);
+        self.input = input;
+        self.skip_bits = (block_start % 8) as u32;
+    }
+
+    /// Appends the bytes of the window from `block_start` on that haven't been handed out yet.
+    fn emit(&mut self, block_start: usize, output: &mut Vec<u8>) {
+        let new = self.emitted.saturating_sub(self.inflated);
+        if let Some(bytes) = self.window.get(block_start + new..) {
+            output.extend_from_slice(bytes);
+            self.emitted += bytes.len();
+        }
+    }
+
+    /// Skips the zlib header once it has been received, returns `false` until then.
+    fn read_header(&mut self) -> bool {
+        if self.header_read {
+            return true;
+        }
+
+        if self.input.len() < 2 {
+            return false;
+        }
+
+        if self.input[0] & 0x0F != 8 {
+            self.finished = true;
+            return false;
+        }
+
+        let has_dictionary = (self.input[1] >> 5) & 1 != 0;
+        let length = if has_dictionary { 6 } else { 2 };
+        if self.input.len() < length {
+            return false;
+        }
+
+        self.input.drain(..length);
+        self.header_read = true;
+
+        true
     }
 }
@@ -0,0 +1,651 @@
+use super::{CODE_LENGTH_ORDER, DIST_BASE, DIST_EXTRA, FIXED_DIST_LENGTHS, FIXED_LITLEN_LENGTHS, LENGTH_BASE, LENGTH_EXTRA, MAX_BITS};
+
+const WINDOW_SIZE: usize = 32768;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+
+const HASH_BITS: u32 = 15;
+const HASH_SIZE: usize = 1 << HASH_BITS;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// Minimum-length matches further away than this cost more than the literals they replace.
+const TOO_FAR: usize = 4096;
+
+/// Number of symbols collected before a block is written.
+const BLOCK_TOKENS: usize = 1 << 14;
+
+const MAX_STORED: usize = 65535;
+const MAX_CODE_LENGTH_BITS: usize = 7;
+
+const END_OF_BLOCK: usize = 256;
+
+const NONE: usize = usize::MAX;
+
+pub const DEFAULT_LEVEL: u8 = 6;
+
+/// Match finder settings of a compression level, modelled on zlib's.
+struct LevelParams {
+    /// Matches at least this long are taken without looking for a longer one at the next byte.
+    max_lazy: usize,
+    /// Matches at least this long end the search.
+    nice_length: usize,
+    /// Number of hash chain entries checked per position.
+    max_chain: usize,
+    lazy: bool,
+}
+
+#[rustfmt::skip]
+const LEVELS: [LevelParams; 10] = [
+    LevelParams { max_lazy: 0,   nice_length: 0,   max_chain: 0,    lazy: false },
+    LevelParams { max_lazy: 4,   nice_length: 8,   max_chain: 4,    lazy: false },
+    LevelParams { max_lazy: 5,   nice_length: 16,  max_chain: 8,    lazy: false },
+    LevelParams { max_lazy: 6,   nice_length: 32,  max_chain: 32,   lazy: false },
+    LevelParams { max_lazy: 4,   nice_length: 16,  max_chain: 16,   lazy: true },
+    LevelParams { max_lazy: 16,  nice_length: 32,  max_chain: 32,   lazy: true },
+    LevelParams { max_lazy: 16,  nice_length: 128, max_chain: 128,  lazy: true },
+    LevelParams { max_lazy: 32,  nice_length: 128, max_chain: 256,  lazy: true },
+    LevelParams { max_lazy: 128, nice_length: 258, max_chain: 1024, lazy: true },
+    LevelParams { max_lazy: 258, nice_length: 258, max_chain: 4096, lazy: true },
+];
+
+/// A literal byte, or a match of `length` bytes `dist` bytes back. Literals have a `dist` of 0.
+#[derive(Clone, Copy)]
+struct Token {
+    length: u16,
+    dist: u16,
+}
+
+impl Token {
+    fn literal(byte: u8) -> Self {
+        Token { length: byte as u16, dist: 0 }
+    }
+
+    fn matched(length: usize, dist: usize) -> Self {
+        Token {
+            length: length as u16,
+            dist: dist as u16,
+        }
+    }
+
+    fn is_literal(self) -> bool {
+        self.dist == 0
+    }
+}
+
+#[inline(always)]
+fn length_symbol(length: usize) -> usize {
+    LENGTH_BASE.partition_point(|&base| base as usize <= length) - 1
+}
+
+#[inline(always)]
+fn dist_symbol(dist: usize) -> usize {
+    DIST_BASE.partition_point(|&base| base as usize <= dist) - 1
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    buf: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new(capacity: usize) -> Self {
+        BitWriter {
+            out: Vec::with_capacity(capacity),
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn write_bits(&mut self, value: u32, n: u32) {
+        self.buf |= (value as u64) << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.buf as u8);
+            self.buf >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.count > 0 {
+            self.out.push(self.buf as u8);
+            self.buf = 0;
+            self.count = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align_to_byte();
+        self.out
+    }
+}
+
+/// Returns the lengths of an optimal prefix code for `freqs` with no code longer than
+/// `max_bits`, using the package-merge algorithm. Unused symbols get a length of 0.
+fn code_lengths(freqs: &[u32], max_bits: usize) -> Vec<u8> {
+    let mut lengths = vec![0u8; freqs.len()];
+
+    let mut leaves: Vec<(u64, usize)> = freqs
+        .iter()
+        .enumerate()
+        .filter(|(_, &freq)| freq > 0)
+        .map(|(symbol, &freq)| (freq as u64, symbol))
+        .collect();
+
+    // A single code would be incomplete, which some inflaters reject
+    match leaves.len() {
+        0 => return lengths,
+        1 => {
+            let symbol = leaves[0].1;
+            lengths[symbol] = 1;
+            lengths[if symbol == 0 { 1 } else { 0 }] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+
+    leaves.sort();
+
+    // Every item is a weight and the leaves it contains
+    let leaf_items: Vec<(u64, Vec<usize>)> = leaves.iter().map(|&(weight, symbol)| (weight, vec![symbol])).collect();
+    let mut items = leaf_items.clone();
+
+    for _ in 1..max_bits {
+        let packages = items.chunks_exact(2).map(|pair| {
+            let mut symbols = pair[0].1.clone();
+            symbols.extend_from_slice(&pair[1].1);
+            (pair[0].0 + pair[1].0, symbols)
+        });
+
+        let mut merged = Vec::with_capacity(leaf_items.len() * 2);
+        let mut leaves = leaf_items.iter().cloned().peekable();
+        let mut packages = packages.peekable();
+        loop {
+            let take_leaf = match (leaves.peek(), packages.peek()) {
+                (Some(leaf), Some(package)) => leaf.0 <= package.0,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+
+            merged.push(if take_leaf { leaves.next().unwrap() } else { packages.next().unwrap() });
+        }
+
+        items = merged;
+    }
+
+    for (_, symbols) in items.iter().take(2 * leaves.len() - 2) {
+        for &symbol in symbols {
+            lengths[symbol] += 1;
+        }
+    }
+
+    lengths
+}
+
+/// Returns the canonical codes for `lengths`, bit-reversed so they can be written LSB first.
+fn codes_for(lengths: &[u8]) -> Vec<u16> {
+    let mut bl_count = [0u32; MAX_BITS + 1];
+    for &length in lengths {
+        bl_count[length as usize] += 1;
+    }
+    bl_count[0] = 0;
+
+    let mut next_code = [0u32; MAX_BITS + 1];
+    let mut code = 0u32;
+    for bits in 1..=MAX_BITS {
+        code = (code + bl_count[bits - 1]) << 1;
+        next_code[bits] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&length| {
+            if length == 0 {
+                return 0;
+            }
+
+            let code = next_code[length as usize];
+            next_code[length as usize] += 1;
+            ((code << (32 - length as u32)).reverse_bits()) as u16
+        })
+        .collect()
+}
+
+/// A prefix code, as code lengths and the reversed codes.
+struct HuffCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+}
+
+impl HuffCode {
+    fn new(lengths: Vec<u8>) -> Self {
+        let codes = codes_for(&lengths);
+        HuffCode { lengths, codes }
+    }
+
+    #[inline(always)]
+    fn write(&self, writer: &mut BitWriter, symbol: usize) {
+        writer.write_bits(self.codes[symbol] as u32, self.lengths[symbol] as u32);
+    }
+}
+
+/// The code lengths of a dynamic block, run-length encoded with the code length alphabet.
+struct DynamicHeader {
+    hlit: usize,
+    hdist: usize,
+    hclen: usize,
+    /// Code length symbols with the value of their extra bits.
+    symbols: Vec<(u8, u8)>,
+    code_length_code: HuffCode,
+}
+
+impl DynamicHeader {
+    fn new(litlen_lengths: &[u8], dist_lengths: &[u8]) -> Self {
+        let hlit = litlen_lengths.iter().rposition(|&l| l != 0).map_or(0, |i| i + 1).max(257);
+        let hdist = dist_lengths.iter().rposition(|&l| l != 0).map_or(0, |i| i + 1).max(1);
+
+        let mut all = litlen_lengths[..hlit].to_vec();
+        all.extend_from_slice(&dist_lengths[..hdist]);
+
+        let mut symbols = Vec::new();
+        let mut i = 0;
+        while i < all.len() {
+            let value = all[i];
+            let mut run = all[i..].iter().take_while(|&&l| l == value).count();
+            i += run;
+
+            if value == 0 {
+                while run >= 11 {
+                    let n = run.min(138);
+                    symbols.push((18, (n - 11) as u8));
+                    run -= n;
+                }
+                if run >= 3 {
+                    symbols.push((17, (run - 3) as u8));
+                    run = 0;
+                }
+            } else {
+                symbols.push((value, 0));
+                run -= 1;
+                while run >= 3 {
+                    let n = run.min(6);
+                    symbols.push((16, (n - 3) as u8));
+                    run -= n;
+                }
+            }
+
+            symbols.extend(std::iter::repeat_n((value, 0), run));
+        }
+
+        let mut freqs = [0u32; 19];
+        for &(symbol, _) in &symbols {
+            freqs[symbol as usize] += 1;
+        }
+        let code_length_code = HuffCode::new(code_lengths(&freqs, MAX_CODE_LENGTH_BITS));
+
+        let hclen = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| code_length_code.lengths[symbol] != 0)
+            .map_or(0, |i| i + 1)
+            .max(4);
+
+        DynamicHeader {
+            hlit,
+            hdist,
+            hclen,
+            symbols,
+            code_length_code,
+        }
+    }
+
+    fn cost(&self) -> u64 {
+        let symbols: u64 = self
+            .symbols
+            .iter()
+            .map(|&(symbol, _)| self.code_length_code.lengths[symbol as usize] as u64 + extra_bits_of(symbol) as u64)
+            .sum();
+
+        5 + 5 + 4 + 3 * self.hclen as u64 + symbols
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        writer.write_bits((self.hlit - 257) as u32, 5);
+        writer.write_bits((self.hdist - 1) as u32, 5);
+        writer.write_bits((self.hclen - 4) as u32, 4);
+
+        for &symbol in &CODE_LENGTH_ORDER[..self.hclen] {
+            writer.write_bits(self.code_length_code.lengths[symbol] as u32, 3);
+        }
+
+        for &(symbol, extra) in &self.symbols {
+            self.code_length_code.write(writer, symbol as usize);
+            writer.write_bits(extra as u32, extra_bits_of(symbol));
+        }
+    }
+}
+
+fn extra_bits_of(code_length_symbol: u8) -> u32 {
+    match code_length_symbol {
+        16 => 2,
+        17 => 3,
+        18 => 7,
+        _ => 0,
+    }
+}
+
+/// LZ77 match finder over the whole input, with hash chains limited to the deflate window.
+struct MatchFinder {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl MatchFinder {
+    fn new() -> Self {
+        MatchFinder {
+            head: vec![NONE; HASH_SIZE],
+            prev: vec![NONE; WINDOW_SIZE],
+        }
+    }
+
+    #[inline(always)]
+    fn hash(data: &[u8], pos: usize) -> usize {
+        let value = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+        (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        if pos + MIN_MATCH > data.len() {
+            return;
+        }
+
+        let hash = Self::hash(data, pos);
+        self.prev[pos & WINDOW_MASK] = self.head[hash];
+        self.head[hash] = pos;
+    }
+
+    /// Returns the length and distance of the longest match for `pos` among the positions
+    /// inserted so far, or a length of 0.
+    fn find(&self, data: &[u8], pos: usize, params: &LevelParams) -> (usize, usize) {
+        if pos + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+
+        let max_length = MAX_MATCH.min(data.len() - pos);
+        let limit = pos.saturating_sub(WINDOW_SIZE);
+        let current = &data[pos..pos + max_length];
+
+        let (mut best_length, mut best_dist) = (0, 0);
+        let mut candidate = self.head[Self::hash(data, pos)];
+        let mut chain = params.max_chain;
+
+        while candidate != NONE && candidate >= limit && candidate < pos && chain > 0 {
+            let previous = &data[candidate..candidate + max_length];
+
+            // Only a longer match is worth comparing in full
+            if best_length == 0 || previous[best_length.min(max_length - 1)] == current[best_length.min(max_length - 1)] {
+                let length = previous.iter().zip(current).take_while(|(a, b)| a == b).count();
+                if length > best_length {
+                    best_length = length;
+                    best_dist = pos - candidate;
+                    if length >= params.nice_length || length == max_length {
+                        break;
+                    }
+                }
+            }
+
+            // Entries overwritten by a later position would lead forward, so the chain ends there
+            let next = self.prev[candidate & WINDOW_MASK];
+            if next == NONE || next >= candidate {
+                break;
+            }
+            candidate = next;
+            chain -= 1;
+        }
+
+        if best_length < MIN_MATCH || (best_length == MIN_MATCH && best_dist > TOO_FAR) {
+            return (0, 0);
+        }
+
+        (best_length, best_dist)
+    }
+}
+
+/// Compresses `data` into a raw deflate stream.
+fn compress(data: &[u8], level: u8) -> Vec<u8> {
+    let mut writer = BitWriter::new(data.len() / 2 + 64);
+
+    if level == 0 {
+        write_stored(&mut writer, data, true);
+        return writer.finish();
+    }
+
+    let params = &LEVELS[level as usize];
+    let mut finder = MatchFinder::new();
+    let mut tokens = Vec::with_capacity(BLOCK_TOKENS);
+    let mut block_start = 0;
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let (mut length, mut dist) = finder.find(data, pos, params);
+        finder.insert(data, pos);
+
+        // Lazy matching: a literal followed by a longer match beats a shorter match
+        if params.lazy {
+            while length >= MIN_MATCH && length < params.max_lazy && pos + 1 < data.len() {
+                let (next_length, next_dist) = finder.find(data, pos + 1, params);
+                if next_length <= length {
+                    break;
+                }
+
+                tokens.push(Token::literal(data[pos]));
+                pos += 1;
+                finder.insert(data, pos);
+                length = next_length;
+                dist = next_dist;
+            }
+        }
+
+        if length >= MIN_MATCH {
+            tokens.push(Token::matched(length, dist));
+            for p in pos + 1..pos + length {
+                finder.insert(data, p);
+            }
+            pos += length;
+        } else {
+            tokens.push(Token::literal(data[pos]));
+            pos += 1;
+        }
+
+        if tokens.len() >= BLOCK_TOKENS {
+            write_block(&mut writer, &tokens, &data[block_start..pos], pos == data.len());
+            tokens.clear();
+            block_start = pos;
+        }
+    }
+
+    if !tokens.is_empty() || data.is_empty() {
+        write_block(&mut writer, &tokens, &data[block_start..], true);
+    }
+
+    writer.finish()
+}
+
+/// Writes `tokens`, which encode `raw`, as a stored, fixed or dynamic block, whichever is
+/// smallest.
+fn write_block(writer: &mut BitWriter, tokens: &[Token], raw: &[u8], last: bool) {
+    let mut litlen_freqs = [0u32; 286];
+    let mut dist_freqs = [0u32; 30];
+    for &token in tokens {
+        if token.is_literal() {
+            litlen_freqs[token.length as usize] += 1;
+        } else {
+            litlen_freqs[257 + length_symbol(token.length as usize)] += 1;
+            dist_freqs[dist_symbol(token.dist as usize)] += 1;
+        }
+    }
+    litlen_freqs[END_OF_BLOCK] = 1;
+
+    let litlen = HuffCode::new(code_lengths(&litlen_freqs, MAX_BITS));
+    let dist = HuffCode::new(code_lengths(&dist_freqs, MAX_BITS));
+    let header = DynamicHeader::new(&litlen.lengths, &dist.lengths);
+
+    let extra_bits: u64 = litlen_freqs[257..]
+        .iter()
+        .zip(LENGTH_EXTRA)
+        .chain(dist_freqs.iter().zip(DIST_EXTRA))
+        .map(|(&freq, extra)| freq as u64 * extra as u64)
+        .sum();
+    let symbol_cost = |litlen_lengths: &[u8], dist_lengths: &[u8]| -> u64 {
+        let litlen: u64 = litlen_freqs.iter().zip(litlen_lengths).map(|(&f, &l)| f as u64 * l as u64).sum();
+        let dist: u64 = dist_freqs.iter().zip(dist_lengths).map(|(&f, &l)| f as u64 * l as u64).sum();
+        litlen + dist + extra_bits
+    };
+
+    let dynamic_cost = 3 + header.cost() + symbol_cost(&litlen.lengths, &dist.lengths);
+    let fixed_cost = 3 + symbol_cost(&FIXED_LITLEN_LENGTHS, &FIXED_DIST_LENGTHS);
+    let stored_cost = raw.len().div_ceil(MAX_STORED).max(1) as u64 * 40 + raw.len() as u64 * 8;
+
+    if stored_cost <= fixed_cost && stored_cost <= dynamic_cost {
+        write_stored(writer, raw, last);
+    } else if fixed_cost <= dynamic_cost {
+        writer.write_bits(last as u32 | 1 << 1, 3);
+        let litlen = HuffCode::new(FIXED_LITLEN_LENGTHS.to_vec());
+        let dist = HuffCode::new(FIXED_DIST_LENGTHS.to_vec());
+        write_tokens(writer, tokens, &litlen, &dist);
+    } else {
+        writer.write_bits(last as u32 | 2 << 1, 3);
+        header.write(writer);
+        write_tokens(writer, tokens, &litlen, &dist);
+    }
+}
+
+fn write_tokens(writer: &mut BitWriter, tokens: &[Token], litlen: &HuffCode, dist: &HuffCode) {
+    for &token in tokens {
+        if token.is_literal() {
+            litlen.write(writer, token.length as usize);
+            continue;
+        }
+
+        let length = token.length as usize;
+        let symbol = length_symbol(length);
+        litlen.write(writer, 257 + symbol);
+        writer.write_bits((length - LENGTH_BASE[symbol] as usize) as u32, LENGTH_EXTRA[symbol]);
+
+        let distance = token.dist as usize;
+        let symbol = dist_symbol(distance);
+        dist.write(writer, symbol);
+        writer.write_bits((distance - DIST_BASE[symbol] as usize) as u32, DIST_EXTRA[symbol]);
+    }
+
+    litlen.write(writer, END_OF_BLOCK);
+}
+
+/// Writes `raw` as stored blocks of at most 65535 bytes. Empty input still gets one block.
+fn write_stored(writer: &mut BitWriter, raw: &[u8], last: bool) {
+    let mut chunks = raw.chunks(MAX_STORED).peekable();
+    if chunks.peek().is_none() {
+        writer.write_bits(last as u32, 3);
+        writer.align_to_byte();
+        writer.out.extend_from_slice(&[0x00, 0x00, 0xFF, 0xFF]);
+        return;
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let final_chunk = last && chunks.peek().is_none();
+        writer.write_bits(final_chunk as u32, 3);
+        writer.align_to_byte();
+
+        let len = chunk.len() as u16;
+        writer.out.extend_from_slice(&len.to_le_bytes());
+        writer.out.extend_from_slice(&(!len).to_le_bytes());
+        writer.out.extend_from_slice(chunk);
+    }
+}
+
+/// Computes the Adler-32 checksum of `data`, as stored at the end of zlib streams.
+pub fn adler32(data: &[u8]) -> u32 {
+    // Largest number of bytes that can be summed before `b` overflows
+    const NMAX: usize = 5552;
+    const MOD: u32 = 65521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(NMAX) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+
+    (b << 16) | a
+}
+
+/// Compresses data into a raw deflate stream (RFC 1951).
+///
+/// Level 0 writes stored blocks only. Levels 1 to 3 use greedy matching and levels 4 to 9 lazy
+/// matching with increasingly long hash chains, like zlib. Each block is written stored, with
+/// the fixed codes or with its own Huffman codes, whichever is smallest.
+pub struct DeflateEncoder {
+    level: u8,
+}
+
+impl DeflateEncoder {
+    /// Creates an encoder with a compression level from 0 to 9. Higher levels are clamped to 9.
+    pub fn new(level: u8) -> Self {
+        DeflateEncoder { level: level.min(9) }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        compress(data, self.level)
+    }
+}
+
+impl Default for DeflateEncoder {
+    fn default() -> Self {
+        DeflateEncoder::new(DEFAULT_LEVEL)
+    }
+}
+
+/// Compresses data into a zlib stream (RFC 1950): a deflate stream with a two byte header and
+/// an Adler-32 checksum, as used by PNG and TIFF.
+pub struct ZlibEncoder {
+    level: u8,
+}
+
+impl ZlibEncoder {
+    /// Creates an encoder with a compression level from 0 to 9. Higher levels are clamped to 9.
+    pub fn new(level: u8) -> Self {
+        ZlibEncoder { level: level.min(9) }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        // 32K window and deflate, then the level hint zlib uses
+        let cmf = 0x78u8;
+        let flevel = match self.level {
+            0 | 1 => 0,
+            2..=5 => 1,
+            6 => 2,
+            _ => 3,
+        };
+        let mut flg = flevel << 6;
+        flg |= 31 - ((cmf as u16 * 256 + flg as u16) % 31) as u8;
+
+        let mut out = vec![cmf, flg];
+        out.extend_from_slice(&compress(data, self.level));
+        out.extend_from_slice(&adler32(data).to_be_bytes());
+
+        out
+    }
+}
+
+impl Default for ZlibEncoder {
+    fn default() -> Self {
+        ZlibEncoder::new(DEFAULT_LEVEL)
+    }
+}
@@ -1,3 +1,5 @@
+use crate::utils::image::ImageFormat;
+use crate::utils::report::DecodeWarning;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
 use std::io;
@@ -11,6 +13,92 @@ pub enum VexelError {
     LimitExceeded(String),
     Custom(String),
     Panic(String),
+    /// The input is malformed and was rejected in [`Strictness::Strict`](crate::Strictness::Strict)
+    /// mode. `warning` describes the first problem found.
+    Malformed { format: ImageFormat, warning: DecodeWarning },
+    /// The decode was cancelled through [`DecodeOptions::cancellation`](crate::DecodeOptions::cancellation).
+    Cancelled,
+    /// The data ends before everything the decoder needs was read. `offset` is where the data
+    /// ends and `expected` the number of bytes the failed read asked for, if known.
+    Truncated { format: ImageFormat, offset: Option<u64>, expected: Option<u64> },
+    /// The data is invalid in a way the decoder cannot recover from.
+    Corrupt { format: ImageFormat, offset: Option<u64>, reason: String },
+    /// The file uses a feature the decoder does not support, such as a compression method.
+    Unsupported { format: ImageFormat, feature: String },
+    /// A part of the file that is needed for decoding is missing, such as the image data or
+    /// a frame header.
+    MissingData { format: ImageFormat, what: String },
+}
+
+impl VexelError {
+    pub(crate) fn corrupt(format: ImageFormat, reason: impl Into<String>) -> Self {
+        VexelError::Corrupt {
+            format,
+            offset: None,
+            reason: reason.into(),
+        }
+    }
+
+    pub(crate) fn unsupported(format: ImageFormat, feature: impl Into<String>) -> Self {
+        VexelError::Unsupported {
+            format,
+            feature: feature.into(),
+        }
+    }
+
+    pub(crate) fn missing(format: ImageFormat, what: impl Into<String>) -> Self {
+        VexelError::MissingData {
+            format,
+            what: what.into(),
+        }
+    }
+
+    /// Sets the format of errors raised by code shared between decoders, which leaves it
+    /// [`ImageFormat::Unknown`].
+    pub(crate) fn with_format(mut self, image_format: &ImageFormat) -> Self {
+        match &mut self {
+            VexelError::Malformed { format, .. }
+            | VexelError::Truncated { format, .. }
+            | VexelError::Corrupt { format, .. }
+            | VexelError::Unsupported { format, .. }
+            | VexelError::MissingData { format, .. }
+                if *format == ImageFormat::Unknown =>
+            {
+                *format = image_format.clone();
+            }
+            _ => {}
+        }
+
+        self
+    }
+}
+
+/// Payload of the [`io::ErrorKind::UnexpectedEof`] errors returned by
+/// [`BitReader`](crate::bitreader::BitReader), converted to [`VexelError::Truncated`].
+#[derive(Debug)]
+pub(crate) struct TruncatedRead {
+    pub offset: u64,
+    pub expected: u64,
+}
+
+impl Error for TruncatedRead {}
+
+impl Display for TruncatedRead {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unexpected end of data at offset {}, {} more bytes expected", self.offset, self.expected)
+    }
+}
+
+/// Writes the format followed by a space, or nothing for errors whose format is not known.
+struct FormatName<'a>(&'a ImageFormat);
+
+impl Display for FormatName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ImageFormat::Unknown => Ok(()),
+            format => write!(f, "{:?} ", format),
+        }
+    }
 }
 
 impl Error for VexelError {
@@ -33,13 +121,55 @@ impl Display for VexelError {
             VexelError::LimitExceeded(msg) => write!(f, "limit exceeded: {}", msg),
             VexelError::Custom(msg) => write!(f, "{}", msg),
             VexelError::Panic(msg) => write!(f, "Decoder panicked: {}", msg),
+            VexelError::Cancelled => write!(f, "Decoding was cancelled"),
+            VexelError::Malformed { format, warning } => {
+                write!(f, "Malformed {}data", FormatName(format))?;
+                if let Some(frame) = warning.frame {
+                    write!(f, " in frame {}", frame)?;
+                }
+                if let Some(offset) = warning.offset {
+                    write!(f, " at offset {}", offset)?;
+                }
+                write!(f, ": {}", warning.message)
+            }
+            VexelError::Truncated { format, offset, expected } => {
+                write!(f, "Truncated {}data", FormatName(format))?;
+                if let Some(offset) = offset {
+                    write!(f, ", ends at offset {}", offset)?;
+                }
+                if let Some(expected) = expected {
+                    write!(f, ", {} more bytes expected", expected)?;
+                }
+                Ok(())
+            }
+            VexelError::Corrupt { format, offset, reason } => {
+                write!(f, "Corrupt {}data", FormatName(format))?;
+                if let Some(offset) = offset {
+                    write!(f, " at offset {}", offset)?;
+                }
+                write!(f, ": {}", reason)
+            }
+            VexelError::Unsupported { format, feature } => write!(f, "Unsupported {}feature: {}", FormatName(format), feature),
+            VexelError::MissingData { format, what } => write!(f, "Missing {}data: {}", FormatName(format), what),
         }
     }
 }
 
 impl From<io::Error> for VexelError {
     fn from(error: io::Error) -> Self {
-        VexelError::IoError(error)
+        match error.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                let read = error.get_ref().and_then(|e| e.downcast_ref::<TruncatedRead>());
+
+                VexelError::Truncated {
+                    format: ImageFormat::Unknown,
+                    offset: read.map(|r| r.offset),
+                    expected: read.map(|r| r.expected),
+                }
+            }
+            io::ErrorKind::InvalidData => VexelError::corrupt(ImageFormat::Unknown, error.to_string()),
+            _ => VexelError::IoError(error),
+        }
     }
 }
 
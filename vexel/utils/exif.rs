@@ -16,6 +16,8 @@ pub enum ExifValue {
     SRational(Vec<(i32, i32)>),
     Float(Vec<f32>),
     Double(Vec<f64>),
+    Long8(Vec<u64>),
+    SLong8(Vec<i64>),
 }
 
 #[derive(Debug, Clone, Serialize, Tsify)]
@@ -41,7 +43,7 @@ pub struct ExifData {
 }
 
 #[derive(Clone, Copy, PartialEq)]
-enum IfdType {
+pub(crate) enum IfdType {
     Ifd0,
     ExifIfd,
     GpsIfd,
@@ -98,6 +100,24 @@ impl<'a> ExifReader<'a> {
         Some(ExifData { byte_order, ifd0, exif_ifd, gps_ifd, interop_ifd, ifd1 })
     }
 
+    /// Decodes the raw bytes of a single IFD entry value.
+    /// `bytes` must hold the value itself, not the offset pointing to it.
+    pub(crate) fn decode_value(byte_order: ByteOrder, data_type: u16, count: usize, bytes: &'a [u8]) -> ExifValue {
+        let reader = ExifReader { data: bytes, byte_order };
+        reader.parse_value(data_type, count, bytes)
+    }
+
+    /// Returns the size in bytes of a single value of the given field type.
+    pub(crate) fn type_size(data_type: u16) -> Option<usize> {
+        match data_type {
+            1 | 2 | 6 | 7 => Some(1),
+            3 | 8 => Some(2),
+            4 | 9 | 11 => Some(4),
+            5 | 10 | 12 | 16 | 17 | 18 => Some(8),
+            _ => None,
+        }
+    }
+
     fn read_u16_at(&self, offset: usize) -> Option<u16> {
         let bytes = self.data.get(offset..offset + 2)?;
         Some(match self.byte_order {
@@ -140,13 +160,7 @@ impl<'a> ExifReader<'a> {
         let data_type = self.read_u16_at(base + 2)?;
         let count = self.read_u32_at(base + 4)? as usize;
 
-        let type_size: usize = match data_type {
-            1 | 2 | 6 | 7 => 1,
-            3 | 8 => 2,
-            4 | 9 | 11 => 4,
-            5 | 10 | 12 => 8,
-            _ => return None,
-        };
+        let type_size = Self::type_size(data_type)?;
 
         let total_size = count.saturating_mul(type_size);
 
@@ -286,6 +300,28 @@ impl<'a> ExifReader<'a> {
                     })
                     .collect(),
             ),
+            16 | 18 => ExifValue::Long8(
+                (0..count)
+                    .filter_map(|i| {
+                        let b = bytes.get(i * 8..i * 8 + 8)?;
+                        Some(match self.byte_order {
+                            ByteOrder::LittleEndian => u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+                            ByteOrder::BigEndian => u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+                        })
+                    })
+                    .collect(),
+            ),
+            17 => ExifValue::SLong8(
+                (0..count)
+                    .filter_map(|i| {
+                        let b = bytes.get(i * 8..i * 8 + 8)?;
+                        Some(match self.byte_order {
+                            ByteOrder::LittleEndian => i64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+                            ByteOrder::BigEndian => i64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+                        })
+                    })
+                    .collect(),
+            ),
             _ => ExifValue::Undefined(bytes.iter().take(count).copied().collect()),
         }
     }
@@ -299,7 +335,7 @@ fn ifd_pointer_offset(value: &ExifValue) -> Option<usize> {
     }
 }
 
-fn tag_name(tag: u16, ifd_type: IfdType) -> Option<&'static str> {
+pub(crate) fn tag_name(tag: u16, ifd_type: IfdType) -> Option<&'static str> {
     match ifd_type {
         IfdType::GpsIfd => match tag {
             0x0000 => Some("GPSVersionID"),
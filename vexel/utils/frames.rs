@@ -0,0 +1,155 @@
+use crate::utils::error::VexelResult;
+use crate::utils::metadata::ImageMetadata;
+use crate::utils::report::DecodeReport;
+use crate::{Image, ImageFrame, Vexel};
+use std::io::{Read, Seek};
+
+enum FrameSource {
+    /// The decoder produces frames one at a time.
+    Decoder,
+    /// The format can only be decoded as a whole, so the frames of the full image are handed out.
+    Decoded(std::vec::IntoIter<ImageFrame>),
+    /// The full image has not been decoded yet.
+    NotDecoded,
+}
+
+/// Iterator over the frames of an image, returned by [`Vexel::frames`].
+///
+/// Each item is a single decoded frame. The iterator ends after the last frame or after the
+/// first error. Warnings are collected in [`report`](Self::report), and in
+/// [`Strictness::Strict`](crate::Strictness::Strict) mode the first recovery warning ends the
+/// iterator with [`VexelError::Malformed`](crate::VexelError::Malformed).
+pub struct Frames<'a, R: Read + Seek> {
+    vexel: &'a mut Vexel<R>,
+    source: FrameSource,
+    metadata: Option<ImageMetadata>,
+    report: DecodeReport,
+    finished: bool,
+}
+
+impl<'a, R: Read + Seek + Sync> Frames<'a, R> {
+    pub(crate) fn new(vexel: &'a mut Vexel<R>) -> VexelResult<Frames<'a, R>> {
+        let (decodes_frames, warnings) = vexel.run_decoder(|vexel| vexel.source.start_frames())?;
+        let source = match decodes_frames {
+            true => FrameSource::Decoder,
+            false => FrameSource::NotDecoded,
+        };
+
+        Ok(Frames {
+            vexel,
+            source,
+            metadata: None,
+            report: DecodeReport::new(warnings, 0),
+            finished: false,
+        })
+    }
+
+    /// Returns the warnings raised while decoding the frames returned or skipped so far. Formats
+    /// that are decoded in full report the warnings of all frames after the first one.
+    pub fn report(&self) -> &DecodeReport {
+        &self.report
+    }
+
+    fn next_frame(&mut self) -> VexelResult<Option<ImageFrame>> {
+        match &mut self.source {
+            FrameSource::Decoder => {
+                let (frame, warnings) =
+                    self.vexel.collect_warnings(|vexel| vexel.call_decoder(|vexel| vexel.source.next_frame()))?;
+                let Some(frame) = frame else {
+                    self.report.add(warnings, 0);
+                    return Ok(None);
+                };
+
+                let frame = self.finish_frame(frame);
+                self.report.add(warnings, frame.width() as u64 * frame.height() as u64);
+
+                Ok(Some(frame))
+            }
+            FrameSource::Decoded(frames) => Ok(frames.next()),
+            FrameSource::NotDecoded => {
+                // The report of the full decode already includes the warnings of `new`
+                let image = self.vexel.decode()?;
+                self.report = image.report().clone();
+
+                let mut frames = image.into_frames().into_iter();
+                let frame = frames.next();
+                self.source = FrameSource::Decoded(frames);
+
+                Ok(frame)
+            }
+        }
+    }
+
+    fn skip_frame(&mut self) -> VexelResult<bool> {
+        match &mut self.source {
+            FrameSource::Decoder => {
+                let (skipped, warnings) =
+                    self.vexel.collect_warnings(|vexel| vexel.call_decoder(|vexel| vexel.source.skip_frame()))?;
+                self.report.add(warnings, 0);
+
+                Ok(skipped)
+            }
+            _ => self.next_frame().map(|frame| frame.is_some()),
+        }
+    }
+
+    /// Attaches the metadata to a frame and applies orientation and colour conversion,
+    /// the same way [`Vexel::decode`] does for the whole image.
+    fn finish_frame(&mut self, frame: ImageFrame) -> ImageFrame {
+        // Everything the metadata is built from comes before the first frame
+        let metadata = self.metadata.get_or_insert_with(|| self.vexel.source.metadata());
+
+        let mut image = Image::from_frame(frame);
+        image.set_metadata(metadata.clone());
+
+        if self.vexel.apply_orientation {
+            image.apply_orientation();
+        }
+
+        image.apply_color_profile(self.vexel.color_target);
+
+        image.into_frames().remove(0)
+    }
+
+    fn finish<T>(&mut self, result: VexelResult<Option<T>>) -> Option<VexelResult<T>> {
+        match result {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek + Sync> Iterator for Frames<'_, R> {
+    type Item = VexelResult<ImageFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let result = self.next_frame();
+        self.finish(result)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        for _ in 0..n {
+            if self.finished {
+                return None;
+            }
+
+            let result = self.skip_frame().map(|skipped| skipped.then_some(()));
+            if let Some(Err(e)) = self.finish(result) {
+                return Some(Err(e));
+            }
+        }
+
+        self.next()
+    }
+}
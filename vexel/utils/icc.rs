@@ -140,9 +140,34 @@ pub enum ICCTagData {
     Float64Array(Vec<f64>),
     Cicp(CicpData),
     TextDescription(TextDescriptionData),
+    Lut(LutData),
     Unknown(Vec<u8>),
 }
 
+/// A multi-dimensional lookup table from a `mft1` (lut8), `mft2` (lut16), `mAB ` or `mBA ` tag.
+///
+/// For `mft1`/`mft2` the input tables are stored in `a_curves` and the output tables in
+/// `b_curves`. Curves are [`ICCTagData::Curve`] or [`ICCTagData::ParametricCurve`].
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct LutData {
+    pub lut_type: String,
+    pub input_channels: u8,
+    pub output_channels: u8,
+    /// 3x3 matrix in row-major order, followed by 3 offsets.
+    pub matrix: Option<Vec<f64>>,
+    pub a_curves: Vec<ICCTagData>,
+    pub clut: Option<ClutData>,
+    pub m_curves: Vec<ICCTagData>,
+    pub b_curves: Vec<ICCTagData>,
+}
+
+/// Colour lookup table with values scaled to 16 bits. The first input channel varies slowest.
+#[derive(Debug, Clone, Serialize, Tsify)]
+pub struct ClutData {
+    pub grid_points: Vec<u8>,
+    pub values: Vec<u16>,
+}
+
 #[derive(Debug, Clone, Serialize, Tsify)]
 pub struct LocalizedString {
     pub language: String,
@@ -779,6 +804,214 @@ fn parse_desc_tag(data: &[u8]) -> ICCTagData {
     })
 }
 
+fn read_matrix(data: &[u8], offset: usize, count: usize) -> Option<Vec<f64>> {
+    (0..count).map(|i| read_s15_fixed16(data, offset + i * 4)).collect()
+}
+
+/// Number of CLUT entries for the given grid, `None` if it does not fit in memory.
+fn clut_entries(grid_points: &[u8], output_channels: u8) -> Option<usize> {
+    grid_points
+        .iter()
+        .try_fold(output_channels as usize, |acc, &g| acc.checked_mul(g as usize))
+        .filter(|&n| n > 0)
+}
+
+/// Reads `count` lut8 tables of 256 entries each, advancing `offset` past them.
+fn read_lut8_tables(data: &[u8], offset: &mut usize, count: u8) -> Option<Vec<ICCTagData>> {
+    (0..count)
+        .map(|_| {
+            let table = data.get(*offset..*offset + 256)?.iter().map(|&v| v as u16 * 257).collect();
+            *offset += 256;
+            Some(ICCTagData::Curve(CurveData::Table(table)))
+        })
+        .collect()
+}
+
+fn parse_lut8_tag(data: &[u8]) -> Option<LutData> {
+    let input_channels = read_u8(data, 8)?;
+    let output_channels = read_u8(data, 9)?;
+    let grid = read_u8(data, 10)?;
+
+    let matrix = read_matrix(data, 12, 9)?;
+    let mut offset = 48;
+
+    let a_curves = read_lut8_tables(data, &mut offset, input_channels)?;
+
+    let grid_points = vec![grid; input_channels as usize];
+    let entries = clut_entries(&grid_points, output_channels)?;
+    let values = data.get(offset..offset.checked_add(entries)?)?.iter().map(|&v| v as u16 * 257).collect();
+    offset += entries;
+
+    let b_curves = read_lut8_tables(data, &mut offset, output_channels)?;
+
+    Some(LutData {
+        lut_type: "mft1".to_string(),
+        input_channels,
+        output_channels,
+        matrix: Some(matrix.into_iter().chain([0.0; 3]).collect()),
+        a_curves,
+        clut: Some(ClutData { grid_points, values }),
+        m_curves: Vec::new(),
+        b_curves,
+    })
+}
+
+fn parse_lut16_tag(data: &[u8]) -> Option<LutData> {
+    let input_channels = read_u8(data, 8)?;
+    let output_channels = read_u8(data, 9)?;
+    let grid = read_u8(data, 10)?;
+
+    let matrix = read_matrix(data, 12, 9)?;
+    let input_entries = read_u16_be(data, 48)? as usize;
+    let output_entries = read_u16_be(data, 50)? as usize;
+    let mut offset = 52;
+
+    let read_u16s = |offset: usize, count: usize| -> Option<Vec<u16>> {
+        let bytes = data.get(offset..offset.checked_add(count.checked_mul(2)?)?)?;
+        Some(bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect())
+    };
+
+    let mut a_curves = Vec::with_capacity(input_channels as usize);
+    for _ in 0..input_channels {
+        a_curves.push(ICCTagData::Curve(CurveData::Table(read_u16s(offset, input_entries)?)));
+        offset += input_entries * 2;
+    }
+
+    let grid_points = vec![grid; input_channels as usize];
+    let entries = clut_entries(&grid_points, output_channels)?;
+    let values = read_u16s(offset, entries)?;
+    offset += entries * 2;
+
+    let mut b_curves = Vec::with_capacity(output_channels as usize);
+    for _ in 0..output_channels {
+        b_curves.push(ICCTagData::Curve(CurveData::Table(read_u16s(offset, output_entries)?)));
+        offset += output_entries * 2;
+    }
+
+    Some(LutData {
+        lut_type: "mft2".to_string(),
+        input_channels,
+        output_channels,
+        matrix: Some(matrix.into_iter().chain([0.0; 3]).collect()),
+        a_curves,
+        clut: Some(ClutData { grid_points, values }),
+        m_curves: Vec::new(),
+        b_curves,
+    })
+}
+
+/// Reads `count` consecutive `curv`/`para` elements, each padded to a 4-byte boundary.
+fn parse_curve_sequence(data: &[u8], mut offset: usize, count: u8) -> Option<Vec<ICCTagData>> {
+    let mut curves = Vec::with_capacity(count as usize);
+
+    for _ in 0..count {
+        let element = data.get(offset..)?;
+        let (curve, length) = match element.get(0..4)? {
+            b"curv" => {
+                let entries = read_u32_be(element, 8)? as usize;
+                (parse_curve_tag(element), 12 + entries.checked_mul(2)?)
+            }
+            b"para" => {
+                let params = match read_u16_be(element, 8)? {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return None,
+                };
+                (parse_para_tag(element), 12 + params * 4)
+            }
+            _ => return None,
+        };
+
+        curves.push(curve);
+        offset = offset.checked_add(length.next_multiple_of(4))?;
+    }
+
+    Some(curves)
+}
+
+fn parse_lut_ab_tag(data: &[u8], lut_type: &str) -> Option<LutData> {
+    let input_channels = read_u8(data, 8)?;
+    let output_channels = read_u8(data, 9)?;
+
+    let b_offset = read_u32_be(data, 12)? as usize;
+    let matrix_offset = read_u32_be(data, 16)? as usize;
+    let m_offset = read_u32_be(data, 20)? as usize;
+    let clut_offset = read_u32_be(data, 24)? as usize;
+    let a_offset = read_u32_be(data, 28)? as usize;
+
+    // A curves are on the device side, B curves on the PCS side
+    let (a_count, b_count) = if lut_type == "mAB " {
+        (input_channels, output_channels)
+    } else {
+        (output_channels, input_channels)
+    };
+
+    let curves_at = |offset: usize, count: u8| -> Option<Vec<ICCTagData>> {
+        if offset == 0 {
+            Some(Vec::new())
+        } else {
+            parse_curve_sequence(data, offset, count)
+        }
+    };
+
+    let a_curves = curves_at(a_offset, a_count)?;
+    let m_curves = curves_at(m_offset, 3)?;
+    let b_curves = curves_at(b_offset, b_count)?;
+
+    let matrix = if matrix_offset == 0 {
+        None
+    } else {
+        Some(read_matrix(data, matrix_offset, 12)?)
+    };
+
+    let clut = if clut_offset == 0 {
+        None
+    } else {
+        let grid_points = data.get(clut_offset..clut_offset + input_channels as usize)?.to_vec();
+        let precision = read_u8(data, clut_offset + 16)?;
+        let entries = clut_entries(&grid_points, output_channels)?;
+        let start = clut_offset + 20;
+
+        let values = match precision {
+            1 => data.get(start..start.checked_add(entries)?)?.iter().map(|&v| v as u16 * 257).collect(),
+            2 => {
+                let bytes = data.get(start..start.checked_add(entries.checked_mul(2)?)?)?;
+                bytes.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect()
+            }
+            _ => return None,
+        };
+
+        Some(ClutData { grid_points, values })
+    };
+
+    Some(LutData {
+        lut_type: lut_type.to_string(),
+        input_channels,
+        output_channels,
+        matrix,
+        a_curves,
+        clut,
+        m_curves,
+        b_curves,
+    })
+}
+
+fn parse_lut_tag(type_sig: &str, data: &[u8]) -> ICCTagData {
+    let lut = match type_sig {
+        "mft1" => parse_lut8_tag(data),
+        "mft2" => parse_lut16_tag(data),
+        _ => parse_lut_ab_tag(data, type_sig),
+    };
+
+    match lut {
+        Some(lut) => ICCTagData::Lut(lut),
+        None => ICCTagData::Unknown(data.to_vec()),
+    }
+}
+
 fn parse_tag(type_sig: &str, data: &[u8]) -> ICCTagData {
     match type_sig.trim_end_matches('\0') {
         "text" => parse_text_tag(data),
@@ -804,6 +1037,7 @@ fn parse_tag(type_sig: &str, data: &[u8]) -> ICCTagData {
         "fl64" => parse_fl64_tag(data),
         "cicp" => parse_cicp_tag(data),
         "desc" => parse_desc_tag(data),
+        "mft1" | "mft2" | "mAB " | "mBA " => parse_lut_tag(type_sig, data),
         _ => ICCTagData::Unknown(data.to_vec()),
     }
 }
@@ -1,5 +1,9 @@
 use crate::log_warn;
 use crate::utils::channel_simd;
+use crate::utils::color_transform::{ColorTarget, MatrixTrcTransform};
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::metadata::{ImageMetadata, Orientation};
+use crate::utils::report::{DecodeReport, DecodeWarning, WarningKind};
 use serde::Serialize;
 
 fn drop_transparency_channel(pixels: Vec<u8>) -> Vec<u8> {
@@ -53,6 +57,8 @@ pub enum ImageFormat {
     Jbig1,
     Ico,
     Cur,
+    /// A format handled by a decoder registered with [`register_decoder`](crate::register_decoder).
+    Custom(&'static str),
     Unknown,
 }
 
@@ -77,6 +83,112 @@ pub enum PixelFormat {
     LA64F,
 }
 
+impl PixelFormat {
+    /// Returns the number of channels, including alpha.
+    pub fn channels(&self) -> usize {
+        match self {
+            PixelFormat::L1 | PixelFormat::L8 | PixelFormat::L16 | PixelFormat::L32F | PixelFormat::L64F => 1,
+            PixelFormat::LA8 | PixelFormat::LA16 | PixelFormat::LA32F | PixelFormat::LA64F => 2,
+            PixelFormat::RGB8 | PixelFormat::RGB16 | PixelFormat::RGB32F | PixelFormat::RGB64F => 3,
+            PixelFormat::RGBA8 | PixelFormat::RGBA16 | PixelFormat::RGBA32F | PixelFormat::RGBA64F => 4,
+        }
+    }
+
+    /// Returns the number of bytes a pixel takes in [`PixelData`]. `L1` pixels take one byte each.
+    pub fn bytes_per_pixel(&self) -> usize {
+        let bytes_per_channel = match self {
+            PixelFormat::L1 | PixelFormat::L8 | PixelFormat::LA8 | PixelFormat::RGB8 | PixelFormat::RGBA8 => 1,
+            PixelFormat::L16 | PixelFormat::LA16 | PixelFormat::RGB16 | PixelFormat::RGBA16 => 2,
+            PixelFormat::L32F | PixelFormat::LA32F | PixelFormat::RGB32F | PixelFormat::RGBA32F => 4,
+            PixelFormat::L64F | PixelFormat::LA64F | PixelFormat::RGB64F | PixelFormat::RGBA64F => 8,
+        };
+
+        self.channels() * bytes_per_channel
+    }
+}
+
+/// Basic image properties read from the file headers, without decoding any pixel data.
+///
+/// Returned by [`Vexel::probe`](crate::Vexel::probe). The values describe what a subsequent
+/// [`decode`](crate::Vexel::decode) call is expected to produce. For the few cases where the
+/// output format depends on the pixel data itself (for example, 32-bit BMPs that have no
+/// alpha mask but store non-zero alpha bytes), `pixel_format` is the best guess that can
+/// be made from the headers alone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageHeader {
+    /// Width of the image in pixels. For multi-frame images this is the canvas width.
+    pub width: u32,
+    /// Height of the image in pixels. For multi-frame images this is the canvas height.
+    pub height: u32,
+    /// Pixel format of the first frame, as it would be returned by the decoder.
+    pub pixel_format: PixelFormat,
+    /// Number of frames (animation steps, pages, or icon entries) in the file.
+    pub frame_count: u32,
+    /// `true` if the frames form an animation (GIF, APNG) rather than independent pages.
+    pub is_animated: bool,
+}
+
+/// A rectangle of pixels, used for region decoding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub(crate) fn new(x: u32, y: u32, width: u32, height: u32) -> Region {
+        Region { x, y, width, height }
+    }
+
+    pub(crate) fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub(crate) fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// Checks that the region is not empty and lies within an image of the given size.
+    pub(crate) fn check_bounds(&self, width: u32, height: u32) -> VexelResult<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(VexelError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        let fits = |start: u32, length: u32, size: u32| start.checked_add(length).is_some_and(|end| end <= size);
+        if !fits(self.x, self.width, width) || !fits(self.y, self.height, height) {
+            return Err(VexelError::Custom(format!(
+                "Region {}x{} at ({}, {}) is outside of the {}x{} image",
+                self.width, self.height, self.x, self.y, width, height
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the part of the region that lies within an image of the given size,
+    /// or `None` if they don't overlap.
+    pub(crate) fn clamped(&self, width: u32, height: u32) -> Option<Region> {
+        let right = self.x.saturating_add(self.width).min(width);
+        let bottom = self.y.saturating_add(self.height).min(height);
+
+        if self.x >= right || self.y >= bottom {
+            return None;
+        }
+
+        Some(Region::new(self.x, self.y, right - self.x, bottom - self.y))
+    }
+
+    /// Returns the region relative to `origin`, which must contain it.
+    pub(crate) fn relative_to(&self, origin: Region) -> Region {
+        Region::new(self.x - origin.x, self.y - origin.y, self.width, self.height)
+    }
+}
+
 /// A decoded image, consisting of one or more frames.
 ///
 /// Single-frame formats (JPEG, PNG, BMP, …) always produce exactly one frame.
@@ -92,15 +204,21 @@ pub struct Image {
     height: u32,
     pixel_format: PixelFormat,
     frames: Vec<ImageFrame>,
+    metadata: ImageMetadata,
+    report: DecodeReport,
 }
 
 impl Image {
-    pub(crate) fn new(width: u32, height: u32, pixel_format: PixelFormat, frames: Vec<ImageFrame>) -> Image {
+    /// Creates an image from its frames. `width` and `height` are the canvas size, `pixel_format`
+    /// is the format of the frames.
+    pub fn new(width: u32, height: u32, pixel_format: PixelFormat, frames: Vec<ImageFrame>) -> Image {
         Image {
             width,
             height,
             pixel_format,
             frames,
+            metadata: ImageMetadata::default(),
+            report: DecodeReport::default(),
         }
     }
 
@@ -110,10 +228,13 @@ impl Image {
             height: frame.height(),
             pixel_format: frame.pixel_format(),
             frames: Vec::from([frame]),
+            metadata: ImageMetadata::default(),
+            report: DecodeReport::default(),
         }
     }
 
-    pub(crate) fn from_pixels(width: u32, height: u32, pixels: PixelData) -> Image {
+    /// Creates a single-frame image from `width` x `height` pixels.
+    pub fn from_pixels(width: u32, height: u32, pixels: PixelData) -> Image {
         let frame = ImageFrame::new(width, height, pixels, 0);
         Image::from_frame(frame)
     }
@@ -167,6 +288,144 @@ impl Image {
         &self.frames
     }
 
+    /// Returns the metadata found in the file, such as the ICC profile, EXIF data and text entries.
+    pub fn metadata(&self) -> &ImageMetadata {
+        &self.metadata
+    }
+
+    /// Returns the metadata for editing, such as before encoding the image.
+    pub fn metadata_mut(&mut self) -> &mut ImageMetadata {
+        &mut self.metadata
+    }
+
+    pub(crate) fn set_metadata(&mut self, metadata: ImageMetadata) {
+        self.metadata = metadata;
+    }
+
+    /// Returns the problems the decoder recovered from, such as truncated data or pixels that
+    /// had to be filled in. The report is empty for images decoded without any warnings.
+    pub fn report(&self) -> &DecodeReport {
+        &self.report
+    }
+
+    pub(crate) fn set_report(&mut self, report: DecodeReport) {
+        self.report = report;
+    }
+
+    /// Returns the number of pixels in all frames.
+    pub(crate) fn total_pixels(&self) -> u64 {
+        self.frames.iter().map(|f| f.width as u64 * f.height as u64).sum()
+    }
+
+    /// Pads or truncates the pixel data of every frame to the size of the frame.
+    pub(crate) fn correct_pixels(&mut self) {
+        for frame in &mut self.frames {
+            frame.pixels.correct_pixels(frame.width, frame.height);
+        }
+    }
+
+    pub(crate) fn into_frames(self) -> Vec<ImageFrame> {
+        self.frames
+    }
+
+    /// Crops all frames to `region`. Frames smaller than the image are cropped to the part of
+    /// the region they cover, frames outside of the region are dropped.
+    pub(crate) fn cropped(mut self, region: Region) -> Image {
+        self.frames = self
+            .frames
+            .into_iter()
+            .filter_map(|mut frame| {
+                let frame_region = region.clamped(frame.width, frame.height)?;
+                frame.crop(frame_region);
+                Some(frame)
+            })
+            .collect();
+
+        self.width = region.width;
+        self.height = region.height;
+
+        self
+    }
+
+    /// Appends `rows` rows of `pixels` to the bottom of the first frame, for images that are
+    /// decoded while they are being received.
+    pub(crate) fn append_rows(&mut self, rows: u32, pixels: PixelData) {
+        if let Some(frame) = self.frames.first_mut() {
+            frame.pixels.append(pixels);
+            frame.height += rows;
+            self.height = frame.height;
+        }
+    }
+
+    /// Rotates and mirrors all frames so the image is displayed upright, according to
+    /// the orientation reported in [`ImageMetadata::orientation`].
+    ///
+    /// When the orientation swaps the axes, [`width`](Self::width) and [`height`](Self::height)
+    /// are swapped as well. The reported orientation is reset to [`Orientation::TopLeft`]
+    /// afterwards, so calling this more than once has no further effect.
+    pub fn apply_orientation(&mut self) {
+        let orientation = self.metadata.orientation;
+        if orientation == Orientation::TopLeft {
+            return;
+        }
+
+        for frame in &mut self.frames {
+            frame.apply_orientation(orientation);
+        }
+
+        if orientation.swaps_dimensions() {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+
+        self.metadata.orientation = Orientation::TopLeft;
+    }
+
+    /// Converts all frames from the colour space of the embedded ICC profile to `target`.
+    ///
+    /// Matrix/TRC profiles (RGB and gray display profiles) are supported. CMYK images are
+    /// already converted to RGB while decoding, so only the profile is removed from them; set
+    /// the target with [`Vexel::set_color_target`](crate::Vexel::set_color_target) to apply it.
+    /// Images without a profile, or with a profile that cannot be used, are left unchanged. With
+    /// [`ColorTarget::LinearSrgb`] integer pixel data is converted to 32-bit float.
+    ///
+    /// After a successful conversion the profile is removed from [`metadata`](Self::metadata),
+    /// since it no longer describes the pixels.
+    pub fn apply_color_profile(&mut self, target: ColorTarget) {
+        if target == ColorTarget::Original {
+            return;
+        }
+
+        let Some(profile) = &self.metadata.icc_profile else {
+            return;
+        };
+
+        // Decoders already convert CMYK pixels to sRGB with the profile
+        let transform = if profile.header.color_space.trim() == "CMYK" {
+            (target == ColorTarget::LinearSrgb).then(MatrixTrcTransform::srgb)
+        } else {
+            let transform = MatrixTrcTransform::new(profile);
+            if transform.is_none() {
+                log_warn!("Colour conversion is not supported for this {} ICC profile", profile.header.color_space);
+                return;
+            }
+            transform
+        };
+
+        if let Some(transform) = transform {
+            for frame in &mut self.frames {
+                let pixels = std::mem::replace(&mut frame.pixels, PixelData::L8(Vec::new()));
+                frame.pixels = transform.apply(pixels, target);
+            }
+
+            if let Some(frame) = self.frames.first() {
+                self.pixel_format = frame.pixel_format();
+            }
+        }
+
+        self.metadata.icc_data = None;
+        self.metadata.icc_profile = None;
+    }
+
     /// Converts the image to RGB8 format, consuming the original image.
     ///
     /// This method converts all frames to RGB8 format, while [`as_rgb8`](Self::as_rgb8) returns
@@ -175,7 +434,10 @@ impl Image {
     pub fn into_rgb8(mut self) -> Image {
         let new_frames = self.frames.drain(..).map(|frame| frame.into_rgb8()).collect();
 
-        Image::new(self.width, self.height, PixelFormat::RGB8, new_frames)
+        let mut image = Image::new(self.width, self.height, PixelFormat::RGB8, new_frames);
+        image.metadata = std::mem::take(&mut self.metadata);
+        image.report = std::mem::take(&mut self.report);
+        image
     }
 
     /// Converts the image to RGBA8 format, consuming the original image.
@@ -186,7 +448,10 @@ impl Image {
     pub fn into_rgba8(mut self) -> Image {
         let new_frames = self.frames.drain(..).map(|frame| frame.into_rgba8()).collect();
 
-        Image::new(self.width, self.height, PixelFormat::RGBA8, new_frames)
+        let mut image = Image::new(self.width, self.height, PixelFormat::RGBA8, new_frames);
+        image.metadata = std::mem::take(&mut self.metadata);
+        image.report = std::mem::take(&mut self.report);
+        image
     }
 
     /// Returns the first frame's pixels as a vector of RGB8 bytes.
@@ -225,7 +490,8 @@ pub struct ImageFrame {
 }
 
 impl ImageFrame {
-    pub(crate) fn new(width: u32, height: u32, pixels: PixelData, delay: u32) -> ImageFrame {
+    /// Creates a frame from `width` x `height` pixels, shown for `delay` milliseconds in an animation.
+    pub fn new(width: u32, height: u32, pixels: PixelData, delay: u32) -> ImageFrame {
         ImageFrame {
             width,
             height,
@@ -267,6 +533,26 @@ impl ImageFrame {
         self.pixels.pixel_format()
     }
 
+    fn apply_orientation(&mut self, orientation: Orientation) {
+        let pixels = std::mem::replace(&mut self.pixels, PixelData::L8(Vec::new()));
+        self.pixels = pixels.oriented(self.width, self.height, orientation);
+
+        if orientation.swaps_dimensions() {
+            std::mem::swap(&mut self.width, &mut self.height);
+        }
+    }
+
+    pub(crate) fn crop(&mut self, region: Region) {
+        let pixels = std::mem::replace(&mut self.pixels, PixelData::L8(Vec::new()));
+        self.pixels = pixels.cropped(self.width, self.height, region);
+        self.width = region.width;
+        self.height = region.height;
+    }
+
+    pub(crate) fn into_pixels(self) -> PixelData {
+        self.pixels
+    }
+
     /// Returns `true` if this frame's pixel format includes an alpha channel.
     pub fn has_alpha(&self) -> bool {
         match self.pixels {
@@ -598,6 +884,136 @@ impl PixelData {
         }
     }
 
+    /// Rotates and mirrors the pixels of a `width` x `height` image according to `orientation`.
+    /// If the orientation swaps the axes, the result is `height` pixels wide.
+    pub(crate) fn oriented(mut self, width: u32, height: u32, orientation: Orientation) -> PixelData {
+        if orientation == Orientation::TopLeft {
+            return self;
+        }
+
+        self.correct_pixels(width, height);
+
+        let (width, height) = (width as usize, height as usize);
+
+        return match self {
+            PixelData::RGB8(pixels) => PixelData::RGB8(orient(&pixels, width, height, 3, orientation)),
+            PixelData::RGBA8(pixels) => PixelData::RGBA8(orient(&pixels, width, height, 4, orientation)),
+            PixelData::RGB16(pixels) => PixelData::RGB16(orient(&pixels, width, height, 3, orientation)),
+            PixelData::RGBA16(pixels) => PixelData::RGBA16(orient(&pixels, width, height, 4, orientation)),
+            PixelData::RGB32F(pixels) => PixelData::RGB32F(orient(&pixels, width, height, 3, orientation)),
+            PixelData::RGBA32F(pixels) => PixelData::RGBA32F(orient(&pixels, width, height, 4, orientation)),
+            PixelData::RGB64F(pixels) => PixelData::RGB64F(orient(&pixels, width, height, 3, orientation)),
+            PixelData::RGBA64F(pixels) => PixelData::RGBA64F(orient(&pixels, width, height, 4, orientation)),
+            PixelData::L1(pixels) => PixelData::L1(orient(&pixels, width, height, 1, orientation)),
+            PixelData::L8(pixels) => PixelData::L8(orient(&pixels, width, height, 1, orientation)),
+            PixelData::L16(pixels) => PixelData::L16(orient(&pixels, width, height, 1, orientation)),
+            PixelData::L32F(pixels) => PixelData::L32F(orient(&pixels, width, height, 1, orientation)),
+            PixelData::L64F(pixels) => PixelData::L64F(orient(&pixels, width, height, 1, orientation)),
+            PixelData::LA8(pixels) => PixelData::LA8(orient(&pixels, width, height, 2, orientation)),
+            PixelData::LA16(pixels) => PixelData::LA16(orient(&pixels, width, height, 2, orientation)),
+            PixelData::LA32F(pixels) => PixelData::LA32F(orient(&pixels, width, height, 2, orientation)),
+            PixelData::LA64F(pixels) => PixelData::LA64F(orient(&pixels, width, height, 2, orientation)),
+        };
+
+        fn orient<T: Copy>(src: &[T], width: usize, height: usize, channels: usize, orientation: Orientation) -> Vec<T> {
+            let (out_width, out_height) = if orientation.swaps_dimensions() {
+                (height, width)
+            } else {
+                (width, height)
+            };
+
+            let mut out = Vec::with_capacity(src.len());
+
+            for y in 0..out_height {
+                for x in 0..out_width {
+                    // Position of the output pixel in the stored image
+                    let (src_x, src_y) = match orientation {
+                        Orientation::TopLeft => (x, y),
+                        Orientation::TopRight => (width - 1 - x, y),
+                        Orientation::BottomRight => (width - 1 - x, height - 1 - y),
+                        Orientation::BottomLeft => (x, height - 1 - y),
+                        Orientation::LeftTop => (y, x),
+                        Orientation::RightTop => (y, height - 1 - x),
+                        Orientation::RightBottom => (width - 1 - y, height - 1 - x),
+                        Orientation::LeftBottom => (width - 1 - y, x),
+                    };
+
+                    let offset = (src_y * width + src_x) * channels;
+                    out.extend_from_slice(&src[offset..offset + channels]);
+                }
+            }
+
+            out
+        }
+    }
+
+    /// Returns the pixels inside `region`, which must lie within the `width` x `height` image.
+    /// Appends the samples of `other`. Samples of a different format are ignored.
+    pub(crate) fn append(&mut self, other: PixelData) {
+        match (self, other) {
+            (PixelData::RGB8(pixels), PixelData::RGB8(other)) => pixels.extend(other),
+            (PixelData::RGBA8(pixels), PixelData::RGBA8(other)) => pixels.extend(other),
+            (PixelData::RGB16(pixels), PixelData::RGB16(other)) => pixels.extend(other),
+            (PixelData::RGBA16(pixels), PixelData::RGBA16(other)) => pixels.extend(other),
+            (PixelData::RGB32F(pixels), PixelData::RGB32F(other)) => pixels.extend(other),
+            (PixelData::RGBA32F(pixels), PixelData::RGBA32F(other)) => pixels.extend(other),
+            (PixelData::RGB64F(pixels), PixelData::RGB64F(other)) => pixels.extend(other),
+            (PixelData::RGBA64F(pixels), PixelData::RGBA64F(other)) => pixels.extend(other),
+            (PixelData::L1(pixels), PixelData::L1(other)) => pixels.extend(other),
+            (PixelData::L8(pixels), PixelData::L8(other)) => pixels.extend(other),
+            (PixelData::L16(pixels), PixelData::L16(other)) => pixels.extend(other),
+            (PixelData::LA8(pixels), PixelData::LA8(other)) => pixels.extend(other),
+            (PixelData::LA16(pixels), PixelData::LA16(other)) => pixels.extend(other),
+            (PixelData::L32F(pixels), PixelData::L32F(other)) => pixels.extend(other),
+            (PixelData::LA32F(pixels), PixelData::LA32F(other)) => pixels.extend(other),
+            (PixelData::L64F(pixels), PixelData::L64F(other)) => pixels.extend(other),
+            (PixelData::LA64F(pixels), PixelData::LA64F(other)) => pixels.extend(other),
+            _ => {}
+        }
+    }
+
+    pub(crate) fn cropped(mut self, width: u32, height: u32, region: Region) -> PixelData {
+        if region == Region::new(0, 0, width, height) {
+            return self;
+        }
+
+        self.correct_pixels(width, height);
+
+        let width = width as usize;
+
+        return match self {
+            PixelData::RGB8(pixels) => PixelData::RGB8(crop(&pixels, width, 3, region)),
+            PixelData::RGBA8(pixels) => PixelData::RGBA8(crop(&pixels, width, 4, region)),
+            PixelData::RGB16(pixels) => PixelData::RGB16(crop(&pixels, width, 3, region)),
+            PixelData::RGBA16(pixels) => PixelData::RGBA16(crop(&pixels, width, 4, region)),
+            PixelData::RGB32F(pixels) => PixelData::RGB32F(crop(&pixels, width, 3, region)),
+            PixelData::RGBA32F(pixels) => PixelData::RGBA32F(crop(&pixels, width, 4, region)),
+            PixelData::RGB64F(pixels) => PixelData::RGB64F(crop(&pixels, width, 3, region)),
+            PixelData::RGBA64F(pixels) => PixelData::RGBA64F(crop(&pixels, width, 4, region)),
+            PixelData::L1(pixels) => PixelData::L1(crop(&pixels, width, 1, region)),
+            PixelData::L8(pixels) => PixelData::L8(crop(&pixels, width, 1, region)),
+            PixelData::L16(pixels) => PixelData::L16(crop(&pixels, width, 1, region)),
+            PixelData::L32F(pixels) => PixelData::L32F(crop(&pixels, width, 1, region)),
+            PixelData::L64F(pixels) => PixelData::L64F(crop(&pixels, width, 1, region)),
+            PixelData::LA8(pixels) => PixelData::LA8(crop(&pixels, width, 2, region)),
+            PixelData::LA16(pixels) => PixelData::LA16(crop(&pixels, width, 2, region)),
+            PixelData::LA32F(pixels) => PixelData::LA32F(crop(&pixels, width, 2, region)),
+            PixelData::LA64F(pixels) => PixelData::LA64F(crop(&pixels, width, 2, region)),
+        };
+
+        fn crop<T: Copy>(src: &[T], width: usize, channels: usize, region: Region) -> Vec<T> {
+            let row_length = region.width as usize * channels;
+            let mut out = Vec::with_capacity(row_length * region.height as usize);
+
+            for y in region.y as usize..region.bottom() as usize {
+                let offset = (y * width + region.x as usize) * channels;
+                out.extend_from_slice(&src[offset..offset + row_length]);
+            }
+
+            out
+        }
+    }
+
     // Used as a last resort to correct the number of pixels in the image
     // in case something went wrong during decoding
     pub(crate) fn correct_pixels(&mut self, width: u32, height: u32) -> () {
@@ -637,20 +1053,19 @@ impl PixelData {
                 return;
             }
 
+            let received = current_len / components_per_pixel;
+            let expected = expected_len / components_per_pixel;
+
             if current_len > expected_len {
-                log_warn!(
-                    "Truncating excess pixels. Received from decoder: {}, Expected: {}",
-                    current_len / components_per_pixel,
-                    expected_len / components_per_pixel
-                );
+                let message = format!("Truncating excess pixels. Received from decoder: {}, Expected: {}", received, expected);
+                DecodeWarning::new(WarningKind::PixelCountMismatch, message).report();
 
                 pixels.truncate(expected_len);
             } else {
-                log_warn!(
-                    "Adding missing pixels. Received from decoder: {}, Expected: {}",
-                    current_len / components_per_pixel,
-                    expected_len / components_per_pixel
-                );
+                let message = format!("Adding missing pixels. Received from decoder: {}, Expected: {}", received, expected);
+                DecodeWarning::new(WarningKind::PixelCountMismatch, message)
+                    .with_synthesized_pixels((expected - received) as u64)
+                    .report();
 
                 let default_pixel = vec![T::default(); components_per_pixel];
                 while pixels.len() < expected_len {
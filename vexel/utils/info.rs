@@ -8,13 +8,14 @@ use crate::decoders::jpeg_ls::types::JpegLsSectionInfo;
 use crate::decoders::netpbm::NetpbmSectionInfo;
 use crate::decoders::png::PngChunkInfo;
 use crate::decoders::tga::types::TgaSectionInfo;
+use crate::decoders::tiff::types::TiffSectionInfo;
 use crate::utils::exif::{ExifIfd, ExifValue};
 use serde::Serialize;
 use std::fmt;
 use tsify::Tsify;
 use wasm_bindgen::prelude::wasm_bindgen;
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub enum ImageInfo {
     Jpeg(JpegInfo),
@@ -27,68 +28,86 @@ pub enum ImageInfo {
     Jbig1(Jbig1Info),
     Ico(IcoInfo),
     Tga(TgaInfo),
+    Tiff(TiffInfo),
+    Custom(CustomInfo),
+    Unknown,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct JpegInfo {
     pub sections: Vec<JpegSegmentInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct PngInfo {
     pub sections: Vec<PngChunkInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct BmpInfo {
     pub sections: Vec<BmpSectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct GifInfo {
     pub sections: Vec<GifSectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct NetpbmInfo {
     pub sections: Vec<NetpbmSectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct HdrInfo {
     pub sections: Vec<HdrSectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct Jbig1Info {
     pub sections: Vec<Jbig1SectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct JpegLsInfo {
     pub sections: Vec<JpegLsSectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct IcoInfo {
     pub sections: Vec<IcoSectionInfo>,
 }
 
-#[derive(Debug, Serialize, Tsify)]
+#[derive(Debug, Clone, Serialize, Tsify)]
 #[tsify(into_wasm_abi)]
 pub struct TgaInfo {
     pub sections: Vec<TgaSectionInfo>,
 }
 
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct TiffInfo {
+    pub sections: Vec<TiffSectionInfo>,
+}
+
+/// Information returned by a decoder registered with [`register_decoder`](crate::register_decoder),
+/// as a list of named values.
+#[derive(Debug, Clone, Serialize, Tsify)]
+#[tsify(into_wasm_abi)]
+pub struct CustomInfo {
+    pub format: String,
+    pub entries: Vec<(String, String)>,
+}
+
 impl fmt::Display for ImageInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -102,10 +121,26 @@ impl fmt::Display for ImageInfo {
             ImageInfo::Jbig1(info) => write!(f, "{}", info),
             ImageInfo::Ico(info) => write!(f, "{}", info),
             ImageInfo::Tga(info) => write!(f, "{}", info),
+            ImageInfo::Tiff(info) => write!(f, "{}", info),
+            ImageInfo::Custom(info) => write!(f, "{}", info),
+            ImageInfo::Unknown => writeln!(f, "Unknown format"),
         }
     }
 }
 
+impl fmt::Display for CustomInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} Information", self.format)?;
+        writeln!(f, "=====================")?;
+
+        for (name, value) in &self.entries {
+            writeln!(f, "{}: {}", name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl fmt::Display for PngInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "PNG Information")?;
@@ -287,6 +322,8 @@ fn fmt_exif_value(value: &ExifValue) -> String {
             .join(", "),
         ExifValue::Float(v) => v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "),
         ExifValue::Double(v) => v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "),
+        ExifValue::Long8(v) => v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "),
+        ExifValue::SLong8(v) => v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", "),
         ExifValue::Byte(v) => format!("{} bytes", v.len()),
         ExifValue::Undefined(v) => format!("{} bytes (undefined)", v.len()),
     }
@@ -1173,3 +1210,129 @@ impl fmt::Display for TgaInfo {
         Ok(())
     }
 }
+
+fn fmt_tiff_field_type(field_type: u16) -> String {
+    let name = match field_type {
+        1 => "BYTE",
+        2 => "ASCII",
+        3 => "SHORT",
+        4 => "LONG",
+        5 => "RATIONAL",
+        6 => "SBYTE",
+        7 => "UNDEFINED",
+        8 => "SSHORT",
+        9 => "SLONG",
+        10 => "SRATIONAL",
+        11 => "FLOAT",
+        12 => "DOUBLE",
+        13 => "IFD",
+        16 => "LONG8",
+        17 => "SLONG8",
+        18 => "IFD8",
+        _ => return format!("type {}", field_type),
+    };
+    name.to_string()
+}
+
+fn fmt_tiff_value(value: &ExifValue) -> String {
+    const MAX_VALUES: usize = 16;
+
+    fn limited<T: fmt::Display>(values: &[T]) -> String {
+        let shown: Vec<String> = values.iter().take(MAX_VALUES).map(|x| x.to_string()).collect();
+        if values.len() > MAX_VALUES {
+            format!("{}, ... ({} values)", shown.join(", "), values.len())
+        } else {
+            shown.join(", ")
+        }
+    }
+
+    match value {
+        ExifValue::Short(v) => limited(v),
+        ExifValue::Long(v) => limited(v),
+        ExifValue::SShort(v) => limited(v),
+        ExifValue::SLong(v) => limited(v),
+        ExifValue::SByte(v) => limited(v),
+        ExifValue::Float(v) => limited(v),
+        ExifValue::Double(v) => limited(v),
+        ExifValue::Long8(v) => limited(v),
+        ExifValue::SLong8(v) => limited(v),
+        ExifValue::Rational(v) => limited(&v.iter().map(|(n, d)| format!("{}/{}", n, d)).collect::<Vec<_>>()),
+        ExifValue::SRational(v) => limited(&v.iter().map(|(n, d)| format!("{}/{}", n, d)).collect::<Vec<_>>()),
+        ExifValue::Ascii(_) | ExifValue::Byte(_) | ExifValue::Undefined(_) => fmt_exif_value(value),
+    }
+}
+
+fn fmt_tiff_ifd(f: &mut fmt::Formatter<'_>, ifd: &crate::decoders::tiff::types::TiffIfdData) -> fmt::Result {
+    writeln!(f, "  Entries: {}", ifd.entries.len())?;
+    for entry in &ifd.entries {
+        let label = entry.tag_name.as_deref()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("0x{:04X}", entry.tag));
+        writeln!(f, "    {} ({}) {} x{}: {}",
+            label, entry.tag, fmt_tiff_field_type(entry.field_type), entry.count, fmt_tiff_value(&entry.value))?;
+    }
+
+    if let Some(layout) = &ifd.layout {
+        let (kind, unit) = if layout.tiled { ("Tiles", "Tile") } else { ("Strips", "Strip") };
+        writeln!(f, "  {}: {} ({}x{} per plane, {} plane(s))",
+            kind, layout.chunk_count, layout.chunks_across, layout.chunks_down, layout.planes)?;
+        writeln!(f, "  {} size: {}x{}", unit, layout.chunk_width, layout.chunk_length)?;
+        writeln!(f, "  Compressed data: {} bytes", layout.total_bytes)?;
+    }
+
+    writeln!(f, "  Next IFD offset: 0x{:08X}", ifd.next_ifd_offset)?;
+    Ok(())
+}
+
+impl fmt::Display for TiffInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use crate::decoders::tiff::types::TiffSectionData;
+
+        writeln!(f, "TIFF Image Information")?;
+        writeln!(f, "=====================")?;
+        writeln!(f, "Total sections: {}", self.sections.len())?;
+        writeln!(f)?;
+
+        for section in &self.sections {
+            match &section.data {
+                TiffSectionData::FileHeader(h) => {
+                    writeln!(f, "Offset 0x{:08X}  File Header", section.start_offset)?;
+                    writeln!(f, "  Byte order: {:?}", h.byte_order)?;
+                    writeln!(f, "  Magic: {}", h.magic)?;
+                    writeln!(f, "  First IFD offset: 0x{:08X}", h.first_ifd_offset)?;
+                }
+                TiffSectionData::Ifd(ifd) => {
+                    writeln!(f, "Offset 0x{:08X}  IFD #{}", section.start_offset, ifd.index)?;
+                    fmt_tiff_ifd(f, ifd)?;
+                }
+                TiffSectionData::SubIfd(ifd) => {
+                    writeln!(f, "Offset 0x{:08X}  SubIFD of IFD #{}", section.start_offset, ifd.index)?;
+                    fmt_tiff_ifd(f, ifd)?;
+                }
+                TiffSectionData::ExifIfd(ifd) => {
+                    writeln!(f, "Offset 0x{:08X}  EXIF IFD", section.start_offset)?;
+                    fmt_exif_ifd(f, "ExifIFD", ifd)?;
+                }
+                TiffSectionData::GpsIfd(ifd) => {
+                    writeln!(f, "Offset 0x{:08X}  GPS IFD", section.start_offset)?;
+                    fmt_exif_ifd(f, "GPSIFD", ifd)?;
+                }
+                TiffSectionData::InteropIfd(ifd) => {
+                    writeln!(f, "Offset 0x{:08X}  Interoperability IFD", section.start_offset)?;
+                    fmt_exif_ifd(f, "InteroperabilityIFD", ifd)?;
+                }
+                TiffSectionData::IccProfile(profile) => {
+                    writeln!(f, "Offset 0x{:08X}  ICC Profile", section.start_offset)?;
+                    writeln!(f, "  Size: {} bytes", profile.header.size)?;
+                    writeln!(f, "  Class: {}", profile.header.profile_class)?;
+                    writeln!(f, "  Color space: {}", profile.header.color_space)?;
+                    writeln!(f, "  PCS: {}", profile.header.pcs)?;
+                    writeln!(f, "  Tags: {}", profile.tag_table.tag_count)?;
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
 use crate::utils::error::{VexelError, VexelResult};
+use std::time::Duration;
 
 /// Resource limits enforced during decoding.
 ///
 /// `None` for a field means that dimension or allocation is unconstrained.
-/// The default limits allow any image dimensions but cap total pixel memory at 512 MiB.
+/// The default limits allow any image dimensions and metadata size but cap total pixel
+/// memory at 512 MiB.
 /// Use [`no_limits`](Limits::no_limits) to remove all constraints, or construct
 /// the struct directly to set only the fields you care about.
 #[derive(Clone, Debug, PartialEq)]
@@ -12,10 +14,22 @@ pub struct Limits {
     pub max_image_width: Option<u32>,
     /// Maximum permitted image height in pixels. `None` means no limit.
     pub max_image_height: Option<u32>,
-    /// Maximum number of bytes that may be allocated for pixel data across
-    /// the entire decode operation. Tracked as a decreasing budget: each
+    /// Maximum number of bytes that may be allocated for pixel data and
+    /// intermediate buffers, such as inflated or tile data, across the entire
+    /// decode operation. Tracked as a decreasing budget: each
     /// allocation subtracts from it; `None` means no limit. Default is 512 MiB.
     pub max_alloc: Option<u64>,
+    /// Maximum number of frames or pages in an animation or multi-page image. `None` means no limit.
+    pub max_frames: Option<u32>,
+    /// Maximum number of bytes of ICC profiles, EXIF, XMP and text metadata across the entire
+    /// decode operation, counted after decompression. Tracked as a decreasing budget like
+    /// [`max_alloc`](Self::max_alloc); `None` means no limit, which is the default.
+    pub max_metadata_bytes: Option<u64>,
+    /// Maximum size of the input in bytes. `None` means no limit.
+    pub max_input_bytes: Option<u64>,
+    /// Maximum wall-clock time a decode may take. Decoders check it at the same points as the
+    /// [`cancellation`](crate::DecodeOptions::cancellation) token. `None` means no limit.
+    pub max_decode_duration: Option<Duration>,
 }
 
 impl Default for Limits {
@@ -24,6 +38,10 @@ impl Default for Limits {
             max_image_width: None,
             max_image_height: None,
             max_alloc: Some(512 * 1024 * 1024),
+            max_frames: None,
+            max_metadata_bytes: None,
+            max_input_bytes: None,
+            max_decode_duration: None,
         }
     }
 }
@@ -35,10 +53,15 @@ impl Limits {
             max_image_width: None,
             max_image_height: None,
             max_alloc: None,
+            max_frames: None,
+            max_metadata_bytes: None,
+            max_input_bytes: None,
+            max_decode_duration: None,
         }
     }
 
-    pub(crate) fn check_dimensions(&self, width: u32, height: u32) -> VexelResult<()> {
+    /// Returns [`VexelError::LimitExceeded`] if `width` or `height` exceeds the configured maximum.
+    pub fn check_dimensions(&self, width: u32, height: u32) -> VexelResult<()> {
         if let Some(max_w) = self.max_image_width {
             if width > max_w {
                 return Err(VexelError::LimitExceeded(format!(
@@ -58,6 +81,67 @@ impl Limits {
         Ok(())
     }
 
+    /// Returns [`VexelError::LimitExceeded`] if an image with `count` frames or pages exceeds the
+    /// configured maximum.
+    pub fn check_frames(&self, count: usize) -> VexelResult<()> {
+        if let Some(max_frames) = self.max_frames {
+            if count > max_frames as usize {
+                return Err(VexelError::LimitExceeded(format!(
+                    "frame count {} exceeds limit {}",
+                    count, max_frames
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns [`VexelError::LimitExceeded`] if an input of `bytes` bytes exceeds the configured maximum.
+    pub(crate) fn check_input_size(&self, bytes: u64) -> VexelResult<()> {
+        if let Some(max_input) = self.max_input_bytes {
+            if bytes > max_input {
+                return Err(VexelError::LimitExceeded(format!(
+                    "input size of {} bytes exceeds limit {}",
+                    bytes, max_input
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns [`VexelError::LimitExceeded`] if `bytes` of metadata don't fit in the remaining
+    /// metadata budget, without subtracting them.
+    pub(crate) fn check_metadata(&self, bytes: u64) -> VexelResult<()> {
+        if let Some(remaining) = self.max_metadata_bytes {
+            if remaining < bytes {
+                return Err(VexelError::LimitExceeded(format!(
+                    "{} bytes of metadata would exceed remaining budget of {} bytes",
+                    bytes, remaining
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Subtracts `bytes` of metadata from the metadata budget.
+    pub(crate) fn reserve_metadata(&mut self, bytes: u64) -> VexelResult<()> {
+        self.check_metadata(bytes)?;
+        if let Some(remaining) = self.max_metadata_bytes.as_mut() {
+            *remaining -= bytes;
+        }
+        Ok(())
+    }
+
+    /// Returns how many bytes of metadata can still be reserved, for bounding decompression.
+    pub(crate) fn metadata_remaining(&self) -> Option<u64> {
+        self.max_metadata_bytes
+    }
+
+    /// Returns how many bytes can still be reserved against [`max_alloc`](Self::max_alloc), for
+    /// bounding decompression.
+    pub(crate) fn alloc_remaining(&self) -> Option<u64> {
+        self.max_alloc
+    }
+
     pub(crate) fn reserve(&mut self, bytes: u64) -> VexelResult<()> {
         if let Some(remaining) = self.max_alloc.as_mut() {
             if *remaining < bytes {
@@ -89,6 +173,13 @@ impl Limits {
         self.reserve(size)
     }
 
+    pub(crate) fn free_buffer(&mut self, width: u32, height: u32, bytes_per_pixel: u8) {
+        let size = u64::from(width)
+            .saturating_mul(u64::from(height))
+            .saturating_mul(u64::from(bytes_per_pixel));
+        self.free(size)
+    }
+
     pub(crate) fn free(&mut self, bytes: u64) {
         if let Some(remaining) = self.max_alloc.as_mut() {
             *remaining = remaining.saturating_add(bytes);
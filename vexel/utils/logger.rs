@@ -1,17 +1,26 @@
+use crate::utils::image::ImageFormat;
+use crate::utils::progress;
+use crate::utils::report::{self, DecodeWarning, WarningKind};
 use std::fmt::Display;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU8, Ordering};
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "log")))]
 use std::time::SystemTime;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", not(feature = "log")))]
 use web_sys::console;
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", not(feature = "log")))]
 use js_sys::Date;
 
+// With the `log` feature, messages are printed by the logger installed for the `log` crate
+#[cfg(not(feature = "log"))]
 const RESET: &str = "\x1b[0m";
+#[cfg(not(feature = "log"))]
 const BLUE: &str = "\x1b[34m";
+#[cfg(not(feature = "log"))]
 const YELLOW: &str = "\x1b[33m";
+#[cfg(not(feature = "log"))]
 const RED: &str = "\x1b[31m";
 
 static MIN_LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Error as u8);
@@ -21,6 +30,11 @@ static MIN_LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Error as u8);
 /// Messages below `level` are silently discarded. The level is stored in a
 /// process-global atomic, so it applies to every [`Vexel`](crate::Vexel) instance.
 /// The default level is [`LogLevel::Error`].
+///
+/// The level only applies to messages printed by vexel itself. A
+/// [`DecodeOptions::log_sink`](crate::DecodeOptions::log_sink) receives every message, and with
+/// the `log` feature enabled messages are passed to the [`log`](https://docs.rs/log) crate, which
+/// filters them with its own level.
 pub fn set_log_level(level: LogLevel) {
     MIN_LOG_LEVEL.store(level as u8, Ordering::Relaxed);
 }
@@ -82,9 +96,29 @@ pub enum LogLevel {
     Error = 2,
 }
 
+/// A message logged while decoding, passed to a [`LogSink`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub message: String,
+    /// Format of the image being decoded, if the message was logged during a decode.
+    pub format: Option<ImageFormat>,
+    /// Byte offset in the file the message refers to, if known.
+    pub offset: Option<u64>,
+    /// Index of the frame the message refers to, if known.
+    pub frame: Option<usize>,
+}
+
+/// Callback that receives the messages logged by a decode, set in
+/// [`DecodeOptions::log_sink`](crate::DecodeOptions::log_sink).
+///
+/// Like a [`ProgressCallback`](crate::ProgressCallback), it can be called from worker threads.
+pub type LogSink = Arc<dyn Fn(&LogRecord) + Send + Sync>;
+
 pub struct Logger {}
 
 impl Logger {
+    #[cfg(not(feature = "log"))]
     fn get_timestamp() -> String {
         #[cfg(target_arch = "wasm32")]
         let (secs, millis) = {
@@ -107,7 +141,66 @@ impl Logger {
         format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, seconds, millis)
     }
 
+    /// Prints `message` if `level` is enabled. Warnings and errors are also added to the
+    /// [`DecodeReport`](crate::DecodeReport) of the current decode.
     pub fn log(level: LogLevel, message: impl Display) {
+        if level != LogLevel::Debug && report::is_collecting() {
+            report::record(DecodeWarning::new(WarningKind::Other, message.to_string()));
+        }
+
+        Self::print(level, message);
+    }
+
+    /// Prints `message` if `level` is enabled, without adding it to a report.
+    pub(crate) fn print(level: LogLevel, message: impl Display) {
+        Self::emit(level, message, None, None);
+    }
+
+    /// Passes `message` to the log sink of the current decode if it has one, to the `log` crate
+    /// if the feature is enabled, and prints it otherwise.
+    pub(crate) fn emit(level: LogLevel, message: impl Display, offset: Option<u64>, frame: Option<usize>) {
+        let (sink, format) = progress::log_context();
+
+        if let Some(sink) = sink {
+            sink(&LogRecord {
+                level,
+                message: message.to_string(),
+                format,
+                offset,
+                frame,
+            });
+            return;
+        }
+
+        #[cfg(feature = "log")]
+        {
+            let level = match level {
+                LogLevel::Debug => log::Level::Debug,
+                LogLevel::Warning => log::Level::Warn,
+                LogLevel::Error => log::Level::Error,
+            };
+            let format = format.map(|format| format!("{:?}", format));
+
+            log::log!(
+                target: "vexel",
+                level,
+                format = format.as_deref(),
+                offset = offset,
+                frame = frame;
+                "{}",
+                message
+            );
+        }
+
+        #[cfg(not(feature = "log"))]
+        {
+            let _ = (format, offset, frame);
+            Self::print_colored(level, message);
+        }
+    }
+
+    #[cfg(not(feature = "log"))]
+    fn print_colored(level: LogLevel, message: impl Display) {
         if (level as u8) < MIN_LOG_LEVEL.load(Ordering::Relaxed) {
             return;
         }
@@ -0,0 +1,259 @@
+use crate::log_warn;
+use crate::utils::error::VexelError;
+use crate::utils::exif::{ExifData, ExifValue};
+use crate::utils::icc::ICCProfile;
+
+pub(crate) const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Unit of a [`Resolution`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolutionUnit {
+    /// No physical unit, the values only describe the pixel aspect ratio.
+    None,
+    Inch,
+    Centimeter,
+    Meter,
+}
+
+/// Physical pixel density of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolution {
+    /// Horizontal pixels per unit.
+    pub x: f64,
+    /// Vertical pixels per unit.
+    pub y: f64,
+    pub unit: ResolutionUnit,
+}
+
+impl Resolution {
+    /// Returns the resolution converted to dots per inch.
+    ///
+    /// Returns `None` when the unit is [`ResolutionUnit::None`], since then only the aspect ratio is known.
+    pub fn dpi(&self) -> Option<(f64, f64)> {
+        let scale = match self.unit {
+            ResolutionUnit::None => return None,
+            ResolutionUnit::Inch => 1.0,
+            ResolutionUnit::Centimeter => 2.54,
+            ResolutionUnit::Meter => 0.0254,
+        };
+
+        Some((self.x * scale, self.y * scale))
+    }
+
+    /// Builds a resolution from JFIF density fields.
+    pub(crate) fn from_jfif(units: u8, x_density: u16, y_density: u16) -> Option<Resolution> {
+        if x_density == 0 || y_density == 0 {
+            return None;
+        }
+
+        let unit = match units {
+            1 => ResolutionUnit::Inch,
+            2 => ResolutionUnit::Centimeter,
+            _ => ResolutionUnit::None,
+        };
+
+        Some(Resolution {
+            x: x_density as f64,
+            y: y_density as f64,
+            unit,
+        })
+    }
+
+    /// Builds a resolution from TIFF-style `XResolution`, `YResolution` and `ResolutionUnit` values.
+    pub(crate) fn from_tiff(x: f64, y: f64, unit: u32) -> Option<Resolution> {
+        if !(x > 0.0 && y > 0.0) {
+            return None;
+        }
+
+        let unit = match unit {
+            2 => ResolutionUnit::Inch,
+            3 => ResolutionUnit::Centimeter,
+            _ => ResolutionUnit::None,
+        };
+
+        Some(Resolution { x, y, unit })
+    }
+
+    /// Reads the resolution tags from IFD0 of the EXIF data.
+    pub(crate) fn from_exif(exif: &ExifData) -> Option<Resolution> {
+        let find = |tag: u16| exif.ifd0.entries.iter().find(|entry| entry.tag == tag).map(|entry| &entry.value);
+
+        let rational = |value: Option<&ExifValue>| match value {
+            Some(ExifValue::Rational(v)) => v.first().filter(|(_, d)| *d != 0).map(|&(n, d)| n as f64 / d as f64),
+            _ => None,
+        };
+
+        let unit = match find(0x0128) {
+            Some(ExifValue::Short(v)) => v.first().map(|&u| u as u32).unwrap_or(2),
+            _ => 2,
+        };
+
+        Resolution::from_tiff(rational(find(0x011A))?, rational(find(0x011B))?, unit)
+    }
+}
+
+/// EXIF/TIFF `Orientation` value, describing where the first stored row and column
+/// should be displayed.
+///
+/// Variant names follow the TIFF specification: `RightTop` means the first row is the
+/// visual right side and the first column is the visual top, i.e. the stored image has
+/// to be rotated 90° clockwise to display it upright.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Orientation {
+    /// Stored upright, no transformation needed.
+    #[default]
+    TopLeft = 1,
+    /// Mirrored horizontally.
+    TopRight = 2,
+    /// Rotated 180°.
+    BottomRight = 3,
+    /// Mirrored vertically.
+    BottomLeft = 4,
+    /// Mirrored along the top-left to bottom-right diagonal.
+    LeftTop = 5,
+    /// Needs a 90° clockwise rotation.
+    RightTop = 6,
+    /// Mirrored along the top-right to bottom-left diagonal.
+    RightBottom = 7,
+    /// Needs a 90° counter-clockwise rotation.
+    LeftBottom = 8,
+}
+
+impl Orientation {
+    /// Returns `true` if applying this orientation swaps the width and height.
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(
+            self,
+            Orientation::LeftTop | Orientation::RightTop | Orientation::RightBottom | Orientation::LeftBottom
+        )
+    }
+
+    /// Reads the `Orientation` tag from IFD0 of the EXIF data.
+    pub(crate) fn from_exif(exif: &ExifData) -> Option<Orientation> {
+        let value = exif.ifd0.entries.iter().find(|entry| entry.tag == 0x0112).and_then(|entry| match &entry.value {
+            ExifValue::Short(v) => v.first().map(|&v| v as u32),
+            ExifValue::Long(v) => v.first().copied(),
+            _ => None,
+        })?;
+
+        Orientation::try_from(value).ok()
+    }
+}
+
+impl TryFrom<u32> for Orientation {
+    type Error = VexelError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::TopLeft),
+            2 => Ok(Self::TopRight),
+            3 => Ok(Self::BottomRight),
+            4 => Ok(Self::BottomLeft),
+            5 => Ok(Self::LeftTop),
+            6 => Ok(Self::RightTop),
+            7 => Ok(Self::RightBottom),
+            8 => Ok(Self::LeftBottom),
+            _ => Err(VexelError::Custom(format!("Invalid orientation value: {}", value))),
+        }
+    }
+}
+
+/// A textual key/value pair stored in the file, such as a PNG `tEXt` chunk or a JPEG comment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// Format-independent metadata attached to a decoded [`Image`](crate::Image).
+///
+/// Every field is optional or may be empty, depending on what the file contains and what
+/// the format can carry. Format-specific details are still available through
+/// [`Vexel::get_info`](crate::Vexel::get_info).
+#[derive(Debug, Clone, Default)]
+pub struct ImageMetadata {
+    /// Raw bytes of the embedded ICC profile, reassembled if it was split across segments.
+    pub icc_data: Option<Vec<u8>>,
+    /// The parsed ICC profile. `None` if there is no profile or it could not be parsed.
+    pub icc_profile: Option<ICCProfile>,
+    /// Parsed EXIF data.
+    pub exif: Option<ExifData>,
+    /// XMP packets, in the order they appear in the file.
+    pub xmp: Vec<String>,
+    /// Text entries, in the order they appear in the file.
+    pub text: Vec<TextEntry>,
+    /// Physical resolution of the image.
+    pub resolution: Option<Resolution>,
+    /// Orientation the pixels still have to be transformed with to display the image upright.
+    ///
+    /// This is reset to [`Orientation::TopLeft`] once the orientation has been applied,
+    /// see [`Image::apply_orientation`](crate::Image::apply_orientation).
+    pub orientation: Orientation,
+}
+
+impl ImageMetadata {
+    /// Returns the value of the first text entry with the given key.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        self.text.iter().find(|entry| entry.key == key).map(|entry| entry.value.as_str())
+    }
+
+    /// Returns `true` if no metadata was found.
+    pub fn is_empty(&self) -> bool {
+        self.icc_data.is_none()
+            && self.exif.is_none()
+            && self.xmp.is_empty()
+            && self.text.is_empty()
+            && self.resolution.is_none()
+            && self.orientation == Orientation::TopLeft
+    }
+
+    /// Stores the raw ICC profile and tries to parse it.
+    pub(crate) fn set_icc(&mut self, data: Vec<u8>) {
+        self.icc_profile = match ICCProfile::new(&data) {
+            Ok(profile) => Some(profile),
+            Err(e) => {
+                log_warn!("Error reading ICC profile: {}", e);
+                None
+            }
+        };
+        self.icc_data = Some(data);
+    }
+
+    pub(crate) fn push_text(&mut self, key: &str, value: String) {
+        self.text.push(TextEntry { key: key.to_string(), value });
+    }
+
+    /// Keeps the EXIF data already parsed from a JPEG-style APP1 payload, or reads an XMP packet from it.
+    /// Only the first EXIF block is kept, other payloads are ignored.
+    pub(crate) fn read_app1(&mut self, payload: &[u8], exif: Option<&ExifData>) {
+        if let Some(exif) = exif {
+            if self.exif.is_none() {
+                self.orientation = Orientation::from_exif(exif).unwrap_or_default();
+                self.exif = Some(exif.clone());
+            }
+        } else if payload.starts_with(XMP_SIGNATURE) {
+            self.xmp.push(String::from_utf8_lossy(&payload[XMP_SIGNATURE.len()..]).to_string());
+        }
+    }
+}
+
+/// Joins ICC profile chunks from JPEG `APP2` segments in sequence order.
+pub(crate) fn assemble_icc_chunks(chunks: &[(u8, Vec<u8>)]) -> Option<Vec<u8>> {
+    if chunks.is_empty() {
+        return None;
+    }
+
+    let mut sorted: Vec<&(u8, Vec<u8>)> = chunks.iter().collect();
+    sorted.sort_by_key(|(sequence, _)| *sequence);
+
+    Some(sorted.into_iter().flat_map(|(_, data)| data.iter().copied()).collect())
+}
+
+/// Picks the resolution of a JPEG-style file from its JFIF density and EXIF tags.
+/// JFIF density without units only gives the aspect ratio, so EXIF wins in that case.
+pub(crate) fn jpeg_resolution(jfif: Option<Resolution>, exif: Option<&ExifData>) -> Option<Resolution> {
+    match jfif {
+        Some(resolution) if resolution.unit != ResolutionUnit::None => Some(resolution),
+        _ => exif.and_then(Resolution::from_exif).or(jfif),
+    }
+}
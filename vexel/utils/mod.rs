@@ -1,13 +1,24 @@
 pub mod bitreader;
 pub mod limits;
 pub mod channel_simd;
+pub mod color_transform;
+pub mod convert;
 pub mod deflate;
 pub mod error;
 pub mod exif;
+pub mod frames;
 pub mod icc;
 pub mod image;
 pub mod info;
 pub mod logger;
+pub mod metadata;
+pub mod options;
+pub mod progress;
+pub mod stream;
 pub mod marker;
+pub mod registry;
+pub mod report;
+pub mod scan;
+pub mod sniff;
 pub mod traits;
 pub mod types;
@@ -0,0 +1,85 @@
+use crate::utils::logger::LogSink;
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How the decoders treat malformed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Recover from malformed input where possible and report what was recovered from in
+    /// [`Image::report`](crate::Image::report).
+    #[default]
+    Lenient,
+    /// Reject any input the decoder would have to recover from with
+    /// [`VexelError::Malformed`](crate::VexelError::Malformed). Warnings of kind
+    /// [`WarningKind::Other`](crate::WarningKind::Other) are only reported.
+    Strict,
+}
+
+/// Progress of a decode, passed to [`DecodeOptions::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeProgress {
+    /// `completed` of the `total` rows of frame `frame` have been decoded. Decoders that work in
+    /// larger units, such as MCU rows, progressive scans, strips or tiles, report the share of
+    /// those units that is done, so `completed` can skip ahead.
+    Rows { frame: usize, completed: u32, total: u32 },
+    /// `completed` of the `total` frames have been decoded.
+    Frames { completed: usize, total: usize },
+}
+
+/// Callback that receives the progress of a decode.
+///
+/// It is called from the thread running the decode, and from worker threads for formats whose
+/// frames are decoded in parallel, so it should return quickly.
+pub type ProgressCallback = Arc<dyn Fn(DecodeProgress) + Send + Sync>;
+
+/// Token that cancels a running decode, set in [`DecodeOptions::cancellation`].
+///
+/// Clones share the same state, so a clone can be kept by another thread and cancelled from
+/// there. The decoders check the token between rows, strips or frames, and return
+/// [`VexelError::Cancelled`](crate::VexelError::Cancelled) once it is cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every decode using this token or one of its clones.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if [`cancel`](Self::cancel) was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Options that control how images are decoded, set with
+/// [`Vexel::set_decode_options`](crate::Vexel::set_decode_options).
+#[derive(Clone, Default)]
+pub struct DecodeOptions {
+    /// How malformed input is handled. Defaults to [`Strictness::Lenient`].
+    pub strictness: Strictness,
+    /// Called as rows or frames are decoded.
+    pub progress: Option<ProgressCallback>,
+    /// Cancels the decode when cancelled.
+    pub cancellation: Option<CancellationToken>,
+    /// Receives the messages logged while decoding, instead of the global logger. Messages of
+    /// every level are passed, regardless of [`set_log_level`](crate::set_log_level).
+    pub log_sink: Option<LogSink>,
+}
+
+impl Debug for DecodeOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecodeOptions")
+            .field("strictness", &self.strictness)
+            .field("progress", &self.progress.as_ref().map(|_| "Fn(DecodeProgress)"))
+            .field("cancellation", &self.cancellation)
+            .field("log_sink", &self.log_sink.as_ref().map(|_| "Fn(&LogRecord)"))
+            .finish()
+    }
+}
@@ -0,0 +1,160 @@
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::ImageFormat;
+use crate::utils::limits::Limits;
+use crate::utils::logger::LogSink;
+use crate::utils::options::{CancellationToken, DecodeOptions, DecodeProgress, ProgressCallback};
+use crate::utils::report;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+/// The progress callback, cancellation token, deadline and log sink of a decode call, and the
+/// format being decoded, which log records are attributed to.
+#[derive(Clone)]
+pub(crate) struct Hooks {
+    progress: Option<ProgressCallback>,
+    cancellation: Option<CancellationToken>,
+    deadline: Option<(Instant, Duration)>,
+    log_sink: Option<LogSink>,
+    format: ImageFormat,
+}
+
+impl Hooks {
+    /// Returns the hooks set in `options` together with the deadline from
+    /// [`Limits::max_decode_duration`], or `None` if there are none.
+    pub(crate) fn from_options(options: &DecodeOptions, limits: &Limits, format: &ImageFormat) -> Option<Self> {
+        // Records passed to the `log` crate carry the format, so the hooks are always needed then
+        let has_hooks = options.progress.is_some()
+            || options.cancellation.is_some()
+            || options.log_sink.is_some()
+            || limits.max_decode_duration.is_some()
+            || cfg!(feature = "log");
+        if !has_hooks {
+            return None;
+        }
+
+        // The clock is only read when a duration is set, `Instant` is not available on every target
+        let deadline = limits
+            .max_decode_duration
+            .and_then(|duration| Instant::now().checked_add(duration).map(|deadline| (deadline, duration)));
+
+        Some(Self {
+            progress: options.progress.clone(),
+            cancellation: options.cancellation.clone(),
+            deadline,
+            log_sink: options.log_sink.clone(),
+            format: format.clone(),
+        })
+    }
+}
+
+thread_local! {
+    static HOOKS: RefCell<Option<Hooks>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous hooks of the thread when dropped, also when unwinding.
+struct HooksGuard(Option<Hooks>);
+
+impl Drop for HooksGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        HOOKS.with(|hooks| *hooks.borrow_mut() = previous);
+    }
+}
+
+/// Returns the hooks of the decode running on this thread. Parallel code passes them to
+/// [`with_hooks`] on the worker threads, like [`report::current_sink`](crate::utils::report::current_sink).
+pub(crate) fn current_hooks() -> Option<Hooks> {
+    HOOKS.with(|hooks| hooks.borrow().clone())
+}
+
+/// Runs `f` with `hooks` checked by [`rows`], [`frames`] and [`check_cancelled`].
+pub(crate) fn with_hooks<T>(hooks: Option<Hooks>, f: impl FnOnce() -> T) -> T {
+    let previous = HOOKS.with(|current| current.replace(hooks));
+    let _guard = HooksGuard(previous);
+
+    f()
+}
+
+/// Returns the log sink of the decode running on this thread and the format it decodes.
+pub(crate) fn log_context() -> (Option<LogSink>, Option<ImageFormat>) {
+    HOOKS.with(|hooks| match hooks.borrow().as_ref() {
+        Some(hooks) => (hooks.log_sink.clone(), Some(hooks.format.clone())),
+        None => (None, None),
+    })
+}
+
+/// Returns [`VexelError::Cancelled`] if the decode running on this thread was cancelled,
+/// [`VexelError::LimitExceeded`] if it ran past its deadline, or [`VexelError::Malformed`] if it
+/// is strict and had to recover from bad data.
+pub(crate) fn check_cancelled() -> VexelResult<()> {
+    report::check_rejected()?;
+
+    let (cancelled, deadline) = HOOKS.with(|hooks| match hooks.borrow().as_ref() {
+        Some(hooks) => (
+            hooks.cancellation.as_ref().is_some_and(|token| token.is_cancelled()),
+            hooks.deadline,
+        ),
+        None => (false, None),
+    });
+
+    if cancelled {
+        return Err(VexelError::Cancelled);
+    }
+
+    if let Some((deadline, duration)) = deadline {
+        if Instant::now() >= deadline {
+            return Err(VexelError::LimitExceeded(format!(
+                "decode took longer than the limit of {:?}",
+                duration
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks for cancellation and reports `progress`.
+fn report(progress: DecodeProgress) -> VexelResult<()> {
+    check_cancelled()?;
+
+    // The callback is called without holding the borrow, so it can start a decode of its own
+    let callback = HOOKS.with(|hooks| hooks.borrow().as_ref().and_then(|hooks| hooks.progress.clone()));
+    if let Some(callback) = callback {
+        callback(progress);
+    }
+
+    Ok(())
+}
+
+/// Reports that `completed` of the `total` rows of `frame` were decoded, and returns
+/// [`VexelError::Cancelled`] if the decode was cancelled.
+pub(crate) fn rows(frame: usize, completed: u32, total: u32) -> VexelResult<()> {
+    report(DecodeProgress::Rows {
+        frame,
+        completed: completed.min(total),
+        total,
+    })
+}
+
+/// Reports that `completed` of `total` units of work on `frame` are done, as the same share of its
+/// `rows`, and returns [`VexelError::Cancelled`] if the decode was cancelled. Used where the
+/// decoder works in strips, tiles or planes instead of rows.
+pub(crate) fn rows_share(frame: usize, completed: u64, total: u64, rows: u32) -> VexelResult<()> {
+    let completed_rows = completed.min(total) * rows as u64 / total.max(1);
+
+    self::rows(frame, completed_rows as u32, rows)
+}
+
+/// Reports that `completed` of `total` frames were decoded, and returns
+/// [`VexelError::Cancelled`] if the decode was cancelled.
+pub(crate) fn frames(completed: usize, total: usize) -> VexelResult<()> {
+    report(DecodeProgress::Frames { completed, total })
+}
+
+/// Runs `f` without reporting progress, for parts of an image decoded by another decoder, such
+/// as JPEG-compressed TIFF strips. Cancellation is still checked.
+pub(crate) fn without_progress<T>(f: impl FnOnce() -> T) -> T {
+    let hooks = current_hooks().map(|hooks| Hooks { progress: None, ..hooks });
+
+    with_hooks(hooks, f)
+}
@@ -0,0 +1,352 @@
+use crate::utils::error::VexelResult;
+use crate::utils::metadata::ImageMetadata;
+use crate::{BuiltinDecoder, ColorTarget, Image, ImageFormat, ImageHeader, ImageInfo, JpegScale, Limits};
+use std::io::{Cursor, Read, Seek};
+use std::sync::{LazyLock, RwLock};
+
+/// Number of bytes from the start of the file that are passed to the sniffers. Shorter files are
+/// padded with zeros.
+pub(crate) const SNIFF_LENGTH: usize = 32;
+
+/// A source that [`ImageDecoder`]s read from.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// A decoder for an image format.
+///
+/// Decoders are registered with [`register_decoder`] and picked by [`Vexel::new`](crate::Vexel::new).
+/// The built-in formats are registered the same way, and [`Vexel`](crate::Vexel) probes, decodes
+/// and reads [`info`](Self::info) through this trait for every format. Calls are wrapped in the
+/// same panic catching, so a panicking decoder returns [`VexelError::Panic`](crate::VexelError::Panic),
+/// and [`Vexel::set_limits`](crate::Vexel::set_limits) is forwarded through [`set_limits`](Self::set_limits).
+/// The image dimensions returned by [`probe`](Self::probe) and [`decode`](Self::decode) are checked
+/// against the limits afterwards as well.
+///
+/// The reader is passed to every call and is positioned at the start of the file. Inputs that are
+/// in memory as a whole are passed to [`probe_bytes`](Self::probe_bytes) and
+/// [`decode_bytes`](Self::decode_bytes) instead.
+pub trait ImageDecoder {
+    /// Reads the headers and returns the basic properties of the image.
+    fn probe(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<ImageHeader>;
+
+    /// Sets the limits the decoder should enforce. Use [`Limits::check_dimensions`] for the
+    /// image size.
+    fn set_limits(&mut self, limits: Limits);
+
+    /// Decodes the image.
+    fn decode(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<Image>;
+
+    /// Like [`probe`](Self::probe), for an input that is in memory as a whole, such as one passed
+    /// to [`Vexel::from_slice`](crate::Vexel::from_slice). Reads `data` through a [`Cursor`] by
+    /// default, decoders that can borrow from the slice instead of copying it override this.
+    fn probe_bytes(&mut self, data: &[u8]) -> VexelResult<ImageHeader> {
+        self.probe(&mut Cursor::new(data))
+    }
+
+    /// Like [`decode`](Self::decode), for an input that is in memory as a whole, see
+    /// [`probe_bytes`](Self::probe_bytes).
+    fn decode_bytes(&mut self, data: &[u8]) -> VexelResult<Image> {
+        self.decode(&mut Cursor::new(data))
+    }
+
+    /// Returns format-specific information collected by the last [`decode`](Self::decode) call.
+    fn info(&self) -> ImageInfo {
+        ImageInfo::Unknown
+    }
+
+    /// Returns the metadata collected by the last [`decode`](Self::decode) call.
+    fn metadata(&self) -> ImageMetadata {
+        ImageMetadata::default()
+    }
+
+    /// Sets the colour space device colour such as CMYK is converted to, see
+    /// [`Vexel::set_color_target`](crate::Vexel::set_color_target). Ignored by default.
+    fn set_color_target(&mut self, _target: ColorTarget) {}
+
+    /// Sets the reduced size to decode at, see [`Vexel::set_jpeg_scale`](crate::Vexel::set_jpeg_scale).
+    /// Ignored by default.
+    fn set_jpeg_scale(&mut self, _scale: JpegScale) {}
+}
+
+/// Describes a format handled by an [`ImageDecoder`], passed to [`register_decoder`].
+#[derive(Clone, Copy)]
+pub struct DecoderRegistration {
+    /// Name of the format, reported as [`ImageFormat::Custom`].
+    pub name: &'static str,
+    /// Returns `true` if the first 32 bytes of a file, padded with zeros, belong to this format.
+    pub sniff: fn(&[u8]) -> bool,
+    /// Creates a decoder for one image.
+    pub create: fn() -> Box<dyn ImageDecoder>,
+}
+
+/// An entry of the format registry. Built-in formats report their own [`ImageFormat`] instead of
+/// [`ImageFormat::Custom`].
+#[derive(Clone)]
+struct FormatEntry {
+    registration: DecoderRegistration,
+    builtin: Option<ImageFormat>,
+}
+
+impl FormatEntry {
+    fn format(&self) -> ImageFormat {
+        self.builtin.clone().unwrap_or(ImageFormat::Custom(self.registration.name))
+    }
+}
+
+/// Registration of a built-in format, recognized when `$sniff` returns `$format`.
+macro_rules! builtin {
+    ($name:literal, $sniff:ident, $format:expr) => {
+        FormatEntry {
+            registration: DecoderRegistration {
+                name: $name,
+                sniff: |header| $sniff(header) == Some($format),
+                create: || Box::new(BuiltinDecoder::new($format)),
+            },
+            builtin: Some($format),
+        }
+    };
+}
+
+static REGISTRY: LazyLock<RwLock<Vec<FormatEntry>>> = LazyLock::new(|| {
+    RwLock::new(vec![
+        builtin!("jpeg-ls", sniff_jpeg_ls, ImageFormat::JpegLs),
+        builtin!("jpeg", sniff_jpeg, ImageFormat::Jpeg),
+        builtin!("png", sniff_png, ImageFormat::Png),
+        builtin!("gif", sniff_gif, ImageFormat::Gif),
+        builtin!("pbm", sniff_netpbm, ImageFormat::NetPbmP1),
+        builtin!("pgm", sniff_netpbm, ImageFormat::NetPbmP2),
+        builtin!("ppm", sniff_netpbm, ImageFormat::NetPbmP3),
+        builtin!("pbm-raw", sniff_netpbm, ImageFormat::NetPbmP4),
+        builtin!("pgm-raw", sniff_netpbm, ImageFormat::NetPbmP5),
+        builtin!("ppm-raw", sniff_netpbm, ImageFormat::NetPbmP6),
+        builtin!("pam", sniff_netpbm, ImageFormat::NetPbmP7),
+        builtin!("bmp", sniff_bmp, ImageFormat::Bmp),
+        builtin!("ico", sniff_ico, ImageFormat::Ico),
+        builtin!("cur", sniff_ico, ImageFormat::Cur),
+        builtin!("hdr", sniff_hdr, ImageFormat::Hdr),
+        builtin!("tiff", sniff_tiff, ImageFormat::Tiff),
+        builtin!("tga", sniff_tga, ImageFormat::Tga),
+        builtin!("jbig1", sniff_jbig1, ImageFormat::Jbig1),
+    ])
+});
+
+/// Registers a decoder for a new image format.
+///
+/// Registered formats are checked before the built-in ones, in the order they were registered,
+/// so a format can also take over files that a built-in decoder would otherwise handle.
+/// Registering a name again replaces the earlier registration. The registry is global and
+/// applies to every [`Vexel`](crate::Vexel) created afterwards.
+///
+/// ```no_run
+/// use vexel::{register_decoder, DecoderRegistration, Image, ImageDecoder, ImageHeader, Limits, ReadSeek, VexelResult};
+///
+/// struct MyDecoder;
+///
+/// impl ImageDecoder for MyDecoder {
+///     fn probe(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<ImageHeader> {
+///         todo!()
+///     }
+///
+///     fn set_limits(&mut self, limits: Limits) {}
+///
+///     fn decode(&mut self, reader: &mut dyn ReadSeek) -> VexelResult<Image> {
+///         todo!()
+///     }
+/// }
+///
+/// register_decoder(DecoderRegistration {
+///     name: "my-format",
+///     sniff: |header| header.starts_with(b"MYFMT"),
+///     create: || Box::new(MyDecoder),
+/// });
+/// ```
+pub fn register_decoder(registration: DecoderRegistration) {
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    let entry = FormatEntry {
+        registration,
+        builtin: None,
+    };
+
+    let existing = registry
+        .iter()
+        .position(|entry| entry.builtin.is_none() && entry.registration.name == registration.name);
+
+    match existing {
+        Some(index) => registry[index] = entry,
+        None => {
+            let index = registry
+                .iter()
+                .position(|entry| entry.builtin.is_some())
+                .unwrap_or(registry.len());
+
+            registry.insert(index, entry);
+        }
+    }
+}
+
+/// Returns the format of the first registry entry that recognizes `header`.
+pub(crate) fn sniff(header: &[u8]) -> Option<ImageFormat> {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+
+    registry
+        .iter()
+        .find(|entry| (entry.registration.sniff)(header))
+        .map(FormatEntry::format)
+}
+
+/// Returns the registered formats that recognize `header`, in the order they are checked.
+pub(crate) fn sniff_custom(header: &[u8]) -> Vec<ImageFormat> {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+
+    registry
+        .iter()
+        .filter(|entry| entry.builtin.is_none() && (entry.registration.sniff)(header))
+        .map(FormatEntry::format)
+        .collect()
+}
+
+/// Creates the decoder of `format`, or `None` for [`ImageFormat::Unknown`] and names that were
+/// never registered.
+pub(crate) fn create_decoder(format: &ImageFormat) -> Option<Box<dyn ImageDecoder>> {
+    let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+
+    registry
+        .iter()
+        .find(|entry| entry.format() == *format)
+        .map(|entry| (entry.registration.create)())
+}
+
+pub(crate) fn sniff_jpeg_ls(header: &[u8]) -> Option<ImageFormat> {
+    if !header.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    if header.windows(2).any(|window| window == [0xFF, 0xF7]) {
+        return Some(ImageFormat::JpegLs);
+    }
+
+    if header.len() >= 12 && header[2] == 0xFF && header[3] == 0xE8 && &header[6..12] == b"SPIFF\0" {
+        return Some(ImageFormat::JpegLs);
+    }
+
+    None
+}
+
+pub(crate) fn sniff_jpeg(header: &[u8]) -> Option<ImageFormat> {
+    header.starts_with(&[0xFF, 0xD8]).then_some(ImageFormat::Jpeg)
+}
+
+pub(crate) fn sniff_png(header: &[u8]) -> Option<ImageFormat> {
+    header
+        .starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A])
+        .then_some(ImageFormat::Png)
+}
+
+pub(crate) fn sniff_gif(header: &[u8]) -> Option<ImageFormat> {
+    (header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a")).then_some(ImageFormat::Gif)
+}
+
+pub(crate) fn sniff_netpbm(header: &[u8]) -> Option<ImageFormat> {
+    if !header.starts_with(b"P") {
+        return None;
+    }
+
+    match header.get(1) {
+        Some(b'1') => Some(ImageFormat::NetPbmP1),
+        Some(b'2') => Some(ImageFormat::NetPbmP2),
+        Some(b'3') => Some(ImageFormat::NetPbmP3),
+        Some(b'4') => Some(ImageFormat::NetPbmP4),
+        Some(b'5') => Some(ImageFormat::NetPbmP5),
+        Some(b'6') => Some(ImageFormat::NetPbmP6),
+        Some(b'7') => Some(ImageFormat::NetPbmP7),
+        _ => None,
+    }
+}
+
+pub(crate) fn sniff_bmp(header: &[u8]) -> Option<ImageFormat> {
+    match header.get(0..2)? {
+        b"BM" | b"BA" | b"CI" | b"CP" | b"IC" | b"PT" => Some(ImageFormat::Bmp),
+        _ => None,
+    }
+}
+
+pub(crate) fn sniff_ico(header: &[u8]) -> Option<ImageFormat> {
+    let ico_count = u16::from_le_bytes([*header.get(4)?, *header.get(5)?]);
+    if ico_count == 0 {
+        return None;
+    }
+
+    if header.starts_with(&[0x00, 0x00, 0x01, 0x00]) {
+        return Some(ImageFormat::Ico);
+    }
+
+    if header.starts_with(&[0x00, 0x00, 0x02, 0x00]) {
+        return Some(ImageFormat::Cur);
+    }
+
+    None
+}
+
+pub(crate) fn sniff_hdr(header: &[u8]) -> Option<ImageFormat> {
+    header.starts_with(b"#?RADIANCE").then_some(ImageFormat::Hdr)
+}
+
+pub(crate) fn sniff_tiff(header: &[u8]) -> Option<ImageFormat> {
+    if header.len() < 4 {
+        return None;
+    }
+
+    // Version 42 is classic TIFF, 43 is BigTIFF
+    ((header.starts_with(b"II") || header.starts_with(b"MM"))
+        && matches!((header[2], header[3]), (42 | 43, 0) | (0, 42 | 43)))
+        .then_some(ImageFormat::Tiff)
+}
+
+pub(crate) fn sniff_tga(header: &[u8]) -> Option<ImageFormat> {
+    if header.len() < 18 {
+        return None;
+    }
+
+    // Targa does not have a magic number, so we have to check the header manually.
+    let image_type = header[2];
+    let color_map_type = header[1];
+    let palette_bpp = header[7];
+
+    let valid_image_type = matches!(image_type, 0 | 1 | 2 | 3 | 9 | 10 | 11 | 32 | 33);
+    let valid_color_map = matches!(color_map_type, 0 | 1);
+
+    let pixel_depth = header[16];
+    let valid_depth = matches!(pixel_depth, 1 | 4 | 8 | 15 | 16 | 24 | 32);
+
+    let descriptor = header[17];
+    let valid_descriptor = (descriptor & 0xC0) == 0;
+
+    let is_paletted_type = matches!(image_type, 1 | 9);
+    let palette_consistent = !is_paletted_type || color_map_type == 1;
+    let valid_palette_bpp = color_map_type != 1 || matches!(palette_bpp, 15 | 16 | 24 | 32);
+
+    (valid_image_type && valid_color_map && valid_depth && valid_descriptor && palette_consistent && valid_palette_bpp)
+        .then_some(ImageFormat::Tga)
+}
+
+pub(crate) fn sniff_jbig1(header: &[u8]) -> Option<ImageFormat> {
+    if header.len() < 16 {
+        return None;
+    }
+
+    // No magic bytes; validate the 20-byte BIH header
+    // Byte 0 (DL) <= byte 1 (D), byte 2 (planes) in 1..=8, byte 3 = 0 (reserved)
+    // XD, YD, L0 (big-endian u32 at offsets 4, 8, 12) must be non-zero and <= 65535
+    let xd = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let yd = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+    let l0 = u32::from_be_bytes([header[12], header[13], header[14], header[15]]);
+
+    (header[3] == 0
+        && (1..=8).contains(&header[2])
+        && header[0] <= header[1]
+        && xd > 0 && xd <= 65535
+        && yd > 0 && yd <= 65535
+        && l0 > 0 && l0 <= 65535)
+        .then_some(ImageFormat::Jbig1)
+}
@@ -0,0 +1,261 @@
+use crate::utils::error::{VexelError, VexelResult};
+use crate::utils::image::ImageFormat;
+use crate::utils::logger::{LogLevel, Logger};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of warnings kept in a [`DecodeReport`]. Further warnings are only counted.
+const MAX_WARNINGS: usize = 1024;
+
+/// The kind of problem a decoder recovered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The file ended before all image data was read.
+    TruncatedData,
+    /// Image data was invalid and was decoded as well as possible.
+    CorruptData,
+    /// A feature of the file is not supported, so the affected pixels are likely wrong.
+    Unsupported,
+    /// The decoder produced more or fewer pixels than the frame holds, and the pixel data was
+    /// truncated or padded.
+    PixelCountMismatch,
+    /// Data in front of the image signature was skipped by
+    /// [`Vexel::new_scanning`](crate::Vexel::new_scanning). The offset of the warning is where
+    /// the image starts.
+    LeadingData,
+    /// Any other warning logged by a decoder.
+    Other,
+}
+
+impl WarningKind {
+    /// Whether the decoder had to recover from bad data, which fails the decode in
+    /// [`Strictness::Strict`](crate::Strictness::Strict) mode. [`Other`](Self::Other) warnings
+    /// are informational and never fail it.
+    pub fn is_recovery(&self) -> bool {
+        !matches!(self, WarningKind::Other)
+    }
+}
+
+/// A problem a decoder recovered from while decoding an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeWarning {
+    /// What went wrong.
+    pub kind: WarningKind,
+    /// Human-readable description, the same text that is logged.
+    pub message: String,
+    /// Byte offset in the file where the problem was found, if known.
+    pub offset: Option<u64>,
+    /// Index of the affected frame, if known.
+    pub frame: Option<usize>,
+    /// Number of pixels that could not be decoded and were filled in instead.
+    pub synthesized_pixels: u64,
+}
+
+impl DecodeWarning {
+    pub(crate) fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            offset: None,
+            frame: None,
+            synthesized_pixels: 0,
+        }
+    }
+
+    /// Creates the warning for a decoder that carries on after `error`, for example by skipping
+    /// the rest of a chunk or keeping the frames read so far. The kind follows from the error.
+    pub(crate) fn recovered_from(error: &VexelError, message: impl Into<String>) -> Self {
+        let kind = match error {
+            VexelError::Truncated { .. } | VexelError::MissingData { .. } => WarningKind::TruncatedData,
+            VexelError::IoError(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => WarningKind::TruncatedData,
+            VexelError::Unsupported { .. } | VexelError::UnsupportedFormat(_) => WarningKind::Unsupported,
+            _ => WarningKind::CorruptData,
+        };
+        let warning = Self::new(kind, message);
+
+        match error {
+            VexelError::Truncated { offset: Some(offset), .. } | VexelError::Corrupt { offset: Some(offset), .. } => {
+                warning.at_offset(*offset)
+            }
+            _ => warning,
+        }
+    }
+
+    pub(crate) fn at_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub(crate) fn in_frame(mut self, frame: usize) -> Self {
+        self.frame = Some(frame);
+        self
+    }
+
+    pub(crate) fn with_synthesized_pixels(mut self, pixels: u64) -> Self {
+        self.synthesized_pixels = pixels;
+        self
+    }
+
+    /// Logs the warning and adds it to the report of the current decode.
+    pub(crate) fn report(self) {
+        Logger::emit(LogLevel::Warning, &self.message, self.offset, self.frame);
+        record(self);
+    }
+}
+
+/// The problems found while decoding an image, returned by [`Image::report`](crate::Image::report).
+///
+/// Warnings raised by a decoder are collected for the call that raised them, so concurrent
+/// decodes on other threads don't end up in the same report. Messages logged without a more
+/// specific kind are reported as [`WarningKind::Other`], regardless of the configured
+/// [log level](crate::set_log_level).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeReport {
+    /// Warnings in the order they were raised. At most 1024 are kept.
+    pub warnings: Vec<DecodeWarning>,
+    /// Number of warnings that were dropped after the first 1024.
+    pub omitted_warnings: usize,
+    /// Number of pixels in all returned frames.
+    pub total_pixels: u64,
+    /// Number of pixels that were filled in instead of decoded, summed over all warnings
+    /// including omitted ones. Overlapping warnings may count a pixel twice, so the value never
+    /// exceeds [`total_pixels`](Self::total_pixels).
+    pub synthesized_pixels: u64,
+}
+
+impl DecodeReport {
+    /// Returns `true` if the image was decoded without any warnings.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty() && self.omitted_warnings == 0
+    }
+
+    /// Returns the number of pixels that were actually decoded from the file.
+    pub fn decoded_pixels(&self) -> u64 {
+        self.total_pixels - self.synthesized_pixels
+    }
+
+    /// Returns `true` if any warning is of the given kind.
+    pub fn has(&self, kind: WarningKind) -> bool {
+        self.warnings.iter().any(|w| w.kind == kind)
+    }
+
+    pub(crate) fn new(collector: Collector, total_pixels: u64) -> Self {
+        let mut report = Self::default();
+        report.add(collector, total_pixels);
+
+        report
+    }
+
+    /// Adds the warnings of another decode call, which returned `total_pixels` more pixels.
+    pub(crate) fn add(&mut self, collector: Collector, total_pixels: u64) {
+        let kept = collector.warnings.len().min(MAX_WARNINGS - self.warnings.len());
+        self.omitted_warnings += collector.omitted_warnings + collector.warnings.len() - kept;
+        self.warnings.extend(collector.warnings.into_iter().take(kept));
+
+        self.total_pixels += total_pixels;
+        let synthesized_pixels = self.synthesized_pixels.saturating_add(collector.synthesized_pixels);
+        self.synthesized_pixels = synthesized_pixels.min(self.total_pixels);
+    }
+}
+
+/// Warnings collected during one decode call.
+#[derive(Debug, Default)]
+pub(crate) struct Collector {
+    warnings: Vec<DecodeWarning>,
+    omitted_warnings: usize,
+    synthesized_pixels: u64,
+    /// Set for [`Strictness::Strict`](crate::Strictness::Strict) decodes.
+    strict: bool,
+    rejected: Option<DecodeWarning>,
+}
+
+impl Collector {
+    /// Returns the first recovery warning raised in strict mode.
+    pub(crate) fn rejected(&self) -> Option<&DecodeWarning> {
+        self.rejected.as_ref()
+    }
+}
+
+/// Shared handle to the collector of a decode call, see [`current_sink`].
+pub(crate) type Sink = Arc<Mutex<Collector>>;
+
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous sink of the thread when dropped, also when unwinding.
+struct SinkGuard(Option<Sink>);
+
+impl Drop for SinkGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SINK.with(|sink| *sink.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` and returns the warnings it raised. With `strict`, the first recovery warning is
+/// kept as [`Collector::rejected`] and makes [`check_rejected`] fail.
+pub(crate) fn collect<T>(strict: bool, f: impl FnOnce() -> T) -> (T, Collector) {
+    let sink = Sink::new(Mutex::new(Collector { strict, ..Collector::default() }));
+    let result = with_sink(Some(sink.clone()), f);
+
+    let collector = std::mem::take(&mut *sink.lock().unwrap_or_else(|e| e.into_inner()));
+
+    (result, collector)
+}
+
+/// Returns the sink warnings on this thread are recorded to. Parallel code passes it to
+/// [`with_sink`] on the worker threads, so their warnings end up in the same report.
+pub(crate) fn current_sink() -> Option<Sink> {
+    SINK.with(|sink| sink.borrow().clone())
+}
+
+/// Runs `f` with warnings recorded to `sink`.
+pub(crate) fn with_sink<T>(sink: Option<Sink>, f: impl FnOnce() -> T) -> T {
+    let previous = SINK.with(|current| current.replace(sink));
+    let _guard = SinkGuard(previous);
+
+    f()
+}
+
+/// Returns `true` if warnings on this thread are being collected.
+pub(crate) fn is_collecting() -> bool {
+    SINK.with(|sink| sink.borrow().is_some())
+}
+
+/// Adds a warning to the report of the current decode, if there is one.
+pub(crate) fn record(warning: DecodeWarning) {
+    let Some(sink) = current_sink() else {
+        return;
+    };
+
+    let mut collector = sink.lock().unwrap_or_else(|e| e.into_inner());
+    collector.synthesized_pixels = collector.synthesized_pixels.saturating_add(warning.synthesized_pixels);
+
+    if collector.strict && collector.rejected.is_none() && warning.kind.is_recovery() {
+        collector.rejected = Some(warning.clone());
+    }
+
+    if collector.warnings.len() < MAX_WARNINGS {
+        collector.warnings.push(warning);
+    } else {
+        collector.omitted_warnings += 1;
+    }
+}
+
+/// Returns [`VexelError::Malformed`] once a strict decode on this thread raised a recovery
+/// warning, so the decoder stops at its next check instead of finishing the image.
+pub(crate) fn check_rejected() -> VexelResult<()> {
+    let Some(sink) = current_sink() else {
+        return Ok(());
+    };
+
+    let collector = sink.lock().unwrap_or_else(|e| e.into_inner());
+    match &collector.rejected {
+        Some(warning) => Err(VexelError::Malformed {
+            format: ImageFormat::Unknown,
+            warning: warning.clone(),
+        }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,141 @@
+use crate::utils::error::VexelResult;
+use crate::utils::registry;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Signatures searched for by [`find_signature`]. JPEG matches need a marker after the SOI.
+const SIGNATURES: [&[u8]; 6] = [
+    &[0xFF, 0xD8, 0xFF],
+    &[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A],
+    b"GIF87a",
+    b"GIF89a",
+    b"II*\0",
+    b"MM\0*",
+];
+
+/// Length of the longest signature, plus the marker byte following a JPEG SOI.
+const LONGEST_SIGNATURE: usize = 8;
+
+const SCAN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A reader over the part of another reader that starts at a fixed offset, used to decode images
+/// preceded by other data. Offsets and seeks are relative to that start.
+///
+/// Returned as the source of [`Vexel::new_scanning`](crate::Vexel::new_scanning).
+#[derive(Debug)]
+pub struct SubReader<R> {
+    inner: R,
+    start: u64,
+}
+
+impl<R: Seek> SubReader<R> {
+    /// Creates a reader over `inner` from `start` onwards, positioned at its start.
+    pub fn new(mut inner: R, start: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(start))?;
+
+        Ok(Self { inner, start })
+    }
+
+    /// Returns the offset in the underlying reader this reader starts at.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Converts a position of the underlying reader, failing for positions before the start.
+    fn relative(&self, position: u64) -> io::Result<u64> {
+        position.checked_sub(self.start).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to a position before the start of the image")
+        })
+    }
+}
+
+impl<R: Read> Read for SubReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for SubReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => {
+                let offset = self.start.checked_add(offset).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "seek offset overflows")
+                })?;
+                self.inner.seek(SeekFrom::Start(offset))?
+            }
+            SeekFrom::End(_) | SeekFrom::Current(_) => {
+                let previous = self.inner.stream_position()?;
+                let position = self.inner.seek(pos)?;
+                if position < self.start {
+                    self.inner.seek(SeekFrom::Start(previous))?;
+                }
+                position
+            }
+        };
+
+        self.relative(position)
+    }
+}
+
+/// Returns `true` if `data` starts with one of the [`SIGNATURES`] and a built-in sniffer agrees.
+fn is_signature(data: &[u8]) -> bool {
+    let matched = SIGNATURES.iter().any(|signature| {
+        data.starts_with(signature) && (signature[0] != 0xFF || data.get(3).is_some_and(|&marker| (0xC0..=0xFE).contains(&marker)))
+    });
+
+    if !matched {
+        return false;
+    }
+
+    // The sniffers expect a zero padded header
+    let mut header = [0u8; registry::SNIFF_LENGTH];
+    let len = data.len().min(header.len());
+    header[..len].copy_from_slice(&data[..len]);
+
+    registry::sniff(&header).is_some()
+}
+
+/// Finds the first offset in `reader` at which a PNG, JPEG, GIF or TIFF signature starts, or
+/// `None` if there is none. The whole reader is scanned, a chunk at a time.
+pub(crate) fn find_signature<R: Read + Seek>(reader: &mut R) -> VexelResult<Option<u64>> {
+    reader.seek(SeekFrom::Start(0))?;
+
+    // Each chunk starts with the end of the previous one, so signatures crossing chunks are found
+    let overlap = registry::SNIFF_LENGTH.max(LONGEST_SIGNATURE);
+    let mut buffer = vec![0u8; SCAN_CHUNK_SIZE + overlap];
+    let mut carried = 0;
+    let mut base = 0u64;
+
+    loop {
+        let mut len = carried;
+        while len < buffer.len() {
+            match reader.read(&mut buffer[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let at_end = len < buffer.len();
+        // Positions in the overlap are checked again with more data in the next chunk
+        let searched = if at_end { len } else { len - overlap };
+
+        if let Some(position) = (0..searched).find(|&i| is_signature(&buffer[i..len])) {
+            return Ok(Some(base + position as u64));
+        }
+
+        if at_end {
+            return Ok(None);
+        }
+
+        buffer.copy_within(searched..len, 0);
+        base += searched as u64;
+        carried = len - searched;
+    }
+}
@@ -0,0 +1,312 @@
+use crate::utils::registry::{self, SNIFF_LENGTH};
+use crate::ImageFormat;
+
+/// Number of bytes from the start of the file checked for damaged signatures and chunk names.
+pub(crate) const HEADER_LENGTH: usize = 64;
+
+/// Number of bytes from the end of the file checked for trailers, such as the TGA 2.0 footer.
+pub(crate) const TRAILER_LENGTH: usize = 26;
+
+const TGA_FOOTER: &[u8] = b"TRUEVISION-XFILE.\0";
+
+/// How likely a format returned by [`guess_format`] is. Ordered from least to most likely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// Only weak hints, such as chunk names somewhere in the header or a matching trailer.
+    Low,
+    /// The header is consistent with the format, but the format has no magic bytes or they are
+    /// damaged.
+    Medium,
+    /// The magic bytes match, but they are short or the rest of the header was not checked.
+    High,
+    /// The magic bytes and the header fields following them match.
+    Certain,
+}
+
+/// Guesses the format of an image from its first bytes, and its last bytes if `data` holds the
+/// whole file.
+///
+/// Returns every plausible format, most likely first. Unlike [`Vexel::new`](crate::Vexel::new),
+/// this also scores formats whose magic bytes are damaged or missing, like TGA and JBIG1, which
+/// have none. `extension` is an optional file extension, with or without the leading dot, that
+/// raises the confidence of the formats it names. It resolves headers that are valid for both
+/// TGA and JBIG1.
+///
+/// Formats registered with [`register_decoder`](crate::register_decoder) are reported as
+/// [`Confidence::Certain`] when their sniffer accepts the header.
+///
+/// ```
+/// use vexel::{guess_format, Confidence, ImageFormat};
+///
+/// let guesses = guess_format(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", None);
+/// assert_eq!(guesses.first(), Some(&(ImageFormat::Png, Confidence::Certain)));
+/// ```
+pub fn guess_format(data: &[u8], extension: Option<&str>) -> Vec<(ImageFormat, Confidence)> {
+    let trailer = &data[data.len().saturating_sub(TRAILER_LENGTH)..];
+
+    score(data, trailer, extension)
+}
+
+/// Scores every format against `header`, the start of the file, and `trailer`, the end of it.
+pub(crate) fn score(header: &[u8], trailer: &[u8], extension: Option<&str>) -> Vec<(ImageFormat, Confidence)> {
+    // The sniffers expect a zero padded header of at least `SNIFF_LENGTH` bytes
+    let mut padded = header[..header.len().min(HEADER_LENGTH)].to_vec();
+    if padded.len() < SNIFF_LENGTH {
+        padded.resize(SNIFF_LENGTH, 0);
+    }
+    let header = padded.as_slice();
+
+    let mut guesses = Vec::new();
+
+    for format in registry::sniff_custom(header) {
+        guesses.push((format, Confidence::Certain));
+    }
+
+    guesses.extend(score_jpeg(header, trailer));
+    guesses.extend(score_png(header));
+    guesses.extend(score_gif(header));
+    guesses.extend(score_netpbm(header));
+    guesses.extend(score_bmp(header));
+    guesses.extend(score_ico(header));
+    guesses.extend(score_hdr(header));
+    guesses.extend(score_tiff(header));
+    guesses.extend(score_tga(header, trailer));
+    guesses.extend(score_jbig1(header));
+
+    if let Some(hinted) = extension.map(formats_for_extension) {
+        for (format, confidence) in guesses.iter_mut() {
+            if hinted.contains(format) && *confidence < Confidence::High {
+                *confidence = raise(*confidence);
+            }
+        }
+    }
+
+    // Stable, so formats with the same confidence stay in the order the registry checks them
+    guesses.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut seen = Vec::new();
+    guesses.retain(|(format, _)| {
+        let first = !seen.contains(format);
+        seen.push(format.clone());
+        first
+    });
+
+    guesses
+}
+
+fn raise(confidence: Confidence) -> Confidence {
+    match confidence {
+        Confidence::Low => Confidence::Medium,
+        Confidence::Medium => Confidence::High,
+        Confidence::High | Confidence::Certain => Confidence::Certain,
+    }
+}
+
+/// Returns the formats commonly stored with `extension`.
+fn formats_for_extension(extension: &str) -> Vec<ImageFormat> {
+    let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+
+    match extension.as_str() {
+        "jpg" | "jpeg" | "jpe" | "jfif" => vec![ImageFormat::Jpeg],
+        "jls" => vec![ImageFormat::JpegLs],
+        "png" => vec![ImageFormat::Png],
+        "gif" => vec![ImageFormat::Gif],
+        "bmp" | "dib" => vec![ImageFormat::Bmp],
+        "pbm" => vec![ImageFormat::NetPbmP1, ImageFormat::NetPbmP4],
+        "pgm" => vec![ImageFormat::NetPbmP2, ImageFormat::NetPbmP5],
+        "ppm" => vec![ImageFormat::NetPbmP3, ImageFormat::NetPbmP6],
+        "pam" => vec![ImageFormat::NetPbmP7],
+        "pnm" => vec![
+            ImageFormat::NetPbmP1,
+            ImageFormat::NetPbmP2,
+            ImageFormat::NetPbmP3,
+            ImageFormat::NetPbmP4,
+            ImageFormat::NetPbmP5,
+            ImageFormat::NetPbmP6,
+        ],
+        "hdr" | "pic" | "rgbe" => vec![ImageFormat::Hdr],
+        "tif" | "tiff" => vec![ImageFormat::Tiff],
+        "tga" | "icb" | "vda" | "vst" => vec![ImageFormat::Tga],
+        "jbg" | "jbig" | "bie" => vec![ImageFormat::Jbig1],
+        "ico" => vec![ImageFormat::Ico],
+        "cur" => vec![ImageFormat::Cur],
+        _ => Vec::new(),
+    }
+}
+
+fn contains(header: &[u8], needle: &[u8]) -> bool {
+    header.windows(needle.len()).any(|window| window == needle)
+}
+
+fn score_jpeg(header: &[u8], trailer: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    if let Some(format) = registry::sniff_jpeg_ls(header) {
+        return vec![(format, Confidence::Certain)];
+    }
+
+    if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return vec![(ImageFormat::Jpeg, Confidence::Certain)];
+    }
+
+    if header.starts_with(&[0xFF, 0xD8]) {
+        return vec![(ImageFormat::Jpeg, Confidence::High)];
+    }
+
+    // A damaged start, but the file still ends with an EOI marker
+    if trailer.ends_with(&[0xFF, 0xD9]) {
+        return vec![(ImageFormat::Jpeg, Confidence::Low)];
+    }
+
+    Vec::new()
+}
+
+fn score_png(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    if registry::sniff_png(header).is_some() {
+        return vec![(ImageFormat::Png, Confidence::Certain)];
+    }
+
+    // The signature with a byte or two mangled, e.g. by a text mode transfer
+    if &header[1..4] == b"PNG" || &header[12..16] == b"IHDR" {
+        return vec![(ImageFormat::Png, Confidence::High)];
+    }
+
+    if [b"IHDR", b"IDAT", b"IEND"].iter().any(|chunk| contains(header, *chunk)) {
+        return vec![(ImageFormat::Png, Confidence::Medium)];
+    }
+
+    Vec::new()
+}
+
+fn score_gif(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    if registry::sniff_gif(header).is_some() {
+        return vec![(ImageFormat::Gif, Confidence::Certain)];
+    }
+
+    if header.starts_with(b"GIF8") {
+        return vec![(ImageFormat::Gif, Confidence::High)];
+    }
+
+    // The signature shifted by a few bytes, or with its first byte lost
+    let shifted = (0..4).any(|i| {
+        let rest = &header[i..];
+        rest.starts_with(b"GIF87a") || rest.starts_with(b"GIF89a") || rest.starts_with(b"IF87a") || rest.starts_with(b"IF89a")
+    });
+    if shifted {
+        return vec![(ImageFormat::Gif, Confidence::Medium)];
+    }
+
+    Vec::new()
+}
+
+fn score_netpbm(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    let Some(format) = registry::sniff_netpbm(header) else {
+        return Vec::new();
+    };
+
+    // Two bytes are a weak signature on their own, the header continues with whitespace
+    let confidence = if header[2].is_ascii_whitespace() {
+        Confidence::High
+    } else {
+        Confidence::Medium
+    };
+
+    vec![(format, confidence)]
+}
+
+fn score_bmp(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    if registry::sniff_bmp(header).is_none() {
+        return Vec::new();
+    }
+
+    let dib_header_size = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+    let valid_dib = matches!(dib_header_size, 12 | 16 | 40 | 52 | 56 | 64 | 108 | 124);
+
+    let confidence = match (header.starts_with(b"BM"), valid_dib) {
+        (true, true) => Confidence::Certain,
+        (false, true) => Confidence::High,
+        (true, false) => Confidence::Medium,
+        (false, false) => Confidence::Low,
+    };
+
+    vec![(ImageFormat::Bmp, confidence)]
+}
+
+fn score_ico(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    let Some(format) = registry::sniff_ico(header) else {
+        return Vec::new();
+    };
+
+    // The first directory entry has a reserved zero byte and, for icons, 0 or 1 colour planes
+    let planes = u16::from_le_bytes([header[10], header[11]]);
+    let valid_entry = header[9] == 0 && (format == ImageFormat::Cur || planes <= 1);
+
+    let confidence = if valid_entry { Confidence::Certain } else { Confidence::Medium };
+
+    vec![(format, confidence)]
+}
+
+fn score_hdr(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    if registry::sniff_hdr(header).is_some() || header.starts_with(b"#?RGBE") {
+        return vec![(ImageFormat::Hdr, Confidence::Certain)];
+    }
+
+    if contains(header, b"FORMAT=32-bit_rle_") {
+        return vec![(ImageFormat::Hdr, Confidence::High)];
+    }
+
+    if header.starts_with(b"#?") {
+        return vec![(ImageFormat::Hdr, Confidence::Medium)];
+    }
+
+    Vec::new()
+}
+
+fn score_tiff(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    // Classic TIFF and BigTIFF
+    let signatures: [&[u8]; 4] = [b"II*\0", b"MM\0*", b"II+\0", b"MM\0+"];
+    if signatures.iter().any(|signature| header.starts_with(signature)) {
+        return vec![(ImageFormat::Tiff, Confidence::Certain)];
+    }
+
+    // The version number in the other byte order, which the decoder accepts as well
+    if registry::sniff_tiff(header).is_some() {
+        return vec![(ImageFormat::Tiff, Confidence::High)];
+    }
+
+    // A damaged byte order mark, but the version number is intact
+    let version = &header[2..4];
+    let little_endian = matches!(version, b"*\0" | b"+\0") && (header[0] == b'I' || header[1] == b'I');
+    let big_endian = matches!(version, b"\0*" | b"\0+") && (header[0] == b'M' || header[1] == b'M');
+    if little_endian || big_endian {
+        return vec![(ImageFormat::Tiff, Confidence::Medium)];
+    }
+
+    Vec::new()
+}
+
+fn score_tga(header: &[u8], trailer: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    let valid_header = registry::sniff_tga(header).is_some();
+
+    // TGA 2.0 files end with a footer, which is the only signature the format has
+    if trailer.ends_with(TGA_FOOTER) {
+        let confidence = if valid_header { Confidence::Certain } else { Confidence::Medium };
+        return vec![(ImageFormat::Tga, confidence)];
+    }
+
+    if valid_header {
+        return vec![(ImageFormat::Tga, Confidence::Medium)];
+    }
+
+    Vec::new()
+}
+
+fn score_jbig1(header: &[u8]) -> Vec<(ImageFormat, Confidence)> {
+    if registry::sniff_jbig1(header).is_none() {
+        return Vec::new();
+    }
+
+    // The upper bit of the options byte and the unused bits of the order byte are reserved
+    let reserved_clear = header[19] & 0x80 == 0 && header[18] & 0xF0 == 0;
+    let confidence = if reserved_clear { Confidence::Medium } else { Confidence::Low };
+
+    vec![(ImageFormat::Jbig1, confidence)]
+}
@@ -0,0 +1,317 @@
+use crate::decoders::gif::stream::GifScanner;
+use crate::decoders::jpeg::stream::JpegScanner;
+use crate::decoders::png::stream::PngScanner;
+use crate::utils::error::{VexelError, VexelResult};
+use crate::{Image, ImageFormat, ImageHeader, Limits, PixelData, Vexel};
+use std::io::Cursor;
+
+/// Number of bytes needed to detect the image format.
+const FORMAT_DETECTION_LENGTH: usize = 32;
+
+/// What a format scanner has found in the data received so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct ScanProgress {
+    /// Everything the pixel data depends on (dimensions, palettes, tables) has been received.
+    pub header_complete: bool,
+    /// Number of complete units: IDAT chunks, GIF frames or JPEG scans.
+    pub units: usize,
+    /// The end of the image has been reached.
+    pub done: bool,
+}
+
+enum StreamScanner {
+    Png(PngScanner),
+    Gif(GifScanner),
+    Jpeg(JpegScanner),
+}
+
+impl StreamScanner {
+    fn new(format: &ImageFormat) -> VexelResult<StreamScanner> {
+        match format {
+            ImageFormat::Png => Ok(StreamScanner::Png(PngScanner::new())),
+            ImageFormat::Gif => Ok(StreamScanner::Gif(GifScanner::new())),
+            ImageFormat::Jpeg => Ok(StreamScanner::Jpeg(JpegScanner::new())),
+            _ => Err(VexelError::UnsupportedFormat(format!(
+                "Streaming is not supported for {:?} images",
+                format
+            ))),
+        }
+    }
+
+    fn scan(&mut self, data: &[u8]) -> ScanProgress {
+        match self {
+            StreamScanner::Png(scanner) => scanner.scan(data),
+            StreamScanner::Gif(scanner) => scanner.scan(data),
+            StreamScanner::Jpeg(scanner) => scanner.scan(data),
+        }
+    }
+
+    /// Returns `true` if the scanner decodes rows as they arrive, instead of the image being
+    /// decoded again at checkpoints.
+    fn decodes_rows(&self) -> bool {
+        match self {
+            StreamScanner::Png(scanner) => scanner.decodes_rows(),
+            StreamScanner::Gif(_) => false,
+            StreamScanner::Jpeg(scanner) => scanner.decodes_rows(),
+        }
+    }
+
+    /// Decodes the rows completed by the data received since the last call, see
+    /// [`decodes_rows`](Self::decodes_rows). Returns the number of new rows and their pixels.
+    fn decode_rows(&mut self, data: &[u8]) -> VexelResult<Option<(u32, PixelData)>> {
+        match self {
+            StreamScanner::Png(scanner) => scanner.decode_rows(),
+            StreamScanner::Gif(_) => Ok(None),
+            StreamScanner::Jpeg(scanner) => scanner.decode_rows(data),
+        }
+    }
+
+    /// Describes a partial image decoded at a checkpoint, in the units of the format.
+    fn progress(&self, scan: ScanProgress) -> Progress {
+        match self {
+            // Interlaced and animated images have no complete rows until the end
+            StreamScanner::Png(_) => Progress::NeedMoreData,
+            StreamScanner::Gif(_) => Progress::Frames(scan.units),
+            StreamScanner::Jpeg(_) => Progress::Scans(scan.units),
+        }
+    }
+}
+
+/// Progress reported by [`StreamDecoder::feed`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    /// Nothing new could be decoded, more input is needed.
+    NeedMoreData,
+    /// The headers have been received. For GIF, `frame_count` only counts the frames received so far.
+    Header(ImageHeader),
+    /// The first rows of a non-interlaced PNG or a baseline JPEG are complete.
+    Rows(u32),
+    /// The first frames of a GIF are complete.
+    Frames(usize),
+    /// The first scans of a progressive JPEG are complete, each one makes the image sharper.
+    Scans(usize),
+    /// The end of the image has been received and the final image is available.
+    Done,
+}
+
+/// Buffering decoder for images that arrive in pieces, for example from a socket.
+///
+/// Data passed to [`feed`](Self::feed) is appended to an internal buffer, and the decoder follows
+/// the structure of the file (PNG chunks, GIF blocks, JPEG markers) as it arrives.
+///
+/// Non-interlaced PNGs and baseline JPEGs are decoded incrementally. The decoder keeps its state
+/// between calls, inflating the image data or decoding the MCU rows of the scan from where the
+/// previous call stopped, and appends the rows they complete to the partial image.
+///
+/// Other images (GIFs, progressive JPEGs, interlaced or animated PNGs) are decoded again from the
+/// start with [`Vexel`] at checkpoints, where new frames, scans or image data are complete.
+/// Checkpoints are taken at most each time the buffer grows by a quarter, so all partial decodes
+/// together cost a few times a single decode of the file.
+///
+/// Once the end of the image arrives, the final image is decoded from the whole buffer with
+/// [`Vexel`], so it has the same metadata and report as any other decode. Running out of data is
+/// not an error, `feed` reports [`Progress::NeedMoreData`] until more of the image can be decoded.
+///
+/// ```no_run
+/// use vexel::{Progress, StreamDecoder};
+///
+/// let mut decoder = StreamDecoder::new();
+/// for chunk in std::fs::read("image.png")?.chunks(4096) {
+///     match decoder.feed(chunk)? {
+///         Progress::Rows(rows) => println!("{} rows ready", rows),
+///         Progress::Done => break,
+///         _ => {}
+///     }
+/// }
+/// let image = decoder.finish()?;
+/// # Ok::<(), vexel::VexelError>(())
+/// ```
+pub struct StreamDecoder {
+    data: Vec<u8>,
+    format: ImageFormat,
+    scanner: Option<StreamScanner>,
+    limits: Limits,
+    header: Option<ImageHeader>,
+    image: Option<Image>,
+    decoded_units: usize,
+    decoded_length: usize,
+    done: bool,
+}
+
+impl Default for StreamDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamDecoder {
+    pub fn new() -> StreamDecoder {
+        StreamDecoder {
+            data: Vec::new(),
+            format: ImageFormat::Unknown,
+            scanner: None,
+            limits: Limits::default(),
+            header: None,
+            image: None,
+            decoded_units: 0,
+            decoded_length: 0,
+            done: false,
+        }
+    }
+
+    /// Sets resource limits that are enforced for every partial and the final decode.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Appends `data` to the buffer and decodes the rows it completes, or decodes the buffer again
+    /// if a checkpoint was reached.
+    ///
+    /// Returns the most advanced progress reached by this call. Data fed after
+    /// [`Progress::Done`] is ignored. A checkpoint that fails to decode because the data ends too
+    /// early, with [`VexelError::Truncated`] or [`VexelError::MissingData`], is reported as
+    /// [`Progress::NeedMoreData`] and retried at a later checkpoint.
+    ///
+    /// # Errors
+    ///
+    /// - [`VexelError::UnsupportedFormat`] — the data is not a PNG, GIF or JPEG
+    /// - [`VexelError::LimitExceeded`] — the buffer or the image breaks the [`Limits`]
+    /// - Any other error [`Vexel::decode`] returns for the received data, such as
+    ///   [`VexelError::Corrupt`]
+    pub fn feed(&mut self, data: &[u8]) -> VexelResult<Progress> {
+        if self.done {
+            return Ok(Progress::Done);
+        }
+
+        self.limits.check_input_size((self.data.len() + data.len()) as u64)?;
+        self.data.extend_from_slice(data);
+
+        if self.scanner.is_none() {
+            if self.data.len() < FORMAT_DETECTION_LENGTH {
+                return Ok(Progress::NeedMoreData);
+            }
+
+            self.detect_format()?;
+        }
+
+        let Some(scanner) = self.scanner.as_mut() else {
+            return Ok(Progress::NeedMoreData);
+        };
+
+        let scan = scanner.scan(&self.data);
+        let mut progress = Progress::NeedMoreData;
+
+        if scan.header_complete && self.header.is_none() {
+            let Some(header) = partial(self.decoder()?.probe())? else {
+                return Ok(Progress::NeedMoreData);
+            };
+            self.header = Some(header.clone());
+            progress = Progress::Header(header);
+        }
+
+        if scan.done {
+            self.image = Some(self.decoder()?.decode()?);
+            self.done = true;
+            return Ok(Progress::Done);
+        }
+
+        if let Some(scanner) = self.scanner.as_mut().filter(|scanner| scanner.decodes_rows()) {
+            if let (Some((rows, pixels)), Some(header)) = (scanner.decode_rows(&self.data)?, &self.header) {
+                let image = match &mut self.image {
+                    Some(image) => {
+                        image.append_rows(rows, pixels);
+                        image
+                    }
+                    None => self.image.insert(Image::from_pixels(header.width, rows, pixels)),
+                };
+                progress = Progress::Rows(image.height());
+            }
+
+            return Ok(progress);
+        }
+
+        // Failed checkpoints count too, so data that never decodes isn't decoded on every call
+        let grown = self.data.len() >= self.decoded_length + self.decoded_length / 4;
+        if scan.units > self.decoded_units && grown {
+            self.decoded_length = self.data.len();
+            let Some(image) = partial(self.decoder()?.decode())? else {
+                return Ok(progress);
+            };
+            self.image = Some(image);
+            self.decoded_units = scan.units;
+
+            if let Some(scanner) = &self.scanner {
+                match scanner.progress(scan) {
+                    Progress::NeedMoreData => {}
+                    units => progress = units,
+                }
+            }
+        }
+
+        Ok(progress)
+    }
+
+    /// Returns the headers once they have been received.
+    pub fn header(&self) -> Option<&ImageHeader> {
+        self.header.as_ref()
+    }
+
+    /// Returns the most recently decoded image. Before [`Progress::Done`] this is a partial image:
+    /// the rows decoded so far for incrementally decoded images, or the last checkpoint for
+    /// others, where rows and frames that have not fully arrived may be missing or incomplete.
+    pub fn image(&self) -> Option<&Image> {
+        self.image.as_ref()
+    }
+
+    /// Returns the detected image format, [`ImageFormat::Unknown`] until enough data has arrived.
+    pub fn format(&self) -> ImageFormat {
+        self.format.clone()
+    }
+
+    /// Returns `true` once the end of the image has been received.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Ends the stream and returns the final image, decoding whatever has been received if the
+    /// end of the image never arrived.
+    pub fn finish(mut self) -> VexelResult<Image> {
+        if self.done {
+            if let Some(image) = self.image.take() {
+                return Ok(image);
+            }
+        }
+
+        if self.scanner.is_none() {
+            self.detect_format()?;
+        }
+
+        self.decoder()?.decode()
+    }
+
+    fn detect_format(&mut self) -> VexelResult<()> {
+        let format = self.decoder()?.get_format();
+        self.scanner = Some(StreamScanner::new(&format)?);
+        self.format = format;
+
+        Ok(())
+    }
+
+    fn decoder(&self) -> VexelResult<Vexel<Cursor<&[u8]>>> {
+        let mut decoder = Vexel::from_slice(&self.data)?;
+        decoder.set_limits(self.limits.clone());
+
+        Ok(decoder)
+    }
+}
+
+/// Returns `None` for errors caused by data that ends too early, which a later checkpoint may
+/// decode.
+fn partial<T>(result: VexelResult<T>) -> VexelResult<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(VexelError::Truncated { .. } | VexelError::MissingData { .. }) => Ok(None),
+        Err(VexelError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}